@@ -1,18 +1,19 @@
 use winit::{
     event::{Event, WindowEvent, MouseButton, ElementState},
-    event_loop::{ControlFlow, EventLoop},
+    event_loop::{ControlFlow, EventLoopBuilder, EventLoopProxy},
     window::WindowBuilder,
 };
 use pixels::{Pixels, SurfaceTexture};
-use rusttype::{Scale, point};
 
 use engine::parser::html::tree_builder::HtmlParser;
-use engine::style::{Stylesheet, Style, Selector, Viewport};
+use engine::style::{ComputedStyles, Stylesheet, Viewport};
 use engine::layout::LayoutEngine;
+use engine::paint::{Canvas, DisplayList};
 use engine::dom::{NodeType, Dom, NodeId};
-use engine::font::FontManager;
-use engine::net::NetworkManager;
+use engine::font::{FontManager, FontSource};
+use engine::net::{form_owner, form_submission, is_submit_button, FetchedResource, FormSubmission, NetworkConfig, NetworkManager};
 use engine::net::url::resolve_url;
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
 use std::fs::OpenOptions;
@@ -145,36 +146,113 @@ fn find_anchor_in_dom(dom: &Dom, node_id: NodeId, x: f32, y: f32) -> Option<Stri
     None
 }
 
-// Helper function to load and parse a page given a URL
-fn load_page(url: &str, network_manager: &NetworkManager) -> (Arc<Dom>, Stylesheet) {
-    // Set the document URL for resolving relative URLs
-    network_manager.set_document_url(url);
-    
-    // Fetch HTML from a web URL
-    let html = match fetch_html(url) {
-        Ok(content) => content,
-        Err(e) => {
-            eprintln!("Failed to fetch HTML from {}: {}", url, e);
-            eprintln!("Using fallback HTML");
-            format!(
-                r#"
-                <!DOCTYPE html>
-                <html>
-                <head>
-                    <title>Error</title>
-                </head>
-                <body>
-                    <h1>Failed to Load Page</h1>
-                    <p>Could not fetch: {}</p>
-                    <p>Error: {}</p>
-                </body>
-                </html>
-                "#,
-                url, e
-            )
+/// Where a click goes: a link, or a form submission
+enum Navigation {
+    Link(String),
+    Form(FormSubmission),
+}
+
+// Find the submit button (or an element inside one) at the given coordinates
+fn find_submit_button_at_position(
+    layout: &engine::layout::LayoutBox,
+    dom: &Dom,
+    x: f32,
+    y: f32,
+    scale_factor: f32,
+) -> Option<NodeId> {
+    let node_id = find_deepest_node_at(layout, x / scale_factor, y / scale_factor)?;
+    std::iter::successors(Some(node_id), |&id| dom.nodes[id].parent)
+        .find(|&id| is_submit_button(dom, id))
+}
+
+// The node of the innermost layout box containing the point
+fn find_deepest_node_at(layout: &engine::layout::LayoutBox, x: f32, y: f32) -> Option<NodeId> {
+    let dims = &layout.dimensions;
+    if x < dims.x || x > dims.x + dims.width || y < dims.y || y > dims.y + dims.height {
+        return None;
+    }
+    layout.children.iter()
+        .find_map(|child| find_deepest_node_at(child, x, y))
+        .or(Some(layout.node_id))
+}
+
+/// What wakes the event loop besides the window
+enum UserEvent {
+    /// A background fetch finished; repaint with it
+    ResourceLoaded,
+    /// The response to navigation number `navigation` has arrived, or the
+    /// request for `url` failed
+    PageArrived { navigation: u64, url: String, page: Option<FetchedResource> },
+    /// The linked stylesheets of the page being loaded, in document order
+    StylesheetsArrived { navigation: u64, sheets: Vec<Option<FetchedResource>> },
+    /// The web fonts of the page shown, by the URL its `@font-face` rules give
+    FontsArrived { navigation: u64, fonts: HashMap<String, Vec<u8>> },
+}
+
+/// A page whose response has arrived but whose stylesheets are loading
+struct LoadingPage {
+    navigation: u64,
+    url: String,
+    dom: Arc<Dom>,
+}
+
+// Start fetching the page a link or form goes to, on a worker thread; the
+// response comes back to the event loop as a `PageArrived` event. The request
+// goes out while the page being left is still the document, so that SameSite
+// cookies see where the navigation comes from
+fn start_navigation(
+    navigation: Navigation,
+    id: u64,
+    network_manager: &NetworkManager,
+    proxy: EventLoopProxy<UserEvent>,
+) {
+    let arrived = move |url: String| {
+        move |page| {
+            let _ = proxy.send_event(UserEvent::PageArrived { navigation: id, url, page });
+        }
+    };
+    match navigation {
+        Navigation::Link(url) => {
+            log(&format!("Navigating to: {}", url));
+            network_manager.fetch_document_with(&url, arrived(url.clone()));
+        }
+        Navigation::Form(submission) => {
+            eprintln!("Submitting form: {} {}", submission.method.as_str(), submission.url);
+            let mut request = network_manager.request(submission.method, &submission.url).navigation();
+            if let Some(content_type) = &submission.content_type {
+                request = request.header("Content-Type", content_type);
+            }
+            if let Some(body) = submission.body {
+                request = request.body(body);
+            }
+            request.send_with(arrived(submission.url));
+        }
+    }
+}
+
+// Make the page that answered a navigation the document and parse it; returns
+// its URL, which redirects may have changed, its DOM and the URLs of its
+// linked stylesheets
+fn open_page(url: String, page: Option<FetchedResource>, network_manager: &NetworkManager) -> (String, Arc<Dom>, Vec<String>) {
+    // An error status still comes with a page to show, such as a login
+    // form with its error messages
+    let (url, html) = match page {
+        Some(resource) => {
+            if !(200..300).contains(&resource.status) {
+                eprintln!("{} answered with status {}", resource.url, resource.status);
+            }
+            eprintln!("Fetched {} bytes from {}", resource.data.len(), resource.url);
+            (resource.url, String::from_utf8_lossy(&resource.data).into_owned())
+        }
+        None => {
+            eprintln!("Failed to load {}", url);
+            let html = error_page(&url, "the request failed");
+            (url, html)
         }
     };
 
+    // Set the document URL for resolving relative URLs
+    network_manager.set_document_url(&url);
     let dom = HtmlParser::new(&html).parse();
     
     // Extract and set the <base href> if present
@@ -183,45 +261,86 @@ fn load_page(url: &str, network_manager: &NetworkManager) -> (Arc<Dom>, Styleshe
         network_manager.set_base_href(&base_href);
     }
     
-    let dom = Arc::new(dom);
+    let links: Vec<String> = engine::parser::html::extract_stylesheet_links(&dom)
+        .iter()
+        .map(|(_, href)| network_manager.resolve_url(href))
+        .collect();
+    (url, Arc::new(dom), links)
+}
 
-    // Extract CSS from <style> tags in the DOM
-    let css = extract_css_from_dom(&*dom, dom.root());
-    
-    log(&format!("Extracted CSS from <style> tags: {} bytes", css.len()));
+fn error_page(url: &str, error: &str) -> String {
+    format!(
+        r#"
+        <!DOCTYPE html>
+        <html>
+        <head>
+            <title>Error</title>
+        </head>
+        <body>
+            <h1>Failed to Load Page</h1>
+            <p>Could not fetch: {}</p>
+            <p>Error: {}</p>
+        </body>
+        </html>
+        "#,
+        url, error
+    )
+}
 
-    // --- CSS (parse and apply) ---
+// The stylesheet of a page: its linked sheets, then its <style> elements
+fn page_stylesheet(dom: &Dom, sheets: Vec<Option<FetchedResource>>, network_manager: &NetworkManager) -> Stylesheet {
     let mut stylesheet = Stylesheet::new();
+    let linked = sheets.len();
+    // Font URLs in a linked sheet are relative to the sheet, not the page
+    for resource in sheets.into_iter().flatten() {
+        stylesheet.add_css_with_base(&String::from_utf8_lossy(&resource.data), &resource.url);
+    }
+    if linked > 0 {
+        log(&format!("Loaded {} linked stylesheets, {} rules", linked, stylesheet.rules.len()));
+    }
     
-    // Parse CSS from style tags and convert to stylesheet rules
+    // Parse CSS from style tags; the engine matches the parsed selectors directly
+    let css = extract_css_from_dom(dom, dom.root());
+    log(&format!("Extracted CSS from <style> tags: {} bytes", css.len()));
     if !css.is_empty() {
-        log(&format!("CSS extracted: {} bytes", css.len()));
-        let mut css_tokenizer = engine::parser::css::CssTokenizer::new(&css);
-        let tokens = css_tokenizer.tokenize();
-        let mut css_parser = engine::parser::css::CssParser::new(tokens);
-        let css_items = css_parser.parse();
-        log(&format!("=== Parsed {} CSS items from <style> tags ===", css_items.len()));
-        
-        // Convert CSS rules to stylesheet rules
-        for item in css_items {
-            if let engine::parser::css::parser::CssItem::Rule(rule) = item {
-                let selector = convert_css_selector(&rule.selector);
-                let mut style = Style::new();
-                
-                for decl in rule.declarations {
-                    style.properties.insert(decl.property.clone(), decl.value.clone());
-                }
-                
-                stylesheet.add_rule(selector, style);
+        // An empty URL resolves to the document's base URL
+        stylesheet.add_css_with_base(&css, &network_manager.resolve_url(""));
+        log(&format!("Stylesheet now has {} rules", stylesheet.rules.len()));
+    }
+    stylesheet
+}
+
+// Start fetching the `@font-face` fonts of `stylesheet` in parallel on the
+// network's worker threads; they come back to the event loop as a
+// `FontsArrived` event. Every url() source is fetched, not just the first,
+// so `load_font_faces` can fall back to the next one when a file is missing
+// or does not decode
+fn start_web_fonts(stylesheet: &Stylesheet, navigation: u64, network_manager: &NetworkManager, proxy: EventLoopProxy<UserEvent>) {
+    let mut urls: Vec<String> = Vec::new();
+    for source in stylesheet.font_faces.iter().flat_map(|face| &face.sources) {
+        if let FontSource::Url { url, .. } = source {
+            if !urls.contains(url) {
+                urls.push(url.clone());
             }
         }
-        log(&format!("Stylesheet now has {} rules", stylesheet.rules.len()));
     }
-    
-    // Note: Default styles are now handled by the engine's apply_default_styles() method
-    // in style::Stylesheet::compute_style(), so we don't need to add them here
+    if urls.is_empty() {
+        return;
+    }
+    network_manager.fetch_resources_with(&urls.clone(), move |fonts| {
+        let fonts = urls.into_iter()
+            .zip(fonts)
+            .filter_map(|(url, font)| Some((url, font?.data)))
+            .collect();
+        let _ = proxy.send_event(UserEvent::FontsArrived { navigation, fonts });
+    });
+}
 
-    (dom, stylesheet)
+/// Add the `@font-face` fonts of `stylesheet` from `fonts`, the files
+/// fetched for them by their already resolved source URL
+fn load_web_fonts(font_manager: &mut FontManager, stylesheet: &Stylesheet, fonts: &HashMap<String, Vec<u8>>) {
+    let loaded = font_manager.load_font_faces(&stylesheet.font_faces, |url| fonts.get(url).cloned());
+    log(&format!("Loaded {} of {} @font-face fonts", loaded, stylesheet.font_faces.len()));
 }
 
 fn main() {
@@ -239,16 +358,17 @@ fn main() {
     // Initial URL to load
     let initial_url = "https://info.cern.ch/";
     
-    // --- Network Manager (created early so load_page can use it) ---
-    let network_manager = Arc::new(NetworkManager::new());
+    // --- Network Manager ---
+    let network_manager = Arc::new(NetworkManager::with_config(NetworkConfig {
+        cookie_file: Some(data_dir().join("cookies.txt")),
+        cache_dir: Some(cache_dir()),
+        ..NetworkConfig::default()
+    }));
     
-    // Load initial page
-    let (mut dom, mut stylesheet) = load_page(initial_url, &network_manager);
+    // A blank page shows until the initial one arrives
     let mut current_url = initial_url.to_string();
-
-    // Extract title from DOM
-    let page_title = extract_title(&dom);
-    let window_title = format!("Grob Browser - {}", page_title);
+    let mut dom = Arc::new(HtmlParser::new("").parse());
+    let mut stylesheet = Stylesheet::new();
 
     // --- Layout ---
     let mut layout_engine = LayoutEngine::new();
@@ -256,18 +376,28 @@ fn main() {
     // --- Font Manager ---
     let mut font_manager = FontManager::new();
 
-    // State for navigation
-    let pending_navigation = Arc::new(Mutex::new(Option::<String>::None));
-
     // --- Window ---
-    let event_loop = EventLoop::new();
+    // Background fetches wake the event loop so finished images get drawn
+    let event_loop = EventLoopBuilder::<UserEvent>::with_user_event().build();
+    let proxy = event_loop.create_proxy();
+    let listener_proxy = Mutex::new(proxy.clone());
+    network_manager.set_load_listener(move |_| {
+        if let Ok(proxy) = listener_proxy.lock() {
+            let _ = proxy.send_event(UserEvent::ResourceLoaded);
+        }
+    });
+
+    // Navigations are numbered so that only the latest one's page is shown
+    let mut navigation_id = 1;
+    let mut loading_page: Option<LoadingPage> = None;
+    start_navigation(Navigation::Link(initial_url.to_string()), navigation_id, &network_manager, proxy.clone());
     
     // Use a logical size that will result in even physical dimensions at any scale factor
     // 800x600 logical -> 1600x1200 at scale 2, 800x600 at scale 1
     let initial_logical_size = winit::dpi::LogicalSize::new(800.0, 600.0);
     
     let window = WindowBuilder::new()
-        .with_title(&window_title)
+        .with_title("Grob Browser")
         .with_inner_size(initial_logical_size)
         .build(&event_loop)
         .expect("Failed to create window");
@@ -293,6 +423,8 @@ fn main() {
     let mut last_mouse_pos = (0.0, 0.0);
     let mut last_layout_root: Option<engine::layout::LayoutBox> = None;
     let mut needs_layout = true;
+    // Computed styles only change when the page or viewport does
+    let mut computed_styles: Option<ComputedStyles> = None;
     
     // Request an initial redraw
     window.request_redraw();
@@ -336,40 +468,74 @@ fn main() {
                 window.request_redraw();
             }
             Event::WindowEvent { event: WindowEvent::MouseInput { state: ElementState::Released, button: MouseButton::Left, .. }, .. } => {
-                // Handle click on anchor tag
+                // Handle click on anchor tag or submit button
                 if let Some(layout) = &last_layout_root {
                     if let Some(href) = find_anchor_at_position(layout, &dom, last_mouse_pos.0, last_mouse_pos.1, scale_factor) {
                         // Resolve relative URL against current page URL
                         // resolve_url(base_url, relative_url) - base is current page, relative is the href
                         let resolved_url = resolve_url(&current_url, &href);
                         log(&format!("SUCCESS: Navigating to {} (resolved from {})", resolved_url, href));
-                        if let Ok(mut nav) = pending_navigation.lock() {
-                            *nav = Some(resolved_url);
+                        navigation_id += 1;
+                        loading_page = None;
+                        start_navigation(Navigation::Link(resolved_url), navigation_id, &network_manager, proxy.clone());
+                    } else if let Some(button) = find_submit_button_at_position(layout, &dom, last_mouse_pos.0, last_mouse_pos.1, scale_factor) {
+                        let submission = form_owner(&dom, button)
+                            .and_then(|form| form_submission(&dom, form, Some(button), &current_url));
+                        if let Some(submission) = submission {
+                            log(&format!("Submitting form: {} {}", submission.method.as_str(), submission.url));
+                            navigation_id += 1;
+                            loading_page = None;
+                            start_navigation(Navigation::Form(submission), navigation_id, &network_manager, proxy.clone());
                         }
                     }
                 }
                 window.request_redraw();
             }
-            Event::RedrawRequested(_) => {
-                // Check if we need to navigate to a new page
-                if let Ok(mut nav) = pending_navigation.lock() {
-                    if let Some(new_url) = nav.take() {
-                        log(&format!("Navigating to: {}", new_url));
-                        current_url = new_url.clone();
-                        let (new_dom, new_stylesheet) = load_page(&new_url, &network_manager);
-                        dom = new_dom;
-                        stylesheet = new_stylesheet;
-                        stylesheet.set_viewport(viewport);
-                        needs_layout = true;
-                        
-                        // Update window title
-                        let new_title = extract_title(&dom);
-                        window.set_title(&format!("Grob Browser - {}", new_title));
-                    }
-                }
+            Event::UserEvent(UserEvent::ResourceLoaded) => {
+                // A resource finished loading; repaint with it
+                window.request_redraw();
+            }
+            Event::UserEvent(UserEvent::PageArrived { navigation, url, page }) if navigation == navigation_id => {
+                // The page becomes the document now; it is shown once its
+                // stylesheets have arrived too
+                let (url, new_dom, links) = open_page(url, page, &network_manager);
+                loading_page = Some(LoadingPage { navigation, url, dom: new_dom });
+                let proxy = proxy.clone();
+                network_manager.fetch_resources_with(&links, move |sheets| {
+                    let _ = proxy.send_event(UserEvent::StylesheetsArrived { navigation, sheets });
+                });
+            }
+            Event::UserEvent(UserEvent::StylesheetsArrived { navigation, sheets })
+                if loading_page.as_ref().is_some_and(|page| page.navigation == navigation) =>
+            {
+                let page = loading_page.take().unwrap();
+                current_url = page.url;
+                dom = page.dom;
+                stylesheet = page_stylesheet(&dom, sheets, &network_manager);
+                stylesheet.set_viewport(viewport);
+                // The previous page's web fonts go; this page's draw with
+                // fallback fonts until they arrive
+                font_manager.clear_font_faces();
+                start_web_fonts(&stylesheet, navigation, &network_manager, proxy.clone());
+                needs_layout = true;
                 
+                // Update window title
+                let new_title = extract_title(&dom);
+                window.set_title(&format!("Grob Browser - {}", new_title));
+                window.request_redraw();
+            }
+            Event::UserEvent(UserEvent::FontsArrived { navigation, fonts }) if navigation == navigation_id => {
+                load_web_fonts(&mut font_manager, &stylesheet, &fonts);
+                needs_layout = true;
+                window.request_redraw();
+            }
+            Event::RedrawRequested(_) => {
                 // Always recompute layout to ensure it fills current viewport
-                let layout_root = layout_engine.layout_with_full_viewport(&dom, &stylesheet, viewport, &mut font_manager);
+                if needs_layout || computed_styles.is_none() {
+                    computed_styles = Some(stylesheet.compute_styles(&dom, &viewport));
+                }
+                let styles = computed_styles.as_ref().unwrap();
+                let layout_root = layout_engine.layout_with_styles(&dom, styles, viewport, &mut font_manager);
                 last_layout_root = Some(layout_root);
                 needs_layout = false;
                 
@@ -381,10 +547,12 @@ fn main() {
                     *byte = 255;
                 }
 
-                // Draw layout and text - pass both logical and physical dimensions for proper scaling
+                // Paint the display list, scaling CSS px to physical pixels
                 if let Some(ref layout_root) = last_layout_root {
-                    draw_layout_and_text(frame, layout_root, &dom, &mut font_manager, physical_size.width as usize, physical_size.height as usize, scale_factor);
-                    draw_images(frame, layout_root, &dom, &network_manager, physical_size.width as usize, physical_size.height as usize, scale_factor);
+                    let display_list = DisplayList::build(layout_root, &dom, scale_factor);
+                    Canvas::new(frame, physical_size.width as usize, physical_size.height as usize, scale_factor)
+                        .skip_pending_images()
+                        .draw(&display_list, &mut font_manager, Some(&*network_manager));
                 }
 
                 pixels.render().unwrap();
@@ -400,152 +568,6 @@ fn main() {
     });
 }
 
-// --- Combined layout and text drawing ---
-fn draw_layout_and_text(
-    frame: &mut [u8],
-    layout: &engine::layout::LayoutBox,
-    dom: &engine::dom::Dom,
-    font_manager: &mut FontManager,
-    screen_width: usize,
-    screen_height: usize,
-    scale_factor: f32,
-) {
-    draw_box_recursive(frame, layout, dom, font_manager, screen_width, screen_height, scale_factor);
-}
-
-fn draw_box_recursive(
-    frame: &mut [u8],
-    layout: &engine::layout::LayoutBox,
-    dom: &engine::dom::Dom,
-    font_manager: &mut FontManager,
-    screen_width: usize,
-    screen_height: usize,
-    scale_factor: f32,
-) {
-    let dims = &layout.dimensions;
-    
-    // Scale logical coordinates to physical pixels
-    let x = (dims.x * scale_factor) as usize;
-    let y = (dims.y * scale_factor) as usize;
-    let width = (dims.width * scale_factor) as usize;
-    let height = (dims.height * scale_factor) as usize;
-    
-    // Draw background if element has one
-    if let Some((bg_r, bg_g, bg_b)) = layout.style.get_background_color() {
-        for py in y..(y + height).min(screen_height) {
-            for px in x..(x + width).min(screen_width) {
-                let idx = (py * screen_width + px) * 4;
-                if idx + 3 < frame.len() {
-                    frame[idx] = bg_r;
-                    frame[idx + 1] = bg_g;
-                    frame[idx + 2] = bg_b;
-                    frame[idx + 3] = 255;
-                }
-            }
-        }
-    }
-
-    // Draw text if this layout box has text content
-    if let Some(text_content) = &layout.text_content {
-        let parent_id = dom.nodes[layout.node_id].parent;
-        let should_skip = if let Some(pid) = parent_id {
-            if let engine::dom::NodeType::Element(elem) = &dom.nodes[pid].node_type {
-                matches!(elem.tag_name.as_str(), "style" | "script" | "head" | "title" | "meta" | "link")
-            } else {
-                false
-            }
-        } else {
-            false
-        };
-
-        if !should_skip {
-            draw_text_glyphs(frame, layout, text_content, font_manager, screen_width, screen_height, scale_factor);
-        }
-    }
-
-    // Draw children
-    for child in &layout.children {
-        draw_box_recursive(frame, child, dom, font_manager, screen_width, screen_height, scale_factor);
-    }
-}
-
-fn draw_text_glyphs(
-    frame: &mut [u8],
-    layout: &engine::layout::LayoutBox,
-    text: &str,
-    font_manager: &mut FontManager,
-    screen_width: usize,
-    screen_height: usize,
-    scale_factor: f32,
-) {
-    let font_family = layout.style.get_font_family();
-    let font_size = layout.style.get_font_size() * scale_factor;
-    let (text_r, text_g, text_b) = layout.style.get_color();
-    let has_underline = layout.style.has_text_decoration("underline");
-    let is_bold = layout.style.is_bold();
-    let is_italic = layout.style.is_italic();
-    let scale = Scale::uniform(font_size);
-
-    if let Some(font) = font_manager.load_font_variant(font_family, is_bold, is_italic) {
-        let v_metrics = font.v_metrics(scale);
-        let mut x = layout.dimensions.x * scale_factor;
-        let y = layout.dimensions.y * scale_factor + v_metrics.ascent;
-        let text_start_x = x;
-
-        for c in text.chars() {
-            let glyph = font.glyph(c).scaled(scale).positioned(point(x, y));
-
-            if let Some(bb) = glyph.pixel_bounding_box() {
-                glyph.draw(|gx, gy, v| {
-                    let px = gx as i32 + bb.min.x;
-                    let py = gy as i32 + bb.min.y;
-
-                    if px >= 0 && py >= 0 && px < screen_width as i32 && py < screen_height as i32 {
-                        let idx = (py as usize * screen_width + px as usize) * 4;
-                        if idx + 3 < frame.len() {
-                            let coverage = (v * 255.0) as u8;
-                            let bg_r = frame[idx] as u32;
-                            let bg_g = frame[idx + 1] as u32;
-                            let bg_b = frame[idx + 2] as u32;
-                            let cov = coverage as u32;
-
-                            frame[idx] = ((bg_r * (255 - cov) + text_r as u32 * cov) / 255) as u8;
-                            frame[idx + 1] = ((bg_g * (255 - cov) + text_g as u32 * cov) / 255) as u8;
-                            frame[idx + 2] = ((bg_b * (255 - cov) + text_b as u32 * cov) / 255) as u8;
-                            frame[idx + 3] = 255;
-                        }
-                    }
-                });
-            }
-
-            x += glyph.unpositioned().h_metrics().advance_width;
-        }
-
-        // Draw underline if needed
-        if has_underline {
-            let underline_y = (layout.dimensions.y * scale_factor + font_size * 1.1) as usize;
-            let start_x = text_start_x as usize;
-            let end_x = x as usize;
-            let thickness = (font_size / 16.0).max(1.0) as usize;
-
-            for t in 0..thickness {
-                let uy = underline_y + t;
-                if uy < screen_height {
-                    for px in start_x..end_x.min(screen_width) {
-                        let idx = (uy * screen_width + px) * 4;
-                        if idx + 3 < frame.len() {
-                            frame[idx] = text_r;
-                            frame[idx + 1] = text_g;
-                            frame[idx + 2] = text_b;
-                            frame[idx + 3] = 255;
-                        }
-                    }
-                }
-            }
-        }
-    }
-}
-
 fn extract_title(dom: &engine::dom::Dom) -> String {
     // Find the title element
     let title_node = find_title_element(dom, dom.root());
@@ -577,196 +599,28 @@ fn find_title_element(dom: &engine::dom::Dom, node_id: engine::dom::NodeId) -> O
     None
 }
 
-// Draw images from img tags
-fn draw_images(frame: &mut [u8], layout: &engine::layout::LayoutBox, dom: &Arc<engine::dom::Dom>, network: &Arc<NetworkManager>, screen_width: usize, screen_height: usize, scale_factor: f32) {
-    let node = &dom.nodes[layout.node_id];
-    
-    // Check if this is an img element
-    if let NodeType::Element(el) = &node.node_type {
-        if el.tag_name == "img" {
-            // Check for srcset first, then fallback to src
-            let srcset = el.attributes.iter().find(|(k, _)| k == "srcset").map(|(_, v)| v.clone());
-            let src = el.attributes.iter().find(|(k, _)| k == "src").map(|(_, v)| v.clone());
-            let alt = el.attributes.iter().find(|(k, _)| k == "alt").map(|(_, v)| v.clone()).unwrap_or_else(|| "Image".to_string());
-            
-            // Select the best image URL
-            let image_url = if let Some(srcset_attr) = srcset {
-                // Parse srcset and select the best image for the current viewport
-                let viewport_width = layout.dimensions.width as u32;
-                let srcset_entries = engine::net::parse_srcset(&srcset_attr);
-                engine::net::select_srcset_image(&srcset_entries, src.as_deref(), viewport_width, scale_factor)
-            } else {
-                src
-            };
-            
-            if let Some(url) = image_url {
-                // The NetworkManager will handle URL resolution internally
-                if let Some(img_data) = network.fetch_image(&url) {
-                    draw_real_image(frame, layout, &img_data, &alt, screen_width, screen_height);
-                } else {
-                    // Fall back to placeholder
-                    draw_image_placeholder(frame, layout, &alt, screen_width, screen_height);
-                }
-            }
-        }
-    }
-    
-    // Also check for CSS background images
-    if let Some(bg) = layout.style.get("background-image").or(layout.style.get("background")) {
-        if let Some(url) = extract_url_from_css_value(bg) {
-            if let Some(img_data) = network.fetch_image(&url) {
-                draw_background_image(frame, layout, &img_data, screen_width, screen_height);
-            }
-        }
-    }
-    
-    for child in &layout.children {
-        draw_images(frame, child, dom, network, screen_width, screen_height, scale_factor);
-    }
+/// Where the browser keeps data between runs, such as cookies:
+/// `$XDG_DATA_HOME/grob`, else `~/.local/share/grob`
+fn data_dir() -> std::path::PathBuf {
+    xdg_dir("XDG_DATA_HOME", ".local/share", "grob_data")
 }
 
-// Extract URL from CSS url(...) value
-fn extract_url_from_css_value(value: &str) -> Option<String> {
-    let value = value.trim().to_lowercase();
-    if let Some(start) = value.find("url(") {
-        let rest = &value[start + 4..];
-        if let Some(end) = rest.find(')') {
-            let url = rest[..end].trim();
-            // Remove quotes if present
-            let url = url.trim_matches(|c| c == '"' || c == '\'');
-            if !url.is_empty() && !url.starts_with("data:") {
-                return Some(url.to_string());
-            }
-        }
-    }
-    None
+/// Where the browser keeps its HTTP cache: `$XDG_CACHE_HOME/grob`, else
+/// `~/.cache/grob`
+fn cache_dir() -> std::path::PathBuf {
+    xdg_dir("XDG_CACHE_HOME", ".cache", "grob_cache")
 }
 
-// Draw a background image
-fn draw_background_image(frame: &mut [u8], layout: &engine::layout::LayoutBox, img: &image::RgbaImage, screen_width: usize, screen_height: usize) {
-    let dims = &layout.dimensions;
-    let x = dims.x as usize;
-    let y = dims.y as usize;
-    let width = dims.width as usize;
-    let height = dims.height as usize;
-    
-    // Tile or stretch the background image
-    for py in 0..height {
-        if y + py >= screen_height {
-            break;
-        }
-        for px in 0..width {
-            if x + px >= screen_width {
-                break;
-            }
-            
-            // Tile the image
-            let src_x = (px as u32) % img.width();
-            let src_y = (py as u32) % img.height();
-            
-            if let Some(pixel) = img.get_pixel_checked(src_x, src_y) {
-                let screen_idx = ((y + py) * screen_width + (x + px)) * 4;
-                if screen_idx + 3 < frame.len() && pixel[3] > 0 {
-                    // Alpha blending
-                    let alpha = pixel[3] as u32;
-                    let inv_alpha = 255 - alpha;
-                    frame[screen_idx] = ((frame[screen_idx] as u32 * inv_alpha + pixel[0] as u32 * alpha) / 255) as u8;
-                    frame[screen_idx + 1] = ((frame[screen_idx + 1] as u32 * inv_alpha + pixel[1] as u32 * alpha) / 255) as u8;
-                    frame[screen_idx + 2] = ((frame[screen_idx + 2] as u32 * inv_alpha + pixel[2] as u32 * alpha) / 255) as u8;
-                    frame[screen_idx + 3] = 255;
-                }
-            }
-        }
-    }
-}
-
-fn draw_image_placeholder(frame: &mut [u8], layout: &engine::layout::LayoutBox, alt: &str, screen_width: usize, screen_height: usize) {
-    let dims = &layout.dimensions;
-    let x = dims.x as usize;
-    let y = dims.y as usize;
-    let width = dims.width as usize;
-    let height = dims.height as usize;
-    
-    // Draw a light gray placeholder with border
-    for py in y..(y + height).min(screen_height) {
-        for px in x..(x + width).min(screen_width) {
-            let idx = (py * screen_width + px) * 4;
-            if idx + 3 < frame.len() {
-                // Light gray background
-                frame[idx] = 200;     // R
-                frame[idx + 1] = 200; // G
-                frame[idx + 2] = 200; // B
-                frame[idx + 3] = 255; // A
-                
-                // Draw border (dark gray)
-                if py == y || py == y + height - 1 || px == x || px == x + width - 1 {
-                    frame[idx] = 100;
-                    frame[idx + 1] = 100;
-                    frame[idx + 2] = 100;
-                }
-            }
-        }
-    }
-    
-    eprintln!("Drew image placeholder for '{}' ({}x{}) at ({},{})", 
-        alt, width, height, x, y);
-}
-
-fn draw_real_image(frame: &mut [u8], layout: &engine::layout::LayoutBox, img: &image::RgbaImage, alt: &str, screen_width: usize, screen_height: usize) {
-    let dims = &layout.dimensions;
-    let x = dims.x as usize;
-    let y = dims.y as usize;
-    let width = dims.width.min(img.width() as f32) as usize;
-    let height = dims.height.min(img.height() as f32) as usize;
-    
-    // Draw the image, scaling if necessary
-    for py in 0..height {
-        if y + py >= screen_height {
-            break;
-        }
-        for px in 0..width {
-            if x + px >= screen_width {
-                break;
-            }
-            
-            // Sample from source image (scaled)
-            let src_x = (px as f32 * img.width() as f32 / width as f32) as u32;
-            let src_y = (py as f32 * img.height() as f32 / height as f32) as u32;
-            
-            if let Some(pixel) = img.get_pixel_checked(src_x, src_y) {
-                let screen_idx = ((y + py) * screen_width + (x + px)) * 4;
-                if screen_idx + 3 < frame.len() {
-                    frame[screen_idx] = pixel[0];     // R
-                    frame[screen_idx + 1] = pixel[1]; // G
-                    frame[screen_idx + 2] = pixel[2]; // B
-                    frame[screen_idx + 3] = 255;      // A (opaque)
-                }
-            }
-        }
-    }
-    
-    eprintln!("Drew real image '{}' ({}x{}) at ({},{})", alt, width, height, x, y);
-}
-
-fn fetch_html(url: &str) -> Result<String, Box<dyn std::error::Error>> {
-    use reqwest::blocking::Client;
-    
-    let client = Client::builder()
-        .timeout(std::time::Duration::from_secs(10))
-        .build()?;
-    
-    eprintln!("Fetching HTML from: {}", url);
-    
-    let response = client.get(url).send()?;
-    let status = response.status();
-    
-    if !status.is_success() {
-        return Err(format!("{}", status).into());
+/// `$variable/grob`, else `~/home_relative/grob`, else `fallback` in the
+/// working directory
+fn xdg_dir(variable: &str, home_relative: &str, fallback: &str) -> std::path::PathBuf {
+    match std::env::var_os(variable).filter(|dir| !dir.is_empty()) {
+        Some(dir) => std::path::PathBuf::from(dir).join("grob"),
+        None => match std::env::var_os("HOME") {
+            Some(home) => std::path::PathBuf::from(home).join(home_relative).join("grob"),
+            None => std::path::PathBuf::from(fallback),
+        },
     }
-    
-    let html = response.text()?;
-    eprintln!("Successfully fetched {} bytes from {}", html.len(), url);
-    Ok(html)
 }
 
 fn extract_css_from_dom(dom: &engine::dom::Dom, node_id: engine::dom::NodeId) -> String {
@@ -810,48 +664,3 @@ fn extract_css_from_dom(dom: &engine::dom::Dom, node_id: engine::dom::NodeId) ->
     log(&format!("extract_css_from_dom done: {} bytes", css_content.len()));
     css_content
 }
-
-fn convert_css_selector(css_selector: &engine::parser::css::parser::Selector) -> Selector {
-    use engine::parser::css::parser::Selector as CssSelector;
-    
-    fn extract_tag_and_pseudo(sel: &CssSelector) -> (Option<String>, Option<String>) {
-        match sel {
-            CssSelector::Element(tag) => (Some(tag.clone()), None),
-            CssSelector::PseudoClass(pseudo) => (None, Some(pseudo.clone())),
-            CssSelector::Descendant(parent, child) => {
-                let (p_tag, p_pseudo) = extract_tag_and_pseudo(parent);
-                let (c_tag, c_pseudo) = extract_tag_and_pseudo(child);
-                (c_tag.or(p_tag), c_pseudo.or(p_pseudo))
-            },
-            CssSelector::Child(parent, child) => {
-                let (p_tag, p_pseudo) = extract_tag_and_pseudo(parent);
-                let (c_tag, c_pseudo) = extract_tag_and_pseudo(child);
-                (c_tag.or(p_tag), c_pseudo.or(p_pseudo))
-            },
-            _ => (None, None),
-        }
-    }
-    
-    match css_selector {
-        CssSelector::Element(tag) => Selector::Tag(tag.clone()),
-        CssSelector::Id(id) => Selector::Id(id.clone()),
-        CssSelector::Class(class) => Selector::Class(class.clone()),
-        CssSelector::Descendant(_, _) | CssSelector::Child(_, _) => {
-            let (tag, pseudo) = extract_tag_and_pseudo(css_selector);
-            match (tag, pseudo) {
-                (Some(t), Some(p)) => Selector::TagWithPseudo(t, p),
-                (Some(t), None) => Selector::Tag(t),
-                (None, Some(p)) => Selector::Tag(p),
-                (None, None) => Selector::Any,
-            }
-        },
-        CssSelector::Adjacent(_, child) => convert_css_selector(child),
-        CssSelector::GeneralSibling(_, child) => convert_css_selector(child),
-        CssSelector::Universal => Selector::Any,
-        _ => Selector::Any,
-    }
-}
-
-
-
-
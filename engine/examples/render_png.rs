@@ -0,0 +1,106 @@
+// Render an HTML file or URL to a PNG without a window
+//
+//     cargo run --example render_png -- <file-or-url> <output.png> [--width 800] [--height 600] [--dpr 1]
+
+use std::process::exit;
+
+use grob_engine::dom::{Dom, NodeId, NodeType};
+use grob_engine::font::FontManager;
+use grob_engine::layout::LayoutEngine;
+use grob_engine::net::NetworkManager;
+use grob_engine::paint::{render, DisplayList};
+use grob_engine::parser::html::{extract_base_href, extract_stylesheet_links};
+use grob_engine::parser::html::tree_builder::HtmlParser;
+use grob_engine::style::{Stylesheet, Viewport};
+
+const USAGE: &str = "usage: render_png <file-or-url> <output.png> [--width 800] [--height 600] [--dpr 1]";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut positional = Vec::new();
+    let mut viewport = Viewport::new(800.0, 600.0);
+    let mut device_pixel_ratio = 1.0;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut number = |name: &str| -> f32 {
+            match iter.next().and_then(|v| v.parse::<f32>().ok()).filter(|v| *v > 0.0) {
+                Some(value) => value,
+                None => fail(&format!("{} needs a positive number", name)),
+            }
+        };
+        match arg.as_str() {
+            "--width" => viewport.width = number("--width"),
+            "--height" => viewport.height = number("--height"),
+            "--dpr" => device_pixel_ratio = number("--dpr"),
+            _ if arg.starts_with("--") => fail(&format!("unknown option {}", arg)),
+            _ => positional.push(arg.as_str()),
+        }
+    }
+    let [input, output] = positional[..] else {
+        fail(USAGE);
+    };
+
+    let network = NetworkManager::new();
+    let html = if input.starts_with("http://") || input.starts_with("https://") {
+        network.set_document_url(input);
+        match network.fetch_resource(input) {
+            Some(resource) => String::from_utf8_lossy(&resource.data).into_owned(),
+            None => fail(&format!("could not fetch {}", input)),
+        }
+    } else {
+        std::fs::read_to_string(input).unwrap_or_else(|e| fail(&format!("could not read {}: {}", input, e)))
+    };
+
+    let dom = HtmlParser::new(&html).parse();
+    if let Some(base_href) = extract_base_href(&dom) {
+        network.set_base_href(&base_href);
+    }
+    let mut stylesheet = Stylesheet::new();
+    // Linked sheets load in parallel and apply before the <style> elements
+    let links: Vec<String> = extract_stylesheet_links(&dom).iter().map(|(_, href)| network.resolve_url(href)).collect();
+    for resource in network.fetch_resources(&links).into_iter().flatten() {
+        stylesheet.add_css_with_base(&String::from_utf8_lossy(&resource.data), &resource.url);
+    }
+    stylesheet.add_css_with_base(&style_text(&dom, dom.root()), &network.resolve_url(""));
+    stylesheet.set_viewport(viewport);
+
+    let mut font_manager = FontManager::new();
+    font_manager.load_font_faces(&stylesheet.font_faces, |url| {
+        network.fetch_resource(url).map(|resource| resource.data)
+    });
+    let mut layout_engine = LayoutEngine::new();
+    layout_engine.set_viewport(viewport);
+    let styles = stylesheet.compute_styles(&dom, &viewport);
+    let root = layout_engine.layout_with_styles(&dom, &styles, viewport, &mut font_manager);
+    let list = DisplayList::build(&root, &dom, device_pixel_ratio);
+
+    let image = render(&list, viewport, device_pixel_ratio, &mut font_manager, Some(&network));
+    if let Err(e) = image.save(output) {
+        fail(&format!("could not write {}: {}", output, e));
+    }
+    println!("Wrote {}x{} image to {}", image.width(), image.height(), output);
+}
+
+/// The contents of every `<style>` element, in document order
+fn style_text(dom: &Dom, node_id: NodeId) -> String {
+    let node = &dom.nodes[node_id];
+    let mut css = String::new();
+    if dom.tag_name(node_id) == Some("style") {
+        for &child in &node.children {
+            if let NodeType::Text(text) = &dom.nodes[child].node_type {
+                css.push_str(text);
+                css.push('\n');
+            }
+        }
+    }
+    for &child in &node.children {
+        css.push_str(&style_text(dom, child));
+    }
+    css
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("render_png: {}", message);
+    exit(1);
+}
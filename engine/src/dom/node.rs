@@ -62,6 +62,61 @@ impl Dom {
         0
     }
 
+    /// Remove a node from its parent's child list, leaving it (and its subtree) detached
+    pub fn detach(&mut self, id: NodeId) {
+        if let Some(pid) = self.nodes[id].parent.take() {
+            self.nodes[pid].children.retain(|&c| c != id);
+        }
+    }
+
+    /// Append a node as the last child of `parent`, moving it if it is already attached
+    pub fn append_child(&mut self, parent: NodeId, child: NodeId) {
+        self.detach(child);
+        self.nodes[child].parent = Some(parent);
+        self.nodes[parent].children.push(child);
+    }
+
+    /// Insert `child` into `parent` immediately before `reference`.
+    /// Appends if `reference` is not a child of `parent`.
+    pub fn insert_before(&mut self, parent: NodeId, child: NodeId, reference: NodeId) {
+        self.detach(child);
+        self.nodes[child].parent = Some(parent);
+        let siblings = &mut self.nodes[parent].children;
+        match siblings.iter().position(|&c| c == reference) {
+            Some(pos) => siblings.insert(pos, child),
+            None => siblings.push(child),
+        }
+    }
+
+    /// Move all children of `from` to the end of `to`, preserving order
+    pub fn reparent_children(&mut self, from: NodeId, to: NodeId) {
+        let children = std::mem::take(&mut self.nodes[from].children);
+        for &child in &children {
+            self.nodes[child].parent = Some(to);
+        }
+        self.nodes[to].children.extend(children);
+    }
+
+    /// Tag name of an element node, or None for text nodes
+    pub fn tag_name(&self, id: NodeId) -> Option<&str> {
+        match &self.nodes[id].node_type {
+            NodeType::Element(el) => Some(el.tag_name.as_str()),
+            NodeType::Text(_) => None,
+        }
+    }
+
+    /// Value of an attribute on an element node (names compared ASCII case-insensitively)
+    pub fn get_attribute(&self, id: NodeId, name: &str) -> Option<&str> {
+        match &self.nodes[id].node_type {
+            NodeType::Element(el) => el
+                .attributes
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(name))
+                .map(|(_, v)| v.as_str()),
+            NodeType::Text(_) => None,
+        }
+    }
+
     pub fn pretty_print(&self, id: NodeId, indent: usize) {
         let node = &self.nodes[id];
         println!(
@@ -0,0 +1,424 @@
+// engine/src/font/discovery.rs
+// System font discovery, in the manner of fontconfig
+//
+// - Font directories are scanned recursively for TrueType and OpenType files
+//   and collections (.ttf, .otf, .ttc, .otc), once per process
+// - Each face is indexed under its family names (the typographic family and
+//   the legacy one, which differ for fonts with more than four styles) with
+//   the weight, style and stretch from its OS/2 table
+// - Faces can also be looked up by full or PostScript name, for `local()`
+//   sources of `@font-face` rules
+// - Faces are matched as in CSS Fonts 4 section 5.2: the nearest stretch,
+//   then the closest style, then the nearest weight
+// - Generic families, and common proprietary families that are not
+//   installed, resolve through an alias table that callers can change
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+
+use rustybuzz::ttf_parser::{self, name_id};
+
+/// Directory nesting followed while scanning, which also stops symlink loops
+const MAX_SCAN_DEPTH: usize = 8;
+
+/// Aliases lookups follow into other aliases
+const MAX_ALIAS_DEPTH: usize = 4;
+
+/// Family lists for generic families and for common families with
+/// metric-compatible replacements, tried in order. Entries may name other
+/// aliases.
+const DEFAULT_ALIASES: &[(&str, &[&str])] = &[
+    ("serif", &["Liberation Serif", "DejaVu Serif", "Noto Serif", "Tinos", "FreeSerif", "Nimbus Roman"]),
+    ("sans-serif", &["Liberation Sans", "DejaVu Sans", "Ubuntu", "Noto Sans", "Arimo", "FreeSans", "Nimbus Sans"]),
+    (
+        "monospace",
+        &["Liberation Mono", "DejaVu Sans Mono", "Noto Sans Mono", "Ubuntu Mono", "Cousine", "FreeMono", "Nimbus Mono PS"],
+    ),
+    ("cursive", &["Comic Neue", "Comic Sans MS", "serif"]),
+    ("fantasy", &["Impact", "serif"]),
+    ("math", &["DejaVu Math TeX Gyre", "Latin Modern Math", "serif"]),
+    ("emoji", &["Noto Emoji", "Symbola"]),
+    ("system-ui", &["Cantarell", "sans-serif"]),
+    ("ui-serif", &["serif"]),
+    ("ui-sans-serif", &["sans-serif"]),
+    ("ui-monospace", &["monospace"]),
+    ("sans", &["sans-serif"]),
+    ("times new roman", &["Liberation Serif", "Tinos", "Nimbus Roman", "serif"]),
+    ("times", &["times new roman"]),
+    ("arial", &["Liberation Sans", "Arimo", "Nimbus Sans", "sans-serif"]),
+    ("helvetica", &["arial"]),
+    ("courier new", &["Liberation Mono", "Cousine", "Nimbus Mono PS", "monospace"]),
+    ("courier", &["courier new"]),
+    ("georgia", &["Gelasio", "serif"]),
+    ("verdana", &["DejaVu Sans", "sans-serif"]),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaceStyle {
+    Normal,
+    Italic,
+    Oblique,
+}
+
+/// One face of a font file
+#[derive(Debug, Clone, PartialEq)]
+pub struct FaceInfo {
+    pub path: PathBuf,
+    /// Index of the face in a collection; 0 for single font files
+    pub index: u32,
+    /// The family names the face is indexed under, as the font spells them
+    pub families: Vec<String>,
+    /// 1 to 1000, as CSS `font-weight`
+    pub weight: u16,
+    pub style: FaceStyle,
+    /// Percent of the normal width, as CSS `font-stretch`
+    pub stretch: f32,
+}
+
+/// Installed faces by family, and the aliases families resolve through
+#[derive(Debug, Clone)]
+pub struct FontIndex {
+    faces: Vec<FaceInfo>,
+    /// Positions in `faces` by lowercase family name
+    by_family: HashMap<String, Vec<usize>>,
+    /// Family lists by lowercase alias
+    aliases: HashMap<String, Vec<String>>,
+    /// Positions in `faces` by lowercase full and PostScript name, for
+    /// `local()` font sources
+    by_name: HashMap<String, usize>,
+}
+
+impl Default for FontIndex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FontIndex {
+    /// An index with no faces and the default aliases
+    pub fn new() -> Self {
+        let mut index = FontIndex {
+            faces: Vec::new(),
+            by_family: HashMap::new(),
+            aliases: HashMap::new(),
+            by_name: HashMap::new(),
+        };
+        for (alias, families) in DEFAULT_ALIASES {
+            index.set_alias(alias, families.iter().map(|family| family.to_string()).collect());
+        }
+        index
+    }
+
+    /// The faces in the system font directories, scanned on first use
+    pub fn system() -> Arc<FontIndex> {
+        static SYSTEM: OnceLock<Arc<FontIndex>> = OnceLock::new();
+        SYSTEM.get_or_init(|| Arc::new(FontIndex::scan(&font_dirs()))).clone()
+    }
+
+    /// Index every font file under `dirs`
+    pub fn scan(dirs: &[PathBuf]) -> Self {
+        let mut index = FontIndex::new();
+        for dir in dirs {
+            index.scan_dir(dir, 0);
+        }
+        index
+    }
+
+    fn scan_dir(&mut self, dir: &Path, depth: usize) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        // Sorted, so the same installation always indexes the same way
+        let mut paths: Vec<PathBuf> = entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()).collect();
+        paths.sort();
+        for path in paths {
+            if path.is_dir() {
+                if depth < MAX_SCAN_DEPTH {
+                    self.scan_dir(&path, depth + 1);
+                }
+                continue;
+            }
+            let extension = path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase);
+            if matches!(extension.as_deref(), Some("ttf" | "otf" | "ttc" | "otc")) {
+                if let Ok(data) = std::fs::read(&path) {
+                    self.add_file(&path, &data);
+                }
+            }
+        }
+    }
+
+    /// Index every face of the font file at `path`, whose contents are
+    /// `data`. Returns how many faces were added.
+    pub fn add_file(&mut self, path: &Path, data: &[u8]) -> usize {
+        let count = ttf_parser::fonts_in_collection(data).unwrap_or(1);
+        let mut added = 0;
+        for index in 0..count {
+            let Ok(face) = ttf_parser::Face::parse(data, index) else {
+                continue;
+            };
+            let mut families: Vec<String> = Vec::new();
+            for id in [name_id::TYPOGRAPHIC_FAMILY, name_id::FAMILY] {
+                let names = face.names().into_iter().filter(|name| name.name_id == id && name.is_unicode());
+                for family in names.filter_map(|name| name.to_string()) {
+                    if !families.iter().any(|known| known.eq_ignore_ascii_case(&family)) {
+                        families.push(family);
+                    }
+                }
+            }
+            if families.is_empty() {
+                continue;
+            }
+            for name in face.names().into_iter().filter(|name| {
+                (name.name_id == name_id::FULL_NAME || name.name_id == name_id::POST_SCRIPT_NAME) && name.is_unicode()
+            }) {
+                if let Some(name) = name.to_string() {
+                    self.by_name.entry(name.to_lowercase()).or_insert(self.faces.len());
+                }
+            }
+            let style = match face.style() {
+                ttf_parser::Style::Normal => FaceStyle::Normal,
+                ttf_parser::Style::Italic => FaceStyle::Italic,
+                ttf_parser::Style::Oblique => FaceStyle::Oblique,
+            };
+            self.add_face(FaceInfo {
+                path: path.to_path_buf(),
+                index,
+                families,
+                weight: face.weight().to_number(),
+                style,
+                stretch: stretch_percent(face.width().to_number()),
+            });
+            added += 1;
+        }
+        added
+    }
+
+    fn add_face(&mut self, face: FaceInfo) {
+        let position = self.faces.len();
+        for family in &face.families {
+            self.by_family.entry(family.to_lowercase()).or_default().push(position);
+        }
+        self.faces.push(face);
+    }
+
+    pub fn faces(&self) -> &[FaceInfo] {
+        &self.faces
+    }
+
+    /// Make `alias` resolve to the first installed family of `families`,
+    /// when no installed family is called `alias` itself
+    pub fn set_alias(&mut self, alias: &str, families: Vec<String>) {
+        self.aliases.insert(alias.to_lowercase(), families);
+    }
+
+    /// The face of `family` (an installed family or an alias) that best
+    /// matches the weight, style and stretch
+    pub fn find(&self, family: &str, weight: u16, style: FaceStyle, stretch: f32) -> Option<&FaceInfo> {
+        self.find_within(family, weight, style, stretch, 0)
+    }
+
+    /// The face whose full name or PostScript name is `name`, as CSS
+    /// `local()` looks faces up
+    pub fn find_local(&self, name: &str) -> Option<&FaceInfo> {
+        self.by_name.get(&name.to_lowercase()).map(|&i| &self.faces[i])
+    }
+
+    fn find_within(&self, family: &str, weight: u16, style: FaceStyle, stretch: f32, depth: usize) -> Option<&FaceInfo> {
+        let key = family.to_lowercase();
+        if let Some(positions) = self.by_family.get(&key) {
+            let faces = positions.iter().map(|&i| &self.faces[i]).collect();
+            return best_match(faces, |face| (face.weight, face.style, face.stretch), weight, style, stretch);
+        }
+        if depth >= MAX_ALIAS_DEPTH {
+            return None;
+        }
+        self.aliases
+            .get(&key)?
+            .iter()
+            .find_map(|family| self.find_within(family, weight, style, stretch, depth + 1))
+    }
+}
+
+/// CSS Fonts 4 font matching within one family, of faces whose weight,
+/// style and stretch `descriptors` gives
+pub(crate) fn best_match<T>(
+    faces: Vec<T>,
+    descriptors: impl Fn(&T) -> (u16, FaceStyle, f32),
+    weight: u16,
+    style: FaceStyle,
+    stretch: f32,
+) -> Option<T> {
+    // Narrower widths are preferred for condensed requests, wider ones for
+    // expanded requests
+    let stretch_rank = |s: f32| match (stretch <= 100.0, s <= stretch) {
+        (true, true) => (0, stretch - s),
+        (true, false) => (1, s - stretch),
+        (false, false) => (0, s - stretch),
+        (false, true) => (1, stretch - s),
+    };
+    let best_stretch =
+        faces.iter().map(|f| descriptors(f).2).min_by(|a, b| rank_cmp(stretch_rank(*a), stretch_rank(*b)))?;
+    let faces: Vec<T> = faces.into_iter().filter(|f| descriptors(f).2 == best_stretch).collect();
+
+    let style_order = match style {
+        FaceStyle::Italic => [FaceStyle::Italic, FaceStyle::Oblique, FaceStyle::Normal],
+        FaceStyle::Oblique => [FaceStyle::Oblique, FaceStyle::Italic, FaceStyle::Normal],
+        FaceStyle::Normal => [FaceStyle::Normal, FaceStyle::Oblique, FaceStyle::Italic],
+    };
+    let best_style = style_order.into_iter().find(|s| faces.iter().any(|f| descriptors(f).1 == *s))?;
+
+    let weight = weight as f32;
+    let weight_rank = |w: u16| {
+        let w = w as f32;
+        if (400.0..=500.0).contains(&weight) {
+            // Up to 500 first, then lighter, then heavier
+            if w >= weight && w <= 500.0 {
+                (0, w - weight)
+            } else if w < weight {
+                (1, weight - w)
+            } else {
+                (2, w - weight)
+            }
+        } else if weight < 400.0 {
+            if w <= weight { (0, weight - w) } else { (1, w - weight) }
+        } else if w >= weight {
+            (0, w - weight)
+        } else {
+            (1, weight - w)
+        }
+    };
+    faces
+        .into_iter()
+        .filter(|f| descriptors(f).1 == best_style)
+        .min_by(|a, b| rank_cmp(weight_rank(descriptors(a).0), weight_rank(descriptors(b).0)))
+}
+
+fn rank_cmp(a: (u8, f32), b: (u8, f32)) -> std::cmp::Ordering {
+    a.0.cmp(&b.0).then(a.1.total_cmp(&b.1))
+}
+
+/// The `font-stretch` percentage of an OS/2 width class (1 to 9)
+fn stretch_percent(width_class: u16) -> f32 {
+    const PERCENTS: [f32; 9] = [50.0, 62.5, 75.0, 87.5, 100.0, 112.5, 125.0, 150.0, 200.0];
+    PERCENTS[width_class.clamp(1, 9) as usize - 1]
+}
+
+/// Directories fonts are installed in, most general first
+fn font_dirs() -> Vec<PathBuf> {
+    #[cfg(target_os = "linux")]
+    {
+        let mut dirs = Vec::new();
+        let data_dirs = std::env::var("XDG_DATA_DIRS").unwrap_or_default();
+        for dir in data_dirs.split(':').filter(|dir| !dir.is_empty()) {
+            dirs.push(PathBuf::from(dir).join("fonts"));
+        }
+        for dir in ["/usr/share/fonts", "/usr/local/share/fonts"] {
+            dirs.push(PathBuf::from(dir));
+        }
+        if let Ok(home) = std::env::var("HOME") {
+            let data_home = std::env::var("XDG_DATA_HOME").unwrap_or_else(|_| format!("{}/.local/share", home));
+            dirs.push(PathBuf::from(data_home).join("fonts"));
+            dirs.push(PathBuf::from(home).join(".fonts"));
+        }
+        // XDG_DATA_DIRS usually repeats /usr/share
+        let mut seen = Vec::new();
+        dirs.retain(|dir| {
+            let canonical = dir.canonicalize().unwrap_or_else(|_| dir.clone());
+            let new = !seen.contains(&canonical);
+            seen.push(canonical);
+            new
+        });
+        dirs
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn face(family: &str, weight: u16, style: FaceStyle, stretch: f32) -> FaceInfo {
+        FaceInfo {
+            path: PathBuf::from(format!("{}-{}-{:?}-{}.ttf", family, weight, style, stretch)),
+            index: 0,
+            families: vec![family.to_string()],
+            weight,
+            style,
+            stretch,
+        }
+    }
+
+    fn index_of(faces: Vec<FaceInfo>) -> FontIndex {
+        let mut index = FontIndex::new();
+        for face in faces {
+            index.add_face(face);
+        }
+        index
+    }
+
+    #[test]
+    fn test_nearest_weight() {
+        let index = index_of(
+            [300, 500, 700].into_iter().map(|w| face("Test", w, FaceStyle::Normal, 100.0)).collect(),
+        );
+        let weight = |w| index.find("test", w, FaceStyle::Normal, 100.0).unwrap().weight;
+        // 400 looks up to 500 before going lighter
+        assert_eq!(weight(400), 500);
+        assert_eq!(weight(450), 500);
+        // Bold looks heavier first, then lighter
+        assert_eq!(weight(600), 700);
+        assert_eq!(weight(900), 700);
+        // Light looks lighter first, then heavier
+        assert_eq!(weight(200), 300);
+        assert_eq!(weight(100), 300);
+
+        // Without anything from 450 to 500, lighter comes before heavier
+        let sparse = index_of(vec![face("Test", 300, FaceStyle::Normal, 100.0), face("Test", 600, FaceStyle::Normal, 100.0)]);
+        assert_eq!(sparse.find("test", 450, FaceStyle::Normal, 100.0).unwrap().weight, 300);
+    }
+
+    #[test]
+    fn test_stretch_then_style() {
+        let index = index_of(vec![
+            face("Test", 400, FaceStyle::Italic, 87.5),
+            face("Test", 400, FaceStyle::Normal, 100.0),
+            face("Test", 700, FaceStyle::Oblique, 100.0),
+        ]);
+        // Normal width wins over italic, and oblique stands in for italic
+        let found = index.find("Test", 400, FaceStyle::Italic, 100.0).unwrap();
+        assert_eq!((found.style, found.weight), (FaceStyle::Oblique, 700));
+        let condensed = index.find("Test", 400, FaceStyle::Normal, 75.0).unwrap();
+        assert_eq!(condensed.stretch, 87.5);
+    }
+
+    #[test]
+    fn test_aliases() {
+        let mut index = index_of(vec![face("Real Serif", 400, FaceStyle::Normal, 100.0)]);
+        assert!(index.find("serif", 400, FaceStyle::Normal, 100.0).is_none());
+        index.set_alias("serif", vec!["Missing".to_string(), "Real Serif".to_string()]);
+        // Times New Roman goes through serif
+        let found = index.find("Times", 400, FaceStyle::Normal, 100.0).unwrap();
+        assert_eq!(found.families, ["Real Serif"]);
+        assert!(index.find("Unknown", 400, FaceStyle::Normal, 100.0).is_none());
+    }
+
+    #[test]
+    fn test_indexes_font_files() {
+        let dir = PathBuf::from("/usr/share/fonts/truetype/dejavu");
+        if !dir.join("DejaVuSans-Bold.ttf").exists() {
+            return;
+        }
+        let index = FontIndex::scan(&[dir]);
+        let bold = index.find("DejaVu Sans", 700, FaceStyle::Normal, 100.0).unwrap();
+        assert!(bold.path.ends_with("DejaVuSans-Bold.ttf"), "{:?}", bold.path);
+        let regular = index.find("dejavu sans", 400, FaceStyle::Normal, 100.0).unwrap();
+        assert!(regular.path.ends_with("DejaVuSans.ttf"), "{:?}", regular.path);
+        let italic = index.find("DejaVu Serif", 400, FaceStyle::Italic, 100.0).unwrap();
+        assert_eq!(italic.style, FaceStyle::Italic);
+    }
+}
@@ -1,8 +1,64 @@
+pub mod discovery;
+pub mod shaping;
+pub mod web;
+pub mod woff;
+
 use rusttype::Font;
 use std::collections::HashMap;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+pub use discovery::{FaceInfo, FaceStyle, FontIndex};
+pub use shaping::{GlyphRun, ShapedGlyph};
+pub use web::{FontFace, FontSource};
+
+/// Shaped runs kept before the cache is emptied
+const RUN_CACHE_LIMIT: usize = 16384;
+
+/// Used when no family of a `font-family` list is installed
+const DEFAULT_FAMILY: &str = "sans-serif";
+
+/// A font loaded by a `FontManager`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FontId(usize);
+
+/// One font file, parsed for rasterizing and for shaping
+struct LoadedFont {
+    font: Font<'static>,
+    face: rustybuzz::Face<'static>,
+    /// Code points the font is used for, from an `@font-face`
+    /// unicode-range; empty for all of them
+    unicode_range: Vec<RangeInclusive<u32>>,
+}
+
+/// A face registered by an `@font-face` rule
+struct WebFont {
+    /// Lowercase family name
+    family: String,
+    weight: (u16, u16),
+    style: FaceStyle,
+    font: FontId,
+}
 
 pub struct FontManager {
-    fonts: HashMap<String, Font<'static>>,
+    fonts: Vec<LoadedFont>,
+    /// Installed fonts by family, on Linux
+    index: Arc<FontIndex>,
+    /// Fonts by file and face index; None if the file does not load
+    files: HashMap<(PathBuf, u32), Option<FontId>>,
+    /// Faces from `@font-face` rules, in the order they were added
+    web_fonts: Vec<WebFont>,
+    /// The fonts for each single family and variant: one, or for web fonts
+    /// every face split by unicode-range; empty if none loads
+    families: HashMap<String, Vec<FontId>>,
+    /// System fallback fonts loaded so far, in fallback order
+    fallbacks: Vec<FontId>,
+    /// How many of `fallback_font_paths` have been tried
+    fallback_paths_tried: usize,
+    /// Shaped runs by font key, size bits and text, so a word the line
+    /// breaker measured is painted from the same glyphs
+    runs: HashMap<(String, u32, String), Arc<GlyphRun>>,
 }
 
 impl Default for FontManager {
@@ -14,66 +70,271 @@ impl Default for FontManager {
 impl FontManager {
     pub fn new() -> Self {
         Self {
-            fonts: HashMap::new(),
+            fonts: Vec::new(),
+            index: FontIndex::system(),
+            files: HashMap::new(),
+            web_fonts: Vec::new(),
+            families: HashMap::new(),
+            fallbacks: Vec::new(),
+            fallback_paths_tried: 0,
+            runs: HashMap::new(),
         }
     }
 
+    /// The installed fonts families are looked up in. Changing it (to add
+    /// aliases, say) only affects families not loaded yet.
+    pub fn font_index_mut(&mut self) -> &mut FontIndex {
+        Arc::make_mut(&mut self.index)
+    }
+
     /// Load a system font by family name with optional bold/italic variants
     pub fn load_system_font(&mut self, family: &str) -> Option<&Font<'static>> {
         self.load_font_variant(family, false, false)
     }
 
     pub fn load_font_variant(&mut self, family: &str, bold: bool, italic: bool) -> Option<&Font<'static>> {
-        let key = format!("{}-{}-{}", family, bold, italic);
-        
-        if self.fonts.contains_key(&key) {
-            return self.fonts.get(&key);
-        }
-
-        // Handle font-family lists (e.g., "system-ui,sans-serif")
-        let families: Vec<&str> = family.split(',').map(|f| f.trim()).collect();
-        
-        for font_family in families {
-            if let Some(font_data) = self.get_system_font_bytes_variant(font_family, bold, italic) {
-                let font_bytes: &'static [u8] = Box::leak(font_data.into_boxed_slice());
-                if let Some(font) = Font::try_from_bytes(font_bytes) {
-                    self.fonts.insert(key.clone(), font);
-                    return self.fonts.get(&key);
+        self.load_font(family, if bold { 700 } else { 400 }, italic)
+    }
+
+    /// The first family of a `font-family` list that loads, in the face
+    /// nearest to `weight` (100 to 900)
+    pub fn load_font(&mut self, family: &str, weight: u16, italic: bool) -> Option<&Font<'static>> {
+        let id = self.family_chain(family, weight, italic).into_iter().next()?;
+        Some(self.font(id))
+    }
+
+    pub fn font(&self, id: FontId) -> &Font<'static> {
+        &self.fonts[id.0].font
+    }
+
+    /// The fonts of a `font-family` list (e.g., "system-ui,sans-serif") that
+    /// load, in order and without repeats; the default family if none does
+    fn family_chain(&mut self, family: &str, weight: u16, italic: bool) -> Vec<FontId> {
+        let mut chain = Vec::new();
+        for font_family in family.split(',').map(|f| f.trim().trim_matches(|c| c == '"' || c == '\'')) {
+            for id in self.load_family(font_family, weight, italic) {
+                if !chain.contains(&id) {
+                    chain.push(id);
                 }
             }
         }
-        
+        if chain.is_empty() {
+            chain.extend(self.load_family(DEFAULT_FAMILY, weight, italic));
+        }
+        chain
+    }
+
+    /// The fonts of one family: its `@font-face` faces if it has any,
+    /// otherwise the installed face
+    fn load_family(&mut self, family: &str, weight: u16, italic: bool) -> Vec<FontId> {
+        let key = Self::font_key(family, weight, italic);
+        if let Some(ids) = self.families.get(&key) {
+            return ids.clone();
+        }
+        let ids = match self.web_family(family, weight, italic) {
+            Some(ids) => ids,
+            None => self
+                .system_font_path(family, weight, italic)
+                .and_then(|(path, index)| self.load_file(&path, index))
+                .into_iter()
+                .collect(),
+        };
+        self.families.insert(key, ids.clone());
+        ids
+    }
+
+    /// The `@font-face` faces of `family` that best match the weight and
+    /// style, matched as installed faces are (a weight range matches the
+    /// weights inside it exactly); faces that differ only in unicode-range
+    /// all count, the last added first. None if no rule names the family.
+    fn web_family(&self, family: &str, weight: u16, italic: bool) -> Option<Vec<FontId>> {
+        let family = family.to_lowercase();
+        let faces: Vec<&WebFont> = self.web_fonts.iter().filter(|face| face.family == family).collect();
+        let style = if italic { FaceStyle::Italic } else { FaceStyle::Normal };
+        let descriptors = |face: &&WebFont| (weight.clamp(face.weight.0, face.weight.1), face.style, 100.0);
+        let best = discovery::best_match(faces.clone(), descriptors, weight, style, 100.0)?;
+        Some(
+            faces
+                .iter()
+                .rev()
+                .filter(|face| face.weight == best.weight && face.style == best.style)
+                .map(|face| face.font)
+                .collect(),
+        )
+    }
+
+    /// Load the faces of `@font-face` rules, trying each rule's sources in
+    /// order; `fetch` gets the bytes of a `url()` source. Returns how many
+    /// faces loaded.
+    pub fn load_font_faces(&mut self, faces: &[FontFace], mut fetch: impl FnMut(&str) -> Option<Vec<u8>>) -> usize {
+        let mut loaded = 0;
+        for face in faces {
+            let font = face.sources.iter().find_map(|source| match source {
+                FontSource::Local(name) => {
+                    let local = self.index.find_local(name)?;
+                    let id = self.load_file(&local.path.clone(), local.index)?;
+                    let (font, face) = (self.fonts[id.0].font.clone(), self.fonts[id.0].face.clone());
+                    Some(LoadedFont { font, face, unicode_range: Vec::new() })
+                }
+                FontSource::Url { url, .. } => {
+                    let font = fetch(url).and_then(|data| Self::parse_font(data, 0));
+                    if font.is_none() {
+                        eprintln!("Failed to load font {} for {}", url, face.family);
+                    }
+                    font
+                }
+            });
+            if let Some(font) = font {
+                self.add_web_font(face, font);
+                loaded += 1;
+            }
+        }
+        loaded
+    }
+
+    /// Add a font file (TrueType, OpenType, WOFF or WOFF2) as the face an
+    /// `@font-face` rule describes, ahead of any installed family of the
+    /// same name
+    pub fn add_font_face(&mut self, face: &FontFace, data: Vec<u8>) -> Option<FontId> {
+        let font = Self::parse_font(data, 0)?;
+        Some(self.add_web_font(face, font))
+    }
+
+    /// Forget the faces added from `@font-face` rules, as when leaving the
+    /// page that declared them
+    pub fn clear_font_faces(&mut self) {
+        self.web_fonts.clear();
+        self.families.clear();
+        self.runs.clear();
+    }
+
+    fn add_web_font(&mut self, face: &FontFace, mut font: LoadedFont) -> FontId {
+        font.unicode_range = face.unicode_range.clone();
+        self.fonts.push(font);
+        let id = FontId(self.fonts.len() - 1);
+        self.web_fonts.push(WebFont {
+            family: face.family.to_lowercase(),
+            weight: face.weight,
+            style: face.style,
+            font: id,
+        });
+        // Families and runs resolved so far may now use the new face
+        self.families.clear();
+        self.runs.clear();
+        id
+    }
+
+    fn font_key(family: &str, weight: u16, italic: bool) -> String {
+        format!("{}-{}-{}", family, weight, italic)
+    }
+
+    /// Load face `index` of a font file, once per file and face
+    fn load_file(&mut self, path: &Path, index: u32) -> Option<FontId> {
+        let file = (path.to_path_buf(), index);
+        if let Some(id) = self.files.get(&file) {
+            return *id;
+        }
+        let id = std::fs::read(path).ok().and_then(|data| Self::parse_font(data, index)).map(|font| {
+            self.fonts.push(font);
+            FontId(self.fonts.len() - 1)
+        });
+        self.files.insert(file, id);
+        id
+    }
+
+    /// Parse face `index` of a font file, unwrapping WOFF and WOFF2
+    fn parse_font(data: Vec<u8>, index: u32) -> Option<LoadedFont> {
+        let data = if data.starts_with(b"wOF") { woff::decode(&data)? } else { data };
+        let font_bytes: &'static [u8] = Box::leak(data.into_boxed_slice());
+        let font = Font::try_from_bytes_and_index(font_bytes, index)?;
+        let face = rustybuzz::Face::from_slice(font_bytes, index)?;
+        Some(LoadedFont { font, face, unicode_range: Vec::new() })
+    }
+
+    fn covers(&self, id: FontId, c: char) -> bool {
+        let font = &self.fonts[id.0];
+        (font.unicode_range.is_empty() || font.unicode_range.iter().any(|range| range.contains(&(c as u32))))
+            && font.face.glyph_index(c).is_some()
+    }
+
+    /// The first system fallback font with a glyph for `c`, loading more of
+    /// the fallback list as needed
+    fn fallback_for(&mut self, c: char) -> Option<FontId> {
+        if let Some(&id) = self.fallbacks.iter().find(|&&id| self.covers(id, c)) {
+            return Some(id);
+        }
+        let paths = fallback_font_paths();
+        while self.fallback_paths_tried < paths.len() {
+            let path = &paths[self.fallback_paths_tried];
+            self.fallback_paths_tried += 1;
+            let Some(id) = self.load_file(Path::new(path), 0) else {
+                continue;
+            };
+            if self.fallbacks.contains(&id) {
+                continue;
+            }
+            self.fallbacks.push(id);
+            if self.covers(id, c) {
+                return Some(id);
+            }
+        }
         None
     }
 
-    /// Get font bytes from system directories
-    fn get_system_font_bytes_variant(&self, family: &str, bold: bool, italic: bool) -> Option<Vec<u8>> {
+    /// Split `text` into byte ranges that each use one font: the first of
+    /// `chain` with a glyph for the character, else the first system
+    /// fallback with one, else the primary font (which draws its missing
+    /// glyph box). Combining marks and joiners stay with the character
+    /// before them, and so does whitespace the current font has.
+    fn font_segments(&mut self, text: &str, chain: &[FontId]) -> Vec<(std::ops::Range<usize>, FontId)> {
+        let mut segments: Vec<(std::ops::Range<usize>, FontId)> = Vec::new();
+        for (index, c) in text.char_indices() {
+            let end = index + c.len_utf8();
+            if let Some((range, current)) = segments.last_mut() {
+                if shaping::continues_cluster(c) || (c.is_whitespace() && self.covers(*current, c)) {
+                    range.end = end;
+                    continue;
+                }
+            }
+            let id = match chain.iter().find(|&&id| self.covers(id, c)) {
+                Some(&id) => id,
+                None => self.fallback_for(c).unwrap_or(chain[0]),
+            };
+            match segments.last_mut() {
+                Some((range, current)) if *current == id => range.end = end,
+                _ => segments.push((index..end, id)),
+            }
+        }
+        segments
+    }
+
+    /// The file and face index of a system font
+    fn system_font_path(&self, family: &str, weight: u16, italic: bool) -> Option<(PathBuf, u32)> {
         #[cfg(target_os = "windows")]
         {
-            return self.load_windows_font_variant(family, bold, italic);
+            return self.load_windows_font_variant(family, weight >= 600, italic).map(|path| (path, 0));
         }
 
         #[cfg(target_os = "macos")]
         {
-            return self.load_macos_font(family);
+            return self.load_macos_font(family).map(|path| (path, 0));
         }
 
         #[cfg(target_os = "linux")]
         {
-            return self.load_linux_font(family);
+            self.load_linux_font(family, weight, italic)
         }
 
         #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
         {
-            let _ = (family, bold, italic);
+            let _ = (family, weight, italic);
             None
         }
     }
 
     #[cfg(target_os = "windows")]
-    fn load_windows_font_variant(&self, family: &str, bold: bool, italic: bool) -> Option<Vec<u8>> {
+    fn load_windows_font_variant(&self, family: &str, bold: bool, italic: bool) -> Option<PathBuf> {
         use std::env;
-        use std::fs;
 
         let fonts_dir = env::var("WINDIR")
             .ok()
@@ -103,14 +364,12 @@ impl FontManager {
             _ => "arial.ttf",
         };
 
-        let path = format!("{}\\{}", fonts_dir, filename);
-        fs::read(&path).ok()
+        let path = PathBuf::from(format!("{}\\{}", fonts_dir, filename));
+        path.exists().then_some(path)
     }
 
     #[cfg(target_os = "macos")]
-    fn load_macos_font(&self, family: &str) -> Option<Vec<u8>> {
-        use std::fs;
-
+    fn load_macos_font(&self, family: &str) -> Option<PathBuf> {
         let home = std::env::var("HOME").ok()?;
         let fonts_paths = vec![
             format!("{}/Library/Fonts", home),
@@ -128,80 +387,208 @@ impl FontManager {
         };
 
         for fonts_path in fonts_paths {
-            let path = format!("{}/{}", fonts_path, filename);
-            if let Ok(data) = fs::read(&path) {
-                return Some(data);
+            let path = PathBuf::from(format!("{}/{}", fonts_path, filename));
+            if path.exists() {
+                return Some(path);
             }
         }
         None
     }
 
     #[cfg(target_os = "linux")]
-    fn load_linux_font(&self, family: &str) -> Option<Vec<u8>> {
-        use std::fs;
-
-        let home_fonts = format!("{}/.local/share/fonts", std::env::var("HOME").ok()?);
-        let fonts_paths = vec![
-            "/usr/share/fonts/truetype",
-            "/usr/local/share/fonts/truetype",
-            &home_fonts,
-        ];
+    fn load_linux_font(&self, family: &str, weight: u16, italic: bool) -> Option<(PathBuf, u32)> {
+        let style = if italic { FaceStyle::Italic } else { FaceStyle::Normal };
+        let face = self.index.find(family, weight, style, 100.0)?;
+        Some((face.path.clone(), face.index))
+    }
 
-        // Build a list of font filenames to try, with fallbacks
-        let filenames = match family.to_lowercase().as_str() {
-            "times new roman" | "times" | "serif" => vec![
-                "liberation/LiberationSerif-Regular.ttf",
-                "dejavu/DejaVuSerif.ttf",
-            ],
-            "arial" | "sans-serif" | "system-ui" | "sans" => vec![
-                "liberation/LiberationSans-Regular.ttf",
-                "dejavu/DejaVuSans.ttf",
-                "ubuntu/Ubuntu-Regular.ttf",
-                "noto/NotoSans-Regular.ttf",
-            ],
-            "georgia" => vec![
-                "liberation/LiberationSerif-Regular.ttf",
-                "dejavu/DejaVuSerif.ttf",
-            ],
-            "verdana" => vec![
-                "liberation/LiberationSans-Regular.ttf",
-                "dejavu/DejaVuSans.ttf",
-            ],
-            "courier new" | "courier" | "monospace" => vec![
-                "liberation/LiberationMono-Regular.ttf",
-                "dejavu/DejaVuSansMono.ttf",
-            ],
-            // Default fallback for unknown families
-            _ => vec![
-                "liberation/LiberationSans-Regular.ttf",
-                "dejavu/DejaVuSans.ttf",
-            ],
-        };
+    /// Shape `text` at `font_size` px into positioned glyphs, each from the
+    /// first font of `font_family` that has it, or else from a system
+    /// fallback font. Runs are cached, so the layout and the painter share
+    /// one shaping of each word.
+    pub fn shape(&mut self, text: &str, font_family: &str, font_size: f32, weight: u16, italic: bool) -> Arc<GlyphRun> {
+        let key = Self::font_key(font_family, weight, italic);
+        let run_key = (key, font_size.to_bits(), text.to_string());
+        if let Some(run) = self.runs.get(&run_key) {
+            return run.clone();
+        }
 
-        for fonts_path in fonts_paths {
-            for filename in &filenames {
-                let path = format!("{}/{}", fonts_path, filename);
-                if let Ok(data) = fs::read(&path) {
-                    return Some(data);
+        let chain = self.family_chain(font_family, weight, italic);
+        let run = Arc::new(match chain.first() {
+            Some(&primary) => {
+                let mut run = shaping::shape(&self.fonts[primary.0].face, primary, "", font_size);
+                for (range, id) in self.font_segments(text, &chain) {
+                    let segment = shaping::shape(&self.fonts[id.0].face, id, &text[range.clone()], font_size);
+                    run.glyphs.extend(segment.glyphs.into_iter().map(|glyph| ShapedGlyph {
+                        cluster: glyph.cluster + range.start,
+                        x: glyph.x + run.width,
+                        ..glyph
+                    }));
+                    run.width += segment.width;
                 }
+                run
             }
+            None => shaping::estimate(text, font_size),
+        });
+        if self.runs.len() >= RUN_CACHE_LIMIT {
+            self.runs.clear();
         }
-        None
+        self.runs.insert(run_key, run.clone());
+        run
+    }
+
+    /// Measure the width of a text string as shaped with the font
+    pub fn measure_text(&mut self, text: &str, font_family: &str, font_size: f32, weight: u16, italic: bool) -> f32 {
+        self.shape(text, font_family, font_size, weight, italic).width
+    }
+}
+
+/// Font files to search, in order, for characters no font in a
+/// `font-family` list has
+fn fallback_font_paths() -> Vec<String> {
+    #[cfg(target_os = "windows")]
+    {
+        let fonts_dir = std::env::var("WINDIR").map(|wd| format!("{}\\Fonts", wd)).unwrap_or_default();
+        ["segoeui.ttf", "seguisym.ttf", "seguiemj.ttf", "msyh.ttc", "YuGothM.ttc", "malgun.ttf", "nirmala.ttf", "arialuni.ttf"]
+            .iter()
+            .map(|file| format!("{}\\{}", fonts_dir, file))
+            .collect()
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        [
+            "/System/Library/Fonts/Helvetica.ttc",
+            "/System/Library/Fonts/PingFang.ttc",
+            "/System/Library/Fonts/Hiragino Sans GB.ttc",
+            "/System/Library/Fonts/AppleSDGothicNeo.ttc",
+            "/System/Library/Fonts/Apple Symbols.ttf",
+            "/System/Library/Fonts/Supplemental/Arial Unicode.ttf",
+            "/Library/Fonts/Arial Unicode.ttf",
+        ]
+        .iter()
+        .map(|path| path.to_string())
+        .collect()
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        // Outline fonts only: rusttype cannot draw color bitmap emoji
+        let files = [
+            "truetype/dejavu/DejaVuSans.ttf",
+            "truetype/noto/NotoSans-Regular.ttf",
+            "truetype/noto/NotoSansArabic-Regular.ttf",
+            "truetype/noto/NotoSansHebrew-Regular.ttf",
+            "truetype/noto/NotoSansDevanagari-Regular.ttf",
+            "truetype/noto/NotoSansThai-Regular.ttf",
+            "opentype/noto/NotoSansCJK-Regular.ttc",
+            "truetype/wqy/wqy-microhei.ttc",
+            "truetype/droid/DroidSansFallbackFull.ttf",
+            "truetype/noto/NotoEmoji-Regular.ttf",
+            "truetype/noto/NotoSansSymbols-Regular.ttf",
+            "truetype/noto/NotoSansSymbols2-Regular.ttf",
+            "truetype/ancient-scripts/Symbola_hint.ttf",
+            "truetype/freefont/FreeSerif.ttf",
+            "truetype/unifont/unifont.ttf",
+        ];
+        let mut dirs = vec!["/usr/share/fonts".to_string(), "/usr/local/share/fonts".to_string()];
+        if let Ok(home) = std::env::var("HOME") {
+            dirs.push(format!("{}/.local/share/fonts", home));
+        }
+        dirs.iter().flat_map(|dir| files.iter().map(move |file| format!("{}/{}", dir, file))).collect()
     }
 
-    /// Measure the width of a text string using actual font metrics
-    pub fn measure_text(&mut self, text: &str, font_family: &str, font_size: f32, bold: bool, italic: bool) -> f32 {
-        if let Some(font) = self.load_font_variant(font_family, bold, italic) {
-            let scale = rusttype::Scale::uniform(font_size);
-            let mut width = 0.0_f32;
-            for c in text.chars() {
-                let glyph = font.glyph(c).scaled(scale);
-                width += glyph.h_metrics().advance_width;
+    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+    {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_glyphs_fall_back_to_other_fonts() {
+        let mut font_manager = FontManager::new();
+        let text = "ab \u{0633}\u{0644}";
+        let run = font_manager.shape(text, "serif", 16.0, 400, false);
+        let Some(&primary) = font_manager.family_chain("serif", 400, false).first() else {
+            return;
+        };
+        assert_eq!(run.glyphs[0].font, primary);
+        // Every character some font has is drawn from a font that has it
+        for glyph in &run.glyphs {
+            let c = text[glyph.cluster..].chars().next().unwrap();
+            if font_manager.fallback_for(c).is_some() || font_manager.covers(primary, c) {
+                assert!(font_manager.covers(glyph.font, c), "{:?} drawn from a font without it", c);
             }
-            width
-        } else {
-            // Fallback to estimate if font not available
-            text.len() as f32 * font_size * 0.5
         }
     }
+
+    #[test]
+    fn test_segments_keep_marks_and_spaces_together() {
+        let mut font_manager = FontManager::new();
+        let chain = font_manager.family_chain("sans-serif", 400, false);
+        if chain.is_empty() {
+            return;
+        }
+        let segments = font_manager.font_segments("e\u{301} x", &chain);
+        assert_eq!(segments, [(0.."e\u{301} x".len(), chain[0])]);
+    }
+
+    #[test]
+    fn test_family_chain_skips_repeats() {
+        let mut font_manager = FontManager::new();
+        let single = font_manager.family_chain("sans-serif", 400, false);
+        let doubled = font_manager.family_chain("sans-serif, 'sans-serif'", 400, false);
+        assert_eq!(single, doubled);
+        assert!(single.len() <= 1);
+    }
+
+    fn face(family: &str, weight: (u16, u16), unicode_range: Vec<RangeInclusive<u32>>) -> FontFace {
+        FontFace { family: family.to_string(), sources: Vec::new(), weight, style: FaceStyle::Normal, unicode_range }
+    }
+
+    #[test]
+    fn test_font_faces_split_by_unicode_range() {
+        let (Ok(sans), Ok(mono)) = (
+            std::fs::read("/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf"),
+            std::fs::read("/usr/share/fonts/truetype/dejavu/DejaVuSansMono.ttf"),
+        ) else {
+            return;
+        };
+        let mut font_manager = FontManager::new();
+        let ascii = font_manager.add_font_face(&face("Brand", (400, 400), vec![0..=0x7F]), sans).unwrap();
+        let cyrillic = font_manager.add_font_face(&face("Brand", (400, 400), vec![0x400..=0x4FF]), mono).unwrap();
+        let chain = font_manager.family_chain("Brand, serif", 400, false);
+        assert!(chain.contains(&ascii) && chain.contains(&cyrillic));
+
+        let text = "aé ж";
+        let segments = font_manager.font_segments(text, &chain);
+        let font_of = |c: char| {
+            let index = text.find(c).unwrap();
+            segments.iter().find(|(range, _)| range.contains(&index)).unwrap().1
+        };
+        assert_eq!(font_of('a'), ascii);
+        assert_ne!(font_of('é'), ascii);
+        assert_eq!(font_of('ж'), cyrillic);
+    }
+
+    #[test]
+    fn test_font_faces_match_weight_ranges() {
+        let Ok(sans) = std::fs::read("/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf") else {
+            return;
+        };
+        let mut font_manager = FontManager::new();
+        let regular = font_manager.add_font_face(&face("Brand", (300, 500), Vec::new()), sans.clone()).unwrap();
+        let bold = font_manager.add_font_face(&face("Brand", (700, 700), Vec::new()), sans).unwrap();
+        assert_eq!(font_manager.family_chain("Brand", 450, false), [regular]);
+        assert_eq!(font_manager.family_chain("Brand", 600, false), [bold]);
+        assert_eq!(font_manager.family_chain("Brand", 100, false), [regular]);
+
+        // Installed families of the same name are hidden until the faces go
+        font_manager.clear_font_faces();
+        assert!(!font_manager.family_chain("Brand", 400, false).contains(&regular));
+    }
 }
@@ -0,0 +1,208 @@
+// engine/src/font/shaping.rs
+// Text shaping with rustybuzz (a port of HarfBuzz)
+//
+// - A run of text is shaped once per font and size into positioned glyphs,
+//   applying the font's kerning, ligatures and mark positioning (GSUB/GPOS,
+//   or the legacy `kern` table)
+// - Script and direction are guessed from the text, so Arabic joins and
+//   right-to-left runs come out in visual order
+// - Glyph positions are in px at the font size, in em units scaled by
+//   `size / units_per_em`, with y growing downwards; the painter scales them
+//   by the device pixel ratio, so painted text is exactly as wide as the
+//   line breaker measured it
+
+use rustybuzz::{Face, UnicodeBuffer};
+
+use super::FontId;
+
+/// One glyph of a shaped run
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShapedGlyph {
+    /// The font the glyph comes from; runs can mix fonts when some
+    /// characters fall back to others
+    pub font: FontId,
+    /// Glyph index in the font, not a character
+    pub id: u16,
+    /// Byte offset in the text of the first character this glyph comes from.
+    /// A ligature covers several characters; a mark shares its base's
+    /// cluster.
+    pub cluster: usize,
+    /// Pen position to draw at, relative to the run's origin on the baseline
+    pub x: f32,
+    pub y: f32,
+    /// How far the pen moves after this glyph
+    pub advance: f32,
+}
+
+/// Positioned glyphs for a run of text at one size
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GlyphRun {
+    pub glyphs: Vec<ShapedGlyph>,
+    /// Total advance
+    pub width: f32,
+    /// Distance from the baseline to the top of the primary font's em box
+    pub ascent: f32,
+    /// Distance from the baseline down to the bottom of the em box
+    pub descent: f32,
+}
+
+impl GlyphRun {
+    /// Byte length of the longest prefix of `text` (the text this run was
+    /// shaped from) whose glyphs fit in `width`, but at least its first
+    /// cluster. Prefixes end between clusters, so ligatures and marks are
+    /// never split.
+    pub fn fit(&self, text: &str, width: f32) -> usize {
+        if self.glyphs.is_empty() {
+            // Estimated runs have the same width for every character
+            let per_char = self.width / text.chars().count().max(1) as f32;
+            let count = ((width / per_char).floor() as usize).max(1);
+            return text.char_indices().nth(count).map_or(text.len(), |(i, _)| i);
+        }
+        // Right-to-left runs list their glyphs in visual order, so gather
+        // the advances by cluster and walk them in text order
+        let mut clusters: Vec<(usize, f32)> = Vec::new();
+        for glyph in &self.glyphs {
+            match clusters.iter_mut().find(|(cluster, _)| *cluster == glyph.cluster) {
+                Some((_, advance)) => *advance += glyph.advance,
+                None => clusters.push((glyph.cluster, glyph.advance)),
+            }
+        }
+        clusters.sort_by_key(|(cluster, _)| *cluster);
+        let mut used = 0.0;
+        for (index, (cluster, advance)) in clusters.into_iter().enumerate() {
+            used += advance;
+            if used > width && index > 0 {
+                return cluster;
+            }
+        }
+        text.len()
+    }
+}
+
+/// Shape `text` with `face`, the font `font`, at `size` px
+pub fn shape(face: &Face<'_>, font: FontId, text: &str, size: f32) -> GlyphRun {
+    let scale = size / face.units_per_em() as f32;
+    let ascent = face.ascender() as f32 * scale;
+    let descent = -(face.descender() as f32) * scale;
+    if text.is_empty() {
+        return GlyphRun { glyphs: Vec::new(), width: 0.0, ascent, descent };
+    }
+
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str(text);
+    buffer.guess_segment_properties();
+    let shaped = rustybuzz::shape(face, &[], buffer);
+
+    let mut pen = 0.0;
+    let glyphs = shaped
+        .glyph_infos()
+        .iter()
+        .zip(shaped.glyph_positions())
+        .map(|(info, position)| {
+            let advance = position.x_advance as f32 * scale;
+            let glyph = ShapedGlyph {
+                font,
+                id: info.glyph_id as u16,
+                cluster: info.cluster as usize,
+                x: pen + position.x_offset as f32 * scale,
+                // Font units grow upwards
+                y: -(position.y_offset as f32) * scale,
+                advance,
+            };
+            pen += advance;
+            glyph
+        })
+        .collect();
+    GlyphRun { glyphs, width: pen, ascent, descent }
+}
+
+/// A run for when no font could be loaded: no glyphs, with a rough width so
+/// layout still has something to break lines with
+pub fn estimate(text: &str, size: f32) -> GlyphRun {
+    GlyphRun {
+        glyphs: Vec::new(),
+        width: text.chars().count() as f32 * size * 0.5,
+        ascent: size * 0.8,
+        descent: size * 0.2,
+    }
+}
+
+/// Whether `c` belongs with the character before it whatever font that
+/// uses: combining marks, joiners, variation selectors, emoji modifiers and
+/// tag characters
+pub fn continues_cluster(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0300}'..='\u{036F}'
+            | '\u{0483}'..='\u{0489}'
+            | '\u{0591}'..='\u{05BD}'
+            | '\u{064B}'..='\u{065F}'
+            | '\u{0670}'
+            | '\u{1AB0}'..='\u{1AFF}'
+            | '\u{1DC0}'..='\u{1DFF}'
+            | '\u{200C}'..='\u{200D}'
+            | '\u{20D0}'..='\u{20FF}'
+            | '\u{FE00}'..='\u{FE0F}'
+            | '\u{FE20}'..='\u{FE2F}'
+            | '\u{1F3FB}'..='\u{1F3FF}'
+            | '\u{E0020}'..='\u{E007F}'
+            | '\u{E0100}'..='\u{E01EF}'
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font::FontManager;
+
+    /// Shape with the system sans-serif font, or None on machines without one
+    fn shaped(font_manager: &mut FontManager, text: &str) -> Option<std::sync::Arc<GlyphRun>> {
+        let run = font_manager.shape(text, "sans-serif", 20.0, 400, false);
+        (!run.glyphs.is_empty()).then_some(run)
+    }
+
+    #[test]
+    fn test_kerning_tightens_pairs() {
+        let mut font_manager = FontManager::new();
+        let (Some(pair), Some(a), Some(v)) =
+            (shaped(&mut font_manager, "AV"), shaped(&mut font_manager, "A"), shaped(&mut font_manager, "V"))
+        else {
+            return;
+        };
+        assert!(pair.width < a.width + v.width, "{} vs {}", pair.width, a.width + v.width);
+        assert_eq!(pair.glyphs[1].x, pair.glyphs[0].advance);
+    }
+
+    #[test]
+    fn test_marks_stay_with_their_base() {
+        let mut font_manager = FontManager::new();
+        let Some(run) = shaped(&mut font_manager, "e\u{301}x") else {
+            return;
+        };
+        // The accent never ends up alone at the start of the next line
+        assert_eq!(run.fit("e\u{301}x", 0.0), "e\u{301}".len());
+        assert!(run.glyphs.iter().filter(|glyph| glyph.cluster == 0).count() >= 1);
+    }
+
+    #[test]
+    fn test_arabic_joins_right_to_left() {
+        let mut font_manager = FontManager::new();
+        let (Some(word), Some(letter)) = (shaped(&mut font_manager, "سلام"), shaped(&mut font_manager, "س")) else {
+            return;
+        };
+        // Glyphs come in visual order, the first letter last, in its
+        // initial (joining) form rather than the isolated one
+        let first_letter = word.glyphs.last().unwrap();
+        assert_eq!(first_letter.cluster, 0);
+        assert_ne!(first_letter.id, letter.glyphs[0].id);
+    }
+
+    #[test]
+    fn test_fit_estimated_runs_by_character() {
+        let run = estimate("abcd", 10.0);
+        assert_eq!(run.width, 20.0);
+        assert_eq!(run.fit("abcd", 12.0), 2);
+        assert_eq!(run.fit("abcd", 1.0), 1);
+        assert_eq!(run.fit("abcd", 100.0), 4);
+    }
+}
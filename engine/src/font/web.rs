@@ -0,0 +1,247 @@
+// engine/src/font/web.rs
+// Web fonts declared by `@font-face` rules
+//
+// - A rule names a family and lists sources to try in order: `url()`s, with
+//   an optional `format()` hint, and `local()` names of installed faces
+// - The weight, style and unicode-range descriptors say which requests the
+//   face serves; a family can be split over several faces that differ only
+//   in unicode-range, and characters use whichever of them covers them
+// - Sources with formats or technologies this engine cannot draw are
+//   skipped without being fetched
+
+use std::ops::RangeInclusive;
+
+use super::FaceStyle;
+use crate::parser::css::Declaration;
+
+/// The largest code point
+const MAX_CODE_POINT: u32 = 0x10FFFF;
+
+/// `format()` hints of sources that can be loaded
+const SUPPORTED_FORMATS: &[&str] = &["truetype", "opentype", "woff", "woff2"];
+
+/// `tech()` requirements that the rasterizer meets (a variable font draws
+/// its default instance)
+const SUPPORTED_TECHNOLOGIES: &[&str] = &["features-opentype", "variations"];
+
+/// One `@font-face` rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontFace {
+    pub family: String,
+    pub sources: Vec<FontSource>,
+    /// Lowest and highest `font-weight` the face serves; variable fonts give
+    /// a range
+    pub weight: (u16, u16),
+    pub style: FaceStyle,
+    /// Code points the face serves; empty for all of them
+    pub unicode_range: Vec<RangeInclusive<u32>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FontSource {
+    /// A font file to fetch, with its `format()` hint in lowercase
+    Url { url: String, format: Option<String> },
+    /// An installed face, by full or PostScript name
+    Local(String),
+}
+
+impl FontFace {
+    /// The face described by the descriptors of an `@font-face` block;
+    /// None without a family or a usable source
+    pub fn parse(declarations: &[Declaration]) -> Option<FontFace> {
+        let mut face = FontFace {
+            family: String::new(),
+            sources: Vec::new(),
+            weight: (400, 400),
+            style: FaceStyle::Normal,
+            unicode_range: Vec::new(),
+        };
+        for declaration in declarations {
+            let value = declaration.value.trim();
+            match declaration.property.as_str() {
+                "font-family" => face.family = unquote(value).to_string(),
+                "src" => face.sources = split_commas(value).into_iter().filter_map(parse_source).collect(),
+                "font-weight" => face.weight = parse_weight(value).unwrap_or(face.weight),
+                "font-style" => face.style = parse_style(value).unwrap_or(face.style),
+                "unicode-range" => face.unicode_range = split_commas(value).into_iter().filter_map(parse_range).collect(),
+                _ => {}
+            }
+        }
+        (!face.family.is_empty() && !face.sources.is_empty()).then_some(face)
+    }
+
+    /// Whether the face serves `c`
+    pub fn covers(&self, c: char) -> bool {
+        self.unicode_range.is_empty() || self.unicode_range.iter().any(|range| range.contains(&(c as u32)))
+    }
+}
+
+/// `value` without surrounding quotes
+fn unquote(value: &str) -> &str {
+    let value = value.trim();
+    for quote in ['"', '\''] {
+        if let Some(inner) = value.strip_prefix(quote).and_then(|v| v.strip_suffix(quote)) {
+            return inner;
+        }
+    }
+    value
+}
+
+/// Split a descriptor value at commas outside parentheses and quotes
+fn split_commas(value: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut start = 0;
+    for (index, c) in value.char_indices() {
+        match (c, quote) {
+            (_, Some(q)) if c == q => quote = None,
+            (_, Some(_)) => {}
+            ('"' | '\'', None) => quote = Some(c),
+            ('(', None) => depth += 1,
+            (')', None) => depth = depth.saturating_sub(1),
+            (',', None) if depth == 0 => {
+                parts.push(value[start..index].trim());
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(value[start..].trim());
+    parts.retain(|part| !part.is_empty());
+    parts
+}
+
+/// The argument of the function `name(...)` in `text`, and the text after it
+fn function_argument<'a>(text: &'a str, name: &str) -> Option<(&'a str, &'a str)> {
+    let start = text.to_ascii_lowercase().find(&format!("{}(", name))? + name.len() + 1;
+    let end = start + text[start..].find(')')?;
+    Some((text[start..end].trim(), &text[end + 1..]))
+}
+
+/// One entry of a `src` list
+fn parse_source(text: &str) -> Option<FontSource> {
+    let lower = text.to_ascii_lowercase();
+    if lower.starts_with("local(") {
+        let (name, _) = function_argument(text, "local")?;
+        return Some(FontSource::Local(unquote(name).to_string()));
+    }
+    if !lower.starts_with("url(") {
+        return None;
+    }
+    let (url, hints) = function_argument(text, "url")?;
+    let url = unquote(url).to_string();
+    let format = match function_argument(hints, "format") {
+        Some((formats, _)) => {
+            // Older sheets list several formats; the first known one counts
+            let format = split_commas(formats)
+                .into_iter()
+                .map(|format| unquote(format).to_ascii_lowercase())
+                .find(|format| SUPPORTED_FORMATS.contains(&format.as_str()))?;
+            Some(format)
+        }
+        None => None,
+    };
+    if let Some((technologies, _)) = function_argument(hints, "tech") {
+        let supported = split_commas(technologies)
+            .into_iter()
+            .all(|tech| SUPPORTED_TECHNOLOGIES.contains(&tech.to_ascii_lowercase().as_str()));
+        if !supported {
+            return None;
+        }
+    }
+    Some(FontSource::Url { url, format })
+}
+
+/// `normal`, `bold`, a number, or a range of two
+fn parse_weight(value: &str) -> Option<(u16, u16)> {
+    let weight = |word: &str| match word.to_ascii_lowercase().as_str() {
+        "normal" => Some(400),
+        "bold" => Some(700),
+        number => number.parse::<f32>().ok().filter(|w| (1.0..=1000.0).contains(w)).map(|w| w.round() as u16),
+    };
+    let words: Vec<&str> = value.split_whitespace().collect();
+    match words[..] {
+        [single] => weight(single).map(|w| (w, w)),
+        [low, high] => {
+            let (low, high) = (weight(low)?, weight(high)?);
+            Some((low.min(high), low.max(high)))
+        }
+        _ => None,
+    }
+}
+
+fn parse_style(value: &str) -> Option<FaceStyle> {
+    match value.split_whitespace().next()?.to_ascii_lowercase().as_str() {
+        "normal" => Some(FaceStyle::Normal),
+        "italic" => Some(FaceStyle::Italic),
+        "oblique" => Some(FaceStyle::Oblique),
+        _ => None,
+    }
+}
+
+/// `U+26`, `U+0-7F` or `U+4??`
+fn parse_range(text: &str) -> Option<RangeInclusive<u32>> {
+    let digits = text.strip_prefix("U+").or_else(|| text.strip_prefix("u+"))?;
+    let hex = |digits: &str| u32::from_str_radix(digits, 16).ok().filter(|_| (1..=6).contains(&digits.len()));
+    let (start, end) = if digits.contains('?') {
+        (hex(&digits.replace('?', "0"))?, hex(&digits.replace('?', "F"))?)
+    } else {
+        match digits.split_once('-') {
+            Some((start, end)) => (hex(start)?, hex(end)?),
+            None => (hex(digits)?, hex(digits)?),
+        }
+    };
+    (start <= end && start <= MAX_CODE_POINT).then_some(start..=end.min(MAX_CODE_POINT))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::css::{CssItem, CssParser, CssTokenizer};
+
+    fn parse_face(css: &str) -> Option<FontFace> {
+        match CssParser::new(CssTokenizer::new(css).tokenize()).parse().into_iter().next() {
+            Some(CssItem::AtRule { declarations, .. }) => FontFace::parse(&declarations),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_parses_descriptors() {
+        let face = parse_face(
+            r#"@font-face {
+                font-family: "Open Sans";
+                src: local("Open Sans Bold"), url(fonts/open-sans.woff2) format("woff2"),
+                     url('fonts/open-sans.eot') format("embedded-opentype"), url("fonts/open-sans.ttf");
+                font-weight: 600 800;
+                font-style: italic;
+                unicode-range: U+0000-00FF, U+0131, U+4??;
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(face.family, "Open Sans");
+        assert_eq!(
+            face.sources,
+            vec![
+                FontSource::Local("Open Sans Bold".to_string()),
+                FontSource::Url { url: "fonts/open-sans.woff2".to_string(), format: Some("woff2".to_string()) },
+                FontSource::Url { url: "fonts/open-sans.ttf".to_string(), format: None },
+            ]
+        );
+        assert_eq!(face.weight, (600, 800));
+        assert_eq!(face.style, FaceStyle::Italic);
+        assert_eq!(face.unicode_range, vec![0..=0xFF, 0x131..=0x131, 0x400..=0x4FF]);
+        assert!(face.covers('é') && face.covers('ı') && face.covers('ж'));
+        assert!(!face.covers('Ω'));
+    }
+
+    #[test]
+    fn test_needs_family_and_source() {
+        assert!(parse_face("@font-face { src: url(a.ttf) }").is_none());
+        assert!(parse_face("@font-face { font-family: A; src: url(a.svg) format('svg') }").is_none());
+        let face = parse_face("@font-face { font-family: A; src: url(a.ttf); font-weight: bold }").unwrap();
+        assert_eq!(face.weight, (700, 700));
+        assert!(face.covers('\u{1F600}'));
+    }
+}
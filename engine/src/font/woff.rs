@@ -0,0 +1,677 @@
+// engine/src/font/woff.rs
+// WOFF and WOFF2 decoding
+//
+// - Web fonts come as bare TrueType/OpenType data, as WOFF (each table
+//   zlib-compressed on its own) or as WOFF2 (all tables in one Brotli
+//   stream); both unwrap to the sfnt data the font parsers read
+// - WOFF2 fonts usually store glyf and loca transformed: glyph outlines are
+//   split into separate streams of contour counts, point counts, flags and
+//   packed coordinate triplets. They are rebuilt into TrueType glyphs as in
+//   section 5 of the WOFF2 specification, with loca recomputed from them
+// - A transformed hmtx table leaves out left side bearings that equal the
+//   glyphs' xMin; they are restored from the rebuilt glyf
+// - Font collections in WOFF2 are not supported
+
+use std::io::Read;
+
+/// First four bytes of TrueType, OpenType (CFF), Apple TrueType and
+/// collection files
+const SFNT_TAGS: [&[u8]; 4] = [b"\x00\x01\x00\x00", b"OTTO", b"true", b"ttcf"];
+
+/// WOFF2 tags by their index in a table directory entry's flags
+const KNOWN_TAGS: [&[u8; 4]; 63] = [
+    b"cmap", b"head", b"hhea", b"hmtx", b"maxp", b"name", b"OS/2", b"post", b"cvt ", b"fpgm", b"glyf", b"loca", b"prep",
+    b"CFF ", b"VORG", b"EBDT", b"EBLC", b"gasp", b"hdmx", b"kern", b"LTSH", b"PCLT", b"VDMX", b"vhea", b"vmtx", b"BASE",
+    b"GDEF", b"GPOS", b"GSUB", b"EBSC", b"JSTF", b"MATH", b"CBDT", b"CBLC", b"COLR", b"CPAL", b"SVG ", b"sbix", b"acnt",
+    b"avar", b"bdat", b"bloc", b"bsln", b"cvar", b"fdsc", b"feat", b"fmtx", b"fvar", b"gvar", b"hsty", b"just", b"lcar",
+    b"mort", b"morx", b"opbd", b"prop", b"trak", b"Zapf", b"Silf", b"Glat", b"Gloc", b"Feat", b"Sill",
+];
+
+/// TrueType simple glyph flags
+const ON_CURVE_POINT: u8 = 0x01;
+const X_SHORT_VECTOR: u8 = 0x02;
+const Y_SHORT_VECTOR: u8 = 0x04;
+const X_IS_SAME_OR_POSITIVE: u8 = 0x10;
+const Y_IS_SAME_OR_POSITIVE: u8 = 0x20;
+const OVERLAP_SIMPLE: u8 = 0x40;
+
+/// TrueType composite glyph flags
+const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+const WE_HAVE_A_SCALE: u16 = 0x0008;
+const MORE_COMPONENTS: u16 = 0x0020;
+const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+const WE_HAVE_INSTRUCTIONS: u16 = 0x0100;
+
+/// sfnt data for a font file in any of the formats; None if it is none of
+/// them or is malformed
+pub fn decode(data: &[u8]) -> Option<Vec<u8>> {
+    match data.get(..4)? {
+        b"wOFF" => decode_woff(data),
+        b"wOF2" => decode_woff2(data),
+        tag if SFNT_TAGS.contains(&tag) => Some(data.to_vec()),
+        _ => None,
+    }
+}
+
+/// Big-endian reads from a byte slice
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Reader { data, pos: 0 }
+    }
+
+    fn bytes(&mut self, count: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos.checked_add(count)?)?;
+        self.pos += count;
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.bytes(2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn i16(&mut self) -> Option<i16> {
+        self.u16().map(|v| v as i16)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.bytes(4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    /// WOFF2 `UIntBase128`: up to five bytes of seven bits, high bit set on
+    /// all but the last
+    fn base128(&mut self) -> Option<u32> {
+        let mut value: u32 = 0;
+        for index in 0..5 {
+            let byte = self.u8()?;
+            if index == 0 && byte == 0x80 {
+                return None;
+            }
+            if value & 0xFE00_0000 != 0 {
+                return None;
+            }
+            value = (value << 7) | (byte & 0x7F) as u32;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    /// WOFF2 `255UInt16`: one byte for small values, with escape codes for
+    /// larger ones
+    fn u255(&mut self) -> Option<u16> {
+        match self.u8()? {
+            253 => self.u16(),
+            254 => Some(self.u8()? as u16 + 253 * 2),
+            255 => Some(self.u8()? as u16 + 253),
+            code => Some(code as u16),
+        }
+    }
+}
+
+/// A table of the sfnt being rebuilt
+struct Table {
+    tag: [u8; 4],
+    data: Vec<u8>,
+}
+
+/// Read all of `reader`, failing if it yields anything but `length` bytes
+fn read_exactly(reader: impl Read, length: usize) -> Option<Vec<u8>> {
+    let mut data = Vec::with_capacity(length);
+    // One byte over, so an oversized stream is noticed without reading it all
+    reader.take(length as u64 + 1).read_to_end(&mut data).ok()?;
+    (data.len() == length).then_some(data)
+}
+
+fn decode_woff(data: &[u8]) -> Option<Vec<u8>> {
+    let mut header = Reader::new(data);
+    header.bytes(4)?; // signature
+    let flavor = header.u32()?;
+    header.u32()?; // length
+    let num_tables = header.u16()?;
+    header.bytes(30)?; // the rest of the header
+
+    let mut tables = Vec::with_capacity(num_tables as usize);
+    for _ in 0..num_tables {
+        let tag = header.bytes(4)?.try_into().ok()?;
+        let offset = header.u32()? as usize;
+        let compressed_length = header.u32()? as usize;
+        let length = header.u32()? as usize;
+        header.u32()?; // checksum
+        let stored = data.get(offset..offset.checked_add(compressed_length)?)?;
+        let table = if compressed_length < length {
+            read_exactly(flate2::read::ZlibDecoder::new(stored), length)?
+        } else if compressed_length == length {
+            stored.to_vec()
+        } else {
+            return None;
+        };
+        tables.push(Table { tag, data: table });
+    }
+    Some(build_sfnt(flavor, tables))
+}
+
+/// A WOFF2 table directory entry
+struct Woff2Entry {
+    tag: [u8; 4],
+    /// Stored transformed rather than as the sfnt table
+    transformed: bool,
+    /// Bytes in the decompressed stream
+    stored_length: usize,
+}
+
+fn decode_woff2(data: &[u8]) -> Option<Vec<u8>> {
+    let mut header = Reader::new(data);
+    header.bytes(4)?; // signature
+    let flavor = header.u32()?;
+    header.u32()?; // length
+    let num_tables = header.u16()?;
+    header.u16()?; // reserved
+    header.u32()?; // totalSfntSize
+    let compressed_length = header.u32()? as usize;
+    header.bytes(24)?; // version, metadata and private data
+    if flavor == u32::from_be_bytes(*b"ttcf") {
+        return None;
+    }
+
+    let mut entries = Vec::with_capacity(num_tables as usize);
+    for _ in 0..num_tables {
+        let flags = header.u8()?;
+        let tag = match flags & 0x3F {
+            63 => header.bytes(4)?.try_into().ok()?,
+            known => *KNOWN_TAGS[known as usize],
+        };
+        let version = flags >> 6;
+        let length = header.base128()? as usize;
+        // glyf and loca are transformed unless the version is 3; other
+        // tables are transformed unless it is 0
+        let transformed = match &tag {
+            b"glyf" | b"loca" => version != 3,
+            _ => version != 0,
+        };
+        let stored_length = if transformed { header.base128()? as usize } else { length };
+        if transformed && !matches!(&tag, b"glyf" | b"loca" | b"hmtx") {
+            return None;
+        }
+        entries.push(Woff2Entry { tag, transformed, stored_length });
+    }
+
+    let total: usize = entries.iter().try_fold(0usize, |total, entry| total.checked_add(entry.stored_length))?;
+    let compressed = data.get(header.pos..header.pos.checked_add(compressed_length)?)?;
+    let stream = read_exactly(brotli_decompressor::Decompressor::new(compressed, 4096), total)?;
+
+    let mut stored = Vec::with_capacity(entries.len());
+    let mut offset = 0;
+    for entry in &entries {
+        stored.push(&stream[offset..offset + entry.stored_length]);
+        offset += entry.stored_length;
+    }
+    let find = |tag: &[u8; 4]| entries.iter().position(|entry| &entry.tag == tag);
+
+    // glyf first: loca and hmtx are computed from it
+    let mut rebuilt_glyf = None;
+    if let Some(glyf) = find(b"glyf").filter(|&i| entries[i].transformed) {
+        rebuilt_glyf = Some(rebuild_glyf(stored[glyf])?);
+    }
+
+    let mut tables = Vec::with_capacity(entries.len());
+    for (entry, data) in entries.iter().zip(&stored) {
+        let data = match (&entry.tag, entry.transformed) {
+            (_, false) => data.to_vec(),
+            (b"glyf", true) => rebuilt_glyf.as_ref()?.glyf.clone(),
+            (b"loca", true) => rebuilt_glyf.as_ref()?.loca.clone(),
+            (b"hmtx", true) => {
+                let glyf = rebuilt_glyf.as_ref()?;
+                let mut hhea = Reader::new(stored[find(b"hhea")?]);
+                hhea.bytes(34)?;
+                rebuild_hmtx(data, hhea.u16()? as usize, &glyf.x_mins)?
+            }
+            _ => return None,
+        };
+        tables.push(Table { tag: entry.tag, data });
+    }
+    Some(build_sfnt(flavor, tables))
+}
+
+/// glyf and loca rebuilt from a transformed glyf table
+struct RebuiltGlyf {
+    glyf: Vec<u8>,
+    loca: Vec<u8>,
+    /// Each glyph's xMin, for hmtx
+    x_mins: Vec<i16>,
+}
+
+fn rebuild_glyf(data: &[u8]) -> Option<RebuiltGlyf> {
+    let mut header = Reader::new(data);
+    header.u16()?; // reserved
+    let option_flags = header.u16()?;
+    let num_glyphs = header.u16()? as usize;
+    let long_offsets = header.u16()? != 0;
+    let mut streams = Vec::with_capacity(7);
+    let mut offset: usize = 36;
+    for _ in 0..7 {
+        let length = header.u32()? as usize;
+        streams.push(Reader::new(data.get(offset..offset.checked_add(length)?)?));
+        offset += length;
+    }
+    let [mut contour_counts, mut point_counts, mut flags, mut glyphs, mut composites, mut bboxes, mut instructions] =
+        <[Reader; 7]>::try_from(streams).ok()?;
+    let bbox_bitmap = bboxes.bytes(num_glyphs.div_ceil(32) * 4)?;
+    let overlap_bitmap = if option_flags & 1 != 0 { Some(data.get(offset..offset + num_glyphs.div_ceil(8))?) } else { None };
+    let bit = |bitmap: &[u8], index: usize| bitmap[index / 8] & (0x80 >> (index % 8)) != 0;
+
+    let mut rebuilt = RebuiltGlyf { glyf: Vec::new(), loca: Vec::new(), x_mins: Vec::with_capacity(num_glyphs) };
+    let mut offsets = Vec::with_capacity(num_glyphs + 1);
+    for index in 0..num_glyphs {
+        offsets.push(rebuilt.glyf.len());
+        let explicit_bbox = bit(bbox_bitmap, index);
+        let glyph = &mut rebuilt.glyf;
+        match contour_counts.i16()? {
+            0 => {
+                if explicit_bbox {
+                    return None;
+                }
+                rebuilt.x_mins.push(0);
+                continue;
+            }
+            -1 => {
+                // Composite glyphs always have their bounding box stored
+                if !explicit_bbox {
+                    return None;
+                }
+                let bbox = bboxes.bytes(8)?;
+                let start = composites.pos;
+                let mut has_instructions = false;
+                loop {
+                    let component_flags = composites.u16()?;
+                    let arguments = if component_flags & ARG_1_AND_2_ARE_WORDS != 0 { 4 } else { 2 };
+                    let transform = if component_flags & WE_HAVE_A_SCALE != 0 {
+                        2
+                    } else if component_flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+                        4
+                    } else if component_flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+                        8
+                    } else {
+                        0
+                    };
+                    composites.bytes(2 + arguments + transform)?; // glyph index, arguments, transform
+                    has_instructions |= component_flags & WE_HAVE_INSTRUCTIONS != 0;
+                    if component_flags & MORE_COMPONENTS == 0 {
+                        break;
+                    }
+                }
+                glyph.extend_from_slice(&(-1i16).to_be_bytes());
+                glyph.extend_from_slice(bbox);
+                glyph.extend_from_slice(&composites.data[start..composites.pos]);
+                if has_instructions {
+                    let length = glyphs.u255()?;
+                    glyph.extend_from_slice(&length.to_be_bytes());
+                    glyph.extend_from_slice(instructions.bytes(length as usize)?);
+                }
+                rebuilt.x_mins.push(i16::from_be_bytes([bbox[0], bbox[1]]));
+            }
+            contours if contours > 0 => {
+                let mut end_points = Vec::with_capacity(contours as usize);
+                let mut total: usize = 0;
+                for _ in 0..contours {
+                    total += point_counts.u255()? as usize;
+                    end_points.push(u16::try_from(total.checked_sub(1)?).ok()?);
+                }
+                let point_flags = flags.bytes(total)?;
+                let mut points = Vec::with_capacity(total);
+                let (mut x, mut y) = (0i32, 0i32);
+                for &flag in point_flags {
+                    let (dx, dy) = decode_triplet(flag & 0x7F, &mut glyphs)?;
+                    x += dx;
+                    y += dy;
+                    points.push((x, y, flag & 0x80 == 0));
+                }
+                let instruction_length = glyphs.u255()?;
+                let glyph_instructions = instructions.bytes(instruction_length as usize)?;
+
+                let bbox = if explicit_bbox {
+                    let mut bbox = Reader::new(bboxes.bytes(8)?);
+                    [bbox.i16()?, bbox.i16()?, bbox.i16()?, bbox.i16()?]
+                } else {
+                    let (xs, ys) = (points.iter().map(|p| p.0), points.iter().map(|p| p.1));
+                    let (x_min, x_max) = (xs.clone().min().unwrap_or(0), xs.max().unwrap_or(0));
+                    let (y_min, y_max) = (ys.clone().min().unwrap_or(0), ys.max().unwrap_or(0));
+                    [x_min as i16, y_min as i16, x_max as i16, y_max as i16]
+                };
+                let overlap = overlap_bitmap.is_some_and(|bitmap| bit(bitmap, index));
+
+                glyph.extend_from_slice(&contours.to_be_bytes());
+                for value in bbox {
+                    glyph.extend_from_slice(&value.to_be_bytes());
+                }
+                for end_point in end_points {
+                    glyph.extend_from_slice(&end_point.to_be_bytes());
+                }
+                glyph.extend_from_slice(&instruction_length.to_be_bytes());
+                glyph.extend_from_slice(glyph_instructions);
+                encode_points(&points, overlap, glyph);
+                rebuilt.x_mins.push(bbox[0]);
+            }
+            _ => return None,
+        }
+        // Glyphs start on four-byte boundaries
+        glyph.resize(glyph.len().next_multiple_of(4), 0);
+    }
+    offsets.push(rebuilt.glyf.len());
+
+    for offset in offsets {
+        if long_offsets {
+            rebuilt.loca.extend_from_slice(&u32::try_from(offset).ok()?.to_be_bytes());
+        } else {
+            rebuilt.loca.extend_from_slice(&u16::try_from(offset / 2).ok()?.to_be_bytes());
+        }
+    }
+    Some(rebuilt)
+}
+
+/// The coordinate change a WOFF2 point flag and its triplet bytes encode
+fn decode_triplet(flag: u8, data: &mut Reader) -> Option<(i32, i32)> {
+    let flag = flag as i32;
+    let with_sign = |flag: i32, value: i32| if flag & 1 != 0 { value } else { -value };
+    Some(match flag {
+        0..=9 => (0, with_sign(flag, ((flag & 14) << 7) + data.u8()? as i32)),
+        10..=19 => (with_sign(flag, (((flag - 10) & 14) << 7) + data.u8()? as i32), 0),
+        20..=83 => {
+            let (b0, b1) = (flag - 20, data.u8()? as i32);
+            (with_sign(flag, 1 + (b0 & 0x30) + (b1 >> 4)), with_sign(flag >> 1, 1 + ((b0 & 0x0C) << 2) + (b1 & 0x0F)))
+        }
+        84..=119 => {
+            let b0 = flag - 84;
+            let (b1, b2) = (data.u8()? as i32, data.u8()? as i32);
+            (with_sign(flag, 1 + ((b0 / 12) << 8) + b1), with_sign(flag >> 1, 1 + (((b0 % 12) >> 2) << 8) + b2))
+        }
+        120..=123 => {
+            let (b1, b2, b3) = (data.u8()? as i32, data.u8()? as i32, data.u8()? as i32);
+            (with_sign(flag, (b1 << 4) + (b2 >> 4)), with_sign(flag >> 1, ((b2 & 0x0F) << 8) + b3))
+        }
+        _ => {
+            let (b1, b2, b3, b4) = (data.u8()? as i32, data.u8()? as i32, data.u8()? as i32, data.u8()? as i32);
+            (with_sign(flag, (b1 << 8) + b2), with_sign(flag >> 1, (b3 << 8) + b4))
+        }
+    })
+}
+
+/// Append the flags and coordinates of a TrueType simple glyph
+fn encode_points(points: &[(i32, i32, bool)], overlap: bool, glyph: &mut Vec<u8>) {
+    let mut flags = Vec::with_capacity(points.len());
+    let (mut xs, mut ys) = (Vec::new(), Vec::new());
+    let (mut last_x, mut last_y) = (0, 0);
+    for (index, &(x, y, on_curve)) in points.iter().enumerate() {
+        let mut flag = if on_curve { ON_CURVE_POINT } else { 0 };
+        if index == 0 && overlap {
+            flag |= OVERLAP_SIMPLE;
+        }
+        for (delta, coordinates, short, same_or_positive) in [
+            (x - last_x, &mut xs, X_SHORT_VECTOR, X_IS_SAME_OR_POSITIVE),
+            (y - last_y, &mut ys, Y_SHORT_VECTOR, Y_IS_SAME_OR_POSITIVE),
+        ] {
+            if delta == 0 {
+                flag |= same_or_positive;
+            } else if delta.abs() < 256 {
+                flag |= short;
+                if delta > 0 {
+                    flag |= same_or_positive;
+                }
+                coordinates.push(delta.unsigned_abs() as u8);
+            } else {
+                coordinates.extend_from_slice(&(delta as i16).to_be_bytes());
+            }
+        }
+        flags.push(flag);
+        (last_x, last_y) = (x, y);
+    }
+    glyph.extend_from_slice(&flags);
+    glyph.extend_from_slice(&xs);
+    glyph.extend_from_slice(&ys);
+}
+
+/// hmtx from its transformed form, with left side bearings the transform
+/// left out taken from the glyphs' xMin
+fn rebuild_hmtx(data: &[u8], num_metrics: usize, x_mins: &[i16]) -> Option<Vec<u8>> {
+    let mut reader = Reader::new(data);
+    let flags = reader.u8()?;
+    let num_glyphs = x_mins.len();
+    let advances = (0..num_metrics).map(|_| reader.u16()).collect::<Option<Vec<u16>>>()?;
+    let mut bearings = |range: std::ops::Range<usize>, omitted: bool| -> Option<Vec<i16>> {
+        if omitted {
+            Some(x_mins.get(range)?.to_vec())
+        } else {
+            range.map(|_| reader.i16()).collect()
+        }
+    };
+    let proportional = bearings(0..num_metrics, flags & 1 != 0)?;
+    let monospaced = bearings(num_metrics..num_glyphs.max(num_metrics), flags & 2 != 0)?;
+
+    let mut hmtx = Vec::with_capacity(num_metrics * 4 + monospaced.len() * 2);
+    for (advance, bearing) in advances.iter().zip(&proportional) {
+        hmtx.extend_from_slice(&advance.to_be_bytes());
+        hmtx.extend_from_slice(&bearing.to_be_bytes());
+    }
+    for bearing in monospaced {
+        hmtx.extend_from_slice(&bearing.to_be_bytes());
+    }
+    Some(hmtx)
+}
+
+/// An sfnt file of `tables`, sorted by tag as the format requires
+fn build_sfnt(flavor: u32, mut tables: Vec<Table>) -> Vec<u8> {
+    tables.sort_by_key(|table| table.tag);
+    let count = tables.len() as u16;
+    let entry_selector = if count == 0 { 0 } else { 15 - count.leading_zeros() as u16 };
+    let search_range = (1u16 << entry_selector) * 16;
+
+    let mut sfnt = Vec::new();
+    sfnt.extend_from_slice(&flavor.to_be_bytes());
+    for value in [count, search_range, count * 16 - search_range, entry_selector] {
+        sfnt.extend_from_slice(&value.to_be_bytes());
+    }
+    let mut offset = 12 + 16 * tables.len();
+    for table in &tables {
+        sfnt.extend_from_slice(&table.tag);
+        sfnt.extend_from_slice(&checksum(&table.data).to_be_bytes());
+        sfnt.extend_from_slice(&(offset as u32).to_be_bytes());
+        sfnt.extend_from_slice(&(table.data.len() as u32).to_be_bytes());
+        offset += table.data.len().next_multiple_of(4);
+    }
+    for table in &tables {
+        sfnt.extend_from_slice(&table.data);
+        sfnt.resize(sfnt.len().next_multiple_of(4), 0);
+    }
+    sfnt
+}
+
+/// Sum of a table's big-endian 32-bit words, zero-padded
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustybuzz::ttf_parser;
+    use std::io::Write;
+
+    const DEJAVU_SANS: &str = "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf";
+
+    /// The tables of an sfnt file
+    fn tables_of(sfnt: &[u8]) -> Vec<Table> {
+        let face = ttf_parser::RawFace::parse(sfnt, 0).unwrap();
+        face.table_records
+            .into_iter()
+            .map(|record| {
+                let range = record.offset as usize..(record.offset + record.length) as usize;
+                Table { tag: record.tag.to_bytes(), data: sfnt[range].to_vec() }
+            })
+            .collect()
+    }
+
+    fn woff_of(sfnt: &[u8]) -> Vec<u8> {
+        let tables = tables_of(sfnt);
+        let mut header = Vec::new();
+        header.extend_from_slice(b"wOFF");
+        header.extend_from_slice(&sfnt[..4]);
+        header.extend_from_slice(&[0; 4]); // length, unchecked
+        header.extend_from_slice(&(tables.len() as u16).to_be_bytes());
+        header.extend_from_slice(&[0; 30]);
+        let mut data = Vec::new();
+        let mut offset = 44 + 20 * tables.len();
+        for table in &tables {
+            let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(&table.data).unwrap();
+            let compressed = encoder.finish().unwrap();
+            // Tables that do not shrink are stored as they are
+            let stored = if compressed.len() < table.data.len() { compressed } else { table.data.clone() };
+            header.extend_from_slice(&table.tag);
+            for value in [offset, stored.len(), table.data.len(), 0] {
+                header.extend_from_slice(&(value as u32).to_be_bytes());
+            }
+            offset += stored.len();
+            data.extend_from_slice(&stored);
+        }
+        header.extend_from_slice(&data);
+        header
+    }
+
+    /// WOFF2 with every table stored as it is (transform version 3 for
+    /// glyf and loca, 0 for the rest)
+    fn woff2_of(sfnt: &[u8]) -> Vec<u8> {
+        let tables = tables_of(sfnt);
+        let mut directory = Vec::new();
+        let mut stream = Vec::new();
+        for table in &tables {
+            let transform = if matches!(&table.tag, b"glyf" | b"loca") { 3 << 6 } else { 0 };
+            match KNOWN_TAGS.iter().position(|tag| **tag == table.tag) {
+                Some(index) => directory.push(transform | index as u8),
+                None => {
+                    directory.push(transform | 63);
+                    directory.extend_from_slice(&table.tag);
+                }
+            }
+            directory.extend_from_slice(&base128(table.data.len() as u32));
+            stream.extend_from_slice(&table.data);
+        }
+        let mut compressed = Vec::new();
+        brotli::BrotliCompress(&mut &stream[..], &mut compressed, &Default::default()).unwrap();
+
+        let mut woff2 = Vec::new();
+        woff2.extend_from_slice(b"wOF2");
+        woff2.extend_from_slice(&sfnt[..4]);
+        woff2.extend_from_slice(&[0; 4]);
+        woff2.extend_from_slice(&(tables.len() as u16).to_be_bytes());
+        woff2.extend_from_slice(&[0; 6]);
+        woff2.extend_from_slice(&(compressed.len() as u32).to_be_bytes());
+        woff2.extend_from_slice(&[0; 24]);
+        woff2.extend_from_slice(&directory);
+        woff2.extend_from_slice(&compressed);
+        woff2
+    }
+
+    fn base128(mut value: u32) -> Vec<u8> {
+        let mut bytes = vec![(value & 0x7F) as u8];
+        value >>= 7;
+        while value > 0 {
+            bytes.insert(0, (value & 0x7F) as u8 | 0x80);
+            value >>= 7;
+        }
+        bytes
+    }
+
+    /// Whether two fonts map and measure some text the same
+    fn assert_same_font(a: &[u8], b: &[u8]) {
+        let (a, b) = (ttf_parser::Face::parse(a, 0).unwrap(), ttf_parser::Face::parse(b, 0).unwrap());
+        assert_eq!(a.number_of_glyphs(), b.number_of_glyphs());
+        for c in "Wafer ﬁ ж".chars() {
+            let glyph = a.glyph_index(c);
+            assert_eq!(glyph, b.glyph_index(c));
+            let glyph = glyph.unwrap();
+            assert_eq!(a.glyph_hor_advance(glyph), b.glyph_hor_advance(glyph));
+            assert_eq!(a.glyph_bounding_box(glyph), b.glyph_bounding_box(glyph));
+        }
+    }
+
+    #[test]
+    fn test_sfnt_passes_through() {
+        assert_eq!(decode(b"OTTO\0\0\0\0"), Some(b"OTTO\0\0\0\0".to_vec()));
+        assert_eq!(decode(b"<!DOCTYPE html>"), None);
+        assert_eq!(decode(b"wOF2\0\0"), None);
+    }
+
+    #[test]
+    fn test_decodes_woff() {
+        let Ok(sfnt) = std::fs::read(DEJAVU_SANS) else {
+            return;
+        };
+        assert_same_font(&sfnt, &decode(&woff_of(&sfnt)).unwrap());
+    }
+
+    #[test]
+    fn test_decodes_woff2() {
+        let Ok(sfnt) = std::fs::read(DEJAVU_SANS) else {
+            return;
+        };
+        assert_same_font(&sfnt, &decode(&woff2_of(&sfnt)).unwrap());
+    }
+
+    #[test]
+    fn test_rebuilds_transformed_glyf() {
+        // Glyph 0 is empty, glyph 1 a contour of three points with one
+        // instruction, glyph 2 a composite of glyph 1 moved by (5, -5)
+        let contour_counts = [0, 0, 0, 1, 0xFF, 0xFF];
+        let point_counts = [3];
+        // (0, +50) on-curve; (+300, -10) off-curve, in three bytes;
+        // (-20, 0) on-curve
+        let flags = [1, 0x80 | 121, 10];
+        let glyphs = [50, 0x12, 0xC0, 10, 20, 1];
+        let composites = [0x00, 0x01, 0x00, 0x01, 0x00, 0x05, 0xFF, 0xFB];
+        // Only glyph 2 has its bounding box stored
+        let bboxes = [0x20, 0, 0, 0, 0x00, 0x05, 0xFF, 0xFB, 0x01, 0x31, 0x00, 0x37];
+        let instructions = [0xB0];
+        let streams: [&[u8]; 7] = [&contour_counts, &point_counts, &flags, &glyphs, &composites, &bboxes, &instructions];
+        let mut table = vec![0, 0, 0, 0, 0, 3, 0, 0];
+        for stream in streams {
+            table.extend_from_slice(&(stream.len() as u32).to_be_bytes());
+        }
+        for stream in streams {
+            table.extend_from_slice(stream);
+        }
+
+        let rebuilt = rebuild_glyf(&table).unwrap();
+        let simple: &[u8] = &[
+            0, 1, // contours
+            0, 0, 0, 40, 0x01, 0x2C, 0, 50, // bounding box from the points
+            0, 2, // last point
+            0, 1, 0xB0, // instructions
+            0x35, 0x04, 0x23, // flags
+            0x01, 0x2C, 20, // x: 0, +300, -20
+            50, 10, // y: +50, -10, 0
+            0, // padding
+        ];
+        let composite: &[u8] = &[
+            0xFF, 0xFF, 0x00, 0x05, 0xFF, 0xFB, 0x01, 0x31, 0x00, 0x37, // -1 contours, bounding box
+            0x00, 0x01, 0x00, 0x01, 0x00, 0x05, 0xFF, 0xFB, // component
+            0, 0, // padding
+        ];
+        assert_eq!(rebuilt.glyf, [simple, composite].concat());
+        assert_eq!(rebuilt.loca, [0, 0, 0, 0, 0, 12, 0, 22]);
+        assert_eq!(rebuilt.x_mins, [0, 0, 5]);
+    }
+}
@@ -0,0 +1,846 @@
+// engine/src/layout/flex.rs
+// Flex layout (CSS Flexible Box Layout Level 1)
+//
+// - Items are the element children plus runs of non-whitespace text (anonymous items),
+//   reordered by `order`
+// - `flex-basis`, `flex-grow` and `flex-shrink` (or the `flex` shorthand) size items along the
+//   main axis; items that hit their min/max size are frozen and the rest re-flexed (section 9.7)
+// - `flex-wrap` breaks items into lines; `gap` separates items and lines
+// - Main-axis auto margins, then `justify-content`, distribute free space on each line
+// - `align-items`/`align-self` place items within their line and `align-content` places lines;
+//   `stretch` lays an item out again at the line's cross size
+// - `row-reverse`, `column-reverse` and `wrap-reverse` mirror positions along their axis
+
+use std::sync::Arc;
+
+use crate::dom::{Dom, NodeId, NodeType};
+use crate::font::FontManager;
+use crate::style::{ComputedStyle, ComputedStyles, CssValue, Display, Length, Overflow, Viewport};
+
+use super::float::FloatContext;
+use super::{layout_log, translate, LayoutBox, LayoutEngine, UsedSize, PROBE_WIDTH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Row,
+    RowReverse,
+    Column,
+    ColumnReverse,
+}
+
+impl Direction {
+    fn parse(value: Option<&str>) -> Self {
+        match keyword(value).as_deref() {
+            Some("row-reverse") => Direction::RowReverse,
+            Some("column") => Direction::Column,
+            Some("column-reverse") => Direction::ColumnReverse,
+            _ => Direction::Row,
+        }
+    }
+
+    fn is_row(self) -> bool {
+        matches!(self, Direction::Row | Direction::RowReverse)
+    }
+
+    fn is_reverse(self) -> bool {
+        matches!(self, Direction::RowReverse | Direction::ColumnReverse)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FlexWrap {
+    NoWrap,
+    Wrap,
+    WrapReverse,
+}
+
+impl FlexWrap {
+    fn parse(value: Option<&str>) -> Self {
+        match keyword(value).as_deref() {
+            Some("wrap") => FlexWrap::Wrap,
+            Some("wrap-reverse") => FlexWrap::WrapReverse,
+            _ => FlexWrap::NoWrap,
+        }
+    }
+}
+
+/// How free space is spread along an axis (`justify-content`, `align-content`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Distribution {
+    Start,
+    End,
+    Center,
+    SpaceBetween,
+    SpaceAround,
+    SpaceEvenly,
+    Stretch,
+}
+
+impl Distribution {
+    /// Parse a content-distribution value; `normal` and unknown values give `default`
+    pub(super) fn parse(value: Option<&str>, default: Self) -> Self {
+        match keyword(value).as_deref() {
+            Some("flex-start" | "start" | "left" | "top") => Distribution::Start,
+            Some("flex-end" | "end" | "right" | "bottom") => Distribution::End,
+            Some("center") => Distribution::Center,
+            Some("space-between") => Distribution::SpaceBetween,
+            Some("space-around") => Distribution::SpaceAround,
+            Some("space-evenly") => Distribution::SpaceEvenly,
+            Some("stretch") => Distribution::Stretch,
+            _ => default,
+        }
+    }
+
+    /// Offset of the first of `count` subjects and the extra space between
+    /// neighbours. Negative free space falls back to start or center.
+    pub(super) fn spacing(self, free: f32, count: usize) -> (f32, f32) {
+        let count = count.max(1) as f32;
+        match self {
+            Distribution::Start | Distribution::Stretch => (0.0, 0.0),
+            Distribution::End => (free, 0.0),
+            Distribution::Center => (free / 2.0, 0.0),
+            Distribution::SpaceBetween if free > 0.0 && count > 1.0 => (0.0, free / (count - 1.0)),
+            Distribution::SpaceBetween => (0.0, 0.0),
+            Distribution::SpaceAround if free > 0.0 => (free / count / 2.0, free / count),
+            Distribution::SpaceEvenly if free > 0.0 => (free / (count + 1.0), free / (count + 1.0)),
+            Distribution::SpaceAround | Distribution::SpaceEvenly => (free / 2.0, 0.0),
+        }
+    }
+}
+
+/// Placement of an item in the cross axis of its line (`align-items`, `align-self`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Alignment {
+    Start,
+    End,
+    Center,
+    Stretch,
+}
+
+impl Alignment {
+    /// Parse a self-alignment value. Baselines are not tracked, so baseline
+    /// alignment behaves like `start`. Returns None for `auto`.
+    pub(super) fn parse(value: Option<&str>) -> Option<Self> {
+        match keyword(value).as_deref() {
+            Some("flex-start" | "start" | "self-start" | "left" | "baseline" | "first baseline" | "last baseline") => {
+                Some(Alignment::Start)
+            }
+            Some("flex-end" | "end" | "self-end" | "right") => Some(Alignment::End),
+            Some("center") => Some(Alignment::Center),
+            Some("stretch" | "normal") => Some(Alignment::Stretch),
+            _ => None,
+        }
+    }
+
+    /// Offset of a box of size `size` within `space`
+    pub(super) fn offset(self, space: f32, size: f32) -> f32 {
+        match self {
+            Alignment::Start | Alignment::Stretch => 0.0,
+            Alignment::End => space - size,
+            Alignment::Center => (space - size) / 2.0,
+        }
+    }
+}
+
+fn keyword(value: Option<&str>) -> Option<String> {
+    value.map(|v| v.split_whitespace().collect::<Vec<_>>().join(" ").to_ascii_lowercase())
+}
+
+/// `row-gap` and `column-gap`
+pub(super) fn gaps(style: &ComputedStyle) -> (Option<Length>, Option<Length>) {
+    (style.length("row-gap"), style.length("column-gap"))
+}
+
+/// Resolve a height-like length, whose percentages need a definite basis
+pub(super) fn definite_height(length: &Length, basis: Option<f32>) -> Option<f32> {
+    match basis {
+        Some(basis) => Some(length.resolve(basis)),
+        None => length.definite(),
+    }
+}
+
+/// `flex-grow`, `flex-shrink` and `flex-basis` (None for `auto`)
+fn flex_factors(style: &ComputedStyle) -> (f32, f32, Option<Length>) {
+    let (mut grow, mut shrink, mut basis) = (0.0, 1.0, None);
+    if let Some(n) = style.get("flex-grow").and_then(CssValue::parse).and_then(|v| v.to_number()) {
+        grow = n;
+    }
+    if let Some(n) = style.get("flex-shrink").and_then(CssValue::parse).and_then(|v| v.to_number()) {
+        shrink = n;
+    }
+    if let Some(value) = style.get("flex-basis").and_then(CssValue::parse) {
+        basis = if value.is_keyword("auto") || value.is_keyword("content") {
+            None
+        } else {
+            style.to_length(&value)
+        };
+    }
+    (grow.max(0.0), shrink.max(0.0), basis)
+}
+
+/// What a flex or grid item lays out
+pub(super) enum ItemContent {
+    Element(NodeId),
+    /// A run of text children wrapped in an anonymous item
+    Text(Vec<NodeId>),
+}
+
+/// The items of a flex or grid container: its element children and runs of
+/// non-whitespace text, sorted by `order`
+pub(super) fn child_items(
+    dom: &Dom,
+    styles: &ComputedStyles,
+    node_id: NodeId,
+    exclude_tags: &[&str],
+) -> Vec<ItemContent> {
+    let mut contents: Vec<(i32, ItemContent)> = Vec::new();
+    let mut text_run = Vec::new();
+    for &child_id in &dom.nodes[node_id].children {
+        match &dom.nodes[child_id].node_type {
+            NodeType::Text(_) => text_run.push(child_id),
+            NodeType::Element(el) => {
+                let child_style = styles.get(child_id);
+                if exclude_tags.contains(&el.tag_name.as_str())
+                    || child_style.display == Display::None
+                    || child_style.position.is_out_of_flow()
+                {
+                    continue;
+                }
+                flush_text_run(dom, &mut text_run, &mut contents);
+                let order = child_style
+                    .get("order")
+                    .and_then(|v| v.trim().parse().ok())
+                    .unwrap_or(0);
+                contents.push((order, ItemContent::Element(child_id)));
+            }
+        }
+    }
+    flush_text_run(dom, &mut text_run, &mut contents);
+    // Stable, so equal `order` keeps document order
+    contents.sort_by_key(|(order, _)| *order);
+    contents.into_iter().map(|(_, content)| content).collect()
+}
+
+struct FlexItem {
+    content: ItemContent,
+    grow: f32,
+    shrink: f32,
+    /// Margins in main-start, main-end, cross-start, cross-end order
+    margins: [f32; 4],
+    auto_margins: [bool; 4],
+    align: Alignment,
+    /// The cross size is `auto`, so the item may be stretched
+    auto_cross: bool,
+    /// Border-box sizes along the main axis
+    base: f32,
+    min: f32,
+    max: f32,
+    main: f32,
+    frozen: bool,
+    /// Border-box width given to column items before they are stretched
+    column_width: f32,
+    cross: f32,
+    layout_box: Option<LayoutBox>,
+}
+
+impl FlexItem {
+    fn hypothetical(&self) -> f32 {
+        self.base.clamp(self.min, self.max)
+    }
+
+    fn main_margins(&self) -> f32 {
+        self.margins[0] + self.margins[1]
+    }
+
+    fn cross_margins(&self) -> f32 {
+        self.margins[2] + self.margins[3]
+    }
+}
+
+/// Shared inputs for laying out one container's items
+struct FlexContainer<'a> {
+    dom: &'a Dom,
+    styles: &'a ComputedStyles,
+    node_id: NodeId,
+    exclude_tags: &'a [&'a str],
+    viewport: &'a Viewport,
+    direction: Direction,
+    /// Content-box width, the percentage basis for item margins and padding
+    width: f32,
+    /// Content-box height when definite
+    height: Option<f32>,
+}
+
+/// One flex line: a range of items and its cross size
+struct FlexLine {
+    start: usize,
+    end: usize,
+    cross: f32,
+}
+
+impl LayoutEngine {
+    /// Lay out the children of a flex container whose content box starts at
+    /// (`content_x`, `content_y`). Returns the item boxes, in `order`, and the
+    /// height they occupy.
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn layout_flex_items(
+        &self,
+        dom: &Dom,
+        styles: &ComputedStyles,
+        node_id: NodeId,
+        content_x: f32,
+        content_y: f32,
+        content_width: f32,
+        content_height: Option<f32>,
+        exclude_tags: &[&str],
+        viewport: &Viewport,
+        font_manager: &mut FontManager,
+    ) -> (Vec<LayoutBox>, f32) {
+        let style = styles.get(node_id).clone();
+        let direction = Direction::parse(style.get("flex-direction"));
+        let wrap = FlexWrap::parse(style.get("flex-wrap"));
+
+        let container = FlexContainer {
+            dom, styles, node_id, exclude_tags, viewport,
+            direction,
+            width: content_width,
+            height: content_height,
+        };
+        self.layout_flex_items_with(&container, &style, wrap, content_x, content_y, font_manager)
+    }
+
+    fn layout_flex_items_with(
+        &self,
+        container: &FlexContainer,
+        style: &ComputedStyle,
+        wrap: FlexWrap,
+        content_x: f32,
+        content_y: f32,
+        font_manager: &mut FontManager,
+    ) -> (Vec<LayoutBox>, f32) {
+        let is_row = container.direction.is_row();
+        let justify = Distribution::parse(style.get("justify-content"), Distribution::Start);
+        let align_items = Alignment::parse(style.get("align-items")).unwrap_or(Alignment::Stretch);
+        let align_content = Distribution::parse(style.get("align-content"), Distribution::Stretch);
+
+        let (row_gap, column_gap) = gaps(style);
+        let row_gap = row_gap.map_or(0.0, |gap| definite_height(&gap, container.height).unwrap_or(0.0));
+        let column_gap = column_gap.map_or(0.0, |gap| gap.resolve(container.width));
+        let (main_gap, cross_gap) = if is_row { (column_gap, row_gap) } else { (row_gap, column_gap) };
+
+        // Under a max-content probe (see `intrinsic_widths`) there is no free
+        // space to distribute, only content to measure
+        let available_main = if is_row {
+            (container.width < PROBE_WIDTH / 2.0).then_some(container.width)
+        } else {
+            container.height
+        };
+        let available_cross = if is_row { container.height } else { Some(container.width) };
+
+        layout_log(&format!(
+            "layout_flex: {:?} {:?} main={:?} cross={:?}",
+            container.direction, wrap, available_main, available_cross
+        ));
+
+        // Step 1: Collect items and their flex base sizes
+        let mut items = self.collect_flex_items(container, align_items, wrap, font_manager);
+        if items.is_empty() {
+            return (Vec::new(), 0.0);
+        }
+
+        // Step 2: Break items into lines
+        let mut lines = Vec::new();
+        let mut start = 0;
+        let mut used = 0.0;
+        for (index, item) in items.iter().enumerate() {
+            let outer = item.hypothetical() + item.main_margins();
+            if let (Some(available), true) = (available_main, wrap != FlexWrap::NoWrap && index > start) {
+                if used + main_gap + outer > available {
+                    lines.push(FlexLine { start, end: index, cross: 0.0 });
+                    start = index;
+                    used = outer;
+                    continue;
+                }
+            }
+            used += if index > start { main_gap + outer } else { outer };
+        }
+        lines.push(FlexLine { start, end: items.len(), cross: 0.0 });
+
+        // Step 3: Resolve flexible lengths and each line's main extent
+        let mut main_extent = available_main.unwrap_or(0.0);
+        for line in &lines {
+            let line_items = &mut items[line.start..line.end];
+            let gaps = main_gap * (line_items.len() - 1) as f32;
+            let hypothetical: f32 = line_items.iter().map(|item| item.hypothetical() + item.main_margins()).sum();
+            let available = available_main.unwrap_or(hypothetical + gaps);
+            resolve_flexible_lengths(line_items, available - gaps);
+            main_extent = main_extent.max(available);
+        }
+
+        // Step 4: Hypothetical cross sizes, from laying each item out at its main size
+        for item in &mut items {
+            let layout_box = if is_row {
+                self.layout_flex_item(container, item, Some(item.main), None, font_manager)
+            } else {
+                self.layout_flex_item(container, item, Some(item.column_width), Some(item.main), font_manager)
+            };
+            item.cross = if is_row { layout_box.dimensions.height } else { layout_box.dimensions.width };
+            item.layout_box = Some(layout_box);
+        }
+
+        // Step 5: Line cross sizes; a single-line container's line fills it
+        for line in &mut lines {
+            line.cross = items[line.start..line.end]
+                .iter()
+                .map(|item| item.cross + item.cross_margins())
+                .fold(0.0, f32::max);
+        }
+        if let (FlexWrap::NoWrap, Some(cross)) = (wrap, available_cross) {
+            lines[0].cross = cross;
+        }
+
+        // Step 6: align-content places the lines in a multi-line container
+        let line_gaps = cross_gap * (lines.len() - 1) as f32;
+        let lines_cross: f32 = lines.iter().map(|line| line.cross).sum::<f32>() + line_gaps;
+        let (mut line_offset, line_between) = match (wrap, available_cross) {
+            (FlexWrap::NoWrap, _) | (_, None) => (0.0, 0.0),
+            (_, Some(cross)) => {
+                let free = cross - lines_cross;
+                if align_content == Distribution::Stretch && free > 0.0 {
+                    let share = free / lines.len() as f32;
+                    for line in &mut lines {
+                        line.cross += share;
+                    }
+                    (0.0, 0.0)
+                } else {
+                    align_content.spacing(free, lines.len())
+                }
+            }
+        };
+        let cross_extent = match wrap {
+            FlexWrap::NoWrap => lines[0].cross,
+            _ => available_cross.unwrap_or(lines_cross),
+        };
+
+        // Step 7: Stretch items and position them within their lines
+        let mut boxes = Vec::with_capacity(items.len());
+        for line in &lines {
+            let line_items = &mut items[line.start..line.end];
+            let gaps = main_gap * (line_items.len() - 1) as f32;
+            let used: f32 = line_items.iter().map(|item| item.main + item.main_margins()).sum();
+            let available = available_main.unwrap_or(used + gaps);
+            let mut free = available - used - gaps;
+
+            // Auto margins absorb positive free space before justify-content
+            let auto_count = line_items
+                .iter()
+                .map(|item| item.auto_margins[..2].iter().filter(|&&auto| auto).count())
+                .sum::<usize>();
+            if auto_count > 0 && free > 0.0 {
+                let share = free / auto_count as f32;
+                for item in line_items.iter_mut() {
+                    for side in 0..2 {
+                        if item.auto_margins[side] {
+                            item.margins[side] = share;
+                        }
+                    }
+                }
+                free = 0.0;
+            }
+            let (offset, between) = justify.spacing(free, line_items.len());
+
+            let mut main_pos = offset;
+            for item in line_items.iter_mut() {
+                if item.align == Alignment::Stretch
+                    && item.auto_cross
+                    && !item.auto_margins[2]
+                    && !item.auto_margins[3]
+                {
+                    let target = (line.cross - item.cross_margins()).max(0.0);
+                    if (target - item.cross).abs() > 0.01 {
+                        let layout_box = if is_row {
+                            self.layout_flex_item(container, item, Some(item.main), Some(target), font_manager)
+                        } else {
+                            self.layout_flex_item(container, item, Some(target), Some(item.main), font_manager)
+                        };
+                        item.cross = target;
+                        item.layout_box = Some(layout_box);
+                    }
+                }
+
+                let cross_free = line.cross - item.cross - item.cross_margins();
+                let cross_pos = match item.auto_margins[2..] {
+                    [true, true] if cross_free > 0.0 => item.margins[2] + cross_free / 2.0,
+                    [true, false] if cross_free > 0.0 => item.margins[2] + cross_free,
+                    [false, true] => item.margins[2],
+                    _ => item.margins[2] + item.align.offset(line.cross - item.cross_margins(), item.cross),
+                };
+
+                main_pos += item.margins[0];
+                let mut main = main_pos;
+                let mut cross = line_offset + cross_pos;
+                if container.direction.is_reverse() {
+                    main = main_extent - main - item.main;
+                }
+                if wrap == FlexWrap::WrapReverse {
+                    cross = cross_extent - cross - item.cross;
+                }
+                main_pos += item.main + item.margins[1] + main_gap + between;
+
+                let (x, y) = if is_row { (main, cross) } else { (cross, main) };
+                let mut layout_box = item.layout_box.take().expect("flex item was laid out");
+                let (dx, dy) = (content_x + x - layout_box.dimensions.x, content_y + y - layout_box.dimensions.y);
+                translate(&mut layout_box, dx, dy);
+                boxes.push(layout_box);
+            }
+            line_offset += line.cross + cross_gap + line_between;
+        }
+
+        let height = if is_row { cross_extent } else { main_extent };
+        (boxes, height)
+    }
+
+    /// Build the items of a flex container, sorted by `order`, with their
+    /// flex base sizes and min/max main sizes
+    fn collect_flex_items(
+        &self,
+        container: &FlexContainer,
+        align_items: Alignment,
+        wrap: FlexWrap,
+        font_manager: &mut FontManager,
+    ) -> Vec<FlexItem> {
+        let is_row = container.direction.is_row();
+        let contents = child_items(container.dom, container.styles, container.node_id, container.exclude_tags);
+
+        let mut items = Vec::with_capacity(contents.len());
+        for content in contents {
+            let style = match &content {
+                ItemContent::Element(id) => container.styles.get(*id).clone(),
+                ItemContent::Text(_) => Arc::default(),
+            };
+            let (grow, shrink, basis) = flex_factors(&style);
+            let margin = style.margin.resolve(container.width);
+            // Padding plus border, which sizes add to the content box
+            let pb = style.padding_border(container.width);
+            let (t, r, b, l) = style.margin_auto;
+            let (margins, auto_margins, main_padding, cross_padding) = if is_row {
+                ([margin.left, margin.right, margin.top, margin.bottom], [l, r, t, b],
+                 pb.left + pb.right, pb.top + pb.bottom)
+            } else {
+                ([margin.top, margin.bottom, margin.left, margin.right], [t, b, l, r],
+                 pb.top + pb.bottom, pb.left + pb.right)
+            };
+            let align = Alignment::parse(style.get("align-self")).unwrap_or(align_items);
+
+            let width = style.width.as_ref().map(|w| w.resolve(container.width));
+            let height = style.height.as_ref().and_then(|h| definite_height(h, container.height));
+            let (specified_main, specified_cross) = if is_row {
+                (width.map(|w| w + main_padding), height.map(|h| h + cross_padding))
+            } else {
+                (height.map(|h| h + main_padding), width.map(|w| w + cross_padding))
+            };
+
+            let mut item = FlexItem {
+                content,
+                grow,
+                shrink,
+                margins: margins.map(|m| if m.is_finite() { m } else { 0.0 }),
+                auto_margins,
+                align,
+                auto_cross: specified_cross.is_none(),
+                base: 0.0,
+                min: 0.0,
+                max: f32::INFINITY,
+                main: 0.0,
+                frozen: false,
+                column_width: 0.0,
+                cross: 0.0,
+                layout_box: None,
+            };
+
+            // Column items need a width before their content height is known
+            let available_cross = (container.width - item.cross_margins()).max(0.0);
+            let (min_content, max_content) = self.flex_item_intrinsic_widths(container, &item, font_manager);
+            if !is_row {
+                item.column_width = specified_cross.unwrap_or_else(|| {
+                    if align == Alignment::Stretch && wrap == FlexWrap::NoWrap {
+                        available_cross
+                    } else {
+                        max_content.min(available_cross).max(min_content)
+                    }
+                });
+            }
+            let content_size = if is_row {
+                max_content
+            } else {
+                let probe = self.layout_flex_item(container, &item, Some(item.column_width), None, font_manager);
+                probe.dimensions.height
+            };
+
+            let basis = basis.and_then(|basis| if is_row {
+                Some(basis.resolve(container.width))
+            } else {
+                definite_height(&basis, container.height)
+            });
+            item.base = basis.map(|b| b + main_padding).or(specified_main).unwrap_or(content_size);
+
+            let (min_property, max_property) = if is_row { ("min-width", "max-width") } else { ("min-height", "max-height") };
+            let resolve = |length: Length| if is_row {
+                Some(length.resolve(container.width))
+            } else {
+                definite_height(&length, container.height)
+            };
+            item.max = style
+                .length(max_property)
+                .and_then(resolve)
+                .map_or(f32::INFINITY, |max| max + main_padding);
+            item.min = match style.length(min_property).and_then(resolve) {
+                Some(min) => min + main_padding,
+                // Automatic minimum size: the content size, unless the item is a scroll container
+                None if style.overflow_x != Overflow::Visible || style.overflow_y != Overflow::Visible => 0.0,
+                None => {
+                    let content_min = if is_row { min_content } else { content_size };
+                    specified_main.map_or(content_min, |s| s.min(content_min)).min(item.max)
+                }
+            };
+            items.push(item);
+        }
+        items
+    }
+
+    /// Border-box min-content and max-content widths of an item
+    fn flex_item_intrinsic_widths(
+        &self,
+        container: &FlexContainer,
+        item: &FlexItem,
+        font_manager: &mut FontManager,
+    ) -> (f32, f32) {
+        match &item.content {
+            ItemContent::Element(id) => self.intrinsic_widths(
+                container.dom, container.styles, *id, container.width, container.viewport, font_manager,
+            ),
+            ItemContent::Text(nodes) => {
+                let probe = self.layout_inline_line(
+                    container.dom, container.styles, nodes,
+                    0.0, 0.0, PROBE_WIDTH,
+                    &FloatContext::default(), container.exclude_tags, font_manager,
+                );
+                self.probe_intrinsic_widths(&probe, container.width, font_manager)
+            }
+        }
+    }
+
+    /// Lay out an item with its border box at the origin, at a border-box
+    /// `width` and optionally a fixed border-box `height`
+    fn layout_flex_item(
+        &self,
+        container: &FlexContainer,
+        item: &FlexItem,
+        width: Option<f32>,
+        height: Option<f32>,
+        font_manager: &mut FontManager,
+    ) -> LayoutBox {
+        let width = width.unwrap_or(container.width);
+        match &item.content {
+            ItemContent::Element(id) => self.layout_block_sized(
+                container.dom, container.styles, *id,
+                0.0, 0.0, container.width,
+                container.exclude_tags, container.viewport, font_manager,
+                UsedSize { width: Some(width), height },
+                None,
+            ),
+            ItemContent::Text(nodes) => {
+                let mut line = self.layout_inline_line(
+                    container.dom, container.styles, nodes,
+                    0.0, 0.0, width,
+                    &FloatContext::default(), container.exclude_tags, font_manager,
+                );
+                line.dimensions.width = width;
+                if let Some(height) = height {
+                    line.dimensions.height = height;
+                }
+                line
+            }
+        }
+    }
+}
+
+/// Turn a run of text children into an anonymous item unless it is all whitespace
+fn flush_text_run(dom: &Dom, run: &mut Vec<NodeId>, contents: &mut Vec<(i32, ItemContent)>) {
+    let has_text = run.iter().any(|&id| match &dom.nodes[id].node_type {
+        NodeType::Text(text) => !text.trim().is_empty(),
+        _ => false,
+    });
+    if has_text {
+        contents.push((0, ItemContent::Text(std::mem::take(run))));
+    }
+    run.clear();
+}
+
+/// Resolve the main sizes of one line's items to fill `available` (CSS Flexbox section 9.7)
+fn resolve_flexible_lengths(items: &mut [FlexItem], available: f32) {
+    let outer_hypothetical: f32 = items.iter().map(|item| item.hypothetical() + item.main_margins()).sum();
+    let growing = outer_hypothetical < available;
+    let factor = |item: &FlexItem| if growing { item.grow } else { item.shrink };
+
+    // Size inflexible items
+    for item in items.iter_mut() {
+        item.frozen = factor(item) == 0.0
+            || (growing && item.base > item.hypothetical())
+            || (!growing && item.base < item.hypothetical());
+        item.main = if item.frozen { item.hypothetical() } else { item.base };
+    }
+
+    let remaining = |items: &[FlexItem]| {
+        available
+            - items
+                .iter()
+                .map(|item| item.main_margins() + if item.frozen { item.main } else { item.base })
+                .sum::<f32>()
+    };
+    let initial_free = remaining(items);
+
+    while items.iter().any(|item| !item.frozen) {
+        let mut free = remaining(items);
+        let factors: f32 = items.iter().filter(|item| !item.frozen).map(factor).sum();
+        if factors < 1.0 && (initial_free * factors).abs() < free.abs() {
+            free = initial_free * factors;
+        }
+
+        // Distribute free space in proportion to the flex factors
+        if growing {
+            for item in items.iter_mut().filter(|item| !item.frozen) {
+                item.main = item.base + if factors > 0.0 { free * item.grow / factors } else { 0.0 };
+            }
+        } else {
+            let scaled: f32 = items.iter().filter(|item| !item.frozen).map(|item| item.shrink * item.base).sum();
+            for item in items.iter_mut().filter(|item| !item.frozen) {
+                let ratio = if scaled > 0.0 { item.shrink * item.base / scaled } else { 0.0 };
+                item.main = item.base + free * ratio;
+            }
+        }
+
+        // Fix min/max violations, then freeze the items on the violated side
+        let mut total_violation = 0.0;
+        let mut violations = Vec::with_capacity(items.len());
+        for item in items.iter_mut() {
+            if item.frozen {
+                violations.push(0.0);
+                continue;
+            }
+            let clamped = item.main.clamp(item.min, item.max).max(0.0);
+            violations.push(clamped - item.main);
+            total_violation += clamped - item.main;
+            item.main = clamped;
+        }
+        for (item, violation) in items.iter_mut().zip(violations) {
+            if item.frozen {
+                continue;
+            }
+            item.frozen = total_violation == 0.0
+                || (total_violation > 0.0 && violation > 0.0)
+                || (total_violation < 0.0 && violation < 0.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::html::tree_builder::HtmlParser;
+    use crate::style::Stylesheet;
+
+    fn layout_html(html: &str) -> (Dom, LayoutBox) {
+        let dom = HtmlParser::new(html).parse();
+        let root = LayoutEngine::new().layout(&dom, &Stylesheet::new());
+        (dom, root)
+    }
+
+    fn find<'a>(dom: &Dom, layout_box: &'a LayoutBox, id: &str) -> &'a LayoutBox {
+        fn search<'a>(dom: &Dom, b: &'a LayoutBox, id: &str) -> Option<&'a LayoutBox> {
+            if b.text_content.is_none() && dom.get_attribute(b.node_id, "id") == Some(id) {
+                return Some(b);
+            }
+            b.children.iter().find_map(|child| search(dom, child, id))
+        }
+        search(dom, layout_box, id).unwrap_or_else(|| panic!("no box with id {}", id))
+    }
+
+    #[test]
+    fn test_grow_shares_free_space() {
+        let (dom, root) = layout_html(
+            "<div style=\"display:flex; width:400px\">\
+             <div id=a style=\"width:100px\"></div>\
+             <div id=b style=\"flex:1\"></div>\
+             <div id=c style=\"flex:3\"></div></div>",
+        );
+        let (a, b, c) = (find(&dom, &root, "a"), find(&dom, &root, "b"), find(&dom, &root, "c"));
+        assert_eq!(a.dimensions.width, 100.0);
+        assert_eq!(b.dimensions.width, 75.0);
+        assert_eq!(c.dimensions.width, 225.0);
+        assert_eq!(b.dimensions.x, a.dimensions.x + 100.0);
+        assert_eq!(c.dimensions.x, b.dimensions.x + 75.0);
+    }
+
+    #[test]
+    fn test_shrink_respects_min_width() {
+        let (dom, root) = layout_html(
+            "<div style=\"display:flex; width:300px\">\
+             <div id=a style=\"width:200px; min-width:180px\"></div>\
+             <div id=b style=\"width:200px\"></div></div>",
+        );
+        let (a, b) = (find(&dom, &root, "a"), find(&dom, &root, "b"));
+        assert_eq!(a.dimensions.width, 180.0);
+        assert_eq!(b.dimensions.width, 120.0);
+    }
+
+    #[test]
+    fn test_wrap_with_gap_and_order() {
+        let (dom, root) = layout_html(
+            "<div style=\"display:flex; flex-wrap:wrap; gap:10px 20px; width:250px\">\
+             <div id=a style=\"width:100px; height:30px; order:2\"></div>\
+             <div id=b style=\"width:100px; height:40px\"></div>\
+             <div id=c style=\"width:100px; height:30px\"></div></div>",
+        );
+        let (a, b, c) = (find(&dom, &root, "a"), find(&dom, &root, "b"), find(&dom, &root, "c"));
+        // b and c come first; a wraps onto the second line
+        assert_eq!(c.dimensions.x, b.dimensions.x + 120.0);
+        assert_eq!(c.dimensions.y, b.dimensions.y);
+        assert_eq!(a.dimensions.x, b.dimensions.x);
+        assert_eq!(a.dimensions.y, b.dimensions.y + 40.0 + 10.0);
+        // Items without a height stretch to their line
+        assert_eq!(c.dimensions.height, 30.0);
+    }
+
+    #[test]
+    fn test_justify_and_align_center() {
+        let (dom, root) = layout_html(
+            "<div id=box style=\"display:flex; justify-content:center; align-items:center; width:300px; height:100px\">\
+             <div id=a style=\"width:50px; height:20px\"></div>\
+             <div id=b style=\"width:50px\"><div style=\"height:40px\"></div></div></div>",
+        );
+        let container = find(&dom, &root, "box");
+        let (a, b) = (find(&dom, &root, "a"), find(&dom, &root, "b"));
+        assert_eq!(container.dimensions.height, 100.0);
+        assert_eq!(a.dimensions.x - container.dimensions.x, 100.0);
+        assert_eq!(b.dimensions.x - container.dimensions.x, 150.0);
+        assert_eq!(a.dimensions.y - container.dimensions.y, 40.0);
+        assert_eq!(b.dimensions.y - container.dimensions.y, 30.0);
+    }
+
+    #[test]
+    fn test_column_reverse_stretches_width() {
+        let (dom, root) = layout_html(
+            "<div id=box style=\"display:flex; flex-direction:column-reverse; width:200px\">\
+             <div id=a style=\"height:20px\"></div>\
+             <div id=b style=\"height:30px; align-self:flex-end; width:50px\"></div></div>",
+        );
+        let container = find(&dom, &root, "box");
+        let (a, b) = (find(&dom, &root, "a"), find(&dom, &root, "b"));
+        assert_eq!(container.dimensions.height, 50.0);
+        assert_eq!(a.dimensions.width, 200.0);
+        assert_eq!(a.dimensions.y - container.dimensions.y, 30.0);
+        assert_eq!(b.dimensions.y, container.dimensions.y);
+        assert_eq!(b.dimensions.x - container.dimensions.x, 150.0);
+    }
+}
@@ -0,0 +1,287 @@
+// engine/src/layout/float.rs
+// Floats and block formatting contexts (CSS 2.1 sections 9.4.1 and 9.5)
+//
+// - Every block formatting context keeps the margin boxes of the floats placed
+//   in it; blocks that do not establish a new context share their parent's
+// - A float goes as high as it can without rising above earlier floats or the
+//   current line, then as far left (or right) as it can; it moves down past
+//   float bottoms until it fits beside them
+// - Line boxes shrink to the space left between floats, and move down when
+//   even the first item of a line does not fit
+// - `clear` moves a block's border edge below the floats on the cleared sides
+// - Formatting context roots (floats, absolutely positioned boxes, flex and
+//   grid containers, tables, `display: flow-root` and scroll containers)
+//   contain their floats and are narrowed so they do not overlap outside ones
+
+use crate::dom::{Dom, NodeId};
+use crate::font::FontManager;
+use crate::style::{Clear, ComputedStyle, ComputedStyles, Display, Float, Viewport};
+
+use super::{layout_log, translate, LayoutBox, LayoutEngine, UsedSize};
+
+/// Size of the placeholder an `<img>` gets, as in inline layout
+const IMAGE_PLACEHOLDER: (f32, f32) = (100.0, 80.0);
+
+/// The margin box of a placed float
+#[derive(Debug, Clone, Copy)]
+struct PlacedFloat {
+    side: Float,
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+}
+
+impl PlacedFloat {
+    fn bottom(&self) -> f32 {
+        self.y + self.height
+    }
+
+    /// Whether the float intersects the band `[y, y + height)`. An empty band
+    /// still intersects floats that span its top.
+    fn overlaps(&self, y: f32, height: f32) -> bool {
+        let top_in_band = if height > 0.0 { self.y < y + height } else { self.y <= y };
+        top_in_band && self.bottom() > y
+    }
+}
+
+/// The floats of one block formatting context, in absolute coordinates
+#[derive(Debug, Default)]
+pub(super) struct FloatContext {
+    floats: Vec<PlacedFloat>,
+}
+
+impl FloatContext {
+    /// The part of `[left, right]` not covered by floats within the band
+    /// `[y, y + height)`
+    pub(super) fn available(&self, y: f32, height: f32, left: f32, right: f32) -> (f32, f32) {
+        let (mut start, mut end) = (left, right);
+        for float in self.floats.iter().filter(|f| f.overlaps(y, height)) {
+            match float.side {
+                Float::Left => start = start.max(float.x + float.width),
+                Float::Right => end = end.min(float.x),
+                Float::None => {}
+            }
+        }
+        (start, end.max(start))
+    }
+
+    /// The first band at or below `y` where `needed` px fit beside the floats,
+    /// as `(y, start, end)`. Gives up on fitting once no float is in the way.
+    pub(super) fn fit(&self, mut y: f32, height: f32, left: f32, right: f32, needed: f32) -> (f32, f32, f32) {
+        loop {
+            let (start, end) = self.available(y, height, left, right);
+            if end - start >= needed {
+                return (y, start, end);
+            }
+            match self.next_bottom(y, height) {
+                Some(bottom) => y = bottom,
+                None => return (y, start, end),
+            }
+        }
+    }
+
+    /// The nearest bottom edge of a float in the band, where space may open up
+    fn next_bottom(&self, y: f32, height: f32) -> Option<f32> {
+        self.floats
+            .iter()
+            .filter(|f| f.overlaps(y, height))
+            .map(PlacedFloat::bottom)
+            .reduce(f32::min)
+    }
+
+    /// Place a float's margin box no higher than `y` between `left` and
+    /// `right`, returning its top-left corner
+    pub(super) fn place(&mut self, side: Float, width: f32, height: f32, y: f32, left: f32, right: f32) -> (f32, f32) {
+        // A float's top may not be higher than the top of any earlier float
+        let y = self.floats.iter().map(|f| f.y).fold(y, f32::max);
+        let (y, start, end) = self.fit(y, height, left, right, width);
+        let x = if side == Float::Right { end - width } else { start };
+        self.floats.push(PlacedFloat { side, x, y, width, height });
+        (x, y)
+    }
+
+    /// The lowest `y` at or below `y` that is clear of the floats `clear` names
+    pub(super) fn clear(&self, clear: Clear, y: f32) -> f32 {
+        self.floats
+            .iter()
+            .filter(|f| clear.clears(f.side))
+            .map(PlacedFloat::bottom)
+            .fold(y, f32::max)
+    }
+
+    /// Bottom of the lowest float, which a formatting context root's auto
+    /// height must reach
+    pub(super) fn bottom(&self) -> Option<f32> {
+        self.floats.iter().map(PlacedFloat::bottom).reduce(f32::max)
+    }
+}
+
+/// Whether a box lays out its contents in a new block formatting context
+/// instead of its parent's
+pub(super) fn establishes_bfc(style: &ComputedStyle) -> bool {
+    style.float != Float::None
+        || style.position.is_out_of_flow()
+        || style.overflow_x.is_scrollable()
+        || style.overflow_y.is_scrollable()
+        || matches!(
+            style.display,
+            Display::FlowRoot
+                | Display::InlineBlock
+                | Display::Table
+                | Display::TableCell
+                | Display::TableCaption
+                | Display::Flex
+                | Display::InlineFlex
+                | Display::Grid
+                | Display::InlineGrid
+        )
+}
+
+impl LayoutEngine {
+    /// Lay out a floated element and place it in `floats`, no higher than `y`
+    /// and within `[content_x, content_x + content_width]`
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn layout_float(
+        &self,
+        dom: &Dom,
+        styles: &ComputedStyles,
+        node_id: NodeId,
+        content_x: f32,
+        y: f32,
+        content_width: f32,
+        floats: &mut FloatContext,
+        exclude_tags: &[&str],
+        viewport: &Viewport,
+        font_manager: &mut FontManager,
+    ) -> LayoutBox {
+        let style = styles.get(node_id).clone();
+        let margin = style.margin.resolve(content_width);
+        let pb = style.padding_border(content_width);
+        let is_image = dom.tag_name(node_id) == Some("img");
+
+        // Border-box size: specified, the image placeholder, or shrink-to-fit
+        let width = match style.width.as_ref() {
+            Some(w) => w.resolve(content_width) + pb.left + pb.right,
+            None if is_image => IMAGE_PLACEHOLDER.0 + pb.left + pb.right,
+            None => {
+                let available = (content_width - margin.left - margin.right).max(0.0);
+                let (min, max) = self.intrinsic_widths(dom, styles, node_id, content_width, viewport, font_manager);
+                max.min(available).max(min)
+            }
+        };
+        let height = match style.height.as_ref().and_then(|h| h.definite()) {
+            Some(h) => Some(h + pb.top + pb.bottom),
+            None if is_image => Some(IMAGE_PLACEHOLDER.1 + pb.top + pb.bottom),
+            None => None,
+        };
+
+        let mut float_box = self.layout_block_sized(
+            dom, styles, node_id,
+            0.0, 0.0, content_width,
+            exclude_tags, viewport, font_manager,
+            UsedSize { width: Some(width), height },
+            None,
+        );
+
+        let y = floats.clear(style.clear, y);
+        let (x, y) = floats.place(
+            style.float,
+            float_box.dimensions.width + margin.left + margin.right,
+            float_box.dimensions.height + margin.top + margin.bottom,
+            y,
+            content_x,
+            content_x + content_width,
+        );
+        layout_log(&format!("  float {:?} node {} placed at ({}, {})", style.float, node_id, x, y));
+        translate(&mut float_box, x + margin.left, y + margin.top);
+        float_box
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::html::tree_builder::HtmlParser;
+    use crate::style::Stylesheet;
+
+    fn layout_html(html: &str) -> (Dom, LayoutBox) {
+        let dom = HtmlParser::new(html).parse();
+        let root = LayoutEngine::new().layout(&dom, &Stylesheet::new());
+        (dom, root)
+    }
+
+    fn find<'a>(dom: &Dom, layout_box: &'a LayoutBox, id: &str) -> &'a LayoutBox {
+        fn search<'a>(dom: &Dom, b: &'a LayoutBox, id: &str) -> Option<&'a LayoutBox> {
+            if b.text_content.is_none() && dom.get_attribute(b.node_id, "id") == Some(id) {
+                return Some(b);
+            }
+            b.children.iter().find_map(|child| search(dom, child, id))
+        }
+        search(dom, layout_box, id).unwrap_or_else(|| panic!("no box with id {}", id))
+    }
+
+    #[test]
+    fn test_floats_stack_on_both_sides() {
+        let (dom, root) = layout_html(
+            "<div style=\"width:400px\">\
+             <div id=a style=\"float:left; width:100px; height:50px\"></div>\
+             <div id=b style=\"float:left; width:100px; height:20px\"></div>\
+             <div id=c style=\"float:right; width:150px; height:20px\"></div>\
+             <div id=d style=\"float:left; width:100px; height:20px\"></div></div>",
+        );
+        let x = find(&dom, &root, "a").dimensions.x;
+        assert_eq!(find(&dom, &root, "b").dimensions.x, x + 100.0);
+        assert_eq!(find(&dom, &root, "c").dimensions.x, x + 250.0);
+        // No room left on the first row, so d drops below the shorter float
+        let d = find(&dom, &root, "d");
+        assert_eq!((d.dimensions.x, d.dimensions.y), (x + 100.0, find(&dom, &root, "b").dimensions.y + 20.0));
+    }
+
+    #[test]
+    fn test_clear_moves_below_floats() {
+        let (dom, root) = layout_html(
+            "<div id=f style=\"float:left; width:100px; height:50px\"></div>\
+             <div id=r style=\"float:right; width:100px; height:80px\"></div>\
+             <div id=cl style=\"clear:left; height:10px\"></div>\
+             <div id=cb style=\"clear:both; height:10px\"></div>",
+        );
+        let top = find(&dom, &root, "f").dimensions.y;
+        assert_eq!(find(&dom, &root, "cl").dimensions.y, top + 50.0);
+        assert_eq!(find(&dom, &root, "cb").dimensions.y, top + 80.0);
+    }
+
+    #[test]
+    fn test_lines_wrap_around_float() {
+        let (dom, root) = layout_html(
+            "<div id=p style=\"width:300px\"><img id=i align=left>text beside the image</div>",
+        );
+        let image = find(&dom, &root, "i");
+        assert_eq!((image.dimensions.width, image.dimensions.height), (100.0, 80.0));
+        let p = find(&dom, &root, "p");
+        let first_word = p
+            .children
+            .iter()
+            .flat_map(|line| line.children.iter())
+            .find(|b| b.text_content.is_some())
+            .unwrap();
+        // Right of the image and its 3px margin
+        assert_eq!(first_word.dimensions.x, image.dimensions.x + 103.0);
+        // The float hangs out of a block that does not contain it
+        assert!(p.dimensions.height < 80.0);
+    }
+
+    #[test]
+    fn test_flow_root_contains_and_avoids_floats() {
+        let (dom, root) = layout_html(
+            "<div id=outer style=\"display:flow-root; width:400px\">\
+             <div id=f style=\"float:left; width:100px; height:60px\"></div>\
+             <div id=side style=\"overflow:hidden; height:10px\"></div></div>",
+        );
+        let outer = find(&dom, &root, "outer");
+        assert_eq!(outer.dimensions.height, 60.0);
+        let side = find(&dom, &root, "side");
+        assert_eq!(side.dimensions.x, outer.dimensions.x + 100.0);
+        assert_eq!(side.dimensions.width, 300.0);
+    }
+}
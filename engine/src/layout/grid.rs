@@ -0,0 +1,1148 @@
+// engine/src/layout/grid.rs
+// Grid layout (CSS Grid Layout Level 1)
+//
+// - `grid-template-columns`/`-rows` define the explicit tracks: lengths, percentages, `fr`,
+//   `auto`, `min-content`, `max-content`, `minmax()`, `fit-content()`, `[line names]` and
+//   `repeat()`, including `auto-fill` and `auto-fit`
+// - `grid-template-areas` names rectangular areas, whose edges become lines named
+//   `<area>-start` and `<area>-end`
+// - Items are placed by line number, line name, `span` or area (the `grid-row-start` family
+//   of longhands, which `grid-area`, `grid-row` and `grid-column` expand to); the rest are auto-placed in `order` following
+//   `grid-auto-flow` (row or column, optionally dense)
+// - Tracks outside the explicit grid are implicit and sized by `grid-auto-rows`/`-columns`
+// - Track sizing follows section 11: fixed sizes, item min/max-content contributions,
+//   free space, `fr` tracks, then stretching `auto` tracks
+// - `row-gap`/`column-gap` separate tracks; `justify-content`/`align-content` distribute leftover space and
+//   `justify-items`/`justify-self`/`align-items`/`align-self` place items in their areas
+
+use std::ops::Range;
+use std::sync::Arc;
+
+use crate::dom::{Dom, NodeId};
+use crate::font::FontManager;
+use crate::style::{ComputedStyle, ComputedStyles, CssValue, Length, Viewport};
+
+use super::flex::{child_items, definite_height, gaps, Alignment, Distribution, ItemContent};
+use super::float::FloatContext;
+use super::{layout_log, translate, LayoutBox, LayoutEngine, UsedSize, PROBE_WIDTH};
+
+const ROWS: usize = 0;
+const COLUMNS: usize = 1;
+
+/// One side of a track sizing function, before percentages are resolved
+#[derive(Debug, Clone)]
+enum Breadth {
+    Length(Length),
+    Fr(f32),
+    MinContent,
+    MaxContent,
+    Auto,
+    FitContent(Length),
+}
+
+impl Breadth {
+    fn parse(text: &str, style: &ComputedStyle) -> Option<Breadth> {
+        let lower = text.trim().to_ascii_lowercase();
+        match lower.as_str() {
+            "auto" => Some(Breadth::Auto),
+            "min-content" => Some(Breadth::MinContent),
+            "max-content" => Some(Breadth::MaxContent),
+            _ => match lower.strip_suffix("fr").and_then(|n| n.parse::<f32>().ok()) {
+                Some(fr) if fr >= 0.0 => Some(Breadth::Fr(fr)),
+                Some(_) => None,
+                None => style.to_length(&CssValue::parse(text)?).map(Breadth::Length),
+            },
+        }
+    }
+}
+
+/// A track sizing function: `minmax(min, max)`, with plain sizes as both
+#[derive(Debug, Clone)]
+struct TrackSize {
+    min: Breadth,
+    max: Breadth,
+}
+
+impl TrackSize {
+    fn auto() -> Self {
+        TrackSize { min: Breadth::Auto, max: Breadth::Auto }
+    }
+
+    fn parse(text: &str, style: &ComputedStyle) -> Option<TrackSize> {
+        if let Some(args) = function_args(text, "minmax") {
+            let (min, max) = args.split_once(',')?;
+            let min = Breadth::parse(min, style)?;
+            if matches!(min, Breadth::Fr(_)) {
+                return None;
+            }
+            return Some(TrackSize { min, max: Breadth::parse(max, style)? });
+        }
+        if let Some(arg) = function_args(text, "fit-content") {
+            let limit = style.to_length(&CssValue::parse(arg)?)?;
+            return Some(TrackSize { min: Breadth::Auto, max: Breadth::FitContent(limit) });
+        }
+        Some(match Breadth::parse(text, style)? {
+            // A bare `fr` is `minmax(auto, fr)`
+            Breadth::Fr(fr) => TrackSize { min: Breadth::Auto, max: Breadth::Fr(fr) },
+            breadth => TrackSize { min: breadth.clone(), max: breadth },
+        })
+    }
+
+    /// Size used to count `auto-fill` repetitions, if the function has one
+    fn fixed_size(&self, basis: f32) -> Option<f32> {
+        match (&self.max, &self.min) {
+            (Breadth::Length(length), _) | (_, Breadth::Length(length)) => Some(length.resolve(basis)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Repetition {
+    Count(usize),
+    AutoFill,
+    AutoFit,
+}
+
+/// One component of a `grid-template-rows`/`-columns` value
+#[derive(Debug, Clone)]
+enum TrackEntry {
+    Names(Vec<String>),
+    Size(TrackSize),
+    Repeat(Repetition, Vec<TrackEntry>),
+}
+
+fn parse_track_list(text: &str, style: &ComputedStyle) -> Vec<TrackEntry> {
+    if text.trim().eq_ignore_ascii_case("none") {
+        return Vec::new();
+    }
+    split_top_level(text, char::is_whitespace)
+        .into_iter()
+        .filter_map(|part| parse_track_entry(part, style))
+        .collect()
+}
+
+fn parse_track_entry(part: &str, style: &ComputedStyle) -> Option<TrackEntry> {
+    if let Some(names) = part.strip_prefix('[').and_then(|names| names.strip_suffix(']')) {
+        return Some(TrackEntry::Names(names.split_whitespace().map(str::to_string).collect()));
+    }
+    if let Some(args) = function_args(part, "repeat") {
+        let (count, tracks) = args.split_once(',')?;
+        let repetition = match count.trim().to_ascii_lowercase().as_str() {
+            "auto-fill" => Repetition::AutoFill,
+            "auto-fit" => Repetition::AutoFit,
+            count => Repetition::Count(count.parse().ok().filter(|&n| n > 0)?),
+        };
+        let tracks = parse_track_list(tracks, style);
+        return (!tracks.is_empty()).then_some(TrackEntry::Repeat(repetition, tracks));
+    }
+    TrackSize::parse(part, style).map(TrackEntry::Size)
+}
+
+/// Arguments of `name(...)` when `text` is a call to that function
+fn function_args<'a>(text: &'a str, name: &str) -> Option<&'a str> {
+    let text = text.trim();
+    let open = text.find('(')?;
+    (text[..open].trim().eq_ignore_ascii_case(name) && text.ends_with(')'))
+        .then(|| &text[open + 1..text.len() - 1])
+}
+
+/// Split at characters matching `at` outside parentheses and brackets
+fn split_top_level(text: &str, at: impl Fn(char) -> bool) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            c if depth == 0 && at(c) => {
+                parts.push(text[start..i].trim());
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(text[start..].trim());
+    parts.retain(|part| !part.is_empty());
+    parts
+}
+
+/// Total fixed size and number of the tracks in `entries`, skipping auto repeats
+fn fixed_sum<'a>(entries: impl Iterator<Item = &'a TrackEntry>, basis: f32) -> (f32, usize) {
+    let mut sum = (0.0, 0);
+    for entry in entries {
+        match entry {
+            TrackEntry::Size(size) => {
+                sum.0 += size.fixed_size(basis).unwrap_or(0.0);
+                sum.1 += 1;
+            }
+            TrackEntry::Repeat(Repetition::Count(n), inner) => {
+                let (size, count) = fixed_sum(inner.iter(), basis);
+                sum.0 += size * *n as f32;
+                sum.1 += count * n;
+            }
+            _ => {}
+        }
+    }
+    sum
+}
+
+/// The tracks of one axis's explicit grid, with `repeat()` expanded
+#[derive(Debug, Default)]
+struct TrackList {
+    sizes: Vec<TrackSize>,
+    /// Names of each line; one more entry than there are tracks
+    line_names: Vec<Vec<String>>,
+    /// Tracks generated by `repeat(auto-fit, ...)`, which collapse when empty
+    auto_fit: Option<Range<usize>>,
+}
+
+impl TrackList {
+    fn new(entries: &[TrackEntry], available: Option<f32>, gap: f32) -> Self {
+        let mut list = TrackList { line_names: vec![Vec::new()], ..TrackList::default() };
+        for entry in entries {
+            match entry {
+                TrackEntry::Repeat(repetition @ (Repetition::AutoFill | Repetition::AutoFit), inner) => {
+                    let count = auto_repeat_count(entries, inner, available, gap);
+                    let start = list.sizes.len();
+                    for _ in 0..count {
+                        list.push(inner);
+                    }
+                    if *repetition == Repetition::AutoFit {
+                        list.auto_fit = Some(start..list.sizes.len());
+                    }
+                }
+                entry => list.push(std::slice::from_ref(entry)),
+            }
+        }
+        list
+    }
+
+    fn push(&mut self, entries: &[TrackEntry]) {
+        for entry in entries {
+            match entry {
+                TrackEntry::Names(names) => {
+                    if let Some(line) = self.line_names.last_mut() {
+                        line.extend(names.iter().cloned());
+                    }
+                }
+                TrackEntry::Size(size) => self.push_track(size.clone()),
+                TrackEntry::Repeat(Repetition::Count(n), inner) => {
+                    for _ in 0..*n {
+                        self.push(inner);
+                    }
+                }
+                TrackEntry::Repeat(_, inner) => self.push(inner),
+            }
+        }
+    }
+
+    fn push_track(&mut self, size: TrackSize) {
+        self.sizes.push(size);
+        self.line_names.push(Vec::new());
+    }
+}
+
+/// Repetitions of `repeated` for `auto-fill`/`auto-fit`: as many as fit the
+/// available space alongside the other tracks, and at least one
+fn auto_repeat_count(entries: &[TrackEntry], repeated: &[TrackEntry], available: Option<f32>, gap: f32) -> usize {
+    let Some(available) = available else {
+        return 1;
+    };
+    let (repeat_size, repeat_count) = fixed_sum(repeated.iter(), available);
+    let (other_size, other_count) = fixed_sum(entries.iter(), available);
+    let per_repetition = repeat_size + gap * repeat_count as f32;
+    if per_repetition <= 0.0 {
+        return 1;
+    }
+    let room = available - other_size - gap * (other_count as f32 - 1.0);
+    ((room / per_repetition).floor().max(1.0)) as usize
+}
+
+/// A named area from `grid-template-areas`
+#[derive(Debug, Clone, PartialEq)]
+struct Area {
+    name: String,
+    rows: Range<usize>,
+    columns: Range<usize>,
+}
+
+/// Parse `grid-template-areas` into its areas and the row and column counts.
+/// Rows of different lengths or non-rectangular areas make it invalid.
+fn parse_areas(text: &str) -> Option<(Vec<Area>, usize, usize)> {
+    let text = text.replace('\'', "\"");
+    let rows: Vec<Vec<&str>> = text
+        .split('"')
+        .skip(1)
+        .step_by(2)
+        .map(|row| row.split_whitespace().collect())
+        .collect();
+    let columns = rows.first()?.len();
+    if columns == 0 || rows.iter().any(|row| row.len() != columns) {
+        return None;
+    }
+
+    let mut areas: Vec<Area> = Vec::new();
+    let mut cells = Vec::new();
+    for (r, row) in rows.iter().enumerate() {
+        for (c, &name) in row.iter().enumerate() {
+            // One or more dots mark an unnamed cell
+            if name.chars().all(|ch| ch == '.') {
+                continue;
+            }
+            cells.push(name);
+            match areas.iter_mut().find(|area| area.name == name) {
+                Some(area) => {
+                    area.rows = area.rows.start.min(r)..area.rows.end.max(r + 1);
+                    area.columns = area.columns.start.min(c)..area.columns.end.max(c + 1);
+                }
+                None => areas.push(Area { name: name.to_string(), rows: r..r + 1, columns: c..c + 1 }),
+            }
+        }
+    }
+    let rectangular = areas
+        .iter()
+        .all(|area| cells.iter().filter(|&&name| name == area.name).count() == area.rows.len() * area.columns.len());
+    rectangular.then_some((areas, rows.len(), columns))
+}
+
+/// A `grid-row-start`-style value
+#[derive(Debug, Clone, PartialEq)]
+enum GridLine {
+    Auto,
+    /// 1-based line number, negative counting from the end of the explicit grid
+    Line(i32),
+    /// The nth line with a name (or the edge of the area with that name)
+    Named(String, i32),
+    Span(usize),
+}
+
+impl GridLine {
+    fn parse(text: &str) -> GridLine {
+        let (mut span, mut number, mut name) = (false, None, None);
+        for part in text.split_whitespace() {
+            if part.eq_ignore_ascii_case("span") {
+                span = true;
+            } else if let Ok(n) = part.parse::<i32>() {
+                number = Some(n);
+            } else if !part.eq_ignore_ascii_case("auto") {
+                name = Some(part.to_string());
+            }
+        }
+        match (span, number, name) {
+            (true, number, _) => GridLine::Span(number.unwrap_or(1).max(1) as usize),
+            (false, Some(0), None) | (false, None, None) => GridLine::Auto,
+            (false, Some(n), None) => GridLine::Line(n),
+            (false, n, Some(name)) => GridLine::Named(name, n.unwrap_or(1)),
+        }
+    }
+}
+
+/// An item's requested lines, in `grid-area` order: row-start, column-start,
+/// row-end, column-end
+fn placement(style: &ComputedStyle) -> [GridLine; 4] {
+    ["grid-row-start", "grid-column-start", "grid-row-end", "grid-column-end"]
+        .map(|property| style.get(property).map_or(GridLine::Auto, GridLine::parse))
+}
+
+/// Find the nth line called `name` (from the end when negative). An area
+/// name matches its implicit `<name>-start`/`<name>-end` line first.
+fn find_line(line_names: &[Vec<String>], name: &str, side: &str, nth: i32) -> Option<i32> {
+    let implicit = format!("{}-{}", name, side);
+    [implicit.as_str(), name].iter().find_map(|wanted| {
+        let lines: Vec<usize> = line_names
+            .iter()
+            .enumerate()
+            .filter(|(_, names)| names.iter().any(|n| n == wanted))
+            .map(|(index, _)| index)
+            .collect();
+        let index = if nth > 0 {
+            nth as usize - 1
+        } else {
+            lines.len().checked_sub(nth.unsigned_abs() as usize)?
+        };
+        lines.get(index).map(|&line| line as i32)
+    })
+}
+
+/// Resolve one axis of a placement to a start line (0-based, and possibly
+/// outside the explicit grid) when definite, and a span
+fn resolve_axis(start: &GridLine, end: &GridLine, line_names: &[Vec<String>]) -> (Option<i32>, usize) {
+    let explicit = line_names.len() as i32 - 1;
+    let line = |line: &GridLine, side: &str| match line {
+        GridLine::Line(n) if *n > 0 => Some(n - 1),
+        GridLine::Line(n) => Some(explicit + 1 + n),
+        GridLine::Named(name, nth) => find_line(line_names, name, side, *nth),
+        _ => None,
+    };
+    let span = |line: &GridLine| match line {
+        GridLine::Span(n) => *n,
+        _ => 1,
+    };
+    match (line(start, "start"), line(end, "end")) {
+        (Some(s), Some(e)) if s == e => (Some(s), 1),
+        (Some(s), Some(e)) => (Some(s.min(e)), s.abs_diff(e) as usize),
+        (Some(s), None) => (Some(s), span(end)),
+        (None, Some(e)) => (Some(e - span(start) as i32), span(start)),
+        (None, None) => (None, span(start).max(span(end))),
+    }
+}
+
+/// Place every item, returning its row and column track ranges and the
+/// number of rows and columns. Tracks added before the explicit grid (for
+/// negative or out-of-range lines) are counted in `leading`.
+fn place_items(
+    requests: &[[(Option<i32>, usize); 2]],
+    explicit: [usize; 2],
+    flow_column: bool,
+    dense: bool,
+) -> (Vec<[Range<usize>; 2]>, [usize; 2], [usize; 2]) {
+    let mut leading = [0usize; 2];
+    for request in requests {
+        for axis in [ROWS, COLUMNS] {
+            if let (Some(start), _) = request[axis] {
+                leading[axis] = leading[axis].max((-start).max(0) as usize);
+            }
+        }
+    }
+    let definite = |request: &[(Option<i32>, usize); 2], axis: usize| {
+        request[axis].0.map(|start| (start + leading[axis] as i32) as usize)
+    };
+
+    let major = if flow_column { COLUMNS } else { ROWS };
+    let minor = 1 - major;
+    let mut minor_count = explicit[minor] + leading[minor];
+    for request in requests {
+        let span = request[minor].1;
+        minor_count = minor_count.max(definite(request, minor).map_or(span, |start| start + span));
+    }
+
+    let mut placed: Vec<Option<[Range<usize>; 2]>> = vec![None; requests.len()];
+    let fits = |placed: &[Option<[Range<usize>; 2]>], area: &[Range<usize>; 2]| {
+        placed.iter().flatten().all(|other| {
+            other[0].end <= area[0].start
+                || area[0].end <= other[0].start
+                || other[1].end <= area[1].start
+                || area[1].end <= other[1].start
+        })
+    };
+    let area_at = |major_start: usize, minor_start: usize, request: &[(Option<i32>, usize); 2]| {
+        let mut area = [0..0, 0..0];
+        area[major] = major_start..major_start + request[major].1;
+        area[minor] = minor_start..minor_start + request[minor].1;
+        area
+    };
+
+    // Step 1: Items with a definite position in both axes
+    for (index, request) in requests.iter().enumerate() {
+        if let (Some(row), Some(column)) = (definite(request, ROWS), definite(request, COLUMNS)) {
+            placed[index] = Some([row..row + request[ROWS].1, column..column + request[COLUMNS].1]);
+        }
+    }
+
+    // Step 2: Items locked to a major track (a row, in row flow)
+    let mut cursors: Vec<(usize, usize)> = Vec::new();
+    for (index, request) in requests.iter().enumerate() {
+        let (Some(major_start), None) = (definite(request, major), definite(request, minor)) else {
+            continue;
+        };
+        let cursor = cursors.iter().position(|(track, _)| *track == major_start);
+        let mut minor_start = match (dense, cursor) {
+            (false, Some(i)) => cursors[i].1,
+            _ => 0,
+        };
+        while !fits(&placed, &area_at(major_start, minor_start, request)) {
+            minor_start += 1;
+        }
+        let area = area_at(major_start, minor_start, request);
+        minor_count = minor_count.max(area[minor].end);
+        match cursor {
+            Some(i) => cursors[i].1 = area[minor].end,
+            None => cursors.push((major_start, area[minor].end)),
+        }
+        placed[index] = Some(area);
+    }
+
+    // Step 3: Everything else, moving a cursor through the grid
+    let (mut cursor_major, mut cursor_minor) = (0, 0);
+    for (index, request) in requests.iter().enumerate() {
+        if placed[index].is_some() {
+            continue;
+        }
+        if dense {
+            (cursor_major, cursor_minor) = (0, 0);
+        }
+        if let Some(minor_start) = definite(request, minor) {
+            if !dense && minor_start < cursor_minor {
+                cursor_major += 1;
+            }
+            cursor_minor = minor_start;
+            while !fits(&placed, &area_at(cursor_major, cursor_minor, request)) {
+                cursor_major += 1;
+            }
+            placed[index] = Some(area_at(cursor_major, cursor_minor, request));
+        } else {
+            loop {
+                if cursor_minor + request[minor].1 > minor_count {
+                    cursor_major += 1;
+                    cursor_minor = 0;
+                    continue;
+                }
+                let area = area_at(cursor_major, cursor_minor, request);
+                if fits(&placed, &area) {
+                    placed[index] = Some(area);
+                    cursor_minor += request[minor].1;
+                    break;
+                }
+                cursor_minor += 1;
+            }
+        }
+    }
+
+    let areas: Vec<[Range<usize>; 2]> = placed.into_iter().flatten().collect();
+    let mut counts = [explicit[ROWS] + leading[ROWS], explicit[COLUMNS] + leading[COLUMNS]];
+    for area in &areas {
+        counts[ROWS] = counts[ROWS].max(area[ROWS].end);
+        counts[COLUMNS] = counts[COLUMNS].max(area[COLUMNS].end);
+    }
+    (areas, counts, leading)
+}
+
+/// A track sizing function with percentages resolved
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Sizing {
+    Fixed(f32),
+    Fr(f32),
+    MinContent,
+    MaxContent,
+    Auto,
+    FitContent(f32),
+}
+
+impl Sizing {
+    fn new(breadth: &Breadth, basis: Option<f32>) -> Sizing {
+        let resolve = |length: &Length| basis.map(|basis| length.resolve(basis)).or_else(|| length.definite());
+        match breadth {
+            // A percentage of an indefinite size behaves as `auto`
+            Breadth::Length(length) => resolve(length).map_or(Sizing::Auto, Sizing::Fixed),
+            Breadth::FitContent(length) => resolve(length).map_or(Sizing::MaxContent, Sizing::FitContent),
+            Breadth::Fr(fr) => Sizing::Fr(*fr),
+            Breadth::MinContent => Sizing::MinContent,
+            Breadth::MaxContent => Sizing::MaxContent,
+            Breadth::Auto => Sizing::Auto,
+        }
+    }
+
+    fn is_intrinsic(self) -> bool {
+        matches!(self, Sizing::MinContent | Sizing::MaxContent | Sizing::Auto | Sizing::FitContent(_))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Track {
+    min: Sizing,
+    max: Sizing,
+    base: f32,
+    /// Growth limit; infinite until an item sets it
+    limit: f32,
+    /// An empty `auto-fit` track, sized 0 with no gaps around it
+    collapsed: bool,
+}
+
+impl Track {
+    fn new(size: &TrackSize, basis: Option<f32>) -> Track {
+        let min = Sizing::new(&size.min, basis);
+        let max = Sizing::new(&size.max, basis);
+        let base = match min {
+            Sizing::Fixed(px) => px,
+            _ => 0.0,
+        };
+        let limit = match max {
+            Sizing::Fixed(px) => px.max(base),
+            Sizing::Fr(_) => base,
+            _ => f32::INFINITY,
+        };
+        Track { min, max, base, limit, collapsed: false }
+    }
+
+    fn collapsed() -> Track {
+        Track { min: Sizing::Fixed(0.0), max: Sizing::Fixed(0.0), base: 0.0, limit: 0.0, collapsed: true }
+    }
+
+    fn flex(&self) -> f32 {
+        match self.max {
+            Sizing::Fr(fr) => fr,
+            _ => 0.0,
+        }
+    }
+
+    /// Raise the growth limit to `size`, replacing it if still unset
+    fn grow_limit(&mut self, size: f32) {
+        self.limit = if self.limit.is_finite() { self.limit.max(size) } else { size };
+    }
+}
+
+/// What an item needs from the tracks it spans in one axis
+struct Contribution {
+    tracks: Range<usize>,
+    min: f32,
+    max: f32,
+}
+
+/// Total gap between the tracks that are not collapsed
+fn total_gaps(tracks: &[Track], gap: f32) -> f32 {
+    let visible = tracks.iter().filter(|track| !track.collapsed).count();
+    gap * visible.saturating_sub(1) as f32
+}
+
+/// Size the tracks of one axis (section 11.3, simplified)
+fn size_tracks(tracks: &mut [Track], items: &[Contribution], available: Option<f32>, gap: f32, stretch: bool) {
+    // Items in a single track
+    for item in items.iter().filter(|item| item.tracks.len() == 1) {
+        let track = &mut tracks[item.tracks.start];
+        match track.min {
+            Sizing::MinContent | Sizing::Auto => track.base = track.base.max(item.min),
+            Sizing::MaxContent => track.base = track.base.max(item.max),
+            _ => {}
+        }
+        match track.max {
+            Sizing::MinContent => track.grow_limit(item.min),
+            Sizing::MaxContent | Sizing::Auto => track.grow_limit(item.max),
+            Sizing::FitContent(limit) => track.grow_limit(item.max.min(limit)),
+            _ => {}
+        }
+    }
+
+    // Items spanning several tracks, none of them flexible, narrowest spans first
+    let mut spanning: Vec<&Contribution> = items
+        .iter()
+        .filter(|item| item.tracks.len() > 1 && tracks[item.tracks.clone()].iter().all(|t| t.flex() == 0.0))
+        .collect();
+    spanning.sort_by_key(|item| item.tracks.len());
+    for item in spanning {
+        let spanned = &mut tracks[item.tracks.clone()];
+        let gaps = gap * (spanned.len() - 1) as f32;
+
+        let growable = spanned.iter().filter(|t| t.min.is_intrinsic()).count();
+        let extra = item.min - spanned.iter().map(|t| t.base).sum::<f32>() - gaps;
+        if extra > 0.0 && growable > 0 {
+            for track in spanned.iter_mut().filter(|t| t.min.is_intrinsic()) {
+                track.base += extra / growable as f32;
+            }
+        }
+
+        let growable = spanned.iter().filter(|t| t.max.is_intrinsic()).count();
+        let limits: f32 = spanned.iter().map(|t| if t.limit.is_finite() { t.limit } else { t.base }).sum();
+        let extra = item.max - limits - gaps;
+        if extra > 0.0 && growable > 0 {
+            for track in spanned.iter_mut().filter(|t| t.max.is_intrinsic()) {
+                let limit = if track.limit.is_finite() { track.limit } else { track.base };
+                track.limit = limit + extra / growable as f32;
+            }
+        }
+    }
+    for track in tracks.iter_mut() {
+        if !track.limit.is_finite() {
+            track.limit = track.base;
+        }
+        track.limit = track.limit.max(track.base);
+    }
+
+    // Grow base sizes towards their limits, freezing tracks that reach them
+    let gaps = total_gaps(tracks, gap);
+    match available {
+        Some(available) => {
+            let mut free = available - gaps - tracks.iter().map(|t| t.base).sum::<f32>();
+            loop {
+                let growable: Vec<usize> = (0..tracks.len())
+                    .filter(|&i| tracks[i].flex() == 0.0 && tracks[i].base < tracks[i].limit)
+                    .collect();
+                if free <= 0.01 || growable.is_empty() {
+                    break;
+                }
+                let share = free / growable.len() as f32;
+                for i in growable {
+                    let grow = share.min(tracks[i].limit - tracks[i].base);
+                    tracks[i].base += grow;
+                    free -= grow;
+                }
+            }
+        }
+        None => {
+            for track in tracks.iter_mut().filter(|t| t.flex() == 0.0) {
+                track.base = track.limit;
+            }
+        }
+    }
+
+    // Expand flexible tracks
+    let flexible: Vec<usize> = (0..tracks.len()).filter(|&i| tracks[i].flex() > 0.0).collect();
+    if !flexible.is_empty() {
+        let fr = match available {
+            Some(available) => find_fr_size(tracks, flexible.clone(), available - gaps),
+            None => {
+                // Large enough for every flexible track's base size and every item's max-content
+                let mut fr = flexible
+                    .iter()
+                    .map(|&i| tracks[i].base / tracks[i].flex().max(1.0))
+                    .fold(0.0, f32::max);
+                for item in items {
+                    let spanned = &tracks[item.tracks.clone()];
+                    let flex: f32 = spanned.iter().map(Track::flex).sum();
+                    if flex > 0.0 {
+                        let fixed: f32 = spanned.iter().filter(|t| t.flex() == 0.0).map(|t| t.base).sum();
+                        let gaps = gap * (spanned.len() - 1) as f32;
+                        fr = fr.max((item.max - fixed - gaps) / flex.max(1.0));
+                    }
+                }
+                fr
+            }
+        };
+        for i in flexible {
+            tracks[i].base = tracks[i].base.max(fr * tracks[i].flex());
+        }
+    }
+
+    // Stretch `auto` tracks into any space left
+    if let (true, Some(available)) = (stretch, available) {
+        let free = available - gaps - tracks.iter().map(|t| t.base).sum::<f32>();
+        let auto: Vec<&mut Track> = tracks.iter_mut().filter(|t| t.max == Sizing::Auto && !t.collapsed).collect();
+        if free > 0.0 && !auto.is_empty() {
+            let share = free / auto.len() as f32;
+            for track in auto {
+                track.base += share;
+            }
+        }
+    }
+}
+
+/// Size of `1fr` that fills `space`, treating tracks whose base size exceeds
+/// their share as inflexible
+fn find_fr_size(tracks: &[Track], mut flexible: Vec<usize>, space: f32) -> f32 {
+    loop {
+        let inflexible: f32 = (0..tracks.len()).filter(|i| !flexible.contains(i)).map(|i| tracks[i].base).sum();
+        let flex: f32 = flexible.iter().map(|&i| tracks[i].flex()).sum();
+        let fr = ((space - inflexible) / flex.max(1.0)).max(0.0);
+        let count = flexible.len();
+        flexible.retain(|&i| fr * tracks[i].flex() >= tracks[i].base);
+        if flexible.len() == count {
+            return fr;
+        }
+    }
+}
+
+/// Offset of each track's start after content distribution, and the total extent
+fn track_offsets(tracks: &[Track], available: Option<f32>, gap: f32, distribution: Distribution) -> (Vec<f32>, f32) {
+    let visible = tracks.iter().filter(|track| !track.collapsed).count();
+    let used = tracks.iter().map(|track| track.base).sum::<f32>() + total_gaps(tracks, gap);
+    let free = available.map_or(0.0, |available| available - used);
+    let (offset, between) = distribution.spacing(free, visible);
+
+    let mut offsets = Vec::with_capacity(tracks.len());
+    let mut position = offset;
+    for (index, track) in tracks.iter().enumerate() {
+        offsets.push(position);
+        if !track.collapsed {
+            position += track.base;
+            if tracks[index + 1..].iter().any(|track| !track.collapsed) {
+                position += gap + between;
+            }
+        }
+    }
+    (offsets, position)
+}
+
+/// Start and size of the span of `range` tracks
+fn span_extent(tracks: &[Track], offsets: &[f32], range: &Range<usize>) -> (f32, f32) {
+    let last = range.end - 1;
+    (offsets[range.start], offsets[last] + tracks[last].base - offsets[range.start])
+}
+
+/// Shared inputs for laying out one container's items
+struct GridContainer<'a> {
+    dom: &'a Dom,
+    styles: &'a ComputedStyles,
+    exclude_tags: &'a [&'a str],
+    viewport: &'a Viewport,
+    /// Content-box width, the percentage basis for item margins and padding
+    width: f32,
+}
+
+struct GridItem {
+    content: ItemContent,
+    style: Arc<ComputedStyle>,
+    /// Row and column track ranges
+    area: [Range<usize>; 2],
+    justify: Alignment,
+    align: Alignment,
+    /// Border-box width, once the columns are sized
+    width: f32,
+    layout_box: Option<LayoutBox>,
+}
+
+impl LayoutEngine {
+    /// Lay out the children of a grid container whose content box starts at
+    /// (`content_x`, `content_y`). Returns the item boxes and the height of
+    /// the grid.
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn layout_grid_items(
+        &self,
+        dom: &Dom,
+        styles: &ComputedStyles,
+        node_id: NodeId,
+        content_x: f32,
+        content_y: f32,
+        content_width: f32,
+        content_height: Option<f32>,
+        exclude_tags: &[&str],
+        viewport: &Viewport,
+        font_manager: &mut FontManager,
+    ) -> (Vec<LayoutBox>, f32) {
+        let style = styles.get(node_id).clone();
+        let container = GridContainer { dom, styles, exclude_tags, viewport, width: content_width };
+
+        // Under a max-content probe (see `intrinsic_widths`) columns take their max-content size
+        let available = [content_height, (content_width < PROBE_WIDTH / 2.0).then_some(content_width)];
+        let (row_gap, column_gap) = gaps(&style);
+        let gap = [
+            row_gap.and_then(|gap| definite_height(&gap, content_height)).unwrap_or(0.0),
+            column_gap.map_or(0.0, |gap| gap.resolve(content_width)),
+        ];
+
+        // Step 1: The explicit grid, from the templates and named areas
+        let mut lists = [("grid-template-rows", ROWS), ("grid-template-columns", COLUMNS)].map(|(property, axis)| {
+            let entries = style.get(property).map(|value| parse_track_list(value, &style)).unwrap_or_default();
+            TrackList::new(&entries, available[axis], gap[axis])
+        });
+        let auto_sizes = ["grid-auto-rows", "grid-auto-columns"].map(|property| {
+            let sizes: Vec<TrackSize> = style
+                .get(property)
+                .map(|value| parse_track_list(value, &style))
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|entry| match entry {
+                    TrackEntry::Size(size) => Some(size),
+                    _ => None,
+                })
+                .collect();
+            if sizes.is_empty() { vec![TrackSize::auto()] } else { sizes }
+        });
+        if let Some((areas, rows, columns)) = style.get("grid-template-areas").and_then(parse_areas) {
+            for (axis, count) in [(ROWS, rows), (COLUMNS, columns)] {
+                while lists[axis].sizes.len() < count {
+                    let size = auto_sizes[axis][(lists[axis].sizes.len()) % auto_sizes[axis].len()].clone();
+                    lists[axis].push_track(size);
+                }
+            }
+            for area in &areas {
+                for (axis, range) in [(ROWS, &area.rows), (COLUMNS, &area.columns)] {
+                    lists[axis].line_names[range.start].push(format!("{}-start", area.name));
+                    lists[axis].line_names[range.end].push(format!("{}-end", area.name));
+                }
+            }
+        }
+
+        // Step 2: Place the items
+        let contents = child_items(dom, styles, node_id, exclude_tags);
+        let item_styles: Vec<Arc<ComputedStyle>> = contents
+            .iter()
+            .map(|content| match content {
+                ItemContent::Element(id) => styles.get(*id).clone(),
+                ItemContent::Text(_) => Arc::default(),
+            })
+            .collect();
+        let requests: Vec<[(Option<i32>, usize); 2]> = item_styles
+            .iter()
+            .map(|item_style| {
+                let lines = placement(item_style);
+                [ROWS, COLUMNS].map(|axis| resolve_axis(&lines[axis], &lines[axis + 2], &lists[axis].line_names))
+            })
+            .collect();
+        let flow = style.get("grid-auto-flow").unwrap_or_default().to_ascii_lowercase();
+        let explicit = [lists[ROWS].sizes.len(), lists[COLUMNS].sizes.len()];
+        let (areas, counts, leading) = place_items(&requests, explicit, flow.contains("column"), flow.contains("dense"));
+
+        layout_log(&format!(
+            "layout_grid: {} items in {}x{} tracks ({} leading rows, {} leading columns)",
+            areas.len(), counts[ROWS], counts[COLUMNS], leading[ROWS], leading[COLUMNS]
+        ));
+
+        let mut tracks = [ROWS, COLUMNS].map(|axis| {
+            let auto = &auto_sizes[axis];
+            (0..counts[axis])
+                .map(|index| {
+                    let size = match index.checked_sub(leading[axis]) {
+                        // Implicit tracks before the explicit grid repeat the auto sizes backwards
+                        None => &auto[auto.len() - 1 - (leading[axis] - 1 - index) % auto.len()],
+                        Some(explicit_index) if explicit_index < explicit[axis] => &lists[axis].sizes[explicit_index],
+                        Some(explicit_index) => &auto[(explicit_index - explicit[axis]) % auto.len()],
+                    };
+                    let collapsed = lists[axis].auto_fit.as_ref().is_some_and(|auto_fit| {
+                        index >= leading[axis]
+                            && auto_fit.contains(&(index - leading[axis]))
+                            && !areas.iter().any(|area| area[axis].contains(&index))
+                    });
+                    if collapsed {
+                        Track::collapsed()
+                    } else {
+                        Track::new(size, available[axis])
+                    }
+                })
+                .collect::<Vec<Track>>()
+        });
+
+        let justify_items = Alignment::parse(style.get("justify-items")).unwrap_or(Alignment::Stretch);
+        let align_items = Alignment::parse(style.get("align-items")).unwrap_or(Alignment::Stretch);
+        let mut items: Vec<GridItem> = contents
+            .into_iter()
+            .zip(item_styles)
+            .zip(areas)
+            .map(|((content, item_style), area)| GridItem {
+                justify: Alignment::parse(item_style.get("justify-self")).unwrap_or(justify_items),
+                align: Alignment::parse(item_style.get("align-self")).unwrap_or(align_items),
+                content,
+                style: item_style,
+                area,
+                width: 0.0,
+                layout_box: None,
+            })
+            .collect();
+
+        // Step 3: Size the columns from the items' min-content and max-content widths
+        let justify_content = Distribution::parse(style.get("justify-content"), Distribution::Stretch);
+        let mut contributions = Vec::with_capacity(items.len());
+        for item in &items {
+            let margin = item.style.margin.resolve(content_width);
+            let pb = item.style.padding_border(content_width);
+            let (min, max) = match &item.style.width {
+                Some(width) => {
+                    let width = width.resolve(content_width) + pb.left + pb.right;
+                    (width, width)
+                }
+                None => self.grid_item_intrinsic_widths(&container, &item.content, font_manager),
+            };
+            let margins = margin.left + margin.right;
+            contributions.push(Contribution { tracks: item.area[COLUMNS].clone(), min: min + margins, max: max + margins });
+        }
+        size_tracks(
+            &mut tracks[COLUMNS], &contributions,
+            available[COLUMNS], gap[COLUMNS], justify_content == Distribution::Stretch,
+        );
+        let (column_offsets, _) = track_offsets(&tracks[COLUMNS], available[COLUMNS], gap[COLUMNS], justify_content);
+
+        // Step 4: Fit each item to its columns, then size the rows from the items' heights
+        let align_content = Distribution::parse(style.get("align-content"), Distribution::Stretch);
+        let mut contributions = Vec::with_capacity(items.len());
+        for item in &mut items {
+            let margin = item.style.margin.resolve(content_width);
+            let pb = item.style.padding_border(content_width);
+            let (_, area_width) = span_extent(&tracks[COLUMNS], &column_offsets, &item.area[COLUMNS]);
+            let room = (area_width - margin.left - margin.right).max(0.0);
+            let (_, right_auto, _, left_auto) = item.style.margin_auto;
+            item.width = match &item.style.width {
+                Some(width) => width.resolve(content_width) + pb.left + pb.right,
+                None if item.justify == Alignment::Stretch && !left_auto && !right_auto => room,
+                None => {
+                    let (min, max) = self.grid_item_intrinsic_widths(&container, &item.content, font_manager);
+                    max.min(room).max(min)
+                }
+            };
+            let layout_box = self.layout_grid_item(&container, &item.content, item.width, None, font_manager);
+            let height = layout_box.dimensions.height + margin.top + margin.bottom;
+            contributions.push(Contribution { tracks: item.area[ROWS].clone(), min: height, max: height });
+            item.layout_box = Some(layout_box);
+        }
+        size_tracks(
+            &mut tracks[ROWS], &contributions,
+            available[ROWS], gap[ROWS], align_content == Distribution::Stretch,
+        );
+        let (row_offsets, height) = track_offsets(&tracks[ROWS], available[ROWS], gap[ROWS], align_content);
+
+        // Step 5: Stretch and align each item within its area
+        let mut boxes = Vec::with_capacity(items.len());
+        for item in &mut items {
+            let margin = item.style.margin.resolve(content_width);
+            let (top_auto, right_auto, bottom_auto, left_auto) = item.style.margin_auto;
+            let (area_x, area_width) = span_extent(&tracks[COLUMNS], &column_offsets, &item.area[COLUMNS]);
+            let (area_y, area_height) = span_extent(&tracks[ROWS], &row_offsets, &item.area[ROWS]);
+
+            let mut layout_box = item.layout_box.take().expect("grid item was laid out");
+            if item.align == Alignment::Stretch && item.style.height.is_none() && !top_auto && !bottom_auto {
+                let height = (area_height - margin.top - margin.bottom).max(0.0);
+                if (height - layout_box.dimensions.height).abs() > 0.01 {
+                    layout_box = self.layout_grid_item(&container, &item.content, item.width, Some(height), font_manager);
+                }
+            }
+
+            let (width, height) = (layout_box.dimensions.width, layout_box.dimensions.height);
+            let x = area_x + margin.left + match (left_auto, right_auto) {
+                (true, true) => (area_width - margin.left - margin.right - width) / 2.0,
+                (true, false) => area_width - margin.left - margin.right - width,
+                (false, true) => 0.0,
+                (false, false) => item.justify.offset(area_width - margin.left - margin.right, width),
+            };
+            let y = area_y + margin.top + match (top_auto, bottom_auto) {
+                (true, true) => (area_height - margin.top - margin.bottom - height) / 2.0,
+                (true, false) => area_height - margin.top - margin.bottom - height,
+                (false, true) => 0.0,
+                (false, false) => item.align.offset(area_height - margin.top - margin.bottom, height),
+            };
+            let (dx, dy) = (content_x + x - layout_box.dimensions.x, content_y + y - layout_box.dimensions.y);
+            translate(&mut layout_box, dx, dy);
+            boxes.push(layout_box);
+        }
+
+        (boxes, height)
+    }
+
+    /// Border-box min-content and max-content widths of an item
+    fn grid_item_intrinsic_widths(
+        &self,
+        container: &GridContainer,
+        content: &ItemContent,
+        font_manager: &mut FontManager,
+    ) -> (f32, f32) {
+        match content {
+            ItemContent::Element(id) => self.intrinsic_widths(
+                container.dom, container.styles, *id, container.width, container.viewport, font_manager,
+            ),
+            ItemContent::Text(nodes) => {
+                let probe = self.layout_inline_line(
+                    container.dom, container.styles, nodes,
+                    0.0, 0.0, PROBE_WIDTH,
+                    &FloatContext::default(), container.exclude_tags, font_manager,
+                );
+                self.probe_intrinsic_widths(&probe, container.width, font_manager)
+            }
+        }
+    }
+
+    /// Lay out an item with its border box at the origin
+    fn layout_grid_item(
+        &self,
+        container: &GridContainer,
+        content: &ItemContent,
+        width: f32,
+        height: Option<f32>,
+        font_manager: &mut FontManager,
+    ) -> LayoutBox {
+        match content {
+            ItemContent::Element(id) => self.layout_block_sized(
+                container.dom, container.styles, *id,
+                0.0, 0.0, container.width,
+                container.exclude_tags, container.viewport, font_manager,
+                UsedSize { width: Some(width), height },
+                None,
+            ),
+            ItemContent::Text(nodes) => {
+                let mut line = self.layout_inline_line(
+                    container.dom, container.styles, nodes,
+                    0.0, 0.0, width,
+                    &FloatContext::default(), container.exclude_tags, font_manager,
+                );
+                line.dimensions.width = width;
+                if let Some(height) = height {
+                    line.dimensions.height = height;
+                }
+                line
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::html::tree_builder::HtmlParser;
+    use crate::style::Stylesheet;
+
+    fn layout_html(html: &str) -> (Dom, LayoutBox) {
+        let dom = HtmlParser::new(html).parse();
+        let root = LayoutEngine::new().layout(&dom, &Stylesheet::new());
+        (dom, root)
+    }
+
+    /// Position of the box with `id` relative to the box with id `grid`
+    fn rect(dom: &Dom, root: &LayoutBox, id: &str) -> (f32, f32, f32, f32) {
+        fn search<'a>(dom: &Dom, b: &'a LayoutBox, id: &str) -> Option<&'a LayoutBox> {
+            if b.text_content.is_none() && dom.get_attribute(b.node_id, "id") == Some(id) {
+                return Some(b);
+            }
+            b.children.iter().find_map(|child| search(dom, child, id))
+        }
+        let grid = search(dom, root, "grid").expect("no grid").dimensions;
+        let d = &search(dom, root, id).unwrap_or_else(|| panic!("no box with id {}", id)).dimensions;
+        (d.x - grid.x, d.y - grid.y, d.width, d.height)
+    }
+
+    #[test]
+    fn test_auto_fill_minmax_columns() {
+        let (dom, root) = layout_html(
+            "<div id=grid style=\"display:grid; width:350px; grid-template-columns:repeat(auto-fill, minmax(100px, 1fr)); grid-auto-rows:40px\">\
+             <div id=a></div><div id=b></div><div id=c></div><div id=d></div></div>",
+        );
+        let third = 350.0 / 3.0;
+        assert_eq!(rect(&dom, &root, "a"), (0.0, 0.0, third, 40.0));
+        assert_eq!(rect(&dom, &root, "c").0, 2.0 * third);
+        assert_eq!(rect(&dom, &root, "d"), (0.0, 40.0, third, 40.0));
+    }
+
+    #[test]
+    fn test_named_areas() {
+        let (dom, root) = layout_html(
+            "<div id=grid style=\"display:grid; width:400px; grid-template-columns:100px 1fr; \
+             grid-template-rows:50px 80px; grid-template-areas:'head head' 'side main'\">\
+             <div id=main style=\"grid-area:main\"></div>\
+             <div id=head style=\"grid-area:head\"></div>\
+             <div id=side style=\"grid-column:side-start; grid-row:2\"></div></div>",
+        );
+        assert_eq!(rect(&dom, &root, "head"), (0.0, 0.0, 400.0, 50.0));
+        assert_eq!(rect(&dom, &root, "main"), (100.0, 50.0, 300.0, 80.0));
+        assert_eq!(rect(&dom, &root, "side"), (0.0, 50.0, 100.0, 80.0));
+    }
+
+    #[test]
+    fn test_fr_tracks_and_gap() {
+        let (dom, root) = layout_html(
+            "<div id=grid style=\"display:grid; width:310px; height:200px; grid-template-columns:1fr 2fr; \
+             grid-template-rows:1fr 3fr; gap:10px\">\
+             <div id=a></div><div id=b></div><div id=c></div></div>",
+        );
+        assert_eq!(rect(&dom, &root, "a"), (0.0, 0.0, 100.0, 47.5));
+        assert_eq!(rect(&dom, &root, "b"), (110.0, 0.0, 200.0, 47.5));
+        assert_eq!(rect(&dom, &root, "c"), (0.0, 57.5, 100.0, 142.5));
+    }
+
+    #[test]
+    fn test_line_placement_creates_implicit_tracks() {
+        let (dom, root) = layout_html(
+            "<div id=grid style=\"display:grid; width:300px; grid-template-columns:100px 100px; \
+             grid-auto-columns:50px; grid-auto-rows:30px\">\
+             <div id=a style=\"grid-column:2 / span 2; grid-row:2\"></div>\
+             <div id=b style=\"grid-column:-2\"></div>\
+             <div id=c></div><div id=d></div></div>",
+        );
+        assert_eq!(rect(&dom, &root, "a"), (100.0, 30.0, 150.0, 30.0));
+        // -2 is the last explicit column line's predecessor: the second column
+        assert_eq!(rect(&dom, &root, "b"), (100.0, 0.0, 100.0, 30.0));
+        // Auto-placed items fill the cells left around the placed ones
+        assert_eq!(rect(&dom, &root, "c"), (200.0, 0.0, 50.0, 30.0));
+        assert_eq!(rect(&dom, &root, "d"), (0.0, 30.0, 100.0, 30.0));
+    }
+
+    #[test]
+    fn test_column_flow_dense_and_alignment() {
+        let (dom, root) = layout_html(
+            "<div id=grid style=\"display:grid; width:200px; grid-template-columns:100px 100px; \
+             grid-template-rows:40px 40px; grid-auto-flow:column dense; justify-items:center\">\
+             <div id=a style=\"width:20px\"></div>\
+             <div id=b style=\"grid-row:span 2; width:20px; align-self:end; height:10px\"></div>\
+             <div id=c style=\"width:20px\"></div></div>",
+        );
+        assert_eq!(rect(&dom, &root, "a"), (40.0, 0.0, 20.0, 40.0));
+        assert_eq!(rect(&dom, &root, "b"), (140.0, 70.0, 20.0, 10.0));
+        // Dense packing fills the hole under a
+        assert_eq!(rect(&dom, &root, "c"), (40.0, 40.0, 20.0, 40.0));
+    }
+}
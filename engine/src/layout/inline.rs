@@ -0,0 +1,745 @@
+// engine/src/layout/inline.rs
+// Inline formatting: white space, line breaking and line boxes
+//
+// - A block's run of inline children is flattened into one paragraph of
+//   items: text, atomic boxes (images) and forced breaks (`<br>` and
+//   preserved newlines), each with the inline elements it sits in
+// - `white-space` decides per text node whether spaces and tabs collapse
+//   (also across element boundaries, so `a <b>b</b> c` keeps one space on
+//   each side of the b), whether newlines force breaks and whether lines wrap
+// - Lines may end at UAX #14 break opportunities. `word-break: keep-all`
+//   removes those between letters, `break-all` and `overflow-wrap` split words
+//   between glyph clusters, and `hyphens` decides whether soft hyphens
+//   (U+00AD) are opportunities; a line ending at one shows a hyphen
+// - Collapsible spaces at the end of a line hang past it and are not drawn
+// - Every line gets its own fragment of each inline element on it, so element
+//   backgrounds and links cover only their part of the line
+
+use std::ops::Range;
+use std::sync::Arc;
+
+use unicode_linebreak::linebreaks;
+
+use crate::dom::{Dom, NodeId, NodeType};
+use crate::font::FontManager;
+use crate::style::{ComputedStyle, ComputedStyles, Display, Hyphens, OverflowWrap, WhiteSpace, WordBreak};
+
+use super::float::FloatContext;
+use super::{layout_log, text_log, translate, BoxType, Dimensions, LayoutBox, LayoutEngine};
+
+/// Stands in for an atomic inline in the paragraph text; UAX #14 allows a
+/// break on either side of it
+const OBJECT_REPLACEMENT: &str = "\u{FFFC}";
+
+const SOFT_HYPHEN: char = '\u{AD}';
+
+/// Drawn at the end of a line that breaks at a soft hyphen
+const HYPHEN: &str = "-";
+
+/// Columns between tab stops in preserved white space
+const TAB_SIZE: usize = 8;
+
+enum ItemKind {
+    Text,
+    /// A box placed as a unit, laid out at the origin
+    Atomic(LayoutBox),
+    /// A `<br>` or preserved newline
+    Break,
+}
+
+/// One piece of a paragraph
+struct Item {
+    /// The text node, image or `<br>` element
+    node_id: NodeId,
+    style: Arc<ComputedStyle>,
+    kind: ItemKind,
+    /// Inline elements the item is inside, outermost first
+    ancestors: Vec<NodeId>,
+    /// Where the item is in the paragraph text
+    range: Range<usize>,
+}
+
+impl Item {
+    fn height(&self) -> f32 {
+        match &self.kind {
+            ItemKind::Atomic(atomic) => atomic.dimensions.height,
+            _ => self.style.line_height_px(),
+        }
+    }
+}
+
+/// The inline content of a block as one string, white space already
+/// processed, and the items it was made from
+struct Paragraph {
+    text: String,
+    items: Vec<Item>,
+}
+
+impl Paragraph {
+    fn new(dom: &Dom, styles: &ComputedStyles, nodes: &[NodeId], exclude_tags: &[&str], width: f32) -> Paragraph {
+        let mut paragraph = Paragraph { text: String::new(), items: Vec::new() };
+        // Collapsible spaces at the start of the paragraph are removed
+        let mut after_space = true;
+        for &node_id in nodes {
+            paragraph.collect(dom, styles, node_id, &mut Vec::new(), exclude_tags, width, &mut after_space);
+        }
+        paragraph
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn collect(
+        &mut self,
+        dom: &Dom,
+        styles: &ComputedStyles,
+        node_id: NodeId,
+        ancestors: &mut Vec<NodeId>,
+        exclude_tags: &[&str],
+        width: f32,
+        after_space: &mut bool,
+    ) {
+        let style = styles.get(node_id).clone();
+        match &dom.nodes[node_id].node_type {
+            NodeType::Text(text) => {
+                let text = collapse_white_space(text, style.white_space, after_space);
+                for (index, line) in text.split('\n').enumerate() {
+                    if index > 0 {
+                        self.push(node_id, style.clone(), ItemKind::Break, ancestors, "\n");
+                    }
+                    if !line.is_empty() {
+                        self.push(node_id, style.clone(), ItemKind::Text, ancestors, line);
+                    }
+                }
+            }
+            NodeType::Element(el) => {
+                if exclude_tags.contains(&el.tag_name.as_str()) || style.display == Display::None {
+                    return;
+                }
+                match el.tag_name.as_str() {
+                    "br" => {
+                        *after_space = true;
+                        self.push(node_id, style, ItemKind::Break, ancestors, "\n");
+                    }
+                    "img" => {
+                        *after_space = false;
+                        let image = LayoutBox {
+                            node_id,
+                            box_type: BoxType::Inline,
+                            dimensions: Dimensions { x: 0.0, y: 0.0, width: 100.0_f32.min(width), height: 80.0 },
+                            style: style.clone(),
+                            children: vec![],
+                            text_content: None,
+                        };
+                        self.push(node_id, style, ItemKind::Atomic(image), ancestors, OBJECT_REPLACEMENT);
+                    }
+                    _ => {
+                        ancestors.push(node_id);
+                        for &child in &dom.nodes[node_id].children {
+                            self.collect(dom, styles, child, ancestors, exclude_tags, width, after_space);
+                        }
+                        ancestors.pop();
+                    }
+                }
+            }
+        }
+    }
+
+    fn push(&mut self, node_id: NodeId, style: Arc<ComputedStyle>, kind: ItemKind, ancestors: &[NodeId], text: &str) {
+        let start = self.text.len();
+        self.text.push_str(text);
+        self.items.push(Item { node_id, style, kind, ancestors: ancestors.to_vec(), range: start..self.text.len() });
+    }
+
+    /// Index of the item holding the byte at `offset`
+    fn item_at(&self, offset: usize) -> usize {
+        self.items.partition_point(|item| item.range.end <= offset)
+    }
+
+    /// Offsets where a line may end, after the last one included
+    fn break_opportunities(&self) -> Vec<usize> {
+        let mut offsets: Vec<usize> = linebreaks(&self.text)
+            .map(|(offset, _)| offset)
+            .filter(|&offset| offset > 0 && offset < self.text.len())
+            .filter(|&offset| {
+                let style = &self.items[self.item_at(offset - 1)].style;
+                allows_break(&self.text, offset, style)
+            })
+            .collect();
+        offsets.push(self.text.len());
+        offsets
+    }
+
+    /// The parts of `range` in each item, as (item, range)
+    fn pieces(&self, range: Range<usize>) -> Vec<(usize, Range<usize>)> {
+        let mut pieces = Vec::new();
+        let mut index = self.item_at(range.start);
+        while let Some(item) = self.items.get(index).filter(|item| item.range.start < range.end) {
+            pieces.push((index, item.range.start.max(range.start)..item.range.end.min(range.end)));
+            index += 1;
+        }
+        pieces
+    }
+}
+
+/// Apply `white_space` to a text node's text. Collapsible spaces right after
+/// `after_space`, which carries over between text nodes, are dropped.
+/// Newlines left in the result are forced breaks.
+fn collapse_white_space(text: &str, white_space: WhiteSpace, after_space: &mut bool) -> String {
+    let text = text.replace("\r\n", "\n").replace('\r', "\n");
+    let mut result = String::with_capacity(text.len());
+    let mut column = 0;
+    for c in text.chars() {
+        match c {
+            '\n' if white_space.preserves_newlines() => {
+                // pre-line removes the spaces around a newline
+                if white_space.collapses_spaces() {
+                    result.truncate(result.trim_end_matches(' ').len());
+                }
+                result.push('\n');
+                column = 0;
+                *after_space = true;
+            }
+            ' ' | '\t' | '\n' | '\x0c' if white_space.collapses_spaces() => {
+                if !*after_space {
+                    result.push(' ');
+                    *after_space = true;
+                }
+            }
+            '\t' => {
+                let spaces = TAB_SIZE - column % TAB_SIZE;
+                result.extend(std::iter::repeat_n(' ', spaces));
+                column += spaces;
+                *after_space = false;
+            }
+            _ => {
+                result.push(c);
+                column += 1;
+                *after_space = false;
+            }
+        }
+    }
+    result
+}
+
+/// Whether `style`, the style of the text before `offset`, lets a line end
+/// at a UAX #14 break opportunity there
+fn allows_break(text: &str, offset: usize, style: &ComputedStyle) -> bool {
+    let before = text[..offset].chars().next_back();
+    let after = text[offset..].chars().next();
+    match before {
+        Some('\n') => true,
+        _ if !style.white_space.wraps() => false,
+        Some(SOFT_HYPHEN) => style.hyphens != Hyphens::None,
+        Some(before) if style.word_break == WordBreak::KeepAll => {
+            !(before.is_alphanumeric() && after.is_some_and(char::is_alphanumeric))
+        }
+        _ => true,
+    }
+}
+
+/// Whether a word in `style` may be split between any two clusters when it
+/// does not fit
+fn splits_words(style: &ComputedStyle) -> bool {
+    style.overflow_wrap != OverflowWrap::Normal || style.word_break == WordBreak::BreakAll
+}
+
+fn measure(text: &str, style: &ComputedStyle, font_manager: &mut FontManager) -> f32 {
+    font_manager.measure_text(text, &style.font_family, style.font_size, style.font_weight, style.is_italic())
+}
+
+/// Whether spaces at the end of a line in `style` hang instead of taking room
+fn hangs_spaces(style: &ComputedStyle) -> bool {
+    style.white_space != WhiteSpace::Pre
+}
+
+/// Min-content contribution of a text box's `text`: its widest unbreakable
+/// piece, without hanging spaces
+pub(super) fn min_content_width(text: &str, style: &ComputedStyle, font_manager: &mut FontManager) -> f32 {
+    if style.white_space.wraps()
+        && (style.overflow_wrap == OverflowWrap::Anywhere || style.word_break == WordBreak::BreakAll)
+    {
+        let mut buffer = [0; 4];
+        return text
+            .chars()
+            .map(|c| measure(c.encode_utf8(&mut buffer), style, font_manager))
+            .fold(0.0, f32::max);
+    }
+    let mut widest = 0.0_f32;
+    let mut start = 0;
+    let ends = linebreaks(text)
+        .map(|(offset, _)| offset)
+        .filter(|&offset| offset == text.len() || (offset > 0 && allows_break(text, offset, style)));
+    for end in ends {
+        let piece = &text[start..end];
+        let piece = if hangs_spaces(style) { piece.trim_end_matches(' ') } else { piece };
+        let mut width = measure(piece, style, font_manager);
+        if piece.ends_with(SOFT_HYPHEN) && end < text.len() {
+            width += measure(HYPHEN, style, font_manager);
+        }
+        widest = widest.max(width);
+        start = end;
+    }
+    widest
+}
+
+/// Why a line ends
+#[derive(Debug, Clone, Copy)]
+enum LineEnd {
+    /// The next piece does not fit
+    Wrap,
+    /// At a forced break, the item
+    Forced(usize),
+    /// The paragraph ends
+    Last,
+}
+
+/// Part of an item placed on the current line
+struct Placed {
+    item: usize,
+    /// Range of the paragraph text; the replacement character for atomic items
+    range: Range<usize>,
+    x: f32,
+    width: f32,
+    /// The line ends at a soft hyphen in this text and shows a hyphen
+    hyphen: bool,
+}
+
+/// Greedy line breaking of one paragraph around floats
+struct LineBuilder<'a> {
+    paragraph: &'a Paragraph,
+    styles: &'a ComputedStyles,
+    floats: &'a FloatContext,
+    /// Left edge and width of the block's content box
+    left: f32,
+    width: f32,
+    /// Top of the current line
+    y: f32,
+    /// The part of the current line that floats leave
+    start: f32,
+    end: f32,
+    /// Where the next piece goes
+    pen: f32,
+    placed: Vec<Placed>,
+    /// Boxes of the finished lines
+    boxes: Vec<LayoutBox>,
+}
+
+impl LineBuilder<'_> {
+    fn is_line_empty(&self) -> bool {
+        self.placed.is_empty()
+    }
+
+    fn width_of(&self, item: usize, range: &Range<usize>, font_manager: &mut FontManager) -> f32 {
+        let item = &self.paragraph.items[item];
+        match &item.kind {
+            ItemKind::Text => measure(&self.paragraph.text[range.clone()], &item.style, font_manager),
+            ItemKind::Atomic(atomic) => atomic.dimensions.width,
+            ItemKind::Break => 0.0,
+        }
+    }
+
+    /// Lay out the text between two break opportunities
+    fn add_segment(&mut self, range: Range<usize>, font_manager: &mut FontManager) {
+        let paragraph = self.paragraph;
+        let pieces = paragraph.pieces(range);
+        let Some((last, last_range)) = pieces.last().cloned() else {
+            return;
+        };
+        let widths: Vec<f32> = pieces.iter().map(|(item, range)| self.width_of(*item, range, font_manager)).collect();
+        let width: f32 = widths.iter().sum();
+
+        // Room the segment needs when the line ends after it: trailing
+        // spaces hang, and a soft hyphen turns into a hyphen
+        let last_item = &paragraph.items[last];
+        let mut needed = width;
+        if matches!(last_item.kind, ItemKind::Text) {
+            let text = &paragraph.text[last_range];
+            if hangs_spaces(&last_item.style) && text.ends_with(' ') {
+                needed -= widths[widths.len() - 1] - measure(text.trim_end_matches(' '), &last_item.style, font_manager);
+            }
+            if text.ends_with(SOFT_HYPHEN) {
+                needed += measure(HYPHEN, &last_item.style, font_manager);
+            }
+        }
+        let first = &paragraph.items[pieces[0].0];
+        let height = first.height();
+
+        // An empty line too narrow beside floats moves down past them
+        if self.is_line_empty() && self.pen + needed > self.end {
+            (self.y, self.start, self.end) = self.floats.fit(self.y, height, self.left, self.left + self.width, needed);
+            self.pen = self.start;
+        }
+
+        let splits = pieces.iter().any(|(item, _)| splits_words(&paragraph.items[*item].style));
+        if self.pen + needed > self.end && !self.is_line_empty() {
+            // break-all fills the line before splitting; otherwise the
+            // segment first tries a line of its own
+            if first.style.word_break == WordBreak::BreakAll {
+                self.place_split(&pieces, font_manager);
+                return;
+            }
+            text_log(&format!("  wrap before {:?}", &paragraph.text[pieces[0].1.clone()]));
+            self.finish_line(LineEnd::Wrap, font_manager);
+            (self.y, self.start, self.end) = self.floats.fit(self.y, height, self.left, self.left + self.width, needed);
+            self.pen = self.start;
+        }
+
+        if self.pen + needed > self.end && splits {
+            self.place_split(&pieces, font_manager);
+            return;
+        }
+        for ((item, range), width) in pieces.into_iter().zip(widths) {
+            self.place(item, range, width, font_manager);
+        }
+    }
+
+    /// Place a piece at the pen; a break ends the line
+    fn place(&mut self, item: usize, range: Range<usize>, width: f32, font_manager: &mut FontManager) {
+        if matches!(self.paragraph.items[item].kind, ItemKind::Break) {
+            self.finish_line(LineEnd::Forced(item), font_manager);
+            return;
+        }
+        match self.placed.last_mut() {
+            Some(last) if last.item == item && last.range.end == range.start => {
+                last.range.end = range.end;
+                last.width += width;
+            }
+            _ => self.placed.push(Placed { item, range, x: self.pen, width, hyphen: false }),
+        }
+        self.pen += width;
+    }
+
+    /// Place a segment too wide for its line, splitting its text between
+    /// glyph clusters where the style allows
+    fn place_split(&mut self, pieces: &[(usize, Range<usize>)], font_manager: &mut FontManager) {
+        for (item, range) in pieces.iter().cloned() {
+            let style = self.paragraph.items[item].style.clone();
+            let mut range = range;
+            loop {
+                let width = self.width_of(item, &range, font_manager);
+                let available = self.end - self.pen;
+                let splittable = matches!(self.paragraph.items[item].kind, ItemKind::Text) && splits_words(&style);
+                if width <= available || !splittable {
+                    if width > available && !self.is_line_empty() {
+                        self.finish_line(LineEnd::Wrap, font_manager);
+                    }
+                    self.place(item, range, width, font_manager);
+                    break;
+                }
+                let text = &self.paragraph.text[range.clone()];
+                let run = font_manager.shape(text, &style.font_family, style.font_size, style.font_weight, style.is_italic());
+                let head = range.start..range.start + run.fit(text, available);
+                let head_width = self.width_of(item, &head, font_manager);
+                // Even the first cluster is too wide: try it on a new line
+                if head_width > available && !self.is_line_empty() {
+                    self.finish_line(LineEnd::Wrap, font_manager);
+                    continue;
+                }
+                range.start = head.end;
+                self.place(item, head, head_width, font_manager);
+                if range.is_empty() {
+                    break;
+                }
+                self.finish_line(LineEnd::Wrap, font_manager);
+            }
+        }
+    }
+
+    /// End the current line and move to the next
+    fn finish_line(&mut self, end: LineEnd, font_manager: &mut FontManager) {
+        let paragraph = self.paragraph;
+
+        // Trailing spaces hang, so they are neither drawn nor counted
+        while let Some(last) = self.placed.last_mut() {
+            let item = &paragraph.items[last.item];
+            if !matches!(item.kind, ItemKind::Text) || !hangs_spaces(&item.style) {
+                break;
+            }
+            let text = &paragraph.text[last.range.clone()];
+            let trimmed = text.trim_end_matches(' ');
+            if trimmed.len() == text.len() {
+                break;
+            }
+            if trimmed.is_empty() {
+                self.placed.pop();
+                continue;
+            }
+            last.range.end = last.range.start + trimmed.len();
+            last.width = measure(trimmed, &item.style, font_manager);
+            break;
+        }
+        if let Some(last) = self.placed.last_mut() {
+            let item = &paragraph.items[last.item];
+            let at_soft_hyphen = paragraph.text[last.range.clone()].ends_with(SOFT_HYPHEN);
+            if matches!(end, LineEnd::Wrap) && at_soft_hyphen && item.style.hyphens != Hyphens::None {
+                last.hyphen = true;
+                last.width += measure(HYPHEN, &item.style, font_manager);
+            }
+        }
+
+        // A forced break gives even an empty line its line height
+        let strut = match end {
+            LineEnd::Forced(item) => paragraph.items[item].height(),
+            _ => 0.0,
+        };
+        let height = self.placed.iter().map(|p| paragraph.items[p.item].height()).fold(strut, f32::max);
+        let placed = std::mem::take(&mut self.placed);
+        self.boxes.extend(self.fragments(placed));
+
+        self.y += height;
+        (self.start, self.end) = self.floats.available(self.y, 0.0, self.left, self.left + self.width);
+        self.pen = self.start;
+    }
+
+    /// Boxes for the pieces of one line, nested in fragments of the inline
+    /// elements they are in
+    fn fragments(&self, placed: Vec<Placed>) -> Vec<LayoutBox> {
+        let paragraph = self.paragraph;
+        let mut line = Vec::new();
+        let mut open: Vec<(NodeId, Vec<LayoutBox>)> = Vec::new();
+        for piece in placed {
+            let item = &paragraph.items[piece.item];
+            let shared = open
+                .iter()
+                .zip(&item.ancestors)
+                .take_while(|((open_id, _), ancestor)| open_id == *ancestor)
+                .count();
+            while open.len() > shared {
+                self.close_fragment(&mut open, &mut line);
+            }
+            open.extend(item.ancestors[shared..].iter().map(|&ancestor| (ancestor, Vec::new())));
+
+            let leaf = match &item.kind {
+                ItemKind::Atomic(atomic) => {
+                    let mut atomic = atomic.clone();
+                    let (dx, dy) = (piece.x - atomic.dimensions.x, self.y - atomic.dimensions.y);
+                    translate(&mut atomic, dx, dy);
+                    atomic
+                }
+                _ => {
+                    let mut text = paragraph.text[piece.range].to_string();
+                    if piece.hyphen {
+                        text.push_str(HYPHEN);
+                    }
+                    LayoutBox {
+                        node_id: item.node_id,
+                        box_type: BoxType::Inline,
+                        dimensions: Dimensions { x: piece.x, y: self.y, width: piece.width, height: item.height() },
+                        style: item.style.clone(),
+                        children: vec![],
+                        text_content: Some(text),
+                    }
+                }
+            };
+            match open.last_mut() {
+                Some((_, children)) => children.push(leaf),
+                None => line.push(leaf),
+            }
+        }
+        while !open.is_empty() {
+            self.close_fragment(&mut open, &mut line);
+        }
+        line
+    }
+
+    /// Close the innermost open element fragment, sized to its children
+    fn close_fragment(&self, open: &mut Vec<(NodeId, Vec<LayoutBox>)>, line: &mut Vec<LayoutBox>) {
+        let Some((node_id, children)) = open.pop() else {
+            return;
+        };
+        let left = children.iter().map(|b| b.dimensions.x).fold(f32::INFINITY, f32::min);
+        let right = children.iter().map(|b| b.dimensions.x + b.dimensions.width).fold(f32::NEG_INFINITY, f32::max);
+        let height = children.iter().map(|b| b.dimensions.height).fold(0.0, f32::max);
+        let fragment = LayoutBox {
+            node_id,
+            box_type: BoxType::Inline,
+            dimensions: Dimensions { x: left, y: self.y, width: right - left, height },
+            style: self.styles.get(node_id).clone(),
+            children,
+            text_content: None,
+        };
+        match open.last_mut() {
+            Some((_, children)) => children.push(fragment),
+            None => line.push(fragment),
+        }
+    }
+}
+
+impl LayoutEngine {
+    /// Lay out a run of inline children as lines of `width` px starting at
+    /// (`x`, `y`), around `floats`. Returns an anonymous block holding every
+    /// line's boxes, zero-height when nothing is visible.
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn layout_inline_line(
+        &self,
+        dom: &Dom,
+        styles: &ComputedStyles,
+        inline_children: &[NodeId],
+        x: f32,
+        y: f32,
+        width: f32,
+        floats: &FloatContext,
+        exclude_tags: &[&str],
+        font_manager: &mut FontManager,
+    ) -> LayoutBox {
+        text_log(&format!("=== layout_inline_line: x={}, y={}, width={} ===", x, y, width));
+        let paragraph = Paragraph::new(dom, styles, inline_children, exclude_tags, width);
+        let (start, end) = floats.available(y, 0.0, x, x + width);
+        let mut lines = LineBuilder {
+            paragraph: &paragraph,
+            styles,
+            floats,
+            left: x,
+            width,
+            y,
+            start,
+            end,
+            pen: start,
+            placed: Vec::new(),
+            boxes: Vec::new(),
+        };
+        let mut segment_start = 0;
+        for segment_end in paragraph.break_opportunities() {
+            lines.add_segment(segment_start..segment_end, font_manager);
+            segment_start = segment_end;
+        }
+        if !lines.is_line_empty() {
+            lines.finish_line(LineEnd::Last, font_manager);
+        }
+
+        let (children, bottom) = (lines.boxes, lines.y);
+        if bottom <= y {
+            layout_log("  inline_line: no visible content, returning empty box");
+            return LayoutBox {
+                node_id: 0,
+                box_type: BoxType::Block,
+                dimensions: Dimensions { x, y, width: 0.0, height: 0.0 },
+                style: Arc::default(),
+                children: vec![],
+                text_content: None,
+            };
+        }
+        layout_log(&format!("  inline_line: {} children, height={}", children.len(), bottom - y));
+        LayoutBox {
+            node_id: 0,
+            box_type: BoxType::Block,
+            dimensions: Dimensions { x, y, width, height: bottom - y },
+            style: Arc::default(),
+            children,
+            text_content: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::html::tree_builder::HtmlParser;
+    use crate::style::Stylesheet;
+
+    /// Text boxes in paint order, with their rectangles
+    fn text_boxes(html: &str) -> Vec<(String, Dimensions)> {
+        fn collect(b: &LayoutBox, out: &mut Vec<(String, Dimensions)>) {
+            if let Some(text) = &b.text_content {
+                out.push((text.clone(), b.dimensions));
+            }
+            b.children.iter().for_each(|child| collect(child, out));
+        }
+        let dom = HtmlParser::new(html).parse();
+        let root = LayoutEngine::new().layout(&dom, &Stylesheet::new());
+        let mut boxes = Vec::new();
+        collect(&root, &mut boxes);
+        boxes
+    }
+
+    fn texts(boxes: &[(String, Dimensions)]) -> Vec<&str> {
+        boxes.iter().map(|(text, _)| text.as_str()).collect()
+    }
+
+    #[test]
+    fn test_collapse_white_space() {
+        let mut after_space = false;
+        assert_eq!(collapse_white_space("a \n\t b ", WhiteSpace::Normal, &mut after_space), "a b ");
+        assert!(after_space);
+        assert_eq!(collapse_white_space(" c", WhiteSpace::Normal, &mut after_space), "c");
+        assert_eq!(collapse_white_space("a  \n  b", WhiteSpace::PreLine, &mut after_space), "a\nb");
+        assert_eq!(collapse_white_space("a\tb\r\n  c", WhiteSpace::Pre, &mut after_space), "a       b\n  c");
+    }
+
+    #[test]
+    fn test_spaces_collapse_across_elements() {
+        let boxes = text_boxes("<p>Some <b>text</b> <i>in</i>   <u> here </u></p>");
+        assert_eq!(texts(&boxes), ["Some ", "text", " ", "in", " ", "here"]);
+        // Each box starts where the previous one ends, on one line
+        for pair in boxes.windows(2) {
+            let (left, right) = (pair[0].1, pair[1].1);
+            assert!((left.x + left.width - right.x).abs() < 0.01);
+            assert_eq!(left.y, right.y);
+        }
+    }
+
+    #[test]
+    fn test_pre_keeps_spaces_and_newlines() {
+        let boxes = text_boxes("<pre style=\"line-height:20px\">a\n  b\n\nc</pre>");
+        assert_eq!(texts(&boxes), ["a", "  b", "c"]);
+        let top = boxes[0].1.y;
+        assert_eq!(boxes[1].1.y, top + 20.0);
+        assert_eq!(boxes[2].1.y, top + 60.0);
+        assert_eq!(boxes[0].1.x, boxes[1].1.x);
+    }
+
+    #[test]
+    fn test_nowrap_and_br() {
+        let boxes = text_boxes("<div style=\"width:50px; white-space:nowrap\">one two three<br>four</div>");
+        assert_eq!(texts(&boxes), ["one two three", "four"]);
+        assert!(boxes[0].1.width > 50.0);
+        assert!(boxes[1].1.y > boxes[0].1.y);
+    }
+
+    #[test]
+    fn test_long_words_overflow_unless_overflow_wrap() {
+        let word = "Pneumonoultramicroscopicsilicovolcanoconiosis";
+        let boxes = text_boxes(&format!("<div style=\"width:100px\">{}</div>", word));
+        assert_eq!(texts(&boxes), [word]);
+        assert!(boxes[0].1.width > 100.0);
+
+        let boxes = text_boxes(&format!("<div style=\"width:100px; overflow-wrap:anywhere\">{}</div>", word));
+        assert!(boxes.len() > 1);
+        assert_eq!(texts(&boxes).concat(), word);
+        assert!(boxes.iter().all(|(_, rect)| rect.width <= 100.0));
+        assert!(boxes.windows(2).all(|pair| pair[1].1.y > pair[0].1.y));
+    }
+
+    #[test]
+    fn test_soft_hyphens() {
+        let html = |hyphens: &str| {
+            format!("<div style=\"width:70px; hyphens:{}\">extra\u{AD}ordinarily</div>", hyphens)
+        };
+        let boxes = text_boxes(&html("manual"));
+        assert_eq!(texts(&boxes), ["extra\u{AD}-", "ordinarily"]);
+        let boxes = text_boxes(&html("none"));
+        assert_eq!(texts(&boxes), ["extra\u{AD}ordinarily"]);
+    }
+
+    #[test]
+    fn test_break_rules() {
+        let style = |css: &str| {
+            let dom = HtmlParser::new(&format!("<p style=\"{}\">x</p>", css)).parse();
+            let p = dom.nodes.iter().position(|node| matches!(&node.node_type, NodeType::Element(el) if el.tag_name == "p")).unwrap();
+            Stylesheet::new().compute_styles(&dom, &crate::style::Viewport::default()).get(p).clone()
+        };
+        let normal = style("");
+        let keep_all = style("word-break: keep-all");
+        // After a space, and between ideographs unless keep-all
+        assert!(allows_break("a b", 2, &normal));
+        assert!(allows_break("日本", 3, &normal));
+        assert!(!allows_break("日本", 3, &keep_all));
+        assert!(allows_break("日 本", 4, &keep_all));
+        assert!(!allows_break("a b", 2, &style("white-space: pre")));
+
+        let mut font_manager = FontManager::new();
+        // URLs break after slashes, so the longest path part is the widest
+        let word = measure("resource", &normal, &mut font_manager);
+        assert_eq!(min_content_width("a/very/long/resource", &normal, &mut font_manager), word);
+        assert!(min_content_width("resource", &style("word-break: break-all"), &mut font_manager) < word / 4.0);
+    }
+}
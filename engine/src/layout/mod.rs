@@ -17,9 +17,22 @@
 //
 // Key principle: CSS "width" property sets CONTENT width, not border-box width.
 
+mod flex;
+mod float;
+mod grid;
+mod inline;
+mod position;
+mod stacking;
+mod table;
+
 use crate::dom::{Dom, NodeId};
 use crate::font::FontManager;
-use crate::style::{Stylesheet, Style, Viewport};
+use std::sync::Arc;
+
+use crate::style::{ComputedStyle, ComputedStyles, Display, Edges, Float, Length, Stylesheet, Viewport};
+use float::{establishes_bfc, FloatContext};
+
+pub use stacking::{paint_order, PaintStep};
 
 pub const CSS_PX_SCALE: f32 = 1.0;
 pub const BASE_FONT_SIZE: f32 = 16.0;
@@ -49,14 +62,50 @@ fn get_tag_name(dom: &Dom, node_id: NodeId) -> String {
 pub enum BoxType {
     Block,
     Inline,
+    /// A flex container; its children are flex items
+    Flex,
+    /// A grid container; its children are grid items
+    Grid,
 }
 
-#[derive(Debug, Clone)]
+/// Width used to lay out content when measuring its max-content width
+const PROBE_WIDTH: f32 = 100_000.0;
+
+/// Border-box size imposed on a box by its formatting context (a flex or
+/// grid container) rather than derived from `width`/`height`
+#[derive(Debug, Clone, Copy, Default)]
+struct UsedSize {
+    width: Option<f32>,
+    height: Option<f32>,
+}
+
+/// Move a box and all of its descendants
+fn translate(layout_box: &mut LayoutBox, dx: f32, dy: f32) {
+    layout_box.dimensions.x += dx;
+    layout_box.dimensions.y += dy;
+    for child in &mut layout_box.children {
+        translate(child, dx, dy);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Dimensions {
     pub x: f32,       // Border-box x position
     pub y: f32,       // Border-box y position  
-    pub width: f32,   // Border-box width (content + padding)
-    pub height: f32,  // Border-box height (content + padding)
+    pub width: f32,   // Border-box width (content + padding + border)
+    pub height: f32,  // Border-box height (content + padding + border)
+}
+
+impl Dimensions {
+    /// The rectangle inside `edges`, such as the padding box inside the border
+    pub fn shrink(&self, edges: &Edges) -> Dimensions {
+        Dimensions {
+            x: self.x + edges.left,
+            y: self.y + edges.top,
+            width: (self.width - edges.left - edges.right).max(0.0),
+            height: (self.height - edges.top - edges.bottom).max(0.0),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -64,13 +113,26 @@ pub struct LayoutBox {
     pub node_id: NodeId,
     pub box_type: BoxType,
     pub dimensions: Dimensions,
-    pub style: Style,
+    pub style: Arc<ComputedStyle>,
     pub children: Vec<LayoutBox>,
     pub text_content: Option<String>,
 }
 
+impl LayoutBox {
+    /// The border box shrunk by the border. Text boxes share their parent's
+    /// style but have no border of their own.
+    pub fn padding_box(&self) -> Dimensions {
+        match self.text_content {
+            Some(_) => self.dimensions,
+            None => self.dimensions.shrink(&self.style.border_widths()),
+        }
+    }
+}
+
 pub struct LayoutEngine {
     viewport: Viewport,
+    /// How far the viewport is scrolled, for fixed and sticky boxes
+    scroll_offset: (f32, f32),
 }
 
 impl Default for LayoutEngine {
@@ -83,11 +145,18 @@ impl LayoutEngine {
     pub fn new() -> Self {
         Self {
             viewport: Viewport::default(),
+            scroll_offset: (0.0, 0.0),
         }
     }
 
     pub fn with_viewport(viewport: Viewport) -> Self {
-        Self { viewport }
+        Self { viewport, scroll_offset: (0.0, 0.0) }
+    }
+
+    /// Set the document scroll position. Fixed boxes stay put on screen and
+    /// sticky boxes stick relative to it.
+    pub fn set_scroll_offset(&mut self, x: f32, y: f32) {
+        self.scroll_offset = (x, y);
     }
 
     pub fn set_viewport(&mut self, viewport: Viewport) {
@@ -99,8 +168,8 @@ impl LayoutEngine {
     }
     
     /// Measure text width using font manager (accurate)
-    fn measure_text_width(&self, text: &str, font_manager: &mut FontManager, font_family: &str, font_size: f32, bold: bool, italic: bool) -> f32 {
-        font_manager.measure_text(text, font_family, font_size, bold, italic)
+    fn measure_text_width(&self, text: &str, font_manager: &mut FontManager, font_family: &str, font_size: f32, font_weight: u16, italic: bool) -> f32 {
+        font_manager.measure_text(text, font_family, font_size, font_weight, italic)
     }
 
     pub fn layout(&self, dom: &Dom, stylesheet: &Stylesheet) -> LayoutBox {
@@ -109,26 +178,36 @@ impl LayoutEngine {
 
     pub fn layout_with_viewport(&self, dom: &Dom, stylesheet: &Stylesheet, viewport_width: f32) -> LayoutBox {
         let viewport = Viewport::new(viewport_width, self.viewport.height);
-        let root_id = dom.root();
-        let exclude_tags = ["head", "meta", "link", "title", "style", "script", "base", "noscript"];
-        
+        let styles = stylesheet.compute_styles(dom, &viewport);
+
         // Use a temporary font manager for fallback - this path doesn't use accurate text metrics
         let mut font_manager = FontManager::new();
-        let mut root_box = self.layout_root_element(dom, stylesheet, root_id, &viewport, &exclude_tags, &mut font_manager);
-        root_box.dimensions.width = viewport.width;
-        root_box.dimensions.height = root_box.dimensions.height.max(viewport.height);
-        root_box
+        self.layout_with_styles(dom, &styles, viewport, &mut font_manager)
     }
 
     /// Layout with font manager for accurate text measurement
     pub fn layout_with_full_viewport(&self, dom: &Dom, stylesheet: &Stylesheet, viewport: Viewport, font_manager: &mut FontManager) -> LayoutBox {
+        let styles = stylesheet.compute_styles(dom, &viewport);
+        self.layout_with_styles(dom, &styles, viewport, font_manager)
+    }
+
+    /// Layout from styles computed ahead of time by [`Stylesheet::compute_styles`].
+    /// Callers that relayout often (e.g. on every frame) should keep the
+    /// computed styles and only recompute them when the DOM, the stylesheet
+    /// or the viewport changes.
+    pub fn layout_with_styles(&self, dom: &Dom, styles: &ComputedStyles, viewport: Viewport, font_manager: &mut FontManager) -> LayoutBox {
         layout_log(&format!("=== LAYOUT START === viewport: {}x{}", viewport.width, viewport.height));
         let root_id = dom.root();
         let exclude_tags = ["head", "meta", "link", "title", "style", "script", "base", "noscript"];
         
-        let mut root_box = self.layout_root_element(dom, stylesheet, root_id, &viewport, &exclude_tags, font_manager);
+        // The root establishes the initial block formatting context
+        let mut floats = FloatContext::default();
+        let mut root_box = self.layout_root_element(dom, styles, root_id, &viewport, &exclude_tags, &mut floats, font_manager);
         root_box.dimensions.width = viewport.width;
-        root_box.dimensions.height = root_box.dimensions.height.max(viewport.height);
+        root_box.dimensions.height = root_box.dimensions.height
+            .max(viewport.height)
+            .max(floats.bottom().unwrap_or(0.0));
+        self.position_boxes(&mut root_box, dom, styles, &exclude_tags, &viewport, font_manager);
         layout_log(&format!("=== LAYOUT END === root box: x={}, y={}, w={}, h={}", 
             root_box.dimensions.x, root_box.dimensions.y, 
             root_box.dimensions.width, root_box.dimensions.height));
@@ -142,37 +221,35 @@ impl LayoutEngine {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn layout_root_element(
         &self,
         dom: &Dom,
-        stylesheet: &Stylesheet,
+        styles: &ComputedStyles,
         node_id: NodeId,
         viewport: &Viewport,
         exclude_tags: &[&str],
+        floats: &mut FloatContext,
         font_manager: &mut FontManager,
     ) -> LayoutBox {
         let tag = get_tag_name(dom, node_id);
         layout_log(&format!("layout_root_element: <{}> viewport_width={}", tag, viewport.width));
         
-        let style = stylesheet.compute_style_with_viewport(dom, node_id, viewport);
+        let style = styles.get(node_id).clone();
         
         // Debug: log all style properties for this element
-        layout_log(&format!("  <{}> style props: {:?}", tag, style.properties.keys().collect::<Vec<_>>()));
-        if let Some(margin) = style.get("margin") {
-            layout_log(&format!("  <{}> margin property: '{}'", tag, margin));
-        }
+        layout_log(&format!("  <{}> style props: {:?}", tag, style.properties().properties.keys().collect::<Vec<_>>()));
         if let Some(width) = style.get("width") {
             layout_log(&format!("  <{}> width property: '{}'", tag, width));
         }
         
-        // Get margin with viewport height awareness for vh units
-        let (body_mt, body_mr, body_mb, body_ml) = style.get_margin_with_viewport(viewport.height);
+                let (body_mt, body_mr, body_mb, body_ml) = style.margin.resolve(viewport.width).trbl();
         let has_auto_margin = style.has_auto_horizontal_margin();
         layout_log(&format!("  <{}> margins: top={}, right={}, bottom={}, left={}, auto={}", 
             tag, body_mt, body_mr, body_mb, body_ml, has_auto_margin));
         
         // Check for explicit width on body (e.g., width: 60vw)
-        let explicit_width = style.get_width_px(viewport.width);
+        let explicit_width = style.width.as_ref().map(|w| w.resolve(viewport.width));
         layout_log(&format!("  <{}> explicit_width: {:?}", tag, explicit_width));
         
         // Calculate the actual content width for this root element
@@ -197,10 +274,13 @@ impl LayoutEngine {
         let mut children_boxes = Vec::new();
         let mut current_y = body_mt;
         let children = dom.nodes[node_id].children.clone();
+        let mut child_idx = 0;
 
-        for child_id in children {
+        while child_idx < children.len() {
+            let child_id = children[child_idx];
+            child_idx += 1;
             let should_exclude = if let crate::dom::NodeType::Element(el) = &dom.nodes[child_id].node_type {
-                exclude_tags.contains(&el.tag_name.as_str())
+                exclude_tags.contains(&el.tag_name.as_str()) || styles.get(child_id).display == Display::None
             } else {
                 false
             };
@@ -209,50 +289,82 @@ impl LayoutEngine {
                 continue;
             }
 
-            if self.is_root_element(dom, child_id) {
-                let mut child_box = self.layout_root_element(dom, stylesheet, child_id, viewport, exclude_tags, font_manager);
+            if self.is_out_of_flow(dom, styles, child_id) {
+                children_boxes.push(self.layout_static_position(
+                    dom, styles, child_id,
+                    content_x, current_y, content_width,
+                    exclude_tags, viewport, font_manager,
+                ));
+            } else if self.is_root_element(dom, child_id) {
+                let mut child_box = self.layout_root_element(dom, styles, child_id, viewport, exclude_tags, floats, font_manager);
                 child_box.dimensions.x = content_x;
                 child_box.dimensions.y = current_y;
                 child_box.dimensions.width = content_width;
                 current_y += child_box.dimensions.height;
                 children_boxes.push(child_box);
-            } else if self.is_list_container(dom, child_id) {
-                // List containers (ul, ol)
-                let child_style = stylesheet.compute_style_with_viewport(dom, child_id, viewport);
-                let (child_mt, _, child_mb, _) = child_style.get_margin_with_viewport(viewport.height);
-                current_y += child_mt;
-                
-                let list_box = self.layout_list_container(
-                    dom, stylesheet, child_id,
+            } else if self.is_float(dom, styles, child_id) {
+                children_boxes.push(self.layout_float(
+                    dom, styles, child_id,
                     content_x, current_y, content_width,
-                    exclude_tags, viewport, font_manager,
-                    0,
-                );
-                current_y += list_box.dimensions.height + child_mb;
-                children_boxes.push(list_box);
-            } else if self.is_block_element(dom, child_id) {
-                // Get child margins first to properly position
-                let child_style = stylesheet.compute_style_with_viewport(dom, child_id, viewport);
-                let (child_mt, _, child_mb, _) = child_style.get_margin_with_viewport(viewport.height);
-                
-                // Add top margin before laying out child
-                current_y += child_mt;
-                
-                let child_box = self.layout_block_element(
-                    dom, stylesheet, child_id, 
-                    content_x, current_y, content_width, 
-                    exclude_tags, viewport, font_manager
+                    floats, exclude_tags, viewport, font_manager,
+                ));
+            } else if self.is_list_container(dom, child_id)
+                || self.is_table(dom, child_id)
+                || self.is_block_element(dom, styles, child_id)
+            {
+                let (child_box, next_y) = self.layout_flow_block(
+                    dom, styles, child_id,
+                    content_x, current_y, content_width,
+                    floats, exclude_tags, viewport, font_manager,
                 );
-                
-                // Move down by child's border-box height plus bottom margin
-                current_y += child_box.dimensions.height + child_mb;
+                current_y = next_y;
                 children_boxes.push(child_box);
             } else {
-                let inline_children = vec![child_id];
+                // Consecutive inline children share lines
+                let mut inline_children = vec![child_id];
+                while let Some(&next_id) = children.get(child_idx) {
+                    let is_excluded = if let crate::dom::NodeType::Element(el) = &dom.nodes[next_id].node_type {
+                        exclude_tags.contains(&el.tag_name.as_str()) || styles.get(next_id).display == Display::None
+                    } else {
+                        false
+                    };
+                    if is_excluded {
+                        child_idx += 1;
+                        continue;
+                    }
+                    if self.is_out_of_flow(dom, styles, next_id) {
+                        children_boxes.push(self.layout_static_position(
+                            dom, styles, next_id,
+                            content_x, current_y, content_width,
+                            exclude_tags, viewport, font_manager,
+                        ));
+                        child_idx += 1;
+                        continue;
+                    }
+                    if self.is_float(dom, styles, next_id) {
+                        children_boxes.push(self.layout_float(
+                            dom, styles, next_id,
+                            content_x, current_y, content_width,
+                            floats, exclude_tags, viewport, font_manager,
+                        ));
+                        child_idx += 1;
+                        continue;
+                    }
+                    if self.is_root_element(dom, next_id)
+                        || self.is_list_container(dom, next_id)
+                        || self.is_table(dom, next_id)
+                        || self.is_block_element(dom, styles, next_id)
+                    {
+                        break;
+                    }
+                    inline_children.push(next_id);
+                    child_idx += 1;
+                }
+
                 let line_box = self.layout_inline_line(
-                    dom, stylesheet, &inline_children, 
+                    dom, styles, &inline_children, 
                     content_x, current_y, content_width, 
-                    exclude_tags, viewport, font_manager
+                    floats, exclude_tags, font_manager
                 );
                 // Only add line box if it has content (non-zero height)
                 if line_box.dimensions.height > 0.0 {
@@ -280,40 +392,108 @@ impl LayoutEngine {
         }
     }
 
-    fn is_block_element(&self, dom: &Dom, node_id: NodeId) -> bool {
+    /// Whether a node is an absolutely or fixed positioned element
+    fn is_out_of_flow(&self, dom: &Dom, styles: &ComputedStyles, node_id: NodeId) -> bool {
+        matches!(dom.nodes[node_id].node_type, crate::dom::NodeType::Element(_))
+            && styles.get(node_id).position.is_out_of_flow()
+    }
+
+    /// Whether a node is a floated element
+    fn is_float(&self, dom: &Dom, styles: &ComputedStyles, node_id: NodeId) -> bool {
+        matches!(dom.nodes[node_id].node_type, crate::dom::NodeType::Element(_))
+            && styles.get(node_id).float != Float::None
+    }
+
+    /// Lay out a block-level child in normal flow with its top margin edge
+    /// at `y`, returning the box and the `y` below its bottom margin.
+    /// Lists, tables and formatting context roots are narrowed so they do
+    /// not overlap floats; other blocks share `floats` with their parent.
+    #[allow(clippy::too_many_arguments)]
+    fn layout_flow_block(
+        &self,
+        dom: &Dom,
+        styles: &ComputedStyles,
+        node_id: NodeId,
+        content_x: f32,
+        y: f32,
+        content_width: f32,
+        floats: &mut FloatContext,
+        exclude_tags: &[&str],
+        viewport: &Viewport,
+        font_manager: &mut FontManager,
+    ) -> (LayoutBox, f32) {
+        let style = styles.get(node_id).clone();
+        let (margin_top, _, margin_bottom, _) = style.margin.resolve(content_width).trbl();
+        let y = floats.clear(style.clear, y + margin_top);
+
+        let is_list = self.is_list_container(dom, node_id);
+        let is_table = self.is_table(dom, node_id);
+        let (x, width) = if is_list || is_table || establishes_bfc(&style) {
+            let (start, end) = floats.available(y, 0.0, content_x, content_x + content_width);
+            (start, end - start)
+        } else {
+            (content_x, content_width)
+        };
+
+        let child_box = if is_list {
+            self.layout_list_container(
+                dom, styles, node_id,
+                x, y, width,
+                exclude_tags, viewport, font_manager,
+                0, // list depth starts at 0
+            )
+        } else if is_table {
+            self.layout_table(
+                dom, styles, node_id,
+                x, y, width,
+                exclude_tags, viewport, font_manager,
+            )
+        } else {
+            self.layout_block_sized(
+                dom, styles, node_id,
+                x, y, width,
+                exclude_tags, viewport, font_manager,
+                UsedSize::default(),
+                Some(floats),
+            )
+        };
+        let next_y = y + child_box.dimensions.height + margin_bottom;
+        (child_box, next_y)
+    }
+
+    /// Lay out an out-of-flow element where it would have been in normal
+    /// flow, without taking up space. `position_boxes` moves it into place.
+    #[allow(clippy::too_many_arguments)]
+    fn layout_static_position(
+        &self,
+        dom: &Dom,
+        styles: &ComputedStyles,
+        node_id: NodeId,
+        x: f32,
+        y: f32,
+        containing_width: f32,
+        exclude_tags: &[&str],
+        viewport: &Viewport,
+        font_manager: &mut FontManager,
+    ) -> LayoutBox {
+        let margin_top = styles.get(node_id).margin.top.resolve(containing_width);
+        self.layout_block_element(
+            dom, styles, node_id,
+            x, y + margin_top, containing_width,
+            exclude_tags, viewport, font_manager,
+        )
+    }
+
+    /// Whether a node takes part in block layout, from its computed `display`
+    fn is_block_element(&self, dom: &Dom, styles: &ComputedStyles, node_id: NodeId) -> bool {
         match &dom.nodes[node_id].node_type {
-            crate::dom::NodeType::Text(_) => false,
-            crate::dom::NodeType::Element(el) => {
-                // Check if display is explicitly set via style attribute
-                // For now, use HTML default block/inline classification
-                // Per HTML spec, these elements have display: block by default
-                matches!(el.tag_name.to_lowercase().as_str(),
-                    // Document structure
-                    "html" | "body" | "document" | "head" | "title" | "meta" | "link" | 
-                    "style" | "script" | "noscript" | "template" |
-                    // Sections
-                    "article" | "aside" | "footer" | "header" | "nav" | "section" | "main" |
-                    // Grouping content
-                    "p" | "div" | "blockquote" | "pre" | "hr" | "address" |
-                    "figure" | "figcaption" | "center" |
-                    // Headings
-                    "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "hgroup" |
-                    // Lists
-                    "ul" | "ol" | "li" | "dl" | "dt" | "dd" | "dir" | "menu" |
-                    // Tables
-                    "table" | "caption" | "thead" | "tbody" | "tfoot" | "tr" | "td" | "th" | "col" | "colgroup" |
-                    // Forms
-                    "form" | "fieldset" | "legend" | "label" | "input" | "button" | 
-                    "select" | "textarea" | "option" | "optgroup" | "datalist" | "output" |
-                    // Embedded content (often block-like)
-                    "img" | "iframe" | "video" | "audio" | "canvas" | "object" | "embed" |
-                    "picture" | "source" | "track" |
-                    // Interactive
-                    "details" | "summary" | "dialog" |
-                    // Deprecated but still used
-                    "xmp" | "listing" | "plaintext" | "frameset" | "frame" | "noframes"
-                )
+            // There are no atomic inline boxes yet, so inline-flex and
+            // inline-grid lay out like flex and grid
+            crate::dom::NodeType::Element(_) => {
+                let display = styles.get(node_id).display;
+                display.is_block_level() || matches!(display, Display::InlineFlex | Display::InlineGrid)
             }
+            _ => false,
         }
     }
     
@@ -325,6 +505,11 @@ impl LayoutEngine {
         }
     }
     
+    /// Check if element is a table (laid out by the table formatting model)
+    fn is_table(&self, dom: &Dom, node_id: NodeId) -> bool {
+        dom.tag_name(node_id) == Some("table")
+    }
+
     /// Check if element is a list item
     fn is_list_item(&self, dom: &Dom, node_id: NodeId) -> bool {
         match &dom.nodes[node_id].node_type {
@@ -359,7 +544,36 @@ impl LayoutEngine {
     fn layout_block_element(
         &self,
         dom: &Dom,
-        stylesheet: &Stylesheet,
+        styles: &ComputedStyles,
+        node_id: NodeId,
+        x: f32,
+        y: f32,
+        containing_width: f32,
+        exclude_tags: &[&str],
+        viewport: &Viewport,
+        font_manager: &mut FontManager,
+    ) -> LayoutBox {
+        self.layout_block_sized(
+            dom, styles, node_id,
+            x, y, containing_width,
+            exclude_tags, viewport, font_manager,
+            UsedSize::default(),
+            None,
+        )
+    }
+
+    /// `layout_block_element` with an optional border-box size imposed by the
+    /// parent formatting context. When a width is imposed, `x` is the
+    /// border-box edge: the caller has already placed the margins.
+    ///
+    /// `floats` is the parent's block formatting context. Without one, or
+    /// when the element establishes its own, its floats are kept to itself
+    /// and its auto height grows to contain them.
+    #[allow(clippy::too_many_arguments)]
+    fn layout_block_sized(
+        &self,
+        dom: &Dom,
+        styles: &ComputedStyles,
         node_id: NodeId,
         x: f32,
         y: f32,
@@ -367,42 +581,53 @@ impl LayoutEngine {
         exclude_tags: &[&str],
         viewport: &Viewport,
         font_manager: &mut FontManager,
+        used: UsedSize,
+        floats: Option<&mut FloatContext>,
     ) -> LayoutBox {
         let tag = get_tag_name(dom, node_id);
-        let style = stylesheet.compute_style_with_viewport(dom, node_id, viewport);
+        let style = styles.get(node_id).clone();
+        let mut own_floats = FloatContext::default();
+        let (floats, contains_floats) = match floats {
+            Some(floats) if !establishes_bfc(&style) => (floats, false),
+            _ => (&mut own_floats, true),
+        };
         
-        // Step 1: Get padding values
-        let (padding_top, padding_right, padding_bottom, padding_left) = style.get_padding();
+        // Step 1: Get padding plus border widths, which separate the border
+        // box from the content box
+        let (pb_top, pb_right, pb_bottom, pb_left) = style.padding_border(containing_width).trbl();
         
-        // Step 2: Get margin values with viewport height awareness for vh units
-        let (margin_top, margin_right, margin_bottom, margin_left) = style.get_margin_with_viewport(viewport.height);
+        // Step 2: Get margin values
+        let (margin_top, margin_right, margin_bottom, margin_left) = style.margin.resolve(containing_width).trbl();
         let has_auto_margin = style.has_auto_horizontal_margin();
         
         layout_log(&format!("layout_block: <{}> at ({}, {}) containing_width={}", tag, x, y, containing_width));
         layout_log(&format!("  margins: t={}, r={}, b={}, l={}, auto={}", margin_top, margin_right, margin_bottom, margin_left, has_auto_margin));
-        layout_log(&format!("  padding: t={}, r={}, b={}, l={}", padding_top, padding_right, padding_bottom, padding_left));
+        layout_log(&format!("  padding+border: t={}, r={}, b={}, l={}", pb_top, pb_right, pb_bottom, pb_left));
         
         // Check for explicit width
-        let explicit_width = style.get_width_percentage().map(|f| viewport.width * f)
-            .or_else(|| style.get_width_px(viewport.width));
+        let explicit_width = style.width.as_ref().map(|w| w.resolve(containing_width));
         layout_log(&format!("  explicit_width: {:?}", explicit_width));
         
         // Step 3: Calculate content width
-        let content_width = if let Some(w) = explicit_width {
+        let content_width = if let Some(w) = used.width {
+            (w - pb_left - pb_right).max(0.0)
+        } else if let Some(w) = explicit_width {
             w
         } else {
-            // Block elements fill available width (containing_width - padding - margins)
+            // Block elements fill available width (containing_width - padding - border - margins)
             let horizontal_margin = if has_auto_margin { 0.0 } else { margin_left + margin_right };
-            (containing_width - padding_left - padding_right - horizontal_margin).max(0.0)
+            (containing_width - pb_left - pb_right - horizontal_margin).max(0.0)
         };
         
         layout_log(&format!("  content_width: {}", content_width));
         
-        // Step 4: Calculate border-box width (content + padding)
-        let border_box_width = content_width + padding_left + padding_right;
+        // Step 4: Calculate border-box width (content + padding + border)
+        let border_box_width = content_width + pb_left + pb_right;
         
         // Step 5: Calculate horizontal margins
-        let (final_margin_left, final_margin_right) = if has_auto_margin {
+        let (final_margin_left, final_margin_right) = if used.width.is_some() {
+            (0.0, 0.0)
+        } else if has_auto_margin {
             // Auto margins: distribute remaining space equally for centering
             let remaining = (containing_width - border_box_width).max(0.0);
             layout_log(&format!("  AUTO MARGIN: remaining={}, each side={}", remaining, remaining / 2.0));
@@ -419,11 +644,97 @@ impl LayoutEngine {
         
         layout_log(&format!("  border_box: x={}, y={}, width={}", border_box_x, border_box_y, border_box_width));
         
-        // Step 7: Calculate content area position (inside padding)
-        let content_x = border_box_x + padding_left;
-        let content_y = border_box_y + padding_top;
+        // Step 7: Calculate content area position (inside padding and border)
+        let content_x = border_box_x + pb_left;
+        let content_y = border_box_y + pb_top;
+        
+        // Step 8: Layout children within the content area. A percentage
+        // height has no definite containing block height to resolve against.
+        let explicit_height = style.height.as_ref().and_then(Length::definite);
+        let content_box_height = used
+            .height
+            .map(|h| (h - pb_top - pb_bottom).max(0.0))
+            .or(explicit_height);
+        let (mut children_boxes, children_height, box_type) = if matches!(style.display, Display::Flex | Display::InlineFlex) {
+            let (children, height) = self.layout_flex_items(
+                dom, styles, node_id,
+                content_x, content_y, content_width, content_box_height,
+                exclude_tags, viewport, font_manager,
+            );
+            (children, height, BoxType::Flex)
+        } else if matches!(style.display, Display::Grid | Display::InlineGrid) {
+            let (children, height) = self.layout_grid_items(
+                dom, styles, node_id,
+                content_x, content_y, content_width, content_box_height,
+                exclude_tags, viewport, font_manager,
+            );
+            (children, height, BoxType::Grid)
+        } else {
+            let (children, height) = self.layout_block_children(
+                dom, styles, node_id,
+                content_x, content_y, content_width,
+                floats, exclude_tags, viewport, font_manager,
+            );
+            (children, height, BoxType::Block)
+        };
+        if !matches!(box_type, BoxType::Block) {
+            // Absolutely positioned children of flex and grid containers are
+            // not items; their static position is the content box's corner
+            for &child_id in &dom.nodes[node_id].children {
+                if self.is_out_of_flow(dom, styles, child_id) {
+                    children_boxes.push(self.layout_static_position(
+                        dom, styles, child_id,
+                        content_x, content_y, content_width,
+                        exclude_tags, viewport, font_manager,
+                    ));
+                }
+            }
+        }
+
+        // Step 9: Calculate content height (determined by children unless specified)
+        let children_height = match floats.bottom() {
+            Some(bottom) if contains_floats => children_height.max(bottom - content_y),
+            _ => children_height,
+        };
+        let content_height = content_box_height.unwrap_or(children_height);
+        
+        // Step 10: Calculate border-box height
+        let border_box_height = content_height + pb_top + pb_bottom;
         
-        // Step 8: Layout children within the content area
+        // Step 11: Build the layout box
+        // dimensions represents the border-box (what gets painted with background)
+        LayoutBox {
+            node_id,
+            box_type,
+            dimensions: Dimensions { 
+                x: border_box_x,
+                y: border_box_y, 
+                width: border_box_width, 
+                height: border_box_height,  // Don't force min height - empty blocks should be zero-height
+            },
+            style,
+            children: children_boxes,
+            text_content: None,
+        }
+    }
+
+    /// Lay out a block container's children in normal flow, stacking block
+    /// boxes and line boxes from `content_y`. Returns the boxes and the
+    /// height they occupy.
+    #[allow(clippy::too_many_arguments)]
+    fn layout_block_children(
+        &self,
+        dom: &Dom,
+        styles: &ComputedStyles,
+        node_id: NodeId,
+        content_x: f32,
+        content_y: f32,
+        content_width: f32,
+        floats: &mut FloatContext,
+        exclude_tags: &[&str],
+        viewport: &Viewport,
+        font_manager: &mut FontManager,
+    ) -> (Vec<LayoutBox>, f32) {
         let mut children_boxes = Vec::new();
         let mut current_y = content_y;
         let children = dom.nodes[node_id].children.clone();
@@ -432,7 +743,7 @@ impl LayoutEngine {
         while child_idx < children.len() {
             let child_id = children[child_idx];
             let should_exclude = if let crate::dom::NodeType::Element(el) = &dom.nodes[child_id].node_type {
-                exclude_tags.contains(&el.tag_name.as_str())
+                exclude_tags.contains(&el.tag_name.as_str()) || styles.get(child_id).display == Display::None
             } else {
                 false
             };
@@ -442,39 +753,32 @@ impl LayoutEngine {
                 continue;
             }
 
-            // Check for list containers first (ul, ol)
-            if self.is_list_container(dom, child_id) {
-                let child_style = stylesheet.compute_style_with_viewport(dom, child_id, viewport);
-                let (child_mt, _, child_mb, _) = child_style.get_margin_with_viewport(viewport.height);
-                current_y += child_mt;
-                
-                let list_box = self.layout_list_container(
-                    dom, stylesheet, child_id,
+            if self.is_out_of_flow(dom, styles, child_id) {
+                children_boxes.push(self.layout_static_position(
+                    dom, styles, child_id,
                     content_x, current_y, content_width,
                     exclude_tags, viewport, font_manager,
-                    0, // list depth starts at 0
-                );
-                current_y += list_box.dimensions.height + child_mb;
-                children_boxes.push(list_box);
+                ));
                 child_idx += 1;
-            } else if self.is_block_element(dom, child_id) {
-                // Block element: layout within content area
-                // Child's containing width is THIS element's content width
-                // Get child margins first to properly position
-                let child_style = stylesheet.compute_style_with_viewport(dom, child_id, viewport);
-                let (child_mt, _, child_mb, _) = child_style.get_margin_with_viewport(viewport.height);
-                
-                // Add top margin before laying out child
-                current_y += child_mt;
-                
-                let child_box = self.layout_block_element(
-                    dom, stylesheet, child_id, 
-                    content_x, current_y, content_width, 
-                    exclude_tags, viewport, font_manager
+            } else if self.is_float(dom, styles, child_id) {
+                children_boxes.push(self.layout_float(
+                    dom, styles, child_id,
+                    content_x, current_y, content_width,
+                    floats, exclude_tags, viewport, font_manager,
+                ));
+                child_idx += 1;
+            } else if self.is_list_container(dom, child_id)
+                || self.is_table(dom, child_id)
+                || self.is_block_element(dom, styles, child_id)
+            {
+                // Block-level child: its containing width is THIS element's
+                // content width
+                let (child_box, next_y) = self.layout_flow_block(
+                    dom, styles, child_id,
+                    content_x, current_y, content_width,
+                    floats, exclude_tags, viewport, font_manager,
                 );
-                
-                // Move down by the child's border-box height plus bottom margin
-                current_y += child_box.dimensions.height + child_mb;
+                current_y = next_y;
                 children_boxes.push(child_box);
                 child_idx += 1;
             } else {
@@ -485,7 +789,7 @@ impl LayoutEngine {
                 while child_idx < children.len() {
                     let next_id = children[child_idx];
                     let is_excluded = if let crate::dom::NodeType::Element(el) = &dom.nodes[next_id].node_type {
-                        exclude_tags.contains(&el.tag_name.as_str())
+                        exclude_tags.contains(&el.tag_name.as_str()) || styles.get(next_id).display == Display::None
                     } else {
                         false
                     };
@@ -494,8 +798,30 @@ impl LayoutEngine {
                         child_idx += 1;
                         continue;
                     }
+
+                    // Out-of-flow boxes do not break the line
+                    if self.is_out_of_flow(dom, styles, next_id) {
+                        children_boxes.push(self.layout_static_position(
+                            dom, styles, next_id,
+                            content_x, current_y, content_width,
+                            exclude_tags, viewport, font_manager,
+                        ));
+                        child_idx += 1;
+                        continue;
+                    }
+
+                    // Floats go at the top of the lines that follow them
+                    if self.is_float(dom, styles, next_id) {
+                        children_boxes.push(self.layout_float(
+                            dom, styles, next_id,
+                            content_x, current_y, content_width,
+                            floats, exclude_tags, viewport, font_manager,
+                        ));
+                        child_idx += 1;
+                        continue;
+                    }
                     
-                    if self.is_block_element(dom, next_id) {
+                    if self.is_block_element(dom, styles, next_id) {
                         break;
                     }
                     inline_children.push(next_id);
@@ -504,9 +830,9 @@ impl LayoutEngine {
 
                 // Layout inline children as a line box
                 let line_box = self.layout_inline_line(
-                    dom, stylesheet, &inline_children, 
+                    dom, styles, &inline_children, 
                     content_x, current_y, content_width, 
-                    exclude_tags, viewport, font_manager
+                    floats, exclude_tags, font_manager
                 );
                 // Only add line box if it has content (non-zero height)
                 if line_box.dimensions.height > 0.0 {
@@ -516,34 +842,63 @@ impl LayoutEngine {
             }
         }
 
-        // Step 9: Calculate content height (determined by children)
-        let content_height = (current_y - content_y).max(0.0);
-        
-        // Step 10: Calculate border-box height
-        let border_box_height = content_height + padding_top + padding_bottom;
-        
-        // Step 11: Build the layout box
-        // dimensions represents the border-box (what gets painted with background)
-        LayoutBox {
-            node_id,
-            box_type: BoxType::Block,
-            dimensions: Dimensions { 
-                x: border_box_x,
-                y: border_box_y, 
-                width: border_box_width, 
-                height: border_box_height,  // Don't force min height - empty blocks should be zero-height
-            },
-            style,
-            children: children_boxes,
-            text_content: None,
+        (children_boxes, (current_y - content_y).max(0.0))
+    }
+
+    /// Border-box min-content and max-content widths of `node_id`, measured
+    /// by laying it out at an unconstrained width. Percentage padding is
+    /// resolved against `basis`.
+    fn intrinsic_widths(
+        &self,
+        dom: &Dom,
+        styles: &ComputedStyles,
+        node_id: NodeId,
+        basis: f32,
+        viewport: &Viewport,
+        font_manager: &mut FontManager,
+    ) -> (f32, f32) {
+        let exclude_tags = ["head", "meta", "link", "title", "style", "script", "base", "noscript"];
+        let probe = self.layout_block_element(
+            dom, styles, node_id,
+            0.0, 0.0, PROBE_WIDTH,
+            &exclude_tags, viewport, font_manager,
+        );
+        self.probe_intrinsic_widths(&probe, basis, font_manager)
+    }
+
+    /// Min-content and max-content widths of a box laid out at `PROBE_WIDTH`:
+    /// max-content is the right edge of the widest line, min-content the
+    /// widest piece of text no line can break inside, or image
+    fn probe_intrinsic_widths(&self, probe: &LayoutBox, basis: f32, font_manager: &mut FontManager) -> (f32, f32) {
+        let pb = probe.style.padding_border(basis);
+        let left = probe.dimensions.x;
+
+        let mut right_edge = left + pb.left;
+        let mut widest_unit = 0.0_f32;
+        let mut pending = vec![probe];
+        while let Some(b) = pending.pop() {
+            pending.extend(b.children.iter());
+            if !matches!(b.box_type, BoxType::Inline) || !b.children.is_empty() {
+                continue;
+            }
+            right_edge = right_edge.max(b.dimensions.x + b.dimensions.width);
+            let unit = match &b.text_content {
+                Some(text) => inline::min_content_width(text, &b.style, font_manager),
+                None => b.dimensions.width,
+            };
+            widest_unit = widest_unit.max(unit);
         }
+
+        let min = widest_unit + pb.left + pb.right;
+        let max = right_edge - left + pb.right;
+        (min, max.max(min))
     }
 
     /// Layout a list container (ul or ol) with proper indentation
     fn layout_list_container(
         &self,
         dom: &Dom,
-        stylesheet: &Stylesheet,
+        styles: &ComputedStyles,
         node_id: NodeId,
         x: f32,
         y: f32,
@@ -554,7 +909,7 @@ impl LayoutEngine {
         list_depth: usize,
     ) -> LayoutBox {
         let tag = get_tag_name(dom, node_id);
-        let style = stylesheet.compute_style_with_viewport(dom, node_id, viewport);
+        let style = styles.get(node_id).clone();
         let list_type = self.get_list_type(dom, node_id);
         
         layout_log(&format!("layout_list_container: <{}> depth={} at ({}, {})", tag, list_depth, x, y));
@@ -562,19 +917,21 @@ impl LayoutEngine {
         // Default list indentation: 40px (standard browser default)
         let list_indent = 40.0;
         
-        // Get any explicit padding from style, or use default
-        let (padding_top, padding_right, padding_bottom, padding_left) = style.get_padding();
-        let effective_padding_left = if padding_left > 0.0 { padding_left } else { list_indent };
+        // Get any explicit padding from style, or use default. `pb_*` are
+        // padding plus border; the indent goes inside the border.
+        let (pb_top, pb_right, pb_bottom, pb_left) = style.padding_border(containing_width).trbl();
+        let border_left = style.border.left.width;
+        let effective_padding_left = if pb_left > border_left { pb_left } else { border_left + list_indent };
         
         // Get margins (margin_top/bottom not currently used for list containers)
-        let (_margin_top, margin_right, _margin_bottom, margin_left) = style.get_margin_with_viewport(viewport.height);
+        let (_margin_top, margin_right, _margin_bottom, margin_left) = style.margin.resolve(containing_width).trbl();
         
         // Calculate content area
         let border_box_x = x + margin_left;
         let border_box_y = y;
-        let content_width = (containing_width - margin_left - margin_right - effective_padding_left - padding_right).max(0.0);
+        let content_width = (containing_width - margin_left - margin_right - effective_padding_left - pb_right).max(0.0);
         let content_x = border_box_x + effective_padding_left;
-        let content_y = border_box_y + padding_top;
+        let content_y = border_box_y + pb_top;
         
         layout_log(&format!("  list indent: {}, content_x: {}, content_width: {}", effective_padding_left, content_x, content_width));
         
@@ -590,10 +947,10 @@ impl LayoutEngine {
                 child_tag,
                 self.is_list_item(dom, child_id),
                 self.is_list_container(dom, child_id),
-                self.is_block_element(dom, child_id)));
+                self.is_block_element(dom, styles, child_id)));
             
             let should_exclude = if let crate::dom::NodeType::Element(el) = &dom.nodes[child_id].node_type {
-                exclude_tags.contains(&el.tag_name.as_str())
+                exclude_tags.contains(&el.tag_name.as_str()) || styles.get(child_id).display == Display::None
             } else {
                 false
             };
@@ -606,7 +963,7 @@ impl LayoutEngine {
                 item_index += 1;
                 layout_log(&format!("    -> calling layout_list_item for <{}> #{}", child_tag, item_index));
                 let li_box = self.layout_list_item(
-                    dom, stylesheet, child_id,
+                    dom, styles, child_id,
                     content_x, current_y, content_width,
                     exclude_tags, viewport, font_manager,
                     list_type, item_index, list_depth,
@@ -616,21 +973,21 @@ impl LayoutEngine {
             } else if self.is_list_container(dom, child_id) {
                 // Nested list
                 let nested_list = self.layout_list_container(
-                    dom, stylesheet, child_id,
+                    dom, styles, child_id,
                     content_x, current_y, content_width,
                     exclude_tags, viewport, font_manager,
                     list_depth + 1,
                 );
                 current_y += nested_list.dimensions.height;
                 children_boxes.push(nested_list);
-            } else if self.is_block_element(dom, child_id) {
+            } else if self.is_block_element(dom, styles, child_id) {
                 // Other block element inside list (unusual but possible)
-                let child_style = stylesheet.compute_style_with_viewport(dom, child_id, viewport);
-                let (child_mt, _, child_mb, _) = child_style.get_margin_with_viewport(viewport.height);
+                let child_style = styles.get(child_id).clone();
+                let (child_mt, _, child_mb, _) = child_style.margin.resolve(content_width).trbl();
                 current_y += child_mt;
                 
                 let child_box = self.layout_block_element(
-                    dom, stylesheet, child_id,
+                    dom, styles, child_id,
                     content_x, current_y, content_width,
                     exclude_tags, viewport, font_manager
                 );
@@ -641,8 +998,8 @@ impl LayoutEngine {
         }
 
         let content_height = (current_y - content_y).max(0.0);
-        let border_box_height = content_height + padding_top + padding_bottom;
-        let border_box_width = content_width + effective_padding_left + padding_right;
+        let border_box_height = content_height + pb_top + pb_bottom;
+        let border_box_width = content_width + effective_padding_left + pb_right;
 
         LayoutBox {
             node_id,
@@ -663,7 +1020,7 @@ impl LayoutEngine {
     fn layout_list_item(
         &self,
         dom: &Dom,
-        stylesheet: &Stylesheet,
+        styles: &ComputedStyles,
         node_id: NodeId,
         x: f32,
         y: f32,
@@ -675,12 +1032,12 @@ impl LayoutEngine {
         item_index: usize,
         _list_depth: usize,
     ) -> LayoutBox {
-        let style = stylesheet.compute_style_with_viewport(dom, node_id, viewport);
-        let font_size = style.get_font_size();
-        let font_family = style.get_font_family();
-        let is_bold = style.is_bold();
+        let style = styles.get(node_id).clone();
+        let font_size = style.font_size;
+        let font_family = style.font_family.as_str();
+        let font_weight = style.font_weight;
         let is_italic = style.is_italic();
-        let line_height = font_size * 1.2;
+        let line_height = style.line_height_px();
         
         // Generate marker text
         let marker_text = match list_type {
@@ -690,7 +1047,7 @@ impl LayoutEngine {
         };
         
         // Measure marker width
-        let marker_width = self.measure_text_width(&marker_text, font_manager, &font_family, font_size, is_bold, is_italic);
+        let marker_width = self.measure_text_width(&marker_text, font_manager, font_family, font_size, font_weight, is_italic);
         let marker_spacing = font_size * 0.5; // Space between marker and content
         let marker_area_width = marker_width + marker_spacing;
         
@@ -729,7 +1086,7 @@ impl LayoutEngine {
         while child_idx < children.len() {
             let child_id = children[child_idx];
             let should_exclude = if let crate::dom::NodeType::Element(el) = &dom.nodes[child_id].node_type {
-                exclude_tags.contains(&el.tag_name.as_str())
+                exclude_tags.contains(&el.tag_name.as_str()) || styles.get(child_id).display == Display::None
             } else {
                 false
             };
@@ -742,7 +1099,7 @@ impl LayoutEngine {
             if self.is_list_container(dom, child_id) {
                 // Nested list inside li
                 let nested_list = self.layout_list_container(
-                    dom, stylesheet, child_id,
+                    dom, styles, child_id,
                     content_x, current_y, content_width,
                     exclude_tags, viewport, font_manager,
                     _list_depth + 1,
@@ -751,14 +1108,28 @@ impl LayoutEngine {
                 children_boxes.push(nested_list);
                 child_idx += 1;
                 first_line = false;
-            } else if self.is_block_element(dom, child_id) {
+            } else if self.is_table(dom, child_id) {
+                let child_style = styles.get(child_id).clone();
+                let (child_mt, _, child_mb, _) = child_style.margin.resolve(content_width).trbl();
+                current_y += child_mt;
+
+                let table_box = self.layout_table(
+                    dom, styles, child_id,
+                    content_x, current_y, content_width,
+                    exclude_tags, viewport, font_manager,
+                );
+                current_y += table_box.dimensions.height + child_mb;
+                children_boxes.push(table_box);
+                child_idx += 1;
+                first_line = false;
+            } else if self.is_block_element(dom, styles, child_id) {
                 // Block element inside li
-                let child_style = stylesheet.compute_style_with_viewport(dom, child_id, viewport);
-                let (child_mt, _, child_mb, _) = child_style.get_margin_with_viewport(viewport.height);
+                let child_style = styles.get(child_id).clone();
+                let (child_mt, _, child_mb, _) = child_style.margin.resolve(content_width).trbl();
                 current_y += child_mt;
                 
                 let child_box = self.layout_block_element(
-                    dom, stylesheet, child_id,
+                    dom, styles, child_id,
                     content_x, current_y, content_width,
                     exclude_tags, viewport, font_manager
                 );
@@ -774,7 +1145,7 @@ impl LayoutEngine {
                 while child_idx < children.len() {
                     let next_id = children[child_idx];
                     let is_excluded = if let crate::dom::NodeType::Element(el) = &dom.nodes[next_id].node_type {
-                        exclude_tags.contains(&el.tag_name.as_str())
+                        exclude_tags.contains(&el.tag_name.as_str()) || styles.get(next_id).display == Display::None
                     } else {
                         false
                     };
@@ -784,7 +1155,7 @@ impl LayoutEngine {
                         continue;
                     }
                     
-                    if self.is_block_element(dom, next_id) || self.is_list_container(dom, next_id) {
+                    if self.is_block_element(dom, styles, next_id) || self.is_list_container(dom, next_id) {
                         break;
                     }
                     inline_children.push(next_id);
@@ -792,9 +1163,9 @@ impl LayoutEngine {
                 }
 
                 let line_box = self.layout_inline_line(
-                    dom, stylesheet, &inline_children,
+                    dom, styles, &inline_children,
                     content_x, current_y, content_width,
-                    exclude_tags, viewport, font_manager
+                    &FloatContext::default(), exclude_tags, font_manager
                 );
                 
                 if line_box.dimensions.height > 0.0 {
@@ -824,308 +1195,4 @@ impl LayoutEngine {
             text_content: None,
         }
     }
-
-    fn layout_inline_line(
-        &self,
-        dom: &Dom,
-        stylesheet: &Stylesheet,
-        inline_children: &[NodeId],
-        x: f32,
-        mut y: f32,
-        width: f32,
-        exclude_tags: &[&str],
-        viewport: &Viewport,
-        font_manager: &mut FontManager,
-    ) -> LayoutBox {
-        let mut line_boxes = Vec::new();
-        let mut current_x = x;
-        let mut max_height = 0.0_f32;
-        let mut total_height = 0.0_f32;
-        let start_y = y;
-
-        text_log(&format!("=== layout_inline_line: x={}, y={}, width={} ===", x, y, width));
-
-        for &child_id in inline_children {
-            if let crate::dom::NodeType::Text(text) = &dom.nodes[child_id].node_type {
-                // Skip whitespace-only text nodes
-                if text.trim().is_empty() {
-                    text_log(&format!("  SKIP whitespace-only text node"));
-                    continue;
-                }
-                
-                let style = stylesheet.compute_style_with_viewport(dom, child_id, viewport);
-                let font_size = style.get_font_size();
-                let font_family = style.get_font_family();
-                let is_bold = style.is_bold();
-                let is_italic = style.is_italic();
-                let line_height = font_size * 1.2;
-                
-                text_log(&format!("  text node: '{}' font_size={}", text.chars().take(50).collect::<String>(), font_size));
-                
-                let words: Vec<&str> = text.split_whitespace().collect();
-                if words.is_empty() {
-                    text_log(&format!("  SKIP empty words after split"));
-                    continue;
-                }
-                text_log(&format!("  split into {} words: {:?}", words.len(), words));
-                
-                // Measure space width using actual font
-                let space_width = self.measure_text_width(" ", font_manager, font_family, font_size, is_bold, is_italic);
-                
-                for (word_idx, word) in words.iter().enumerate() {
-                    // Measure word using actual font metrics
-                    let word_width = self.measure_text_width(word, font_manager, font_family, font_size, is_bold, is_italic);
-                    
-                    text_log(&format!("    word[{}] '{}': width={:.2}, space_width={:.2}, current_x={:.2}, available={:.2}", 
-                        word_idx, word, word_width, space_width, current_x, x + width - current_x));
-                    
-                    // Check if word fits on current line
-                    if current_x + word_width > x + width && current_x > x {
-                        text_log(&format!("      -> WRAP: word doesn't fit (needs {:.2}, have {:.2})", word_width, x + width - current_x));
-                        // Word doesn't fit, check if we need character-level wrapping
-                        if word_width > width {
-                            text_log(&format!("      -> CHARACTER WRAP: word wider than line ({:.2} > {:.2})", word_width, width));
-                            // Word is wider than available width, do character wrapping
-                            let mut remaining_word = *word;
-                            while !remaining_word.is_empty() {
-                                let mut char_count = 0;
-                                let mut accumulated_width = 0.0;
-                                let available = if current_x > x { x + width - current_x } else { width };
-                                
-                                for c in remaining_word.chars() {
-                                    // Measure character using actual font
-                                    let char_str = c.to_string();
-                                    let char_width = self.measure_text_width(&char_str, font_manager, font_family, font_size, is_bold, is_italic);
-                                    if accumulated_width + char_width > available && char_count > 0 {
-                                        break;
-                                    }
-                                    accumulated_width += char_width;
-                                    char_count += 1;
-                                }
-                                
-                                if char_count == 0 {
-                                    // Need new line first
-                                    total_height += max_height;
-                                    y += max_height;
-                                    current_x = x;
-                                    max_height = 0.0;
-                                    continue;
-                                }
-                                
-                                let (chunk, rest) = remaining_word.split_at(
-                                    remaining_word.char_indices()
-                                        .nth(char_count)
-                                        .map(|(i, _)| i)
-                                        .unwrap_or(remaining_word.len())
-                                );
-                                remaining_word = rest;
-                                
-                                // Measure chunk using actual font
-                                let chunk_width = self.measure_text_width(chunk, font_manager, font_family, font_size, is_bold, is_italic);
-                                
-                                let word_box = LayoutBox {
-                                    node_id: child_id,
-                                    box_type: BoxType::Inline,
-                                    dimensions: Dimensions { x: current_x, y, width: chunk_width, height: line_height },
-                                    style: style.clone(),
-                                    children: vec![],
-                                    text_content: Some(chunk.to_string()),
-                                };
-                                
-                                max_height = max_height.max(line_height);
-                                current_x += chunk_width;
-                                line_boxes.push(word_box);
-                                
-                                if !remaining_word.is_empty() {
-                                    total_height += max_height;
-                                    y += max_height;
-                                    current_x = x;
-                                    max_height = 0.0;
-                                }
-                            }
-                            continue;
-                        } else {
-                            // Normal line break
-                            text_log(&format!("      -> LINE BREAK"));
-                            total_height += max_height;
-                            y += max_height;
-                            current_x = x;
-                            max_height = 0.0;
-                        }
-                    }
-                    
-                    text_log(&format!("      -> PLACE at x={:.2}, word_width={:.2}", current_x, word_width));
-                    
-                    let word_box = LayoutBox {
-                        node_id: child_id,
-                        box_type: BoxType::Inline,
-                        dimensions: Dimensions { x: current_x, y, width: word_width, height: line_height },
-                        style: style.clone(),
-                        children: vec![],
-                        text_content: Some(word.to_string()),
-                    };
-                    
-                    max_height = max_height.max(line_height);
-                    let _old_x = current_x;
-                    current_x += word_width;
-                    
-                    // Add space after word (except at line end)
-                    let is_last_word = word_idx == words.len() - 1;
-                    if !is_last_word && current_x + space_width <= x + width {
-                        text_log(&format!("      -> ADD SPACE: {:.2} (current_x: {:.2} -> {:.2})", space_width, current_x, current_x + space_width));
-                        current_x += space_width;
-                    } else if !is_last_word {
-                        text_log(&format!("      -> NO SPACE (would overflow): space={:.2}, available={:.2}", space_width, x + width - current_x));
-                    }
-                    
-                    line_boxes.push(word_box);
-                }
-            } else {
-                let mut child_box = self.layout_inline_element(dom, stylesheet, child_id, current_x, y, width - (current_x - x), exclude_tags, viewport, font_manager);
-                
-                let child_width = child_box.dimensions.width;
-                let child_height = child_box.dimensions.height;
-                
-                // Skip inline elements with zero dimensions
-                if child_width <= 0.0 && child_height <= 0.0 {
-                    continue;
-                }
-                
-                if current_x + child_width > x + width && current_x > x {
-                    total_height += max_height;
-                    y += max_height;
-                    current_x = x;
-                    max_height = 0.0;
-                    child_box.dimensions.x = current_x;
-                    child_box.dimensions.y = y;
-                }
-                
-                max_height = max_height.max(child_height);
-                current_x += child_width;
-                line_boxes.push(child_box);
-            }
-        }
-
-        total_height += max_height;
-        
-        // Filter out any boxes with zero dimensions
-        let visible_boxes: Vec<_> = line_boxes.into_iter()
-            .filter(|b| b.dimensions.width > 0.0 || b.dimensions.height > 0.0)
-            .collect();
-        
-        // Only create line box if we have visible content
-        if visible_boxes.is_empty() || total_height <= 0.0 {
-            layout_log(&format!("  inline_line: no visible content, returning empty box"));
-            // Return a zero-height box
-            return LayoutBox {
-                node_id: 0,
-                box_type: BoxType::Block,
-                dimensions: Dimensions { x, y: start_y, width: 0.0, height: 0.0 },
-                style: Style::new(),
-                children: vec![],
-                text_content: None,
-            };
-        }
-        
-        layout_log(&format!("  inline_line: {} children, height={}", visible_boxes.len(), total_height));
-        LayoutBox {
-            node_id: 0,
-            box_type: BoxType::Block,
-            dimensions: Dimensions { x, y: start_y, width, height: total_height },
-            style: Style::new(),
-            children: visible_boxes,
-            text_content: None,
-        }
-    }
-
-    fn layout_inline_element(
-        &self,
-        dom: &Dom,
-        stylesheet: &Stylesheet,
-        node_id: NodeId,
-        x: f32,
-        y: f32,
-        max_width: f32,
-        exclude_tags: &[&str],
-        viewport: &Viewport,
-        font_manager: &mut FontManager,
-    ) -> LayoutBox {
-        let style = stylesheet.compute_style_with_viewport(dom, node_id, viewport);
-
-        match &dom.nodes[node_id].node_type {
-            crate::dom::NodeType::Text(text) => {
-                // Skip whitespace-only text nodes
-                if text.trim().is_empty() {
-                    return LayoutBox {
-                        node_id,
-                        box_type: BoxType::Inline,
-                        dimensions: Dimensions { x, y, width: 0.0, height: 0.0 },
-                        style: style.clone(),
-                        children: vec![],
-                        text_content: None,
-                    };
-                }
-                
-                let font_size = style.get_font_size();
-                let line_height = font_size * 1.2;
-                let font_family = style.get_font_family();
-                let is_bold = style.get_font_weight() == "bold";
-                let is_italic = style.get_font_style() == "italic";
-                let text_width = self.measure_text_width(text, font_manager, &font_family, font_size, is_bold, is_italic);
-
-                LayoutBox {
-                    node_id,
-                    box_type: BoxType::Inline,
-                    dimensions: Dimensions { x, y, width: text_width.min(max_width), height: line_height },
-                    style: style.clone(),
-                    children: vec![],
-                    text_content: Some(text.to_string()),
-                }
-            }
-            crate::dom::NodeType::Element(el) => {
-                if el.tag_name == "img" {
-                    LayoutBox {
-                        node_id,
-                        box_type: BoxType::Inline,
-                        dimensions: Dimensions { x, y, width: 100.0_f32.min(max_width), height: 80.0 },
-                        style,
-                        children: vec![],
-                        text_content: None,
-                    }
-                } else {
-                    let mut children_boxes = Vec::new();
-                    let mut current_x = x;
-                    let mut max_height = 0.0_f32; // Start with 0 height, don't assume 16px
-
-                    for &child_id in &dom.nodes[node_id].children {
-                        let should_exclude = if let crate::dom::NodeType::Element(el) = &dom.nodes[child_id].node_type {
-                            exclude_tags.contains(&el.tag_name.as_str())
-                        } else {
-                            false
-                        };
-
-                        if !should_exclude {
-                            let remaining_width = (x + max_width - current_x).max(0.0);
-                            let child_box = self.layout_inline_element(dom, stylesheet, child_id, current_x, y, remaining_width, exclude_tags, viewport, font_manager);
-                            // Only count child if it has content
-                            if child_box.dimensions.width > 0.0 || child_box.dimensions.height > 0.0 {
-                                max_height = max_height.max(child_box.dimensions.height);
-                                current_x += child_box.dimensions.width;
-                                children_boxes.push(child_box);
-                            }
-                        }
-                    }
-
-                    LayoutBox {
-                        node_id,
-                        box_type: BoxType::Inline,
-                        dimensions: Dimensions { x, y, width: (current_x - x).min(max_width), height: max_height },
-                        style,
-                        children: children_boxes,
-                        text_content: None,
-                    }
-                }
-            }
-        }
-    }
 }
@@ -0,0 +1,355 @@
+// engine/src/layout/position.rs
+// Positioned layout (CSS Positioned Layout Module Level 3)
+//
+// - Runs after normal flow, walking the box tree top-down so every box sees
+//   its containing block in its final place
+// - `relative` boxes are shifted by their insets without affecting siblings
+// - `absolute` boxes are laid out against the padding box of the nearest
+//   positioned ancestor, or the initial containing block (the viewport at the
+//   document origin); `fixed` boxes against the viewport at the scroll offset
+// - Absolute and fixed boxes with `auto` insets keep their static position,
+//   and with an `auto` width shrink to fit unless both `left` and `right` are set
+// - `sticky` boxes are shifted to stay inside the scrollport inset by their
+//   `top`/`right`/`bottom`/`left`, but never out of their parent's content box
+
+use crate::dom::Dom;
+use crate::font::FontManager;
+use crate::style::{ComputedStyles, Length, Position, Viewport};
+
+use super::{layout_log, translate, Dimensions, LayoutBox, LayoutEngine, UsedSize};
+
+/// Shared inputs for positioning one tree
+struct PositionContext<'a> {
+    dom: &'a Dom,
+    styles: &'a ComputedStyles,
+    exclude_tags: &'a [&'a str],
+    viewport: &'a Viewport,
+}
+
+/// Resolve an inset against its percentage basis, `None` for `auto`
+fn resolve_inset(inset: &Option<Length>, basis: f32) -> Option<f32> {
+    inset.as_ref().map(|length| length.resolve(basis))
+}
+
+/// The content box of a laid out box whose percentages resolve against
+/// `containing_width`
+fn content_rect(layout_box: &LayoutBox, containing_width: f32) -> Dimensions {
+    layout_box.dimensions.shrink(&layout_box.style.padding_border(containing_width))
+}
+
+/// Keep a shift that moves a box toward its sticky limit from pushing it past
+/// the edges of its container. `room_before` is how far it may move backward
+/// (<= 0), `room_after` how far forward (>= 0).
+fn clamp_shift(shift: f32, room_before: f32, room_after: f32) -> f32 {
+    if shift > 0.0 {
+        shift.min(room_after.max(0.0))
+    } else {
+        shift.max(room_before.min(0.0))
+    }
+}
+
+impl LayoutEngine {
+    /// Move positioned boxes into place once normal flow is done
+    pub(super) fn position_boxes(
+        &self,
+        root: &mut LayoutBox,
+        dom: &Dom,
+        styles: &ComputedStyles,
+        exclude_tags: &[&str],
+        viewport: &Viewport,
+        font_manager: &mut FontManager,
+    ) {
+        let context = PositionContext { dom, styles, exclude_tags, viewport };
+        let initial = Dimensions { x: 0.0, y: 0.0, width: viewport.width, height: viewport.height };
+        self.position_children(&context, root, viewport.width, &initial, font_manager);
+    }
+
+    /// Position the children of `parent`, then their descendants.
+    /// `containing_width` is what the parent's percentages resolved against
+    /// and `containing_block` is the rectangle absolute descendants use.
+    fn position_children(
+        &self,
+        context: &PositionContext,
+        parent: &mut LayoutBox,
+        containing_width: f32,
+        containing_block: &Dimensions,
+        font_manager: &mut FontManager,
+    ) {
+        let parent_content = content_rect(parent, containing_width);
+
+        for child in &mut parent.children {
+            // Text boxes share their parent's style but are never positioned
+            let position = if child.text_content.is_some() {
+                Position::Static
+            } else {
+                child.style.position
+            };
+
+            let mut child_containing_width = parent_content.width;
+            match position {
+                Position::Static => {}
+                Position::Relative => self.offset_relative(child, &parent_content),
+                Position::Sticky => self.offset_sticky(child, &parent_content, context.viewport),
+                Position::Absolute => {
+                    self.place_absolute(context, child, containing_block, font_manager);
+                    child_containing_width = containing_block.width;
+                }
+                Position::Fixed => {
+                    let (scroll_x, scroll_y) = self.scroll_offset;
+                    let viewport_rect = Dimensions {
+                        x: scroll_x,
+                        y: scroll_y,
+                        width: context.viewport.width,
+                        height: context.viewport.height,
+                    };
+                    self.place_absolute(context, child, &viewport_rect, font_manager);
+                    child_containing_width = viewport_rect.width;
+                }
+            }
+
+            // A positioned box's padding box contains its absolute descendants
+            let child_block = if position.is_positioned() {
+                child.padding_box()
+            } else {
+                *containing_block
+            };
+            self.position_children(context, child, child_containing_width, &child_block, font_manager);
+        }
+    }
+
+    /// Shift a relatively positioned box by its insets. `left` wins over
+    /// `right` and `top` over `bottom`.
+    fn offset_relative(&self, layout_box: &mut LayoutBox, container: &Dimensions) {
+        let inset = &layout_box.style.inset;
+        let dx = resolve_inset(&inset.left, container.width)
+            .or_else(|| resolve_inset(&inset.right, container.width).map(|right| -right))
+            .unwrap_or(0.0);
+        let dy = resolve_inset(&inset.top, container.height)
+            .or_else(|| resolve_inset(&inset.bottom, container.height).map(|bottom| -bottom))
+            .unwrap_or(0.0);
+        translate(layout_box, dx, dy);
+    }
+
+    /// Shift a sticky box so it stays inside the scrollport inset by its
+    /// `top`/`right`/`bottom`/`left`, while staying inside `container`.
+    fn offset_sticky(&self, layout_box: &mut LayoutBox, container: &Dimensions, viewport: &Viewport) {
+        let (scroll_x, scroll_y) = self.scroll_offset;
+        let inset = &layout_box.style.inset;
+        let margin = layout_box.style.margin.resolve(container.width);
+        let d = &layout_box.dimensions;
+
+        // When both sides are set the start side wins, so apply it last
+        let mut dx = 0.0;
+        if let Some(right) = resolve_inset(&inset.right, viewport.width) {
+            dx = (scroll_x + viewport.width - right - (d.x + d.width)).min(0.0);
+        }
+        if let Some(left) = resolve_inset(&inset.left, viewport.width) {
+            dx = dx.max(scroll_x + left - d.x);
+        }
+        let mut dy = 0.0;
+        if let Some(bottom) = resolve_inset(&inset.bottom, viewport.height) {
+            dy = (scroll_y + viewport.height - bottom - (d.y + d.height)).min(0.0);
+        }
+        if let Some(top) = resolve_inset(&inset.top, viewport.height) {
+            dy = dy.max(scroll_y + top - d.y);
+        }
+
+        let dx = clamp_shift(
+            dx,
+            container.x - (d.x - margin.left),
+            container.x + container.width - (d.x + d.width + margin.right),
+        );
+        let dy = clamp_shift(
+            dy,
+            container.y - (d.y - margin.top),
+            container.y + container.height - (d.y + d.height + margin.bottom),
+        );
+        translate(layout_box, dx, dy);
+    }
+
+    /// Lay out an absolutely positioned box against `containing_block` and
+    /// replace the static-position box with it
+    fn place_absolute(
+        &self,
+        context: &PositionContext,
+        layout_box: &mut LayoutBox,
+        containing_block: &Dimensions,
+        font_manager: &mut FontManager,
+    ) {
+        let node_id = layout_box.node_id;
+        let style = layout_box.style.clone();
+        let cb = containing_block;
+
+        let left = resolve_inset(&style.inset.left, cb.width);
+        let right = resolve_inset(&style.inset.right, cb.width);
+        let top = resolve_inset(&style.inset.top, cb.height);
+        let bottom = resolve_inset(&style.inset.bottom, cb.height);
+        let margin = style.margin.resolve(cb.width);
+        let pb = style.padding_border(cb.width);
+        let (top_auto, right_auto, bottom_auto, left_auto) = style.margin_auto;
+
+        // Border-box width: specified, stretched between both insets, or
+        // shrink-to-fit within the space left by whichever inset is set
+        let width = match (style.width.as_ref(), left, right) {
+            (Some(w), _, _) => w.resolve(cb.width) + pb.left + pb.right,
+            (None, Some(l), Some(r)) => (cb.width - l - r - margin.left - margin.right).max(0.0),
+            (None, _, _) => {
+                let available = (cb.width - left.or(right).unwrap_or(0.0) - margin.left - margin.right).max(0.0);
+                let (min, max) = self.intrinsic_widths(
+                    context.dom, context.styles, node_id,
+                    cb.width, context.viewport, font_manager,
+                );
+                max.min(available).max(min)
+            }
+        };
+        let height = match (style.height.as_ref(), top, bottom) {
+            (Some(h), _, _) => Some(h.resolve(cb.height) + pb.top + pb.bottom),
+            (None, Some(t), Some(b)) => Some((cb.height - t - b - margin.top - margin.bottom).max(0.0)),
+            (None, _, _) => None,
+        };
+
+        let mut placed = self.layout_block_sized(
+            context.dom, context.styles, node_id,
+            0.0, 0.0, cb.width,
+            context.exclude_tags, context.viewport, font_manager,
+            UsedSize { width: Some(width), height },
+            None,
+        );
+        let (width, height) = (placed.dimensions.width, placed.dimensions.height);
+
+        let x = match (left, right) {
+            (Some(l), Some(r)) if left_auto && right_auto => {
+                cb.x + l + ((cb.width - l - r - width) / 2.0).max(0.0)
+            }
+            (Some(l), _) => cb.x + l + margin.left,
+            (None, Some(r)) => cb.x + cb.width - r - margin.right - width,
+            (None, None) => layout_box.dimensions.x,
+        };
+        let y = match (top, bottom) {
+            (Some(t), Some(b)) if top_auto && bottom_auto => {
+                cb.y + t + ((cb.height - t - b - height) / 2.0).max(0.0)
+            }
+            (Some(t), _) => cb.y + t + margin.top,
+            (None, Some(b)) => cb.y + cb.height - b - margin.bottom - height,
+            (None, None) => layout_box.dimensions.y,
+        };
+
+        layout_log(&format!("  positioned node {} at ({}, {}) size {}x{}", node_id, x, y, width, height));
+        let (dx, dy) = (x - placed.dimensions.x, y - placed.dimensions.y);
+        translate(&mut placed, dx, dy);
+        *layout_box = placed;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::html::tree_builder::HtmlParser;
+    use crate::style::Stylesheet;
+
+    fn layout_html(html: &str, scroll_y: f32) -> (Dom, LayoutBox) {
+        let dom = HtmlParser::new(html).parse();
+        let mut engine = LayoutEngine::with_viewport(Viewport::new(800.0, 600.0));
+        engine.set_scroll_offset(0.0, scroll_y);
+        let root = engine.layout(&dom, &Stylesheet::new());
+        (dom, root)
+    }
+
+    fn find<'a>(dom: &Dom, layout_box: &'a LayoutBox, id: &str) -> &'a LayoutBox {
+        fn search<'a>(dom: &Dom, b: &'a LayoutBox, id: &str) -> Option<&'a LayoutBox> {
+            if b.text_content.is_none() && dom.get_attribute(b.node_id, "id") == Some(id) {
+                return Some(b);
+            }
+            b.children.iter().find_map(|child| search(dom, child, id))
+        }
+        search(dom, layout_box, id).unwrap_or_else(|| panic!("no box with id {}", id))
+    }
+
+    #[test]
+    fn test_absolute_insets_against_positioned_ancestor() {
+        let (dom, root) = layout_html(
+            "<body style=\"margin:0\">\
+             <div id=cb style=\"position:relative; margin-top:50px; width:400px; height:300px\">\
+             <div style=\"height:20px\"></div>\
+             <div id=abs style=\"position:absolute; left:10px; right:30px; bottom:20px; height:40px\"></div>\
+             </div></body>",
+            0.0,
+        );
+        let abs = find(&dom, &root, "abs");
+        assert_eq!(abs.dimensions.x, 10.0);
+        assert_eq!(abs.dimensions.width, 360.0);
+        assert_eq!(abs.dimensions.y, 50.0 + 300.0 - 20.0 - 40.0);
+    }
+
+    #[test]
+    fn test_borders_size_boxes_and_inset_the_padding_box() {
+        let (dom, root) = layout_html(
+            "<body style=\"margin:0\">\
+             <div id=cb style=\"position:relative; width:200px; height:100px; padding:5px; border:10px solid\">\
+             <div id=flow style=\"height:10px\"></div>\
+             <div id=abs style=\"position:absolute; left:0; top:0; width:20px; height:20px; border-left:3px solid\"></div>\
+             </div></body>",
+            0.0,
+        );
+        let cb = find(&dom, &root, "cb");
+        assert_eq!((cb.dimensions.width, cb.dimensions.height), (230.0, 130.0));
+        let flow = find(&dom, &root, "flow");
+        assert_eq!((flow.dimensions.x, flow.dimensions.y, flow.dimensions.width), (15.0, 15.0, 200.0));
+        // Insets are measured from the padding box, inside the border
+        let abs = find(&dom, &root, "abs");
+        assert_eq!((abs.dimensions.x, abs.dimensions.y, abs.dimensions.width), (10.0, 10.0, 23.0));
+    }
+
+    #[test]
+    fn test_relative_offset_keeps_siblings_in_place() {
+        let (dom, root) = layout_html(
+            "<body style=\"margin:0\">\
+             <div id=a style=\"position:relative; top:5px; left:7px; height:10px\"></div>\
+             <div id=b style=\"height:10px\"></div></body>",
+            0.0,
+        );
+        let (a, b) = (find(&dom, &root, "a"), find(&dom, &root, "b"));
+        assert_eq!((a.dimensions.x, a.dimensions.y), (7.0, 5.0));
+        assert_eq!((b.dimensions.x, b.dimensions.y), (0.0, 10.0));
+    }
+
+    #[test]
+    fn test_fixed_follows_scroll_offset() {
+        let (dom, root) = layout_html(
+            "<body style=\"margin:0\">\
+             <div id=bar style=\"position:fixed; bottom:0; left:0; width:100px; height:30px\"></div>\
+             <div id=tall style=\"height:2000px\"></div></body>",
+            400.0,
+        );
+        assert_eq!(find(&dom, &root, "bar").dimensions.y, 400.0 + 600.0 - 30.0);
+        // The fixed box takes no space in flow
+        assert_eq!(find(&dom, &root, "tall").dimensions.y, 0.0);
+    }
+
+    #[test]
+    fn test_sticky_stays_inside_parent() {
+        let html = "<body style=\"margin:0\">\
+                    <div style=\"height:500px\">\
+                    <div id=header style=\"position:sticky; top:0; height:40px\"></div></div>\
+                    <div style=\"height:2000px\"></div></body>";
+
+        let (dom, root) = layout_html(html, 100.0);
+        assert_eq!(find(&dom, &root, "header").dimensions.y, 100.0);
+
+        // Scrolled past the section, the header stops at its bottom edge
+        let (dom, root) = layout_html(html, 900.0);
+        assert_eq!(find(&dom, &root, "header").dimensions.y, 460.0);
+    }
+
+    #[test]
+    fn test_absolute_shrinks_to_fit() {
+        let (dom, root) = layout_html(
+            "<body style=\"margin:0\">\
+             <div id=abs style=\"position:absolute; top:0; right:0\"><img style=\"display:inline\"></div></body>",
+            0.0,
+        );
+        let abs = find(&dom, &root, "abs");
+        assert_eq!(abs.dimensions.width, 100.0);
+        assert_eq!(abs.dimensions.x, 700.0);
+    }
+}
@@ -0,0 +1,242 @@
+// engine/src/layout/stacking.rs
+// Painting order (CSS 2.1 Appendix E, simplified)
+//
+// - A stacking context is formed by the root, positioned boxes with an
+//   integer `z-index`, `fixed` and `sticky` boxes, flex and grid items with
+//   an integer `z-index`, and boxes with `opacity` below 1
+// - Within a context: the context's own box, then child contexts with a
+//   negative `z-index`, then in-flow boxes in tree order, then floats, then
+//   positioned boxes with `z-index: auto` or 0 in tree order, then positive
+//   `z-index`
+// - Positioned boxes with `z-index: auto` paint like a context, but their
+//   positioned descendants and child contexts belong to the parent context
+// - Boxes with `overflow` other than `visible` clip their descendants to their
+//   padding box; fixed boxes escape every clip
+
+use super::{BoxType, Dimensions, LayoutBox};
+use crate::style::{Float, Overflow, Position};
+
+/// One box to paint, with the opacity and clip it is painted with. Both fold
+/// in those of every ancestor.
+#[derive(Debug, Clone, Copy)]
+pub struct PaintStep<'a> {
+    pub layout_box: &'a LayoutBox,
+    pub opacity: f32,
+    /// Nothing outside this rectangle is painted; `None` when unclipped
+    pub clip: Option<Dimensions>,
+}
+
+/// Something painted as a unit within its parent stacking context
+enum Layer<'a> {
+    /// A child stacking context, painted recursively
+    Context(PaintStep<'a>),
+    /// A positioned `z-index: auto` box with its in-flow descendants
+    Steps(Vec<PaintStep<'a>>),
+}
+
+/// The boxes of a tree in the order they are painted, back to front
+pub fn paint_order(root: &LayoutBox) -> Vec<PaintStep<'_>> {
+    let mut steps = Vec::new();
+    paint_context(PaintStep { layout_box: root, opacity: 1.0, clip: None }, &mut steps);
+    steps
+}
+
+/// The overlap of two rectangles, empty if they are disjoint
+fn intersect(a: Dimensions, b: Dimensions) -> Dimensions {
+    let x = a.x.max(b.x);
+    let y = a.y.max(b.y);
+    let right = (a.x + a.width).min(b.x + b.width);
+    let bottom = (a.y + a.height).min(b.y + b.height);
+    Dimensions { x, y, width: (right - x).max(0.0), height: (bottom - y).max(0.0) }
+}
+
+/// The clip the children of `step` are painted with
+fn clip_for_children(step: &PaintStep) -> Option<Dimensions> {
+    let layout_box = step.layout_box;
+    let style = &layout_box.style;
+    let clips = layout_box.text_content.is_none()
+        && (style.overflow_x != Overflow::Visible || style.overflow_y != Overflow::Visible);
+    if !clips {
+        return step.clip;
+    }
+    let padding_box = layout_box.padding_box();
+    Some(match step.clip {
+        Some(clip) => intersect(clip, padding_box),
+        None => padding_box,
+    })
+}
+
+/// Opacity of a box given its ancestors'. Text boxes share their parent's
+/// style, so only element boxes apply it.
+fn own_opacity(layout_box: &LayoutBox, inherited: f32) -> f32 {
+    if layout_box.text_content.is_some() {
+        inherited
+    } else {
+        inherited * layout_box.style.opacity.clamp(0.0, 1.0)
+    }
+}
+
+/// Whether `layout_box`, a child of a box of type `parent_type`, starts a
+/// stacking context
+fn creates_context(layout_box: &LayoutBox, parent_type: &BoxType) -> bool {
+    if layout_box.text_content.is_some() {
+        return false;
+    }
+    let style = &layout_box.style;
+    let is_item = matches!(parent_type, BoxType::Flex | BoxType::Grid);
+    matches!(style.position, Position::Fixed | Position::Sticky)
+        || (style.z_index.is_some() && (style.position.is_positioned() || is_item))
+        || style.opacity < 1.0
+}
+
+fn paint_context<'a>(context: PaintStep<'a>, out: &mut Vec<PaintStep<'a>>) {
+    out.push(context);
+
+    let mut in_flow = Vec::new();
+    let mut floats = Vec::new();
+    let mut layers = Vec::new();
+    collect(context, &mut in_flow, &mut floats, &mut layers);
+
+    // Stable, so equal z-index keeps tree order
+    layers.sort_by_key(|(z, _)| *z);
+    let split = layers.partition_point(|(z, _)| *z < 0);
+    let (negative, rest) = layers.split_at(split);
+
+    paint_layers(negative, out);
+    out.extend(in_flow);
+    out.extend(floats.into_iter().flatten());
+    paint_layers(rest, out);
+}
+
+fn paint_layers<'a>(layers: &[(i32, Layer<'a>)], out: &mut Vec<PaintStep<'a>>) {
+    for (_, layer) in layers {
+        match layer {
+            Layer::Context(step) => paint_context(*step, out),
+            Layer::Steps(steps) => out.extend(steps.iter().copied()),
+        }
+    }
+}
+
+/// Sort the descendants of `parent` into in-flow boxes, floats (each with
+/// its in-flow descendants) and layers of the current stacking context,
+/// without entering child contexts
+fn collect<'a>(
+    parent: PaintStep<'a>,
+    in_flow: &mut Vec<PaintStep<'a>>,
+    floats: &mut Vec<Vec<PaintStep<'a>>>,
+    layers: &mut Vec<(i32, Layer<'a>)>,
+) {
+    let clip = clip_for_children(&parent);
+    for child in &parent.layout_box.children {
+        let step = PaintStep {
+            layout_box: child,
+            opacity: own_opacity(child, parent.opacity),
+            clip: if child.text_content.is_none() && child.style.position == Position::Fixed { None } else { clip },
+        };
+        if creates_context(child, &parent.layout_box.box_type) {
+            layers.push((child.style.z_index.unwrap_or(0), Layer::Context(step)));
+        } else if child.text_content.is_none() && child.style.position.is_positioned() {
+            // Reserve the slot first so the box paints before any positioned
+            // descendants collected below it
+            let slot = layers.len();
+            layers.push((0, Layer::Steps(Vec::new())));
+            let mut steps = vec![step];
+            collect(step, &mut steps, floats, layers);
+            layers[slot].1 = Layer::Steps(steps);
+        } else if child.text_content.is_none() && child.style.float != Float::None {
+            // Floats paint above the in-flow blocks they overlap
+            let slot = floats.len();
+            floats.push(Vec::new());
+            let mut steps = vec![step];
+            collect(step, &mut steps, floats, layers);
+            floats[slot] = steps;
+        } else {
+            in_flow.push(step);
+            collect(step, in_flow, floats, layers);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dom::Dom;
+    use crate::parser::html::tree_builder::HtmlParser;
+    use crate::layout::LayoutEngine;
+    use crate::style::Stylesheet;
+
+    /// The ids of element boxes in paint order
+    fn painted_ids(html: &str) -> Vec<String> {
+        let dom: Dom = HtmlParser::new(html).parse();
+        let root = LayoutEngine::new().layout(&dom, &Stylesheet::new());
+        paint_order(&root)
+            .iter()
+            .filter(|step| step.layout_box.text_content.is_none())
+            .filter_map(|step| dom.get_attribute(step.layout_box.node_id, "id"))
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_z_index_orders_contexts() {
+        let ids = painted_ids(
+            "<div id=top style=\"position:relative; z-index:2\"></div>\
+             <div id=below style=\"position:absolute; z-index:-1\"></div>\
+             <div id=flow></div>\
+             <div id=auto style=\"position:relative\"></div>\
+             <div id=middle style=\"position:relative; z-index:1\"></div>",
+        );
+        assert_eq!(ids, ["below", "flow", "auto", "middle", "top"]);
+    }
+
+    #[test]
+    fn test_context_contains_descendants() {
+        // The child's z-index only orders it within its parent's context
+        let ids = painted_ids(
+            "<div id=a style=\"position:relative; z-index:1\">\
+             <div id=a1 style=\"position:relative; z-index:100\"></div></div>\
+             <div id=b style=\"position:relative; z-index:2\"></div>",
+        );
+        assert_eq!(ids, ["a", "a1", "b"]);
+    }
+
+    #[test]
+    fn test_floats_paint_above_following_blocks() {
+        let ids = painted_ids(
+            "<div id=f style=\"float:left; width:50px; height:50px\"></div>\
+             <div id=block style=\"height:100px\"></div>\
+             <div id=pos style=\"position:relative\"></div>",
+        );
+        assert_eq!(ids, ["block", "f", "pos"]);
+    }
+
+    #[test]
+    fn test_opacity_multiplies_down_the_tree() {
+        let dom = HtmlParser::new(
+            "<div id=outer style=\"opacity:0.5\"><div id=inner style=\"opacity:0.5\"></div></div>",
+        )
+        .parse();
+        let root = LayoutEngine::new().layout(&dom, &Stylesheet::new());
+        let inner = paint_order(&root)
+            .into_iter()
+            .find(|step| dom.get_attribute(step.layout_box.node_id, "id") == Some("inner"))
+            .unwrap();
+        assert_eq!(inner.opacity, 0.25);
+    }
+
+    #[test]
+    fn test_overflow_clips_descendants() {
+        let dom = HtmlParser::new(
+            "<div id=outer style=\"overflow:hidden; width:100px; height:50px\">\
+             <div id=inner style=\"height:200px\"></div></div>",
+        )
+        .parse();
+        let root = LayoutEngine::new().layout(&dom, &Stylesheet::new());
+        let steps = paint_order(&root);
+        let step = |id| *steps.iter().find(|s| dom.get_attribute(s.layout_box.node_id, "id") == Some(id)).unwrap();
+        let (outer, inner) = (step("outer"), step("inner"));
+        assert!(outer.clip.is_none());
+        let clip = inner.clip.expect("inner is clipped");
+        assert_eq!((clip.width, clip.height), (100.0, 50.0));
+    }
+}
@@ -0,0 +1,599 @@
+// engine/src/layout/table.rs
+// Table formatting (CSS 2.1 section 17)
+//
+// - Rows are collected from thead/tbody/tfoot (thead first, tfoot last) and bare tr children
+// - Cells are placed on a slot grid honoring colspan and rowspan
+// - Column widths use the automatic algorithm (min/max-content per column),
+//   or the fixed algorithm for `table-layout: fixed` tables with a specified width
+// - `border-collapse: separate` spaces cells by `border-spacing`; `collapse` does not
+// - Cell content is positioned inside the row with `vertical-align` (top, middle, bottom)
+
+use crate::dom::{Dom, NodeId};
+use crate::font::FontManager;
+use crate::style::{ComputedStyle, ComputedStyles, CssValue, Viewport};
+
+use super::{layout_log, translate, BoxType, Dimensions, LayoutBox, LayoutEngine};
+
+/// Default `border-spacing` for tables with separated borders
+const DEFAULT_BORDER_SPACING: f32 = 2.0;
+
+/// A cell placed on the table grid
+struct TableCell {
+    node_id: NodeId,
+    row: usize,
+    col: usize,
+    colspan: usize,
+    rowspan: usize,
+}
+
+/// Table structure extracted from the DOM
+struct TableGrid {
+    captions: Vec<NodeId>,
+    rows: Vec<NodeId>,
+    cells: Vec<TableCell>,
+    /// Widths specified on col/colgroup elements, per column
+    column_widths: Vec<Option<f32>>,
+    column_count: usize,
+}
+
+impl TableGrid {
+    fn build(dom: &Dom, table: NodeId) -> Self {
+        let mut captions = Vec::new();
+        let mut head_rows = Vec::new();
+        let mut body_rows = Vec::new();
+        let mut foot_rows = Vec::new();
+        let mut column_widths = Vec::new();
+
+        for &child in &dom.nodes[table].children {
+            match dom.tag_name(child) {
+                Some("caption") => captions.push(child),
+                Some("colgroup") => {
+                    let cols: Vec<NodeId> = element_children(dom, child, &["col"]).collect();
+                    if cols.is_empty() {
+                        push_column_widths(dom, child, &mut column_widths);
+                    }
+                    for col in cols {
+                        push_column_widths(dom, col, &mut column_widths);
+                    }
+                }
+                Some("col") => push_column_widths(dom, child, &mut column_widths),
+                Some("thead") => head_rows.extend(element_children(dom, child, &["tr"])),
+                Some("tbody") => body_rows.extend(element_children(dom, child, &["tr"])),
+                Some("tfoot") => foot_rows.extend(element_children(dom, child, &["tr"])),
+                Some("tr") => body_rows.push(child),
+                _ => {}
+            }
+        }
+
+        let rows: Vec<NodeId> = head_rows.into_iter().chain(body_rows).chain(foot_rows).collect();
+
+        // Place cells, skipping slots taken by rowspans from earlier rows
+        let mut occupied: Vec<Vec<bool>> = vec![Vec::new(); rows.len()];
+        let mut cells = Vec::new();
+        for (row, &tr) in rows.iter().enumerate() {
+            let mut col = 0;
+            for cell in element_children(dom, tr, &["td", "th"]) {
+                while occupied[row].get(col).copied().unwrap_or(false) {
+                    col += 1;
+                }
+                let colspan = span_attribute(dom, cell, "colspan").unwrap_or(1).clamp(1, 1000);
+                let remaining_rows = rows.len() - row;
+                let rowspan = match span_attribute(dom, cell, "rowspan") {
+                    Some(0) => remaining_rows,
+                    Some(n) => n.min(remaining_rows),
+                    None => 1,
+                };
+                for slots in &mut occupied[row..row + rowspan] {
+                    if slots.len() < col + colspan {
+                        slots.resize(col + colspan, false);
+                    }
+                    slots[col..col + colspan].iter_mut().for_each(|slot| *slot = true);
+                }
+                cells.push(TableCell { node_id: cell, row, col, colspan, rowspan });
+                col += colspan;
+            }
+        }
+
+        let column_count = occupied.iter().map(|slots| slots.len()).max().unwrap_or(0);
+
+        Self { captions, rows, cells, column_widths, column_count }
+    }
+}
+
+fn element_children<'a>(dom: &'a Dom, parent: NodeId, tags: &'a [&'a str]) -> impl Iterator<Item = NodeId> + 'a {
+    dom.nodes[parent]
+        .children
+        .iter()
+        .copied()
+        .filter(move |&child| dom.tag_name(child).is_some_and(|tag| tags.contains(&tag)))
+}
+
+fn span_attribute(dom: &Dom, node_id: NodeId, name: &str) -> Option<usize> {
+    dom.get_attribute(node_id, name).and_then(|v| v.trim().parse().ok())
+}
+
+/// Record the width of a col (or childless colgroup) once per spanned column
+fn push_column_widths(dom: &Dom, node_id: NodeId, widths: &mut Vec<Option<f32>>) {
+    let span = span_attribute(dom, node_id, "span").unwrap_or(1).clamp(1, 1000);
+    let width = dom.get_attribute(node_id, "width").and_then(|w| attribute_length(w, 0.0));
+    widths.extend(std::iter::repeat_n(width, span));
+}
+
+/// Parse a presentational `width` attribute: a pixel count or a percentage of `reference`
+fn attribute_length(value: &str, reference: f32) -> Option<f32> {
+    match CssValue::parse(value)? {
+        CssValue::Number(px) => Some(px),
+        CssValue::Percentage(pct) => Some(reference * pct / 100.0),
+        _ => None,
+    }
+}
+
+/// Specified width from the `width` property, falling back to the `width` attribute
+fn specified_width(dom: &Dom, node_id: NodeId, style: &ComputedStyle, reference: f32) -> Option<f32> {
+    style
+        .width
+        .as_ref()
+        .map(|w| w.resolve(reference))
+        .or_else(|| dom.get_attribute(node_id, "width").and_then(|w| attribute_length(w, reference)))
+}
+
+/// Horizontal and vertical `border-spacing`
+fn border_spacing(style: &ComputedStyle) -> (f32, f32) {
+    if style.get("border-collapse").map(str::trim) == Some("collapse") {
+        return (0.0, 0.0);
+    }
+    let parts: Vec<f32> = style.lengths("border-spacing").iter().map(|l| l.resolve(0.0)).collect();
+    match parts.as_slice() {
+        [both] => (*both, *both),
+        [horizontal, vertical, ..] => (*horizontal, *vertical),
+        [] => (DEFAULT_BORDER_SPACING, DEFAULT_BORDER_SPACING),
+    }
+}
+
+/// Spread `extra` over the spanned columns, evenly
+fn distribute_evenly(widths: &mut [f32], extra: f32) {
+    if widths.is_empty() || extra <= 0.0 {
+        return;
+    }
+    let share = extra / widths.len() as f32;
+    widths.iter_mut().for_each(|w| *w += share);
+}
+
+impl LayoutEngine {
+    /// Layout a table element. Like `layout_block_element`, `x`/`y` is where the
+    /// margin box starts and vertical margins are handled by the caller.
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn layout_table(
+        &self,
+        dom: &Dom,
+        styles: &ComputedStyles,
+        node_id: NodeId,
+        x: f32,
+        y: f32,
+        containing_width: f32,
+        exclude_tags: &[&str],
+        viewport: &Viewport,
+        font_manager: &mut FontManager,
+    ) -> LayoutBox {
+        let style = styles.get(node_id).clone();
+        let grid = TableGrid::build(dom, node_id);
+        let (pb_top, pb_right, pb_bottom, pb_left) = style.padding_border(containing_width).trbl();
+        let (_, margin_right, _, margin_left) = style.margin.resolve(containing_width).trbl();
+        let has_auto_margin = style.has_auto_horizontal_margin()
+            || dom.get_attribute(node_id, "align").is_some_and(|a| a.eq_ignore_ascii_case("center"));
+        let (spacing_x, spacing_y) = border_spacing(&style);
+
+        let horizontal_margin = if has_auto_margin { 0.0 } else { margin_left + margin_right };
+        let available = (containing_width - horizontal_margin - pb_left - pb_right).max(0.0);
+        let specified = specified_width(dom, node_id, &style, containing_width);
+        let columns = grid.column_count;
+        let total_spacing = if columns > 0 { spacing_x * (columns + 1) as f32 } else { 0.0 };
+
+        layout_log(&format!("layout_table: {} rows x {} columns, available={}", grid.rows.len(), columns, available));
+
+        // Step 1: Resolve column widths
+        let fixed = style.get("table-layout").map(str::trim) == Some("fixed") && specified.is_some();
+        let (column_widths, table_width) = if fixed {
+            let table_width = specified.unwrap_or(available);
+            let widths = self.fixed_column_widths(dom, styles, &grid, table_width - total_spacing);
+            (widths, table_width)
+        } else {
+            let (min, max) = self.column_min_max_widths(dom, styles, &grid, spacing_x, viewport, font_manager, available);
+            let sum_min: f32 = min.iter().sum::<f32>() + total_spacing;
+            let sum_max: f32 = max.iter().sum::<f32>() + total_spacing;
+            let table_width = match specified {
+                Some(w) => w.max(sum_min),
+                None => sum_max.min(available).max(sum_min),
+            };
+            (Self::distribute_auto_widths(&min, &max, table_width - total_spacing), table_width)
+        };
+
+        // Step 2: Position the table's border box
+        let border_box_width = table_width + pb_left + pb_right;
+        let final_margin_left = if has_auto_margin {
+            ((containing_width - border_box_width) / 2.0).max(0.0)
+        } else {
+            margin_left
+        };
+        let border_box_x = x + final_margin_left;
+        let content_x = border_box_x + pb_left;
+        let content_y = y + pb_top;
+        let mut children_boxes = Vec::new();
+        let mut current_y = content_y;
+
+        // Step 3: Captions sit above the grid and span the table width
+        for &caption in &grid.captions {
+            let caption_box = self.layout_block_element(
+                dom, styles, caption,
+                content_x, current_y, table_width,
+                exclude_tags, viewport, font_manager,
+            );
+            current_y += caption_box.dimensions.height;
+            children_boxes.push(caption_box);
+        }
+
+        // Step 4: Lay out each cell at its column position (y fixed up below)
+        let mut column_x = Vec::with_capacity(columns);
+        let mut next_x = content_x + spacing_x;
+        for width in &column_widths {
+            column_x.push(next_x);
+            next_x += width + spacing_x;
+        }
+        let span_width = |cell: &TableCell| {
+            column_widths[cell.col..cell.col + cell.colspan].iter().sum::<f32>()
+                + spacing_x * (cell.colspan - 1) as f32
+        };
+
+        let mut cell_boxes = Vec::with_capacity(grid.cells.len());
+        for cell in &grid.cells {
+            let width = span_width(cell);
+            let mut cell_box = self.layout_block_element(
+                dom, styles, cell.node_id,
+                column_x[cell.col], 0.0, width,
+                exclude_tags, viewport, font_manager,
+            );
+            cell_box.dimensions.x = column_x[cell.col];
+            cell_box.dimensions.width = width;
+            // A specified cell height is a minimum for the row
+            let (cell_pb_top, _, cell_pb_bottom, _) = cell_box.style.padding_border(width).trbl();
+            if let Some(h) = cell_box.style.height.as_ref().map(|h| h.resolve(0.0)) {
+                cell_box.dimensions.height = cell_box.dimensions.height.max(h + cell_pb_top + cell_pb_bottom);
+            }
+            cell_boxes.push(cell_box);
+        }
+
+        // Step 5: Row heights from single-row cells, then grow the last row of
+        // each rowspan that does not fit
+        let mut row_heights: Vec<f32> = grid
+            .rows
+            .iter()
+            .map(|&tr| {
+                let row_style = styles.get(tr).clone();
+                row_style.height.as_ref().map_or(0.0, |h| h.resolve(0.0))
+            })
+            .collect();
+        for (cell, cell_box) in grid.cells.iter().zip(&cell_boxes) {
+            if cell.rowspan == 1 {
+                row_heights[cell.row] = row_heights[cell.row].max(cell_box.dimensions.height);
+            }
+        }
+        for (cell, cell_box) in grid.cells.iter().zip(&cell_boxes) {
+            if cell.rowspan > 1 {
+                let rows = cell.row..cell.row + cell.rowspan;
+                let spanned = row_heights[rows.clone()].iter().sum::<f32>() + spacing_y * (cell.rowspan - 1) as f32;
+                if cell_box.dimensions.height > spanned {
+                    row_heights[rows.end - 1] += cell_box.dimensions.height - spanned;
+                }
+            }
+        }
+
+        let grid_y = current_y;
+        let mut row_y = Vec::with_capacity(grid.rows.len());
+        let mut next_y = grid_y + if grid.rows.is_empty() { 0.0 } else { spacing_y };
+        for height in &row_heights {
+            row_y.push(next_y);
+            next_y += height + spacing_y;
+        }
+
+        // Step 6: Place cells vertically, aligning their content within the row span
+        let mut row_children: Vec<Vec<LayoutBox>> = (0..grid.rows.len()).map(|_| Vec::new()).collect();
+        for (cell, mut cell_box) in grid.cells.iter().zip(cell_boxes) {
+            let height = row_heights[cell.row..cell.row + cell.rowspan].iter().sum::<f32>()
+                + spacing_y * (cell.rowspan - 1) as f32;
+            let free_space = (height - cell_box.dimensions.height).max(0.0);
+            let offset = match self.cell_vertical_align(dom, styles, cell, &cell_box.style).as_str() {
+                "middle" => free_space / 2.0,
+                "bottom" => free_space,
+                _ => 0.0,
+            };
+            translate(&mut cell_box, 0.0, row_y[cell.row]);
+            for child in &mut cell_box.children {
+                translate(child, 0.0, offset);
+            }
+            cell_box.dimensions.height = height;
+            row_children[cell.row].push(cell_box);
+        }
+
+        for ((&tr, children), (&top, &height)) in grid.rows.iter().zip(row_children).zip(row_y.iter().zip(&row_heights)) {
+            children_boxes.push(LayoutBox {
+                node_id: tr,
+                box_type: BoxType::Block,
+                dimensions: Dimensions { x: content_x, y: top, width: table_width, height },
+                style: styles.get(tr).clone(),
+                children,
+                text_content: None,
+            });
+        }
+
+        let content_height = (next_y - content_y).max(0.0);
+
+        LayoutBox {
+            node_id,
+            box_type: BoxType::Block,
+            dimensions: Dimensions {
+                x: border_box_x,
+                y,
+                width: border_box_width,
+                height: content_height + pb_top + pb_bottom,
+            },
+            style,
+            children: children_boxes,
+            text_content: None,
+        }
+    }
+
+    /// Min-content and max-content width of every column (automatic table layout)
+    #[allow(clippy::too_many_arguments)]
+    fn column_min_max_widths(
+        &self,
+        dom: &Dom,
+        styles: &ComputedStyles,
+        grid: &TableGrid,
+        spacing_x: f32,
+        viewport: &Viewport,
+        font_manager: &mut FontManager,
+        available: f32,
+    ) -> (Vec<f32>, Vec<f32>) {
+        let columns = grid.column_count;
+        let mut min = vec![0.0_f32; columns];
+        let mut max = vec![0.0_f32; columns];
+
+        for (col, width) in grid.column_widths.iter().enumerate().take(columns) {
+            if let Some(w) = width {
+                min[col] = min[col].max(*w);
+                max[col] = max[col].max(*w);
+            }
+        }
+
+        let mut cell_widths: Vec<(&TableCell, f32, f32)> = grid
+            .cells
+            .iter()
+            .map(|cell| {
+                let (cell_min, cell_max) = self.cell_intrinsic_widths(dom, styles, cell.node_id, viewport, font_manager, available);
+                (cell, cell_min, cell_max)
+            })
+            .collect();
+
+        // Single-column cells first, then spanning cells widen the columns they cover
+        cell_widths.sort_by_key(|(cell, _, _)| cell.colspan);
+        for (cell, cell_min, cell_max) in cell_widths {
+            let span = cell.col..cell.col + cell.colspan;
+            let spacing = spacing_x * (cell.colspan - 1) as f32;
+            let span_min = min[span.clone()].iter().sum::<f32>() + spacing;
+            distribute_evenly(&mut min[span.clone()], cell_min - span_min);
+            let span_max = max[span.clone()].iter().sum::<f32>() + spacing;
+            distribute_evenly(&mut max[span.clone()], cell_max - span_max);
+        }
+
+        for (lo, hi) in min.iter().zip(max.iter_mut()) {
+            *hi = hi.max(*lo);
+        }
+        (min, max)
+    }
+
+    /// Border-box min-content and max-content widths of a cell
+    fn cell_intrinsic_widths(
+        &self,
+        dom: &Dom,
+        styles: &ComputedStyles,
+        cell: NodeId,
+        viewport: &Viewport,
+        font_manager: &mut FontManager,
+        available: f32,
+    ) -> (f32, f32) {
+        let (mut min, mut max) = self.intrinsic_widths(dom, styles, cell, available, viewport, font_manager);
+        let style = styles.get(cell);
+        let (_, pb_right, _, pb_left) = style.padding_border(available).trbl();
+        if let Some(w) = specified_width(dom, cell, style, available) {
+            let w = w + pb_left + pb_right;
+            min = min.max(w);
+            max = max.max(w);
+        }
+        (min, max.max(min))
+    }
+
+    /// Fixed table layout: widths come from col elements and the first row only
+    fn fixed_column_widths(
+        &self,
+        dom: &Dom,
+        styles: &ComputedStyles,
+        grid: &TableGrid,
+        target: f32,
+    ) -> Vec<f32> {
+        let columns = grid.column_count;
+        let mut widths: Vec<Option<f32>> = (0..columns)
+            .map(|col| grid.column_widths.get(col).copied().flatten())
+            .collect();
+
+        for cell in grid.cells.iter().filter(|cell| cell.row == 0) {
+            let style = styles.get(cell.node_id).clone();
+            let Some(w) = specified_width(dom, cell.node_id, &style, target) else {
+                continue;
+            };
+            let (_, pb_right, _, pb_left) = style.padding_border(target).trbl();
+            let per_column = (w + pb_left + pb_right) / cell.colspan as f32;
+            for slot in &mut widths[cell.col..cell.col + cell.colspan] {
+                slot.get_or_insert(per_column);
+            }
+        }
+
+        let assigned: f32 = widths.iter().flatten().sum();
+        let unassigned = widths.iter().filter(|w| w.is_none()).count();
+        if unassigned > 0 {
+            let share = ((target - assigned) / unassigned as f32).max(0.0);
+            widths.iter().map(|w| w.unwrap_or(share)).collect()
+        } else if assigned > 0.0 && assigned < target {
+            // All columns specified but narrower than the table: scale them up
+            widths.iter().flatten().map(|w| w * target / assigned).collect()
+        } else {
+            widths.into_iter().flatten().collect()
+        }
+    }
+
+    /// Automatic table layout: share `target` among columns given their min/max widths
+    fn distribute_auto_widths(min: &[f32], max: &[f32], target: f32) -> Vec<f32> {
+        let sum_min: f32 = min.iter().sum();
+        let sum_max: f32 = max.iter().sum();
+
+        if target >= sum_max {
+            // Everything fits: grow columns in proportion to their max-content width
+            let extra = target - sum_max;
+            if sum_max > 0.0 {
+                max.iter().map(|w| w + extra * w / sum_max).collect()
+            } else {
+                vec![target / max.len().max(1) as f32; max.len()]
+            }
+        } else if target > sum_min && sum_max > sum_min {
+            // Interpolate between min-content and max-content
+            let ratio = (target - sum_min) / (sum_max - sum_min);
+            min.iter().zip(max).map(|(lo, hi)| lo + (hi - lo) * ratio).collect()
+        } else {
+            min.to_vec()
+        }
+    }
+
+    /// `vertical-align` of a cell, inherited from its row and row group (or `valign`)
+    fn cell_vertical_align(
+        &self,
+        dom: &Dom,
+        styles: &ComputedStyles,
+        cell: &TableCell,
+        cell_style: &ComputedStyle,
+    ) -> String {
+        let mut node = Some(cell.node_id);
+        while let Some(id) = node {
+            let tag = dom.tag_name(id).unwrap_or("");
+            if !matches!(tag, "td" | "th" | "tr" | "tbody" | "thead" | "tfoot") {
+                break;
+            }
+            let style = if id == cell.node_id { cell_style } else { styles.get(id).as_ref() };
+            let style_value = style.get("vertical-align").map(|v| v.trim().to_lowercase());
+            if let Some(value) = style_value.or_else(|| dom.get_attribute(id, "valign").map(|v| v.trim().to_lowercase())) {
+                return value;
+            }
+            node = dom.nodes[id].parent;
+        }
+        "middle".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::html::tree_builder::HtmlParser;
+    use crate::style::{Selector, Style, Stylesheet};
+
+    fn layout_html(html: &str) -> (Dom, LayoutBox) {
+        layout_html_with_rules(html, &[])
+    }
+
+    fn layout_html_with_rules(html: &str, rules: &[(&str, &[(&str, &str)])]) -> (Dom, LayoutBox) {
+        let dom = HtmlParser::new(html).parse();
+        let mut stylesheet = Stylesheet::new();
+        for (class, declarations) in rules {
+            let mut style = Style::new();
+            for (property, value) in declarations.iter() {
+                style.properties.insert(property.to_string(), value.to_string());
+            }
+            stylesheet.add_rule(Selector::Class(class.to_string()), style);
+        }
+        let root = LayoutEngine::new().layout(&dom, &stylesheet);
+        (dom, root)
+    }
+
+    fn find<'a>(dom: &Dom, layout_box: &'a LayoutBox, tag: &str, nth: usize) -> &'a LayoutBox {
+        fn collect<'a>(dom: &Dom, b: &'a LayoutBox, tag: &str, out: &mut Vec<&'a LayoutBox>) {
+            if b.text_content.is_none() && dom.tag_name(b.node_id) == Some(tag) {
+                out.push(b);
+            }
+            for child in &b.children {
+                collect(dom, child, tag, out);
+            }
+        }
+        let mut found = Vec::new();
+        collect(dom, layout_box, tag, &mut found);
+        found[nth]
+    }
+
+    #[test]
+    fn test_cells_share_rows_and_columns() {
+        let (dom, root) = layout_html("<table><tr><td>a</td><td>bbbbbb</td></tr><tr><td>ccc</td><td>d</td></tr></table>");
+        let (a, b) = (find(&dom, &root, "td", 0), find(&dom, &root, "td", 1));
+        let (c, d) = (find(&dom, &root, "td", 2), find(&dom, &root, "td", 3));
+        assert_eq!(a.dimensions.y, b.dimensions.y);
+        assert!(c.dimensions.y >= a.dimensions.y + a.dimensions.height);
+        assert_eq!(a.dimensions.x, c.dimensions.x);
+        assert_eq!(a.dimensions.width, c.dimensions.width);
+        assert_eq!(b.dimensions.x, d.dimensions.x);
+        assert!(b.dimensions.x >= a.dimensions.x + a.dimensions.width);
+        // Auto layout shrinks the table to its content
+        let table = find(&dom, &root, "table", 0);
+        assert!(table.dimensions.width < 600.0);
+    }
+
+    #[test]
+    fn test_colspan_and_rowspan() {
+        let (dom, root) = layout_html(
+            "<table width=300><tr><td colspan=2>wide</td><td rowspan=2>tall</td></tr>\
+             <tr><td>1</td><td>2</td></tr></table>",
+        );
+        let wide = find(&dom, &root, "td", 0);
+        let tall = find(&dom, &root, "td", 1);
+        let one = find(&dom, &root, "td", 2);
+        let two = find(&dom, &root, "td", 3);
+        assert_eq!(wide.dimensions.x, one.dimensions.x);
+        assert_eq!(wide.dimensions.width, two.dimensions.x + two.dimensions.width - one.dimensions.x);
+        assert_eq!(tall.dimensions.height, two.dimensions.y + two.dimensions.height - wide.dimensions.y);
+        assert!(tall.dimensions.x >= two.dimensions.x + two.dimensions.width);
+    }
+
+    #[test]
+    fn test_fixed_layout_uses_first_row_widths() {
+        let (dom, root) = layout_html_with_rules(
+            "<table class=t><tr><td class=first>a</td><td class=second>b</td></tr></table>",
+            &[
+                ("t", &[("table-layout", "fixed"), ("width", "400px"), ("border-collapse", "separate"), ("border-spacing", "0")]),
+                ("first", &[("width", "100px"), ("padding", "0")]),
+                ("second", &[("padding", "0")]),
+            ],
+        );
+        assert_eq!(find(&dom, &root, "td", 0).dimensions.width, 100.0);
+        assert_eq!(find(&dom, &root, "td", 1).dimensions.width, 300.0);
+    }
+
+    #[test]
+    fn test_vertical_align_in_cells() {
+        let (dom, root) = layout_html_with_rules(
+            "<table><tr><td>short</td><td valign=top>x</td><td class=b>y</td>\
+             <td><div>one</div><div>two</div><div>three</div></td></tr></table>",
+            &[("b", &[("vertical-align", "bottom")])],
+        );
+        let middle = find(&dom, &root, "td", 0);
+        let top = find(&dom, &root, "td", 1);
+        let bottom = find(&dom, &root, "td", 2);
+        let content_y = |cell: &LayoutBox| cell.children[0].dimensions.y;
+        assert_eq!(middle.dimensions.height, top.dimensions.height);
+        assert!(content_y(top) < content_y(middle));
+        assert!(content_y(middle) < content_y(bottom));
+    }
+}
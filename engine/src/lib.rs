@@ -13,7 +13,7 @@ pub use layout::{CSS_PX_SCALE, BASE_FONT_SIZE};
 
 // Re-export commonly used net module items
 pub use net::{
-    NetworkManager, NetworkConfig, FetchedResource,
+    NetworkManager, NetworkConfig, FetchedResource, FetchHandle, Method, RequestBuilder,
     resolve_url, resolve_url_with_base, parse_srcset, select_srcset_image,
     ImageType, detect_image_type,
     HtmlRewriter, RewriterConfig,
@@ -21,6 +21,6 @@ pub use net::{
 
 // Re-export HTML image extraction
 pub use parser::html::{
-    extract_image_refs, extract_base_href, extract_stylesheets,
+    extract_image_refs, extract_base_href, extract_stylesheets, extract_stylesheet_links,
     ImageRef, ImageRefType,
 };
\ No newline at end of file
@@ -4,11 +4,27 @@
 // - URL-keyed caching with ETag/Last-Modified support
 // - Conditional requests (If-None-Match, If-Modified-Since)
 // - 304 Not Modified handling
-// - Cache eviction (LRU-based with size limits)
+// - Freshness from Cache-Control max-age, then Expires, then a heuristic
+//   from Last-Modified; no-cache entries are stored but always revalidated
+// - must-revalidate and stale-while-revalidate, and Vary on request headers
+// - Cache eviction (LRU with size limits, O(log n) per access)
+// - An optional directory that keeps the cache between runs: one file per
+//   response body and an index of URLs, headers and validators
+// - The index is a log: each change appends the entry it stores or the URL
+//   it removes, and the log is compacted into one line per entry when the
+//   cache is opened or once it holds more stale lines than live ones
+// - Body files are read with the cache unlocked, so a slow disk holds up
+//   only the lookup that reads it
 
-use std::collections::HashMap;
-use std::sync::RwLock;
-use std::time::{Duration, Instant};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, SystemTime};
+
+use super::cookies::parse_cookie_date;
+use super::disk::{append, escape, parse_unix_millis, unescape, unix_millis, write_atomically};
 
 /// Maximum cache size in bytes (50 MB default)
 const DEFAULT_MAX_CACHE_SIZE: usize = 50 * 1024 * 1024;
@@ -16,39 +32,118 @@ const DEFAULT_MAX_CACHE_SIZE: usize = 50 * 1024 * 1024;
 /// Maximum age for cached entries (24 hours default)
 const DEFAULT_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
 
+const INDEX_FILE: &str = "index";
+const INDEX_HEADER: &str = "# grob cache index 1";
+/// Stale lines the index may hold beyond one per entry before it is
+/// compacted
+const INDEX_SLACK: usize = 64;
+
 /// Represents HTTP cache validation headers
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct CacheHeaders {
     pub etag: Option<String>,
     pub last_modified: Option<String>,
     pub cache_control: Option<String>,
     pub expires: Option<String>,
+    /// When the server sent the response, the base for Expires
+    pub date: Option<String>,
+    /// The request headers the response depends on
+    pub vary: Option<String>,
 }
 
 impl CacheHeaders {
-    /// Check if the cache headers indicate the resource should not be cached
+    /// Check if the cache headers forbid using the resource without
+    /// revalidating it (`no-cache`) or storing it at all (`no-store`)
     pub fn is_no_cache(&self) -> bool {
-        if let Some(ref cc) = self.cache_control {
-            let cc = cc.to_lowercase();
-            cc.contains("no-cache") || cc.contains("no-store")
-        } else {
-            false
-        }
+        self.has_directive("no-cache") || self.is_no_store()
+    }
+
+    /// Check if the resource must not be stored
+    pub fn is_no_store(&self) -> bool {
+        self.has_directive("no-store")
+    }
+
+    /// Check if a stale copy must never be used without revalidating it
+    pub fn must_revalidate(&self) -> bool {
+        self.has_directive("must-revalidate") || self.has_directive("no-cache")
     }
-    
+
     /// Parse max-age from Cache-Control header
     pub fn max_age(&self) -> Option<Duration> {
-        if let Some(ref cc) = self.cache_control {
-            for part in cc.split(',') {
-                let part = part.trim().to_lowercase();
-                if part.starts_with("max-age=") {
-                    if let Ok(secs) = part.trim_start_matches("max-age=").parse::<u64>() {
-                        return Some(Duration::from_secs(secs));
-                    }
-                }
-            }
+        self.directive_seconds("max-age")
+    }
+
+    /// How long past its freshness a stale copy may be used while it is
+    /// revalidated in the background
+    pub fn stale_while_revalidate(&self) -> Option<Duration> {
+        self.directive_seconds("stale-while-revalidate")
+    }
+
+    /// The headers of a 304 response applied on top of these
+    pub fn updated_with(&self, newer: &CacheHeaders) -> CacheHeaders {
+        CacheHeaders {
+            etag: newer.etag.clone().or_else(|| self.etag.clone()),
+            last_modified: newer.last_modified.clone().or_else(|| self.last_modified.clone()),
+            cache_control: newer.cache_control.clone().or_else(|| self.cache_control.clone()),
+            expires: newer.expires.clone().or_else(|| self.expires.clone()),
+            date: newer.date.clone().or_else(|| self.date.clone()),
+            vary: newer.vary.clone().or_else(|| self.vary.clone()),
+        }
+    }
+
+    /// How long a response with these headers received at `now` stays
+    /// fresh: max-age, else Expires less Date, else a tenth of the time
+    /// since Last-Modified, capped at `default`, else `default`
+    pub fn freshness_lifetime(&self, now: SystemTime, default: Duration) -> Duration {
+        if self.has_directive("no-cache") {
+            return Duration::ZERO;
+        }
+        if let Some(max_age) = self.max_age() {
+            return max_age;
+        }
+        let date = self.date.as_deref().and_then(parse_cookie_date).unwrap_or(now);
+        if let Some(expires) = &self.expires {
+            // An invalid date such as `0` means already expired
+            return parse_cookie_date(expires)
+                .and_then(|expires| expires.duration_since(date).ok())
+                .unwrap_or(Duration::ZERO);
+        }
+        match self.last_modified.as_deref().and_then(parse_cookie_date) {
+            Some(modified) => date.duration_since(modified).map(|age| age / 10).unwrap_or(Duration::ZERO).min(default),
+            None => default,
         }
-        None
+    }
+
+    /// The lowercase header names of Vary; None for `Vary: *`, which no
+    /// request matches
+    fn vary_names(&self) -> Option<Vec<String>> {
+        let vary = self.vary.as_deref().unwrap_or("");
+        let names: Vec<String> = vary.split(',')
+            .map(|name| name.trim().to_ascii_lowercase())
+            .filter(|name| !name.is_empty())
+            .collect();
+        (!names.iter().any(|name| name == "*")).then_some(names)
+    }
+
+    /// The Cache-Control directives, lowercase, with their values unquoted
+    fn directives(&self) -> impl Iterator<Item = (String, Option<String>)> + '_ {
+        self.cache_control.iter()
+            .flat_map(|cc| cc.split(','))
+            .map(|part| match part.split_once('=') {
+                Some((name, value)) => (name.trim().to_lowercase(), Some(value.trim().trim_matches('"').to_string())),
+                None => (part.trim().to_lowercase(), None),
+            })
+    }
+
+    fn has_directive(&self, name: &str) -> bool {
+        self.directives().any(|(directive, _)| directive == name)
+    }
+
+    fn directive_seconds(&self, name: &str) -> Option<Duration> {
+        self.directives()
+            .find(|(directive, _)| directive == name)
+            .and_then(|(_, value)| value?.parse::<u64>().ok())
+            .map(Duration::from_secs)
     }
 }
 
@@ -59,33 +154,57 @@ pub struct CacheEntry {
     pub data: Vec<u8>,
     /// Content-Type of the resource
     pub content_type: String,
-    /// ETag for conditional requests
-    pub etag: Option<String>,
-    /// Last-Modified for conditional requests
-    pub last_modified: Option<String>,
-    /// When this entry was cached
-    pub cached_at: Instant,
-    /// How long this entry is valid
+    /// Validators and freshness headers of the response
+    pub headers: CacheHeaders,
+    /// The request headers Vary named, with the values they had
+    pub vary: Vec<(String, String)>,
+    /// When this entry was cached or last revalidated
+    pub cached_at: SystemTime,
+    /// How long this entry is fresh
     pub max_age: Duration,
     /// Last time this entry was accessed (for LRU)
-    pub last_accessed: Instant,
+    pub last_accessed: SystemTime,
 }
 
 impl CacheEntry {
     /// Check if this entry is still fresh
     pub fn is_fresh(&self) -> bool {
-        self.cached_at.elapsed() < self.max_age
+        self.age(SystemTime::now()) < self.max_age
     }
-    
+
     /// Get the size of this entry in bytes
     pub fn size(&self) -> usize {
         self.data.len()
     }
-    
+
     /// Update last accessed time
     pub fn touch(&mut self) {
-        self.last_accessed = Instant::now();
+        self.last_accessed = SystemTime::now();
+    }
+
+    /// Time since it was cached; a clock that went back counts as none
+    fn age(&self, now: SystemTime) -> Duration {
+        now.duration_since(self.cached_at).unwrap_or(Duration::ZERO)
+    }
+
+    /// Whether a request with `request_headers` may use it
+    fn vary_matches(&self, request_headers: &[(String, String)]) -> bool {
+        self.vary.iter().all(|(name, value)| header_value(request_headers, name) == *value)
     }
+
+    /// Whether it may be used stale while a fresh copy is fetched behind it
+    fn in_stale_window(&self, now: SystemTime) -> bool {
+        let window = self.headers.stale_while_revalidate().unwrap_or(Duration::ZERO);
+        !self.headers.must_revalidate() && self.age(now) < self.max_age.saturating_add(window)
+    }
+}
+
+/// The value of header `name` among `headers`, empty when absent
+fn header_value(headers: &[(String, String)], name: &str) -> String {
+    headers.iter()
+        .find(|(header, _)| header.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.trim().to_string())
+        .unwrap_or_default()
 }
 
 /// Result of a cache lookup
@@ -100,6 +219,9 @@ pub enum CacheLookup {
         etag: Option<String>,
         last_modified: Option<String>,
     },
+    /// Stale entry within its stale-while-revalidate window - use it and
+    /// revalidate in the background
+    StaleWhileRevalidate(CacheEntry),
 }
 
 /// Result of a conditional request
@@ -115,11 +237,74 @@ pub enum ConditionalResult {
     },
 }
 
+/// An entry as the cache holds it: its body in memory, or in a file of the
+/// cache directory
+struct Record {
+    /// The entry, with empty `data` when the body is in a file
+    entry: CacheEntry,
+    file: Option<u64>,
+    size: usize,
+    /// Position in the LRU order
+    tick: u64,
+}
+
+#[derive(Default)]
+struct CacheState {
+    records: HashMap<String, Record>,
+    /// URLs by last access, least recent first
+    lru: BTreeMap<u64, String>,
+    next_tick: u64,
+    total_size: usize,
+    next_file: u64,
+    /// Index lines not yet appended to the file
+    journal: String,
+    /// Lines in the index file, live or not
+    index_lines: usize,
+}
+
+impl CacheState {
+    /// Move `url` to the most recently used end
+    fn touch(&mut self, url: &str) {
+        let tick = self.next_tick;
+        if let Some(record) = self.records.get_mut(url) {
+            self.lru.remove(&record.tick);
+            record.tick = tick;
+            record.entry.touch();
+            self.lru.insert(tick, url.to_string());
+            self.next_tick += 1;
+        }
+    }
+
+    fn insert(&mut self, url: String, mut record: Record) {
+        record.tick = self.next_tick;
+        self.next_tick += 1;
+        self.total_size += record.size;
+        self.lru.insert(record.tick, url.clone());
+        self.records.insert(url, record);
+    }
+
+    fn remove(&mut self, url: &str) -> Option<Record> {
+        let record = self.records.remove(url)?;
+        self.lru.remove(&record.tick);
+        self.total_size -= record.size;
+        Some(record)
+    }
+
+    fn pop_least_recently_used(&mut self) -> Option<(String, Record)> {
+        let (_, url) = self.lru.pop_first()?;
+        let record = self.records.remove(&url)?;
+        self.total_size -= record.size;
+        Some((url, record))
+    }
+}
+
 /// HTTP asset cache
 pub struct AssetCache {
-    entries: RwLock<HashMap<String, CacheEntry>>,
+    state: Mutex<CacheState>,
     max_size: usize,
     max_age: Duration,
+    /// Where bodies and the index are kept; None keeps everything in memory
+    dir: Option<PathBuf>,
 }
 
 impl Default for AssetCache {
@@ -131,153 +316,425 @@ impl Default for AssetCache {
 impl AssetCache {
     /// Create a new cache with default settings
     pub fn new() -> Self {
-        Self {
-            entries: RwLock::new(HashMap::new()),
-            max_size: DEFAULT_MAX_CACHE_SIZE,
-            max_age: DEFAULT_MAX_AGE,
-        }
+        Self::with_config(DEFAULT_MAX_CACHE_SIZE, DEFAULT_MAX_AGE)
     }
-    
+
     /// Create a cache with custom settings
     pub fn with_config(max_size: usize, max_age: Duration) -> Self {
         Self {
-            entries: RwLock::new(HashMap::new()),
+            state: Mutex::new(CacheState::default()),
             max_size,
             max_age,
+            dir: None,
         }
     }
-    
-    /// Look up an entry in the cache
-    pub fn lookup(&self, url: &str) -> CacheLookup {
-        let mut entries = self.entries.write().unwrap();
-        
-        if let Some(entry) = entries.get_mut(url) {
-            entry.touch();
-            
-            if entry.is_fresh() {
-                CacheLookup::Hit(entry.clone())
-            } else {
-                CacheLookup::Stale {
-                    etag: entry.etag.clone(),
-                    last_modified: entry.last_modified.clone(),
+
+    /// Open the cache kept in `dir`, creating it if needed, with default
+    /// settings
+    pub fn open(dir: impl Into<PathBuf>) -> Self {
+        Self::open_with_config(dir, DEFAULT_MAX_CACHE_SIZE, DEFAULT_MAX_AGE)
+    }
+
+    /// Open the cache kept in `dir` with custom settings; entries whose
+    /// body file has gone are dropped, as are files no entry names
+    pub fn open_with_config(dir: impl Into<PathBuf>, max_size: usize, max_age: Duration) -> Self {
+        let dir = dir.into();
+        let mut state = CacheState::default();
+        let index = match fs::read_to_string(dir.join(INDEX_FILE)) {
+            Ok(index) => index,
+            Err(e) => {
+                if e.kind() != io::ErrorKind::NotFound {
+                    eprintln!("Could not read cache index in {}: {}", dir.display(), e);
+                }
+                String::new()
+            }
+        };
+
+        // Later lines replace earlier ones for the same URL
+        let mut latest: HashMap<String, Option<Record>> = HashMap::new();
+        for line in index.lines().filter(|line| !line.starts_with('#') && !line.is_empty()) {
+            if let Some((url, record)) = read_line(line) {
+                latest.insert(url, record);
+            }
+        }
+        let mut records: Vec<(String, Record)> = latest.into_iter()
+            .filter_map(|(url, record)| Some((url, record?)))
+            .filter(|(_, record)| {
+                let file = dir.join(record.file.unwrap_or_default().to_string());
+                fs::metadata(file).is_ok_and(|meta| meta.len() == record.size as u64)
+            })
+            .collect();
+        records.sort_by_key(|(_, record)| record.entry.last_accessed);
+        for (url, record) in records {
+            state.next_file = state.next_file.max(record.file.unwrap_or_default() + 1);
+            state.insert(url, record);
+        }
+
+        // Bodies the index lost track of
+        if let Ok(files) = fs::read_dir(&dir) {
+            let kept: HashSet<u64> = state.records.values().filter_map(|record| record.file).collect();
+            for file in files.flatten() {
+                let id = file.file_name().to_str().and_then(|name| name.parse::<u64>().ok());
+                if id.is_some_and(|id| !kept.contains(&id)) {
+                    let _ = fs::remove_file(file.path());
                 }
             }
+        }
+
+        let cache = Self { state: Mutex::new(state), max_size, max_age, dir: Some(dir) };
+        {
+            let mut state = cache.state.lock().unwrap();
+            cache.evict_if_needed(&mut state, 0);
+            cache.compact_index(&mut state);
+        }
+        cache
+    }
+
+    /// Look up an entry in the cache
+    pub fn lookup(&self, url: &str) -> CacheLookup {
+        self.lookup_with_headers(url, &[])
+    }
+
+    /// Look up an entry for a request with `request_headers`, which decide
+    /// whether an entry stored with Vary applies
+    pub fn lookup_with_headers(&self, url: &str, request_headers: &[(String, String)]) -> CacheLookup {
+        let mut state = self.state.lock().unwrap();
+
+        match state.records.get(url) {
+            Some(record) if record.entry.vary_matches(request_headers) => {}
+            _ => return CacheLookup::Miss,
+        }
+        state.touch(url);
+
+        let now = SystemTime::now();
+        let entry = &state.records[url].entry;
+        if entry.age(now) < entry.max_age {
+            self.with_body(state, url).map_or(CacheLookup::Miss, CacheLookup::Hit)
+        } else if entry.in_stale_window(now) {
+            self.with_body(state, url).map_or(CacheLookup::Miss, CacheLookup::StaleWhileRevalidate)
         } else {
-            CacheLookup::Miss
+            let headers = &entry.headers;
+            CacheLookup::Stale {
+                etag: headers.etag.clone(),
+                last_modified: headers.last_modified.clone(),
+            }
         }
     }
-    
+
+    /// A stale entry to use when the network cannot be reached, unless its
+    /// headers demand revalidation
+    pub fn lookup_stale(&self, url: &str, request_headers: &[(String, String)]) -> Option<CacheEntry> {
+        let state = self.state.lock().unwrap();
+        let record = state.records.get(url)?;
+        if !record.entry.vary_matches(request_headers) || record.entry.headers.must_revalidate() {
+            return None;
+        }
+        self.with_body(state, url)
+    }
+
     /// Store an entry in the cache
     pub fn store(&self, url: &str, data: Vec<u8>, content_type: String, headers: CacheHeaders) {
-        if headers.is_no_cache() {
+        self.store_with_headers(url, &[], data, content_type, headers);
+    }
+
+    /// Store the response to a request with `request_headers`, remembering
+    /// the ones its Vary header names
+    pub fn store_with_headers(
+        &self,
+        url: &str,
+        request_headers: &[(String, String)],
+        data: Vec<u8>,
+        content_type: String,
+        headers: CacheHeaders,
+    ) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(old) = state.remove(url) {
+            self.delete_body(&old);
+            self.journal(&mut state, || write_removal(url));
+        }
+
+        let Some(vary_names) = headers.vary_names() else {
+            self.flush_index(&mut state);
+            return;
+        };
+        if headers.is_no_store() || data.len() > self.max_size {
+            self.flush_index(&mut state);
             return;
         }
-        
-        let max_age = headers.max_age().unwrap_or(self.max_age);
-        let now = Instant::now();
-        
-        let entry = CacheEntry {
+
+        let now = SystemTime::now();
+        let size = data.len();
+        let mut entry = CacheEntry {
             data,
             content_type,
-            etag: headers.etag,
-            last_modified: headers.last_modified,
+            vary: vary_names.into_iter().map(|name| {
+                let value = header_value(request_headers, &name);
+                (name, value)
+            }).collect(),
+            max_age: headers.freshness_lifetime(now, self.max_age),
+            headers,
             cached_at: now,
-            max_age,
             last_accessed: now,
         };
-        
-        let entry_size = entry.size();
-        
-        let mut entries = self.entries.write().unwrap();
-        
+
         // Evict entries if we're over the size limit
-        self.evict_if_needed(&mut entries, entry_size);
-        
-        entries.insert(url.to_string(), entry);
+        self.evict_if_needed(&mut state, size);
+
+        let mut file = None;
+        if let Some(dir) = &self.dir {
+            let id = state.next_file;
+            match write_atomically(&dir.join(id.to_string()), &entry.data) {
+                Ok(()) => {
+                    state.next_file += 1;
+                    file = Some(id);
+                    entry.data = Vec::new();
+                }
+                Err(e) => {
+                    eprintln!("Could not write cache entry for {}: {}", url, e);
+                    self.flush_index(&mut state);
+                    return;
+                }
+            }
+        }
+        let record = Record { entry, file, size, tick: 0 };
+        self.journal(&mut state, || write_record(url, &record));
+        state.insert(url.to_string(), record);
+        self.flush_index(&mut state);
     }
-    
+
     /// Update an entry after receiving a 304 Not Modified
     pub fn refresh(&self, url: &str) {
-        let mut entries = self.entries.write().unwrap();
-        
-        if let Some(entry) = entries.get_mut(url) {
-            entry.cached_at = Instant::now();
-            entry.touch();
+        let mut state = self.state.lock().unwrap();
+
+        if let Some(record) = state.records.get_mut(url) {
+            record.entry.cached_at = SystemTime::now();
+        }
+        state.touch(url);
+        self.journal_record(&mut state, url);
+        self.flush_index(&mut state);
+    }
+
+    /// Update an entry after a 304 Not Modified carrying `headers`, which
+    /// replace the stored ones they name and may change its freshness;
+    /// returns the entry, which the 304 has validated even if it is not fresh
+    pub fn refresh_with_headers(&self, url: &str, headers: &CacheHeaders) -> Option<CacheEntry> {
+        let mut state = self.state.lock().unwrap();
+        let now = SystemTime::now();
+
+        if let Some(record) = state.records.get_mut(url) {
+            record.entry.headers = record.entry.headers.updated_with(headers);
+            record.entry.max_age = record.entry.headers.freshness_lifetime(now, self.max_age);
+            record.entry.cached_at = now;
         }
+        state.touch(url);
+        self.journal_record(&mut state, url);
+        self.flush_index(&mut state);
+        self.with_body(state, url)
     }
-    
+
     /// Remove an entry from the cache
     pub fn remove(&self, url: &str) {
-        let mut entries = self.entries.write().unwrap();
-        entries.remove(url);
+        let mut state = self.state.lock().unwrap();
+        if let Some(record) = state.remove(url) {
+            self.delete_body(&record);
+            self.journal(&mut state, || write_removal(url));
+            self.flush_index(&mut state);
+        }
     }
-    
+
     /// Clear the entire cache
     pub fn clear(&self) {
-        let mut entries = self.entries.write().unwrap();
-        entries.clear();
+        let mut state = self.state.lock().unwrap();
+        for record in state.records.values() {
+            self.delete_body(record);
+        }
+        *state = CacheState { next_file: state.next_file, ..CacheState::default() };
+        self.compact_index(&mut state);
     }
-    
+
     /// Get cache statistics
     pub fn stats(&self) -> CacheStats {
-        let entries = self.entries.read().unwrap();
-        let total_size: usize = entries.values().map(|e| e.size()).sum();
-        let fresh_count = entries.values().filter(|e| e.is_fresh()).count();
-        
+        let state = self.state.lock().unwrap();
+        let fresh_count = state.records.values().filter(|record| record.entry.is_fresh()).count();
+
         CacheStats {
-            entry_count: entries.len(),
+            entry_count: state.records.len(),
             fresh_count,
-            total_size,
+            total_size: state.total_size,
             max_size: self.max_size,
         }
     }
-    
-    /// Evict entries using LRU policy if needed
-    fn evict_if_needed(&self, entries: &mut HashMap<String, CacheEntry>, needed_space: usize) {
-        let current_size: usize = entries.values().map(|e| e.size()).sum();
-        
-        if current_size + needed_space <= self.max_size {
+
+    /// Evict least recently used entries until `needed_space` more bytes fit
+    fn evict_if_needed(&self, state: &mut CacheState, needed_space: usize) {
+        while state.total_size + needed_space > self.max_size {
+            match state.pop_least_recently_used() {
+                Some((url, record)) => {
+                    self.delete_body(&record);
+                    self.journal(state, || write_removal(&url));
+                }
+                None => break,
+            }
+        }
+    }
+
+    /// The entry for `url` with its body, read from its file if need be
+    /// once `state` is unlocked; an entry whose file cannot be read is
+    /// dropped
+    fn with_body(&self, state: MutexGuard<CacheState>, url: &str) -> Option<CacheEntry> {
+        let record = state.records.get(url)?;
+        let (Some(dir), Some(file)) = (&self.dir, record.file) else {
+            return Some(record.entry.clone());
+        };
+        let (entry, size) = (record.entry.clone(), record.size);
+        drop(state);
+
+        match fs::read(dir.join(file.to_string())) {
+            Ok(data) if data.len() == size => Some(CacheEntry { data, ..entry }),
+            _ => {
+                eprintln!("Cache entry for {} is missing its body", url);
+                let mut state = self.state.lock().unwrap();
+                // Unless the entry was replaced in the meantime
+                if state.records.get(url).is_some_and(|record| record.file == Some(file)) {
+                    state.remove(url);
+                    self.journal(&mut state, || write_removal(url));
+                    self.flush_index(&mut state);
+                }
+                None
+            }
+        }
+    }
+
+    fn delete_body(&self, record: &Record) {
+        if let (Some(dir), Some(file)) = (&self.dir, record.file) {
+            let _ = fs::remove_file(dir.join(file.to_string()));
+        }
+    }
+
+    /// Queue an index line, when there is an index to keep
+    fn journal(&self, state: &mut CacheState, line: impl FnOnce() -> String) {
+        if self.dir.is_some() {
+            state.journal.push_str(&line());
+            state.journal.push('\n');
+        }
+    }
+
+    fn journal_record(&self, state: &mut CacheState, url: &str) {
+        if let Some(record) = state.records.get(url) {
+            let line = write_record(url, record);
+            self.journal(state, || line);
+        }
+    }
+
+    /// Append the queued lines to the index file, or compact it instead if
+    /// it has outgrown the entries
+    fn flush_index(&self, state: &mut CacheState) {
+        let Some(dir) = &self.dir else {
+            return;
+        };
+        if state.journal.is_empty() {
             return;
         }
-        
-        // First, remove stale entries
-        let stale_urls: Vec<String> = entries
-            .iter()
-            .filter(|(_, e)| !e.is_fresh())
-            .map(|(url, _)| url.clone())
-            .collect();
-        
-        for url in stale_urls {
-            entries.remove(&url);
+        let lines = state.journal.lines().count();
+        if state.index_lines + lines > 2 * state.records.len() + INDEX_SLACK {
+            self.compact_index(state);
+            return;
+        }
+        match append(&dir.join(INDEX_FILE), state.journal.as_bytes()) {
+            Ok(()) => state.index_lines += lines,
+            Err(e) => eprintln!("Could not save cache index in {}: {}", dir.display(), e),
         }
-        
-        let current_size: usize = entries.values().map(|e| e.size()).sum();
-        if current_size + needed_space <= self.max_size {
+        state.journal.clear();
+    }
+
+    /// Rewrite the index file with one line per entry, least recently used
+    /// first
+    fn compact_index(&self, state: &mut CacheState) {
+        state.journal.clear();
+        let Some(dir) = &self.dir else {
             return;
+        };
+        let mut index = format!("{}\n", INDEX_HEADER);
+        for url in state.lru.values() {
+            index.push_str(&write_record(url, &state.records[url]));
+            index.push('\n');
         }
-        
-        // Then, remove LRU entries until we have enough space
-        loop {
-            let current_size: usize = entries.values().map(|e| e.size()).sum();
-            if current_size + needed_space <= self.max_size || entries.is_empty() {
-                break;
-            }
-            
-            // Find the LRU entry
-            let lru_url = entries
-                .iter()
-                .min_by_key(|(_, e)| e.last_accessed)
-                .map(|(url, _)| url.clone());
-            
-            if let Some(url) = lru_url {
-                entries.remove(&url);
-            } else {
-                break;
-            }
+        match write_atomically(&dir.join(INDEX_FILE), index.as_bytes()) {
+            Ok(()) => state.index_lines = state.records.len(),
+            Err(e) => eprintln!("Could not save cache index in {}: {}", dir.display(), e),
         }
     }
 }
 
+/// One line of the index: tab-separated fields, with absent headers empty
+fn write_record(url: &str, record: &Record) -> String {
+    let entry = &record.entry;
+    let header = |value: &Option<String>| escape(value.as_deref().unwrap_or(""));
+    let vary: Vec<String> = entry.vary.iter().map(|(name, value)| format!("{}:{}", name, value)).collect();
+    [
+        escape(url),
+        record.file.unwrap_or_default().to_string(),
+        record.size.to_string(),
+        escape(&entry.content_type),
+        unix_millis(entry.cached_at).to_string(),
+        unix_millis(entry.last_accessed).to_string(),
+        entry.max_age.as_millis().to_string(),
+        header(&entry.headers.etag),
+        header(&entry.headers.last_modified),
+        header(&entry.headers.cache_control),
+        header(&entry.headers.expires),
+        header(&entry.headers.date),
+        header(&entry.headers.vary),
+        escape(&vary.join("\n")),
+    ]
+    .join("\t")
+}
+
+/// An index line for a URL no longer cached
+fn write_removal(url: &str) -> String {
+    format!("-\t{}", escape(url))
+}
+
+/// An index line: an entry, or None for a removal
+fn read_line(line: &str) -> Option<(String, Option<Record>)> {
+    match line.split_once('\t') {
+        Some(("-", url)) if !url.contains('\t') => Some((unescape(url), None)),
+        _ => read_record(line).map(|(url, record)| (url, Some(record))),
+    }
+}
+
+fn read_record(line: &str) -> Option<(String, Record)> {
+    let fields: Vec<&str> = line.split('\t').collect();
+    let [url, file, size, content_type, cached_at, last_accessed, max_age, etag, last_modified, cache_control, expires, date, vary, vary_values] =
+        fields[..]
+    else {
+        return None;
+    };
+    let header = |field: &str| Some(unescape(field)).filter(|value| !value.is_empty());
+    let vary_values = unescape(vary_values);
+    let entry = CacheEntry {
+        data: Vec::new(),
+        content_type: unescape(content_type),
+        headers: CacheHeaders {
+            etag: header(etag),
+            last_modified: header(last_modified),
+            cache_control: header(cache_control),
+            expires: header(expires),
+            date: header(date),
+            vary: header(vary),
+        },
+        vary: vary_values.split('\n')
+            .filter_map(|pair| pair.split_once(':'))
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect(),
+        cached_at: parse_unix_millis(cached_at)?,
+        max_age: Duration::from_millis(max_age.parse().ok()?),
+        last_accessed: parse_unix_millis(last_accessed)?,
+    };
+    let record = Record { entry, file: Some(file.parse().ok()?), size: size.parse().ok()?, tick: 0 };
+    Some((unescape(url), record))
+}
+
 /// Cache statistics
 #[derive(Debug)]
 pub struct CacheStats {
@@ -300,104 +757,271 @@ impl CacheStats {
 /// Build conditional request headers from cache entry
 pub fn build_conditional_headers(etag: Option<&str>, last_modified: Option<&str>) -> Vec<(String, String)> {
     let mut headers = Vec::new();
-    
+
     if let Some(etag) = etag {
         headers.push(("If-None-Match".to_string(), etag.to_string()));
     }
-    
+
     if let Some(lm) = last_modified {
         headers.push(("If-Modified-Since".to_string(), lm.to_string()));
     }
-    
+
     headers
 }
 
 /// Extract cache-relevant headers from a response
 pub fn extract_cache_headers(headers: &[(String, String)]) -> CacheHeaders {
     let mut result = CacheHeaders::default();
-    
+
     for (name, value) in headers {
         match name.to_lowercase().as_str() {
             "etag" => result.etag = Some(value.clone()),
             "last-modified" => result.last_modified = Some(value.clone()),
             "cache-control" => result.cache_control = Some(value.clone()),
             "expires" => result.expires = Some(value.clone()),
+            "date" => result.date = Some(value.clone()),
+            "vary" => {
+                // Several Vary headers name the union of their headers
+                result.vary = Some(match result.vary.take() {
+                    Some(vary) => format!("{}, {}", vary, value),
+                    None => value.clone(),
+                });
+            }
             _ => {}
         }
     }
-    
+
     result
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    
+
+    fn cache_control(value: &str) -> CacheHeaders {
+        CacheHeaders { cache_control: Some(value.to_string()), ..Default::default() }
+    }
+
     #[test]
     fn test_cache_store_and_lookup() {
         let cache = AssetCache::new();
-        
+
         let data = b"test image data".to_vec();
         let headers = CacheHeaders {
             etag: Some("\"abc123\"".to_string()),
             ..Default::default()
         };
-        
+
         cache.store("https://example.com/image.png", data.clone(), "image/png".to_string(), headers);
-        
+
         match cache.lookup("https://example.com/image.png") {
             CacheLookup::Hit(entry) => {
                 assert_eq!(entry.data, data);
                 assert_eq!(entry.content_type, "image/png");
-                assert_eq!(entry.etag, Some("\"abc123\"".to_string()));
+                assert_eq!(entry.headers.etag, Some("\"abc123\"".to_string()));
             }
             _ => panic!("Expected cache hit"),
         }
     }
-    
+
     #[test]
     fn test_cache_miss() {
         let cache = AssetCache::new();
-        
+
         match cache.lookup("https://example.com/nonexistent.png") {
             CacheLookup::Miss => {}
             _ => panic!("Expected cache miss"),
         }
     }
-    
+
     #[test]
     fn test_cache_no_store() {
         let cache = AssetCache::new();
-        
+
         let headers = CacheHeaders {
             cache_control: Some("no-store".to_string()),
             ..Default::default()
         };
-        
+
         cache.store("https://example.com/private.png", vec![1, 2, 3], "image/png".to_string(), headers);
-        
+
         match cache.lookup("https://example.com/private.png") {
             CacheLookup::Miss => {}
             _ => panic!("Expected cache miss for no-store"),
         }
     }
-    
+
     #[test]
     fn test_cache_headers_max_age() {
         let headers = CacheHeaders {
             cache_control: Some("max-age=3600, public".to_string()),
             ..Default::default()
         };
-        
+
         assert_eq!(headers.max_age(), Some(Duration::from_secs(3600)));
     }
-    
+
     #[test]
     fn test_build_conditional_headers() {
         let headers = build_conditional_headers(Some("\"abc\""), Some("Sat, 01 Jan 2000 00:00:00 GMT"));
-        
+
         assert_eq!(headers.len(), 2);
         assert!(headers.iter().any(|(k, v)| k == "If-None-Match" && v == "\"abc\""));
         assert!(headers.iter().any(|(k, v)| k == "If-Modified-Since" && v == "Sat, 01 Jan 2000 00:00:00 GMT"));
     }
+
+    #[test]
+    fn test_freshness_lifetime() {
+        let date = "Sun, 06 Nov 1994 08:49:37 GMT";
+        let now = parse_cookie_date(date).unwrap();
+        let default = DEFAULT_MAX_AGE;
+        let expires = CacheHeaders {
+            expires: Some("Sun, 06 Nov 1994 09:49:37 GMT".to_string()),
+            date: Some(date.to_string()),
+            ..Default::default()
+        };
+        assert_eq!(expires.freshness_lifetime(now, default), Duration::from_secs(3600));
+        // max-age wins over Expires, and no-cache over both
+        let max_age = CacheHeaders { cache_control: Some("max-age=60".to_string()), ..expires.clone() };
+        assert_eq!(max_age.freshness_lifetime(now, default), Duration::from_secs(60));
+        assert_eq!(cache_control("no-cache, max-age=60").freshness_lifetime(now, default), Duration::ZERO);
+        let invalid = CacheHeaders { expires: Some("0".to_string()), ..Default::default() };
+        assert_eq!(invalid.freshness_lifetime(now, default), Duration::ZERO);
+        // A tenth of the time since the last change
+        let modified = CacheHeaders {
+            last_modified: Some("Sun, 06 Nov 1994 07:49:37 GMT".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(modified.freshness_lifetime(now, default), Duration::from_secs(360));
+        assert_eq!(CacheHeaders::default().freshness_lifetime(now, default), default);
+    }
+
+    #[test]
+    fn test_stale_entries() {
+        let cache = AssetCache::new();
+        cache.store("https://example.com/swr", vec![1], "text/css".to_string(), cache_control("max-age=0, stale-while-revalidate=60"));
+        assert!(matches!(cache.lookup("https://example.com/swr"), CacheLookup::StaleWhileRevalidate(_)));
+
+        let validated = CacheHeaders { etag: Some("\"v1\"".to_string()), ..cache_control("max-age=0, must-revalidate, stale-while-revalidate=60") };
+        cache.store("https://example.com/strict", vec![2], "text/css".to_string(), validated);
+        match cache.lookup("https://example.com/strict") {
+            CacheLookup::Stale { etag, .. } => assert_eq!(etag.as_deref(), Some("\"v1\"")),
+            other => panic!("Expected stale entry, got {:?}", other),
+        }
+        assert!(cache.lookup_stale("https://example.com/strict", &[]).is_none());
+        assert!(cache.lookup_stale("https://example.com/swr", &[]).is_some());
+
+        // A 304 can make an entry fresh again for as long as it says
+        cache.refresh_with_headers("https://example.com/strict", &cache_control("max-age=600"));
+        assert!(matches!(cache.lookup("https://example.com/strict"), CacheLookup::Hit(_)));
+    }
+
+    #[test]
+    fn test_vary() {
+        let cache = AssetCache::new();
+        let url = "https://example.com/greeting";
+        let headers = extract_cache_headers(&[
+            ("Vary".to_string(), "Accept-Language".to_string()),
+            ("Vary".to_string(), "Cookie".to_string()),
+        ]);
+        let request = |language: &str| vec![("Accept-Language".to_string(), language.to_string())];
+        cache.store_with_headers(url, &request("fr"), b"bonjour".to_vec(), "text/plain".to_string(), headers.clone());
+        assert!(matches!(cache.lookup_with_headers(url, &request("fr")), CacheLookup::Hit(_)));
+        assert!(matches!(cache.lookup_with_headers(url, &request("de")), CacheLookup::Miss));
+        let with_cookie = [request("fr"), vec![("cookie".to_string(), "id=1".to_string())]].concat();
+        assert!(matches!(cache.lookup_with_headers(url, &with_cookie), CacheLookup::Miss));
+
+        let star = CacheHeaders { vary: Some("*".to_string()), ..Default::default() };
+        cache.store(url, vec![1], "text/plain".to_string(), star);
+        assert!(matches!(cache.lookup(url), CacheLookup::Miss));
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        let cache = AssetCache::with_config(10, DEFAULT_MAX_AGE);
+        for name in ["a", "b", "c"] {
+            cache.store(name, vec![0; 4], "text/plain".to_string(), CacheHeaders::default());
+        }
+        // Storing c pushed out a, the least recently used
+        assert!(matches!(cache.lookup("a"), CacheLookup::Miss));
+        assert!(matches!(cache.lookup("b"), CacheLookup::Hit(_)));
+        cache.store("d", vec![0; 4], "text/plain".to_string(), CacheHeaders::default());
+        assert!(matches!(cache.lookup("c"), CacheLookup::Miss));
+        assert!(matches!(cache.lookup("b"), CacheLookup::Hit(_)));
+        assert_eq!(cache.stats().total_size, 8);
+        // Too big for the cache at all
+        cache.store("e", vec![0; 11], "text/plain".to_string(), CacheHeaders::default());
+        assert!(matches!(cache.lookup("e"), CacheLookup::Miss));
+        assert_eq!(cache.stats().entry_count, 2);
+    }
+
+    #[test]
+    fn test_cache_survives_reopening() {
+        let dir = std::env::temp_dir().join(format!("grob-cache-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let headers = CacheHeaders {
+            etag: Some("\"tab\there\"".to_string()),
+            date: Some("Sun, 06 Nov 1994 08:49:37 GMT".to_string()),
+            ..cache_control("max-age=3600")
+        };
+        {
+            let cache = AssetCache::open(&dir);
+            cache.store("https://example.com/a.css", b"body { }".to_vec(), "text/css".to_string(), headers.clone());
+            cache.store("https://example.com/gone.css", vec![1, 2], "text/css".to_string(), headers.clone());
+        }
+        fs::write(dir.join("99"), b"orphan").unwrap();
+        fs::remove_file(dir.join("1")).unwrap();
+
+        let cache = AssetCache::open(&dir);
+        match cache.lookup("https://example.com/a.css") {
+            CacheLookup::Hit(entry) => {
+                assert_eq!(entry.data, b"body { }");
+                assert_eq!(entry.content_type, "text/css");
+                assert_eq!(entry.headers, headers);
+                assert_eq!(entry.max_age, Duration::from_secs(3600));
+            }
+            other => panic!("Expected cache hit, got {:?}", other),
+        }
+        // The entry that lost its body and the stray file are gone
+        assert!(matches!(cache.lookup("https://example.com/gone.css"), CacheLookup::Miss));
+        assert!(!dir.join("99").exists());
+        assert_eq!(cache.stats().total_size, 8);
+
+        cache.clear();
+        assert_eq!(AssetCache::open(&dir).stats().entry_count, 0);
+        assert!(!dir.join("0").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_index_is_appended_and_compacted() {
+        let dir = std::env::temp_dir().join(format!("grob-cache-log-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let index_lines = || fs::read_to_string(dir.join(INDEX_FILE)).unwrap().lines().count() - 1;
+        let headers = cache_control("max-age=3600");
+        {
+            let cache = AssetCache::open(&dir);
+            cache.store("a", b"one".to_vec(), "text/plain".to_string(), headers.clone());
+            cache.store("b", b"two".to_vec(), "text/plain".to_string(), headers.clone());
+            cache.remove("a");
+            cache.store("b", b"three".to_vec(), "text/plain".to_string(), headers.clone());
+            // a, b, then a removed, then b removed and stored again
+            assert_eq!(index_lines(), 5);
+        }
+
+        let cache = AssetCache::open(&dir);
+        assert_eq!(index_lines(), 1);
+        assert!(matches!(cache.lookup("a"), CacheLookup::Miss));
+        match cache.lookup("b") {
+            CacheLookup::Hit(entry) => assert_eq!(entry.data, b"three"),
+            other => panic!("Expected cache hit, got {:?}", other),
+        }
+
+        for _ in 0..100 {
+            cache.store("b", b"again".to_vec(), "text/plain".to_string(), headers.clone());
+        }
+        assert!(index_lines() <= 2 + INDEX_SLACK);
+        assert_eq!(AssetCache::open(&dir).stats().entry_count, 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
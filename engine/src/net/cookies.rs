@@ -0,0 +1,896 @@
+// engine/src/net/cookies.rs
+// The cookie jar: RFC 6265 storage of Set-Cookie headers and the Cookie
+// header sent back
+//
+// - Set-Cookie parses as RFC 6265 section 5.2 reads it, including its
+//   lenient cookie-date grammar for Expires; Max-Age wins over Expires and
+//   no cookie outlives 400 days
+// - A cookie without a Domain attribute goes back only to the host that set
+//   it; one with a Domain goes to that domain and its subdomains, if the
+//   host setting it is among them
+// - There is no Public Suffix List: a Domain of a single label (`com`) is
+//   refused, and the "site" SameSite compares is a host's last two labels
+// - Secure cookies are set and sent only over https, and SameSite Strict
+//   and Lax cookies are neither set nor sent by cross-site subresource
+//   requests; a cross-site navigation sends Lax cookies if its method is
+//   safe (a link, a GET form) and may set any; SameSite=None needs Secure
+// - HttpOnly cookies travel with HTTP requests but are hidden from scripts
+// - A jar opened on a file loads it and rewrites it, outside the jar's
+//   lock, whenever a persistent cookie changes; session cookies (no Expires
+//   or Max-Age) are neither written nor cause a write
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::disk::{escape, parse_unix_millis, unescape, unix_millis, write_atomically};
+use super::url::{Host, Url};
+
+/// The longest a cookie may live, whatever its Expires or Max-Age say
+const MAX_LIFETIME: Duration = Duration::from_secs(400 * 24 * 60 * 60);
+
+/// Bytes allowed in a cookie's name and value together
+const MAX_COOKIE_SIZE: usize = 4096;
+
+const MAX_COOKIES_PER_DOMAIN: usize = 50;
+const MAX_COOKIES: usize = 3000;
+
+const FILE_HEADER: &str = "# grob cookie jar";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SameSite {
+    /// Sent only with same-site requests
+    Strict,
+    /// Sent only with same-site requests and cross-site navigations by a
+    /// safe method
+    #[default]
+    Lax,
+    /// Sent with every request; requires Secure
+    None,
+}
+
+impl SameSite {
+    fn as_str(self) -> &'static str {
+        match self {
+            SameSite::Strict => "Strict",
+            SameSite::Lax => "Lax",
+            SameSite::None => "None",
+        }
+    }
+
+    fn parse(value: &str) -> Option<SameSite> {
+        match value.to_ascii_lowercase().as_str() {
+            "strict" => Some(SameSite::Strict),
+            "lax" => Some(SameSite::Lax),
+            "none" => Some(SameSite::None),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    /// The host or domain it is sent to, lowercase and without a leading dot
+    pub domain: String,
+    /// Sent only to `domain` itself, not its subdomains
+    pub host_only: bool,
+    pub path: String,
+    /// None for a session cookie, dropped when the browser closes
+    pub expires: Option<SystemTime>,
+    pub secure: bool,
+    pub http_only: bool,
+    pub same_site: SameSite,
+    pub creation: SystemTime,
+    pub last_access: SystemTime,
+}
+
+impl Cookie {
+    fn is_expired(&self, now: SystemTime) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+
+    /// Whether it goes with a request for `host` and `path` (RFC 6265
+    /// section 5.4, step 1)
+    fn matches(&self, host: &str, is_domain: bool, path: &str) -> bool {
+        let domain_ok = if self.host_only {
+            host == self.domain
+        } else {
+            domain_match(host, is_domain, &self.domain)
+        };
+        domain_ok && path_match(path, &self.path)
+    }
+
+    fn same_identity(&self, other: &Cookie) -> bool {
+        self.name == other.name && self.domain == other.domain && self.path == other.path
+    }
+}
+
+/// How a request relates to the page that caused it, for SameSite
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestSite {
+    SameSite,
+    CrossSite,
+    /// A cross-site page the user goes to, by a GET or HEAD (`safe_method`)
+    /// or otherwise
+    CrossSiteNavigation { safe_method: bool },
+}
+
+impl RequestSite {
+    /// Whether `url` is requested by a page at `site_for_cookies`; requests
+    /// without a page (the browser's own navigations) are same-site
+    pub fn of(url: &Url, site_for_cookies: Option<&Url>) -> RequestSite {
+        match site_for_cookies {
+            Some(page) if site(page) != site(url) => RequestSite::CrossSite,
+            _ => RequestSite::SameSite,
+        }
+    }
+
+    /// Whether going to `url` from a page at `site_for_cookies` is a
+    /// cross-site navigation
+    pub fn of_navigation(url: &Url, site_for_cookies: Option<&Url>, safe_method: bool) -> RequestSite {
+        match RequestSite::of(url, site_for_cookies) {
+            RequestSite::SameSite => RequestSite::SameSite,
+            _ => RequestSite::CrossSiteNavigation { safe_method },
+        }
+    }
+
+    /// Whether a cookie with `same_site` goes with the request
+    fn sends(self, same_site: SameSite) -> bool {
+        match self {
+            RequestSite::SameSite => true,
+            RequestSite::CrossSite => same_site == SameSite::None,
+            RequestSite::CrossSiteNavigation { safe_method } => {
+                same_site == SameSite::None || (safe_method && same_site == SameSite::Lax)
+            }
+        }
+    }
+}
+
+/// A host's registrable domain, approximated as its last two labels;
+/// addresses are their own site
+fn site(url: &Url) -> String {
+    match url.host() {
+        Some(Host::Domain(domain)) => {
+            let domain = domain.trim_end_matches('.');
+            let labels: Vec<&str> = domain.rsplitn(3, '.').collect();
+            match labels.as_slice() {
+                [last, second, _] => format!("{}.{}", second, last),
+                _ => domain.to_string(),
+            }
+        }
+        Some(host) => host.to_string(),
+        None => url.to_string(),
+    }
+}
+
+/// Where a cookie came from, which decides whether HttpOnly ones are
+/// visible
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Api {
+    Http,
+    Script,
+}
+
+/// The cookies of every site, optionally kept in a file
+pub struct CookieJar {
+    cookies: Mutex<Vec<Cookie>>,
+    file: Option<PathBuf>,
+    /// Counts the snapshots of the file's contents taken under `cookies`
+    snapshots: AtomicU64,
+    /// The snapshot last written, so a slower writer never replaces the
+    /// file with older contents
+    written: Mutex<u64>,
+}
+
+impl Default for CookieJar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CookieJar {
+    /// A jar kept in memory only
+    pub fn new() -> Self {
+        CookieJar { cookies: Mutex::new(Vec::new()), file: None, snapshots: AtomicU64::new(0), written: Mutex::new(0) }
+    }
+
+    /// A jar loaded from `path`, if it exists, and saved back to it when its
+    /// persistent cookies change
+    pub fn open(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let cookies = match fs::read_to_string(&path) {
+            Ok(contents) => read_cookies(&contents, SystemTime::now()),
+            Err(e) => {
+                if e.kind() != io::ErrorKind::NotFound {
+                    eprintln!("Could not read cookies from {}: {}", path.display(), e);
+                }
+                Vec::new()
+            }
+        };
+        CookieJar { cookies: Mutex::new(cookies), file: Some(path), snapshots: AtomicU64::new(0), written: Mutex::new(0) }
+    }
+
+    /// Store the cookie of a `Set-Cookie` header received from `url`;
+    /// false when it was refused
+    pub fn set_cookie(&self, url: &Url, header: &str, request_site: RequestSite) -> bool {
+        self.store(url, header, request_site, Api::Http)
+    }
+
+    /// Store the cookies of every `Set-Cookie` among response `headers`
+    pub fn set_cookies_from_headers(&self, url: &Url, headers: &[(String, String)], request_site: RequestSite) {
+        for (name, value) in headers {
+            if name.eq_ignore_ascii_case("set-cookie") {
+                self.set_cookie(url, value, request_site);
+            }
+        }
+    }
+
+    /// The `Cookie` header for a request to `url`, None without cookies
+    pub fn cookie_header(&self, url: &Url, request_site: RequestSite) -> Option<String> {
+        let header = self.serialize(url, request_site, Api::Http);
+        (!header.is_empty()).then_some(header)
+    }
+
+    /// What `document.cookie` reads on a page at `url`: its cookies except
+    /// the HttpOnly ones
+    pub fn script_cookies(&self, url: &Url) -> String {
+        self.serialize(url, RequestSite::SameSite, Api::Script)
+    }
+
+    /// What assigning `document.cookie` on a page at `url` stores; it cannot
+    /// set or replace HttpOnly cookies
+    pub fn set_script_cookie(&self, url: &Url, cookie: &str) -> bool {
+        self.store(url, cookie, RequestSite::SameSite, Api::Script)
+    }
+
+    /// Every unexpired cookie, oldest first
+    pub fn cookies(&self) -> Vec<Cookie> {
+        let now = SystemTime::now();
+        let mut cookies: Vec<Cookie> = self.cookies.lock().unwrap()
+            .iter()
+            .filter(|cookie| !cookie.is_expired(now))
+            .cloned()
+            .collect();
+        cookies.sort_by_key(|cookie| cookie.creation);
+        cookies
+    }
+
+    /// Remove every cookie
+    pub fn clear(&self) {
+        let mut cookies = self.cookies.lock().unwrap();
+        cookies.clear();
+        let snapshot = self.snapshot(&cookies);
+        drop(cookies);
+        self.save(snapshot);
+    }
+
+    /// Remove the cookies sent to `domain` and its subdomains
+    pub fn clear_domain(&self, domain: &str) {
+        let domain = domain.trim_start_matches('.').to_ascii_lowercase();
+        let mut cookies = self.cookies.lock().unwrap();
+        cookies.retain(|cookie| !domain_match(&cookie.domain, true, &domain));
+        let snapshot = self.snapshot(&cookies);
+        drop(cookies);
+        self.save(snapshot);
+    }
+
+    fn store(&self, url: &Url, header: &str, request_site: RequestSite, api: Api) -> bool {
+        let now = SystemTime::now();
+        let Some(cookie) = parse_set_cookie(url, header, request_site, api, now) else {
+            return false;
+        };
+        let mut cookies = self.cookies.lock().unwrap();
+        let secure_request = is_secure_scheme(url.scheme());
+
+        // An insecure page may not shadow a secure cookie
+        if !secure_request && cookies.iter().any(|old| {
+            old.secure
+                && old.name == cookie.name
+                && (domain_match(&cookie.domain, true, &old.domain) || domain_match(&old.domain, true, &cookie.domain))
+                && path_match(&cookie.path, &old.path)
+        }) {
+            return false;
+        }
+        let mut creation = now;
+        let mut persistent_changed = false;
+        if let Some(index) = cookies.iter().position(|old| old.same_identity(&cookie)) {
+            if cookies[index].http_only && api == Api::Script {
+                return false;
+            }
+            creation = cookies[index].creation;
+            persistent_changed = cookies.remove(index).expires.is_some();
+        }
+
+        if !cookie.is_expired(now) {
+            persistent_changed |= cookie.expires.is_some();
+            cookies.push(Cookie { creation, ..cookie });
+            let persistent = |cookies: &[Cookie]| cookies.iter().filter(|cookie| cookie.expires.is_some()).count();
+            let before = persistent(&cookies);
+            evict(&mut cookies, now);
+            persistent_changed |= persistent(&cookies) != before;
+        }
+        if persistent_changed {
+            let snapshot = self.snapshot(&cookies);
+            drop(cookies);
+            self.save(snapshot);
+        }
+        true
+    }
+
+    fn serialize(&self, url: &Url, request_site: RequestSite, api: Api) -> String {
+        let Some(host) = url.host() else {
+            return String::new();
+        };
+        let is_domain = matches!(host, Host::Domain(_));
+        let host = host.to_string();
+        let path = url.pathname();
+        let secure = is_secure_scheme(url.scheme());
+        let now = SystemTime::now();
+
+        let mut cookies = self.cookies.lock().unwrap();
+        let mut sent: Vec<&mut Cookie> = cookies.iter_mut()
+            .filter(|cookie| cookie.matches(&host, is_domain, &path))
+            .filter(|cookie| secure || !cookie.secure)
+            .filter(|cookie| api == Api::Http || !cookie.http_only)
+            .filter(|cookie| request_site.sends(cookie.same_site))
+            .filter(|cookie| !cookie.is_expired(now))
+            .collect();
+        // Longer paths first, then older cookies
+        sent.sort_by(|a, b| b.path.len().cmp(&a.path.len()).then(a.creation.cmp(&b.creation)));
+
+        let mut header = String::new();
+        for cookie in sent {
+            cookie.last_access = now;
+            if !header.is_empty() {
+                header.push_str("; ");
+            }
+            if !cookie.name.is_empty() {
+                header.push_str(&cookie.name);
+                header.push('=');
+            }
+            header.push_str(&cookie.value);
+        }
+        header
+    }
+
+    /// The numbered contents of the jar's file for `cookies`, its persistent
+    /// ones; None without a file. Taken while `cookies` is locked, so the
+    /// numbers follow the order of changes
+    fn snapshot(&self, cookies: &[Cookie]) -> Option<(u64, String)> {
+        self.file.as_ref()?;
+        let now = SystemTime::now();
+        let mut contents = format!("{}\n", FILE_HEADER);
+        for cookie in cookies.iter().filter(|cookie| cookie.expires.is_some() && !cookie.is_expired(now)) {
+            contents.push_str(&write_cookie(cookie));
+            contents.push('\n');
+        }
+        Some((self.snapshots.fetch_add(1, Ordering::Relaxed) + 1, contents))
+    }
+
+    /// Rewrite the jar's file with a snapshot, unless a newer one is
+    /// already there
+    fn save(&self, snapshot: Option<(u64, String)>) {
+        let (Some(path), Some((number, contents))) = (&self.file, snapshot) else {
+            return;
+        };
+        let mut written = self.written.lock().unwrap();
+        if *written > number {
+            return;
+        }
+        match write_atomically(path, contents.as_bytes()) {
+            Ok(()) => *written = number,
+            Err(e) => eprintln!("Could not save cookies to {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// Drop expired cookies, then the least recently used past the per-domain
+/// and total limits
+fn evict(cookies: &mut Vec<Cookie>, now: SystemTime) {
+    cookies.retain(|cookie| !cookie.is_expired(now));
+    let mut domains: Vec<&str> = cookies.iter().map(|cookie| cookie.domain.as_str()).collect();
+    domains.sort_unstable();
+    domains.dedup();
+    let crowded: Vec<String> = domains.into_iter()
+        .filter(|domain| cookies.iter().filter(|cookie| cookie.domain == *domain).count() > MAX_COOKIES_PER_DOMAIN)
+        .map(str::to_string)
+        .collect();
+    for domain in crowded {
+        while cookies.iter().filter(|cookie| cookie.domain == domain).count() > MAX_COOKIES_PER_DOMAIN {
+            remove_least_recently_used(cookies, |cookie| cookie.domain == domain);
+        }
+    }
+    while cookies.len() > MAX_COOKIES {
+        remove_least_recently_used(cookies, |_| true);
+    }
+}
+
+fn remove_least_recently_used(cookies: &mut Vec<Cookie>, candidate: impl Fn(&Cookie) -> bool) {
+    let oldest = cookies.iter()
+        .enumerate()
+        .filter(|(_, cookie)| candidate(cookie))
+        .min_by_key(|(_, cookie)| cookie.last_access)
+        .map(|(index, _)| index);
+    if let Some(index) = oldest {
+        cookies.remove(index);
+    }
+}
+
+fn is_secure_scheme(scheme: &str) -> bool {
+    scheme == "https" || scheme == "wss"
+}
+
+/// Whether `host` is `domain` or one of its subdomains (RFC 6265 section
+/// 5.1.3); an address only matches itself
+fn domain_match(host: &str, is_domain: bool, domain: &str) -> bool {
+    host == domain
+        || (is_domain && host.strip_suffix(domain).is_some_and(|prefix| prefix.ends_with('.')))
+}
+
+/// Whether a cookie with path `cookie_path` goes with requests for
+/// `request_path` (RFC 6265 section 5.1.4)
+fn path_match(request_path: &str, cookie_path: &str) -> bool {
+    match request_path.strip_prefix(cookie_path) {
+        Some(rest) => rest.is_empty() || cookie_path.ends_with('/') || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+/// The directory of `path`: `/a/b` for `/a/b/c` (RFC 6265 section 5.1.4)
+fn default_path(path: &str) -> String {
+    if !path.starts_with('/') {
+        return "/".to_string();
+    }
+    match path.rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(index) => path[..index].to_string(),
+    }
+}
+
+/// The cookie a Set-Cookie header asks `url` to store (RFC 6265 sections
+/// 5.2 and 5.3), None when it must be ignored
+fn parse_set_cookie(url: &Url, header: &str, request_site: RequestSite, api: Api, now: SystemTime) -> Option<Cookie> {
+    let host = url.host()?;
+    let is_domain = matches!(host, Host::Domain(_));
+    let request_host = host.to_string();
+    let secure_request = is_secure_scheme(url.scheme());
+
+    let (pair, attributes) = header.split_once(';').unwrap_or((header, ""));
+    // A pair without `=` is a value with an empty name
+    let (name, value) = pair.split_once('=').unwrap_or(("", pair));
+    let (name, value) = (trim_wsp(name), trim_wsp(value));
+    if (name.is_empty() && value.is_empty())
+        || name.len() + value.len() > MAX_COOKIE_SIZE
+        || name.chars().chain(value.chars()).any(|c| c.is_ascii_control() && c != '\t')
+    {
+        return None;
+    }
+
+    let mut expires = None;
+    let mut max_age = None;
+    let mut domain = None;
+    let mut path = None;
+    let mut secure = false;
+    let mut http_only = false;
+    let mut same_site = None;
+    for attribute in attributes.split(';') {
+        let (key, value) = attribute.split_once('=').unwrap_or((attribute, ""));
+        let value = trim_wsp(value);
+        match trim_wsp(key).to_ascii_lowercase().as_str() {
+            "expires" => {
+                if let Some(date) = parse_cookie_date(value) {
+                    expires = Some(date);
+                }
+            }
+            "max-age" => {
+                if let Some(seconds) = parse_max_age(value) {
+                    max_age = Some(seconds);
+                }
+            }
+            "domain" if !value.is_empty() => {
+                domain = Some(value.trim_start_matches('.').to_ascii_lowercase());
+            }
+            "path" => path = Some(value).filter(|value| value.starts_with('/')),
+            "secure" => secure = true,
+            "httponly" => http_only = true,
+            "samesite" => same_site = SameSite::parse(value).or(same_site),
+            _ => {}
+        }
+    }
+
+    let expires = match max_age {
+        Some(seconds) if seconds <= 0 => Some(UNIX_EPOCH),
+        Some(seconds) => Some(now + MAX_LIFETIME.min(Duration::from_secs(seconds as u64))),
+        None => expires.map(|expires| expires.min(now + MAX_LIFETIME)),
+    };
+
+    let (domain, host_only) = match domain {
+        // A single label is a public suffix; it may only name the host itself
+        Some(domain) if !domain.trim_end_matches('.').contains('.') => {
+            if domain != request_host {
+                return None;
+            }
+            (request_host, true)
+        }
+        Some(domain) => {
+            if !domain_match(&request_host, is_domain, &domain) {
+                return None;
+            }
+            (domain, false)
+        }
+        None => (request_host, true),
+    };
+
+    if (secure && !secure_request) || (http_only && api == Api::Script) {
+        return None;
+    }
+    let same_site = same_site.unwrap_or_default();
+    if same_site == SameSite::None && !secure {
+        return None;
+    }
+    if request_site == RequestSite::CrossSite && same_site != SameSite::None {
+        return None;
+    }
+    let path = path.map(str::to_string).unwrap_or_else(|| default_path(&url.pathname()));
+
+    // Name prefixes that promise where the cookie came from
+    if name.starts_with("__Secure-") && !secure {
+        return None;
+    }
+    if name.starts_with("__Host-") && !(secure && host_only && path == "/") {
+        return None;
+    }
+    // ... which a nameless cookie must not imitate, as it reads `value`
+    if name.is_empty() && ["__secure-", "__host-"].iter().any(|prefix| value.to_ascii_lowercase().starts_with(prefix)) {
+        return None;
+    }
+
+    Some(Cookie {
+        name: name.to_string(),
+        value: value.to_string(),
+        domain,
+        host_only,
+        path,
+        expires,
+        secure,
+        http_only,
+        same_site,
+        creation: now,
+        last_access: now,
+    })
+}
+
+fn trim_wsp(s: &str) -> &str {
+    s.trim_matches([' ', '\t'])
+}
+
+/// Max-Age in seconds: an optional `-` and digits, saturating when huge
+fn parse_max_age(value: &str) -> Option<i64> {
+    let (negative, digits) = match value.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, value),
+    };
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let seconds = digits.parse::<i64>().unwrap_or(i64::MAX);
+    Some(if negative { -seconds } else { seconds })
+}
+
+/// A date in the cookie-date grammar of RFC 6265 section 5.1.1, which takes
+/// the HTTP date formats and most sloppy variations of them
+pub fn parse_cookie_date(input: &str) -> Option<SystemTime> {
+    let is_delimiter = |c: char| {
+        matches!(c, '\t' | ' '..='/' | ';'..='@' | '['..='`' | '{'..='~')
+    };
+    let mut time = None;
+    let mut day = None;
+    let mut month = None;
+    let mut year = None;
+    for token in input.split(is_delimiter).filter(|token| !token.is_empty()) {
+        if time.is_none() {
+            if let Some(hms) = parse_time(token) {
+                time = Some(hms);
+                continue;
+            }
+        }
+        if day.is_none() {
+            if let Some(value) = leading_number(token, 1, 2) {
+                day = Some(value);
+                continue;
+            }
+        }
+        if month.is_none() {
+            if let Some(value) = parse_month(token) {
+                month = Some(value);
+                continue;
+            }
+        }
+        if year.is_none() {
+            if let Some(value) = leading_number(token, 2, 4) {
+                year = Some(value);
+                continue;
+            }
+        }
+    }
+
+    let (hour, minute, second) = time?;
+    let (day, month, mut year) = (day?, month?, year?);
+    if (70..=99).contains(&year) {
+        year += 1900;
+    } else if year <= 69 {
+        year += 2000;
+    }
+    if !(1..=days_in_month(year, month)).contains(&day) || year < 1601 || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+    let seconds = days_from_civil(year as i64, month, day) * 86400
+        + hour as i64 * 3600
+        + minute as i64 * 60
+        + second as i64;
+    Some(system_time(seconds))
+}
+
+/// `hh:mm:ss`, each part one or two digits, optionally followed by
+/// anything but a digit
+fn parse_time(token: &str) -> Option<(u32, u32, u32)> {
+    let mut parts = token.splitn(3, ':');
+    let hour = parts.next().filter(|part| is_number(part, 1, 2))?;
+    let minute = parts.next().filter(|part| is_number(part, 1, 2))?;
+    let second = leading_number(parts.next()?, 1, 2)?;
+    Some((hour.parse().ok()?, minute.parse().ok()?, second))
+}
+
+/// The number `min` to `max` digits long that `token` starts with, if no
+/// further digit follows it
+fn leading_number(token: &str, min: usize, max: usize) -> Option<u32> {
+    let digits = token.bytes().take_while(u8::is_ascii_digit).count();
+    if digits < min || digits > max {
+        return None;
+    }
+    token[..digits].parse().ok()
+}
+
+fn is_number(part: &str, min: usize, max: usize) -> bool {
+    (min..=max).contains(&part.len()) && part.bytes().all(|b| b.is_ascii_digit())
+}
+
+fn parse_month(token: &str) -> Option<u32> {
+    const MONTHS: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+    let prefix = token.get(..3)?.to_ascii_lowercase();
+    MONTHS.iter().position(|month| *month == prefix).map(|index| index as u32 + 1)
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days from 1970-01-01 to a date of the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+fn system_time(seconds: i64) -> SystemTime {
+    if seconds >= 0 {
+        UNIX_EPOCH + Duration::from_secs(seconds as u64)
+    } else {
+        UNIX_EPOCH - Duration::from_secs(seconds.unsigned_abs())
+    }
+}
+
+/// One line of the jar's file
+fn write_cookie(cookie: &Cookie) -> String {
+    let flag = |set: bool| if set { "TRUE" } else { "FALSE" };
+    [
+        cookie.domain.clone(),
+        flag(cookie.host_only).to_string(),
+        cookie.path.clone(),
+        flag(cookie.secure).to_string(),
+        flag(cookie.http_only).to_string(),
+        cookie.same_site.as_str().to_string(),
+        cookie.expires.map(unix_millis).unwrap_or(0).to_string(),
+        unix_millis(cookie.creation).to_string(),
+        escape(&cookie.name),
+        escape(&cookie.value),
+    ]
+    .join("\t")
+}
+
+/// The unexpired cookies of a jar file; malformed lines are skipped
+fn read_cookies(contents: &str, now: SystemTime) -> Vec<Cookie> {
+    contents.lines()
+        .filter(|line| !line.starts_with('#') && !line.is_empty())
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let [domain, host_only, path, secure, http_only, same_site, expires, creation, name, value] = fields[..] else {
+                return None;
+            };
+            let creation = parse_unix_millis(creation)?;
+            Some(Cookie {
+                name: unescape(name),
+                value: unescape(value),
+                domain: domain.to_string(),
+                host_only: host_only == "TRUE",
+                path: path.to_string(),
+                expires: Some(parse_unix_millis(expires)?),
+                secure: secure == "TRUE",
+                http_only: http_only == "TRUE",
+                same_site: SameSite::parse(same_site)?,
+                creation,
+                last_access: creation,
+            })
+        })
+        .filter(|cookie| !cookie.is_expired(now))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(input: &str) -> Url {
+        Url::parse(input).unwrap()
+    }
+
+    fn set(jar: &CookieJar, at: &str, header: &str) -> bool {
+        jar.set_cookie(&url(at), header, RequestSite::SameSite)
+    }
+
+    fn header(jar: &CookieJar, to: &str) -> Option<String> {
+        jar.cookie_header(&url(to), RequestSite::SameSite)
+    }
+
+    #[test]
+    fn test_cookie_dates() {
+        let expected = Some(UNIX_EPOCH + Duration::from_secs(784111777));
+        assert_eq!(parse_cookie_date("Sun, 06 Nov 1994 08:49:37 GMT"), expected);
+        assert_eq!(parse_cookie_date("Sunday, 06-Nov-94 08:49:37 GMT"), expected);
+        assert_eq!(parse_cookie_date("Sun Nov  6 08:49:37 1994"), expected);
+        assert_eq!(parse_cookie_date("6 november 1994 8:49:37"), expected);
+        assert_eq!(parse_cookie_date("Thu, 01 Jan 1970 00:00:00 GMT"), Some(UNIX_EPOCH));
+        assert_eq!(parse_cookie_date("Sat, 01 Jan 1600 00:00:00 GMT"), None);
+        assert_eq!(parse_cookie_date("Fri, 30 Feb 2024 00:00:00 GMT"), None);
+        assert_eq!(parse_cookie_date("Thu, 29 Feb 2024 24:00:00 GMT"), None);
+        assert_eq!(parse_cookie_date("tomorrow"), None);
+    }
+
+    #[test]
+    fn test_domain_and_path_scope() {
+        let jar = CookieJar::new();
+        assert!(set(&jar, "http://www.example.com/a/b", "host=1"));
+        assert!(set(&jar, "http://www.example.com/", "wide=2; Domain=.Example.com; Path=/"));
+        assert!(!set(&jar, "http://www.example.com/", "other=3; Domain=example.org"));
+        assert!(!set(&jar, "http://www.example.com/", "tld=4; Domain=com"));
+        assert!(!set(&jar, "http://www.example.com/", "=; Path=/"));
+
+        // Longer paths first
+        assert_eq!(header(&jar, "http://www.example.com/a/c").as_deref(), Some("host=1; wide=2"));
+        assert_eq!(header(&jar, "http://www.example.com/ab").as_deref(), Some("wide=2"));
+        assert_eq!(header(&jar, "http://static.example.com/a/").as_deref(), Some("wide=2"));
+        assert_eq!(header(&jar, "http://example.org/"), None);
+        assert_eq!(jar.cookies().len(), 2);
+    }
+
+    #[test]
+    fn test_expiry_replacement_and_deletion() {
+        let jar = CookieJar::new();
+        assert!(set(&jar, "http://example.com/", "id=old; Max-Age=3600"));
+        let created = jar.cookies()[0].creation;
+        assert!(set(&jar, "http://example.com/", "id=new; Expires=Wed, 09 Jun 2100 10:18:14 GMT; Max-Age=60"));
+        let cookies = jar.cookies();
+        assert_eq!((cookies.len(), cookies[0].value.as_str()), (1, "new"));
+        assert_eq!(cookies[0].creation, created);
+        // Max-Age beat the far-off Expires
+        assert!(cookies[0].expires.unwrap() < SystemTime::now() + Duration::from_secs(61));
+
+        assert!(set(&jar, "http://example.com/", "id=; Expires=Thu, 01 Jan 1970 00:00:00 GMT"));
+        assert!(jar.cookies().is_empty());
+        assert!(set(&jar, "http://example.com/", "far=1; Max-Age=99999999999999999999"));
+        assert!(jar.cookies()[0].expires.unwrap() <= SystemTime::now() + MAX_LIFETIME);
+    }
+
+    #[test]
+    fn test_secure_http_only_and_same_site() {
+        let jar = CookieJar::new();
+        assert!(!set(&jar, "http://example.com/", "s=1; Secure"));
+        assert!(set(&jar, "https://example.com/", "s=1; Secure"));
+        assert!(!set(&jar, "http://example.com/", "s=2"), "an insecure page cannot shadow a secure cookie");
+        assert!(set(&jar, "https://example.com/", "h=2; HttpOnly"));
+        assert!(!set(&jar, "https://example.com/", "n=3; SameSite=None"));
+        assert!(set(&jar, "https://example.com/", "n=3; SameSite=None; Secure"));
+        assert!(!set(&jar, "https://example.com/", "__Host-x=4; Secure; Domain=example.com"));
+
+        assert_eq!(header(&jar, "http://example.com/"), Some("h=2".to_string()));
+        assert_eq!(header(&jar, "https://example.com/"), Some("s=1; h=2; n=3".to_string()));
+        let cross_site = jar.cookie_header(&url("https://example.com/"), RequestSite::CrossSite);
+        assert_eq!(cross_site, Some("n=3".to_string()));
+        assert!(!jar.set_cookie(&url("https://example.com/"), "lax=5", RequestSite::CrossSite));
+
+        let page = url("https://example.com/");
+        assert_eq!(jar.script_cookies(&page), "s=1; n=3");
+        assert!(!jar.set_script_cookie(&page, "h=replaced"));
+        assert!(jar.set_script_cookie(&page, "js=6"));
+        assert_eq!(RequestSite::of(&url("https://cdn.example.com/"), Some(&page)), RequestSite::SameSite);
+        assert_eq!(RequestSite::of(&url("https://example.org/"), Some(&page)), RequestSite::CrossSite);
+
+        assert!(set(&jar, "https://example.com/", "strict=7; SameSite=Strict"));
+        let link = RequestSite::CrossSiteNavigation { safe_method: true };
+        let post = RequestSite::CrossSiteNavigation { safe_method: false };
+        assert_eq!(jar.cookie_header(&page, link), Some("s=1; h=2; n=3; js=6".to_string()));
+        assert_eq!(jar.cookie_header(&page, post), Some("n=3".to_string()));
+        assert!(jar.set_cookie(&page, "strict=8; SameSite=Strict", post));
+    }
+
+    #[test]
+    fn test_nameless_cookies() {
+        let jar = CookieJar::new();
+        assert!(set(&jar, "https://example.com/", "token"));
+        assert!(set(&jar, "https://example.com/", "a=1"));
+        assert_eq!(header(&jar, "https://example.com/"), Some("token; a=1".to_string()));
+        assert!(set(&jar, "https://example.com/", " other ; Path=/"));
+        assert_eq!(header(&jar, "https://example.com/"), Some("other; a=1".to_string()));
+        assert!(set(&jar, "https://example.com/", "=bare"));
+        assert_eq!(header(&jar, "https://example.com/"), Some("bare; a=1".to_string()));
+
+        assert!(!set(&jar, "https://example.com/", ""));
+        assert!(!set(&jar, "https://example.com/", " = ; Path=/"));
+        assert!(!set(&jar, "https://example.com/", "__Host-id; Secure; Path=/"));
+        assert!(!set(&jar, "https://example.com/", "=__secure-id; Secure"));
+    }
+
+    #[test]
+    fn test_jar_file_round_trip() {
+        let path = std::env::temp_dir().join(format!("grob-cookies-{}.txt", std::process::id()));
+        let _ = fs::remove_file(&path);
+        {
+            let jar = CookieJar::open(&path);
+            assert!(set(&jar, "https://example.com/app/x", "kept=a%09b\tc; Max-Age=600; HttpOnly; SameSite=Strict"));
+            assert!(set(&jar, "https://example.com/", "session=1"));
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+        let jar = CookieJar::open(&path);
+        let cookies = jar.cookies();
+        assert_eq!(cookies.len(), 1);
+        assert_eq!(cookies[0].name, "kept");
+        assert_eq!(cookies[0].value, "a%09b\tc");
+        assert_eq!(cookies[0].path, "/app");
+        assert!(cookies[0].http_only && cookies[0].host_only);
+        assert_eq!(cookies[0].same_site, SameSite::Strict);
+
+        jar.clear();
+        assert!(CookieJar::open(&path).cookies().is_empty());
+        fs::remove_file(&path).unwrap();
+
+        // Session cookies leave the file alone
+        let jar = CookieJar::open(&path);
+        assert!(set(&jar, "https://example.com/", "session=1"));
+        assert!(!path.exists());
+        assert!(set(&jar, "https://example.com/", "kept=1; Max-Age=600"));
+        assert!(path.exists());
+        fs::remove_file(&path).unwrap();
+        assert!(set(&jar, "https://example.com/", "session=2"));
+        assert!(set(&jar, "https://example.com/", "kept=1; Max-Age=0"));
+        assert!(CookieJar::open(&path).cookies().is_empty());
+        fs::remove_file(&path).unwrap();
+    }
+}
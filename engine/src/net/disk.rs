@@ -0,0 +1,81 @@
+// engine/src/net/disk.rs
+// Helpers for the files the network layer keeps between runs: the cookie
+// jar and the HTTP cache index
+//
+// - Both are line-based text, one record per line in tab-separated fields;
+//   `escape` keeps tabs and line breaks inside a field from splitting it
+// - Files are replaced whole through a temporary file, so a crash leaves
+//   either the old contents or the new, never half of each
+// - They hold cookies and browsing history, so on unix they are created
+//   readable and writable by their owner only
+// - The cache index is also appended to between rewrites; a crash can
+//   leave a torn last line, which readers skip as unparseable
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// `field` with `%`, tabs and line breaks percent-encoded
+pub fn escape(field: &str) -> String {
+    field.replace('%', "%25").replace('\t', "%09").replace('\n', "%0A").replace('\r', "%0D")
+}
+
+pub fn unescape(field: &str) -> String {
+    field.replace("%09", "\t").replace("%0A", "\n").replace("%0D", "\r").replace("%25", "%")
+}
+
+/// Milliseconds since the Unix epoch; times before it are 0
+pub fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+pub fn parse_unix_millis(field: &str) -> Option<SystemTime> {
+    field.parse::<u64>().ok().map(|ms| UNIX_EPOCH + Duration::from_millis(ms))
+}
+
+/// Write `contents` to a temporary file beside `path`, then move it over
+/// `path`
+pub fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    // A temporary file left by a crash keeps its old mode unless recreated
+    match fs::remove_file(&temporary) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    private_file().write(true).create_new(true).open(&temporary)?.write_all(contents)?;
+    fs::rename(&temporary, path)
+}
+
+/// Add `contents` to the end of `path`, creating it if needed
+pub fn append(path: &Path, contents: &[u8]) -> io::Result<()> {
+    private_file().create(true).append(true).open(path)?.write_all(contents)
+}
+
+/// Options that create a file only its owner can read
+fn private_file() -> OpenOptions {
+    let mut options = OpenOptions::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_round_trip() {
+        for field in ["plain", "a\tb\nc\rd", "%09 is not a tab", "100%", "%250A"] {
+            assert!(!escape(field).contains(['\t', '\n', '\r']));
+            assert_eq!(unescape(&escape(field)), field);
+        }
+    }
+}
@@ -0,0 +1,579 @@
+// engine/src/net/form.rs
+// Form submission: the entries a <form> submits and the request carrying
+// them, after the HTML Standard's form submission algorithm
+//
+// - `construct_form_data` walks the document in tree order for the
+//   `input`, `select`, `textarea` and `button` elements the form owns:
+//   its descendants, and elements elsewhere naming it in `form="id"`
+// - Disabled controls (or those inside a disabled fieldset), unchecked
+//   checkboxes and radios, and buttons other than the one that submitted
+//   the form contribute nothing
+// - `form_submission` takes action, method and enctype from the form, or
+//   from the submitter's formaction/formmethod/formenctype, and encodes
+//   the entries: into the query for GET, or as an
+//   `application/x-www-form-urlencoded`, `multipart/form-data` or
+//   `text/plain` body for POST
+// - Values are what the markup says; nothing has been typed into a control
+//   or picked from a file chooser, so file inputs submit an empty file
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+use crate::dom::{Dom, NodeId, NodeType};
+use crate::parser::html::extract_base_href;
+
+use super::request::Method;
+use super::url::{resolve_url_with_base, Url};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FormValue {
+    Text(String),
+    File { filename: String, content_type: String, data: Vec<u8> },
+}
+
+/// One name/value pair a form submits
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormEntry {
+    pub name: String,
+    pub value: FormValue,
+}
+
+impl FormEntry {
+    fn text(name: &str, value: &str) -> Self {
+        FormEntry { name: name.to_string(), value: FormValue::Text(value.to_string()) }
+    }
+}
+
+/// The request a form submits: a GET carries its entries in `url`, a POST
+/// in `body`, of `content_type`
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormSubmission {
+    pub method: Method,
+    pub url: String,
+    pub content_type: Option<String>,
+    pub body: Option<Vec<u8>>,
+}
+
+/// The form encoding types, from `enctype`
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Enctype {
+    UrlEncoded,
+    Multipart,
+    TextPlain,
+}
+
+impl Enctype {
+    fn parse(value: Option<&str>) -> Enctype {
+        match value.map(|value| value.trim().to_ascii_lowercase()).as_deref() {
+            Some("multipart/form-data") => Enctype::Multipart,
+            Some("text/plain") => Enctype::TextPlain,
+            _ => Enctype::UrlEncoded,
+        }
+    }
+}
+
+/// The form `node` belongs to: the one its `form` attribute names by id,
+/// or else its nearest `<form>` ancestor
+pub fn form_owner(dom: &Dom, node: NodeId) -> Option<NodeId> {
+    if let Some(id) = dom.get_attribute(node, "form") {
+        return tree_order(dom)
+            .into_iter()
+            .find(|&candidate| dom.get_attribute(candidate, "id") == Some(id))
+            .filter(|&candidate| is_tag(dom, candidate, "form"));
+    }
+    ancestors(dom, node).find(|&ancestor| is_tag(dom, ancestor, "form"))
+}
+
+/// Whether `node` submits its form when activated: a `<button>` of type
+/// submit (the default), or an `<input>` of type submit or image
+pub fn is_submit_button(dom: &Dom, node: NodeId) -> bool {
+    match dom.tag_name(node).map(str::to_ascii_lowercase).as_deref() {
+        Some("button") => button_type(dom, node) == "submit",
+        Some("input") => matches!(input_type(dom, node).as_str(), "submit" | "image"),
+        _ => false,
+    }
+}
+
+/// The entries `form` submits when `submitter` (a submit button, or None
+/// for a submission without one) submits it
+pub fn construct_form_data(dom: &Dom, form: NodeId, submitter: Option<NodeId>) -> Vec<FormEntry> {
+    let controls: Vec<NodeId> = tree_order(dom)
+        .into_iter()
+        .filter(|&node| is_submittable(dom, node) && form_owner(dom, node) == Some(form))
+        .collect();
+    let checked_radios = checked_radios(dom, &controls);
+
+    let mut entries = Vec::new();
+    for &control in &controls {
+        if is_disabled(dom, control) || ancestors(dom, control).any(|ancestor| is_tag(dom, ancestor, "datalist")) {
+            continue;
+        }
+        let tag = dom.tag_name(control).unwrap_or_default().to_ascii_lowercase();
+        let kind = match tag.as_str() {
+            "input" => input_type(dom, control),
+            "button" => button_type(dom, control),
+            _ => tag.clone(),
+        };
+        let is_button = tag == "button" || matches!(kind.as_str(), "submit" | "image" | "reset" | "button");
+        if is_button && Some(control) != submitter {
+            continue;
+        }
+        if kind == "checkbox" && dom.get_attribute(control, "checked").is_none() {
+            continue;
+        }
+        if kind == "radio" && !checked_radios.contains(&control) {
+            continue;
+        }
+        let name = dom.get_attribute(control, "name").unwrap_or_default();
+        if kind == "image" {
+            let (x, y) = match name {
+                "" => ("x".to_string(), "y".to_string()),
+                name => (format!("{}.x", name), format!("{}.y", name)),
+            };
+            entries.push(FormEntry::text(&x, "0"));
+            entries.push(FormEntry::text(&y, "0"));
+            continue;
+        }
+        if name.is_empty() {
+            continue;
+        }
+
+        match kind.as_str() {
+            "select" => {
+                for option in selected_options(dom, control) {
+                    entries.push(FormEntry::text(name, &option_value(dom, option)));
+                }
+            }
+            "checkbox" | "radio" => {
+                entries.push(FormEntry::text(name, dom.get_attribute(control, "value").unwrap_or("on")));
+            }
+            "file" => entries.push(FormEntry {
+                name: name.to_string(),
+                value: FormValue::File {
+                    filename: String::new(),
+                    content_type: "application/octet-stream".to_string(),
+                    data: Vec::new(),
+                },
+            }),
+            "hidden" if name.eq_ignore_ascii_case("_charset_") => entries.push(FormEntry::text(name, "UTF-8")),
+            "textarea" => entries.push(FormEntry::text(name, &text_content(dom, control))),
+            _ => entries.push(FormEntry::text(name, &input_value(dom, control, &kind))),
+        }
+
+        // dirname submits the direction of the text, which is always ltr here
+        if matches!(kind.as_str(), "text" | "search" | "textarea") {
+            if let Some(dirname) = dom.get_attribute(control, "dirname").filter(|dirname| !dirname.is_empty()) {
+                entries.push(FormEntry::text(dirname, "ltr"));
+            }
+        }
+    }
+    entries
+}
+
+/// The request submitting `form` from the page at `document_url`; None
+/// for a `dialog` form and for actions other than http(s) URLs
+pub fn form_submission(dom: &Dom, form: NodeId, submitter: Option<NodeId>, document_url: &str) -> Option<FormSubmission> {
+    let attribute = |submitter_name: &str, form_name: &str| {
+        submitter
+            .and_then(|submitter| dom.get_attribute(submitter, submitter_name))
+            .or_else(|| dom.get_attribute(form, form_name))
+    };
+
+    let action = attribute("formaction", "action").map(str::trim).unwrap_or_default();
+    let action = match action {
+        "" => document_url.to_string(),
+        action => resolve_url_with_base(document_url, extract_base_href(dom).as_deref(), action),
+    };
+    let action = Url::parse(&action).ok().filter(|url| matches!(url.scheme(), "http" | "https"))?;
+    let method = match attribute("formmethod", "method").map(|method| method.trim().to_ascii_lowercase()).as_deref() {
+        Some("post") => Method::Post,
+        Some("dialog") => return None,
+        _ => Method::Get,
+    };
+    let entries = construct_form_data(dom, form, submitter);
+
+    if method == Method::Get {
+        // The entries replace the action's query; its fragment stays
+        let serialized = action.without_fragment().to_string();
+        let without_query = match action.query() {
+            Some(query) => &serialized[..serialized.len() - query.len() - 1],
+            None => serialized.as_str(),
+        };
+        let url = format!("{}?{}{}", without_query, urlencode(&entries), action.hash());
+        return Some(FormSubmission { method, url, content_type: None, body: None });
+    }
+
+    let (content_type, body) = match Enctype::parse(attribute("formenctype", "enctype")) {
+        Enctype::UrlEncoded => ("application/x-www-form-urlencoded".to_string(), urlencode(&entries).into_bytes()),
+        Enctype::Multipart => {
+            let boundary = boundary_for(&entries);
+            (format!("multipart/form-data; boundary={}", boundary), multipart_encode(&entries, &boundary))
+        }
+        Enctype::TextPlain => ("text/plain".to_string(), text_plain_encode(&entries).into_bytes()),
+    };
+    Some(FormSubmission { method, url: action.to_string(), content_type: Some(content_type), body: Some(body) })
+}
+
+/// The `application/x-www-form-urlencoded` serialization of `entries`;
+/// files submit their name
+pub fn urlencode(entries: &[FormEntry]) -> String {
+    entries.iter()
+        .map(|entry| {
+            let value = match &entry.value {
+                FormValue::Text(value) => normalize_newlines(value),
+                FormValue::File { filename, .. } => filename.clone(),
+            };
+            format!("{}={}", urlencode_component(&normalize_newlines(&entry.name)), urlencode_component(&value))
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// The `multipart/form-data` body for `entries`, separated by `boundary`
+/// (RFC 7578)
+pub fn multipart_encode(entries: &[FormEntry], boundary: &str) -> Vec<u8> {
+    let mut body = Vec::new();
+    for entry in entries {
+        let name = escape_multipart(&normalize_newlines(&entry.name));
+        body.extend_from_slice(format!("--{}\r\nContent-Disposition: form-data; name=\"{}\"", boundary, name).as_bytes());
+        match &entry.value {
+            FormValue::Text(value) => {
+                body.extend_from_slice(b"\r\n\r\n");
+                body.extend_from_slice(normalize_newlines(value).as_bytes());
+            }
+            FormValue::File { filename, content_type, data } => {
+                let header = format!("; filename=\"{}\"\r\nContent-Type: {}\r\n\r\n", escape_multipart(filename), content_type);
+                body.extend_from_slice(header.as_bytes());
+                body.extend_from_slice(data);
+            }
+        }
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+    body
+}
+
+/// The `text/plain` body for `entries`: one `name=value` per line
+fn text_plain_encode(entries: &[FormEntry]) -> String {
+    entries.iter()
+        .map(|entry| {
+            let value = match &entry.value {
+                FormValue::Text(value) => normalize_newlines(value),
+                FormValue::File { filename, .. } => filename.clone(),
+            };
+            format!("{}={}\r\n", normalize_newlines(&entry.name), value)
+        })
+        .collect()
+}
+
+/// Bytes other than ASCII alphanumerics and `*-._` percent-encoded, and
+/// spaces as `+`
+fn urlencode_component(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'*' | b'-' | b'.' | b'_' => encoded.push(byte as char),
+            b' ' => encoded.push('+'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Line breaks (CR, LF or CRLF) as CRLF
+fn normalize_newlines(value: &str) -> String {
+    value.replace("\r\n", "\n").replace('\r', "\n").replace('\n', "\r\n")
+}
+
+/// A name or filename fit for a quoted Content-Disposition parameter
+fn escape_multipart(value: &str) -> String {
+    value.replace('\n', "%0A").replace('\r', "%0D").replace('"', "%22")
+}
+
+/// A multipart boundary that occurs in none of the entries
+fn boundary_for(entries: &[FormEntry]) -> String {
+    loop {
+        // RandomState is keyed randomly, so an empty hash is a random number
+        let random = RandomState::new().build_hasher().finish();
+        let boundary = format!("----grobFormBoundary{:016x}", random);
+        let collides = entries.iter().any(|entry| {
+            entry.name.contains(&boundary) || match &entry.value {
+                FormValue::Text(value) => value.contains(&boundary),
+                FormValue::File { filename, data, .. } => {
+                    filename.contains(&boundary) || data.windows(boundary.len()).any(|window| window == boundary.as_bytes())
+                }
+            }
+        });
+        if !collides {
+            return boundary;
+        }
+    }
+}
+
+/// Every node, in tree order
+fn tree_order(dom: &Dom) -> Vec<NodeId> {
+    let mut order = Vec::new();
+    if dom.nodes.is_empty() {
+        return order;
+    }
+    let mut stack = vec![dom.root()];
+    while let Some(node) = stack.pop() {
+        order.push(node);
+        stack.extend(dom.nodes[node].children.iter().rev());
+    }
+    order
+}
+
+fn ancestors(dom: &Dom, node: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+    std::iter::successors(dom.nodes[node].parent, move |&ancestor| dom.nodes[ancestor].parent)
+}
+
+fn is_tag(dom: &Dom, node: NodeId, tag: &str) -> bool {
+    dom.tag_name(node).is_some_and(|name| name.eq_ignore_ascii_case(tag))
+}
+
+fn is_submittable(dom: &Dom, node: NodeId) -> bool {
+    ["input", "select", "textarea", "button"].iter().any(|tag| is_tag(dom, node, tag))
+}
+
+/// An input's type, lowercased; text when missing
+fn input_type(dom: &Dom, node: NodeId) -> String {
+    dom.get_attribute(node, "type").map(|kind| kind.trim().to_ascii_lowercase()).unwrap_or_else(|| "text".to_string())
+}
+
+/// A button's type: submit unless it says reset or button
+fn button_type(dom: &Dom, node: NodeId) -> String {
+    match dom.get_attribute(node, "type").map(|kind| kind.trim().to_ascii_lowercase()).as_deref() {
+        Some(kind @ ("reset" | "button")) => kind.to_string(),
+        _ => "submit".to_string(),
+    }
+}
+
+/// Whether a control is disabled, itself or by a disabled fieldset it is
+/// in (outside that fieldset's first legend)
+fn is_disabled(dom: &Dom, node: NodeId) -> bool {
+    if dom.get_attribute(node, "disabled").is_some() {
+        return true;
+    }
+    let mut child = node;
+    for ancestor in ancestors(dom, node) {
+        if is_tag(dom, ancestor, "fieldset") && dom.get_attribute(ancestor, "disabled").is_some() {
+            let first_legend = dom.nodes[ancestor].children.iter().copied().find(|&c| is_tag(dom, c, "legend"));
+            if first_legend != Some(child) {
+                return true;
+            }
+        }
+        child = ancestor;
+    }
+    false
+}
+
+/// The checked radio buttons among `controls`: per group (same name), the
+/// last one marked checked, as checking one unchecks the rest
+fn checked_radios(dom: &Dom, controls: &[NodeId]) -> Vec<NodeId> {
+    let mut checked: Vec<(&str, NodeId)> = Vec::new();
+    for &control in controls {
+        if !is_tag(dom, control, "input") || input_type(dom, control) != "radio" || dom.get_attribute(control, "checked").is_none() {
+            continue;
+        }
+        let name = dom.get_attribute(control, "name").unwrap_or_default();
+        match checked.iter_mut().find(|(group, _)| !name.is_empty() && *group == name) {
+            Some(entry) => entry.1 = control,
+            None => checked.push((name, control)),
+        }
+    }
+    checked.into_iter().map(|(_, control)| control).collect()
+}
+
+/// A select's selected options that are not disabled, in order. A select
+/// showing one option at a time always has one selected: the last marked
+/// selected, or else the first that is not disabled
+fn selected_options(dom: &Dom, select: NodeId) -> Vec<NodeId> {
+    let options: Vec<NodeId> = dom.nodes[select].children.iter()
+        .flat_map(|&child| match is_tag(dom, child, "optgroup") {
+            true => dom.nodes[child].children.clone(),
+            false => vec![child],
+        })
+        .filter(|&node| is_tag(dom, node, "option"))
+        .collect();
+    let disabled = |option: NodeId| {
+        dom.get_attribute(option, "disabled").is_some()
+            || dom.nodes[option].parent.is_some_and(|parent| {
+                is_tag(dom, parent, "optgroup") && dom.get_attribute(parent, "disabled").is_some()
+            })
+    };
+    let marked = |option: &NodeId| dom.get_attribute(*option, "selected").is_some();
+
+    let multiple = dom.get_attribute(select, "multiple").is_some();
+    let size = dom.get_attribute(select, "size").and_then(|size| size.trim().parse::<u32>().ok()).unwrap_or(0);
+    let selected: Vec<NodeId> = if multiple {
+        options.iter().copied().filter(marked).collect()
+    } else {
+        match options.iter().copied().rfind(marked) {
+            Some(option) => vec![option],
+            None if size <= 1 => options.iter().copied().find(|&option| !disabled(option)).into_iter().collect(),
+            None => Vec::new(),
+        }
+    };
+    selected.into_iter().filter(|&option| !disabled(option)).collect()
+}
+
+/// An option's value attribute, or else its text with whitespace collapsed
+fn option_value(dom: &Dom, option: NodeId) -> String {
+    match dom.get_attribute(option, "value") {
+        Some(value) => value.to_string(),
+        None => text_content(dom, option).split_ascii_whitespace().collect::<Vec<_>>().join(" "),
+    }
+}
+
+/// An input's value attribute as its type sanitizes it: line breaks
+/// removed from single-line text, and url and email values trimmed
+fn input_value(dom: &Dom, node: NodeId, kind: &str) -> String {
+    let value = dom.get_attribute(node, "value").unwrap_or_default();
+    match kind {
+        "text" | "search" | "tel" | "password" => value.replace(['\r', '\n'], ""),
+        "url" | "email" => value.replace(['\r', '\n'], "").trim_matches(|c: char| c.is_ascii_whitespace()).to_string(),
+        _ => value.to_string(),
+    }
+}
+
+fn text_content(dom: &Dom, node: NodeId) -> String {
+    let mut text = String::new();
+    for &child in &dom.nodes[node].children {
+        match &dom.nodes[child].node_type {
+            NodeType::Text(t) => text.push_str(t),
+            NodeType::Element(_) => text.push_str(&text_content(dom, child)),
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::html::tree_builder::HtmlParser;
+
+    /// The document, its first form and the element with `id` (if any)
+    fn parse(html: &str, id: Option<&str>) -> (Dom, NodeId, Option<NodeId>) {
+        let dom = HtmlParser::new(html).parse();
+        let nodes = tree_order(&dom);
+        let form = nodes.iter().copied().find(|&node| is_tag(&dom, node, "form")).unwrap();
+        let element = id.and_then(|id| nodes.into_iter().find(|&node| dom.get_attribute(node, "id") == Some(id)));
+        (dom, form, element)
+    }
+
+    fn texts(entries: &[FormEntry]) -> Vec<(String, String)> {
+        entries.iter()
+            .map(|entry| match &entry.value {
+                FormValue::Text(value) => (entry.name.clone(), value.clone()),
+                FormValue::File { filename, .. } => (entry.name.clone(), format!("file:{}", filename)),
+            })
+            .collect()
+    }
+
+    fn pairs(expected: &[(&str, &str)]) -> Vec<(String, String)> {
+        expected.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn test_entries_follow_the_controls() {
+        let (dom, form, submitter) = parse(r#"
+            <form id="f">
+              <input name="q" value="rust
+ lang">
+              <input name="empty">
+              <input name="off" disabled value="x">
+              <input type="checkbox" name="a" checked>
+              <input type="checkbox" name="b">
+              <input type="radio" name="r" value="1" checked>
+              <input type="radio" name="r" value="2" checked>
+              <select name="s"><option>First</option><option disabled>Second</option></select>
+              <select name="m" multiple>
+                <option value="x" selected>X</option>
+                <optgroup label="g"><option selected>  Y  z </option></optgroup>
+              </select>
+              <textarea name="t">line one
+line two</textarea>
+              <fieldset disabled><legend><input name="in-legend" value="1"></legend><input name="hidden-by-fieldset"></fieldset>
+              <input type="file" name="upload">
+              <button name="other" value="no">Other</button>
+              <button id="go" name="go" value="yes">Go</button>
+            </form>
+            <input name="outside" value="1" form="f">
+        "#, Some("go"));
+        assert_eq!(texts(&construct_form_data(&dom, form, submitter)), pairs(&[
+            ("q", "rust lang"),
+            ("empty", ""),
+            ("a", "on"),
+            ("r", "2"),
+            ("s", "First"),
+            ("m", "x"),
+            ("m", "Y z"),
+            ("t", "line one\nline two"),
+            ("in-legend", "1"),
+            ("upload", "file:"),
+            ("go", "yes"),
+            ("outside", "1"),
+        ]));
+        assert!(construct_form_data(&dom, form, None).iter().all(|entry| entry.name != "go"));
+    }
+
+    #[test]
+    fn test_get_submission_replaces_the_query() {
+        let (dom, form, submitter) = parse(r#"
+            <form action="/search?old=1#results">
+              <input name="q" value="café & crème">
+              <input type="image" id="go" name="pos">
+            </form>
+        "#, Some("go"));
+        let submission = form_submission(&dom, form, submitter, "https://example.com/page").unwrap();
+        assert_eq!(submission.method, Method::Get);
+        assert_eq!(submission.url, "https://example.com/search?q=caf%C3%A9+%26+cr%C3%A8me&pos.x=0&pos.y=0#results");
+        assert_eq!(submission.body, None);
+        let (dom, form, _) = parse(r#"<form method="dialog"></form>"#, None);
+        assert_eq!(form_submission(&dom, form, None, "https://example.com/"), None);
+    }
+
+    #[test]
+    fn test_post_submission_bodies() {
+        let (dom, form, submitter) = parse(r#"
+            <form action="login" method="post">
+              <input name="user" value="ada">
+              <input type="password" name="password" value="p&ss word">
+              <button id="in" formenctype="multipart/form-data" formaction="/upload">Log in</button>
+            </form>
+        "#, Some("in"));
+        let submission = form_submission(&dom, form, None, "https://example.com/account/").unwrap();
+        assert_eq!(submission.method, Method::Post);
+        assert_eq!(submission.url, "https://example.com/account/login");
+        assert_eq!(submission.content_type.as_deref(), Some("application/x-www-form-urlencoded"));
+        assert_eq!(submission.body.as_deref(), Some(&b"user=ada&password=p%26ss+word"[..]));
+
+        let submission = form_submission(&dom, form, submitter, "https://example.com/account/").unwrap();
+        assert_eq!(submission.url, "https://example.com/upload");
+        let content_type = submission.content_type.unwrap();
+        let boundary = content_type.strip_prefix("multipart/form-data; boundary=").unwrap();
+        let expected = format!(
+            "--{b}\r\nContent-Disposition: form-data; name=\"user\"\r\n\r\nada\r\n\
+             --{b}\r\nContent-Disposition: form-data; name=\"password\"\r\n\r\np&ss word\r\n--{b}--\r\n",
+            b = boundary,
+        );
+        assert_eq!(String::from_utf8(submission.body.unwrap()).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_encodings_escape_and_normalize() {
+        let entries = vec![
+            FormEntry::text("a\"b\nc", "one\ntwo"),
+            FormEntry {
+                name: "f".to_string(),
+                value: FormValue::File { filename: "x.txt".to_string(), content_type: "text/plain".to_string(), data: b"hi".to_vec() },
+            },
+        ];
+        assert_eq!(urlencode(&entries), "a%22b%0D%0Ac=one%0D%0Atwo&f=x.txt");
+        let body = String::from_utf8(multipart_encode(&entries, "B")).unwrap();
+        assert_eq!(body, "--B\r\nContent-Disposition: form-data; name=\"a%22b%0D%0Ac\"\r\n\r\none\r\ntwo\r\n\
+                          --B\r\nContent-Disposition: form-data; name=\"f\"; filename=\"x.txt\"\r\nContent-Type: text/plain\r\n\r\nhi\r\n--B--\r\n");
+        assert_eq!(text_plain_encode(&entries), "a\"b\r\nc=one\r\ntwo\r\nf=x.txt\r\n");
+    }
+}
@@ -0,0 +1,499 @@
+// engine/src/net/loader.rs
+// The fetching core that NetworkManager and its worker threads share
+//
+// - One HTTP client serves every request, so connections to a host are
+//   kept alive and reused
+// - At most `max_concurrent` requests are on the wire at once, whichever
+//   threads make them; the rest wait for a slot to free up
+// - A URL requested in the background while an earlier background request
+//   for it is still running joins that request instead of fetching twice
+// - A stale cache entry that allows it (stale-while-revalidate) is used at
+//   once and refreshed on a worker thread; one whose revalidation cannot
+//   reach the network is used unless it demands revalidation
+// - Redirects are followed here, hop by hop, so each hop carries the
+//   cookie jar's cookies and any it sets are kept
+// - Requests with a method and body (`send_request`) go straight to the
+//   network once; they are not retried, since a POST repeated is a second
+//   POST
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+use super::cache::{self, AssetCache, CacheEntry, CacheHeaders, CacheLookup};
+use super::cookies::{CookieJar, RequestSite};
+use super::pool::{Completer, WorkerPool};
+use super::request::{Method, Request};
+use super::url::{self, Url};
+use super::{FetchedResource, NetworkConfig};
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+/// Called with the URL of each finished background fetch
+pub(super) type LoadListener = Arc<dyn Fn(&str) + Send + Sync>;
+
+pub(super) struct Loader {
+    /// Asset cache for raw bytes with HTTP cache headers
+    pub(super) asset_cache: AssetCache,
+    pub(super) config: NetworkConfig,
+    pub(super) cookies: CookieJar,
+    /// The page requests are made for, which decides which are cross-site
+    pub(super) site_for_cookies: Mutex<Option<Url>>,
+    /// Built on the first request; it starts a runtime thread of its own
+    client: OnceLock<Result<reqwest::blocking::Client, String>>,
+    /// Requests on the wire
+    concurrent_count: Mutex<usize>,
+    slot_freed: Condvar,
+    /// Threads for background fetches and revalidations
+    pub(super) workers: WorkerPool,
+    /// Background fetches running, with everyone waiting on each
+    pub(super) in_flight: Mutex<HashMap<String, Vec<Completer>>>,
+    /// Stale entries being revalidated in the background
+    revalidating: Mutex<HashSet<String>>,
+    pub(super) load_listener: Mutex<Option<LoadListener>>,
+}
+
+impl Loader {
+    pub(super) fn new(config: NetworkConfig) -> Self {
+        Self {
+            asset_cache: match &config.cache_dir {
+                Some(dir) => AssetCache::open(dir),
+                None => AssetCache::new(),
+            },
+            cookies: match &config.cookie_file {
+                Some(path) => CookieJar::open(path),
+                None => CookieJar::new(),
+            },
+            site_for_cookies: Mutex::new(None),
+            workers: WorkerPool::new(config.max_concurrent),
+            config,
+            client: OnceLock::new(),
+            concurrent_count: Mutex::new(0),
+            slot_freed: Condvar::new(),
+            in_flight: Mutex::new(HashMap::new()),
+            revalidating: Mutex::new(HashSet::new()),
+            load_listener: Mutex::new(None),
+        }
+    }
+    
+    /// Fetch `url` for everyone waiting on it in `in_flight`, then tell the
+    /// load listener
+    pub(super) fn run_in_flight(self: &Arc<Self>, url: &str) {
+        let mut in_flight = InFlight { loader: self, url, resource: None };
+        in_flight.resource = self.fetch_resource(url);
+    }
+    
+    /// The shared HTTP client
+    fn client(&self) -> Result<&reqwest::blocking::Client, Error> {
+        let client = self.client.get_or_init(|| {
+            reqwest::blocking::Client::builder()
+                .timeout(Duration::from_secs(self.config.timeout_secs))
+                .redirect(reqwest::redirect::Policy::none())
+                .build()
+                .map_err(|e| e.to_string())
+        });
+        client.as_ref().map_err(|e| e.clone().into())
+    }
+    
+    /// Fetch a resource with caching, retries, and redirect handling
+    pub(super) fn fetch_resource(self: &Arc<Self>, url: &str) -> Option<FetchedResource> {
+        // Handle data URIs
+        if url::is_data_uri(url) {
+            return self.handle_data_uri(url);
+        }
+        
+        // Check asset cache
+        let request_headers = self.cache_request_headers(url);
+        match self.asset_cache.lookup_with_headers(url, &request_headers) {
+            CacheLookup::Hit(entry) => {
+                eprintln!("Asset cache hit for: {}", url);
+                return Some(cached_resource(url, entry));
+            }
+            CacheLookup::StaleWhileRevalidate(entry) => {
+                eprintln!("Asset cache hit for: {} (stale, revalidating)", url);
+                self.revalidate_in_background(url, &entry);
+                return Some(cached_resource(url, entry));
+            }
+            CacheLookup::Stale { etag, last_modified } => {
+                // Try conditional request
+                if let Some(resource) = self.fetch_with_validation(url, etag, last_modified) {
+                    return Some(resource);
+                }
+                // If validation fails, fall through to regular fetch
+            }
+            CacheLookup::Miss => {}
+        }
+        
+        // Regular fetch with retries, falling back to a stale copy if the
+        // network fails and the copy allows it
+        self.fetch_with_retries(url, false).or_else(|| {
+            let entry = self.asset_cache.lookup_stale(url, &request_headers)?;
+            eprintln!("Using stale cache entry for: {}", url);
+            Some(cached_resource(url, entry))
+        })
+    }
+    
+    /// Fetch a page to navigate to from the network, whatever the asset
+    /// cache holds, as a navigation from the current document
+    pub(super) fn fetch_document(&self, url: &str) -> Option<FetchedResource> {
+        if url::is_data_uri(url) {
+            return self.handle_data_uri(url);
+        }
+        self.fetch_with_retries(url, true)
+    }
+    
+    /// Handle data URI
+    fn handle_data_uri(&self, uri: &str) -> Option<FetchedResource> {
+        let (content_type, data) = url::parse_data_uri(uri)?;
+        Some(FetchedResource {
+            url: uri.to_string(),
+            status: 200,
+            data,
+            content_type,
+            headers: Vec::new(),
+            from_cache: false,
+        })
+    }
+    
+    /// Fetch with conditional validation (If-None-Match / If-Modified-Since)
+    fn fetch_with_validation(
+        &self,
+        url: &str,
+        etag: Option<String>,
+        last_modified: Option<String>,
+    ) -> Option<FetchedResource> {
+        self.wait_for_slot();
+        
+        let result = self.do_conditional_fetch(url, etag.as_deref(), last_modified.as_deref());
+        
+        self.release_slot();
+        
+        match result {
+            // 304 Not Modified - refresh cache and return cached data
+            Ok(Validation::NotModified(headers)) => self.asset_cache
+                .refresh_with_headers(url, &headers)
+                .map(|entry| cached_resource(url, entry)),
+            Ok(Validation::Modified(resource)) => Some(resource),
+            Err(_) => None,
+        }
+    }
+    
+    /// Refresh a stale entry on a worker thread, once at a time per URL
+    fn revalidate_in_background(self: &Arc<Self>, url: &str, entry: &CacheEntry) {
+        if !self.revalidating.lock().unwrap().insert(url.to_string()) {
+            return;
+        }
+        let loader = Arc::clone(self);
+        let url = url.to_string();
+        let (etag, last_modified) = (entry.headers.etag.clone(), entry.headers.last_modified.clone());
+        self.workers.execute(move || {
+            loader.fetch_with_validation(&url, etag, last_modified);
+            loader.revalidating.lock().unwrap().remove(&url);
+        });
+    }
+    
+    /// The request headers a response's Vary may name: the ones `send` adds
+    fn cache_request_headers(&self, url: &str) -> Vec<(String, String)> {
+        let Ok(url) = Url::parse(url) else {
+            return Vec::new();
+        };
+        let site_for_cookies = self.site_for_cookies.lock().unwrap().clone();
+        let request_site = RequestSite::of(&url, site_for_cookies.as_ref());
+        self.cookies.cookie_header(&url, request_site)
+            .map(|cookie| vec![("Cookie".to_string(), cookie)])
+            .unwrap_or_default()
+    }
+    
+    /// Fetch with retry logic: transport errors and 5xx responses are
+    /// retried, other error statuses are final. A navigation gets its
+    /// last error response back, for the page to show it; a subresource
+    /// gets None
+    fn fetch_with_retries(&self, url: &str, navigation: bool) -> Option<FetchedResource> {
+        let mut attempts = 0;
+        let mut backoff = self.config.initial_backoff_ms;
+        let mut error_response = None;
+        
+        while attempts < self.config.max_retries {
+            self.wait_for_slot();
+            
+            let result = self.do_fetch(url, navigation);
+            
+            self.release_slot();
+            
+            attempts += 1;
+            match result {
+                Ok(resource) if (200..300).contains(&resource.status) => {
+                    // Cache the resource
+                    let headers = cache::extract_cache_headers(&resource.headers);
+                    self.asset_cache.store_with_headers(
+                        url,
+                        &self.cache_request_headers(url),
+                        resource.data.clone(),
+                        resource.content_type.clone(),
+                        headers,
+                    );
+                    return Some(resource);
+                }
+                Ok(resource) => {
+                    eprintln!("Fetch attempt {} for {} answered with status {}", attempts, url, resource.status);
+                    let retry = resource.status >= 500;
+                    error_response = Some(resource);
+                    if !retry {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Fetch attempt {} failed for {}: {}", attempts, url, e);
+                }
+            }
+            
+            if attempts < self.config.max_retries {
+                thread::sleep(Duration::from_millis(backoff));
+                backoff *= 2; // Exponential backoff
+            }
+        }
+        
+        error_response.filter(|_| navigation)
+    }
+    
+    /// Perform the actual HTTP fetch; the response may have any status
+    fn do_fetch(&self, url: &str, navigation: bool) -> Result<FetchedResource, Error> {
+        let response = match navigation {
+            true => self.send(Method::Get, url, &[], None, true)?,
+            false => self.get(url, &[])?,
+        };
+        into_resource(response)
+    }
+    
+    /// Perform conditional fetch
+    fn do_conditional_fetch(
+        &self,
+        url: &str,
+        etag: Option<&str>,
+        last_modified: Option<&str>,
+    ) -> Result<Validation, Error> {
+        let mut headers = Vec::new();
+        if let Some(etag) = etag {
+            headers.push(("If-None-Match", etag));
+        }
+        if let Some(lm) = last_modified {
+            headers.push(("If-Modified-Since", lm));
+        }
+        
+        let response = self.get(url, &headers)?;
+        
+        if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+            let headers: Vec<(String, String)> = response
+                .headers()
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
+                .collect();
+            return Ok(Validation::NotModified(cache::extract_cache_headers(&headers)));
+        }
+        
+        if !response.status().is_success() {
+            return Err(format!("HTTP error: {}", response.status()).into());
+        }
+        
+        let resource = into_resource(response)?;
+        
+        // Cache the new resource
+        let cache_headers = cache::extract_cache_headers(&resource.headers);
+        self.asset_cache.store_with_headers(
+            url,
+            &self.cache_request_headers(url),
+            resource.data.clone(),
+            resource.content_type.clone(),
+            cache_headers,
+        );
+        
+        Ok(Validation::Modified(resource))
+    }
+    
+    /// Send `request` once, without the cache, and return the final
+    /// response whatever its status; a non-error response to an unsafe
+    /// method drops the cached copy of the URL
+    pub(super) fn send_request(&self, request: Request) -> Option<FetchedResource> {
+        self.wait_for_slot();
+        let result = self.send(request.method, &request.url, &request.headers, request.body, request.navigation)
+            .and_then(into_resource);
+        self.release_slot();
+        
+        match result {
+            Ok(resource) => {
+                if !request.method.is_safe() && (200..400).contains(&resource.status) {
+                    self.asset_cache.remove(&request.url);
+                }
+                Some(resource)
+            }
+            Err(e) => {
+                eprintln!("{} {} failed: {}", request.method.as_str(), request.url, e);
+                None
+            }
+        }
+    }
+    
+    /// GET `url`, as a subresource of the document
+    fn get(&self, url: &str, headers: &[(&str, &str)]) -> Result<reqwest::blocking::Response, Error> {
+        let headers: Vec<(String, String)> = headers.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        self.send(Method::Get, url, &headers, None, false)
+    }
+    
+    /// Send a request, following redirects here rather than in the client
+    /// so that every hop sends the jar's cookies and stores the ones it
+    /// sets. A 303, or a 301 or 302 answering a POST, turns the request
+    /// into a GET without a body; other redirects repeat it as it was
+    fn send(
+        &self,
+        mut method: Method,
+        url: &str,
+        headers: &[(String, String)],
+        mut body: Option<Vec<u8>>,
+        navigation: bool,
+    ) -> Result<reqwest::blocking::Response, Error> {
+        let client = self.client()?;
+        let site_for_cookies = self.site_for_cookies.lock().unwrap().clone();
+        let mut current = Url::parse(url)?;
+        let mut headers = headers.to_vec();
+        
+        for _ in 0..=self.config.max_redirects {
+            let request_site = match navigation {
+                true => RequestSite::of_navigation(&current, site_for_cookies.as_ref(), method.is_safe()),
+                false => RequestSite::of(&current, site_for_cookies.as_ref()),
+            };
+            let method_name = reqwest::Method::from_bytes(method.as_str().as_bytes())?;
+            let mut request = client.request(method_name, current.without_fragment().to_string());
+            for (name, value) in &headers {
+                request = request.header(name.as_str(), value.as_str());
+            }
+            if let Some(cookie) = self.cookies.cookie_header(&current, request_site) {
+                request = request.header("Cookie", cookie);
+            }
+            if let Some(body) = &body {
+                request = request.body(body.clone());
+            }
+            
+            let response = request.send()?;
+            for set_cookie in response.headers().get_all("set-cookie") {
+                if let Ok(set_cookie) = set_cookie.to_str() {
+                    self.cookies.set_cookie(&current, set_cookie, request_site);
+                }
+            }
+            
+            let status = response.status().as_u16();
+            let location = response.headers().get("location").and_then(|v| v.to_str().ok());
+            match (status, location) {
+                (301 | 302 | 303 | 307 | 308, Some(location)) => current = current.join(location)?,
+                _ => return Ok(response),
+            }
+            let becomes_get = (status == 303 && method != Method::Head)
+                || (matches!(status, 301 | 302) && method == Method::Post);
+            if becomes_get {
+                method = Method::Get;
+                body = None;
+                headers.retain(|(name, _)| !is_body_header(name));
+            }
+        }
+        
+        Err(format!("Too many redirects fetching {}", url).into())
+    }
+    
+    /// Wait for a concurrency slot
+    fn wait_for_slot(&self) {
+        let count = self.concurrent_count.lock().unwrap();
+        let mut count = self.slot_freed
+            .wait_while(count, |count| *count >= self.config.max_concurrent.max(1))
+            .unwrap();
+        *count += 1;
+    }
+    
+    /// Release a concurrency slot
+    fn release_slot(&self) {
+        let mut count = self.concurrent_count.lock().unwrap();
+        if *count > 0 {
+            *count -= 1;
+        }
+        self.slot_freed.notify_one();
+    }
+}
+
+/// The outcome of a conditional request
+enum Validation {
+    /// 304 Not Modified, with the headers it updates
+    NotModified(CacheHeaders),
+    Modified(FetchedResource),
+}
+
+fn cached_resource(url: &str, entry: CacheEntry) -> FetchedResource {
+    FetchedResource {
+        url: url.to_string(),
+        status: 200,
+        data: entry.data,
+        content_type: entry.content_type,
+        headers: Vec::new(),
+        from_cache: true,
+    }
+}
+
+/// Headers describing a request body, which a redirect to GET drops
+fn is_body_header(name: &str) -> bool {
+    ["content-type", "content-encoding", "content-language", "content-location", "content-length"]
+        .iter()
+        .any(|header| name.eq_ignore_ascii_case(header))
+}
+
+/// A response as a `FetchedResource`, at the URL it finally came from
+fn into_resource(response: reqwest::blocking::Response) -> Result<FetchedResource, Error> {
+    let headers: Vec<(String, String)> = response
+        .headers()
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
+        .collect();
+    
+    let content_type = response
+        .headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("application/octet-stream")
+        .to_string();
+    
+    let final_url = response.url().to_string();
+    let status = response.status().as_u16();
+    let bytes = response.bytes()?.to_vec();
+    
+    Ok(FetchedResource {
+        url: final_url,
+        status,
+        data: bytes,
+        content_type,
+        headers,
+        from_cache: false,
+    })
+}
+
+/// A background fetch that completes its waiters when dropped, so that a
+/// panicking fetch still releases them (empty-handed)
+struct InFlight<'a> {
+    loader: &'a Loader,
+    url: &'a str,
+    resource: Option<FetchedResource>,
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        let waiting = match self.loader.in_flight.lock() {
+            Ok(mut in_flight) => in_flight.remove(self.url).unwrap_or_default(),
+            Err(_) => Vec::new(),
+        };
+        for completer in waiting {
+            completer.complete(self.resource.clone());
+        }
+        let listener = self.loader.load_listener.lock().ok().and_then(|listener| listener.clone());
+        if let Some(listener) = listener {
+            listener(self.url);
+        }
+    }
+}
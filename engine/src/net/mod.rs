@@ -1,17 +1,29 @@
 pub mod url;
 pub mod cache;
+pub mod cookies;
+pub mod form;
 pub mod image;
 pub mod rewriter;
+mod disk;
+mod loader;
+mod pool;
+mod request;
 
 use std::collections::HashMap;
-use std::sync::Mutex;
-use std::time::Duration;
-use std::thread;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 
-pub use url::{resolve_url, resolve_url_with_base, parse_srcset, select_srcset_image, SrcsetEntry, ParsedUrl, is_data_uri, parse_data_uri};
+use loader::Loader;
+use pool::Completer;
+
+pub use url::{resolve_url, resolve_url_with_base, parse_srcset, select_srcset_image, SrcsetEntry, Url, UrlError, Origin, Host, is_data_uri, parse_data_uri};
 pub use cache::{AssetCache, CacheHeaders, CacheLookup, CacheEntry};
+pub use cookies::{Cookie, CookieJar, RequestSite, SameSite};
 pub use image::{ImageType, detect_image_type, decode_image, ImageDecodeError};
 pub use rewriter::{HtmlRewriter, RewriterConfig, ProcessedImage};
+pub use pool::FetchHandle;
+pub use request::{Method, RequestBuilder};
+pub use form::{construct_form_data, form_owner, form_submission, is_submit_button, FormEntry, FormSubmission, FormValue};
 
 /// Configuration for the NetworkManager
 #[derive(Clone)]
@@ -28,6 +40,12 @@ pub struct NetworkConfig {
     pub max_concurrent: usize,
     /// Maximum size for inline data URIs (bytes)
     pub max_inline_size: usize,
+    /// File the cookie jar is kept in between runs; None keeps cookies in
+    /// memory only
+    pub cookie_file: Option<PathBuf>,
+    /// Directory the HTTP cache is kept in between runs; None keeps it in
+    /// memory only
+    pub cache_dir: Option<PathBuf>,
 }
 
 impl Default for NetworkConfig {
@@ -39,6 +57,8 @@ impl Default for NetworkConfig {
             initial_backoff_ms: 100,
             max_concurrent: 6,
             max_inline_size: 32 * 1024, // 32KB
+            cookie_file: None,
+            cache_dir: None,
         }
     }
 }
@@ -47,22 +67,32 @@ impl Default for NetworkConfig {
 #[derive(Debug, Clone)]
 pub struct FetchedResource {
     pub url: String,
+    /// The HTTP status; 200 for data: URIs and cache hits
+    pub status: u16,
     pub data: Vec<u8>,
     pub content_type: String,
     pub headers: Vec<(String, String)>,
     pub from_cache: bool,
 }
 
+/// An image requested through `loaded_image` that has not decoded
+enum ImageLoad {
+    Pending(FetchHandle),
+    Failed,
+}
+
 /// Network manager with caching, retry logic, and concurrency control
+///
+/// Fetches run on the calling thread (`fetch_resource`) or on a pool of
+/// `max_concurrent` worker threads (`fetch_resource_async`,
+/// `fetch_resource_with`); all of them share one connection pool.
 pub struct NetworkManager {
     /// Legacy image cache (RgbaImage) for backward compatibility
     image_cache: Mutex<HashMap<String, ::image::RgbaImage>>,
-    /// Asset cache for raw bytes with HTTP cache headers
-    asset_cache: AssetCache,
-    /// Configuration
-    config: NetworkConfig,
-    /// Semaphore for concurrency limiting
-    concurrent_count: Mutex<usize>,
+    /// Images `loaded_image` is still waiting for, or gave up on
+    image_loads: Mutex<HashMap<String, ImageLoad>>,
+    /// Client, asset cache, limits and worker threads
+    loader: Arc<Loader>,
     /// Base URL of the current document
     document_url: Mutex<Option<String>>,
     /// Base href from <base> tag
@@ -83,9 +113,8 @@ impl NetworkManager {
     pub fn with_config(config: NetworkConfig) -> Self {
         Self {
             image_cache: Mutex::new(HashMap::new()),
-            asset_cache: AssetCache::new(),
-            config,
-            concurrent_count: Mutex::new(0),
+            image_loads: Mutex::new(HashMap::new()),
+            loader: Arc::new(Loader::new(config)),
             document_url: Mutex::new(None),
             base_href: Mutex::new(None),
         }
@@ -95,6 +124,7 @@ impl NetworkManager {
     pub fn set_document_url(&self, url: &str) {
         let mut doc_url = self.document_url.lock().unwrap();
         *doc_url = Some(url.to_string());
+        *self.loader.site_for_cookies.lock().unwrap() = Url::parse(url).ok();
     }
     
     /// Set the base href from <base> tag
@@ -133,7 +163,42 @@ impl NetworkManager {
         
         // Fetch the resource
         let resource = self.fetch_resource(&resolved_url)?;
+        self.decode_and_cache(url, &resource)
+    }
+    
+    /// An image that has already been fetched, without waiting for the
+    /// network: one not yet requested starts loading in the background and
+    /// this returns None until it has arrived (or for good, if it fails)
+    pub fn loaded_image(&self, url: &str) -> Option<::image::RgbaImage> {
+        if let Some(img) = self.image_cache.lock().unwrap().get(url) {
+            return Some(img.clone());
+        }
+        if url::is_data_uri(url) {
+            return self.fetch_image(url);
+        }
         
+        let mut loads = self.image_loads.lock().unwrap();
+        let handle = match loads.remove(url) {
+            Some(ImageLoad::Pending(handle)) if handle.is_finished() => handle,
+            Some(load) => {
+                loads.insert(url.to_string(), load);
+                return None;
+            }
+            None => {
+                let handle = self.fetch_resource_async(&self.resolve_url(url));
+                loads.insert(url.to_string(), ImageLoad::Pending(handle));
+                return None;
+            }
+        };
+        let img = handle.wait().and_then(|resource| self.decode_and_cache(url, &resource));
+        if img.is_none() {
+            loads.insert(url.to_string(), ImageLoad::Failed);
+        }
+        img
+    }
+    
+    /// Decode a fetched image into the image cache
+    fn decode_and_cache(&self, url: &str, resource: &FetchedResource) -> Option<::image::RgbaImage> {
         // Detect image type and decode
         let image_type = detect_image_type(Some(&resource.content_type), &resource.data);
         
@@ -151,260 +216,509 @@ impl NetworkManager {
         }
     }
     
-    /// Fetch a resource with caching, retries, and redirect handling
+    /// Fetch a resource with caching, retries, and redirect handling,
+    /// blocking until it arrives
     pub fn fetch_resource(&self, url: &str) -> Option<FetchedResource> {
-        // Handle data URIs
-        if url::is_data_uri(url) {
-            return self.handle_data_uri(url);
-        }
-        
-        // Check asset cache
-        match self.asset_cache.lookup(url) {
-            CacheLookup::Hit(entry) => {
-                eprintln!("Asset cache hit for: {}", url);
-                return Some(FetchedResource {
-                    url: url.to_string(),
-                    data: entry.data,
-                    content_type: entry.content_type,
-                    headers: Vec::new(),
-                    from_cache: true,
-                });
-            }
-            CacheLookup::Stale { etag, last_modified } => {
-                // Try conditional request
-                if let Some(resource) = self.fetch_with_validation(url, etag, last_modified) {
-                    return Some(resource);
-                }
-                // If validation fails, fall through to regular fetch
-            }
-            CacheLookup::Miss => {}
-        }
-        
-        // Regular fetch with retries
-        self.fetch_with_retries(url)
+        self.loader.fetch_resource(url)
     }
     
-    /// Handle data URI
-    fn handle_data_uri(&self, uri: &str) -> Option<FetchedResource> {
-        let (content_type, data) = url::parse_data_uri(uri)?;
-        Some(FetchedResource {
-            url: uri.to_string(),
-            data,
-            content_type,
-            headers: Vec::new(),
-            from_cache: false,
-        })
+    /// Fetch a page to navigate to, blocking until it arrives; unlike
+    /// `fetch_resource` it always goes to the network, since a page may
+    /// change between visits, and it is sent as a navigation away from the
+    /// current document, so call `set_document_url` only once it arrives.
+    /// An error status comes back as the response, with whatever page the
+    /// server sent to explain it; None means no response arrived
+    pub fn fetch_document(&self, url: &str) -> Option<FetchedResource> {
+        self.loader.fetch_document(url)
     }
     
-    /// Fetch with conditional validation (If-None-Match / If-Modified-Since)
-    fn fetch_with_validation(
-        &self,
-        url: &str,
-        etag: Option<String>,
-        last_modified: Option<String>,
-    ) -> Option<FetchedResource> {
-        self.wait_for_slot();
-        
-        let result = self.do_conditional_fetch(url, etag.as_deref(), last_modified.as_deref());
-        
-        self.release_slot();
-        
-        match result {
-            Ok((is_modified, resource)) => {
-                if !is_modified {
-                    // 304 Not Modified - refresh cache and return cached data
-                    self.asset_cache.refresh(url);
-                    if let CacheLookup::Hit(entry) = self.asset_cache.lookup(url) {
-                        return Some(FetchedResource {
-                            url: url.to_string(),
-                            data: entry.data,
-                            content_type: entry.content_type,
-                            headers: Vec::new(),
-                            from_cache: true,
-                        });
-                    }
-                }
-                resource
-            }
-            Err(_) => None,
-        }
+    /// Fetch a page to navigate to on a worker thread and pass it to
+    /// `callback` there
+    pub fn fetch_document_with(&self, url: &str, callback: impl FnOnce(Option<FetchedResource>) + Send + 'static) {
+        let completer = Completer::callback(callback);
+        let loader = Arc::clone(&self.loader);
+        let url = url.to_string();
+        self.loader.workers.execute(move || completer.complete(loader.fetch_document(&url)));
     }
     
-    /// Fetch with retry logic
-    fn fetch_with_retries(&self, url: &str) -> Option<FetchedResource> {
-        let mut attempts = 0;
-        let mut backoff = self.config.initial_backoff_ms;
-        
-        while attempts < self.config.max_retries {
-            self.wait_for_slot();
-            
-            let result = self.do_fetch(url);
-            
-            self.release_slot();
-            
-            match result {
-                Ok(resource) => {
-                    // Cache the resource
-                    let headers = cache::extract_cache_headers(&resource.headers);
-                    self.asset_cache.store(
-                        url,
-                        resource.data.clone(),
-                        resource.content_type.clone(),
-                        headers,
-                    );
-                    return Some(resource);
-                }
-                Err(e) => {
-                    attempts += 1;
-                    eprintln!("Fetch attempt {} failed for {}: {}", attempts, url, e);
-                    
-                    if attempts < self.config.max_retries {
-                        thread::sleep(Duration::from_millis(backoff));
-                        backoff *= 2; // Exponential backoff
-                    }
-                }
-            }
-        }
-        
-        None
+    /// Start a request with a method, headers and a body; unlike the
+    /// fetches it bypasses the cache and is not retried
+    pub fn request(&self, method: Method, url: &str) -> RequestBuilder<'_> {
+        RequestBuilder::new(self, method, url)
     }
     
-    /// Perform the actual HTTP fetch
-    fn do_fetch(&self, url: &str) -> Result<FetchedResource, Box<dyn std::error::Error + Send + Sync>> {
-        let client = reqwest::blocking::Client::builder()
-            .timeout(Duration::from_secs(self.config.timeout_secs))
-            .redirect(reqwest::redirect::Policy::limited(self.config.max_redirects as usize))
-            .build()?;
-        
-        let response = client.get(url).send()?;
-        
-        if !response.status().is_success() {
-            return Err(format!("HTTP error: {}", response.status()).into());
-        }
-        
-        let headers: Vec<(String, String)> = response
-            .headers()
-            .iter()
-            .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
+    fn send_request(&self, request: request::Request) -> Option<FetchedResource> {
+        self.loader.send_request(request)
+    }
+    
+    fn send_request_in_background(&self, request: request::Request, completer: Completer) {
+        let loader = Arc::clone(&self.loader);
+        self.loader.workers.execute(move || completer.complete(loader.send_request(request)));
+    }
+    
+    /// Fetch a resource on a worker thread; the handle can be waited on or
+    /// awaited
+    pub fn fetch_resource_async(&self, url: &str) -> FetchHandle {
+        let (completer, handle) = Completer::handle();
+        self.fetch_in_background(url, completer);
+        handle
+    }
+    
+    /// Fetch a resource on a worker thread and pass it to `callback` there
+    pub fn fetch_resource_with(&self, url: &str, callback: impl FnOnce(Option<FetchedResource>) + Send + 'static) {
+        self.fetch_in_background(url, Completer::callback(callback));
+    }
+    
+    /// Fetch several resources in parallel, blocking until all have arrived
+    ///
+    /// This waits on the worker threads, so must not be called from a
+    /// `fetch_resource_with` callback.
+    pub fn fetch_resources(&self, urls: &[String]) -> Vec<Option<FetchedResource>> {
+        let handles: Vec<FetchHandle> = urls.iter()
+            .map(|url| self.fetch_resource_async(url))
             .collect();
-        
-        let content_type = response
-            .headers()
-            .get("content-type")
-            .and_then(|v| v.to_str().ok())
-            .unwrap_or("application/octet-stream")
-            .to_string();
-        
-        let final_url = response.url().to_string();
-        let bytes = response.bytes()?.to_vec();
-        
-        Ok(FetchedResource {
-            url: final_url,
-            data: bytes,
-            content_type,
-            headers,
-            from_cache: false,
-        })
+        handles.into_iter().map(FetchHandle::wait).collect()
     }
     
-    /// Perform conditional fetch
-    fn do_conditional_fetch(
+    /// Fetch several resources in parallel and pass them, in order, to
+    /// `callback` on the worker thread that finishes last; with no URLs it
+    /// is called at once, on this thread
+    pub fn fetch_resources_with(
         &self,
-        url: &str,
-        etag: Option<&str>,
-        last_modified: Option<&str>,
-    ) -> Result<(bool, Option<FetchedResource>), Box<dyn std::error::Error + Send + Sync>> {
-        let client = reqwest::blocking::Client::builder()
-            .timeout(Duration::from_secs(self.config.timeout_secs))
-            .redirect(reqwest::redirect::Policy::limited(self.config.max_redirects as usize))
-            .build()?;
-        
-        let mut request = client.get(url);
-        
-        if let Some(etag) = etag {
-            request = request.header("If-None-Match", etag);
+        urls: &[String],
+        callback: impl FnOnce(Vec<Option<FetchedResource>>) + Send + 'static,
+    ) {
+        if urls.is_empty() {
+            callback(Vec::new());
+            return;
         }
-        if let Some(lm) = last_modified {
-            request = request.header("If-Modified-Since", lm);
-        }
-        
-        let response = request.send()?;
-        
-        if response.status() == reqwest::StatusCode::NOT_MODIFIED {
-            return Ok((false, None));
-        }
-        
-        if !response.status().is_success() {
-            return Err(format!("HTTP error: {}", response.status()).into());
+        let gathered = Arc::new(Mutex::new((vec![None; urls.len()], urls.len(), Some(callback))));
+        for (index, url) in urls.iter().enumerate() {
+            let gathered = Arc::clone(&gathered);
+            self.fetch_resource_with(url, move |resource| {
+                let mut gathered = gathered.lock().unwrap();
+                let (resources, remaining, callback) = &mut *gathered;
+                resources[index] = resource;
+                *remaining -= 1;
+                if *remaining == 0 {
+                    let (resources, callback) = (std::mem::take(resources), callback.take());
+                    drop(gathered);
+                    if let Some(callback) = callback {
+                        callback(resources);
+                    }
+                }
+            });
         }
-        
-        let headers: Vec<(String, String)> = response
-            .headers()
-            .iter()
-            .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
-            .collect();
-        
-        let content_type = response
-            .headers()
-            .get("content-type")
-            .and_then(|v| v.to_str().ok())
-            .unwrap_or("application/octet-stream")
-            .to_string();
-        
-        let final_url = response.url().to_string();
-        let bytes = response.bytes()?.to_vec();
-        
-        // Cache the new resource
-        let cache_headers = cache::extract_cache_headers(&headers);
-        self.asset_cache.store(url, bytes.clone(), content_type.clone(), cache_headers);
-        
-        Ok((true, Some(FetchedResource {
-            url: final_url,
-            data: bytes,
-            content_type,
-            headers,
-            from_cache: false,
-        })))
     }
     
-    /// Wait for a concurrency slot
-    fn wait_for_slot(&self) {
-        loop {
-            {
-                let mut count = self.concurrent_count.lock().unwrap();
-                if *count < self.config.max_concurrent {
-                    *count += 1;
-                    return;
-                }
-            }
-            thread::sleep(Duration::from_millis(10));
-        }
+    /// Call `listener` with the URL of every background fetch as it
+    /// finishes, e.g. to repaint once an image has loaded
+    pub fn set_load_listener(&self, listener: impl Fn(&str) + Send + Sync + 'static) {
+        *self.loader.load_listener.lock().unwrap() = Some(Arc::new(listener));
     }
     
-    /// Release a concurrency slot
-    fn release_slot(&self) {
-        let mut count = self.concurrent_count.lock().unwrap();
-        if *count > 0 {
-            *count -= 1;
+    fn fetch_in_background(&self, url: &str, completer: Completer) {
+        {
+            let mut in_flight = self.loader.in_flight.lock().unwrap();
+            if let Some(waiting) = in_flight.get_mut(url) {
+                waiting.push(completer);
+                return;
+            }
+            in_flight.insert(url.to_string(), vec![completer]);
         }
+        let loader = Arc::clone(&self.loader);
+        let url = url.to_string();
+        self.loader.workers.execute(move || {
+            loader.run_in_flight(&url);
+        });
     }
     
     /// Clear all caches
     pub fn clear_cache(&self) {
         let mut image_cache = self.image_cache.lock().unwrap();
         image_cache.clear();
-        self.asset_cache.clear();
+        self.image_loads.lock().unwrap().clear();
+        self.loader.asset_cache.clear();
+    }
+    
+    /// The cookies requests send and responses set, to list or clear
+    pub fn cookie_jar(&self) -> &CookieJar {
+        &self.loader.cookies
     }
     
     /// Get cache statistics
     pub fn cache_stats(&self) -> cache::CacheStats {
-        self.asset_cache.stats()
+        self.loader.asset_cache.stats()
     }
-    
-    /// Fetch multiple resources in parallel
-    pub fn fetch_resources(&self, urls: &[String]) -> Vec<Option<FetchedResource>> {
-        urls.iter()
-            .map(|url| self.fetch_resource(url))
-            .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    /// What the test server has seen
+    #[derive(Default)]
+    struct ServerStats {
+        connections: AtomicUsize,
+        requests: AtomicUsize,
+        active: AtomicUsize,
+        most_active: AtomicUsize,
+        /// The Cookie header of each request, empty when it had none
+        cookies: Mutex<Vec<String>>,
+        /// Conditional requests answered with 304
+        not_modified: AtomicUsize,
+        /// Each request's method, path and body
+        log: Mutex<Vec<String>>,
+    }
+
+    /// A keep-alive HTTP/1.1 server on localhost answering each GET after
+    /// `delay`, with a PNG for `/image.png` and the path otherwise;
+    /// `/login` sets a cookie and `/redirect` sets another on its way to
+    /// `/landing`; `/cached/...` may be cached for an hour and `/swr/...`
+    /// used stale, both with an ETag. Other methods are answered with the
+    /// method, path and body, and `/see-other` and `/temporary` redirect
+    /// them to `/landing` with a 303 and a 307
+    fn serve(delay: Duration) -> (String, Arc<ServerStats>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = format!("http://{}", listener.local_addr().unwrap());
+        let stats = Arc::new(ServerStats::default());
+        let server_stats = Arc::clone(&stats);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                server_stats.connections.fetch_add(1, Ordering::SeqCst);
+                let stats = Arc::clone(&server_stats);
+                thread::spawn(move || answer(stream, delay, &stats));
+            }
+        });
+        (address, stats)
+    }
+
+    fn answer(mut stream: TcpStream, delay: Duration, stats: &ServerStats) {
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        loop {
+            let mut request_line = String::new();
+            if reader.read_line(&mut request_line).unwrap_or(0) == 0 {
+                return;
+            }
+            let mut header = String::new();
+            let mut cookie = String::new();
+            let mut conditional = false;
+            let mut content_length = 0;
+            while reader.read_line(&mut header).unwrap_or(0) > 2 {
+                if let Some((name, value)) = header.split_once(':') {
+                    if name.eq_ignore_ascii_case("cookie") {
+                        cookie = value.trim().to_string();
+                    }
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap_or(0);
+                    }
+                    conditional |= name.eq_ignore_ascii_case("if-none-match");
+                }
+                header.clear();
+            }
+            let mut request_body = vec![0; content_length];
+            if reader.read_exact(&mut request_body).is_err() {
+                return;
+            }
+            let mut words = request_line.split_whitespace();
+            let (method, path) = (words.next().unwrap_or("GET"), words.next().unwrap_or("/"));
+            let request = format!("{} {} {}", method, path, String::from_utf8_lossy(&request_body));
+            stats.log.lock().unwrap().push(request.trim_end().to_string());
+            stats.requests.fetch_add(1, Ordering::SeqCst);
+            stats.cookies.lock().unwrap().push(cookie);
+            let active = stats.active.fetch_add(1, Ordering::SeqCst) + 1;
+            stats.most_active.fetch_max(active, Ordering::SeqCst);
+            thread::sleep(delay);
+            stats.active.fetch_sub(1, Ordering::SeqCst);
+
+            let body = if method != "GET" {
+                request.trim_end().as_bytes().to_vec()
+            } else if path == "/image.png" {
+                let mut png = Vec::new();
+                ::image::DynamicImage::new_rgba8(3, 2)
+                    .write_to(&mut std::io::Cursor::new(&mut png), ::image::ImageOutputFormat::Png)
+                    .unwrap();
+                png
+            } else {
+                path.as_bytes().to_vec()
+            };
+            let extra = match path {
+                "/login" => "Set-Cookie: session=abc; Path=/; Max-Age=3600\r\n",
+                "/redirect" => "Set-Cookie: hop=1\r\nLocation: /landing\r\n",
+                "/see-other" | "/temporary" => "Location: /landing\r\n",
+                _ if path.starts_with("/cached/") => "Cache-Control: max-age=3600\r\nETag: \"v1\"\r\n",
+                _ if path.starts_with("/swr/") => "Cache-Control: max-age=0, stale-while-revalidate=60\r\nETag: \"v1\"\r\n",
+                _ => "",
+            };
+            let (status, body) = match path {
+                "/redirect" => ("302 Found", body),
+                "/see-other" => ("303 See Other", body),
+                "/temporary" => ("307 Temporary Redirect", body),
+                "/forbidden" => ("403 Forbidden", body),
+                "/missing" => ("404 Not Found", body),
+                "/unavailable" => ("503 Service Unavailable", body),
+                _ if conditional => {
+                    stats.not_modified.fetch_add(1, Ordering::SeqCst);
+                    ("304 Not Modified", Vec::new())
+                }
+                _ => ("200 OK", body),
+            };
+            let head = format!(
+                "HTTP/1.1 {}\r\n{}Content-Type: application/octet-stream\r\nContent-Length: {}\r\n\r\n",
+                status, extra, body.len()
+            );
+            if stream.write_all(head.as_bytes()).and_then(|_| stream.write_all(&body)).is_err() {
+                return;
+            }
+        }
+    }
+
+    fn manager(max_concurrent: usize) -> NetworkManager {
+        NetworkManager::with_config(NetworkConfig { max_concurrent, max_retries: 1, ..NetworkConfig::default() })
+    }
+
+    fn body(resource: Option<FetchedResource>) -> String {
+        String::from_utf8(resource.expect("fetch succeeded").data).unwrap()
+    }
+
+    #[test]
+    fn test_requests_share_a_connection() {
+        let (server, stats) = serve(Duration::ZERO);
+        let network = manager(6);
+        for path in ["/a", "/b", "/c"] {
+            assert_eq!(body(network.fetch_resource(&format!("{}{}", server, path))), path);
+        }
+        assert_eq!(stats.requests.load(Ordering::SeqCst), 3);
+        assert_eq!(stats.connections.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_fetch_resources_in_parallel() {
+        let (server, stats) = serve(Duration::from_millis(300));
+        let network = manager(4);
+        let urls: Vec<String> = (0..4).map(|i| format!("{}/{}", server, i)).collect();
+        let start = Instant::now();
+        let bodies: Vec<String> = network.fetch_resources(&urls).into_iter().map(body).collect();
+        assert!(start.elapsed() < Duration::from_millis(900), "took {:?}", start.elapsed());
+        assert_eq!(bodies, vec!["/0", "/1", "/2", "/3"]);
+        assert_eq!(stats.most_active.load(Ordering::SeqCst), 4);
+    }
+
+    #[test]
+    fn test_max_concurrent_caps_requests() {
+        let (server, stats) = serve(Duration::from_millis(50));
+        let network = Arc::new(manager(2));
+        let urls: Vec<String> = (0..4).map(|i| format!("{}/bg{}", server, i)).collect();
+        // Background fetches and blocking ones on other threads share the cap
+        let handles: Vec<FetchHandle> = urls.iter().map(|url| network.fetch_resource_async(url)).collect();
+        let threads: Vec<_> = (0..4)
+            .map(|i| {
+                let (network, url) = (Arc::clone(&network), format!("{}/fg{}", server, i));
+                thread::spawn(move || network.fetch_resource(&url))
+            })
+            .collect();
+        assert!(handles.into_iter().all(|handle| handle.wait().is_some()));
+        assert!(threads.into_iter().all(|thread| thread.join().unwrap().is_some()));
+        assert_eq!(stats.requests.load(Ordering::SeqCst), 8);
+        assert_eq!(stats.most_active.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_background_fetches_of_one_url_share_a_request() {
+        let (server, stats) = serve(Duration::from_millis(100));
+        let network = manager(6);
+        let (loaded, finished) = mpsc::channel();
+        network.set_load_listener(move |url| loaded.send(url.to_string()).unwrap());
+        let url = format!("{}/shared", server);
+        let (sender, received) = mpsc::channel();
+        network.fetch_resource_with(&url, move |resource| sender.send(body(resource)).unwrap());
+        let handle = network.fetch_resource_async(&url);
+        assert_eq!(body(handle.wait()), "/shared");
+        assert_eq!(received.recv_timeout(Duration::from_secs(5)).unwrap(), "/shared");
+        assert_eq!(finished.recv_timeout(Duration::from_secs(5)).unwrap(), url);
+        assert_eq!(stats.requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_callbacks_receive_lists_documents_and_requests() {
+        let (server, _) = serve(Duration::from_millis(50));
+        let network = manager(6);
+        let (sender, received) = mpsc::channel();
+        let urls: Vec<String> = ["/one", "/two", "/three"].iter().map(|path| format!("{}{}", server, path)).collect();
+        let list = sender.clone();
+        network.fetch_resources_with(&urls, move |resources| {
+            list.send(resources.into_iter().map(body).collect::<Vec<_>>().join(" ")).unwrap()
+        });
+        assert_eq!(received.recv_timeout(Duration::from_secs(5)).unwrap(), "/one /two /three");
+        let list = sender.clone();
+        network.fetch_resources_with(&[], move |resources| list.send(format!("{} resources", resources.len())).unwrap());
+        assert_eq!(received.try_recv().unwrap(), "0 resources");
+
+        let document = sender.clone();
+        network.fetch_document_with(&format!("{}/page", server), move |page| document.send(body(page)).unwrap());
+        assert_eq!(received.recv_timeout(Duration::from_secs(5)).unwrap(), "/page");
+        network.request(Method::Post, &format!("{}/forbidden", server))
+            .body("x=1")
+            .send_with(move |response| {
+                let response = response.unwrap();
+                sender.send(format!("{} {}", response.status, String::from_utf8(response.data).unwrap())).unwrap()
+            });
+        assert_eq!(received.recv_timeout(Duration::from_secs(5)).unwrap(), "403 POST /forbidden x=1");
+    }
+
+    #[test]
+    fn test_loaded_image_does_not_wait() {
+        let (server, _) = serve(Duration::from_millis(100));
+        let network = manager(6);
+        let (loaded, finished) = mpsc::channel();
+        network.set_load_listener(move |_| loaded.send(()).unwrap());
+        network.set_document_url(&format!("{}/page.html", server));
+        assert!(network.loaded_image("image.png").is_none());
+        finished.recv_timeout(Duration::from_secs(5)).unwrap();
+        let image = network.loaded_image("image.png").expect("image has loaded");
+        assert_eq!(image.dimensions(), (3, 2));
+        // A resource that is not an image fails once and is not refetched
+        assert!(network.loaded_image("text.png").is_none());
+        finished.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(network.loaded_image("text.png").is_none());
+        assert!(network.loaded_image("text.png").is_none());
+        assert!(finished.recv_timeout(Duration::from_millis(200)).is_err());
+    }
+
+    #[test]
+    fn test_cookies_follow_requests_and_redirects() {
+        let (server, stats) = serve(Duration::ZERO);
+        let network = manager(6);
+        assert_eq!(body(network.fetch_resource(&format!("{}/login", server))), "/login");
+        assert_eq!(body(network.fetch_resource(&format!("{}/redirect", server))), "/landing");
+        assert_eq!(body(network.fetch_resource(&format!("{}/after", server))), "/after");
+        assert_eq!(
+            *stats.cookies.lock().unwrap(),
+            vec!["", "session=abc", "session=abc; hop=1", "session=abc; hop=1"]
+        );
+        let names: Vec<String> = network.cookie_jar().cookies().into_iter().map(|cookie| cookie.name).collect();
+        assert_eq!(names, vec!["session", "hop"]);
+        network.cookie_jar().clear();
+        network.fetch_resource(&format!("{}/cleared", server));
+        assert_eq!(stats.cookies.lock().unwrap().last().unwrap(), "");
+    }
+
+    #[test]
+    fn test_only_server_errors_are_retried() {
+        let (server, stats) = serve(Duration::ZERO);
+        let network = NetworkManager::with_config(NetworkConfig {
+            max_retries: 3,
+            initial_backoff_ms: 1,
+            ..NetworkConfig::default()
+        });
+        let missing = format!("{}/missing", server);
+        assert!(network.fetch_resource(&missing).is_none());
+        // A page that does not exist still has a page to show
+        let page = network.fetch_document(&missing).unwrap();
+        assert_eq!((page.status, page.data.as_slice()), (404, b"/missing".as_slice()));
+        assert_eq!(stats.requests.load(Ordering::SeqCst), 2);
+
+        let page = network.fetch_document(&format!("{}/unavailable", server)).unwrap();
+        assert_eq!(page.status, 503);
+        assert_eq!(stats.requests.load(Ordering::SeqCst), 5);
+    }
+
+    #[test]
+    fn test_documents_are_fetched_as_navigations() {
+        let (server, stats) = serve(Duration::ZERO);
+        let network = manager(6);
+        let site = Url::parse(&server).unwrap();
+        network.cookie_jar().set_cookie(&site, "strict=1; SameSite=Strict", RequestSite::SameSite);
+        network.cookie_jar().set_cookie(&site, "lax=1; SameSite=Lax", RequestSite::SameSite);
+        let last_cookies = || stats.cookies.lock().unwrap().last().unwrap().clone();
+
+        // Following a link from another site sends Lax cookies but not Strict ones
+        network.set_document_url("https://elsewhere.example/page.html");
+        assert_eq!(body(network.fetch_document(&format!("{}/from-elsewhere", server))), "/from-elsewhere");
+        assert_eq!(last_cookies(), "lax=1");
+        // A subresource of that page sends neither
+        network.fetch_resource(&format!("{}/image-elsewhere", server));
+        assert_eq!(last_cookies(), "");
+
+        network.set_document_url(&format!("{}/page.html", server));
+        network.fetch_document(&format!("{}/same-site", server));
+        assert_eq!(last_cookies(), "strict=1; lax=1");
+    }
+
+    #[test]
+    fn test_disk_cache_outlives_the_manager() {
+        let (server, stats) = serve(Duration::ZERO);
+        let dir = std::env::temp_dir().join(format!("grob-net-cache-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let config = NetworkConfig { cache_dir: Some(dir.clone()), max_retries: 1, ..NetworkConfig::default() };
+        let url = format!("{}/cached/style.css", server);
+        assert!(!NetworkManager::with_config(config.clone()).fetch_resource(&url).unwrap().from_cache);
+        let resource = NetworkManager::with_config(config).fetch_resource(&url).unwrap();
+        assert!(resource.from_cache);
+        assert_eq!(String::from_utf8(resource.data).unwrap(), "/cached/style.css");
+        assert_eq!(stats.requests.load(Ordering::SeqCst), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_stale_while_revalidate_answers_from_cache() {
+        let (server, stats) = serve(Duration::from_millis(100));
+        let network = manager(6);
+        let url = format!("{}/swr/app.js", server);
+        assert!(!network.fetch_resource(&url).unwrap().from_cache);
+        let start = Instant::now();
+        let resource = network.fetch_resource(&url).unwrap();
+        assert!(resource.from_cache);
+        assert!(start.elapsed() < Duration::from_millis(100), "took {:?}", start.elapsed());
+        // The revalidation happens behind it and gets a 304
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while stats.not_modified.load(Ordering::SeqCst) == 0 {
+            assert!(Instant::now() < deadline, "no revalidation");
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(stats.requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_requests_with_a_method_and_body() {
+        let (server, stats) = serve(Duration::ZERO);
+        let network = manager(6);
+        let post = |path: &str| {
+            network.request(Method::Post, &format!("{}{}", server, path))
+                .header("Content-Type", "application/x-www-form-urlencoded")
+                .body("user=ada")
+                .send()
+        };
+        assert_eq!(body(post("/echo")), "POST /echo user=ada");
+        // 303 turns the POST into a GET, 307 repeats it
+        assert_eq!(body(post("/see-other")), "/landing");
+        assert_eq!(body(post("/temporary")), "POST /landing user=ada");
+        // An error response still comes back, with its status and body
+        let forbidden = post("/forbidden").unwrap();
+        assert_eq!(forbidden.status, 403);
+        assert_eq!(forbidden.data, b"POST /forbidden user=ada");
+        assert_eq!(post("/echo").unwrap().status, 200);
+        assert_eq!(*stats.log.lock().unwrap(), vec![
+            "POST /echo user=ada",
+            "POST /see-other user=ada",
+            "GET /landing",
+            "POST /temporary user=ada",
+            "POST /landing user=ada",
+            "POST /forbidden user=ada",
+            "POST /echo user=ada",
+        ]);
+
+        // A POST invalidates what was cached for its URL
+        let url = format!("{}/cached/form", server);
+        assert!(network.fetch_resource(&url).is_some());
+        assert!(network.fetch_resource(&url).unwrap().from_cache);
+        assert!(network.request(Method::Post, &url).send().is_some());
+        assert!(!network.fetch_resource(&url).unwrap().from_cache);
     }
 }
@@ -0,0 +1,279 @@
+// engine/src/net/pool.rs
+// Worker threads for background fetches, and the handles their results
+// arrive through
+//
+// - The pool starts threads as work queues up, never more than its size,
+//   and keeps them for later jobs; dropping the pool lets them finish the
+//   queue and exit without waiting for them
+// - A panicking job is contained to that job; whatever it was to complete
+//   completes empty
+// - A fetch completes either a `FetchHandle`, which can be waited on or
+//   awaited as a future, or a callback run on the worker thread
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+
+use super::FetchedResource;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// A fixed-size pool of worker threads
+pub struct WorkerPool {
+    shared: Arc<PoolShared>,
+    size: usize,
+}
+
+struct PoolShared {
+    state: Mutex<PoolState>,
+    job_queued: Condvar,
+}
+
+#[derive(Default)]
+struct PoolState {
+    jobs: VecDeque<Job>,
+    workers: usize,
+    /// Workers waiting for a job
+    idle: usize,
+    closed: bool,
+}
+
+impl WorkerPool {
+    /// A pool of at most `size` threads (at least one)
+    pub fn new(size: usize) -> Self {
+        WorkerPool {
+            shared: Arc::new(PoolShared { state: Mutex::new(PoolState::default()), job_queued: Condvar::new() }),
+            size: size.max(1),
+        }
+    }
+
+    /// Run `job` on a worker thread once one is free
+    pub fn execute(&self, job: impl FnOnce() + Send + 'static) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.jobs.push_back(Box::new(job));
+        if state.jobs.len() > state.idle && state.workers < self.size {
+            state.workers += 1;
+            let shared = Arc::clone(&self.shared);
+            let name = format!("grob-net-{}", state.workers);
+            if thread::Builder::new().name(name).spawn(move || run_worker(&shared)).is_err() {
+                state.workers -= 1;
+            }
+        }
+        drop(state);
+        self.shared.job_queued.notify_one();
+    }
+}
+
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().closed = true;
+        self.shared.job_queued.notify_all();
+    }
+}
+
+fn run_worker(shared: &PoolShared) {
+    loop {
+        let job = {
+            let mut state = shared.state.lock().unwrap();
+            loop {
+                if let Some(job) = state.jobs.pop_front() {
+                    break job;
+                }
+                if state.closed {
+                    state.workers -= 1;
+                    return;
+                }
+                state.idle += 1;
+                state = shared.job_queued.wait(state).unwrap();
+                state.idle -= 1;
+            }
+        };
+        let _ = panic::catch_unwind(AssertUnwindSafe(job));
+    }
+}
+
+/// The result of a background fetch, once it arrives; None when the fetch
+/// failed
+///
+/// `wait` blocks for it; as a `Future` it resolves without blocking.
+pub struct FetchHandle {
+    completion: Arc<Completion>,
+}
+
+#[derive(Default)]
+struct Completion {
+    state: Mutex<CompletionState>,
+    finished: Condvar,
+}
+
+#[derive(Default)]
+struct CompletionState {
+    finished: bool,
+    resource: Option<FetchedResource>,
+    waker: Option<Waker>,
+}
+
+impl FetchHandle {
+    /// Whether the result has arrived, so `wait` will not block
+    pub fn is_finished(&self) -> bool {
+        self.completion.state.lock().unwrap().finished
+    }
+
+    /// Block until the result arrives
+    pub fn wait(self) -> Option<FetchedResource> {
+        let state = self.completion.state.lock().unwrap();
+        let mut state = self.completion.finished.wait_while(state, |state| !state.finished).unwrap();
+        state.resource.take()
+    }
+}
+
+impl Future for FetchHandle {
+    type Output = Option<FetchedResource>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.completion.state.lock().unwrap();
+        if state.finished {
+            return Poll::Ready(state.resource.take());
+        }
+        state.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+/// Who is told when a fetch finishes
+enum Waiter {
+    Handle(Arc<Completion>),
+    Callback(Box<dyn FnOnce(Option<FetchedResource>) + Send + 'static>),
+}
+
+/// The sending side of one `FetchHandle` or callback; dropped without
+/// completing, it completes with None
+pub struct Completer {
+    waiter: Option<Waiter>,
+}
+
+impl Completer {
+    /// A completer and the handle it completes
+    pub fn handle() -> (Completer, FetchHandle) {
+        let completion = Arc::new(Completion::default());
+        let completer = Completer { waiter: Some(Waiter::Handle(Arc::clone(&completion))) };
+        (completer, FetchHandle { completion })
+    }
+
+    /// A completer that calls `callback` with the result
+    pub fn callback(callback: impl FnOnce(Option<FetchedResource>) + Send + 'static) -> Completer {
+        Completer { waiter: Some(Waiter::Callback(Box::new(callback))) }
+    }
+
+    pub fn complete(mut self, resource: Option<FetchedResource>) {
+        self.finish(resource);
+    }
+
+    fn finish(&mut self, resource: Option<FetchedResource>) {
+        match self.waiter.take() {
+            Some(Waiter::Handle(completion)) => {
+                let waker = {
+                    let mut state = completion.state.lock().unwrap();
+                    state.finished = true;
+                    state.resource = resource;
+                    state.waker.take()
+                };
+                completion.finished.notify_all();
+                if let Some(waker) = waker {
+                    waker.wake();
+                }
+            }
+            Some(Waiter::Callback(callback)) => callback(resource),
+            None => {}
+        }
+    }
+}
+
+impl Drop for Completer {
+    fn drop(&mut self) {
+        self.finish(None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    fn resource(url: &str) -> FetchedResource {
+        FetchedResource {
+            url: url.to_string(),
+            status: 200,
+            data: Vec::new(),
+            content_type: "text/plain".to_string(),
+            headers: Vec::new(),
+            from_cache: false,
+        }
+    }
+
+    #[test]
+    fn test_pool_runs_at_most_size_jobs_at_once() {
+        let pool = WorkerPool::new(3);
+        let running = Arc::new(AtomicUsize::new(0));
+        let most = Arc::new(AtomicUsize::new(0));
+        let (done, finished) = mpsc::channel();
+        for _ in 0..9 {
+            let (running, most, done) = (Arc::clone(&running), Arc::clone(&most), done.clone());
+            pool.execute(move || {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                most.fetch_max(now, Ordering::SeqCst);
+                thread::sleep(Duration::from_millis(20));
+                running.fetch_sub(1, Ordering::SeqCst);
+                done.send(()).unwrap();
+            });
+        }
+        for _ in 0..9 {
+            finished.recv_timeout(Duration::from_secs(5)).unwrap();
+        }
+        assert_eq!(most.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_pool_survives_a_panicking_job() {
+        let pool = WorkerPool::new(1);
+        let (completer, handle) = Completer::handle();
+        pool.execute(move || {
+            let _completer = completer;
+            panic!("job failed");
+        });
+        assert!(handle.wait().is_none());
+        let (done, finished) = mpsc::channel();
+        pool.execute(move || done.send(42).unwrap());
+        assert_eq!(finished.recv_timeout(Duration::from_secs(5)), Ok(42));
+    }
+
+    #[test]
+    fn test_handle_as_future() {
+        let (completer, mut handle) = Completer::handle();
+        let mut cx = Context::from_waker(Waker::noop());
+        assert!(Pin::new(&mut handle).poll(&mut cx).is_pending());
+        assert!(!handle.is_finished());
+        completer.complete(Some(resource("data:,a")));
+        assert!(handle.is_finished());
+        match Pin::new(&mut handle).poll(&mut cx) {
+            Poll::Ready(Some(resource)) => assert_eq!(resource.url, "data:,a"),
+            _ => panic!("handle should be ready"),
+        }
+    }
+
+    #[test]
+    fn test_callback_completer() {
+        let (sender, receiver) = mpsc::channel();
+        Completer::callback(move |resource| sender.send(resource.map(|r| r.url)).unwrap()).complete(Some(resource("x")));
+        assert_eq!(receiver.recv().unwrap(), Some("x".to_string()));
+        let (sender, receiver) = mpsc::channel();
+        drop(Completer::callback(move |resource| sender.send(resource.is_none()).unwrap()));
+        assert!(receiver.recv().unwrap());
+    }
+}
@@ -0,0 +1,119 @@
+// engine/src/net/request.rs
+// Requests with a method, headers and a body, built up through
+// `NetworkManager::request`
+//
+// - They skip the asset cache both ways, and are sent once, without
+//   retries, since a POST repeated is a second POST
+// - A successful unsafe request (POST, PUT, ...) drops the cached copy of
+//   its URL, which it may have changed
+// - Redirects follow the Fetch Standard: 303, and 301/302 after a POST,
+//   turn into a GET without the body; 307 and 308 repeat the request
+// - A request marked as a navigation is a page the user goes to from the
+//   current document, which SameSite cookies treat differently from a
+//   subresource
+
+use super::pool::Completer;
+use super::{FetchedResource, NetworkManager};
+
+/// The methods a `RequestBuilder` can send
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    Get,
+    Head,
+    Post,
+    Put,
+    Patch,
+    Delete,
+}
+
+impl Method {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Method::Get => "GET",
+            Method::Head => "HEAD",
+            Method::Post => "POST",
+            Method::Put => "PUT",
+            Method::Patch => "PATCH",
+            Method::Delete => "DELETE",
+        }
+    }
+
+    /// A method name, in any case
+    pub fn parse(name: &str) -> Option<Method> {
+        [Method::Get, Method::Head, Method::Post, Method::Put, Method::Patch, Method::Delete]
+            .into_iter()
+            .find(|method| method.as_str().eq_ignore_ascii_case(name))
+    }
+
+    /// Whether the method only reads, so that it may be repeated freely
+    pub fn is_safe(self) -> bool {
+        matches!(self, Method::Get | Method::Head)
+    }
+}
+
+/// A request as the loader sends it
+pub(super) struct Request {
+    pub(super) method: Method,
+    pub(super) url: String,
+    pub(super) headers: Vec<(String, String)>,
+    pub(super) body: Option<Vec<u8>>,
+    pub(super) navigation: bool,
+}
+
+/// A request being put together; `send` sends it
+pub struct RequestBuilder<'a> {
+    network: &'a NetworkManager,
+    request: Request,
+}
+
+impl<'a> RequestBuilder<'a> {
+    pub(super) fn new(network: &'a NetworkManager, method: Method, url: &str) -> Self {
+        RequestBuilder {
+            network,
+            request: Request { method, url: url.to_string(), headers: Vec::new(), body: None, navigation: false },
+        }
+    }
+
+    /// Add a header; cookies are added from the cookie jar
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.request.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.request.body = Some(body.into());
+        self
+    }
+
+    /// Send it as a navigation away from the current document, as
+    /// following a link or submitting a form is
+    pub fn navigation(mut self) -> Self {
+        self.request.navigation = true;
+        self
+    }
+
+    /// Send the request and wait for the final response, whatever its
+    /// status; None when no response arrives
+    pub fn send(self) -> Option<FetchedResource> {
+        self.network.send_request(self.request)
+    }
+
+    /// Send the request on a worker thread and pass the final response to
+    /// `callback` there
+    pub fn send_with(self, callback: impl FnOnce(Option<FetchedResource>) + Send + 'static) {
+        self.network.send_request_in_background(self.request, Completer::callback(callback));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_method_names() {
+        assert_eq!(Method::parse("post"), Some(Method::Post));
+        assert_eq!(Method::parse("DELETE").map(Method::as_str), Some("DELETE"));
+        assert_eq!(Method::parse("dialog"), None);
+        assert!(Method::Head.is_safe() && !Method::Post.is_safe());
+    }
+}
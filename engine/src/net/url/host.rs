@@ -0,0 +1,306 @@
+// engine/src/net/url/host.rs
+// Hosts: domains, IPv4 and IPv6 addresses, and the opaque hosts of
+// non-special schemes
+//
+// - Domains are percent-decoded and run through IDNA (UTS #46) to their
+//   ASCII form, so `EXAMPLE.com` and `ex%61mple.com` name the same host
+// - A domain whose last label is a number is an IPv4 address, in any of the
+//   legacy forms `inet_aton` accepts: `0x7f.1`, `017700000001`, `2130706433`
+// - IPv6 addresses serialize in their shortest form, compressing the first
+//   longest run of zero pieces
+
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use idna::AsciiDenyList;
+
+use super::percent;
+use super::UrlError;
+
+/// Code points no host may contain
+const FORBIDDEN_HOST_CODE_POINTS: &[char] =
+    &['\0', '\t', '\n', '\r', ' ', '#', '/', ':', '<', '>', '?', '@', '[', '\\', ']', '^', '|'];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Host {
+    /// An ASCII domain, lowercased and with labels in Punycode
+    Domain(String),
+    Ipv4(Ipv4Addr),
+    Ipv6(Ipv6Addr),
+    /// The host of a non-special URL, percent-encoded but otherwise as written
+    Opaque(String),
+    /// The empty host of `file:///` and `sc://`
+    Empty,
+}
+
+impl Host {
+    /// Parse the host of a URL; `opaque` for URLs of non-special schemes
+    pub fn parse(input: &str, opaque: bool) -> Result<Host, UrlError> {
+        if let Some(address) = input.strip_prefix('[') {
+            let address = address.strip_suffix(']').ok_or(UrlError::InvalidIpv6)?;
+            return parse_ipv6(address).map(Host::Ipv6).ok_or(UrlError::InvalidIpv6);
+        }
+        if opaque {
+            if input.contains(FORBIDDEN_HOST_CODE_POINTS) {
+                return Err(UrlError::InvalidHost);
+            }
+            if input.is_empty() {
+                return Ok(Host::Empty);
+            }
+            let mut host = String::new();
+            input.chars().for_each(|c| percent::encode_char(c, percent::C0_CONTROL, &mut host));
+            return Ok(Host::Opaque(host));
+        }
+
+        let domain = percent::decode(input);
+        let domain = String::from_utf8_lossy(&domain);
+        let ascii = idna::domain_to_ascii_cow(domain.as_bytes(), AsciiDenyList::URL)
+            .map_err(|_| UrlError::InvalidDomain)?;
+        if ascii.is_empty() {
+            return Err(UrlError::InvalidDomain);
+        }
+        if ends_in_number(&ascii) {
+            return parse_ipv4(&ascii).map(Host::Ipv4).ok_or(UrlError::InvalidIpv4);
+        }
+        Ok(Host::Domain(ascii.into_owned()))
+    }
+}
+
+impl fmt::Display for Host {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Host::Domain(name) | Host::Opaque(name) => f.write_str(name),
+            Host::Ipv4(address) => write!(f, "{}", address),
+            Host::Ipv6(address) => write_ipv6(f, address),
+            Host::Empty => Ok(()),
+        }
+    }
+}
+
+/// Whether the last label of `domain` (ignoring one trailing dot) is a
+/// decimal, octal or hex number
+fn ends_in_number(domain: &str) -> bool {
+    let domain = match domain.strip_suffix('.') {
+        Some(rest) if !rest.is_empty() => rest,
+        _ => domain,
+    };
+    let last = domain.rsplit('.').next().unwrap_or(domain);
+    (!last.is_empty() && last.bytes().all(|b| b.is_ascii_digit())) || parse_ipv4_number(last).is_some()
+}
+
+/// One dotted part of an IPv4 address; values past `u32` saturate so they
+/// still compare as too large
+fn parse_ipv4_number(part: &str) -> Option<u64> {
+    if part.is_empty() {
+        return None;
+    }
+    let (digits, radix) = match part.get(..2) {
+        Some("0x" | "0X") => (&part[2..], 16),
+        _ if part.len() > 1 && part.starts_with('0') => (&part[1..], 8),
+        _ => (part, 10),
+    };
+    digits.chars().try_fold(0u64, |value, c| {
+        let digit = c.to_digit(radix)?;
+        Some(value.saturating_mul(radix as u64).saturating_add(digit as u64))
+    })
+}
+
+fn parse_ipv4(input: &str) -> Option<Ipv4Addr> {
+    let mut parts: Vec<&str> = input.split('.').collect();
+    if parts.len() > 1 && parts.last() == Some(&"") {
+        parts.pop();
+    }
+    if parts.len() > 4 {
+        return None;
+    }
+    let numbers = parts.iter().map(|part| parse_ipv4_number(part)).collect::<Option<Vec<u64>>>()?;
+    let (&last, leading) = numbers.split_last()?;
+    if leading.iter().any(|&n| n > 255) || last >= 256u64.pow(5 - numbers.len() as u32) {
+        return None;
+    }
+    let address = leading.iter().enumerate().fold(last, |address, (i, &n)| address + (n << (8 * (3 - i))));
+    Some(Ipv4Addr::from(address as u32))
+}
+
+fn parse_ipv6(input: &str) -> Option<Ipv6Addr> {
+    let input: Vec<char> = input.chars().collect();
+    let at = |i: usize| input.get(i).copied();
+    let mut address = [0u16; 8];
+    let mut piece_index = 0;
+    let mut compress = None;
+    let mut pointer = 0;
+
+    if at(0) == Some(':') {
+        if at(1) != Some(':') {
+            return None;
+        }
+        pointer = 2;
+        piece_index = 1;
+        compress = Some(1);
+    }
+    while let Some(c) = at(pointer) {
+        if piece_index == 8 {
+            return None;
+        }
+        if c == ':' {
+            if compress.is_some() {
+                return None;
+            }
+            pointer += 1;
+            piece_index += 1;
+            compress = Some(piece_index);
+            continue;
+        }
+        let mut value = 0u16;
+        let mut length = 0;
+        while let Some(digit) = at(pointer).and_then(|c| c.to_digit(16)).filter(|_| length < 4) {
+            value = value * 0x10 + digit as u16;
+            pointer += 1;
+            length += 1;
+        }
+        match at(pointer) {
+            Some('.') => {
+                // An embedded IPv4 address fills the last two pieces
+                if length == 0 || piece_index > 6 {
+                    return None;
+                }
+                pointer -= length;
+                let mut numbers_seen = 0;
+                while at(pointer).is_some() {
+                    if numbers_seen > 0 {
+                        if at(pointer) == Some('.') && numbers_seen < 4 {
+                            pointer += 1;
+                        } else {
+                            return None;
+                        }
+                    }
+                    let mut number: Option<u16> = None;
+                    while let Some(digit) = at(pointer).and_then(|c| c.to_digit(10)) {
+                        number = match number {
+                            None => Some(digit as u16),
+                            Some(0) => return None,
+                            Some(n) => Some(n * 10 + digit as u16),
+                        };
+                        if number > Some(255) {
+                            return None;
+                        }
+                        pointer += 1;
+                    }
+                    address[piece_index] = address[piece_index] * 0x100 + number?;
+                    numbers_seen += 1;
+                    if numbers_seen == 2 || numbers_seen == 4 {
+                        piece_index += 1;
+                    }
+                }
+                if numbers_seen != 4 {
+                    return None;
+                }
+                break;
+            }
+            Some(':') => {
+                pointer += 1;
+                at(pointer)?;
+            }
+            Some(_) => return None,
+            None => {}
+        }
+        address[piece_index] = value;
+        piece_index += 1;
+    }
+
+    match compress {
+        Some(compress) => {
+            // Move the pieces after `::` to the end
+            let mut swaps = piece_index - compress;
+            piece_index = 7;
+            while piece_index != 0 && swaps > 0 {
+                address.swap(piece_index, compress + swaps - 1);
+                piece_index -= 1;
+                swaps -= 1;
+            }
+        }
+        None if piece_index != 8 => return None,
+        None => {}
+    }
+    Some(Ipv6Addr::from(address))
+}
+
+/// `[1:0:0:2::3]`, compressing the first longest run of two or more zeros
+fn write_ipv6(f: &mut fmt::Formatter<'_>, address: &Ipv6Addr) -> fmt::Result {
+    let pieces = address.segments();
+    let mut compress: Option<(usize, usize)> = None;
+    let mut i = 0;
+    while i < 8 {
+        let run = pieces[i..].iter().take_while(|&&piece| piece == 0).count();
+        if run > 1 && compress.is_none_or(|(_, longest)| run > longest) {
+            compress = Some((i, run));
+        }
+        i += run.max(1);
+    }
+
+    f.write_str("[")?;
+    let mut ignore0 = false;
+    for (index, piece) in pieces.iter().enumerate() {
+        if ignore0 && *piece == 0 {
+            continue;
+        }
+        ignore0 = false;
+        if compress.map(|(start, _)| start) == Some(index) {
+            f.write_str(if index == 0 { "::" } else { ":" })?;
+            ignore0 = true;
+            continue;
+        }
+        write!(f, "{:x}", piece)?;
+        if index != 7 {
+            f.write_str(":")?;
+        }
+    }
+    f.write_str("]")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn host(input: &str) -> String {
+        Host::parse(input, false).unwrap().to_string()
+    }
+
+    #[test]
+    fn test_domains_go_through_idna() {
+        assert_eq!(host("EXAMPLE.com"), "example.com");
+        assert_eq!(host("ex%61mple.com"), "example.com");
+        assert_eq!(host("bücher.de"), "xn--bcher-kva.de");
+        assert_eq!(Host::parse("exa mple.com", false), Err(UrlError::InvalidDomain));
+        assert_eq!(Host::parse("%", false), Err(UrlError::InvalidDomain));
+    }
+
+    #[test]
+    fn test_ipv4_forms() {
+        assert_eq!(host("127.0.0.1"), "127.0.0.1");
+        assert_eq!(host("0x7f.1"), "127.0.0.1");
+        assert_eq!(host("017700000001"), "127.0.0.1");
+        assert_eq!(host("2130706433."), "127.0.0.1");
+        assert_eq!(Host::parse("256.0.0.1", false), Err(UrlError::InvalidIpv4));
+        assert_eq!(Host::parse("1.2.3.4.5", false), Err(UrlError::InvalidIpv4));
+        // Only a numeric last label makes an address
+        assert_eq!(host("1.2.example"), "1.2.example");
+    }
+
+    #[test]
+    fn test_ipv6_serializes_compressed() {
+        assert_eq!(host("[0:0:0:0:0:0:0:1]"), "[::1]");
+        assert_eq!(host("[1:0:0:2:0:0:0:3]"), "[1:0:0:2::3]");
+        assert_eq!(host("[::ffff:192.168.0.1]"), "[::ffff:c0a8:1]");
+        assert_eq!(host("[1:2:3:4:5:6:7:8]"), "[1:2:3:4:5:6:7:8]");
+        assert_eq!(Host::parse("[1:2::3::4]", false), Err(UrlError::InvalidIpv6));
+        assert_eq!(Host::parse("[::1", false), Err(UrlError::InvalidIpv6));
+    }
+
+    #[test]
+    fn test_opaque_hosts() {
+        assert_eq!(Host::parse("Ex%41mple é", true), Err(UrlError::InvalidHost));
+        assert_eq!(Host::parse("Ex%41mple.é", true), Ok(Host::Opaque("Ex%41mple.%C3%A9".to_string())));
+        assert_eq!(Host::parse("", true), Ok(Host::Empty));
+    }
+}
@@ -1,128 +1,18 @@
-// URL resolution utilities for resolving relative URLs against base URLs
+// URLs and the utilities that work on them
 //
 // This module handles:
+// - Parsing and serializing URLs per the WHATWG URL Standard (parser.rs,
+//   with hosts in host.rs and percent-encoding in percent.rs)
 // - Relative URL resolution using document URL + <base href>
 // - srcset attribute parsing and selection
-// - URL normalization
+// - data: URIs
 
-/// Represents a parsed URL with its components
-#[derive(Debug, Clone, PartialEq)]
-pub struct ParsedUrl {
-    pub scheme: String,
-    pub host: String,
-    pub port: Option<u16>,
-    pub path: String,
-    pub query: Option<String>,
-    pub fragment: Option<String>,
-}
+mod host;
+mod parser;
+mod percent;
 
-impl ParsedUrl {
-    /// Parse a URL string into its components
-    pub fn parse(url: &str) -> Option<Self> {
-        let url = url.trim();
-        
-        // Handle data: URLs specially
-        if url.starts_with("data:") {
-            return Some(ParsedUrl {
-                scheme: "data".to_string(),
-                host: String::new(),
-                port: None,
-                path: url[5..].to_string(),
-                query: None,
-                fragment: None,
-            });
-        }
-        
-        // Extract scheme
-        let (scheme, rest) = if let Some(pos) = url.find("://") {
-            (url[..pos].to_lowercase(), &url[pos + 3..])
-        } else {
-            return None; // No scheme found
-        };
-        
-        // Extract fragment
-        let (rest, fragment) = if let Some(pos) = rest.find('#') {
-            (&rest[..pos], Some(rest[pos + 1..].to_string()))
-        } else {
-            (rest, None)
-        };
-        
-        // Extract query
-        let (rest, query) = if let Some(pos) = rest.find('?') {
-            (&rest[..pos], Some(rest[pos + 1..].to_string()))
-        } else {
-            (rest, None)
-        };
-        
-        // Extract host and path
-        let (host_port, path) = if let Some(pos) = rest.find('/') {
-            (&rest[..pos], rest[pos..].to_string())
-        } else {
-            (rest, "/".to_string())
-        };
-        
-        // Extract port from host
-        let (host, port) = if let Some(pos) = host_port.rfind(':') {
-            let potential_port = &host_port[pos + 1..];
-            if potential_port.chars().all(|c| c.is_ascii_digit()) {
-                (
-                    host_port[..pos].to_lowercase(),
-                    potential_port.parse().ok(),
-                )
-            } else {
-                (host_port.to_lowercase(), None)
-            }
-        } else {
-            (host_port.to_lowercase(), None)
-        };
-        
-        Some(ParsedUrl {
-            scheme,
-            host,
-            port,
-            path,
-            query,
-            fragment,
-        })
-    }
-    
-    /// Convert back to a URL string
-    pub fn to_string(&self) -> String {
-        if self.scheme == "data" {
-            return format!("data:{}", self.path);
-        }
-        
-        let mut url = format!("{}://{}", self.scheme, self.host);
-        
-        if let Some(port) = self.port {
-            url.push(':');
-            url.push_str(&port.to_string());
-        }
-        
-        url.push_str(&self.path);
-        
-        if let Some(ref query) = self.query {
-            url.push('?');
-            url.push_str(query);
-        }
-        
-        if let Some(ref fragment) = self.fragment {
-            url.push('#');
-            url.push_str(fragment);
-        }
-        
-        url
-    }
-    
-    /// Get the directory path (path without the filename)
-    pub fn directory(&self) -> String {
-        if let Some(pos) = self.path.rfind('/') {
-            self.path[..=pos].to_string()
-        } else {
-            "/".to_string()
-        }
-    }
-}
+pub use host::Host;
+pub use parser::{Origin, Url, UrlError};
 
 /// Resolve a potentially relative URL against a base URL
 /// 
@@ -131,82 +21,16 @@ impl ParsedUrl {
 /// - resolve("https://example.com/a/b.html", "/c.png") -> "https://example.com/c.png"
 /// - resolve("https://example.com/a/", "../c.png") -> "https://example.com/c.png"
 /// - resolve("https://example.com/a/", "//cdn.example.com/c.png") -> "https://cdn.example.com/c.png"
+///
+/// The result is serialized, so it comes back normalized even when
+/// `relative_url` is already absolute. Input that is not a URL, or is
+/// relative to a base that is not one, is returned trimmed but otherwise
+/// unchanged.
 pub fn resolve_url(base_url: &str, relative_url: &str) -> String {
-    let relative = relative_url.trim();
-    
-    // Already absolute URL
-    if relative.contains("://") {
-        return relative.to_string();
-    }
-    
-    // Data URI - return as-is
-    if relative.starts_with("data:") {
-        return relative.to_string();
-    }
-    
-    let base = match ParsedUrl::parse(base_url) {
-        Some(b) => b,
-        None => return relative.to_string(),
-    };
-    
-    // Protocol-relative URL (//example.com/path)
-    if relative.starts_with("//") {
-        return format!("{}:{}", base.scheme, relative);
-    }
-    
-    // Absolute path (/path/to/resource)
-    if relative.starts_with('/') {
-        let mut result = base.clone();
-        result.path = relative.to_string();
-        result.query = None;
-        result.fragment = None;
-        return result.to_string();
-    }
-    
-    // Relative path
-    let base_dir = base.directory();
-    let combined = format!("{}{}", base_dir, relative);
-    
-    // Normalize the path (resolve . and ..)
-    let normalized_path = normalize_path(&combined);
-    
-    let mut result = base.clone();
-    result.path = normalized_path;
-    result.query = None;
-    result.fragment = None;
-    result.to_string()
-}
-
-/// Normalize a path by resolving . and .. segments
-pub fn normalize_path(path: &str) -> String {
-    let mut segments: Vec<&str> = Vec::new();
-    
-    for segment in path.split('/') {
-        match segment {
-            "" | "." => {
-                // Skip empty segments and current directory references
-                if segments.is_empty() {
-                    segments.push(""); // Keep leading slash
-                }
-            }
-            ".." => {
-                // Go up one directory, but not past root
-                if segments.len() > 1 {
-                    segments.pop();
-                }
-            }
-            _ => {
-                segments.push(segment);
-            }
-        }
-    }
-    
-    if segments.is_empty() {
-        "/".to_string()
-    } else if segments.len() == 1 && segments[0].is_empty() {
-        "/".to_string()
-    } else {
-        segments.join("/")
+    let base = Url::parse(base_url).ok();
+    match Url::parse_with_base(relative_url, base.as_ref()) {
+        Ok(url) => url.to_string(),
+        Err(_) => relative_url.trim().to_string(),
     }
 }
 
@@ -455,17 +279,31 @@ mod tests {
     
     #[test]
     fn test_parse_url() {
-        let url = ParsedUrl::parse("https://example.com:8080/path/to/file.html?query=1#fragment");
-        assert!(url.is_some());
+        let url = Url::parse("https://example.com:8080/path/to/file.html?query=1#fragment");
+        assert!(url.is_ok());
         let url = url.unwrap();
-        assert_eq!(url.scheme, "https");
-        assert_eq!(url.host, "example.com");
-        assert_eq!(url.port, Some(8080));
-        assert_eq!(url.path, "/path/to/file.html");
-        assert_eq!(url.query, Some("query=1".to_string()));
-        assert_eq!(url.fragment, Some("fragment".to_string()));
+        assert_eq!(url.scheme(), "https");
+        assert_eq!(url.hostname(), "example.com");
+        assert_eq!(url.port(), Some(8080));
+        assert_eq!(url.pathname(), "/path/to/file.html");
+        assert_eq!(url.query(), Some("query=1"));
+        assert_eq!(url.fragment(), Some("fragment"));
+
+        // The wrappers serialize what they parse, and pass through what they can't
+        assert_eq!(resolve_url("HTTPS://Example.COM:443/a/./b/../c", ""), "https://example.com/a/c");
+        assert_eq!(resolve_url("not a url", "c.png"), "c.png");
+        assert_eq!(resolve_url("https://example.com/", " http://[::1 "), "http://[::1");
+        assert_eq!(
+            resolve_url_with_base("https://example.com/a/page.html", Some(""), "b.png"),
+            "https://example.com/a/b.png"
+        );
+        assert_eq!(
+            resolve_url_with_base("https://example.com/page.html", Some("https://cdn.example.com/x/"), "../y.png"),
+            "https://cdn.example.com/y.png"
+        );
+        assert_eq!(resolve_url_with_base("https://example.com/", None, "?q=1"), "https://example.com/?q=1");
     }
-    
+
     #[test]
     fn test_resolve_relative_url() {
         assert_eq!(
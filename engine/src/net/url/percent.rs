@@ -0,0 +1,104 @@
+// engine/src/net/url/percent.rs
+// Percent-encoding and the encode sets of the URL Standard
+//
+// Every set escapes the C0 controls, DELETE and all non-ASCII code points
+// (byte by byte, after UTF-8 encoding); the larger sets add ASCII
+// punctuation that would otherwise be read as a delimiter in their component
+
+/// The ASCII bytes a component escapes, as a bit mask
+#[derive(Debug, Clone, Copy)]
+pub struct EncodeSet(u128);
+
+impl EncodeSet {
+    /// This set plus the characters of `chars`
+    const fn with(self, chars: &str) -> EncodeSet {
+        let bytes = chars.as_bytes();
+        let mut mask = self.0;
+        let mut i = 0;
+        while i < bytes.len() {
+            mask |= 1 << bytes[i];
+            i += 1;
+        }
+        EncodeSet(mask)
+    }
+
+    fn contains(self, byte: u8) -> bool {
+        byte >= 0x80 || self.0 & (1 << byte) != 0
+    }
+}
+
+pub const C0_CONTROL: EncodeSet = EncodeSet(0xFFFF_FFFF | 1 << 0x7F);
+pub const FRAGMENT: EncodeSet = C0_CONTROL.with(" \"<>`");
+pub const QUERY: EncodeSet = C0_CONTROL.with(" \"#<>");
+/// The query set of special schemes, which also escape `'`
+pub const SPECIAL_QUERY: EncodeSet = QUERY.with("'");
+pub const PATH: EncodeSet = QUERY.with("?`{}");
+pub const USERINFO: EncodeSet = PATH.with("/:;=@[\\]^|");
+
+/// Append `c` to `out`, escaping its UTF-8 bytes that are in `set`
+pub fn encode_char(c: char, set: EncodeSet, out: &mut String) {
+    if c.is_ascii() && !set.contains(c as u8) {
+        out.push(c);
+        return;
+    }
+    let mut bytes = [0; 4];
+    for &byte in c.encode_utf8(&mut bytes).as_bytes() {
+        if set.contains(byte) {
+            out.push_str(&format!("%{:02X}", byte));
+        } else {
+            out.push(byte as char);
+        }
+    }
+}
+
+/// `input` with every `%` followed by two hex digits replaced by that byte;
+/// other `%` signs are kept
+pub fn decode(input: &str) -> Vec<u8> {
+    let bytes = input.as_bytes();
+    let mut output = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes.get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], escaped) {
+            (b'%', Some(byte)) => {
+                output.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                output.push(byte);
+                i += 1;
+            }
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(input: &str, set: EncodeSet) -> String {
+        let mut out = String::new();
+        input.chars().for_each(|c| encode_char(c, set, &mut out));
+        out
+    }
+
+    #[test]
+    fn test_sets_nest() {
+        assert_eq!(encode("a b\"#<>'?`{}\u{7F}é", C0_CONTROL), "a b\"#<>'?`{}%7F%C3%A9");
+        assert_eq!(encode("a b\"#<>'?`{}", QUERY), "a%20b%22%23%3C%3E'?`{}");
+        assert_eq!(encode("'", SPECIAL_QUERY), "%27");
+        assert_eq!(encode("a b?`{}^%", PATH), "a%20b%3F%60%7B%7D^%");
+        assert_eq!(encode("user:p@ss/;=[]^|", USERINFO), "user%3Ap%40ss%2F%3B%3D%5B%5D%5E%7C");
+        assert_eq!(encode("`#", FRAGMENT), "%60#");
+    }
+
+    #[test]
+    fn test_decode_keeps_stray_percent_signs() {
+        assert_eq!(decode("%41%2e%zz%4"), b"A.%zz%4");
+        assert_eq!(decode("%C3%A9"), "é".as_bytes());
+    }
+}
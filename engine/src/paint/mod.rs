@@ -0,0 +1,330 @@
+//! Painting: turns a laid out box tree into a display list, a flat list of
+//! backend-independent drawing commands in CSS px. The browser window,
+//! headless rendering and tests all draw from the same list.
+//!
+//! Items come in painting order (see `layout::paint_order`) with opacity
+//! already folded into their colors, so a backend only has to draw them one
+//! after another. `raster` draws a list on the CPU, into the browser's frame
+//! or into an image for headless rendering.
+
+pub mod raster;
+
+use crate::dom::{Dom, NodeType};
+use crate::layout::{paint_order, LayoutBox, PaintStep};
+use crate::net::{parse_srcset, select_srcset_image};
+use crate::style::{BorderSide, BoxShadow, Corners, Edges, Rgba, TextDecoration};
+
+pub use crate::layout::Dimensions as Rect;
+pub use raster::{render, Canvas};
+
+/// Elements whose text is never painted
+const HIDDEN_TEXT_PARENTS: &[&str] = &["style", "script", "head", "title", "meta", "link"];
+
+/// Font properties a text run is drawn with
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontSpec {
+    pub family: String,
+    /// In CSS px
+    pub size: f32,
+    /// 1 to 1000, as `font-weight`
+    pub weight: u16,
+    pub italic: bool,
+}
+
+/// How an image fills its rectangle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFit {
+    /// Scaled to the rectangle, as `<img>`
+    Stretch,
+    /// Repeated from the top-left corner at its natural size, as `background-image`
+    Tile,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DisplayItem {
+    /// Fill a rectangle, with rounded corners unless `radii` are zero
+    Rect { rect: Rect, color: Rgba, radii: Corners },
+    /// Stroke the inside of a border box, each side with its own width, style
+    /// and color
+    Border { rect: Rect, sides: Edges<BorderSide>, radii: Corners },
+    /// A blurred shadow of a box. `rect` and `radii` are the border box for
+    /// outer shadows, which only paint outside it, and the padding box for
+    /// inset ones, which only paint inside it.
+    BoxShadow { rect: Rect, radii: Corners, shadow: BoxShadow },
+    /// A run of text; `rect` is its box, whose top is the top of the line
+    Text {
+        rect: Rect,
+        text: String,
+        font: FontSpec,
+        color: Rgba,
+        decoration: TextDecoration,
+    },
+    /// An image loaded from `url`. Backends that cannot load it draw a
+    /// placeholder in its place.
+    Image {
+        rect: Rect,
+        url: String,
+        alt: String,
+        fit: ImageFit,
+        opacity: f32,
+    },
+    /// Clip the items up to the matching `PopClip` to a rectangle
+    PushClip(Rect),
+    PopClip,
+}
+
+/// Drawing commands for a whole page, back to front
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DisplayList {
+    pub items: Vec<DisplayItem>,
+}
+
+impl DisplayList {
+    /// Build the display list of a laid out tree. `device_pixel_ratio` picks
+    /// between `srcset` candidates; coordinates stay in CSS px.
+    pub fn build(root: &LayoutBox, dom: &Dom, device_pixel_ratio: f32) -> Self {
+        let mut list = DisplayList::default();
+        let mut clip = None;
+        for step in paint_order(root) {
+            // Clips are flattened per box, so they never nest
+            if step.clip != clip {
+                if clip.is_some() {
+                    list.items.push(DisplayItem::PopClip);
+                }
+                if let Some(rect) = step.clip {
+                    list.items.push(DisplayItem::PushClip(rect));
+                }
+                clip = step.clip;
+            }
+            list.paint_box(&step, dom, device_pixel_ratio);
+        }
+        if clip.is_some() {
+            list.items.push(DisplayItem::PopClip);
+        }
+        list
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, DisplayItem> {
+        self.items.iter()
+    }
+
+    /// Items for one box, without its children
+    fn paint_box(&mut self, step: &PaintStep, dom: &Dom, device_pixel_ratio: f32) {
+        let layout_box = step.layout_box;
+        let style = &layout_box.style;
+        let rect = layout_box.dimensions;
+
+        if let Some(text) = &layout_box.text_content {
+            let hidden = dom.nodes[layout_box.node_id]
+                .parent
+                .and_then(|parent| dom.tag_name(parent))
+                .is_some_and(|tag| HIDDEN_TEXT_PARENTS.contains(&tag));
+            let color = style.color.with_opacity(step.opacity);
+            if !hidden && !color.is_transparent() {
+                self.items.push(DisplayItem::Text {
+                    rect,
+                    text: text.clone(),
+                    font: FontSpec {
+                        family: style.font_family.clone(),
+                        size: style.font_size,
+                        weight: style.font_weight,
+                        italic: style.is_italic(),
+                    },
+                    color,
+                    decoration: style.text_decoration,
+                });
+            }
+            return;
+        }
+
+        // Text boxes share their parent element's style, and anonymous line
+        // boxes have the initial style, so only element boxes get here with
+        // a background
+        if !matches!(dom.nodes[layout_box.node_id].node_type, NodeType::Element(_)) {
+            return;
+        }
+
+        // Outer shadows, background, inset shadows, then the border. The
+        // first shadow in the list paints on top.
+        let radii = style.border_radius.resolve(rect.width, rect.height);
+        let borders = style.border_widths();
+        let (padding_box, padding_radii) = (rect.shrink(&borders), radii.shrink(&borders));
+        for shadow in style.box_shadow.iter().rev().filter(|shadow| !shadow.inset) {
+            self.push_shadow(rect, radii, shadow, step.opacity);
+        }
+        if let Some(color) = style.background_color.map(|c| c.with_opacity(step.opacity)) {
+            if !color.is_transparent() {
+                self.items.push(DisplayItem::Rect { rect, color, radii });
+            }
+        }
+        if let Some(url) = style.get("background-image").and_then(css_url) {
+            self.items.push(DisplayItem::Image {
+                rect,
+                url,
+                alt: String::new(),
+                fit: ImageFit::Tile,
+                opacity: step.opacity,
+            });
+        }
+        for shadow in style.box_shadow.iter().rev().filter(|shadow| shadow.inset) {
+            self.push_shadow(padding_box, padding_radii, shadow, step.opacity);
+        }
+        let sides = style.border;
+        let visible = |side: &BorderSide| side.width > 0.0 && !side.color.is_transparent();
+        if [sides.top, sides.right, sides.bottom, sides.left].iter().any(visible) {
+            let fade = |side: BorderSide| BorderSide { color: side.color.with_opacity(step.opacity), ..side };
+            let sides = Edges {
+                top: fade(sides.top),
+                right: fade(sides.right),
+                bottom: fade(sides.bottom),
+                left: fade(sides.left),
+            };
+            self.items.push(DisplayItem::Border { rect, sides, radii });
+        }
+
+        if dom.tag_name(layout_box.node_id) == Some("img") {
+            // Replaced content fills the content box. The containing block is
+            // gone by now, so percentage padding resolves against the box.
+            let rect = rect.shrink(&style.padding_border(rect.width));
+            let src = dom.get_attribute(layout_box.node_id, "src");
+            let url = match dom.get_attribute(layout_box.node_id, "srcset") {
+                Some(srcset) => select_srcset_image(
+                    &parse_srcset(srcset),
+                    src,
+                    rect.width as u32,
+                    device_pixel_ratio,
+                ),
+                None => src.map(str::to_string),
+            };
+            if let Some(url) = url {
+                self.items.push(DisplayItem::Image {
+                    rect,
+                    url,
+                    alt: dom.get_attribute(layout_box.node_id, "alt").unwrap_or("Image").to_string(),
+                    fit: ImageFit::Stretch,
+                    opacity: step.opacity,
+                });
+            }
+        }
+    }
+
+    fn push_shadow(&mut self, rect: Rect, radii: Corners, shadow: &BoxShadow, opacity: f32) {
+        let color = shadow.color.with_opacity(opacity);
+        if !color.is_transparent() {
+            self.items.push(DisplayItem::BoxShadow { rect, radii, shadow: BoxShadow { color, ..*shadow } });
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a DisplayList {
+    type Item = &'a DisplayItem;
+    type IntoIter = std::slice::Iter<'a, DisplayItem>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.iter()
+    }
+}
+
+/// The URL in a CSS `url(...)` value, skipping inline `data:` URLs
+fn css_url(value: &str) -> Option<String> {
+    let start = value.to_ascii_lowercase().find("url(")?;
+    let rest = &value[start + 4..];
+    let url = rest[..rest.find(')')?].trim().trim_matches(|c| c == '"' || c == '\'');
+    if url.is_empty() || url.starts_with("data:") {
+        return None;
+    }
+    Some(url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::LayoutEngine;
+    use crate::parser::html::tree_builder::HtmlParser;
+    use crate::style::Stylesheet;
+
+    fn display_list(html: &str) -> DisplayList {
+        let dom = HtmlParser::new(html).parse();
+        let root = LayoutEngine::new().layout(&dom, &Stylesheet::new());
+        DisplayList::build(&root, &dom, 1.0)
+    }
+
+    #[test]
+    fn test_background_then_text() {
+        let list = display_list("<div style=\"background:#ff0000; opacity:0.5\">Hi</div>");
+        let mut items = list.iter();
+        match items.next() {
+            Some(DisplayItem::Rect { color, .. }) => assert_eq!(*color, Rgba::new(255, 0, 0, 128)),
+            other => panic!("expected the background first, got {:?}", other),
+        }
+        match items.next() {
+            Some(DisplayItem::Text { text, color, .. }) => {
+                assert_eq!(text, "Hi");
+                assert_eq!(color.a, 128);
+            }
+            other => panic!("expected the text next, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_overflow_pushes_clip() {
+        let list = display_list(
+            "<div style=\"overflow:hidden; width:50px; height:10px\">\
+             <div style=\"background:blue; height:40px\"></div></div>\
+             <div style=\"background:green; height:5px\"></div>",
+        );
+        let kinds: Vec<&str> = list
+            .iter()
+            .map(|item| match item {
+                DisplayItem::Rect { .. } => "rect",
+                DisplayItem::PushClip(_) => "push",
+                DisplayItem::PopClip => "pop",
+                _ => "other",
+            })
+            .collect();
+        assert_eq!(kinds, ["push", "rect", "pop", "rect"]);
+        assert!(matches!(list.items[0], DisplayItem::PushClip(Rect { width: 50.0, height: 10.0, .. })));
+    }
+
+    #[test]
+    fn test_images_and_backgrounds() {
+        let list = display_list(
+            "<img src=\"a.png\" alt=\"A\">\
+             <div style=\"background-image: url('Tile.PNG'); height:10px\"></div>",
+        );
+        let images: Vec<(&str, ImageFit)> = list
+            .iter()
+            .filter_map(|item| match item {
+                DisplayItem::Image { url, fit, .. } => Some((url.as_str(), *fit)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(images, [("a.png", ImageFit::Stretch), ("Tile.PNG", ImageFit::Tile)]);
+    }
+
+    #[test]
+    fn test_hidden_text_is_skipped() {
+        let list = display_list("<div style=\"color:transparent\">gone</div>");
+        assert!(list.items.is_empty());
+    }
+
+    #[test]
+    fn test_shadows_background_and_border_order() {
+        let list = display_list(
+            "<div style=\"background:white; border:1px solid red; border-radius:4px; \
+             box-shadow: inset 1px 1px red, 2px 2px blue, 3px 3px green\">x</div>",
+        );
+        let kinds: Vec<String> = list
+            .iter()
+            .map(|item| match item {
+                DisplayItem::BoxShadow { shadow, .. } if shadow.inset => "inset".to_string(),
+                DisplayItem::BoxShadow { shadow, .. } => format!("shadow {}", shadow.offset_x),
+                DisplayItem::Rect { radii, .. } => format!("rect {}", radii.top_left.0),
+                DisplayItem::Border { sides, .. } => format!("border {}", sides.top.width),
+                DisplayItem::Text { .. } => "text".to_string(),
+                _ => "other".to_string(),
+            })
+            .collect();
+        assert_eq!(kinds, ["shadow 3", "shadow 2", "rect 4", "inset", "border 1", "text"]);
+    }
+}
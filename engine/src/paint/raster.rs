@@ -0,0 +1,662 @@
+// engine/src/paint/raster.rs
+// CPU rasterizer for display lists
+//
+// - Draws into any RGBA8 buffer: the browser's window frame or an
+//   `image::RgbaImage` for headless rendering
+// - Display list coordinates are CSS px and are scaled by the device pixel
+//   ratio; everything is composited source-over onto the buffer
+// - Text is drawn from the `FontManager`'s shaped glyph runs, the same ones
+//   layout measured, and rasterized with rusttype
+// - Rounded corners are elliptical arcs, anti-aliased by supersampling the
+//   pixels they cross; borders join at the diagonals between their sides
+// - Shadow blur is a Gaussian (standard deviation half the blur radius),
+//   approximated by three box blurs
+
+use ::image::RgbaImage;
+use rusttype::{point, GlyphId, Scale};
+
+use super::{DisplayItem, DisplayList, FontSpec, ImageFit, Rect};
+use crate::font::FontManager;
+use crate::net::NetworkManager;
+use crate::style::{BorderSide, BorderStyle, BoxShadow, Corners, Edges, Rgba, TextDecoration, Viewport};
+
+/// Physical pixel bounds `(x0, y0, x1, y1)`, end exclusive
+type Bounds = (usize, usize, usize, usize);
+
+/// Samples per axis when a pixel straddles a curved edge
+const SUPERSAMPLE: usize = 4;
+
+/// A rectangle with elliptical corners, in physical pixels
+#[derive(Debug, Clone, Copy)]
+struct RoundedRect {
+    left: f32,
+    top: f32,
+    right: f32,
+    bottom: f32,
+    radii: Corners,
+}
+
+impl RoundedRect {
+    fn new(rect: &Rect, radii: &Corners, device_pixel_ratio: f32) -> Self {
+        let scale = |(x, y): (f32, f32)| (x * device_pixel_ratio, y * device_pixel_ratio);
+        RoundedRect {
+            left: rect.x * device_pixel_ratio,
+            top: rect.y * device_pixel_ratio,
+            right: (rect.x + rect.width) * device_pixel_ratio,
+            bottom: (rect.y + rect.height) * device_pixel_ratio,
+            radii: Corners {
+                top_left: scale(radii.top_left),
+                top_right: scale(radii.top_right),
+                bottom_right: scale(radii.bottom_right),
+                bottom_left: scale(radii.bottom_left),
+            },
+        }
+    }
+
+    fn contains(&self, x: f32, y: f32) -> bool {
+        if x < self.left || x >= self.right || y < self.top || y >= self.bottom {
+            return false;
+        }
+        let Corners { top_left, top_right, bottom_right, bottom_left } = self.radii;
+        // The corner whose ellipse decides, as (center, radii)
+        let corner = if x < self.left + top_left.0 && y < self.top + top_left.1 {
+            ((self.left + top_left.0, self.top + top_left.1), top_left)
+        } else if x > self.right - top_right.0 && y < self.top + top_right.1 {
+            ((self.right - top_right.0, self.top + top_right.1), top_right)
+        } else if x > self.right - bottom_right.0 && y > self.bottom - bottom_right.1 {
+            ((self.right - bottom_right.0, self.bottom - bottom_right.1), bottom_right)
+        } else if x < self.left + bottom_left.0 && y > self.bottom - bottom_left.1 {
+            ((self.left + bottom_left.0, self.bottom - bottom_left.1), bottom_left)
+        } else {
+            return true;
+        };
+        let ((cx, cy), (rx, ry)) = corner;
+        let (dx, dy) = ((x - cx) / rx, (y - cy) / ry);
+        dx * dx + dy * dy <= 1.0
+    }
+
+    /// How much of the pixel at `(x, y)` is inside, from 0 to 1
+    fn coverage(&self, x: i32, y: i32) -> f32 {
+        let (x, y) = (x as f32, y as f32);
+        if x + 1.0 <= self.left || x >= self.right || y + 1.0 <= self.top || y >= self.bottom {
+            return 0.0;
+        }
+        // Pixels wholly inside the straight edges and clear of every corner
+        // need no sampling
+        let Corners { top_left, top_right, bottom_right, bottom_left } = self.radii;
+        let inside = x >= self.left && x + 1.0 <= self.right && y >= self.top && y + 1.0 <= self.bottom;
+        let clear_left = x >= self.left + top_left.0.max(bottom_left.0);
+        let clear_right = x + 1.0 <= self.right - top_right.0.max(bottom_right.0);
+        let clear_top = y >= self.top + top_left.1.max(top_right.1);
+        let clear_bottom = y + 1.0 <= self.bottom - bottom_left.1.max(bottom_right.1);
+        if inside && ((clear_left && clear_right) || (clear_top && clear_bottom)) {
+            return 1.0;
+        }
+        let step = 1.0 / SUPERSAMPLE as f32;
+        let mut hits = 0;
+        for sy in 0..SUPERSAMPLE {
+            for sx in 0..SUPERSAMPLE {
+                if self.contains(x + (sx as f32 + 0.5) * step, y + (sy as f32 + 0.5) * step) {
+                    hits += 1;
+                }
+            }
+        }
+        hits as f32 / (SUPERSAMPLE * SUPERSAMPLE) as f32
+    }
+
+    /// Pixel bounds `(x0, y0, x1, y1)` grown by `margin`, end exclusive
+    fn pixel_bounds(&self, margin: i32) -> (i32, i32, i32, i32) {
+        (
+            self.left.floor() as i32 - margin,
+            self.top.floor() as i32 - margin,
+            self.right.ceil() as i32 + margin,
+            self.bottom.ceil() as i32 + margin,
+        )
+    }
+}
+
+/// An RGBA8 pixel buffer that display lists are drawn into
+pub struct Canvas<'a> {
+    frame: &'a mut [u8],
+    width: usize,
+    height: usize,
+    device_pixel_ratio: f32,
+    /// Drawing is limited to these bounds
+    clip: Bounds,
+    saved_clips: Vec<Bounds>,
+    /// Draw only images that have already loaded rather than fetch the rest
+    skip_pending_images: bool,
+}
+
+impl<'a> Canvas<'a> {
+    /// Wrap a `width` x `height` RGBA8 buffer in physical pixels
+    pub fn new(frame: &'a mut [u8], width: usize, height: usize, device_pixel_ratio: f32) -> Self {
+        Canvas {
+            frame,
+            width,
+            height,
+            device_pixel_ratio,
+            clip: (0, 0, width, height),
+            saved_clips: Vec::new(),
+            skip_pending_images: false,
+        }
+    }
+
+    /// Don't wait for images: draw those that have loaded, start loading
+    /// the rest in the background, and leave them out (or draw `<img>`
+    /// placeholders) until a later draw
+    pub fn skip_pending_images(mut self) -> Self {
+        self.skip_pending_images = true;
+        self
+    }
+
+    /// Draw every item of `list` in order. Images are fetched through
+    /// `network`, waiting for them unless `skip_pending_images` was set;
+    /// without one, `<img>` items draw as placeholders.
+    pub fn draw(&mut self, list: &DisplayList, font_manager: &mut FontManager, network: Option<&NetworkManager>) {
+        for item in list {
+            match item {
+                DisplayItem::Rect { rect, color, radii } if radii.is_zero() => self.fill(rect, *color),
+                DisplayItem::Rect { rect, color, radii } => {
+                    self.fill_rounded(&RoundedRect::new(rect, radii, self.device_pixel_ratio), *color);
+                }
+                DisplayItem::Border { rect, sides, radii } => self.draw_border(rect, sides, radii),
+                DisplayItem::BoxShadow { rect, radii, shadow } => self.draw_shadow(rect, radii, shadow),
+                DisplayItem::Text { rect, text, font, color, decoration } => {
+                    self.draw_text(rect, text, font, *color, decoration, font_manager);
+                }
+                DisplayItem::Image { rect, url, fit, opacity, .. } => {
+                    let image = network.and_then(|network| {
+                        if self.skip_pending_images { network.loaded_image(url) } else { network.fetch_image(url) }
+                    });
+                    match image {
+                        Some(img) => self.draw_image(rect, &img, *fit, *opacity),
+                        // Only `<img>` gets a placeholder; a missing background is just not drawn
+                        None if *fit == ImageFit::Stretch => self.draw_image_placeholder(rect, *opacity),
+                        None => {}
+                    }
+                }
+                DisplayItem::PushClip(rect) => {
+                    let clip = self.bounds(rect);
+                    self.saved_clips.push(self.clip);
+                    self.clip = clip;
+                }
+                DisplayItem::PopClip => {
+                    self.clip = self.saved_clips.pop().unwrap_or((0, 0, self.width, self.height));
+                }
+            }
+        }
+    }
+
+    /// Physical pixel bounds of a rectangle in CSS px, within the clip
+    fn bounds(&self, rect: &Rect) -> Bounds {
+        let to_px = |v: f32| (v * self.device_pixel_ratio).max(0.0) as usize;
+        let (cx0, cy0, cx1, cy1) = self.clip;
+        (
+            to_px(rect.x).max(cx0),
+            to_px(rect.y).max(cy0),
+            to_px(rect.x + rect.width).min(cx1),
+            to_px(rect.y + rect.height).min(cy1),
+        )
+    }
+
+    /// Blend `color` (with its alpha scaled by `coverage`) into the pixel at
+    /// `(x, y)` if it is inside the clip
+    fn blend(&mut self, x: i32, y: i32, color: Rgba, coverage: f32) {
+        let (cx0, cy0, cx1, cy1) = self.clip;
+        if x < cx0 as i32 || y < cy0 as i32 || x >= cx1 as i32 || y >= cy1 as i32 {
+            return;
+        }
+        let idx = (y as usize * self.width + x as usize) * 4;
+        if idx + 3 >= self.frame.len() {
+            return;
+        }
+        let (r, g, b) = color.blend_over((self.frame[idx], self.frame[idx + 1], self.frame[idx + 2]), coverage);
+        self.frame[idx] = r;
+        self.frame[idx + 1] = g;
+        self.frame[idx + 2] = b;
+        self.frame[idx + 3] = 255;
+    }
+
+    fn fill(&mut self, rect: &Rect, color: Rgba) {
+        let (x0, y0, x1, y1) = self.bounds(rect);
+        for py in y0..y1 {
+            for px in x0..x1 {
+                self.blend(px as i32, py as i32, color, 1.0);
+            }
+        }
+    }
+
+    fn fill_rounded(&mut self, shape: &RoundedRect, color: Rgba) {
+        let (x0, y0, x1, y1) = self.clipped(shape.pixel_bounds(0));
+        for py in y0..y1 {
+            for px in x0..x1 {
+                let coverage = shape.coverage(px, py);
+                if coverage > 0.0 {
+                    self.blend(px, py, color, coverage);
+                }
+            }
+        }
+    }
+
+    /// Pixel bounds limited to the clip
+    fn clipped(&self, (x0, y0, x1, y1): (i32, i32, i32, i32)) -> (i32, i32, i32, i32) {
+        let (cx0, cy0, cx1, cy1) = self.clip;
+        (x0.max(cx0 as i32), y0.max(cy0 as i32), x1.min(cx1 as i32), y1.min(cy1 as i32))
+    }
+
+    /// Paint the ring between the border box and the padding box. Each pixel
+    /// belongs to the side it is proportionally deepest in, which splits the
+    /// corners along their diagonals.
+    fn draw_border(&mut self, rect: &Rect, sides: &Edges<BorderSide>, radii: &Corners) {
+        let dpr = self.device_pixel_ratio;
+        let widths = Edges {
+            top: sides.top.width,
+            right: sides.right.width,
+            bottom: sides.bottom.width,
+            left: sides.left.width,
+        };
+        let outer = RoundedRect::new(rect, radii, dpr);
+        let inner = RoundedRect::new(&rect.shrink(&widths), &radii.shrink(&widths), dpr);
+        // Rows between these are only border near the left and right edges
+        let corner_depth = |a: (f32, f32), b: (f32, f32)| a.1.max(b.1) * dpr;
+        let band_top = inner.top + corner_depth(inner.radii.top_left, inner.radii.top_right);
+        let band_bottom = inner.bottom - corner_depth(inner.radii.bottom_left, inner.radii.bottom_right);
+        let inner_left = (inner.left + inner.radii.top_left.0.max(inner.radii.bottom_left.0)).ceil() as i32;
+        let inner_right = (inner.right - inner.radii.top_right.0.max(inner.radii.bottom_right.0)).floor() as i32;
+
+        let (x0, y0, x1, y1) = self.clipped(outer.pixel_bounds(0));
+        for py in y0..y1 {
+            let in_band = py as f32 >= band_top && (py + 1) as f32 <= band_bottom;
+            for px in x0..x1 {
+                if in_band && px >= inner_left && px < inner_right {
+                    continue;
+                }
+                let coverage = outer.coverage(px, py) - inner.coverage(px, py);
+                if coverage <= 0.0 {
+                    continue;
+                }
+                let (x, y) = (px as f32 + 0.5, py as f32 + 0.5);
+                // Depth into each side as a fraction of its width
+                let depth = |distance: f32, width: f32| match width > 0.0 {
+                    true => distance / (width * dpr),
+                    false => f32::INFINITY,
+                };
+                let candidates = [
+                    (depth(y - outer.top, widths.top), &sides.top, x - outer.left, false),
+                    (depth(outer.right - x, widths.right), &sides.right, y - outer.top, true),
+                    (depth(outer.bottom - y, widths.bottom), &sides.bottom, x - outer.left, true),
+                    (depth(x - outer.left, widths.left), &sides.left, y - outer.top, false),
+                ];
+                let Some(&(depth, side, along, far)) =
+                    candidates.iter().min_by(|a, b| a.0.total_cmp(&b.0))
+                else {
+                    continue;
+                };
+                if !depth.is_finite() {
+                    continue;
+                }
+                if let Some((color, pattern)) = border_pixel(side, depth, along, far, dpr) {
+                    self.blend(px, py, color, coverage * pattern);
+                }
+            }
+        }
+    }
+
+    fn draw_shadow(&mut self, rect: &Rect, radii: &Corners, shadow: &BoxShadow) {
+        let dpr = self.device_pixel_ratio;
+        let box_shape = RoundedRect::new(rect, radii, dpr);
+        // The shadow's own shape: the box moved by the offset and grown by
+        // the spread (shrunk, for inset shadows, whose shape is the hole)
+        let spread = if shadow.inset { -shadow.spread } else { shadow.spread };
+        let shadow_rect = Rect {
+            x: rect.x + shadow.offset_x - spread,
+            y: rect.y + shadow.offset_y - spread,
+            width: (rect.width + 2.0 * spread).max(0.0),
+            height: (rect.height + 2.0 * spread).max(0.0),
+        };
+        let shape = RoundedRect::new(&shadow_rect, &radii.grow(spread), dpr);
+
+        let sigma = shadow.blur / 2.0 * dpr;
+        let margin = (3.0 * sigma).ceil() as i32 + 1;
+        let bounds = if shadow.inset { box_shape.pixel_bounds(margin) } else { shape.pixel_bounds(margin) };
+        // Everything further out than the blur reaches is off the canvas anyway
+        let (cx0, cy0, cx1, cy1) = self.clip;
+        let (x0, y0) = (bounds.0.max(cx0 as i32 - margin), bounds.1.max(cy0 as i32 - margin));
+        let (x1, y1) = (bounds.2.min(cx1 as i32 + margin), bounds.3.min(cy1 as i32 + margin));
+        if x1 <= x0 || y1 <= y0 {
+            return;
+        }
+        let (width, height) = ((x1 - x0) as usize, (y1 - y0) as usize);
+        let mut mask = Vec::with_capacity(width * height);
+        for py in y0..y1 {
+            for px in x0..x1 {
+                let coverage = shape.coverage(px, py);
+                mask.push(if shadow.inset { 1.0 - coverage } else { coverage });
+            }
+        }
+        blur(&mut mask, width, height, sigma);
+
+        let (dx0, dy0, dx1, dy1) = self.clipped((x0, y0, x1, y1));
+        for py in dy0..dy1 {
+            for px in dx0..dx1 {
+                let value = mask[(py - y0) as usize * width + (px - x0) as usize];
+                let inside_box = box_shape.coverage(px, py);
+                // Outer shadows never show through the box, inset ones never
+                // leave it
+                let alpha = if shadow.inset { value * inside_box } else { value * (1.0 - inside_box) };
+                if alpha > 0.0 {
+                    self.blend(px, py, shadow.color, alpha);
+                }
+            }
+        }
+    }
+
+    fn draw_text(
+        &mut self,
+        rect: &Rect,
+        text: &str,
+        font: &FontSpec,
+        color: Rgba,
+        decoration: &TextDecoration,
+        font_manager: &mut FontManager,
+    ) {
+        let dpr = self.device_pixel_ratio;
+        let run = font_manager.shape(text, &font.family, font.size, font.weight, font.italic);
+        if run.glyphs.is_empty() {
+            return;
+        }
+
+        // The em box sits in the middle of the line, with half the leading
+        // above it
+        let left = rect.x * dpr;
+        let baseline = (rect.y + (rect.height - run.ascent - run.descent) / 2.0 + run.ascent) * dpr;
+        for shaped in &run.glyphs {
+            let loaded = font_manager.font(shaped.font);
+            // rusttype scales to the ascent-to-descent height rather than the em
+            let units = loaded.v_metrics_unscaled();
+            let scale = Scale::uniform(font.size * dpr * (units.ascent - units.descent) / loaded.units_per_em() as f32);
+            let position = point(left + shaped.x * dpr, baseline + shaped.y * dpr);
+            let glyph = loaded.glyph(GlyphId(shaped.id)).scaled(scale).positioned(position);
+            if let Some(bb) = glyph.pixel_bounding_box() {
+                glyph.draw(|gx, gy, v| self.blend(gx as i32 + bb.min.x, gy as i32 + bb.min.y, color, v));
+            }
+        }
+
+        if decoration.underline {
+            let underline_y = (baseline + font.size * dpr / 10.0) as i32;
+            let thickness = (font.size * dpr / 16.0).max(1.0) as i32;
+            for uy in underline_y..underline_y + thickness {
+                for px in left as i32..(left + run.width * dpr) as i32 {
+                    self.blend(px, uy, color, 1.0);
+                }
+            }
+        }
+    }
+
+    /// Draw an image into `rect`, stretched for `<img>` or tiled at its
+    /// natural size for backgrounds
+    fn draw_image(&mut self, rect: &Rect, img: &RgbaImage, fit: ImageFit, opacity: f32) {
+        if img.width() == 0 || img.height() == 0 {
+            return;
+        }
+        let dpr = self.device_pixel_ratio;
+        let (x0, y0, x1, y1) = self.bounds(rect);
+        let (origin_x, origin_y) = (rect.x * dpr, rect.y * dpr);
+        let (width, height) = (rect.width * dpr, rect.height * dpr);
+
+        for py in y0..y1 {
+            for px in x0..x1 {
+                let dx = px as f32 - origin_x;
+                let dy = py as f32 - origin_y;
+                let (src_x, src_y) = match fit {
+                    ImageFit::Stretch => (
+                        (dx * img.width() as f32 / width) as u32,
+                        (dy * img.height() as f32 / height) as u32,
+                    ),
+                    ImageFit::Tile => ((dx / dpr) as u32 % img.width(), (dy / dpr) as u32 % img.height()),
+                };
+                if let Some(pixel) = img.get_pixel_checked(src_x, src_y) {
+                    let color = Rgba::new(pixel[0], pixel[1], pixel[2], pixel[3]);
+                    self.blend(px as i32, py as i32, color, opacity);
+                }
+            }
+        }
+    }
+
+    /// A light gray box with a dark gray border where an image failed to load
+    fn draw_image_placeholder(&mut self, rect: &Rect, opacity: f32) {
+        let dpr = self.device_pixel_ratio;
+        let (x0, y0, x1, y1) = self.bounds(rect);
+        let (left, top) = ((rect.x * dpr) as usize, (rect.y * dpr) as usize);
+        let right = ((rect.x + rect.width) * dpr) as usize;
+        let bottom = ((rect.y + rect.height) * dpr) as usize;
+
+        for py in y0..y1 {
+            for px in x0..x1 {
+                let on_border = py == top || py + 1 == bottom || px == left || px + 1 == right;
+                let color = if on_border { Rgba::new(100, 100, 100, 255) } else { Rgba::new(200, 200, 200, 255) };
+                self.blend(px as i32, py as i32, color, opacity);
+            }
+        }
+    }
+}
+
+/// The color and coverage of one border pixel. `depth` is how far into the
+/// side it is (0 at the outer edge, 1 at the inner), `along` its distance
+/// along the side from the border box's top-left, and `far` whether the
+/// side is the bottom or right one.
+fn border_pixel(side: &BorderSide, depth: f32, along: f32, far: bool, dpr: f32) -> Option<(Rgba, f32)> {
+    let width = (side.width * dpr).max(1.0);
+    let darker = shade(side.color, 0.5);
+    // Top and left sides are "lit" for the 3D styles
+    let (lit, unlit) = if far { (darker, side.color) } else { (side.color, darker) };
+    Some(match side.style {
+        BorderStyle::None | BorderStyle::Hidden => return None,
+        BorderStyle::Dashed => {
+            let dash = 3.0 * width;
+            ((along / dash) as u32).is_multiple_of(2).then_some((side.color, 1.0))?
+        }
+        BorderStyle::Dotted => {
+            // Round dots of the border's width, one every two widths
+            let center_along = (along / (2.0 * width)).floor() * 2.0 * width + width / 2.0;
+            let (da, dd) = (along - center_along, (depth - 0.5) * width);
+            let coverage = (width / 2.0 - (da * da + dd * dd).sqrt() + 0.5).clamp(0.0, 1.0);
+            (side.color, coverage)
+        }
+        BorderStyle::Double if side.width * dpr >= 3.0 => {
+            (!(1.0 / 3.0..2.0 / 3.0).contains(&depth)).then_some((side.color, 1.0))?
+        }
+        BorderStyle::Inset => (unlit, 1.0),
+        BorderStyle::Outset => (lit, 1.0),
+        BorderStyle::Groove => (if depth < 0.5 { unlit } else { lit }, 1.0),
+        BorderStyle::Ridge => (if depth < 0.5 { lit } else { unlit }, 1.0),
+        BorderStyle::Solid | BorderStyle::Double => (side.color, 1.0),
+    })
+}
+
+/// `color` with its channels scaled by `factor`
+fn shade(color: Rgba, factor: f32) -> Rgba {
+    let scale = |channel: u8| (channel as f32 * factor).round() as u8;
+    Rgba::new(scale(color.r), scale(color.g), scale(color.b), color.a)
+}
+
+/// Blur a `width` x `height` mask in place with three box blurs, which
+/// together approximate a Gaussian of standard deviation `sigma`. Samples
+/// past the edges repeat the edge values.
+fn blur(mask: &mut [f32], width: usize, height: usize, sigma: f32) {
+    // Three boxes of width w have variance (w^2 - 1) / 4
+    let radius = (((4.0 * sigma * sigma + 1.0).sqrt() - 1.0) / 2.0).round() as usize;
+    if radius == 0 {
+        return;
+    }
+    let mut line = Vec::new();
+    for _ in 0..3 {
+        for y in 0..height {
+            box_blur_line(mask, y * width, 1, width, radius, &mut line);
+        }
+        for x in 0..width {
+            box_blur_line(mask, x, width, height, radius, &mut line);
+        }
+    }
+}
+
+/// Box blur the `len` values of `mask` starting at `start`, `stride` apart
+fn box_blur_line(mask: &mut [f32], start: usize, stride: usize, len: usize, radius: usize, line: &mut Vec<f32>) {
+    line.clear();
+    line.extend((0..len).map(|i| mask[start + i * stride]));
+    let at = |i: isize| line[i.clamp(0, len as isize - 1) as usize];
+    let r = radius as isize;
+    let mut sum: f32 = (-r..=r).map(at).sum();
+    let scale = 1.0 / (2 * radius + 1) as f32;
+    for i in 0..len as isize {
+        mask[start + i as usize * stride] = sum * scale;
+        sum += at(i + r + 1) - at(i - r);
+    }
+}
+
+/// Render a display list onto a white image the size of `viewport` in
+/// device pixels
+pub fn render(
+    list: &DisplayList,
+    viewport: Viewport,
+    device_pixel_ratio: f32,
+    font_manager: &mut FontManager,
+    network: Option<&NetworkManager>,
+) -> RgbaImage {
+    let width = (viewport.width * device_pixel_ratio).round().max(1.0) as u32;
+    let height = (viewport.height * device_pixel_ratio).round().max(1.0) as u32;
+    let mut image = RgbaImage::from_pixel(width, height, ::image::Rgba([255, 255, 255, 255]));
+    Canvas::new(&mut image, width as usize, height as usize, device_pixel_ratio).draw(list, font_manager, network);
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::LayoutEngine;
+    use crate::parser::html::tree_builder::HtmlParser;
+    use crate::style::Stylesheet;
+
+    fn render_html(html: &str, viewport: Viewport, device_pixel_ratio: f32) -> RgbaImage {
+        let dom = HtmlParser::new(html).parse();
+        let mut font_manager = FontManager::new();
+        let styles = Stylesheet::new().compute_styles(&dom, &viewport);
+        let root = LayoutEngine::new().layout_with_styles(&dom, &styles, viewport, &mut font_manager);
+        let list = DisplayList::build(&root, &dom, device_pixel_ratio);
+        render(&list, viewport, device_pixel_ratio, &mut font_manager, None)
+    }
+
+    #[test]
+    fn test_fills_backgrounds_at_device_pixel_ratio() {
+        let image = render_html(
+            "<body style=\"margin:0\"><div style=\"background:#ff0000; width:10px; height:10px\"></div></body>",
+            Viewport::new(40.0, 30.0),
+            2.0,
+        );
+        assert_eq!(image.dimensions(), (80, 60));
+        assert_eq!(image.get_pixel(19, 19).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(20, 20).0, [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn test_composites_translucent_colors() {
+        let image = render_html(
+            "<body style=\"margin:0\"><div style=\"background:rgba(0, 0, 255, 0.5); height:10px\"></div></body>",
+            Viewport::new(20.0, 20.0),
+            1.0,
+        );
+        let [r, g, b, a] = image.get_pixel(5, 5).0;
+        assert!((126..=129).contains(&r) && r == g, "got {:?}", (r, g, b));
+        assert_eq!((b, a), (255, 255));
+    }
+
+    #[test]
+    fn test_clips_to_overflow_box() {
+        let image = render_html(
+            "<body style=\"margin:0\"><div style=\"overflow:hidden; height:5px\">\
+             <div style=\"background:#00ff00; height:20px\"></div></div></body>",
+            Viewport::new(20.0, 20.0),
+            1.0,
+        );
+        assert_eq!(image.get_pixel(5, 4).0, [0, 255, 0, 255]);
+        assert_eq!(image.get_pixel(5, 5).0, [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn test_missing_image_draws_placeholder() {
+        let image = render_html(
+            "<body style=\"margin:0\"><img src=\"missing.png\" style=\"width:100px; height:80px\"></body>",
+            Viewport::new(200.0, 100.0),
+            1.0,
+        );
+        assert_eq!(image.get_pixel(0, 0).0, [100, 100, 100, 255]);
+        assert_eq!(image.get_pixel(50, 40).0, [200, 200, 200, 255]);
+    }
+
+    #[test]
+    fn test_rounds_corners() {
+        let image = render_html(
+            "<body style=\"margin:0\"><div style=\"background:#0000ff; width:40px; height:40px; \
+             border-radius:20px\"></div></body>",
+            Viewport::new(50.0, 50.0),
+            1.0,
+        );
+        assert_eq!(image.get_pixel(1, 1).0, [255, 255, 255, 255]);
+        assert_eq!(image.get_pixel(20, 20).0, [0, 0, 255, 255]);
+        assert_eq!(image.get_pixel(20, 0).0, [0, 0, 255, 255]);
+        // The arc's edge is anti-aliased
+        let [r, _, b, _] = image.get_pixel(5, 6).0;
+        assert!(r > 0 && r < 255 && b == 255, "got {:?}", image.get_pixel(5, 6).0);
+    }
+
+    #[test]
+    fn test_draws_border_styles() {
+        let image = render_html(
+            "<body style=\"margin:0\"><div style=\"width:40px; height:10px; border:2px solid #ff0000; \
+             border-bottom:4px dashed #00ff00\"></div></body>",
+            Viewport::new(60.0, 30.0),
+            1.0,
+        );
+        assert_eq!(image.get_pixel(10, 0).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(10, 1).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(10, 2).0, [255, 255, 255, 255]);
+        assert_eq!(image.get_pixel(0, 8).0, [255, 0, 0, 255]);
+        // Dashes are three widths long with gaps as long between them
+        assert_eq!(image.get_pixel(6, 15).0, [0, 255, 0, 255]);
+        assert_eq!(image.get_pixel(14, 15).0, [255, 255, 255, 255]);
+        assert_eq!(image.get_pixel(26, 15).0, [0, 255, 0, 255]);
+    }
+
+    #[test]
+    fn test_outer_shadow_paints_outside_the_box() {
+        let image = render_html(
+            "<body style=\"margin:0\"><div style=\"background:#ffffff; width:20px; height:20px; \
+             margin:10px; box-shadow: 0 5px 4px #000000\"></div></body>",
+            Viewport::new(40.0, 50.0),
+            1.0,
+        );
+        // Beneath the box, shaded less with distance
+        let near = image.get_pixel(20, 32).0[0];
+        let far = image.get_pixel(20, 36).0[0];
+        assert!(near < far && far < 255, "got {} and {}", near, far);
+        // The box itself stays white, and the shadow is offset downwards
+        assert_eq!(image.get_pixel(20, 20).0, [255, 255, 255, 255]);
+        assert!(image.get_pixel(20, 6).0[0] > near);
+    }
+
+    #[test]
+    fn test_inset_shadow_paints_inside_the_padding_box() {
+        let image = render_html(
+            "<body style=\"margin:0\"><div style=\"width:30px; height:30px; border:2px solid #0000ff; \
+             box-shadow: inset 0 0 0 4px #ff0000\"></div></body>",
+            Viewport::new(40.0, 40.0),
+            1.0,
+        );
+        assert_eq!(image.get_pixel(1, 15).0, [0, 0, 255, 255]);
+        assert_eq!(image.get_pixel(3, 15).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(5, 15).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(6, 15).0, [255, 255, 255, 255]);
+        assert_eq!(image.get_pixel(38, 15).0, [255, 255, 255, 255]);
+    }
+}
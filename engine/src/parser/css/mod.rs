@@ -21,6 +21,8 @@ pub enum CssToken {
     DoubleColon,       // ::
     Semicolon,         // ;
     Comma,             // ,
+    Bang,              // ! (as in !important)
+    Slash,             // /
 
     // Values
     String(String),
@@ -93,14 +95,25 @@ impl CssTokenizer {
         result
     }
 
+    /// A digit, or `.`/`-` followed by one (`.5`, `-2px`, `-.5em`)
+    fn starts_number(&self, c: char) -> bool {
+        let digit_at = |offset| matches!(self.peek_ahead(offset), Some(d) if d.is_ascii_digit());
+        match c {
+            '.' => digit_at(1),
+            '-' => digit_at(1) || (self.peek_ahead(1) == Some('.') && digit_at(2)),
+            _ => c.is_ascii_digit(),
+        }
+    }
+
     pub fn next_token(&mut self) -> Option<CssToken> {
         match self.peek() {
             None => return Some(CssToken::Eof),
 
-            // Whitespace
+            // Whitespace is kept as a single token: it is the descendant
+            // combinator in selectors, and the parser skips it elsewhere
             Some(c) if c.is_whitespace() => {
                 self.consume_while(|c| c.is_whitespace());
-                return self.next_token();
+                return Some(CssToken::Whitespace);
             }
 
             // Comments
@@ -120,6 +133,8 @@ impl CssTokenizer {
                 return self.next_token(); // Skip comments
             }
 
+            Some('/') => { self.next(); return Some(CssToken::Slash); }
+
             // Strings
             Some('"') => {
                 self.next();
@@ -135,6 +150,21 @@ impl CssTokenizer {
                 return Some(CssToken::String(string));
             }
 
+            // Unicode ranges (`U+0-7F`, `U+4??`), kept whole as an identifier
+            // so the digits don't become numbers
+            Some('u') | Some('U') if self.peek_ahead(1) == Some('+')
+                && matches!(self.peek_ahead(2), Some(c) if c.is_ascii_hexdigit() || c == '?') => {
+                self.next(); // u
+                self.next(); // +
+                let mut range = format!("U+{}", self.consume_while(|c| c.is_ascii_hexdigit() || c == '?'));
+                if self.peek() == Some('-') && matches!(self.peek_ahead(1), Some(c) if c.is_ascii_hexdigit()) {
+                    self.next();
+                    range.push('-');
+                    range.push_str(&self.consume_while(|c| c.is_ascii_hexdigit()));
+                }
+                return Some(CssToken::Ident(range));
+            }
+
             // URLs
             Some('u') | Some('U') if matches!(self.peek_ahead(1), Some('r') | Some('R')) && matches!(self.peek_ahead(2), Some('l') | Some('L')) => {
                 self.next(); // u
@@ -158,9 +188,14 @@ impl CssTokenizer {
             }
 
             // Numbers and dimensions
-            Some(c) if c.is_ascii_digit() || (c == '.' && matches!(self.peek_ahead(1), Some(d) if d.is_ascii_digit())) => {
+            Some(c) if self.starts_number(c) => {
+                let negative = c == '-';
+                if negative {
+                    self.next();
+                }
                 let num_str = self.consume_while(|c| c.is_ascii_digit() || c == '.');
                 let num: f32 = num_str.parse().unwrap_or(0.0);
+                let num = if negative { -num } else { num };
 
                 // Check for percentage
                 if self.peek() == Some('%') {
@@ -185,7 +220,7 @@ impl CssTokenizer {
             // Hash/Color
             Some('#') => {
                 self.next();
-                let hex_or_id = self.consume_while(|c| c.is_alphanumeric());
+                let hex_or_id = self.consume_while(|c| c.is_alphanumeric() || c == '-' || c == '_');
                 
                 if hex_or_id.len() <= 6 && hex_or_id.chars().all(|c| c.is_ascii_hexdigit()) {
                     return Some(CssToken::Color(format!("#{}", hex_or_id)));
@@ -195,7 +230,7 @@ impl CssTokenizer {
             }
 
             // Class
-            Some('.') if matches!(self.peek_ahead(1), Some(c) if c.is_alphabetic()) => {
+            Some('.') if matches!(self.peek_ahead(1), Some(c) if c.is_alphabetic() || c == '_' || c == '-') => {
                 self.next();
                 let class = self.consume_while(|c| c.is_alphanumeric() || c == '-' || c == '_');
                 return Some(CssToken::Dot(class));
@@ -217,6 +252,7 @@ impl CssTokenizer {
             Some(']') => { self.next(); return Some(CssToken::CloseBracket); }
             Some(';') => { self.next(); return Some(CssToken::Semicolon); }
             Some(',') => { self.next(); return Some(CssToken::Comma); }
+            Some('!') => { self.next(); return Some(CssToken::Bang); }
             Some('>') => { self.next(); return Some(CssToken::Greater); }
             Some('+') => { self.next(); return Some(CssToken::Plus); }
             Some('~') if self.peek_ahead(1) == Some('=') => {
@@ -6,13 +6,23 @@ pub enum Selector {
     Element(String),                     // div
     Id(String),                          // #myid
     Class(String),                       // .myclass
-    Attribute {                          // [attr], [attr="value"]
+    Attribute {                          // [attr], [attr="value"], [attr="value" i]
         name: String,
         operator: Option<AttrOperator>,
         value: Option<String>,
+        /// The `i` flag: compare the value ASCII case-insensitively
+        case_insensitive: bool,
     },
     PseudoClass(String),                // :hover, :focus, etc.
     PseudoElement(String),              // ::before, ::after, etc.
+    NthChild {                          // :nth-child(2n+1), :nth-last-of-type(odd), etc.
+        a: i32,
+        b: i32,
+        of_type: bool,
+        from_end: bool,
+    },
+    Not(Vec<Selector>),                 // :not(.a, .b)
+    Compound(Vec<Selector>),            // div.note[title] (simple selectors on one element)
     Descendant(Box<Selector>, Box<Selector>),    // div p
     Child(Box<Selector>, Box<Selector>),        // div > p
     Adjacent(Box<Selector>, Box<Selector>),     // h1 + p
@@ -49,9 +59,16 @@ pub enum CssItem {
         name: String,
         prelude: String,
         content: Vec<CssItem>,
+        /// Descriptors of at-rules whose block is a declaration list
+        /// (`@font-face`, `@page`, ...) rather than rules
+        declarations: Vec<Declaration>,
     },
 }
 
+/// At-rules whose block holds descriptors, like a style rule's
+const DECLARATION_AT_RULES: &[&str] =
+    &["font-face", "page", "counter-style", "property", "font-palette-values"];
+
 pub struct CssParser {
     tokens: Vec<CssToken>,
     pos: usize,
@@ -62,11 +79,29 @@ impl CssParser {
         Self { tokens, pos: 0 }
     }
 
+    /// Next significant token; whitespace only matters inside selectors
     fn peek(&self) -> Option<&CssToken> {
+        self.tokens[self.pos.min(self.tokens.len())..]
+            .iter()
+            .find(|t| !matches!(t, CssToken::Whitespace))
+    }
+
+    /// Next token without skipping whitespace
+    fn peek_raw(&self) -> Option<&CssToken> {
         self.tokens.get(self.pos)
     }
 
+    /// Skip whitespace tokens, returning whether any were skipped
+    fn skip_whitespace(&mut self) -> bool {
+        let start = self.pos;
+        while matches!(self.tokens.get(self.pos), Some(CssToken::Whitespace)) {
+            self.pos += 1;
+        }
+        self.pos > start
+    }
+
     fn next(&mut self) -> Option<&CssToken> {
+        self.skip_whitespace();
         let token = self.tokens.get(self.pos);
         self.pos += 1;
         token
@@ -110,18 +145,40 @@ impl CssParser {
             _ => String::new(),
         };
 
-        // Collect prelude until opening brace
+        // Collect prelude until opening brace (or semicolon for statement at-rules)
+        self.skip_whitespace();
         let mut prelude = String::new();
-        while let Some(token) = self.peek() {
-            if matches!(token, CssToken::OpenBrace) {
-                break;
+        while let Some(token) = self.peek_raw() {
+            match token {
+                CssToken::OpenBrace => break,
+                CssToken::Semicolon => {
+                    self.next();
+                    return CssItem::AtRule {
+                        name,
+                        prelude: prelude.trim().to_string(),
+                        content: Vec::new(),
+                        declarations: Vec::new(),
+                    };
+                }
+                _ => prelude.push_str(&token_text(token)),
             }
-            prelude.push_str(&format!("{:?}", token));
-            self.next();
+            self.pos += 1;
         }
+        let prelude = prelude.trim().to_string();
 
         self.expect(&CssToken::OpenBrace);
 
+        if DECLARATION_AT_RULES.iter().any(|rule| name.eq_ignore_ascii_case(rule)) {
+            let declarations = self.parse_declarations();
+            self.expect(&CssToken::CloseBrace);
+            return CssItem::AtRule {
+                name,
+                prelude,
+                content: Vec::new(),
+                declarations,
+            };
+        }
+
         let mut content = Vec::new();
         let mut depth = 1;
 
@@ -155,6 +212,7 @@ impl CssParser {
             name,
             prelude,
             content,
+            declarations: Vec::new(),
         }
     }
 
@@ -188,41 +246,118 @@ impl CssParser {
     }
 
     fn parse_selector(&mut self) -> Option<Selector> {
-        let mut selector = self.parse_simple_selector()?;
+        let mut selector = self.parse_compound_selector()?;
 
         loop {
-            match self.peek() {
+            let had_whitespace = self.skip_whitespace();
+            match self.peek_raw() {
                 Some(CssToken::Greater) => {
                     self.next();
-                    let right = self.parse_simple_selector()?;
+                    let right = self.parse_compound_selector()?;
                     selector = Selector::Child(Box::new(selector), Box::new(right));
                 }
                 Some(CssToken::Plus) => {
                     self.next();
-                    let right = self.parse_simple_selector()?;
+                    let right = self.parse_compound_selector()?;
                     selector = Selector::Adjacent(Box::new(selector), Box::new(right));
                 }
                 Some(CssToken::Tilde) => {
                     self.next();
-                    let right = self.parse_simple_selector()?;
+                    let right = self.parse_compound_selector()?;
                     selector = Selector::GeneralSibling(Box::new(selector), Box::new(right));
                 }
-                Some(CssToken::Comma) | Some(CssToken::OpenBrace) => break,
-                Some(_) => {
-                    // Descendant selector (implicit whitespace)
-                    if let Some(right) = self.parse_simple_selector() {
+                Some(CssToken::Comma) | Some(CssToken::OpenBrace) | Some(CssToken::CloseParen) => break,
+                Some(_) if had_whitespace => {
+                    // Descendant combinator (whitespace between compounds)
+                    if let Some(right) = self.parse_compound_selector() {
                         selector = Selector::Descendant(Box::new(selector), Box::new(right));
                     } else {
                         break;
                     }
                 }
-                None => break,
+                _ => break,
             }
         }
 
         Some(selector)
     }
 
+    /// A run of simple selectors with no whitespace between them, e.g. `a.nav:first-child`
+    fn parse_compound_selector(&mut self) -> Option<Selector> {
+        self.skip_whitespace();
+        let mut parts = vec![self.parse_simple_selector()?];
+        while !matches!(self.peek_raw(), Some(CssToken::Whitespace) | None) {
+            match self.parse_simple_selector() {
+                Some(part) => parts.push(part),
+                None => break,
+            }
+        }
+
+        if parts.len() == 1 {
+            parts.pop()
+        } else {
+            Some(Selector::Compound(parts))
+        }
+    }
+
+    /// Arguments of a functional pseudo-class such as `:not(...)` or `:nth-child(...)`.
+    /// The function token has been consumed; this consumes through the closing paren.
+    fn parse_pseudo_class_function(&mut self, name: &str) -> Option<Selector> {
+        self.expect(&CssToken::OpenParen);
+        let selector = match name {
+            "not" => {
+                let mut list = Vec::new();
+                while let Some(selector) = self.parse_selector() {
+                    list.push(selector);
+                    if !self.expect(&CssToken::Comma) {
+                        break;
+                    }
+                }
+                Some(Selector::Not(list))
+            }
+            "nth-child" | "nth-last-child" | "nth-of-type" | "nth-last-of-type" => {
+                parse_an_plus_b(&self.argument_text()).map(|(a, b)| Selector::NthChild {
+                    a,
+                    b,
+                    of_type: name.ends_with("of-type"),
+                    from_end: name.contains("last"),
+                })
+            }
+            // Unsupported functional pseudo-classes are kept by name and never match
+            _ => {
+                self.argument_text();
+                Some(Selector::PseudoClass(name.to_string()))
+            }
+        };
+
+        // Skip anything left over (e.g. a malformed argument) up to the closing paren
+        let mut depth = 0;
+        while let Some(token) = self.next() {
+            match token {
+                CssToken::OpenParen => depth += 1,
+                CssToken::CloseParen if depth == 0 => break,
+                CssToken::CloseParen => depth -= 1,
+                _ => {}
+            }
+        }
+
+        selector
+    }
+
+    /// Text of the tokens up to (not including) the closing paren, without whitespace
+    fn argument_text(&mut self) -> String {
+        let mut text = String::new();
+        while let Some(token) = self.peek_raw() {
+            match token {
+                CssToken::CloseParen => break,
+                CssToken::Whitespace => {}
+                _ => text.push_str(&token_text(token)),
+            }
+            self.pos += 1;
+        }
+        text
+    }
+
     fn parse_simple_selector(&mut self) -> Option<Selector> {
         match self.peek() {
             Some(CssToken::Asterisk) => {
@@ -234,13 +369,19 @@ impl CssParser {
                 self.next();
                 Some(Selector::Id(id))
             }
+            // `#abc` tokenizes as a color, but in a selector it is an id
+            Some(CssToken::Color(hex)) if hex.starts_with('#') => {
+                let id = hex[1..].to_string();
+                self.next();
+                Some(Selector::Id(id))
+            }
             Some(CssToken::Dot(class)) => {
                 let class = class.clone();
                 self.next();
                 Some(Selector::Class(class))
             }
             Some(CssToken::Ident(tag)) => {
-                let tag = tag.clone();
+                let tag = tag.to_ascii_lowercase();
                 self.next();
                 Some(Selector::Element(tag))
             }
@@ -248,9 +389,13 @@ impl CssParser {
                 self.next();
                 match self.next() {
                     Some(CssToken::Ident(name)) => {
-                        let name = name.clone();
+                        let name = name.to_ascii_lowercase();
                         Some(Selector::PseudoClass(name))
                     }
+                    Some(CssToken::Function(name)) => {
+                        let name = name.to_ascii_lowercase();
+                        self.parse_pseudo_class_function(&name)
+                    }
                     _ => None,
                 }
             }
@@ -329,19 +474,32 @@ impl CssParser {
                     _ => (None, None),
                 };
 
+                // A trailing `i` or `s` flag; `s` is the default
+                let case_insensitive = match self.peek() {
+                    Some(CssToken::Ident(flag)) if value.is_some() => {
+                        let case_insensitive = flag.eq_ignore_ascii_case("i");
+                        self.next();
+                        case_insensitive
+                    }
+                    _ => false,
+                };
+
                 self.expect(&CssToken::CloseBracket);
 
                 Some(Selector::Attribute {
                     name,
                     operator,
                     value,
+                    case_insensitive,
                 })
             }
             _ => None,
         }
     }
 
-    fn parse_declarations(&mut self) -> Vec<Declaration> {
+    /// Parse a declaration list up to a closing brace or the end of input.
+    /// Also used on its own for `style="..."` attributes.
+    pub fn parse_declarations(&mut self) -> Vec<Declaration> {
         let mut declarations = Vec::new();
 
         while !matches!(self.peek(), Some(CssToken::CloseBrace) | None) {
@@ -365,9 +523,8 @@ impl CssParser {
 
         let value = self.parse_property_value();
 
-        let important = if matches!(self.peek(), Some(CssToken::Ident(s)) if s.to_lowercase() == "important") {
-            self.next();
-            true
+        let important = if self.expect(&CssToken::Bang) {
+            matches!(self.next(), Some(CssToken::Ident(s)) if s.eq_ignore_ascii_case("important"))
         } else {
             false
         };
@@ -381,56 +538,88 @@ impl CssParser {
         })
     }
 
+    /// Collect a declaration value as source text, with runs of
+    /// whitespace collapsed to one space
     fn parse_property_value(&mut self) -> String {
         let mut value = String::new();
-        let mut depth = 0;
+        let mut depth = 0usize;
 
-        while let Some(token) = self.peek() {
+        self.skip_whitespace();
+        while let Some(token) = self.peek_raw() {
             match token {
-                CssToken::Semicolon if depth == 0 => break,
-                CssToken::CloseBrace if depth == 0 => break,
-                CssToken::OpenParen | CssToken::OpenBracket => {
-                    depth += 1;
-                    value.push_str(&format!("{:?}", token));
-                    self.next();
-                }
-                CssToken::CloseParen | CssToken::CloseBracket => {
-                    depth -= 1;
-                    value.push_str(&format!("{:?}", token));
-                    self.next();
-                }
-                _ => {
-                    let token_str = self.token_to_string(token);
-                    // Add space between tokens if value is not empty and doesn't end with a space
-                    // and token_str is not empty and doesn't start with punctuation
-                    if !value.is_empty() && !token_str.is_empty() 
-                       && !value.ends_with(' ') && !value.ends_with('(') && !value.ends_with(',')
-                       && !token_str.starts_with(',') && !token_str.starts_with(')') {
-                        value.push(' ');
-                    }
-                    value.push_str(&token_str);
-                    self.next();
+                CssToken::Semicolon | CssToken::CloseBrace | CssToken::Bang if depth == 0 => break,
+                CssToken::OpenParen | CssToken::OpenBracket => depth += 1,
+                CssToken::CloseParen | CssToken::CloseBracket => depth = depth.saturating_sub(1),
+                CssToken::Whitespace if value.ends_with(' ') => {
+                    self.pos += 1;
+                    continue;
                 }
+                _ => {}
             }
+            value.push_str(&token_text(token));
+            self.pos += 1;
         }
 
         value.trim().to_string()
     }
+}
 
-    fn token_to_string(&self, token: &CssToken) -> String {
-        match token {
-            CssToken::Ident(s) => s.clone(),
-            CssToken::Number(n) => n.to_string(),
-            CssToken::Percentage(p) => format!("{}%", p),
-            CssToken::Dimension { value, unit } => format!("{}{}", value, unit),
-            CssToken::Color(c) => c.clone(),
-            CssToken::String(s) => format!("\"{}\"", s),
-            CssToken::Url(u) => format!("url({})", u),
-            CssToken::Function(f) => format!("{}(", f),
-            CssToken::Colon => ":".to_string(),
-            CssToken::DoubleColon => "::".to_string(),
-            CssToken::Comma => ",".to_string(),
-            _ => String::new(),
+/// Source text of a token, used for at-rule preludes and pseudo-class arguments
+fn token_text(token: &CssToken) -> String {
+    match token {
+        CssToken::Ident(s) => s.clone(),
+        CssToken::Hash(s) => format!("#{}", s),
+        CssToken::Dot(s) => format!(".{}", s),
+        CssToken::Number(n) => n.to_string(),
+        CssToken::Percentage(p) => format!("{}%", p),
+        CssToken::Dimension { value, unit } => format!("{}{}", value, unit),
+        CssToken::Color(c) => c.clone(),
+        CssToken::String(s) => format!("\"{}\"", s),
+        CssToken::Url(u) => format!("url({})", u),
+        CssToken::Function(f) => f.clone(),
+        CssToken::At(a) => format!("@{}", a),
+        CssToken::Asterisk => "*".to_string(),
+        CssToken::Plus => "+".to_string(),
+        CssToken::Greater => ">".to_string(),
+        CssToken::Tilde => "~".to_string(),
+        CssToken::Pipe => "|".to_string(),
+        CssToken::OpenParen => "(".to_string(),
+        CssToken::CloseParen => ")".to_string(),
+        CssToken::OpenBracket => "[".to_string(),
+        CssToken::CloseBracket => "]".to_string(),
+        CssToken::Colon => ":".to_string(),
+        CssToken::DoubleColon => "::".to_string(),
+        CssToken::Comma => ",".to_string(),
+        CssToken::Slash => "/".to_string(),
+        CssToken::Bang => "!".to_string(),
+        CssToken::Equals => "=".to_string(),
+        CssToken::Whitespace => " ".to_string(),
+        _ => String::new(),
+    }
+}
+
+/// Parse the `An+B` micro-syntax of `:nth-child()` and friends
+fn parse_an_plus_b(text: &str) -> Option<(i32, i32)> {
+    let text = text.to_ascii_lowercase();
+    match text.as_str() {
+        "odd" => return Some((2, 1)),
+        "even" => return Some((2, 0)),
+        _ => {}
+    }
+
+    match text.find('n') {
+        Some(n) => {
+            let a = match &text[..n] {
+                "" | "+" => 1,
+                "-" => -1,
+                a => a.trim_start_matches('+').parse().ok()?,
+            };
+            let b = match &text[n + 1..] {
+                "" => 0,
+                b => b.trim_start_matches('+').parse().ok()?,
+            };
+            Some((a, b))
         }
+        None => Some((0, text.trim_start_matches('+').parse().ok()?)),
     }
 }
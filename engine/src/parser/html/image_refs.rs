@@ -408,6 +408,34 @@ fn extract_styles_from_node(dom: &Dom, node_id: NodeId, styles: &mut Vec<(NodeId
     }
 }
 
+/// Extract the href of every `<link rel="stylesheet">`, skipping alternate
+/// stylesheets
+pub fn extract_stylesheet_links(dom: &Dom) -> Vec<(NodeId, String)> {
+    let mut links = Vec::new();
+    extract_links_from_node(dom, dom.root(), &mut links);
+    links
+}
+
+fn extract_links_from_node(dom: &Dom, node_id: NodeId, links: &mut Vec<(NodeId, String)>) {
+    let node = &dom.nodes[node_id];
+    
+    if let NodeType::Element(el) = &node.node_type {
+        if el.tag_name.eq_ignore_ascii_case("link") {
+            let rel = get_attribute(el, "rel").unwrap_or_default().to_lowercase();
+            let rel: Vec<&str> = rel.split_ascii_whitespace().collect();
+            if rel.contains(&"stylesheet") && !rel.contains(&"alternate") {
+                if let Some(href) = get_attribute(el, "href").filter(|href| !href.trim().is_empty()) {
+                    links.push((node_id, href));
+                }
+            }
+        }
+    }
+    
+    for &child_id in &node.children {
+        extract_links_from_node(dom, child_id, links);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -468,4 +496,20 @@ mod tests {
         assert_eq!(refs[0].url, "photo.jpg");
         assert!(matches!(refs[0].ref_type, ImageRefType::ImgSrc));
     }
+    
+    #[test]
+    fn test_extract_stylesheet_links() {
+        use crate::parser::html::tree_builder::HtmlParser;
+        
+        let html = r#"<!DOCTYPE html><html><head>
+            <link rel="stylesheet" href="main.css">
+            <link rel="icon" href="favicon.ico">
+            <link rel="Alternate Stylesheet" href="dark.css">
+            <link rel="preload stylesheet" href="fonts.css">
+            </head><body></body></html>"#;
+        let dom = HtmlParser::new(html).parse();
+        
+        let hrefs: Vec<String> = extract_stylesheet_links(&dom).into_iter().map(|(_, href)| href).collect();
+        assert_eq!(hrefs, vec!["main.css", "fonts.css"]);
+    }
 }
@@ -1,9 +1,10 @@
+pub mod entities;
 pub mod tokenizer;
 pub mod tree_builder;
 pub mod image_refs;
 
 pub use image_refs::{
-    extract_image_refs, extract_base_href, extract_stylesheets,
+    extract_image_refs, extract_base_href, extract_stylesheets, extract_stylesheet_links,
     parse_srcset_attribute, parse_css_urls,
     ImageRef, ImageRefType, SrcsetDescriptor, CssUrlRef,
 };
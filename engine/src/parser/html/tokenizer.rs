@@ -20,9 +20,9 @@
 // ⚠️ Comment state - basic
 // ⚠️ Comment end state - basic
 // ⚠️ DOCTYPE state - basic
-// ❌ Character reference states - not implemented
-// ❌ RCDATA states - not implemented
-// ❌ RAWTEXT states - not implemented
+// ✅ Character reference states (named, numeric, ambiguous ampersand)
+// ✅ RCDATA states
+// ✅ RAWTEXT states
 // ❌ Script data states - not implemented
 //
 // TODO(spec 13.2.5.1): Implement preprocessing input stream
@@ -30,6 +30,8 @@
 
 use std::collections::VecDeque;
 
+use super::entities;
+
 /// Debug logging for tokenizer operations
 const DEBUG_TOKENIZER: bool = false;
 
@@ -115,6 +117,15 @@ pub enum TokenizerState {
     BeforeDoctypeName,
     DoctypeName,
     AfterDoctypeName,
+    CharacterReference,
+    NamedCharacterReference,
+    AmbiguousAmpersand,
+    NumericCharacterReference,
+    HexadecimalCharacterReferenceStart,
+    DecimalCharacterReferenceStart,
+    HexadecimalCharacterReference,
+    DecimalCharacterReference,
+    NumericCharacterReferenceEnd,
 }
 
 /// Void elements that cannot have content (spec 13.1.2)
@@ -145,6 +156,11 @@ pub struct Tokenizer {
     current_attribute: Option<Attribute>,
     last_start_tag_name: Option<String>,
     reconsume: bool,
+    /// Last character returned by consume_next (None at EOF), used when reconsuming
+    current_input_char: Option<char>,
+    /// State to go back to after a character reference (spec 13.2.5.72)
+    return_state: TokenizerState,
+    character_reference_code: u32,
 }
 
 impl Tokenizer {
@@ -159,6 +175,9 @@ impl Tokenizer {
             current_attribute: None,
             last_start_tag_name: None,
             reconsume: false,
+            current_input_char: None,
+            return_state: TokenizerState::Data,
+            character_reference_code: 0,
         }
     }
 
@@ -174,23 +193,16 @@ impl Tokenizer {
     fn consume_next(&mut self) -> Option<char> {
         if self.reconsume {
             self.reconsume = false;
-            return self.current_input_char();
+            return self.current_input_char;
         }
         let c = self.input.get(self.pos).copied();
         if c.is_some() {
             self.pos += 1;
         }
+        self.current_input_char = c;
         c
     }
 
-    fn current_input_char(&self) -> Option<char> {
-        if self.pos > 0 {
-            self.input.get(self.pos - 1).copied()
-        } else {
-            None
-        }
-    }
-
     fn next_chars_are_case_insensitive(&self, s: &str) -> bool {
         let chars: Vec<char> = s.chars().collect();
         for (i, c) in chars.iter().enumerate() {
@@ -348,6 +360,42 @@ impl Tokenizer {
         false
     }
 
+    /// Enter the character reference state, remembering where to return to
+    fn begin_character_reference(&mut self, return_state: TokenizerState) {
+        self.return_state = return_state;
+        self.temp_buffer.clear();
+        self.temp_buffer.push('&');
+        self.state = TokenizerState::CharacterReference;
+    }
+
+    /// Per spec: "consumed as part of an attribute"
+    fn charref_in_attribute(&self) -> bool {
+        matches!(
+            self.return_state,
+            TokenizerState::AttributeValueDoubleQuoted
+                | TokenizerState::AttributeValueSingleQuoted
+                | TokenizerState::AttributeValueUnquoted
+        )
+    }
+
+    /// Emit a code point from a character reference, either into the current
+    /// attribute value or as a character token
+    fn emit_charref_char(&mut self, c: char) {
+        if self.charref_in_attribute() {
+            self.append_to_attribute_value(c);
+        } else {
+            self.emit_char(c);
+        }
+    }
+
+    /// Flush code points consumed as a character reference
+    fn flush_temp_buffer_as_charref(&mut self) {
+        let buffer = std::mem::take(&mut self.temp_buffer);
+        for c in buffer.chars() {
+            self.emit_charref_char(c);
+        }
+    }
+
     pub fn next_token(&mut self) -> Option<Token> {
         loop {
             if let Some(token) = self.token_queue.pop_front() {
@@ -390,6 +438,15 @@ impl Tokenizer {
                 TokenizerState::BeforeDoctypeName => self.before_doctype_name_state(c),
                 TokenizerState::DoctypeName => self.doctype_name_state(c),
                 TokenizerState::AfterDoctypeName => self.after_doctype_name_state(c),
+                TokenizerState::CharacterReference => self.character_reference_state(c),
+                TokenizerState::NamedCharacterReference => self.named_character_reference_state(c),
+                TokenizerState::AmbiguousAmpersand => self.ambiguous_ampersand_state(c),
+                TokenizerState::NumericCharacterReference => self.numeric_character_reference_state(c),
+                TokenizerState::HexadecimalCharacterReferenceStart => self.hexadecimal_character_reference_start_state(c),
+                TokenizerState::DecimalCharacterReferenceStart => self.decimal_character_reference_start_state(c),
+                TokenizerState::HexadecimalCharacterReference => self.hexadecimal_character_reference_state(c),
+                TokenizerState::DecimalCharacterReference => self.decimal_character_reference_state(c),
+                TokenizerState::NumericCharacterReferenceEnd => self.numeric_character_reference_end_state(c),
                 _ => {
                     tokenizer_log(&format!("Unimplemented state: {:?}", self.state));
                     self.state = TokenizerState::Data;
@@ -402,8 +459,7 @@ impl Tokenizer {
     fn data_state(&mut self, c: Option<char>) {
         match c {
             Some('&') => {
-                // TODO: character reference
-                self.emit_char('&');
+                self.begin_character_reference(TokenizerState::Data);
             }
             Some('<') => {
                 self.state = TokenizerState::TagOpen;
@@ -425,7 +481,7 @@ impl Tokenizer {
     fn rcdata_state(&mut self, c: Option<char>) {
         match c {
             Some('&') => {
-                self.emit_char('&');
+                self.begin_character_reference(TokenizerState::RcData);
             }
             Some('<') => {
                 self.state = TokenizerState::RcDataLessThan;
@@ -785,7 +841,7 @@ impl Tokenizer {
                 self.state = TokenizerState::AfterAttributeValueQuoted;
             }
             Some('&') => {
-                self.append_to_attribute_value('&');
+                self.begin_character_reference(TokenizerState::AttributeValueDoubleQuoted);
             }
             Some('\0') => {
                 tokenizer_log("Parse error: unexpected-null-character");
@@ -808,7 +864,7 @@ impl Tokenizer {
                 self.state = TokenizerState::AfterAttributeValueQuoted;
             }
             Some('&') => {
-                self.append_to_attribute_value('&');
+                self.begin_character_reference(TokenizerState::AttributeValueSingleQuoted);
             }
             Some('\0') => {
                 tokenizer_log("Parse error: unexpected-null-character");
@@ -832,7 +888,7 @@ impl Tokenizer {
                 self.state = TokenizerState::BeforeAttributeName;
             }
             Some('&') => {
-                self.append_to_attribute_value('&');
+                self.begin_character_reference(TokenizerState::AttributeValueUnquoted);
             }
             Some('>') => {
                 self.finalize_current_attribute();
@@ -1187,6 +1243,225 @@ impl Tokenizer {
         }
     }
 
+    /// 13.2.5.72 Character reference state
+    fn character_reference_state(&mut self, c: Option<char>) {
+        match c {
+            Some(c) if c.is_ascii_alphanumeric() => {
+                self.reconsume_in(TokenizerState::NamedCharacterReference);
+            }
+            Some('#') => {
+                self.temp_buffer.push('#');
+                self.state = TokenizerState::NumericCharacterReference;
+            }
+            _ => {
+                self.flush_temp_buffer_as_charref();
+                let return_state = self.return_state;
+                self.reconsume_in(return_state);
+            }
+        }
+    }
+
+    /// 13.2.5.73 Named character reference state
+    ///
+    /// Consumes the longest run of input that is a name in the named
+    /// character references table.
+    fn named_character_reference_state(&mut self, _c: Option<char>) {
+        // The current input character is the first character of the name
+        let start = self.pos - 1;
+        let mut candidate = String::new();
+        let mut matched: Option<(usize, &'static str)> = None;
+
+        while candidate.len() < entities::LONGEST_NAME_LEN {
+            let Some(&next) = self.input.get(start + candidate.len()) else {
+                break;
+            };
+            candidate.push(next);
+            if !entities::has_prefix(&candidate) {
+                break;
+            }
+            if let Some(value) = entities::lookup(&candidate) {
+                matched = Some((candidate.len(), value));
+            }
+        }
+
+        match matched {
+            Some((len, value)) => {
+                let name: String = self.input[start..start + len].iter().collect();
+                self.pos = start + len;
+                self.temp_buffer.push_str(&name);
+
+                let next_input = self.input.get(self.pos).copied();
+                let ends_with_semicolon = name.ends_with(';');
+                if !ends_with_semicolon
+                    && self.charref_in_attribute()
+                    && matches!(next_input, Some(n) if n == '=' || n.is_ascii_alphanumeric())
+                {
+                    // Historical reasons: leave "&amp=" style attribute values alone
+                    self.flush_temp_buffer_as_charref();
+                    self.state = self.return_state;
+                    return;
+                }
+
+                if !ends_with_semicolon {
+                    tokenizer_log("Parse error: missing-semicolon-after-character-reference");
+                }
+                self.temp_buffer.clear();
+                for ch in value.chars() {
+                    self.emit_charref_char(ch);
+                }
+                self.state = self.return_state;
+            }
+            None => {
+                self.flush_temp_buffer_as_charref();
+                self.reconsume_in(TokenizerState::AmbiguousAmpersand);
+            }
+        }
+    }
+
+    /// 13.2.5.74 Ambiguous ampersand state
+    fn ambiguous_ampersand_state(&mut self, c: Option<char>) {
+        match c {
+            Some(c) if c.is_ascii_alphanumeric() => {
+                self.emit_charref_char(c);
+            }
+            Some(';') => {
+                tokenizer_log("Parse error: unknown-named-character-reference");
+                let return_state = self.return_state;
+                self.reconsume_in(return_state);
+            }
+            _ => {
+                let return_state = self.return_state;
+                self.reconsume_in(return_state);
+            }
+        }
+    }
+
+    /// 13.2.5.75 Numeric character reference state
+    fn numeric_character_reference_state(&mut self, c: Option<char>) {
+        self.character_reference_code = 0;
+        match c {
+            Some(c @ ('x' | 'X')) => {
+                self.temp_buffer.push(c);
+                self.state = TokenizerState::HexadecimalCharacterReferenceStart;
+            }
+            _ => {
+                self.reconsume_in(TokenizerState::DecimalCharacterReferenceStart);
+            }
+        }
+    }
+
+    /// 13.2.5.76 Hexadecimal character reference start state
+    fn hexadecimal_character_reference_start_state(&mut self, c: Option<char>) {
+        match c {
+            Some(c) if c.is_ascii_hexdigit() => {
+                self.reconsume_in(TokenizerState::HexadecimalCharacterReference);
+            }
+            _ => {
+                tokenizer_log("Parse error: absence-of-digits-in-numeric-character-reference");
+                self.flush_temp_buffer_as_charref();
+                let return_state = self.return_state;
+                self.reconsume_in(return_state);
+            }
+        }
+    }
+
+    /// 13.2.5.77 Decimal character reference start state
+    fn decimal_character_reference_start_state(&mut self, c: Option<char>) {
+        match c {
+            Some(c) if c.is_ascii_digit() => {
+                self.reconsume_in(TokenizerState::DecimalCharacterReference);
+            }
+            _ => {
+                tokenizer_log("Parse error: absence-of-digits-in-numeric-character-reference");
+                self.flush_temp_buffer_as_charref();
+                let return_state = self.return_state;
+                self.reconsume_in(return_state);
+            }
+        }
+    }
+
+    /// 13.2.5.78 Hexadecimal character reference state
+    fn hexadecimal_character_reference_state(&mut self, c: Option<char>) {
+        match c {
+            Some(c) if c.is_ascii_hexdigit() => {
+                let digit = c.to_digit(16).unwrap_or(0);
+                self.character_reference_code = self
+                    .character_reference_code
+                    .saturating_mul(16)
+                    .saturating_add(digit);
+            }
+            Some(';') => {
+                self.state = TokenizerState::NumericCharacterReferenceEnd;
+            }
+            _ => {
+                tokenizer_log("Parse error: missing-semicolon-after-character-reference");
+                self.reconsume_in(TokenizerState::NumericCharacterReferenceEnd);
+            }
+        }
+    }
+
+    /// 13.2.5.79 Decimal character reference state
+    fn decimal_character_reference_state(&mut self, c: Option<char>) {
+        match c {
+            Some(c) if c.is_ascii_digit() => {
+                let digit = c.to_digit(10).unwrap_or(0);
+                self.character_reference_code = self
+                    .character_reference_code
+                    .saturating_mul(10)
+                    .saturating_add(digit);
+            }
+            Some(';') => {
+                self.state = TokenizerState::NumericCharacterReferenceEnd;
+            }
+            _ => {
+                tokenizer_log("Parse error: missing-semicolon-after-character-reference");
+                self.reconsume_in(TokenizerState::NumericCharacterReferenceEnd);
+            }
+        }
+    }
+
+    /// 13.2.5.80 Numeric character reference end state
+    ///
+    /// Does not consume anything itself; the character passed in is
+    /// reconsumed in the return state.
+    fn numeric_character_reference_end_state(&mut self, _c: Option<char>) {
+        let code = self.character_reference_code;
+        let replacement = match code {
+            0 => {
+                tokenizer_log("Parse error: null-character-reference");
+                '\u{FFFD}'
+            }
+            0x11_0000.. => {
+                tokenizer_log("Parse error: character-reference-outside-unicode-range");
+                '\u{FFFD}'
+            }
+            0xD800..=0xDFFF => {
+                tokenizer_log("Parse error: surrogate-character-reference");
+                '\u{FFFD}'
+            }
+            0x80..=0x9F => {
+                tokenizer_log("Parse error: control-character-reference");
+                windows_1252_replacement(code)
+                    .unwrap_or_else(|| char::from_u32(code).unwrap_or('\u{FFFD}'))
+            }
+            _ => {
+                if is_noncharacter(code) {
+                    tokenizer_log("Parse error: noncharacter-character-reference");
+                } else if code == 0x0D || (is_control(code) && !is_ascii_whitespace(code)) {
+                    tokenizer_log("Parse error: control-character-reference");
+                }
+                char::from_u32(code).unwrap_or('\u{FFFD}')
+            }
+        };
+
+        self.temp_buffer.clear();
+        self.emit_charref_char(replacement);
+
+        // This state consumes nothing; hand the current character back
+        let return_state = self.return_state;
+        self.reconsume_in(return_state);
+    }
+
     /// Tokenize entire input (compatibility method)
     pub fn tokenize(&mut self) -> Vec<Token> {
         let mut tokens = Vec::new();
@@ -1201,6 +1476,53 @@ impl Tokenizer {
     }
 }
 
+/// Replacements for numeric references to C1 controls (spec 13.2.5.80 table)
+fn windows_1252_replacement(code: u32) -> Option<char> {
+    let c = match code {
+        0x80 => '\u{20AC}',
+        0x82 => '\u{201A}',
+        0x83 => '\u{0192}',
+        0x84 => '\u{201E}',
+        0x85 => '\u{2026}',
+        0x86 => '\u{2020}',
+        0x87 => '\u{2021}',
+        0x88 => '\u{02C6}',
+        0x89 => '\u{2030}',
+        0x8A => '\u{0160}',
+        0x8B => '\u{2039}',
+        0x8C => '\u{0152}',
+        0x8E => '\u{017D}',
+        0x91 => '\u{2018}',
+        0x92 => '\u{2019}',
+        0x93 => '\u{201C}',
+        0x94 => '\u{201D}',
+        0x95 => '\u{2022}',
+        0x96 => '\u{2013}',
+        0x97 => '\u{2014}',
+        0x98 => '\u{02DC}',
+        0x99 => '\u{2122}',
+        0x9A => '\u{0161}',
+        0x9B => '\u{203A}',
+        0x9C => '\u{0153}',
+        0x9E => '\u{017E}',
+        0x9F => '\u{0178}',
+        _ => return None,
+    };
+    Some(c)
+}
+
+fn is_noncharacter(code: u32) -> bool {
+    (0xFDD0..=0xFDEF).contains(&code) || (code & 0xFFFE) == 0xFFFE
+}
+
+fn is_control(code: u32) -> bool {
+    code <= 0x1F || (0x7F..=0x9F).contains(&code)
+}
+
+fn is_ascii_whitespace(code: u32) -> bool {
+    matches!(code, 0x09 | 0x0A | 0x0C | 0x0D | 0x20)
+}
+
 impl Token {
     /// Get attributes as Vec<(String, String)> for compatibility
     pub fn get_attributes(&self) -> Vec<(String, String)> {
@@ -1305,4 +1627,66 @@ mod tests {
         // Last token should be end tag
         assert!(matches!(&tokens[tokens.len()-1], Token::EndTag { name } if name == "a"));
     }
+
+    fn text_of(input: &str) -> String {
+        Tokenizer::new(input).tokenize().iter()
+            .filter_map(|t| if let Token::Character(c) = t { Some(*c) } else { None })
+            .collect()
+    }
+
+    fn first_attribute_value(input: &str) -> String {
+        match &Tokenizer::new(input).tokenize()[0] {
+            Token::StartTag { attributes, .. } => attributes[0].value.clone(),
+            other => panic!("Expected start tag, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_named_character_references() {
+        assert_eq!(text_of("Fish &amp; Chips"), "Fish & Chips");
+        assert_eq!(text_of("a&nbsp;b"), "a\u{A0}b");
+        assert_eq!(text_of("caf&eacute;"), "café");
+        assert_eq!(text_of("&lt;div&gt;"), "<div>");
+        // Longest match wins, and unknown names pass through untouched
+        assert_eq!(text_of("&notit;"), "¬it;");
+        assert_eq!(text_of("&bogus; &"), "&bogus; &");
+    }
+
+    #[test]
+    fn test_legacy_references_without_semicolon() {
+        assert_eq!(text_of("&copy 2024"), "© 2024");
+        assert_eq!(text_of("AT&T"), "AT&T");
+        assert_eq!(text_of("x &amp"), "x &");
+    }
+
+    #[test]
+    fn test_numeric_character_references() {
+        assert_eq!(text_of("&#8212;&#x2014;&#X41;"), "——A");
+        assert_eq!(text_of("&#65"), "A");
+        assert_eq!(text_of("&#150;"), "\u{2013}");
+        assert_eq!(text_of("&#0;&#xD800;&#x110000;"), "\u{FFFD}\u{FFFD}\u{FFFD}");
+        assert_eq!(text_of("&#;&#x;"), "&#;&#x;");
+    }
+
+    #[test]
+    fn test_character_references_in_attributes() {
+        assert_eq!(first_attribute_value("<a href=\"/s?a=1&amp;b=2\">"), "/s?a=1&b=2");
+        assert_eq!(first_attribute_value("<a title='&quot;hi&quot;'>"), "\"hi\"");
+        assert_eq!(first_attribute_value("<a href=x&lt;y>"), "x<y");
+        // Legacy names followed by '=' or alphanumerics are left alone in attributes
+        assert_eq!(first_attribute_value("<a href=\"?x=1&copy=2&notit\">"), "?x=1&copy=2&notit");
+    }
+
+    #[test]
+    fn test_rcdata_character_references() {
+        let mut tokenizer = Tokenizer::new("Tom &amp; Jerry <b></title>");
+        tokenizer.set_state(TokenizerState::RcData);
+        tokenizer.last_start_tag_name = Some("title".to_string());
+        let tokens = tokenizer.tokenize();
+        let text: String = tokens.iter()
+            .filter_map(|t| if let Token::Character(c) = t { Some(*c) } else { None })
+            .collect();
+        assert_eq!(text, "Tom & Jerry <b>");
+        assert!(matches!(tokens.last(), Some(Token::EndTag { name }) if name == "title"));
+    }
 }
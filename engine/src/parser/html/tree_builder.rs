@@ -3,26 +3,31 @@
 //
 // IMPLEMENTATION STATUS:
 // ✅ Initial mode - basic
-// ✅ BeforeHtml mode - basic
-// ✅ BeforeHead mode - basic
-// ✅ InHead mode - partial (meta, link, title, style, base)
-// ✅ AfterHead mode - basic
-// ✅ InBody mode - basic element creation
-// ⚠️ Text mode - partial
-// ❌ InTable mode - not implemented
+// ✅ BeforeHtml mode
+// ✅ BeforeHead mode
+// ✅ InHead mode - partial (no template, no noscript handling)
+// ✅ AfterHead mode
+// ✅ InBody mode - start/end tag rules incl. scopes and implied end tags
+// ✅ Text mode - emulated (text inside RCDATA/raw text elements bypasses reconstruction)
+// ✅ List of active formatting elements (spec 13.2.4.3) with markers and Noah's Ark clause
+// ✅ Reconstruct the active formatting elements
+// ✅ Adoption agency algorithm (spec 13.2.6.4.7)
+// ✅ InTable mode - with foster parenting
+// ✅ InTableText mode - emulated when flushing buffered text
+// ✅ InCaption mode
+// ✅ InColumnGroup mode
+// ✅ InTableBody mode
+// ✅ InRow mode
+// ✅ InCell mode
 // ❌ InSelect mode - not implemented
-// ❌ InForeignContent mode - not implemented
-// ⚠️ AfterBody mode - partial
+// ❌ InForeignContent mode - not implemented (self-closing svg/math tags are acknowledged)
+// ✅ AfterBody mode
 // ❌ InFrameset mode - not implemented
 // ❌ AfterFrameset mode - not implemented
-// ❌ AfterAfterBody mode - not implemented
-//
-// TODO(spec 13.2.6): Implement full adoption agency algorithm
-// TODO(spec 13.2.6): Implement foster parenting
-// TODO(spec 13.2.6): Implement AAA (adoption agency algorithm)
+// ✅ AfterAfterBody mode
 
-use crate::dom::{Dom, NodeId};
-use super::tokenizer::{Token, Tokenizer, VOID_ELEMENTS};
+use crate::dom::{Dom, NodeId, NodeType};
+use super::tokenizer::{Attribute, Token, Tokenizer, TokenizerState, VOID_ELEMENTS};
 
 /// Debug logging for tree construction
 const DEBUG_TREE_BUILDER: bool = false;
@@ -62,316 +67,1565 @@ enum InsertionMode {
     AfterAfterFrameset,
 }
 
+/// Entry in the list of active formatting elements (spec 13.2.4.3)
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum FormattingEntry {
+    Marker,
+    Element(NodeId),
+}
+
+/// Where the adoption agency algorithm will put the new formatting element
+/// in the list of active formatting elements (spec 13.2.6.4.7, "bookmark")
+enum Bookmark {
+    Replace(NodeId),
+    InsertAfter(NodeId),
+}
+
+/// Kinds of "has an element in ... scope" checks (spec 13.2.4.2)
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Scope {
+    Default,
+    ListItem,
+    Button,
+    Table,
+}
+
 pub struct HtmlParser {
     tokenizer: Tokenizer,
     /// Buffer for accumulating character tokens into text nodes
     pending_text: String,
+    mode: InsertionMode,
+    /// The synthetic "document" root element
+    document: NodeId,
+    /// Stack of open elements (spec 13.2.4.2); `document` is always at the bottom
+    open_elements: Vec<NodeId>,
+    /// List of active formatting elements (spec 13.2.4.3)
+    active_formatting: Vec<FormattingEntry>,
+    /// The head element pointer (spec 13.2.4.4)
+    head: Option<NodeId>,
+    /// Set while "in table" processes a token with the in body rules (spec 13.2.6.1)
+    foster_parenting: bool,
+    /// Set right after a pre, listing or textarea start tag, whose first
+    /// newline is dropped
+    ignore_newline: bool,
 }
 
-// Auto-closing tags that force parent closure
-const AUTO_CLOSING_TAGS: &[&str] = &[
-    "p", "li", "dd", "dt", "option", "optgroup", "tr", "td", "th", 
+/// Elements that terminate a default scope check (spec 13.2.4.2).
+/// "document" is our root and stands in for the Document node.
+const SCOPE_BOUNDARIES: &[&str] = &[
+    "applet", "caption", "html", "table", "td", "th", "marquee", "object", "template", "document",
 ];
 
-// Tags that can be implicitly closed (not currently used, but may be useful for future HTML5 spec compliance)
-#[allow(dead_code)]
+/// Formatting elements (spec 13.2.4.3)
 const FORMATTING_TAGS: &[&str] = &[
     "a", "b", "big", "code", "em", "font", "i", "nobr", "s", "small", "strike", "strong", "tt", "u",
 ];
 
+/// Elements in the "special" category (spec 13.2.4.2)
+const SPECIAL_TAGS: &[&str] = &[
+    "address", "applet", "area", "article", "aside", "base", "basefont", "bgsound", "blockquote",
+    "body", "br", "button", "caption", "center", "col", "colgroup", "dd", "details", "dir", "div",
+    "dl", "dt", "embed", "fieldset", "figcaption", "figure", "footer", "form", "frame", "frameset",
+    "h1", "h2", "h3", "h4", "h5", "h6", "head", "header", "hgroup", "hr", "html", "iframe", "img",
+    "input", "keygen", "li", "link", "listing", "main", "marquee", "menu", "meta", "nav",
+    "noembed", "noframes", "noscript", "object", "ol", "p", "param", "plaintext", "pre", "script",
+    "search", "section", "select", "source", "style", "summary", "table", "tbody", "td",
+    "template", "textarea", "tfoot", "th", "thead", "title", "tr", "track", "ul", "wbr", "xmp",
+    "document",
+];
+
+/// Elements whose end tags are implied (spec 13.2.6.3)
+const IMPLIED_END_TAGS: &[&str] = &[
+    "dd", "dt", "li", "optgroup", "option", "p", "rb", "rp", "rt", "rtc",
+];
+
+/// Start tags that close an open p element in button scope before inserting
+const CLOSES_P_TAGS: &[&str] = &[
+    "address", "article", "aside", "blockquote", "center", "details", "dialog", "dir", "div",
+    "dl", "fieldset", "figcaption", "figure", "footer", "header", "hgroup", "main", "menu", "nav",
+    "ol", "p", "search", "section", "summary", "ul", "pre", "listing", "form", "plaintext", "xmp",
+    "table", "hr",
+];
+
+/// End tags handled by "generate implied end tags, then pop until the same tag name"
+const BLOCK_END_TAGS: &[&str] = &[
+    "address", "article", "aside", "blockquote", "button", "center", "details", "dialog", "dir",
+    "div", "dl", "fieldset", "figcaption", "figure", "footer", "header", "hgroup", "listing",
+    "main", "menu", "nav", "ol", "pre", "search", "section", "summary", "ul", "form",
+];
+
+const HEADING_TAGS: &[&str] = &["h1", "h2", "h3", "h4", "h5", "h6"];
+
+/// Elements whose insertion point moves in front of the table while foster parenting
+const FOSTER_PARENT_TARGETS: &[&str] = &["table", "tbody", "tfoot", "thead", "tr"];
+
+/// Start tags that end a table section, row or cell implicitly
+const TABLE_STRUCTURE_START_TAGS: &[&str] = &[
+    "caption", "col", "colgroup", "tbody", "td", "tfoot", "th", "thead", "tr",
+];
+
+/// Elements whose contents are inserted directly, as in the "text" insertion mode
+const TEXT_CONTENT_TAGS: &[&str] = &[
+    "title", "textarea", "style", "script", "xmp", "iframe", "noembed", "noframes", "noscript",
+];
+
 impl HtmlParser {
     pub fn new(input: &str) -> Self {
         Self {
             tokenizer: Tokenizer::new(input),
             pending_text: String::new(),
-        }
-    }
-
-    /// Flush any pending text to the DOM
-    /// Only creates a text node if there's meaningful content (not just whitespace)
-    fn flush_pending_text(&mut self, dom: &mut Dom, parent: NodeId) {
-        if !self.pending_text.is_empty() {
-            // Only create text node if it has non-whitespace content
-            // OR if it's meaningful whitespace (single space between inline elements)
-            let trimmed = self.pending_text.trim();
-            if !trimmed.is_empty() {
-                tree_builder_log(&format!("Flushing text: {:?}", self.pending_text));
-                dom.create_text(&self.pending_text, Some(parent));
-            } else {
-                tree_builder_log(&format!("Skipping whitespace-only text: {:?}", self.pending_text));
-            }
-            self.pending_text.clear();
+            mode: InsertionMode::Initial,
+            document: 0,
+            open_elements: Vec::new(),
+            active_formatting: Vec::new(),
+            head: None,
+            foster_parenting: false,
+            ignore_newline: false,
         }
     }
 
     /// Convert attribute list from tokenizer format to DOM format
-    fn convert_attributes(attributes: &[super::tokenizer::Attribute]) -> Vec<(String, String)> {
+    fn convert_attributes(attributes: &[Attribute]) -> Vec<(String, String)> {
         attributes.iter().map(|a| (a.name.clone(), a.value.clone())).collect()
     }
 
+    /// Tokenizer state for elements parsed with the generic RCDATA or raw text algorithms
+    fn text_content_state(tag: &str) -> Option<TokenizerState> {
+        match tag {
+            "title" | "textarea" => Some(TokenizerState::RcData),
+            // The tokenizer has no script data states yet; raw text is the closest match
+            "style" | "script" | "xmp" | "iframe" | "noembed" | "noframes" => Some(TokenizerState::RawText),
+            _ => None,
+        }
+    }
+
     pub fn parse(mut self) -> Dom {
         let mut dom = Dom::new();
-        let document = dom.create_element("document", vec![], None);
-        let mut stack: Vec<NodeId> = vec![document];
-        let mut mode = InsertionMode::Initial;
-        let _fragment_context: Option<String> = None;
+        self.document = dom.create_element("document", vec![], None);
+        self.open_elements.push(self.document);
 
         while let Some(token) = self.tokenizer.next_token() {
-            tree_builder_log(&format!("Mode: {:?}, Token: {:?}", mode, token));
-            
             match &token {
                 Token::Eof => {
-                    // Flush any remaining text
-                    if let Some(&parent) = stack.last() {
-                        self.flush_pending_text(&mut dom, parent);
-                    }
+                    self.flush_pending_text(&mut dom);
                     break;
                 }
-                Token::Comment(_) => {
-                    // Flush text before comment
-                    if let Some(&parent) = stack.last() {
-                        self.flush_pending_text(&mut dom, parent);
-                    }
-                    // Append comment to current node (optional for now)
-                    continue;
-                }
-                Token::Doctype { .. } => {
-                    // Doctype only relevant in initial mode
-                    if mode == InsertionMode::Initial {
-                        mode = InsertionMode::BeforeHtml;
-                    }
-                }
                 Token::Character(c) => {
                     // Accumulate characters into pending_text
+                    if std::mem::take(&mut self.ignore_newline) && *c == '\n' {
+                        continue;
+                    }
                     self.pending_text.push(*c);
                     continue;
                 }
-                Token::StartTag { name, attributes, self_closing } => {
-                    // Flush pending text before processing tag
-                    if let Some(&parent) = stack.last() {
-                        self.flush_pending_text(&mut dom, parent);
-                    }
-                    
-                    let tag = name.to_lowercase();
-                    let attrs = Self::convert_attributes(attributes);
-
-                    // -------- INITIAL MODE --------
-                    if mode == InsertionMode::Initial {
-                        // Move directly to BeforeHtml without creating an element yet
-                        mode = InsertionMode::BeforeHtml;
-                    }
-
-                    // -------- BEFORE HTML --------
-                    if mode == InsertionMode::BeforeHtml {
-                        if tag == "html" {
-                            let html = dom.create_element("html", attrs.clone(), Some(document));
-                            stack.push(html);
-                            mode = InsertionMode::BeforeHead;
-                            continue;
-                        } else {
-                            // Auto-insert html element
-                            let html = dom.create_element("html", vec![], Some(document));
-                            stack.push(html);
-                            mode = InsertionMode::BeforeHead;
-                            // Fall through to process this tag in BeforeHead mode
+                Token::Comment(_) => {
+                    // Comments are not represented in the DOM
+                    self.flush_pending_text(&mut dom);
+                    continue;
+                }
+                Token::StartTag { name, self_closing, .. } => {
+                    self.flush_pending_text(&mut dom);
+                    // Elements whose contents are text switch the tokenizer out of
+                    // the data state (spec 13.2.6.2 generic RCDATA/raw text parsing)
+                    if !*self_closing {
+                        if let Some(state) = Self::text_content_state(&name.to_lowercase()) {
+                            self.tokenizer.set_state(state);
                         }
                     }
+                }
+                _ => self.flush_pending_text(&mut dom),
+            }
+            self.process_token(&mut dom, &token);
+            self.ignore_newline = matches!(&token, Token::StartTag { name, .. }
+                if ["pre", "listing", "textarea"].contains(&name.to_lowercase().as_str()));
+        }
 
-                    // -------- BEFORE HEAD / IN HEAD --------
-                    if mode == InsertionMode::BeforeHead || mode == InsertionMode::InHead {
-                        if tag == "head" && mode == InsertionMode::BeforeHead {
-                            if let Some(&parent) = stack.last() {
-                                let head = dom.create_element("head", attrs.clone(), Some(parent));
-                                stack.push(head);
-                                mode = InsertionMode::InHead;
-                            }
-                            continue;
-                        }
-                        
-                        if matches!(tag.as_str(), "meta" | "link" | "title" | "style" | "base") {
-                            if mode == InsertionMode::BeforeHead {
-                                if let Some(&parent) = stack.last() {
-                                    let head = dom.create_element("head", vec![], Some(parent));
-                                    stack.push(head);
-                                    mode = InsertionMode::InHead;
-                                }
-                            }
-                            if let Some(&parent) = stack.last() {
-                                let elem_id = dom.create_element(&tag, attrs.clone(), Some(parent));
-                                // Push non-self-closing elements to stack
-                                if !*self_closing && !matches!(tag.as_str(), "meta" | "link") {
-                                    stack.push(elem_id);
-                                }
-                            }
-                            if *self_closing || matches!(tag.as_str(), "meta" | "link") {
-                                // self-closing, don't push to stack
-                            }
-                            continue;
-                        }
+        dom
+    }
 
-                        if mode == InsertionMode::InHead && tag != "head" {
-                            stack.pop();
-                            mode = InsertionMode::AfterHead;
+    /// Dispatch a token to the current insertion mode, reprocessing it
+    /// whenever a mode asks for that (spec 13.2.6 "reprocess the token")
+    fn process_token(&mut self, dom: &mut Dom, token: &Token) {
+        loop {
+            tree_builder_log(&format!("Mode: {:?}, Token: {:?}", self.mode, token));
+            let reprocess = match self.mode {
+                InsertionMode::Initial => self.handle_initial(token),
+                InsertionMode::BeforeHtml => self.handle_before_html(dom, token),
+                InsertionMode::BeforeHead => self.handle_before_head(dom, token),
+                InsertionMode::InHead => self.handle_in_head(dom, token),
+                InsertionMode::AfterHead => self.handle_after_head(dom, token),
+                InsertionMode::InTable => self.handle_in_table(dom, token),
+                InsertionMode::InCaption => self.handle_in_caption(dom, token),
+                InsertionMode::InColumnGroup => self.handle_in_column_group(dom, token),
+                InsertionMode::InTableBody => self.handle_in_table_body(dom, token),
+                InsertionMode::InRow => self.handle_in_row(dom, token),
+                InsertionMode::InCell => self.handle_in_cell(dom, token),
+                InsertionMode::AfterBody | InsertionMode::AfterAfterBody => self.handle_after_body(dom, token),
+                _ => self.handle_in_body(dom, token),
+            };
+            if !reprocess {
+                break;
+            }
+        }
+    }
+
+    // ---------------------------------------------------------------------
+    // Text
+    // ---------------------------------------------------------------------
+
+    /// Flush buffered character tokens through the current insertion mode.
+    /// Whitespace-only runs are kept in body content, where they can separate
+    /// inline elements or be preserved by `white-space`, and dropped elsewhere.
+    fn flush_pending_text(&mut self, dom: &mut Dom) {
+        if self.pending_text.is_empty() {
+            return;
+        }
+        let text = std::mem::take(&mut self.pending_text);
+
+        // Contents of title/style/script/textarea etc. go straight into the element
+        if self.current_is(dom, TEXT_CONTENT_TAGS) {
+            self.insert_text(dom, &text);
+            return;
+        }
+
+        if text.trim().is_empty() {
+            if matches!(self.mode, InsertionMode::InBody | InsertionMode::InCell | InsertionMode::InCaption) {
+                self.reconstruct_active_formatting(dom);
+                self.insert_text(dom, &text);
+            } else {
+                tree_builder_log(&format!("Skipping whitespace-only text: {:?}", text));
+            }
+            return;
+        }
+
+        // Non-whitespace text in a column group closes it (spec 13.2.6.4.12)
+        if self.mode == InsertionMode::InColumnGroup {
+            if !self.current_is(dom, &["colgroup"]) {
+                return;
+            }
+            self.open_elements.pop();
+            self.mode = InsertionMode::InTable;
+        }
+
+        // 13.2.6.4.10 "in table text": non-whitespace text inside table structure
+        // is a parse error and gets foster parented in front of the table
+        if matches!(self.mode, InsertionMode::InTable | InsertionMode::InTableBody | InsertionMode::InRow) {
+            tree_builder_log(&format!("Foster parenting text: {:?}", text));
+            self.foster_parenting = true;
+            self.reconstruct_active_formatting(dom);
+            self.insert_text(dom, &text);
+            self.foster_parenting = false;
+            return;
+        }
+
+        // Non-whitespace text before <body> implies the missing html/head/body elements
+        loop {
+            match self.mode {
+                InsertionMode::Initial | InsertionMode::BeforeHtml => {
+                    self.insert_html_root(dom, vec![]);
+                }
+                InsertionMode::BeforeHead => {
+                    self.insert_head(dom, vec![]);
+                }
+                InsertionMode::InHead => {
+                    self.open_elements.pop();
+                    self.mode = InsertionMode::AfterHead;
+                }
+                InsertionMode::AfterHead => {
+                    self.insert_element(dom, "body", vec![]);
+                    self.mode = InsertionMode::InBody;
+                }
+                InsertionMode::AfterBody | InsertionMode::AfterAfterBody => {
+                    self.mode = InsertionMode::InBody;
+                }
+                _ => break,
+            }
+        }
+
+        tree_builder_log(&format!("Flushing text: {:?}", text));
+        self.reconstruct_active_formatting(dom);
+        self.insert_text(dom, &text);
+    }
+
+    /// Insert text at the appropriate place, merging with an adjacent text node
+    fn insert_text(&mut self, dom: &mut Dom, text: &str) {
+        let (parent, before) = self.appropriate_place(dom, None);
+        let siblings = &dom.nodes[parent].children;
+        let previous = match before {
+            Some(reference) => siblings
+                .iter()
+                .position(|&c| c == reference)
+                .and_then(|pos| pos.checked_sub(1))
+                .map(|pos| siblings[pos]),
+            None => siblings.last().copied(),
+        };
+        if let Some(previous) = previous {
+            if let NodeType::Text(existing) = &mut dom.nodes[previous].node_type {
+                existing.push_str(text);
+                return;
+            }
+        }
+        let id = dom.create_text(text, None);
+        match before {
+            Some(reference) => dom.insert_before(parent, id, reference),
+            None => dom.append_child(parent, id),
+        }
+    }
+
+    // ---------------------------------------------------------------------
+    // Insertion modes before <body>
+    // ---------------------------------------------------------------------
+
+    /// 13.2.6.4.1 The "initial" insertion mode
+    fn handle_initial(&mut self, token: &Token) -> bool {
+        self.mode = InsertionMode::BeforeHtml;
+        !matches!(token, Token::Doctype { .. })
+    }
+
+    /// 13.2.6.4.2 The "before html" insertion mode
+    fn handle_before_html(&mut self, dom: &mut Dom, token: &Token) -> bool {
+        match token {
+            Token::Doctype { .. } => false,
+            Token::StartTag { name, attributes, .. } if name.eq_ignore_ascii_case("html") => {
+                self.insert_html_root(dom, Self::convert_attributes(attributes));
+                false
+            }
+            Token::EndTag { name } if !is_one_of(name, &["head", "body", "html", "br"]) => false,
+            _ => {
+                self.insert_html_root(dom, vec![]);
+                true
+            }
+        }
+    }
+
+    /// 13.2.6.4.3 The "before head" insertion mode
+    fn handle_before_head(&mut self, dom: &mut Dom, token: &Token) -> bool {
+        match token {
+            Token::Doctype { .. } => false,
+            Token::StartTag { name, attributes, .. } => {
+                let tag = name.to_lowercase();
+                if tag == "html" {
+                    return self.handle_in_body(dom, token);
+                }
+                if tag == "head" {
+                    self.insert_head(dom, Self::convert_attributes(attributes));
+                    return false;
+                }
+                self.insert_head(dom, vec![]);
+                true
+            }
+            Token::EndTag { name } if !is_one_of(name, &["head", "body", "html", "br"]) => false,
+            _ => {
+                self.insert_head(dom, vec![]);
+                true
+            }
+        }
+    }
+
+    /// 13.2.6.4.4 The "in head" insertion mode
+    fn handle_in_head(&mut self, dom: &mut Dom, token: &Token) -> bool {
+        match token {
+            Token::Doctype { .. } => false,
+            Token::StartTag { name, attributes, .. } => {
+                let tag = name.to_lowercase();
+                let attrs = Self::convert_attributes(attributes);
+                match tag.as_str() {
+                    "html" => self.handle_in_body(dom, token),
+                    "base" | "basefont" | "bgsound" | "link" | "meta" => {
+                        self.insert_element(dom, &tag, attrs);
+                        self.open_elements.pop();
+                        false
+                    }
+                    "title" | "style" | "script" | "noscript" | "noframes" | "template" => {
+                        self.insert_element(dom, &tag, attrs);
+                        false
+                    }
+                    "head" => false,
+                    _ => {
+                        self.pop_head();
+                        true
+                    }
+                }
+            }
+            Token::EndTag { name } => {
+                let tag = name.to_lowercase();
+                match tag.as_str() {
+                    "head" => {
+                        self.pop_head();
+                        false
+                    }
+                    "body" | "html" | "br" => {
+                        self.pop_head();
+                        true
+                    }
+                    _ => {
+                        // Closes title/style/script etc.; other end tags are ignored
+                        if self.current_is(dom, &[tag.as_str()]) {
+                            self.open_elements.pop();
                         }
+                        false
                     }
+                }
+            }
+            _ => {
+                self.pop_head();
+                true
+            }
+        }
+    }
 
-                    // -------- AFTER HEAD / IN BODY --------
-                    if mode == InsertionMode::AfterHead || mode == InsertionMode::InBody {
-                        if mode == InsertionMode::AfterHead {
-                            if let Some(&parent) = stack.last() {
-                                let body = dom.create_element("body", vec![], Some(parent));
-                                stack.push(body);
-                                mode = InsertionMode::InBody;
-                            }
-                            // After transitioning to InBody, if this tag is body, skip creating another one
-                            if tag == "body" {
-                                continue;
+    /// 13.2.6.4.6 The "after head" insertion mode
+    fn handle_after_head(&mut self, dom: &mut Dom, token: &Token) -> bool {
+        match token {
+            Token::Doctype { .. } => false,
+            Token::StartTag { name, attributes, .. } => {
+                let tag = name.to_lowercase();
+                match tag.as_str() {
+                    "html" => self.handle_in_body(dom, token),
+                    "body" => {
+                        self.insert_element(dom, "body", Self::convert_attributes(attributes));
+                        self.mode = InsertionMode::InBody;
+                        false
+                    }
+                    "base" | "basefont" | "bgsound" | "link" | "meta" | "noframes" | "script"
+                    | "style" | "template" | "title" => {
+                        // Parse error: reopen head for the duration of this token
+                        if let Some(head) = self.head {
+                            self.open_elements.push(head);
+                            self.handle_in_head(dom, token);
+                            if let Some(pos) = self.open_elements.iter().rposition(|&n| n == head) {
+                                self.open_elements.remove(pos);
                             }
                         }
+                        false
+                    }
+                    "head" => false,
+                    _ => {
+                        self.insert_element(dom, "body", vec![]);
+                        self.mode = InsertionMode::InBody;
+                        true
+                    }
+                }
+            }
+            Token::EndTag { name } if !is_one_of(name, &["body", "html", "br"]) => false,
+            _ => {
+                self.insert_element(dom, "body", vec![]);
+                self.mode = InsertionMode::InBody;
+                true
+            }
+        }
+    }
 
-                        // Handle auto-closing tags (like <p>, <li>, etc.)
-                        if AUTO_CLOSING_TAGS.contains(&tag.as_str()) {
-                            // Close any open tags of the same type by popping them
-                            while let Some(&last) = stack.last() {
-                                if last == document {
-                                    break;
-                                }
-                                if let crate::dom::NodeType::Element(el) = &dom.nodes[last].node_type {
-                                    if el.tag_name.to_lowercase() == tag {
-                                        // Found open tag of same type, close it and stop
-                                        stack.pop();
-                                        break;
-                                    }
-                                    // Don't pop past body, html, document
-                                    if matches!(el.tag_name.as_str(), "body" | "html" | "document") {
-                                        break;
-                                    }
-                                    // Don't pop past list containers when handling li
-                                    if tag == "li" && matches!(el.tag_name.as_str(), "ul" | "ol") {
-                                        break;
-                                    }
-                                    // Don't pop past definition lists when handling dt/dd
-                                    if matches!(tag.as_str(), "dt" | "dd") && el.tag_name == "dl" {
-                                        break;
-                                    }
-                                }
-                                stack.pop();
-                            }
-                        }
+    /// 13.2.6.4.20 / 13.2.6.4.23 The "after body" and "after after body" insertion modes
+    fn handle_after_body(&mut self, dom: &mut Dom, token: &Token) -> bool {
+        match token {
+            Token::Doctype { .. } => false,
+            Token::StartTag { name, .. } if name.eq_ignore_ascii_case("html") => self.handle_in_body(dom, token),
+            Token::EndTag { name } if name.eq_ignore_ascii_case("html") => {
+                self.mode = InsertionMode::AfterAfterBody;
+                false
+            }
+            _ => {
+                // Parse error: content after </body> goes back into the body
+                self.mode = InsertionMode::InBody;
+                true
+            }
+        }
+    }
 
-                        if let Some(&parent) = stack.last() {
-                            let id = dom.create_element(&tag, attrs.clone(), Some(parent));
-                            
-                            // Check if it's a void element that shouldn't be pushed to stack
-                            let is_void = VOID_ELEMENTS.contains(&tag.as_str());
-                            
-                            if !*self_closing && !is_void {
-                                stack.push(id);
-                            }
+    fn insert_html_root(&mut self, dom: &mut Dom, attrs: Vec<(String, String)>) {
+        self.insert_element(dom, "html", attrs);
+        self.mode = InsertionMode::BeforeHead;
+    }
+
+    fn insert_head(&mut self, dom: &mut Dom, attrs: Vec<(String, String)>) {
+        let head = self.insert_element(dom, "head", attrs);
+        self.head = Some(head);
+        self.mode = InsertionMode::InHead;
+    }
+
+    /// Pop the head element off the stack and switch to "after head"
+    fn pop_head(&mut self) {
+        if let Some(head) = self.head {
+            if let Some(pos) = self.open_elements.iter().rposition(|&n| n == head) {
+                self.open_elements.truncate(pos);
+            }
+        }
+        self.mode = InsertionMode::AfterHead;
+    }
+
+    // ---------------------------------------------------------------------
+    // 13.2.6.4.7 The "in body" insertion mode
+    // ---------------------------------------------------------------------
+
+    fn handle_in_body(&mut self, dom: &mut Dom, token: &Token) -> bool {
+        match token {
+            Token::StartTag { name, attributes, self_closing } => {
+                let tag = name.to_lowercase();
+                let attrs = Self::convert_attributes(attributes);
+                self.in_body_start_tag(dom, &tag, attrs, *self_closing);
+                false
+            }
+            Token::EndTag { name } => self.in_body_end_tag(dom, &name.to_lowercase()),
+            _ => false,
+        }
+    }
+
+    fn in_body_start_tag(&mut self, dom: &mut Dom, tag: &str, attrs: Vec<(String, String)>, self_closing: bool) {
+        match tag {
+            "html" => {
+                if let Some(&html) = self.open_elements.get(1) {
+                    merge_attributes(dom, html, attrs);
+                }
+            }
+            "body" => {
+                if let Some(&body) = self.open_elements.get(2) {
+                    if dom.tag_name(body) == Some("body") {
+                        merge_attributes(dom, body, attrs);
+                    }
+                }
+            }
+            "base" | "basefont" | "bgsound" | "link" | "meta" => {
+                self.insert_element(dom, tag, attrs);
+                self.open_elements.pop();
+            }
+            "title" | "style" | "script" | "noframes" | "template" | "textarea" | "iframe" | "noembed" => {
+                self.insert_element(dom, tag, attrs);
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.close_p_if_in_button_scope(dom);
+                if self.current_is(dom, HEADING_TAGS) {
+                    self.open_elements.pop();
+                }
+                self.insert_element(dom, tag, attrs);
+            }
+            "li" => {
+                self.close_list_item(dom, &["li"]);
+                self.insert_element(dom, tag, attrs);
+            }
+            "dd" | "dt" => {
+                self.close_list_item(dom, &["dd", "dt"]);
+                self.insert_element(dom, tag, attrs);
+            }
+            "button" => {
+                if self.has_in_scope(dom, &["button"], Scope::Default) {
+                    self.generate_implied_end_tags(dom, None);
+                    self.pop_until(dom, &["button"]);
+                }
+                self.reconstruct_active_formatting(dom);
+                self.insert_element(dom, tag, attrs);
+            }
+            "a" => {
+                if let Some(existing) = self.formatting_element_after_marker(dom, "a") {
+                    self.adoption_agency(dom, "a");
+                    self.remove_from_active_formatting(existing);
+                    self.open_elements.retain(|&n| n != existing);
+                }
+                self.reconstruct_active_formatting(dom);
+                let id = self.insert_element(dom, tag, attrs);
+                self.push_active_formatting(dom, id);
+            }
+            "nobr" => {
+                self.reconstruct_active_formatting(dom);
+                if self.has_in_scope(dom, &["nobr"], Scope::Default) {
+                    self.adoption_agency(dom, "nobr");
+                    self.reconstruct_active_formatting(dom);
+                }
+                let id = self.insert_element(dom, tag, attrs);
+                self.push_active_formatting(dom, id);
+            }
+            _ if FORMATTING_TAGS.contains(&tag) => {
+                self.reconstruct_active_formatting(dom);
+                let id = self.insert_element(dom, tag, attrs);
+                self.push_active_formatting(dom, id);
+            }
+            "applet" | "marquee" | "object" => {
+                self.reconstruct_active_formatting(dom);
+                self.insert_element(dom, tag, attrs);
+                self.active_formatting.push(FormattingEntry::Marker);
+            }
+            "hr" => {
+                self.close_p_if_in_button_scope(dom);
+                self.insert_element(dom, tag, attrs);
+                self.open_elements.pop();
+            }
+            "xmp" => {
+                self.close_p_if_in_button_scope(dom);
+                self.reconstruct_active_formatting(dom);
+                self.insert_element(dom, tag, attrs);
+            }
+            "table" => {
+                self.close_p_if_in_button_scope(dom);
+                self.insert_element(dom, tag, attrs);
+                self.mode = InsertionMode::InTable;
+            }
+            _ if CLOSES_P_TAGS.contains(&tag) => {
+                self.close_p_if_in_button_scope(dom);
+                self.insert_element(dom, tag, attrs);
+            }
+            "area" | "br" | "embed" | "img" | "keygen" | "wbr" | "input" | "image" => {
+                let tag = if tag == "image" { "img" } else { tag };
+                self.reconstruct_active_formatting(dom);
+                self.insert_element(dom, tag, attrs);
+                self.open_elements.pop();
+            }
+            "param" | "source" | "track" => {
+                self.insert_element(dom, tag, attrs);
+                self.open_elements.pop();
+            }
+            "optgroup" | "option" => {
+                if self.current_is(dom, &["option"]) {
+                    self.open_elements.pop();
+                }
+                self.reconstruct_active_formatting(dom);
+                self.insert_element(dom, tag, attrs);
+            }
+            "head" | "frame" | "frameset" | "caption" | "col" | "colgroup" | "tbody" | "td" | "tfoot"
+            | "th" | "thead" | "tr" => {
+                // Parse error; ignored outside of the table insertion modes
+            }
+            _ => {
+                self.reconstruct_active_formatting(dom);
+                self.insert_element(dom, tag, attrs);
+                let foreign = matches!(tag, "svg" | "math") || self.in_foreign_content(dom);
+                if VOID_ELEMENTS.contains(&tag) || (self_closing && foreign) {
+                    self.open_elements.pop();
+                }
+            }
+        }
+    }
+
+    /// Returns true if the token should be reprocessed in the new insertion mode
+    fn in_body_end_tag(&mut self, dom: &mut Dom, tag: &str) -> bool {
+        match tag {
+            "body" | "html" => {
+                if !self.has_in_scope(dom, &["body"], Scope::Default) {
+                    return false;
+                }
+                self.mode = InsertionMode::AfterBody;
+                tag == "html"
+            }
+            "p" => {
+                if !self.has_in_scope(dom, &["p"], Scope::Button) {
+                    // Parse error: </p> without an open p inserts an empty one
+                    self.insert_element(dom, "p", vec![]);
+                }
+                self.close_p_element(dom);
+                false
+            }
+            "li" => {
+                if self.has_in_scope(dom, &["li"], Scope::ListItem) {
+                    self.generate_implied_end_tags(dom, Some("li"));
+                    self.pop_until(dom, &["li"]);
+                }
+                false
+            }
+            "dd" | "dt" => {
+                if self.has_in_scope(dom, &[tag], Scope::Default) {
+                    self.generate_implied_end_tags(dom, Some(tag));
+                    self.pop_until(dom, &[tag]);
+                }
+                false
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                if self.has_in_scope(dom, HEADING_TAGS, Scope::Default) {
+                    self.generate_implied_end_tags(dom, None);
+                    self.pop_until(dom, HEADING_TAGS);
+                }
+                false
+            }
+            "applet" | "marquee" | "object" => {
+                if self.has_in_scope(dom, &[tag], Scope::Default) {
+                    self.generate_implied_end_tags(dom, None);
+                    self.pop_until(dom, &[tag]);
+                    self.clear_active_formatting_to_last_marker();
+                }
+                false
+            }
+            "br" => {
+                // Parse error: treated as <br>
+                self.reconstruct_active_formatting(dom);
+                self.insert_element(dom, "br", vec![]);
+                self.open_elements.pop();
+                false
+            }
+            _ if FORMATTING_TAGS.contains(&tag) => {
+                self.adoption_agency(dom, tag);
+                false
+            }
+            _ if BLOCK_END_TAGS.contains(&tag) => {
+                if self.has_in_scope(dom, &[tag], Scope::Default) {
+                    self.generate_implied_end_tags(dom, None);
+                    self.pop_until(dom, &[tag]);
+                }
+                false
+            }
+            _ => {
+                self.any_other_end_tag(dom, tag);
+                false
+            }
+        }
+    }
+
+    /// "Any other end tag" steps of the in body insertion mode
+    fn any_other_end_tag(&mut self, dom: &mut Dom, tag: &str) {
+        for index in (0..self.open_elements.len()).rev() {
+            let node = self.open_elements[index];
+            if dom.tag_name(node) == Some(tag) {
+                self.generate_implied_end_tags(dom, Some(tag));
+                self.open_elements.truncate(index);
+                return;
+            }
+            if is_special(dom, node) {
+                // Parse error; ignore the token
+                return;
+            }
+        }
+    }
+
+    /// Shared steps for <li>, <dd> and <dt> start tags
+    fn close_list_item(&mut self, dom: &mut Dom, names: &[&str]) {
+        for index in (0..self.open_elements.len()).rev() {
+            let node = self.open_elements[index];
+            let node_name = dom.tag_name(node).unwrap_or("");
+            if names.contains(&node_name) {
+                let node_name = node_name.to_string();
+                self.generate_implied_end_tags(dom, Some(&node_name));
+                self.pop_until(dom, &[node_name.as_str()]);
+                break;
+            }
+            if is_special(dom, node) && !matches!(node_name, "address" | "div" | "p") {
+                break;
+            }
+        }
+        self.close_p_if_in_button_scope(dom);
+    }
+
+    // ---------------------------------------------------------------------
+    // Table insertion modes (spec 13.2.6.4.9 - 13.2.6.4.15)
+    // ---------------------------------------------------------------------
+
+    /// 13.2.6.4.9 The "in table" insertion mode
+    fn handle_in_table(&mut self, dom: &mut Dom, token: &Token) -> bool {
+        match token {
+            Token::Doctype { .. } => false,
+            Token::StartTag { name, attributes, .. } => {
+                let tag = name.to_lowercase();
+                let attrs = Self::convert_attributes(attributes);
+                match tag.as_str() {
+                    "caption" => {
+                        self.clear_stack_back_to(dom, &["table", "template", "html"]);
+                        self.active_formatting.push(FormattingEntry::Marker);
+                        self.insert_element(dom, "caption", attrs);
+                        self.mode = InsertionMode::InCaption;
+                        false
+                    }
+                    "colgroup" => {
+                        self.clear_stack_back_to(dom, &["table", "template", "html"]);
+                        self.insert_element(dom, "colgroup", attrs);
+                        self.mode = InsertionMode::InColumnGroup;
+                        false
+                    }
+                    "col" => {
+                        self.clear_stack_back_to(dom, &["table", "template", "html"]);
+                        self.insert_element(dom, "colgroup", vec![]);
+                        self.mode = InsertionMode::InColumnGroup;
+                        true
+                    }
+                    "tbody" | "tfoot" | "thead" => {
+                        self.clear_stack_back_to(dom, &["table", "template", "html"]);
+                        self.insert_element(dom, &tag, attrs);
+                        self.mode = InsertionMode::InTableBody;
+                        false
+                    }
+                    "td" | "th" | "tr" => {
+                        // Implied <tbody>
+                        self.clear_stack_back_to(dom, &["table", "template", "html"]);
+                        self.insert_element(dom, "tbody", vec![]);
+                        self.mode = InsertionMode::InTableBody;
+                        true
+                    }
+                    "table" => {
+                        // Parse error: a nested <table> start tag closes the open table
+                        if !self.has_in_scope(dom, &["table"], Scope::Table) {
+                            return false;
                         }
+                        self.pop_until(dom, &["table"]);
+                        self.reset_insertion_mode(dom);
+                        true
                     }
+                    "style" | "script" | "template" => self.handle_in_head(dom, token),
+                    "input" if attrs.iter().any(|(k, v)| k == "type" && v.eq_ignore_ascii_case("hidden")) => {
+                        self.insert_element(dom, &tag, attrs);
+                        self.open_elements.pop();
+                        false
+                    }
+                    "form" => {
+                        self.insert_element(dom, &tag, attrs);
+                        self.open_elements.pop();
+                        false
+                    }
+                    _ => self.in_table_anything_else(dom, token),
                 }
+            }
+            Token::EndTag { name } => {
+                let tag = name.to_lowercase();
+                match tag.as_str() {
+                    "table" => {
+                        if self.has_in_scope(dom, &["table"], Scope::Table) {
+                            self.pop_until(dom, &["table"]);
+                            self.reset_insertion_mode(dom);
+                        }
+                        false
+                    }
+                    "body" | "caption" | "col" | "colgroup" | "html" | "tbody" | "td" | "tfoot"
+                    | "th" | "thead" | "tr" => false,
+                    "template" => self.handle_in_head(dom, token),
+                    _ => self.in_table_anything_else(dom, token),
+                }
+            }
+            _ => self.in_table_anything_else(dom, token),
+        }
+    }
 
-                Token::EndTag { name } => {
-                    // Flush pending text before processing end tag
-                    if let Some(&parent) = stack.last() {
-                        self.flush_pending_text(&mut dom, parent);
-                    }
-                    
-                    let tag = name.to_lowercase();
-
-                    // Special handling for head-related elements
-                    if mode == InsertionMode::InHead {
-                        if tag == "head" {
-                            stack.pop(); // Pop head element
-                            mode = InsertionMode::AfterHead;
-                            continue;
-                        } else if matches!(tag.as_str(), "title" | "meta" | "link" | "style" | "base") {
-                            // Pop the element if it matches
-                            if let Some(&last) = stack.last() {
-                                if let crate::dom::NodeType::Element(el) = &dom.nodes[last].node_type {
-                                    if el.tag_name.to_lowercase() == tag {
-                                        stack.pop();
-                                    }
-                                }
-                            }
-                            continue;
-                        } else {
-                            // Unrecognized tag in head mode - exit head mode
-                            stack.pop(); // Pop head
-                            mode = InsertionMode::AfterHead;
-                            // Reprocess this end tag in AfterHead mode
+    /// "Anything else" in table: process with the in body rules while foster parenting
+    fn in_table_anything_else(&mut self, dom: &mut Dom, token: &Token) -> bool {
+        self.foster_parenting = true;
+        let reprocess = self.handle_in_body(dom, token);
+        self.foster_parenting = false;
+        reprocess
+    }
+
+    /// 13.2.6.4.11 The "in caption" insertion mode
+    fn handle_in_caption(&mut self, dom: &mut Dom, token: &Token) -> bool {
+        match token {
+            Token::StartTag { name, .. } if is_one_of(name, TABLE_STRUCTURE_START_TAGS) => {
+                self.close_caption(dom)
+            }
+            Token::EndTag { name } => {
+                let tag = name.to_lowercase();
+                match tag.as_str() {
+                    "caption" => {
+                        self.close_caption(dom);
+                        false
+                    }
+                    "table" => self.close_caption(dom),
+                    "body" | "col" | "colgroup" | "html" | "tbody" | "td" | "tfoot" | "th" | "thead"
+                    | "tr" => false,
+                    _ => self.handle_in_body(dom, token),
+                }
+            }
+            _ => self.handle_in_body(dom, token),
+        }
+    }
+
+    /// Close an open caption and return to "in table". Returns false if there
+    /// was no caption in table scope, in which case the token is ignored.
+    fn close_caption(&mut self, dom: &mut Dom) -> bool {
+        if !self.has_in_scope(dom, &["caption"], Scope::Table) {
+            return false;
+        }
+        self.generate_implied_end_tags(dom, None);
+        self.pop_until(dom, &["caption"]);
+        self.clear_active_formatting_to_last_marker();
+        self.mode = InsertionMode::InTable;
+        true
+    }
+
+    /// 13.2.6.4.12 The "in column group" insertion mode
+    fn handle_in_column_group(&mut self, dom: &mut Dom, token: &Token) -> bool {
+        match token {
+            Token::Doctype { .. } => false,
+            Token::StartTag { name, attributes, .. } => {
+                let tag = name.to_lowercase();
+                match tag.as_str() {
+                    "html" => self.handle_in_body(dom, token),
+                    "col" => {
+                        self.insert_element(dom, "col", Self::convert_attributes(attributes));
+                        self.open_elements.pop();
+                        false
+                    }
+                    "template" => self.handle_in_head(dom, token),
+                    _ => self.close_column_group(dom),
+                }
+            }
+            Token::EndTag { name } => {
+                let tag = name.to_lowercase();
+                match tag.as_str() {
+                    "colgroup" => {
+                        self.close_column_group(dom);
+                        false
+                    }
+                    "col" => false,
+                    "template" => self.handle_in_head(dom, token),
+                    _ => self.close_column_group(dom),
+                }
+            }
+            _ => self.close_column_group(dom),
+        }
+    }
+
+    fn close_column_group(&mut self, dom: &Dom) -> bool {
+        if !self.current_is(dom, &["colgroup"]) {
+            return false;
+        }
+        self.open_elements.pop();
+        self.mode = InsertionMode::InTable;
+        true
+    }
+
+    /// 13.2.6.4.13 The "in table body" insertion mode
+    fn handle_in_table_body(&mut self, dom: &mut Dom, token: &Token) -> bool {
+        const TABLE_BODY_CONTEXT: &[&str] = &["tbody", "tfoot", "thead", "template", "html"];
+        match token {
+            Token::StartTag { name, attributes, .. } => {
+                let tag = name.to_lowercase();
+                match tag.as_str() {
+                    "tr" => {
+                        self.clear_stack_back_to(dom, TABLE_BODY_CONTEXT);
+                        self.insert_element(dom, "tr", Self::convert_attributes(attributes));
+                        self.mode = InsertionMode::InRow;
+                        false
+                    }
+                    "th" | "td" => {
+                        // Parse error: implied <tr>
+                        self.clear_stack_back_to(dom, TABLE_BODY_CONTEXT);
+                        self.insert_element(dom, "tr", vec![]);
+                        self.mode = InsertionMode::InRow;
+                        true
+                    }
+                    "caption" | "col" | "colgroup" | "tbody" | "tfoot" | "thead" => self.close_table_body(dom),
+                    _ => self.handle_in_table(dom, token),
+                }
+            }
+            Token::EndTag { name } => {
+                let tag = name.to_lowercase();
+                match tag.as_str() {
+                    "tbody" | "tfoot" | "thead" => {
+                        if self.has_in_scope(dom, &[tag.as_str()], Scope::Table) {
+                            self.clear_stack_back_to(dom, TABLE_BODY_CONTEXT);
+                            self.open_elements.pop();
+                            self.mode = InsertionMode::InTable;
                         }
+                        false
                     }
+                    "table" => self.close_table_body(dom),
+                    "body" | "caption" | "col" | "colgroup" | "html" | "td" | "th" | "tr" => false,
+                    _ => self.handle_in_table(dom, token),
+                }
+            }
+            _ => self.handle_in_table(dom, token),
+        }
+    }
 
-                    // Before closing an element, auto-close any open auto-closing tags (like <p>)
-                    if mode == InsertionMode::InBody && !matches!(tag.as_str(), "p" | "li" | "dd" | "dt" | "option" | "optgroup" | "tr" | "td" | "th") {
-                        // We're closing a non-auto-closing tag, so close any open auto-closing tags first
-                        while let Some(&last) = stack.last() {
-                            if last == document {
-                                break;
-                            }
-                            if let crate::dom::NodeType::Element(el) = &dom.nodes[last].node_type {
-                                if matches!(el.tag_name.as_str(), "p" | "li" | "dd" | "dt" | "option" | "optgroup" | "tr" | "td" | "th") {
-                                    // Found an auto-closing tag - check if we should close it
-                                    if el.tag_name.to_lowercase() == tag {
-                                        // This is the tag we're trying to close, stop here
-                                        break;
-                                    }
-                                    // This is an auto-closing tag that's in the way - close it
-                                    stack.pop();
-                                } else {
-                                    // Not an auto-closing tag
-                                    break;
-                                }
-                            } else {
-                                break;
-                            }
+    /// Close the current table section and return to "in table". Returns false
+    /// (ignore the token) if no section is open in table scope.
+    fn close_table_body(&mut self, dom: &mut Dom) -> bool {
+        if !self.has_in_scope(dom, &["tbody", "thead", "tfoot"], Scope::Table) {
+            return false;
+        }
+        self.clear_stack_back_to(dom, &["tbody", "tfoot", "thead", "template", "html"]);
+        self.open_elements.pop();
+        self.mode = InsertionMode::InTable;
+        true
+    }
+
+    /// 13.2.6.4.14 The "in row" insertion mode
+    fn handle_in_row(&mut self, dom: &mut Dom, token: &Token) -> bool {
+        match token {
+            Token::StartTag { name, attributes, .. } => {
+                let tag = name.to_lowercase();
+                match tag.as_str() {
+                    "th" | "td" => {
+                        self.clear_stack_back_to(dom, &["tr", "template", "html"]);
+                        self.insert_element(dom, &tag, Self::convert_attributes(attributes));
+                        self.mode = InsertionMode::InCell;
+                        self.active_formatting.push(FormattingEntry::Marker);
+                        false
+                    }
+                    "caption" | "col" | "colgroup" | "tbody" | "tfoot" | "thead" | "tr" => self.close_row(dom),
+                    _ => self.handle_in_table(dom, token),
+                }
+            }
+            Token::EndTag { name } => {
+                let tag = name.to_lowercase();
+                match tag.as_str() {
+                    "tr" => {
+                        self.close_row(dom);
+                        false
+                    }
+                    "table" => self.close_row(dom),
+                    "tbody" | "tfoot" | "thead" => {
+                        if !self.has_in_scope(dom, &[tag.as_str()], Scope::Table) {
+                            return false;
                         }
+                        self.close_row(dom)
                     }
+                    "body" | "caption" | "col" | "colgroup" | "html" | "td" | "th" => false,
+                    _ => self.handle_in_table(dom, token),
+                }
+            }
+            _ => self.handle_in_table(dom, token),
+        }
+    }
+
+    /// Close the current row and return to "in table body". Returns false
+    /// (ignore the token) if no row is open in table scope.
+    fn close_row(&mut self, dom: &mut Dom) -> bool {
+        if !self.has_in_scope(dom, &["tr"], Scope::Table) {
+            return false;
+        }
+        self.clear_stack_back_to(dom, &["tr", "template", "html"]);
+        self.open_elements.pop();
+        self.mode = InsertionMode::InTableBody;
+        true
+    }
 
-                    // Close elements until we find matching opening tag
-                    while let Some(&last) = stack.last() {
-                        if last == document {
-                            break;
+    /// 13.2.6.4.15 The "in cell" insertion mode
+    fn handle_in_cell(&mut self, dom: &mut Dom, token: &Token) -> bool {
+        match token {
+            Token::StartTag { name, .. } if is_one_of(name, TABLE_STRUCTURE_START_TAGS) => {
+                if !self.has_in_scope(dom, &["td", "th"], Scope::Table) {
+                    return false;
+                }
+                self.close_cell(dom);
+                true
+            }
+            Token::EndTag { name } => {
+                let tag = name.to_lowercase();
+                match tag.as_str() {
+                    "td" | "th" => {
+                        if self.has_in_scope(dom, &[tag.as_str()], Scope::Table) {
+                            self.generate_implied_end_tags(dom, None);
+                            self.pop_until(dom, &[tag.as_str()]);
+                            self.clear_active_formatting_to_last_marker();
+                            self.mode = InsertionMode::InRow;
+                        }
+                        false
+                    }
+                    "body" | "caption" | "col" | "colgroup" | "html" => false,
+                    "table" | "tbody" | "tfoot" | "thead" | "tr" => {
+                        if !self.has_in_scope(dom, &[tag.as_str()], Scope::Table) {
+                            return false;
                         }
+                        self.close_cell(dom);
+                        true
+                    }
+                    _ => self.handle_in_body(dom, token),
+                }
+            }
+            _ => self.handle_in_body(dom, token),
+        }
+    }
 
-                        if let crate::dom::NodeType::Element(el) = &dom.nodes[last].node_type {
-                            if el.tag_name.to_lowercase() == tag {
-                                stack.pop();
-                                break;
-                            }
+    fn close_cell(&mut self, dom: &Dom) {
+        self.generate_implied_end_tags(dom, None);
+        self.pop_until(dom, &["td", "th"]);
+        self.clear_active_formatting_to_last_marker();
+        self.mode = InsertionMode::InRow;
+    }
 
-                            // Mismatch: don't pop scope-limiting elements
-                            if matches!(el.tag_name.as_str(), "body" | "html" | "document") {
-                                // Can't close past body/html/document
-                                break;
-                            }
+    /// Pop elements until the current node is one of `context`
+    /// ("clear the stack back to a table/table body/table row context")
+    fn clear_stack_back_to(&mut self, dom: &Dom, context: &[&str]) {
+        while self.open_elements.len() > 1 && !self.current_is(dom, context) {
+            self.open_elements.pop();
+        }
+    }
 
-                            // For other mismatches, pop the element
-                            stack.pop();
-                        } else {
-                            stack.pop();
-                        }
+    /// Reset the insertion mode appropriately (spec 13.2.4.1)
+    fn reset_insertion_mode(&mut self, dom: &Dom) {
+        for (index, &node) in self.open_elements.iter().enumerate().rev() {
+            let last = index <= 1;
+            self.mode = match dom.tag_name(node).unwrap_or("") {
+                "td" | "th" if !last => InsertionMode::InCell,
+                "tr" => InsertionMode::InRow,
+                "tbody" | "thead" | "tfoot" => InsertionMode::InTableBody,
+                "caption" => InsertionMode::InCaption,
+                "colgroup" => InsertionMode::InColumnGroup,
+                "table" => InsertionMode::InTable,
+                "head" if !last => InsertionMode::InHead,
+                "body" => InsertionMode::InBody,
+                "html" if self.head.is_none() => InsertionMode::BeforeHead,
+                "html" => InsertionMode::AfterHead,
+                _ if last => InsertionMode::InBody,
+                _ => continue,
+            };
+            return;
+        }
+        self.mode = InsertionMode::InBody;
+    }
+
+    // ---------------------------------------------------------------------
+    // Stack of open elements (spec 13.2.4.2)
+    // ---------------------------------------------------------------------
+
+    fn current_node(&self) -> NodeId {
+        *self.open_elements.last().unwrap_or(&self.document)
+    }
+
+    fn current_is(&self, dom: &Dom, names: &[&str]) -> bool {
+        dom.tag_name(self.current_node()).is_some_and(|name| names.contains(&name))
+    }
+
+    /// Create an element at the appropriate place for inserting a node and push it
+    fn insert_element(&mut self, dom: &mut Dom, tag: &str, attrs: Vec<(String, String)>) -> NodeId {
+        let id = dom.create_element(tag, attrs, None);
+        self.insert_at_appropriate_place(dom, id, None);
+        self.open_elements.push(id);
+        id
+    }
+
+    fn insert_at_appropriate_place(&mut self, dom: &mut Dom, node: NodeId, override_target: Option<NodeId>) {
+        match self.appropriate_place(dom, override_target) {
+            (parent, Some(reference)) => dom.insert_before(parent, node, reference),
+            (parent, None) => dom.append_child(parent, node),
+        }
+    }
+
+    /// Appropriate place for inserting a node (spec 13.2.6.1), as a parent and
+    /// an optional sibling to insert before. Foster parenting puts nodes in
+    /// front of the last open table instead of inside its structure.
+    fn appropriate_place(&self, dom: &Dom, override_target: Option<NodeId>) -> (NodeId, Option<NodeId>) {
+        let target = override_target.unwrap_or_else(|| self.current_node());
+        let foster = self.foster_parenting
+            && dom.tag_name(target).is_some_and(|name| FOSTER_PARENT_TARGETS.contains(&name));
+        if !foster {
+            return (target, None);
+        }
+
+        let Some(table_index) = self.open_elements.iter().rposition(|&n| dom.tag_name(n) == Some("table")) else {
+            // No table on the stack (fragment case): use the html element
+            return (self.open_elements.get(1).copied().unwrap_or(self.document), None);
+        };
+        let table = self.open_elements[table_index];
+        match dom.nodes[table].parent {
+            Some(parent) => (parent, Some(table)),
+            None => (self.open_elements[table_index - 1], None),
+        }
+    }
+
+    fn has_in_scope(&self, dom: &Dom, names: &[&str], scope: Scope) -> bool {
+        for &node in self.open_elements.iter().rev() {
+            let name = dom.tag_name(node).unwrap_or("");
+            if names.contains(&name) {
+                return true;
+            }
+            if is_scope_boundary(name, scope) {
+                return false;
+            }
+        }
+        false
+    }
+
+    /// Like `has_in_scope`, but looks for a specific node rather than a tag name
+    fn has_node_in_scope(&self, dom: &Dom, target: NodeId) -> bool {
+        for &node in self.open_elements.iter().rev() {
+            if node == target {
+                return true;
+            }
+            if is_scope_boundary(dom.tag_name(node).unwrap_or(""), Scope::Default) {
+                return false;
+            }
+        }
+        false
+    }
+
+    /// Pop elements until one of `names` has been popped. Never pops the document root.
+    fn pop_until(&mut self, dom: &Dom, names: &[&str]) {
+        while self.open_elements.len() > 1 {
+            let node = self.open_elements.pop().unwrap();
+            if dom.tag_name(node).is_some_and(|name| names.contains(&name)) {
+                break;
+            }
+        }
+    }
+
+    /// Generate implied end tags (spec 13.2.6.3), optionally excluding one tag name
+    fn generate_implied_end_tags(&mut self, dom: &Dom, except: Option<&str>) {
+        while let Some(name) = dom.tag_name(self.current_node()) {
+            if !IMPLIED_END_TAGS.contains(&name) || Some(name) == except {
+                break;
+            }
+            self.open_elements.pop();
+        }
+    }
+
+    fn close_p_element(&mut self, dom: &Dom) {
+        self.generate_implied_end_tags(dom, Some("p"));
+        self.pop_until(dom, &["p"]);
+    }
+
+    fn close_p_if_in_button_scope(&mut self, dom: &Dom) {
+        if self.has_in_scope(dom, &["p"], Scope::Button) {
+            self.close_p_element(dom);
+        }
+    }
+
+    fn in_foreign_content(&self, dom: &Dom) -> bool {
+        self.open_elements.iter().any(|&n| matches!(dom.tag_name(n), Some("svg" | "math")))
+    }
+
+    // ---------------------------------------------------------------------
+    // List of active formatting elements (spec 13.2.4.3)
+    // ---------------------------------------------------------------------
+
+    /// Push a formatting element, applying the Noah's Ark clause: at most three
+    /// entries with the same tag name and attributes after the last marker
+    fn push_active_formatting(&mut self, dom: &Dom, node: NodeId) {
+        let mut matching = Vec::new();
+        for (index, entry) in self.active_formatting.iter().enumerate().rev() {
+            match entry {
+                FormattingEntry::Marker => break,
+                FormattingEntry::Element(other) => {
+                    if same_element_signature(dom, *other, node) {
+                        matching.push(index);
                     }
+                }
+            }
+        }
+        if matching.len() >= 3 {
+            // `matching` is in reverse order, so the last index is the earliest entry
+            self.active_formatting.remove(matching[matching.len() - 1]);
+        }
+        self.active_formatting.push(FormattingEntry::Element(node));
+    }
 
-                    // Determine next mode based on current element
-                    if let Some(&current) = stack.last() {
-                        if let crate::dom::NodeType::Element(el) = &dom.nodes[current].node_type {
-                            if el.tag_name == "body" {
-                                mode = InsertionMode::InBody;
-                            } else if el.tag_name == "head" {
-                                mode = InsertionMode::InHead;
-                            } else if el.tag_name == "html" {
-                                mode = InsertionMode::AfterHead;
-                            }
-                        }
+    /// The last element with the given tag name between the end of the list and the last marker
+    fn formatting_element_after_marker(&self, dom: &Dom, tag: &str) -> Option<NodeId> {
+        for entry in self.active_formatting.iter().rev() {
+            match entry {
+                FormattingEntry::Marker => return None,
+                FormattingEntry::Element(node) if dom.tag_name(*node) == Some(tag) => return Some(*node),
+                FormattingEntry::Element(_) => {}
+            }
+        }
+        None
+    }
+
+    fn active_formatting_index(&self, node: NodeId) -> Option<usize> {
+        self.active_formatting.iter().position(|e| *e == FormattingEntry::Element(node))
+    }
+
+    fn remove_from_active_formatting(&mut self, node: NodeId) {
+        if let Some(index) = self.active_formatting_index(node) {
+            self.active_formatting.remove(index);
+        }
+    }
+
+    fn clear_active_formatting_to_last_marker(&mut self) {
+        while let Some(entry) = self.active_formatting.pop() {
+            if entry == FormattingEntry::Marker {
+                break;
+            }
+        }
+    }
+
+    /// Reconstruct the active formatting elements (spec 13.2.4.3)
+    fn reconstruct_active_formatting(&mut self, dom: &mut Dom) {
+        let is_open_or_marker = |parser: &Self, entry: &FormattingEntry| match entry {
+            FormattingEntry::Marker => true,
+            FormattingEntry::Element(node) => parser.open_elements.contains(node),
+        };
+
+        let Some(last) = self.active_formatting.last() else {
+            return;
+        };
+        if is_open_or_marker(self, last) {
+            return;
+        }
+
+        // Rewind to the entry after the last marker or open element
+        let mut start = self.active_formatting.len() - 1;
+        while start > 0 && !is_open_or_marker(self, &self.active_formatting[start - 1]) {
+            start -= 1;
+        }
+
+        // Advance, creating a fresh clone for each entry
+        for index in start..self.active_formatting.len() {
+            let FormattingEntry::Element(node) = self.active_formatting[index] else {
+                continue;
+            };
+            let clone = clone_element(dom, node);
+            self.insert_at_appropriate_place(dom, clone, None);
+            self.open_elements.push(clone);
+            self.active_formatting[index] = FormattingEntry::Element(clone);
+        }
+    }
+
+    /// The adoption agency algorithm (spec 13.2.6.4.7), run for end tags of
+    /// formatting elements and for misnested <a> and <nobr> start tags
+    fn adoption_agency(&mut self, dom: &mut Dom, subject: &str) {
+        // If the current node is the subject and not in the list, just pop it
+        let current = self.current_node();
+        if dom.tag_name(current) == Some(subject) && self.active_formatting_index(current).is_none() {
+            self.open_elements.pop();
+            return;
+        }
+
+        for _ in 0..8 {
+            let Some(formatting_element) = self.formatting_element_after_marker(dom, subject) else {
+                self.any_other_end_tag(dom, subject);
+                return;
+            };
+
+            let Some(fe_stack_index) = self.open_elements.iter().position(|&n| n == formatting_element) else {
+                // Parse error: the element was already closed
+                self.remove_from_active_formatting(formatting_element);
+                return;
+            };
+
+            if !self.has_node_in_scope(dom, formatting_element) {
+                // Parse error; ignore the token
+                return;
+            }
+
+            // The furthest block is the topmost special element below the formatting element
+            let furthest_block = self.open_elements[fe_stack_index + 1..]
+                .iter()
+                .copied()
+                .find(|&n| is_special(dom, n));
+
+            let Some(furthest_block) = furthest_block else {
+                self.open_elements.truncate(fe_stack_index);
+                self.remove_from_active_formatting(formatting_element);
+                return;
+            };
+
+            let common_ancestor = self.open_elements[fe_stack_index - 1];
+            let mut bookmark = Bookmark::Replace(formatting_element);
+
+            let mut node_index = self.open_elements.iter().position(|&n| n == furthest_block).unwrap();
+            let mut last_node = furthest_block;
+            let mut inner_loop_counter = 0;
+
+            loop {
+                inner_loop_counter += 1;
+                node_index -= 1;
+                let node = self.open_elements[node_index];
+                if node == formatting_element {
+                    break;
+                }
+
+                if inner_loop_counter > 3 {
+                    self.remove_from_active_formatting(node);
+                }
+
+                let Some(list_index) = self.active_formatting_index(node) else {
+                    self.open_elements.remove(node_index);
+                    continue;
+                };
+
+                // Replace the node with a fresh clone in both the list and the stack
+                let clone = clone_element(dom, node);
+                self.active_formatting[list_index] = FormattingEntry::Element(clone);
+                self.open_elements[node_index] = clone;
+
+                if last_node == furthest_block {
+                    bookmark = Bookmark::InsertAfter(clone);
+                }
+
+                dom.append_child(clone, last_node);
+                last_node = clone;
+            }
+
+            self.insert_at_appropriate_place(dom, last_node, Some(common_ancestor));
+
+            let new_element = clone_element(dom, formatting_element);
+            dom.reparent_children(furthest_block, new_element);
+            dom.append_child(furthest_block, new_element);
+
+            match bookmark {
+                Bookmark::Replace(node) => {
+                    if let Some(index) = self.active_formatting_index(node) {
+                        self.active_formatting[index] = FormattingEntry::Element(new_element);
                     }
                 }
+                Bookmark::InsertAfter(node) => {
+                    self.remove_from_active_formatting(formatting_element);
+                    let index = self
+                        .active_formatting_index(node)
+                        .map_or(self.active_formatting.len(), |i| i + 1);
+                    self.active_formatting.insert(index, FormattingEntry::Element(new_element));
+                }
             }
+
+            self.open_elements.retain(|&n| n != formatting_element);
+            let fb_index = self.open_elements.iter().position(|&n| n == furthest_block).unwrap();
+            self.open_elements.insert(fb_index + 1, new_element);
         }
+    }
+}
 
-        dom
+fn is_one_of(name: &str, names: &[&str]) -> bool {
+    names.iter().any(|n| name.eq_ignore_ascii_case(n))
+}
+
+fn is_special(dom: &Dom, node: NodeId) -> bool {
+    dom.tag_name(node).is_some_and(|name| SPECIAL_TAGS.contains(&name))
+}
+
+fn is_scope_boundary(name: &str, scope: Scope) -> bool {
+    match scope {
+        Scope::Default => SCOPE_BOUNDARIES.contains(&name),
+        Scope::ListItem => SCOPE_BOUNDARIES.contains(&name) || matches!(name, "ol" | "ul"),
+        Scope::Button => SCOPE_BOUNDARIES.contains(&name) || name == "button",
+        Scope::Table => matches!(name, "html" | "table" | "template" | "document"),
+    }
+}
+
+/// Create a detached copy of an element with the same tag name and attributes
+fn clone_element(dom: &mut Dom, node: NodeId) -> NodeId {
+    let (tag, attrs) = match &dom.nodes[node].node_type {
+        NodeType::Element(el) => (el.tag_name.clone(), el.attributes.clone()),
+        NodeType::Text(_) => unreachable!("only elements are cloned"),
+    };
+    dom.create_element(&tag, attrs, None)
+}
+
+/// Same tag name and the same attribute set, in any order (Noah's Ark clause)
+fn same_element_signature(dom: &Dom, a: NodeId, b: NodeId) -> bool {
+    match (&dom.nodes[a].node_type, &dom.nodes[b].node_type) {
+        (NodeType::Element(x), NodeType::Element(y)) => {
+            x.tag_name == y.tag_name
+                && x.attributes.len() == y.attributes.len()
+                && x.attributes.iter().all(|attr| y.attributes.contains(attr))
+        }
+        _ => false,
+    }
+}
+
+/// Add attributes from a repeated <html>/<body> start tag that the element lacks
+fn merge_attributes(dom: &mut Dom, node: NodeId, attrs: Vec<(String, String)>) {
+    if let NodeType::Element(el) = &mut dom.nodes[node].node_type {
+        for (name, value) in attrs {
+            if !el.attributes.iter().any(|(existing, _)| *existing == name) {
+                el.attributes.push((name, value));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Serialize a subtree as compact markup (no attributes), html5lib-style
+    fn serialize(dom: &Dom, node: NodeId) -> String {
+        match &dom.nodes[node].node_type {
+            NodeType::Text(text) => text.clone(),
+            NodeType::Element(el) => {
+                let inner: String = dom.nodes[node].children.iter().map(|&c| serialize(dom, c)).collect();
+                format!("<{}>{}</{}>", el.tag_name, inner, el.tag_name)
+            }
+        }
+    }
+
+    fn body_of(html: &str) -> String {
+        let dom = HtmlParser::new(html).parse();
+        let body = (0..dom.nodes.len()).find(|&id| dom.tag_name(id) == Some("body")).expect("body element");
+        dom.nodes[body].children.iter().map(|&c| serialize(&dom, c)).collect()
+    }
+
+    #[test]
+    fn test_implied_document_structure() {
+        let dom = HtmlParser::new("<title>T</title>Hello").parse();
+        assert_eq!(
+            serialize(&dom, dom.root()),
+            "<document><html><head><title>T</title></head><body>Hello</body></html></document>"
+        );
+    }
+
+    #[test]
+    fn test_misnested_formatting_elements() {
+        assert_eq!(body_of("<b><i>x</b>y</i>"), "<b><i>x</i></b><i>y</i>");
+        assert_eq!(body_of("<b>1<p>2</b>3</p>"), "<b>1</b><p><b>2</b>3</p>");
+        assert_eq!(body_of("<a>1<div>2</a>3</div>"), "<a>1</a><div><a>2</a>3</div>");
+    }
+
+    #[test]
+    fn test_formatting_reconstructed_across_blocks() {
+        assert_eq!(body_of("<p><b>x<p>y"), "<p><b>x</b></p><p><b>y</b></p>");
+        assert_eq!(body_of("<a>1<a>2"), "<a>1</a><a>2</a>");
+    }
+
+    #[test]
+    fn test_noahs_ark_clause() {
+        assert_eq!(
+            body_of("<b><b><b><b>x</b></b></b></b><p>y"),
+            "<b><b><b><b>x</b></b></b></b><p>y</p>"
+        );
+        assert_eq!(
+            body_of("<p><b><b><b><b>x<p>y"),
+            "<p><b><b><b><b>x</b></b></b></b></p><p><b><b><b>y</b></b></b></p>"
+        );
+    }
+
+    #[test]
+    fn test_implied_end_tags_and_scopes() {
+        assert_eq!(body_of("<ul><li>a<li>b</ul>"), "<ul><li>a</li><li>b</li></ul>");
+        assert_eq!(body_of("<dl><dt>a<dd>b<dt>c</dl>"), "<dl><dt>a</dt><dd>b</dd><dt>c</dt></dl>");
+        assert_eq!(body_of("<p>a<div>b</div>"), "<p>a</p><div>b</div>");
+        assert_eq!(body_of("<button><p>a<button>b"), "<button><p>a</p></button><button>b</button>");
+        assert_eq!(body_of("<div></p>x</div>"), "<div><p></p>x</div>");
+        assert_eq!(body_of("<div><span>a</div>b"), "<div><span>a</span></div>b");
+    }
+
+    #[test]
+    fn test_implied_table_sections_and_rows() {
+        assert_eq!(
+            body_of("<table><td>1<td>2<tr><td>3</table>"),
+            "<table><tbody><tr><td>1</td><td>2</td></tr><tr><td>3</td></tr></tbody></table>"
+        );
+        assert_eq!(
+            body_of("<table><caption>c<tr><th>h</table>"),
+            "<table><caption>c</caption><tbody><tr><th>h</th></tr></tbody></table>"
+        );
+        assert_eq!(
+            body_of("<table><col><thead><tr><td>a<tbody><tr><td>b</table>"),
+            "<table><colgroup><col></col></colgroup><thead><tr><td>a</td></tr></thead><tbody><tr><td>b</td></tr></tbody></table>"
+        );
+    }
+
+    #[test]
+    fn test_foster_parenting() {
+        assert_eq!(body_of("<table>x<tr><td>y</table>"), "x<table><tbody><tr><td>y</td></tr></tbody></table>");
+        assert_eq!(
+            body_of("<table><tr><div>a</div><td>b</table>"),
+            "<div>a</div><table><tbody><tr><td>b</td></tr></tbody></table>"
+        );
+        assert_eq!(body_of("<table><b>x</b></table>"), "<b>x</b><table></table>");
+    }
+
+    #[test]
+    fn test_nested_tables_and_cell_markers() {
+        assert_eq!(
+            body_of("<table><tr><td><table><tr><td>in</table>out</td></tr></table>"),
+            "<table><tbody><tr><td><table><tbody><tr><td>in</td></tr></tbody></table>out</td></tr></tbody></table>"
+        );
+        // Formatting elements opened in a cell do not leak out of it
+        assert_eq!(
+            body_of("<table><tr><td><b>x</td><td>y</td></tr></table>z"),
+            "<table><tbody><tr><td><b>x</b></td><td>y</td></tr></tbody></table>z"
+        );
+        assert_eq!(body_of("<p>a<table><tr><td>b</table>"), "<p>a</p><table><tbody><tr><td>b</td></tr></tbody></table>");
+    }
+
+    #[test]
+    fn test_whitespace_text_and_pre_newline() {
+        assert_eq!(body_of("<p>a <b>b</b> <i>c</i></p>"), "<p>a <b>b</b> <i>c</i></p>");
+        assert_eq!(body_of("<pre>\n  x\n</pre>"), "<pre>  x\n</pre>");
+        assert_eq!(body_of("<pre>\n\nx</pre>"), "<pre>\nx</pre>");
+        // Whitespace between table parts is still dropped
+        assert_eq!(body_of("<table> <tr> <td>a</td> </tr> </table>"), "<table><tbody><tr><td>a</td></tr></tbody></table>");
+    }
+
+    #[test]
+    fn test_markers_stop_reconstruction() {
+        assert_eq!(
+            body_of("<b>1<object>2</b>3</object>4"),
+            "<b>1<object>23</object>4</b>"
+        );
     }
 }
 
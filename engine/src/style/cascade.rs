@@ -0,0 +1,149 @@
+//! The CSS cascade (CSS Cascading and Inheritance Level 4, section 6).
+//!
+//! Declarations from every origin are sorted by cascade level, then
+//! specificity, then source order; the last one for each property wins.
+//! Shorthands are expanded first, so each longhand is cascaded on its own.
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use super::shorthand::expand_declarations;
+use super::{matches, CssRule, Specificity, Stylesheet, Viewport};
+use crate::dom::{Dom, NodeId};
+use crate::parser::css::{CssItem, CssParser, CssTokenizer, Declaration};
+
+/// Where a stylesheet came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Origin {
+    /// Built-in defaults (see `ua.css`)
+    UserAgent,
+    /// Preferences supplied by the user of the browser
+    User,
+    /// The page's own `<style>` sheets and `style` attributes
+    Author,
+}
+
+/// Precedence of a declaration, lowest first. `!important` inverts the
+/// origin order, and inline `style` declarations sit above author rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum CascadeLevel {
+    UserAgent,
+    User,
+    Author,
+    Inline,
+    ImportantAuthor,
+    ImportantInline,
+    ImportantUser,
+    ImportantUserAgent,
+}
+
+impl CascadeLevel {
+    fn of(origin: Origin, important: bool) -> Self {
+        match (origin, important) {
+            (Origin::UserAgent, false) => CascadeLevel::UserAgent,
+            (Origin::User, false) => CascadeLevel::User,
+            (Origin::Author, false) => CascadeLevel::Author,
+            (Origin::Author, true) => CascadeLevel::ImportantAuthor,
+            (Origin::User, true) => CascadeLevel::ImportantUser,
+            (Origin::UserAgent, true) => CascadeLevel::ImportantUserAgent,
+        }
+    }
+}
+
+const USER_AGENT_CSS: &str = include_str!("ua.css");
+
+/// Rules of the built-in user agent stylesheet, parsed once
+pub fn user_agent_rules() -> &'static [CssRule] {
+    static RULES: OnceLock<Vec<CssRule>> = OnceLock::new();
+    RULES.get_or_init(|| {
+        let mut stylesheet = Stylesheet::new();
+        stylesheet.add_css_with_origin(USER_AGENT_CSS, Origin::UserAgent);
+        stylesheet.rules
+    })
+}
+
+/// Parse the contents of a `style` attribute into declarations
+pub fn parse_inline_style(text: &str) -> Vec<Declaration> {
+    let tokens = CssTokenizer::new(text).tokenize();
+    CssParser::new(tokens).parse_declarations()
+}
+
+/// Parse CSS source text into its top-level items
+pub(super) fn parse_css(css: &str) -> Vec<CssItem> {
+    CssParser::new(CssTokenizer::new(css).tokenize()).parse()
+}
+
+/// Run the cascade for one element, returning the winning (cascaded) value
+/// of every property that has a declaration. Values may still be the
+/// `inherit`, `initial` or `unset` keywords.
+pub(super) fn cascaded_values(
+    stylesheet: &Stylesheet,
+    dom: &Dom,
+    node_id: NodeId,
+    viewport: &Viewport,
+) -> HashMap<String, String> {
+    let rules = user_agent_rules()
+        .iter()
+        .chain(stylesheet.rules.iter())
+        .chain(
+            stylesheet
+                .media_rules
+                .iter()
+                .filter(|media_rule| media_rule.condition.matches(viewport))
+                .flat_map(|media_rule| media_rule.rules.iter()),
+        )
+        .filter(|rule| matches(dom, node_id, &rule.selector));
+
+    let mut entries: Vec<((CascadeLevel, Specificity, usize), Declaration)> = Vec::new();
+    for rule in rules {
+        for declaration in expand_declarations(&rule.declarations) {
+            let level = CascadeLevel::of(rule.origin, declaration.important);
+            entries.push(((level, rule.specificity, rule.source_order), declaration));
+        }
+    }
+
+    let inline = dom
+        .get_attribute(node_id, "style")
+        .map(parse_inline_style)
+        .unwrap_or_default();
+    for (index, declaration) in expand_declarations(&inline).into_iter().enumerate() {
+        let level = if declaration.important {
+            CascadeLevel::ImportantInline
+        } else {
+            CascadeLevel::Inline
+        };
+        entries.push(((level, (0, 0, 0), index), declaration));
+    }
+
+    // Stable sort keeps declaration order within a rule as the final tiebreak
+    entries.sort_by_key(|(priority, _)| *priority);
+
+    let mut values = HashMap::new();
+    for (_, declaration) in entries {
+        values.insert(declaration.property, declaration.value);
+    }
+    values
+}
+
+/// CSS-wide keywords that every property accepts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum CssWideKeyword {
+    Inherit,
+    Initial,
+    Unset,
+}
+
+impl CssWideKeyword {
+    pub(super) fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        if value.eq_ignore_ascii_case("inherit") {
+            Some(CssWideKeyword::Inherit)
+        } else if value.eq_ignore_ascii_case("initial") {
+            Some(CssWideKeyword::Initial)
+        } else if value.eq_ignore_ascii_case("unset") {
+            Some(CssWideKeyword::Unset)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,478 @@
+//! CSS colors (CSS Color Level 4): hex notations, the named colors,
+//! `rgb()`/`rgba()`, `hsl()`/`hsla()`, `hwb()`, `transparent` and `currentColor`.
+
+use crate::parser::css::{CssToken, CssTokenizer};
+
+/// An sRGB color with straight (non-premultiplied) alpha
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rgba {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Rgba {
+    pub const BLACK: Rgba = Rgba::rgb(0, 0, 0);
+    pub const WHITE: Rgba = Rgba::rgb(255, 255, 255);
+    pub const TRANSPARENT: Rgba = Rgba::new(0, 0, 0, 0);
+
+    pub const fn new(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self::new(r, g, b, 255)
+    }
+
+    pub fn is_opaque(self) -> bool {
+        self.a == 255
+    }
+
+    pub fn is_transparent(self) -> bool {
+        self.a == 0
+    }
+
+    /// The same color with its alpha scaled by `opacity` (0.0-1.0)
+    pub fn with_opacity(self, opacity: f32) -> Self {
+        Self { a: (self.a as f32 * opacity.clamp(0.0, 1.0)).round() as u8, ..self }
+    }
+
+    /// Composite this color over an opaque backdrop (source-over), with the
+    /// source alpha additionally scaled by `coverage` (0.0-1.0)
+    pub fn blend_over(self, backdrop: (u8, u8, u8), coverage: f32) -> (u8, u8, u8) {
+        let alpha = self.a as f32 / 255.0 * coverage.clamp(0.0, 1.0);
+        let mix = |src: u8, dst: u8| (src as f32 * alpha + dst as f32 * (1.0 - alpha)).round() as u8;
+        (mix(self.r, backdrop.0), mix(self.g, backdrop.1), mix(self.b, backdrop.2))
+    }
+
+    pub fn to_rgb(self) -> (u8, u8, u8) {
+        (self.r, self.g, self.b)
+    }
+}
+
+impl Default for Rgba {
+    fn default() -> Self {
+        Rgba::BLACK
+    }
+}
+
+/// Parse a CSS color. `current_color` is what `currentColor` stands for:
+/// the element's `color`, or the parent's when parsing `color` itself.
+pub fn parse_color(value: &str, current_color: Rgba) -> Option<Rgba> {
+    color_from_tokens(&CssTokenizer::new(value.trim()).tokenize(), current_color)
+}
+
+/// Parse a color from the tokens of a single component value
+pub(super) fn color_from_tokens(tokens: &[CssToken], current_color: Rgba) -> Option<Rgba> {
+    let tokens: Vec<&CssToken> = tokens
+        .iter()
+        .filter(|token| !matches!(token, CssToken::Whitespace))
+        .collect();
+
+    match tokens.as_slice() {
+        [CssToken::Color(hex)] => parse_hex(hex.trim_start_matches('#')),
+        // Hex digits that don't fit the tokenizer's Color token (e.g. 8 digits)
+        [CssToken::Hash(hex)] => parse_hex(hex),
+        [CssToken::Ident(name)] => {
+            let name = name.to_ascii_lowercase();
+            match name.as_str() {
+                "currentcolor" => Some(current_color),
+                "transparent" => Some(Rgba::TRANSPARENT),
+                _ => named_color(&name),
+            }
+        }
+        [CssToken::Function(name), CssToken::OpenParen, args @ .., CssToken::CloseParen] => {
+            let args = split_arguments(args)?;
+            match name.to_ascii_lowercase().as_str() {
+                "rgb" | "rgba" => parse_rgb(&args),
+                "hsl" | "hsla" => parse_hsl(&args),
+                "hwb" => parse_hwb(&args),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// `#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa`
+fn parse_hex(hex: &str) -> Option<Rgba> {
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let digit = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).ok().map(|d| d * 17);
+    let pair = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    match hex.len() {
+        3 => Some(Rgba::rgb(digit(0)?, digit(1)?, digit(2)?)),
+        4 => Some(Rgba::new(digit(0)?, digit(1)?, digit(2)?, digit(3)?)),
+        6 => Some(Rgba::rgb(pair(0)?, pair(2)?, pair(4)?)),
+        8 => Some(Rgba::new(pair(0)?, pair(2)?, pair(4)?, pair(6)?)),
+        _ => None,
+    }
+}
+
+/// One numeric argument of a color function
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Component {
+    Number(f32),
+    Percentage(f32),
+    /// A hue with an angle unit, already converted to degrees
+    Degrees(f32),
+    /// The `none` keyword (treated as zero)
+    None,
+}
+
+/// Channel values and an optional alpha, in either the legacy comma syntax
+/// (`rgb(1, 2, 3, 0.5)`) or the modern space syntax (`rgb(1 2 3 / 50%)`)
+struct Arguments {
+    channels: Vec<Component>,
+    alpha: Option<Component>,
+}
+
+fn split_arguments(tokens: &[&CssToken]) -> Option<Arguments> {
+    let component = |token: &CssToken| -> Option<Component> {
+        Some(match token {
+            CssToken::Number(n) => Component::Number(*n),
+            CssToken::Percentage(p) => Component::Percentage(*p),
+            CssToken::Dimension { value, unit } => Component::Degrees(match unit.as_str() {
+                "deg" => *value,
+                "rad" => value.to_degrees(),
+                "grad" => value * 0.9,
+                "turn" => value * 360.0,
+                _ => return None,
+            }),
+            CssToken::Ident(ident) if ident.eq_ignore_ascii_case("none") => Component::None,
+            _ => return None,
+        })
+    };
+
+    let legacy = tokens.iter().any(|token| matches!(token, CssToken::Comma));
+    let mut channels = Vec::new();
+    let mut alpha = None;
+    if legacy {
+        for (index, group) in tokens.split(|token| matches!(token, CssToken::Comma)).enumerate() {
+            let [token] = group else { return None };
+            let value = component(token)?;
+            if index < 3 {
+                channels.push(value);
+            } else if index == 3 {
+                alpha = Some(value);
+            } else {
+                return None;
+            }
+        }
+    } else {
+        let mut parts = tokens.split(|token| matches!(token, CssToken::Slash));
+        for token in parts.next()? {
+            channels.push(component(token)?);
+        }
+        if let Some(rest) = parts.next() {
+            let [token] = rest else { return None };
+            alpha = Some(component(token)?);
+        }
+        if parts.next().is_some() {
+            return None;
+        }
+    }
+
+    (channels.len() == 3).then_some(Arguments { channels, alpha })
+}
+
+fn alpha_byte(alpha: Option<Component>) -> Option<u8> {
+    let alpha = match alpha {
+        None => 1.0,
+        Some(Component::Number(n)) => n,
+        Some(Component::Percentage(p)) => p / 100.0,
+        Some(Component::None) => 0.0,
+        Some(Component::Degrees(_)) => return None,
+    };
+    Some((alpha.clamp(0.0, 1.0) * 255.0).round() as u8)
+}
+
+fn unit_byte(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+fn parse_rgb(args: &Arguments) -> Option<Rgba> {
+    let channel = |c: Component| -> Option<u8> {
+        match c {
+            Component::Number(n) => Some(n.clamp(0.0, 255.0).round() as u8),
+            Component::Percentage(p) => Some(unit_byte(p / 100.0)),
+            Component::None => Some(0),
+            Component::Degrees(_) => None,
+        }
+    };
+    let [r, g, b] = [args.channels[0], args.channels[1], args.channels[2]];
+    Some(Rgba::new(channel(r)?, channel(g)?, channel(b)?, alpha_byte(args.alpha)?))
+}
+
+fn hue_degrees(c: Component) -> Option<f32> {
+    match c {
+        Component::Number(n) | Component::Degrees(n) => Some(n.rem_euclid(360.0)),
+        Component::None => Some(0.0),
+        Component::Percentage(_) => None,
+    }
+}
+
+/// Saturation, lightness, whiteness and blackness: a percentage (or, in the
+/// modern syntax, a bare number on the same 0-100 scale) as a 0-1 fraction
+fn fraction(c: Component) -> Option<f32> {
+    match c {
+        Component::Percentage(p) | Component::Number(p) => Some((p / 100.0).clamp(0.0, 1.0)),
+        Component::None => Some(0.0),
+        Component::Degrees(_) => None,
+    }
+}
+
+/// HSL to RGB, each channel 0-1 (CSS Color 4, section 7.1)
+fn hsl_to_rgb(hue: f32, saturation: f32, lightness: f32) -> [f32; 3] {
+    let f = |n: f32| {
+        let k = (n + hue / 30.0) % 12.0;
+        let a = saturation * lightness.min(1.0 - lightness);
+        lightness - a * (k - 3.0).min(9.0 - k).clamp(-1.0, 1.0)
+    };
+    [f(0.0), f(8.0), f(4.0)]
+}
+
+fn parse_hsl(args: &Arguments) -> Option<Rgba> {
+    let hue = hue_degrees(args.channels[0])?;
+    let [r, g, b] = hsl_to_rgb(hue, fraction(args.channels[1])?, fraction(args.channels[2])?);
+    Some(Rgba::new(unit_byte(r), unit_byte(g), unit_byte(b), alpha_byte(args.alpha)?))
+}
+
+fn parse_hwb(args: &Arguments) -> Option<Rgba> {
+    let hue = hue_degrees(args.channels[0])?;
+    let mut white = fraction(args.channels[1])?;
+    let mut black = fraction(args.channels[2])?;
+    if white + black >= 1.0 {
+        let sum = white + black;
+        white /= sum;
+        black /= sum;
+    }
+    let rgb = hsl_to_rgb(hue, 1.0, 0.5).map(|c| c * (1.0 - white - black) + white);
+    Some(Rgba::new(unit_byte(rgb[0]), unit_byte(rgb[1]), unit_byte(rgb[2]), alpha_byte(args.alpha)?))
+}
+
+/// Whether `name` is a color function, e.g. to tell `rgb()` from `calc()`
+pub(super) fn is_color_function(name: &str) -> bool {
+    matches!(name.to_ascii_lowercase().as_str(), "rgb" | "rgba" | "hsl" | "hsla" | "hwb")
+}
+
+/// The CSS named colors, sorted by name for binary search
+const NAMED_COLORS: [(&str, (u8, u8, u8)); 148] = [
+    ("aliceblue", (240, 248, 255)),
+    ("antiquewhite", (250, 235, 215)),
+    ("aqua", (0, 255, 255)),
+    ("aquamarine", (127, 255, 212)),
+    ("azure", (240, 255, 255)),
+    ("beige", (245, 245, 220)),
+    ("bisque", (255, 228, 196)),
+    ("black", (0, 0, 0)),
+    ("blanchedalmond", (255, 235, 205)),
+    ("blue", (0, 0, 255)),
+    ("blueviolet", (138, 43, 226)),
+    ("brown", (165, 42, 42)),
+    ("burlywood", (222, 184, 135)),
+    ("cadetblue", (95, 158, 160)),
+    ("chartreuse", (127, 255, 0)),
+    ("chocolate", (210, 105, 30)),
+    ("coral", (255, 127, 80)),
+    ("cornflowerblue", (100, 149, 237)),
+    ("cornsilk", (255, 248, 220)),
+    ("crimson", (220, 20, 60)),
+    ("cyan", (0, 255, 255)),
+    ("darkblue", (0, 0, 139)),
+    ("darkcyan", (0, 139, 139)),
+    ("darkgoldenrod", (184, 134, 11)),
+    ("darkgray", (169, 169, 169)),
+    ("darkgreen", (0, 100, 0)),
+    ("darkgrey", (169, 169, 169)),
+    ("darkkhaki", (189, 183, 107)),
+    ("darkmagenta", (139, 0, 139)),
+    ("darkolivegreen", (85, 107, 47)),
+    ("darkorange", (255, 140, 0)),
+    ("darkorchid", (153, 50, 204)),
+    ("darkred", (139, 0, 0)),
+    ("darksalmon", (233, 150, 122)),
+    ("darkseagreen", (143, 188, 143)),
+    ("darkslateblue", (72, 61, 139)),
+    ("darkslategray", (47, 79, 79)),
+    ("darkslategrey", (47, 79, 79)),
+    ("darkturquoise", (0, 206, 209)),
+    ("darkviolet", (148, 0, 211)),
+    ("deeppink", (255, 20, 147)),
+    ("deepskyblue", (0, 191, 255)),
+    ("dimgray", (105, 105, 105)),
+    ("dimgrey", (105, 105, 105)),
+    ("dodgerblue", (30, 144, 255)),
+    ("firebrick", (178, 34, 34)),
+    ("floralwhite", (255, 250, 240)),
+    ("forestgreen", (34, 139, 34)),
+    ("fuchsia", (255, 0, 255)),
+    ("gainsboro", (220, 220, 220)),
+    ("ghostwhite", (248, 248, 255)),
+    ("gold", (255, 215, 0)),
+    ("goldenrod", (218, 165, 32)),
+    ("gray", (128, 128, 128)),
+    ("green", (0, 128, 0)),
+    ("greenyellow", (173, 255, 47)),
+    ("grey", (128, 128, 128)),
+    ("honeydew", (240, 255, 240)),
+    ("hotpink", (255, 105, 180)),
+    ("indianred", (205, 92, 92)),
+    ("indigo", (75, 0, 130)),
+    ("ivory", (255, 255, 240)),
+    ("khaki", (240, 230, 140)),
+    ("lavender", (230, 230, 250)),
+    ("lavenderblush", (255, 240, 245)),
+    ("lawngreen", (124, 252, 0)),
+    ("lemonchiffon", (255, 250, 205)),
+    ("lightblue", (173, 216, 230)),
+    ("lightcoral", (240, 128, 128)),
+    ("lightcyan", (224, 255, 255)),
+    ("lightgoldenrodyellow", (250, 250, 210)),
+    ("lightgray", (211, 211, 211)),
+    ("lightgreen", (144, 238, 144)),
+    ("lightgrey", (211, 211, 211)),
+    ("lightpink", (255, 182, 193)),
+    ("lightsalmon", (255, 160, 122)),
+    ("lightseagreen", (32, 178, 170)),
+    ("lightskyblue", (135, 206, 250)),
+    ("lightslategray", (119, 136, 153)),
+    ("lightslategrey", (119, 136, 153)),
+    ("lightsteelblue", (176, 196, 222)),
+    ("lightyellow", (255, 255, 224)),
+    ("lime", (0, 255, 0)),
+    ("limegreen", (50, 205, 50)),
+    ("linen", (250, 240, 230)),
+    ("magenta", (255, 0, 255)),
+    ("maroon", (128, 0, 0)),
+    ("mediumaquamarine", (102, 205, 170)),
+    ("mediumblue", (0, 0, 205)),
+    ("mediumorchid", (186, 85, 211)),
+    ("mediumpurple", (147, 112, 219)),
+    ("mediumseagreen", (60, 179, 113)),
+    ("mediumslateblue", (123, 104, 238)),
+    ("mediumspringgreen", (0, 250, 154)),
+    ("mediumturquoise", (72, 209, 204)),
+    ("mediumvioletred", (199, 21, 133)),
+    ("midnightblue", (25, 25, 112)),
+    ("mintcream", (245, 255, 250)),
+    ("mistyrose", (255, 228, 225)),
+    ("moccasin", (255, 228, 181)),
+    ("navajowhite", (255, 222, 173)),
+    ("navy", (0, 0, 128)),
+    ("oldlace", (253, 245, 230)),
+    ("olive", (128, 128, 0)),
+    ("olivedrab", (107, 142, 35)),
+    ("orange", (255, 165, 0)),
+    ("orangered", (255, 69, 0)),
+    ("orchid", (218, 112, 214)),
+    ("palegoldenrod", (238, 232, 170)),
+    ("palegreen", (152, 251, 152)),
+    ("paleturquoise", (175, 238, 238)),
+    ("palevioletred", (219, 112, 147)),
+    ("papayawhip", (255, 239, 213)),
+    ("peachpuff", (255, 218, 185)),
+    ("peru", (205, 133, 63)),
+    ("pink", (255, 192, 203)),
+    ("plum", (221, 160, 221)),
+    ("powderblue", (176, 224, 230)),
+    ("purple", (128, 0, 128)),
+    ("rebeccapurple", (102, 51, 153)),
+    ("red", (255, 0, 0)),
+    ("rosybrown", (188, 143, 143)),
+    ("royalblue", (65, 105, 225)),
+    ("saddlebrown", (139, 69, 19)),
+    ("salmon", (250, 128, 114)),
+    ("sandybrown", (244, 164, 96)),
+    ("seagreen", (46, 139, 87)),
+    ("seashell", (255, 245, 238)),
+    ("sienna", (160, 82, 45)),
+    ("silver", (192, 192, 192)),
+    ("skyblue", (135, 206, 235)),
+    ("slateblue", (106, 90, 205)),
+    ("slategray", (112, 128, 144)),
+    ("slategrey", (112, 128, 144)),
+    ("snow", (255, 250, 250)),
+    ("springgreen", (0, 255, 127)),
+    ("steelblue", (70, 130, 180)),
+    ("tan", (210, 180, 140)),
+    ("teal", (0, 128, 128)),
+    ("thistle", (216, 191, 216)),
+    ("tomato", (255, 99, 71)),
+    ("turquoise", (64, 224, 208)),
+    ("violet", (238, 130, 238)),
+    ("wheat", (245, 222, 179)),
+    ("white", (255, 255, 255)),
+    ("whitesmoke", (245, 245, 245)),
+    ("yellow", (255, 255, 0)),
+    ("yellowgreen", (154, 205, 50)),
+];
+
+/// Look up a lowercase color name
+fn named_color(name: &str) -> Option<Rgba> {
+    NAMED_COLORS
+        .binary_search_by(|(candidate, _)| (*candidate).cmp(name))
+        .ok()
+        .map(|index| {
+            let (r, g, b) = NAMED_COLORS[index].1;
+            Rgba::rgb(r, g, b)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn color(value: &str) -> Option<Rgba> {
+        parse_color(value, Rgba::rgb(1, 2, 3))
+    }
+
+    #[test]
+    fn hex_forms() {
+        assert_eq!(color("#f00"), Some(Rgba::rgb(255, 0, 0)));
+        assert_eq!(color("#f008"), Some(Rgba::new(255, 0, 0, 136)));
+        assert_eq!(color("#00ff7f"), Some(Rgba::rgb(0, 255, 127)));
+        assert_eq!(color("#00ff7f80"), Some(Rgba::new(0, 255, 127, 128)));
+        assert_eq!(color("#12345"), None);
+        assert_eq!(color("#ggg"), None);
+    }
+
+    #[test]
+    fn named_colors_and_keywords() {
+        assert!(NAMED_COLORS.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert_eq!(color("RebeccaPurple"), Some(Rgba::rgb(102, 51, 153)));
+        assert_eq!(color("lightgoldenrodyellow"), Some(Rgba::rgb(250, 250, 210)));
+        assert_eq!(color("transparent"), Some(Rgba::TRANSPARENT));
+        assert_eq!(color("currentColor"), Some(Rgba::rgb(1, 2, 3)));
+        assert_eq!(color("notacolor"), None);
+    }
+
+    #[test]
+    fn rgb_functions() {
+        assert_eq!(color("rgb(255, 0, 128)"), Some(Rgba::rgb(255, 0, 128)));
+        assert_eq!(color("rgba(0, 0, 0, 0.5)"), Some(Rgba::new(0, 0, 0, 128)));
+        assert_eq!(color("rgb(100% 0% 50% / 25%)"), Some(Rgba::new(255, 0, 128, 64)));
+        assert_eq!(color("rgb(300 -5 0)"), Some(Rgba::rgb(255, 0, 0)));
+        assert_eq!(color("rgb(1, 2)"), None);
+        assert_eq!(color("rgb(1 2, 3)"), None);
+    }
+
+    #[test]
+    fn hsl_and_hwb() {
+        assert_eq!(color("hsl(120, 100%, 25%)"), Some(Rgba::rgb(0, 128, 0)));
+        assert_eq!(color("hsla(0.5turn 100% 50% / 0.5)"), Some(Rgba::new(0, 255, 255, 128)));
+        assert_eq!(color("hsl(-120deg 100% 50%)"), Some(Rgba::rgb(0, 0, 255)));
+        assert_eq!(color("hwb(0 0% 0%)"), Some(Rgba::rgb(255, 0, 0)));
+        assert_eq!(color("hwb(90 60% 60%)"), Some(Rgba::rgb(128, 128, 128)));
+    }
+
+    #[test]
+    fn blending() {
+        let half_red = Rgba::new(255, 0, 0, 128);
+        assert_eq!(half_red.blend_over((0, 0, 255), 1.0), (128, 0, 127));
+        assert_eq!(half_red.blend_over((0, 0, 255), 0.0), (0, 0, 255));
+        assert_eq!(Rgba::WHITE.with_opacity(0.5).a, 128);
+    }
+}
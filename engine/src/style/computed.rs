@@ -0,0 +1,1329 @@
+//! Computed styles: one typed `ComputedStyle` per element, produced by a
+//! single top-down pass over the DOM so that layout never re-runs the cascade.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::cascade::CssWideKeyword;
+use super::values::{CssValue, Length, ResolveContext};
+use super::{parse_color, Rgba, Style, Viewport};
+use crate::dom::NodeId;
+
+/// Initial `font-size` (the `medium` keyword)
+pub(super) const MEDIUM_FONT_SIZE: f32 = 16.0;
+
+/// The `display` property's outer/inner type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Display {
+    None,
+    Block,
+    Inline,
+    InlineBlock,
+    ListItem,
+    FlowRoot,
+    Table,
+    TableCaption,
+    TableRowGroup,
+    TableRow,
+    TableCell,
+    TableColumn,
+    Flex,
+    InlineFlex,
+    Grid,
+    InlineGrid,
+}
+
+impl Display {
+    pub fn parse(value: &str) -> Option<Display> {
+        Some(match value.trim().to_ascii_lowercase().as_str() {
+            "none" => Display::None,
+            "block" => Display::Block,
+            "inline" => Display::Inline,
+            "inline-block" => Display::InlineBlock,
+            "list-item" => Display::ListItem,
+            "flow-root" => Display::FlowRoot,
+            "table" | "inline-table" => Display::Table,
+            "table-caption" => Display::TableCaption,
+            "table-row-group" | "table-header-group" | "table-footer-group" => Display::TableRowGroup,
+            "table-row" => Display::TableRow,
+            "table-cell" => Display::TableCell,
+            "table-column" | "table-column-group" => Display::TableColumn,
+            "flex" => Display::Flex,
+            "inline-flex" => Display::InlineFlex,
+            "grid" => Display::Grid,
+            "inline-grid" => Display::InlineGrid,
+            _ => return None,
+        })
+    }
+
+    /// Default display for an element that has no `display` declaration.
+    /// Per the HTML rendering section, these elements are block-level by default.
+    pub fn default_for_tag(tag: &str) -> Display {
+        match tag.to_ascii_lowercase().as_str() {
+            "li" => Display::ListItem,
+            "table" => Display::Table,
+            "caption" => Display::TableCaption,
+            "thead" | "tbody" | "tfoot" => Display::TableRowGroup,
+            "tr" => Display::TableRow,
+            "td" | "th" => Display::TableCell,
+            "col" | "colgroup" => Display::TableColumn,
+            // Document structure
+            "html" | "body" | "document" | "head" | "title" | "meta" | "link" |
+            "style" | "script" | "noscript" | "template" |
+            // Sections
+            "article" | "aside" | "footer" | "header" | "nav" | "section" | "main" |
+            // Grouping content
+            "p" | "div" | "blockquote" | "pre" | "hr" | "address" |
+            "figure" | "figcaption" | "center" |
+            // Headings
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "hgroup" |
+            // Lists
+            "ul" | "ol" | "dl" | "dt" | "dd" | "dir" | "menu" |
+            // Forms
+            "form" | "fieldset" | "legend" | "label" | "input" | "button" |
+            "select" | "textarea" | "option" | "optgroup" | "datalist" | "output" |
+            // Embedded content (often block-like)
+            "img" | "iframe" | "video" | "audio" | "canvas" | "object" | "embed" |
+            "picture" | "source" | "track" |
+            // Interactive
+            "details" | "summary" | "dialog" |
+            // Deprecated but still used
+            "xmp" | "listing" | "plaintext" | "frameset" | "frame" | "noframes" => Display::Block,
+            _ => Display::Inline,
+        }
+    }
+
+    /// Does this box take part in block layout (as opposed to flowing inline)?
+    pub fn is_block_level(self) -> bool {
+        !matches!(self, Display::Inline | Display::InlineBlock | Display::InlineFlex | Display::InlineGrid)
+    }
+
+    /// The block-level equivalent, for boxes taken out of flow (CSS 2.1 section 9.7)
+    pub fn blockified(self) -> Display {
+        match self {
+            Display::Inline | Display::InlineBlock => Display::Block,
+            Display::InlineFlex => Display::Flex,
+            Display::InlineGrid => Display::Grid,
+            display => display,
+        }
+    }
+}
+
+/// The `position` property
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Position {
+    #[default]
+    Static,
+    Relative,
+    Absolute,
+    Fixed,
+    Sticky,
+}
+
+impl Position {
+    pub fn parse(value: &str) -> Option<Position> {
+        Some(match value.trim().to_ascii_lowercase().as_str() {
+            "static" => Position::Static,
+            "relative" => Position::Relative,
+            "absolute" => Position::Absolute,
+            "fixed" => Position::Fixed,
+            "sticky" | "-webkit-sticky" => Position::Sticky,
+            _ => return None,
+        })
+    }
+
+    /// Anything but `static`: the box is a containing block for absolutely
+    /// positioned descendants and `z-index` applies to it
+    pub fn is_positioned(self) -> bool {
+        self != Position::Static
+    }
+
+    /// Absolutely positioned boxes take no space in normal flow
+    pub fn is_out_of_flow(self) -> bool {
+        matches!(self, Position::Absolute | Position::Fixed)
+    }
+}
+
+/// The `float` property
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Float {
+    #[default]
+    None,
+    Left,
+    Right,
+}
+
+impl Float {
+    pub fn parse(value: &str) -> Option<Float> {
+        Some(match value.trim().to_ascii_lowercase().as_str() {
+            "none" => Float::None,
+            "left" | "inline-start" => Float::Left,
+            "right" | "inline-end" => Float::Right,
+            _ => return None,
+        })
+    }
+}
+
+/// The `clear` property
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Clear {
+    #[default]
+    None,
+    Left,
+    Right,
+    Both,
+}
+
+impl Clear {
+    pub fn parse(value: &str) -> Option<Clear> {
+        Some(match value.trim().to_ascii_lowercase().as_str() {
+            "none" => Clear::None,
+            "left" | "inline-start" => Clear::Left,
+            "right" | "inline-end" => Clear::Right,
+            "both" => Clear::Both,
+            _ => return None,
+        })
+    }
+
+    /// Whether floats on `side` must end above the box
+    pub fn clears(self, side: Float) -> bool {
+        match self {
+            Clear::None => false,
+            Clear::Both => side != Float::None,
+            Clear::Left => side == Float::Left,
+            Clear::Right => side == Float::Right,
+        }
+    }
+}
+
+/// The `overflow-x` and `overflow-y` properties
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Overflow {
+    #[default]
+    Visible,
+    Hidden,
+    Clip,
+    Scroll,
+    Auto,
+}
+
+impl Overflow {
+    pub fn parse(value: &str) -> Option<Overflow> {
+        Some(match value.trim().to_ascii_lowercase().as_str() {
+            "visible" => Overflow::Visible,
+            "hidden" => Overflow::Hidden,
+            "clip" => Overflow::Clip,
+            "scroll" => Overflow::Scroll,
+            "auto" | "overlay" => Overflow::Auto,
+            _ => return None,
+        })
+    }
+
+    /// `hidden`, `scroll` and `auto` make the box a scroll container
+    pub fn is_scrollable(self) -> bool {
+        matches!(self, Overflow::Hidden | Overflow::Scroll | Overflow::Auto)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FontStyle {
+    Normal,
+    Italic,
+    Oblique,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextAlign {
+    Left,
+    Right,
+    Center,
+    Justify,
+}
+
+/// The `white-space` property
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WhiteSpace {
+    #[default]
+    Normal,
+    Pre,
+    Nowrap,
+    PreWrap,
+    PreLine,
+}
+
+impl WhiteSpace {
+    pub fn parse(value: &str) -> Option<WhiteSpace> {
+        Some(match value.trim().to_ascii_lowercase().as_str() {
+            "normal" => WhiteSpace::Normal,
+            "pre" => WhiteSpace::Pre,
+            "nowrap" => WhiteSpace::Nowrap,
+            // `break-spaces` differs only in where preserved spaces may wrap
+            "pre-wrap" | "break-spaces" => WhiteSpace::PreWrap,
+            "pre-line" => WhiteSpace::PreLine,
+            _ => return None,
+        })
+    }
+
+    /// Runs of spaces and tabs collapse to one space
+    pub fn collapses_spaces(self) -> bool {
+        matches!(self, WhiteSpace::Normal | WhiteSpace::Nowrap | WhiteSpace::PreLine)
+    }
+
+    /// Newlines force line breaks instead of acting as spaces
+    pub fn preserves_newlines(self) -> bool {
+        matches!(self, WhiteSpace::Pre | WhiteSpace::PreWrap | WhiteSpace::PreLine)
+    }
+
+    /// Lines may wrap at soft wrap opportunities
+    pub fn wraps(self) -> bool {
+        matches!(self, WhiteSpace::Normal | WhiteSpace::PreWrap | WhiteSpace::PreLine)
+    }
+}
+
+/// The `overflow-wrap` property (also spelled `word-wrap`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverflowWrap {
+    #[default]
+    Normal,
+    /// Split words that fit on no line, without lowering min-content widths
+    BreakWord,
+    /// Split words that fit on no line; min-content widths count clusters
+    Anywhere,
+}
+
+impl OverflowWrap {
+    pub fn parse(value: &str) -> Option<OverflowWrap> {
+        Some(match value.trim().to_ascii_lowercase().as_str() {
+            "normal" => OverflowWrap::Normal,
+            "break-word" => OverflowWrap::BreakWord,
+            "anywhere" => OverflowWrap::Anywhere,
+            _ => return None,
+        })
+    }
+}
+
+/// The `word-break` property
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WordBreak {
+    #[default]
+    Normal,
+    /// Lines may break between any two letters
+    BreakAll,
+    /// No breaks between letters, even in CJK text
+    KeepAll,
+}
+
+/// The `hyphens` property
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Hyphens {
+    /// Soft hyphens are not break opportunities
+    None,
+    /// Break at soft hyphens (U+00AD) only
+    #[default]
+    Manual,
+    /// Treated as `manual`: there are no hyphenation dictionaries
+    Auto,
+}
+
+impl Hyphens {
+    pub fn parse(value: &str) -> Option<Hyphens> {
+        Some(match value.trim().to_ascii_lowercase().as_str() {
+            "none" => Hyphens::None,
+            "manual" => Hyphens::Manual,
+            "auto" => Hyphens::Auto,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TextDecoration {
+    pub underline: bool,
+    pub overline: bool,
+    pub line_through: bool,
+}
+
+/// Computed `line-height`. A bare number is inherited as a factor, not as pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineHeight {
+    Normal,
+    Number(f32),
+    Px(f32),
+}
+
+impl LineHeight {
+    pub fn to_px(self, font_size: f32) -> f32 {
+        match self {
+            LineHeight::Normal => font_size * 1.2,
+            LineHeight::Number(factor) => font_size * factor,
+            LineHeight::Px(px) => px,
+        }
+    }
+}
+
+/// Four box sides: px once resolved, [`Length`]s as computed
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Edges<T = f32> {
+    pub top: T,
+    pub right: T,
+    pub bottom: T,
+    pub left: T,
+}
+
+impl Edges<Length> {
+    /// Resolve percentages against the containing block width, as CSS does
+    /// for all four sides
+    pub fn resolve(&self, containing_width: f32) -> Edges {
+        Edges {
+            top: self.top.resolve(containing_width),
+            right: self.right.resolve(containing_width),
+            bottom: self.bottom.resolve(containing_width),
+            left: self.left.resolve(containing_width),
+        }
+    }
+}
+
+impl Edges {
+    /// (top, right, bottom, left)
+    pub fn trbl(&self) -> (f32, f32, f32, f32) {
+        (self.top, self.right, self.bottom, self.left)
+    }
+}
+
+/// The `border-*-style` properties
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BorderStyle {
+    #[default]
+    None,
+    Hidden,
+    Solid,
+    Dashed,
+    Dotted,
+    Double,
+    Groove,
+    Ridge,
+    Inset,
+    Outset,
+}
+
+impl BorderStyle {
+    pub fn parse(value: &str) -> Option<BorderStyle> {
+        Some(match value.trim().to_ascii_lowercase().as_str() {
+            "none" => BorderStyle::None,
+            "hidden" => BorderStyle::Hidden,
+            "solid" => BorderStyle::Solid,
+            "dashed" => BorderStyle::Dashed,
+            "dotted" => BorderStyle::Dotted,
+            "double" => BorderStyle::Double,
+            "groove" => BorderStyle::Groove,
+            "ridge" => BorderStyle::Ridge,
+            "inset" => BorderStyle::Inset,
+            "outset" => BorderStyle::Outset,
+            _ => return None,
+        })
+    }
+
+    /// `none` and `hidden` borders have no width
+    pub fn has_width(self) -> bool {
+        !matches!(self, BorderStyle::None | BorderStyle::Hidden)
+    }
+}
+
+/// One side of a border
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BorderSide {
+    /// In px; 0 whenever the style is `none` or `hidden`
+    pub width: f32,
+    pub style: BorderStyle,
+    pub color: Rgba,
+}
+
+impl Default for BorderSide {
+    fn default() -> Self {
+        Self { width: 0.0, style: BorderStyle::None, color: Rgba::BLACK }
+    }
+}
+
+/// Four box corners: `(horizontal, vertical)` radii in px once resolved,
+/// [`Length`]s as computed
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Corners<T = (f32, f32)> {
+    pub top_left: T,
+    pub top_right: T,
+    pub bottom_right: T,
+    pub bottom_left: T,
+}
+
+impl Corners<(Length, Length)> {
+    /// Resolve against a border box: horizontal radii take percentages of its
+    /// width, vertical ones of its height. Radii that would overlap are
+    /// scaled down together until they fit.
+    pub fn resolve(&self, width: f32, height: f32) -> Corners {
+        let corner = |(x, y): &(Length, Length)| (x.resolve(width).max(0.0), y.resolve(height).max(0.0));
+        let radii = Corners {
+            top_left: corner(&self.top_left),
+            top_right: corner(&self.top_right),
+            bottom_right: corner(&self.bottom_right),
+            bottom_left: corner(&self.bottom_left),
+        };
+        let fit = |side: f32, a: f32, b: f32| if a + b > side { side / (a + b) } else { 1.0 };
+        let factor = fit(width, radii.top_left.0, radii.top_right.0)
+            .min(fit(width, radii.bottom_left.0, radii.bottom_right.0))
+            .min(fit(height, radii.top_left.1, radii.bottom_left.1))
+            .min(fit(height, radii.top_right.1, radii.bottom_right.1));
+        radii.map(|(x, y)| (x * factor, y * factor))
+    }
+}
+
+impl Corners {
+    pub fn is_zero(&self) -> bool {
+        [self.top_left, self.top_right, self.bottom_right, self.bottom_left]
+            .iter()
+            .all(|&(x, y)| x <= 0.0 || y <= 0.0)
+    }
+
+    /// The radii of a box `edges` inside this one, such as the padding box
+    /// inside the border
+    pub fn shrink(&self, edges: &Edges) -> Corners {
+        let inner = |(x, y): (f32, f32), dx: f32, dy: f32| ((x - dx).max(0.0), (y - dy).max(0.0));
+        Corners {
+            top_left: inner(self.top_left, edges.left, edges.top),
+            top_right: inner(self.top_right, edges.right, edges.top),
+            bottom_right: inner(self.bottom_right, edges.right, edges.bottom),
+            bottom_left: inner(self.bottom_left, edges.left, edges.bottom),
+        }
+    }
+
+    /// The radii of a box grown by `spread` on every side. Square corners
+    /// stay square.
+    pub fn grow(&self, spread: f32) -> Corners {
+        self.map(|(x, y)| {
+            if x <= 0.0 || y <= 0.0 {
+                (0.0, 0.0)
+            } else {
+                ((x + spread).max(0.0), (y + spread).max(0.0))
+            }
+        })
+    }
+
+    fn map(&self, f: impl Fn((f32, f32)) -> (f32, f32)) -> Corners {
+        Corners {
+            top_left: f(self.top_left),
+            top_right: f(self.top_right),
+            bottom_right: f(self.bottom_right),
+            bottom_left: f(self.bottom_left),
+        }
+    }
+}
+
+/// One `box-shadow`, in px
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoxShadow {
+    pub offset_x: f32,
+    pub offset_y: f32,
+    pub blur: f32,
+    pub spread: f32,
+    pub color: Rgba,
+    pub inset: bool,
+}
+
+/// The computed style of one element, with typed values for everything layout
+/// and painting read. Properties without a typed field are still available,
+/// as cascaded strings, through [`ComputedStyle::get`].
+#[derive(Debug, Clone)]
+pub struct ComputedStyle {
+    pub display: Display,
+    pub font_family: String,
+    /// Font size in px
+    pub font_size: f32,
+    /// Numeric weight, 100-900
+    pub font_weight: u16,
+    pub font_style: FontStyle,
+    pub line_height: LineHeight,
+    pub color: Rgba,
+    /// None when transparent
+    pub background_color: Option<Rgba>,
+    /// Applies to the element and its descendants as a group
+    pub opacity: f32,
+    pub text_align: TextAlign,
+    pub text_decoration: TextDecoration,
+    pub white_space: WhiteSpace,
+    pub overflow_wrap: OverflowWrap,
+    pub word_break: WordBreak,
+    pub hyphens: Hyphens,
+    /// `auto` sides are 0 here and flagged in `margin_auto`
+    pub margin: Edges<Length>,
+    /// Which margins are `auto` (top, right, bottom, left)
+    pub margin_auto: (bool, bool, bool, bool),
+    pub padding: Edges<Length>,
+    pub border: Edges<BorderSide>,
+    pub border_radius: Corners<(Length, Length)>,
+    /// Painted first to last, the first on top
+    pub box_shadow: Vec<BoxShadow>,
+    /// None means `auto`
+    pub width: Option<Length>,
+    pub height: Option<Length>,
+    /// None means `none`
+    pub max_width: Option<Length>,
+    pub position: Position,
+    /// `top`, `right`, `bottom` and `left`; None means `auto`
+    pub inset: Edges<Option<Length>>,
+    /// None means `auto`
+    pub z_index: Option<i32>,
+    /// Always `none` for absolutely positioned boxes
+    pub float: Float,
+    pub clear: Clear,
+    pub overflow_x: Overflow,
+    pub overflow_y: Overflow,
+    /// What this element's relative units resolve against
+    units: ResolveContext,
+    properties: Style,
+}
+
+impl Default for ComputedStyle {
+    /// Initial values of every property
+    fn default() -> Self {
+        Self {
+            display: Display::Inline,
+            font_family: "Times New Roman".to_string(),
+            font_size: MEDIUM_FONT_SIZE,
+            font_weight: 400,
+            font_style: FontStyle::Normal,
+            line_height: LineHeight::Normal,
+            color: Rgba::BLACK,
+            background_color: None,
+            opacity: 1.0,
+            text_align: TextAlign::Left,
+            text_decoration: TextDecoration::default(),
+            white_space: WhiteSpace::Normal,
+            overflow_wrap: OverflowWrap::Normal,
+            word_break: WordBreak::Normal,
+            hyphens: Hyphens::Manual,
+            margin: Edges::default(),
+            margin_auto: (false, false, false, false),
+            padding: Edges::default(),
+            border: Edges::default(),
+            border_radius: Corners::default(),
+            box_shadow: Vec::new(),
+            width: None,
+            height: None,
+            max_width: None,
+            position: Position::Static,
+            inset: Edges::default(),
+            z_index: None,
+            float: Float::None,
+            clear: Clear::None,
+            overflow_x: Overflow::Visible,
+            overflow_y: Overflow::Visible,
+            units: ResolveContext {
+                font_size: MEDIUM_FONT_SIZE,
+                root_font_size: MEDIUM_FONT_SIZE,
+                viewport: Viewport::default(),
+            },
+            properties: Style::new(),
+        }
+    }
+}
+
+/// Values needed to resolve relative units
+pub(super) struct ComputeContext<'a> {
+    pub viewport: &'a Viewport,
+    /// Computed font size of the root element, for `rem`
+    pub root_font_size: f32,
+}
+
+impl ComputeContext<'_> {
+    /// Unit resolution for an element whose `em` is `font_size`
+    fn resolve_with(&self, font_size: f32) -> ResolveContext {
+        ResolveContext {
+            font_size,
+            root_font_size: self.root_font_size,
+            viewport: *self.viewport,
+        }
+    }
+}
+
+impl ComputedStyle {
+    /// Cascaded (or inherited) string value of any property
+    pub fn get(&self, property: &str) -> Option<&str> {
+        self.properties.get(property)
+    }
+
+    /// The property map this style was computed from
+    pub fn properties(&self) -> &Style {
+        &self.properties
+    }
+
+    /// Parse any length-valued property that has no typed field
+    pub fn length(&self, property: &str) -> Option<Length> {
+        self.to_length(&CssValue::parse(self.get(property)?)?)
+    }
+
+    /// Parse a whitespace-separated list of lengths, skipping anything else
+    pub fn lengths(&self, property: &str) -> Vec<Length> {
+        self.get(property)
+            .map(CssValue::parse_list)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|value| self.to_length(value))
+            .collect()
+    }
+
+    /// Resolve a value parsed from one of this element's properties
+    pub fn to_length(&self, value: &CssValue) -> Option<Length> {
+        value.to_length(&self.units)
+    }
+
+    pub fn is_bold(&self) -> bool {
+        self.font_weight >= 600
+    }
+
+    pub fn is_italic(&self) -> bool {
+        self.font_style != FontStyle::Normal
+    }
+
+    pub fn line_height_px(&self) -> f32 {
+        self.line_height.to_px(self.font_size)
+    }
+
+    /// Used border widths, 0 on sides without a border
+    pub fn border_widths(&self) -> Edges {
+        Edges {
+            top: self.border.top.width,
+            right: self.border.right.width,
+            bottom: self.border.bottom.width,
+            left: self.border.left.width,
+        }
+    }
+
+    /// Padding plus border on each side: the distance from the border box
+    /// to the content box. Percentage padding resolves against
+    /// `containing_width`.
+    pub fn padding_border(&self, containing_width: f32) -> Edges {
+        let padding = self.padding.resolve(containing_width);
+        let border = self.border_widths();
+        Edges {
+            top: padding.top + border.top,
+            right: padding.right + border.right,
+            bottom: padding.bottom + border.bottom,
+            left: padding.left + border.left,
+        }
+    }
+
+    /// Both horizontal margins are `auto` (the block is centered)
+    pub fn has_auto_horizontal_margin(&self) -> bool {
+        self.margin_auto.1 && self.margin_auto.3
+    }
+
+    /// Compute an element's style.
+    ///
+    /// `cascaded` holds the element's own winning declarations (possibly CSS-wide
+    /// keywords); `properties` is the same map after inheritance and keyword
+    /// resolution. Inherited properties that the element does not declare take
+    /// the parent's computed value rather than re-resolving the parent's string,
+    /// so `font-size: 2em` does not compound down the tree.
+    pub(super) fn compute(
+        tag: &str,
+        cascaded: &HashMap<String, String>,
+        properties: Style,
+        parent: Option<&ComputedStyle>,
+        context: &ComputeContext,
+    ) -> ComputedStyle {
+        let initial = ComputedStyle::default();
+        let inherited = parent.unwrap_or(&initial);
+
+        // Inherited properties: None means "take the parent's computed value"
+        let own = |property: &str| -> Option<&str> {
+            let value = cascaded.get(property)?;
+            match CssWideKeyword::parse(value) {
+                None => Some(value.as_str()),
+                Some(CssWideKeyword::Initial) => Some("initial"),
+                Some(CssWideKeyword::Inherit) | Some(CssWideKeyword::Unset) => None,
+            }
+        };
+
+        let font_size = match own("font-size") {
+            None => inherited.font_size,
+            Some("initial") => MEDIUM_FONT_SIZE,
+            Some(value) => resolve_font_size(value, inherited.font_size, context)
+                .unwrap_or(inherited.font_size),
+        };
+
+        let font_family = match own("font-family") {
+            None => inherited.font_family.clone(),
+            Some("initial") => initial.font_family.clone(),
+            Some(value) => value.trim().to_string(),
+        };
+
+        let font_weight = match own("font-weight") {
+            None => inherited.font_weight,
+            Some("initial") => initial.font_weight,
+            Some(value) => parse_font_weight(value, inherited.font_weight).unwrap_or(inherited.font_weight),
+        };
+
+        let font_style = match own("font-style").map(|v| v.trim().to_ascii_lowercase()) {
+            None => inherited.font_style,
+            Some(value) => match value.as_str() {
+                "italic" => FontStyle::Italic,
+                "oblique" => FontStyle::Oblique,
+                "normal" | "initial" => FontStyle::Normal,
+                _ => inherited.font_style,
+            },
+        };
+
+        let line_height = match own("line-height") {
+            None => inherited.line_height,
+            Some("initial") => LineHeight::Normal,
+            Some(value) => parse_line_height(value, font_size, context).unwrap_or(inherited.line_height),
+        };
+
+        let color = match own("color") {
+            None => inherited.color,
+            Some("initial") => initial.color,
+            // currentColor in `color` itself means the inherited color
+            Some(value) => parse_color(value, inherited.color).unwrap_or(inherited.color),
+        };
+
+        let text_align = match own("text-align").map(|v| v.trim().to_ascii_lowercase()) {
+            None => inherited.text_align,
+            Some(value) => match value.as_str() {
+                "left" | "start" | "initial" => TextAlign::Left,
+                "right" | "end" => TextAlign::Right,
+                "center" => TextAlign::Center,
+                "justify" => TextAlign::Justify,
+                _ => inherited.text_align,
+            },
+        };
+
+        let text_decoration = match own("text-decoration") {
+            None => inherited.text_decoration,
+            Some(value) => {
+                let value = value.to_ascii_lowercase();
+                TextDecoration {
+                    underline: value.contains("underline"),
+                    overline: value.contains("overline"),
+                    line_through: value.contains("line-through"),
+                }
+            }
+        };
+
+        let white_space = match own("white-space") {
+            None => inherited.white_space,
+            Some("initial") => WhiteSpace::Normal,
+            Some(value) => WhiteSpace::parse(value).unwrap_or(inherited.white_space),
+        };
+
+        let overflow_wrap = match own("overflow-wrap") {
+            None => inherited.overflow_wrap,
+            Some("initial") => OverflowWrap::Normal,
+            Some(value) => OverflowWrap::parse(value).unwrap_or(inherited.overflow_wrap),
+        };
+
+        // The legacy `word-break: break-word` means `overflow-wrap: anywhere`
+        let (word_break, overflow_wrap) = match own("word-break").map(|v| v.trim().to_ascii_lowercase()) {
+            None => (inherited.word_break, overflow_wrap),
+            Some(value) => match value.as_str() {
+                "normal" | "initial" => (WordBreak::Normal, overflow_wrap),
+                "break-all" => (WordBreak::BreakAll, overflow_wrap),
+                "keep-all" => (WordBreak::KeepAll, overflow_wrap),
+                "break-word" => (WordBreak::Normal, OverflowWrap::Anywhere),
+                _ => (inherited.word_break, overflow_wrap),
+            },
+        };
+
+        let hyphens = match own("hyphens") {
+            None => inherited.hyphens,
+            Some("initial") => Hyphens::Manual,
+            Some(value) => Hyphens::parse(value).unwrap_or(inherited.hyphens),
+        };
+
+        // Non-inherited properties read the resolved property map
+        let position = properties
+            .get("position")
+            .and_then(Position::parse)
+            .unwrap_or_default();
+        let display = properties
+            .get("display")
+            .and_then(Display::parse)
+            .unwrap_or_else(|| Display::default_for_tag(tag));
+        // Absolute positioning wins over floating; either blockifies
+        let float = match position.is_out_of_flow() {
+            true => Float::None,
+            false => properties.get("float").and_then(Float::parse).unwrap_or_default(),
+        };
+        let display = if position.is_out_of_flow() || float != Float::None {
+            display.blockified()
+        } else {
+            display
+        };
+        let clear = properties
+            .get("clear")
+            .and_then(Clear::parse)
+            .unwrap_or_default();
+        let (overflow_x, overflow_y) = overflow(&properties);
+        let z_index = properties
+            .get("z-index")
+            .and_then(|value| value.trim().parse().ok());
+
+        let background_color = background_color(&properties, color);
+
+        let opacity = properties
+            .get("opacity")
+            .and_then(CssValue::parse)
+            .and_then(|value| match value {
+                CssValue::Percentage(pct) => Some(pct / 100.0),
+                value => value.to_number(),
+            })
+            .map_or(1.0, |o| o.clamp(0.0, 1.0));
+
+
+        let units = context.resolve_with(font_size);
+        let length = |property: &str| CssValue::parse(properties.get(property)?)?.to_length(&units);
+        let width = length("width");
+        let height = length("height");
+        let max_width = length("max-width");
+        let (margin, margin_auto) = box_edges(&properties, "margin", &units);
+        let (padding, _) = box_edges(&properties, "padding", &units);
+        let border = borders(&properties, color, &units);
+        let border_radius = border_radius(&properties, &units);
+        let box_shadow = box_shadows(&properties, color, &units);
+        let inset = insets(&properties, &units);
+
+        ComputedStyle {
+            display,
+            font_family,
+            font_size,
+            font_weight,
+            font_style,
+            line_height,
+            color,
+            background_color,
+            opacity,
+            text_align,
+            text_decoration,
+            white_space,
+            overflow_wrap,
+            word_break,
+            hyphens,
+            margin,
+            margin_auto,
+            padding,
+            border,
+            border_radius,
+            box_shadow,
+            width,
+            height,
+            max_width,
+            position,
+            inset,
+            z_index,
+            float,
+            clear,
+            overflow_x,
+            overflow_y,
+            units,
+            properties,
+        }
+    }
+}
+
+/// Computed styles for a whole document, indexed by `NodeId`.
+/// Text nodes share their parent element's style.
+pub struct ComputedStyles {
+    styles: Vec<Option<Arc<ComputedStyle>>>,
+    fallback: Arc<ComputedStyle>,
+}
+
+impl ComputedStyles {
+    pub(super) fn new(node_count: usize) -> Self {
+        Self {
+            styles: vec![None; node_count],
+            fallback: Arc::new(ComputedStyle::default()),
+        }
+    }
+
+    pub(super) fn set(&mut self, node_id: NodeId, style: Arc<ComputedStyle>) {
+        self.styles[node_id] = Some(style);
+    }
+
+    /// Style of a node. Nodes outside the document tree get initial values.
+    pub fn get(&self, node_id: NodeId) -> &Arc<ComputedStyle> {
+        self.styles
+            .get(node_id)
+            .and_then(Option::as_ref)
+            .unwrap_or(&self.fallback)
+    }
+}
+
+/// `overflow-x` and `overflow-y`. A box that scrolls in one axis cannot be
+/// `visible` or `clip` in the other, so those become `auto`.
+fn overflow(properties: &Style) -> (Overflow, Overflow) {
+    let longhand = |property: &str| properties.get(property).and_then(Overflow::parse).unwrap_or_default();
+    let (x, y) = (longhand("overflow-x"), longhand("overflow-y"));
+    let promote = |own: Overflow, other: Overflow| match own {
+        Overflow::Visible | Overflow::Clip if other.is_scrollable() => Overflow::Auto,
+        own => own,
+    };
+    (promote(x, y), promote(y, x))
+}
+
+fn resolve_font_size(value: &str, parent_size: f32, context: &ComputeContext) -> Option<f32> {
+    let keyword = match value.trim().to_ascii_lowercase().as_str() {
+        "xx-small" => Some(9.0),
+        "x-small" => Some(10.0),
+        "small" => Some(13.0),
+        "medium" => Some(MEDIUM_FONT_SIZE),
+        "large" => Some(18.0),
+        "x-large" => Some(24.0),
+        "xx-large" => Some(32.0),
+        "smaller" => Some(parent_size / 1.2),
+        "larger" => Some(parent_size * 1.2),
+        _ => None,
+    };
+    if keyword.is_some() {
+        return keyword;
+    }
+
+    // em and % are relative to the parent's font size
+    let px = CssValue::parse(value)?.to_px(&context.resolve_with(parent_size), parent_size)?;
+    Some(px.max(0.0))
+}
+
+fn parse_font_weight(value: &str, parent_weight: u16) -> Option<u16> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "normal" => Some(400),
+        "bold" => Some(700),
+        "bolder" => Some(match parent_weight {
+            0..=349 => 400,
+            350..=549 => 700,
+            _ => 900,
+        }),
+        "lighter" => Some(match parent_weight {
+            0..=549 => 100,
+            550..=749 => 400,
+            _ => 700,
+        }),
+        number => number.parse::<u16>().ok().filter(|w| (1..=1000).contains(w)),
+    }
+}
+
+fn parse_line_height(value: &str, font_size: f32, context: &ComputeContext) -> Option<LineHeight> {
+    let value = CssValue::parse(value)?;
+    if value.is_keyword("normal") {
+        return Some(LineHeight::Normal);
+    }
+    if let Some(factor) = value.to_number() {
+        return Some(LineHeight::Number(factor));
+    }
+    // Percentages are of the element's own font size
+    let px = value.to_px(&context.resolve_with(font_size), font_size)?;
+    Some(LineHeight::Px(px))
+}
+
+/// The `background-color`; fully transparent backgrounds are None
+fn background_color(properties: &Style, current_color: Rgba) -> Option<Rgba> {
+    parse_color(properties.get("background-color")?, current_color).filter(|color| !color.is_transparent())
+}
+
+/// The `margin-*` or `padding-*` sides, plus which of them are `auto`
+fn box_edges(
+    properties: &Style,
+    name: &str,
+    units: &ResolveContext,
+) -> (Edges<Length>, (bool, bool, bool, bool)) {
+    let side = |suffix: &str| -> (Length, bool) {
+        match properties.get(&format!("{}-{}", name, suffix)).and_then(CssValue::parse) {
+            Some(value) if value.is_keyword("auto") => (Length::default(), true),
+            Some(value) => (value.to_length(units).unwrap_or_default(), false),
+            None => (Length::default(), false),
+        }
+    };
+    let [top, right, bottom, left] = ["top", "right", "bottom", "left"].map(side);
+    let auto = (top.1, right.1, bottom.1, left.1);
+    (Edges { top: top.0, right: right.0, bottom: bottom.0, left: left.0 }, auto)
+}
+
+/// `top`/`right`/`bottom`/`left`
+fn insets(properties: &Style, units: &ResolveContext) -> Edges<Option<Length>> {
+    let side = |property: &str| CssValue::parse(properties.get(property)?)?.to_length(units);
+    let [top, right, bottom, left] = ["top", "right", "bottom", "left"].map(side);
+    Edges { top, right, bottom, left }
+}
+
+/// A `border-width` value in px: a length or `thin`/`medium`/`thick`
+fn border_width(value: &CssValue, units: &ResolveContext) -> Option<f32> {
+    let px = match value {
+        CssValue::Keyword(keyword) => match keyword.as_str() {
+            "thin" => 1.0,
+            "medium" => 3.0,
+            "thick" => 5.0,
+            _ => return None,
+        },
+        value => value.to_length(units)?.definite()?,
+    };
+    Some(px.max(0.0))
+}
+
+fn value_color(value: &CssValue, current_color: Rgba) -> Option<Rgba> {
+    match value {
+        CssValue::Color(color) => Some(*color),
+        CssValue::Keyword(keyword) => parse_color(keyword, current_color),
+        _ => None,
+    }
+}
+
+/// The four borders, from the `border-<side>-width`/`-style`/`-color`
+/// longhands. Colors default to `currentColor` and widths to `medium`, but a
+/// side whose style is `none` or `hidden` has no width.
+fn borders(properties: &Style, current_color: Rgba, units: &ResolveContext) -> Edges<BorderSide> {
+    let side = |name: &str| -> BorderSide {
+        let longhand = |part: &str| properties.get(&format!("border-{}-{}", name, part));
+        let side = BorderSide {
+            width: longhand("width")
+                .and_then(CssValue::parse)
+                .and_then(|width| border_width(&width, units))
+                .unwrap_or(3.0),
+            style: longhand("style").and_then(BorderStyle::parse).unwrap_or(BorderStyle::None),
+            color: longhand("color")
+                .and_then(|color| parse_color(color, current_color))
+                .unwrap_or(current_color),
+        };
+        match side.style.has_width() {
+            true => side,
+            false => BorderSide { width: 0.0, ..side },
+        }
+    };
+    let [top, right, bottom, left] = ["top", "right", "bottom", "left"].map(side);
+    Edges { top, right, bottom, left }
+}
+
+/// The per-corner `border-*-radius` longhands (`<horizontal> [<vertical>]`)
+fn border_radius(properties: &Style, units: &ResolveContext) -> Corners<(Length, Length)> {
+    let corner = |name: &str| -> (Length, Length) {
+        let value: Option<Vec<Length>> = properties
+            .get(&format!("border-{}-radius", name))
+            .and_then(|text| CssValue::parse_list(text).iter().map(|v| v.to_length(units)).collect());
+        match value.as_deref() {
+            Some([both]) => (both.clone(), both.clone()),
+            Some([x, y]) => (x.clone(), y.clone()),
+            _ => Default::default(),
+        }
+    };
+    let [top_left, top_right, bottom_right, bottom_left] = ["top-left", "top-right", "bottom-right", "bottom-left"].map(corner);
+    Corners { top_left, top_right, bottom_right, bottom_left }
+}
+
+/// `box-shadow`: `none`, or a comma-separated list of
+/// `[inset] <offset-x> <offset-y> [<blur> [<spread>]] [<color>]` in any
+/// order of the three parts. One invalid shadow invalidates the list.
+fn box_shadows(properties: &Style, current_color: Rgba, units: &ResolveContext) -> Vec<BoxShadow> {
+    let Some(value) = properties.get("box-shadow") else {
+        return Vec::new();
+    };
+    let shadow = |values: &[CssValue]| -> Option<BoxShadow> {
+        let mut lengths = Vec::new();
+        let (mut color, mut inset) = (None, false);
+        for value in values {
+            if value.is_keyword("inset") && !inset {
+                inset = true;
+            } else if let Some(px) = value.to_length(units).and_then(|l| l.definite()) {
+                lengths.push(px);
+            } else if color.is_none() {
+                color = Some(value_color(value, current_color)?);
+            } else {
+                return None;
+            }
+        }
+        let (offset_x, offset_y, blur, spread) = match lengths[..] {
+            [x, y] => (x, y, 0.0, 0.0),
+            [x, y, blur] => (x, y, blur, 0.0),
+            [x, y, blur, spread] => (x, y, blur, spread),
+            _ => return None,
+        };
+        Some(BoxShadow {
+            offset_x,
+            offset_y,
+            blur: blur.max(0.0),
+            spread,
+            color: color.unwrap_or(current_color),
+            inset,
+        })
+    };
+    let groups = CssValue::parse_comma_list(value);
+    if matches!(groups.as_slice(), [group] if matches!(group.as_slice(), [v] if v.is_keyword("none"))) {
+        return Vec::new();
+    }
+    groups.iter().map(|group| shadow(group)).collect::<Option<Vec<_>>>().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::html::tree_builder::HtmlParser;
+    use crate::style::Stylesheet;
+
+    fn computed(html: &str, css: &str, viewport: Viewport) -> (crate::dom::Dom, ComputedStyles) {
+        let dom = HtmlParser::new(html).parse();
+        let mut stylesheet = Stylesheet::new();
+        stylesheet.add_css(css);
+        let styles = stylesheet.compute_styles(&dom, &viewport);
+        (dom, styles)
+    }
+
+    fn by_id(dom: &crate::dom::Dom, id: &str) -> NodeId {
+        (0..dom.nodes.len())
+            .find(|&n| dom.get_attribute(n, "id") == Some(id))
+            .expect("element with id")
+    }
+
+    #[test]
+    fn em_resolves_against_parent_once() {
+        let html = r#"<div id="a"><div id="b"><p id="c">x</p></div></div>"#;
+        let css = "#a { font-size: 20px } #b { font-size: 1.5em } #c { font-size: 2em; margin-left: 1em }";
+        let (dom, styles) = computed(html, css, Viewport::default());
+        assert_eq!(styles.get(by_id(&dom, "b")).font_size, 30.0);
+        let c = styles.get(by_id(&dom, "c"));
+        assert_eq!(c.font_size, 60.0);
+        assert_eq!(c.margin.left, Length::Px(60.0));
+    }
+
+    #[test]
+    fn rem_and_viewport_units() {
+        let html = r#"<html style="font-size: 10px"><body><div id="t">x</div></body></html>"#;
+        let css = "#t { font-size: 2rem; width: 50vw; padding: 1vh 0 }";
+        let (dom, styles) = computed(html, css, Viewport::new(800.0, 600.0));
+        let t = styles.get(by_id(&dom, "t"));
+        assert_eq!(t.font_size, 20.0);
+        assert_eq!(t.width, Some(Length::Px(400.0)));
+        assert_eq!(t.padding.top, Length::Px(6.0));
+    }
+
+    #[test]
+    fn percentages_wait_for_the_containing_block() {
+        let html = r#"<div id="t">x</div>"#;
+        let css = "#t { width: calc(50% - 1em); margin: 10% auto; padding: min(5%, 8px) }";
+        let (dom, styles) = computed(html, css, Viewport::default());
+        let t = styles.get(by_id(&dom, "t"));
+        assert_eq!(t.width.as_ref().map(|w| w.resolve(400.0)), Some(184.0));
+        assert_eq!(t.margin.resolve(400.0).top, 40.0);
+        assert!(t.has_auto_horizontal_margin());
+        assert_eq!(t.padding.resolve(100.0).trbl(), (5.0, 5.0, 5.0, 5.0));
+        assert_eq!(t.padding.resolve(400.0).left, 8.0);
+    }
+
+    #[test]
+    fn colors_alpha_and_current_color() {
+        let html = r#"<div id="a"><p id="b">x</p><p id="c">y</p></div>"#;
+        let css = "#a { color: rgb(0 128 0 / 50%); opacity: 40% }
+                   #b { color: currentColor; background: url(x.png) no-repeat hsl(0, 100%, 50%) }
+                   #c { color: tomato; background-color: currentColor } #a { background: transparent }";
+        let (dom, styles) = computed(html, css, Viewport::default());
+        let a = styles.get(by_id(&dom, "a"));
+        assert_eq!(a.color, Rgba::new(0, 128, 0, 128));
+        assert_eq!(a.background_color, None);
+        assert_eq!(a.opacity, 0.4);
+        let b = styles.get(by_id(&dom, "b"));
+        assert_eq!(b.color, a.color);
+        assert_eq!(b.background_color, Some(Rgba::rgb(255, 0, 0)));
+        assert_eq!(b.opacity, 1.0);
+        assert_eq!(styles.get(by_id(&dom, "c")).background_color, Some(Rgba::rgb(255, 99, 71)));
+    }
+
+    #[test]
+    fn line_height_number_is_inherited_as_number() {
+        let html = r#"<div id="a" style="font-size: 10px; line-height: 1.5"><span id="b" style="font-size: 20px">x</span></div>"#;
+        let (dom, styles) = computed(html, "", Viewport::default());
+        assert_eq!(styles.get(by_id(&dom, "a")).line_height_px(), 15.0);
+        assert_eq!(styles.get(by_id(&dom, "b")).line_height_px(), 30.0);
+    }
+
+    #[test]
+    fn display_and_typed_defaults() {
+        let html = r#"<div id="d">x</div><span id="s">y</span><b id="b">z</b><li id="l">w</li>"#;
+        let (dom, styles) = computed(html, "span { display: block } div { display: none }", Viewport::default());
+        assert_eq!(styles.get(by_id(&dom, "d")).display, Display::None);
+        assert_eq!(styles.get(by_id(&dom, "s")).display, Display::Block);
+        assert_eq!(styles.get(by_id(&dom, "l")).display, Display::ListItem);
+        assert!(styles.get(by_id(&dom, "b")).is_bold());
+    }
+
+    #[test]
+    fn position_blockifies_and_reads_insets() {
+        let html = r#"<div><span id="a">x</span><span id="r">y</span></div>"#;
+        let css = "#a { position: absolute; inset: 10px auto; left: 5% ; z-index: 3 } #r { position: relative }";
+        let (dom, styles) = computed(html, css, Viewport::default());
+        let a = styles.get(by_id(&dom, "a"));
+        assert_eq!(a.position, Position::Absolute);
+        assert_eq!(a.display, Display::Block);
+        assert_eq!(a.z_index, Some(3));
+        assert_eq!(a.inset.top.as_ref().map(|l| l.resolve(0.0)), Some(10.0));
+        assert!(a.inset.right.is_none());
+        assert_eq!(a.inset.left.as_ref().map(|l| l.resolve(200.0)), Some(10.0));
+        let r = styles.get(by_id(&dom, "r"));
+        assert_eq!((r.position, r.display, r.z_index), (Position::Relative, Display::Inline, None));
+    }
+
+    #[test]
+    fn float_clear_and_overflow() {
+        let html = r#"<div><span id="f">x</span><div id="o"></div><img id="i" align="left"><img id="r" align=RIGHT></div>"#;
+        let css = "#f { float: right; clear: both } #o { overflow: hidden visible }";
+        let (dom, styles) = computed(html, css, Viewport::default());
+        let f = styles.get(by_id(&dom, "f"));
+        assert_eq!((f.float, f.display), (Float::Right, Display::Block));
+        assert!(f.clear.clears(Float::Left) && f.clear.clears(Float::Right));
+        let o = styles.get(by_id(&dom, "o"));
+        assert_eq!((o.overflow_x, o.overflow_y), (Overflow::Hidden, Overflow::Auto));
+        assert_eq!(styles.get(by_id(&dom, "i")).float, Float::Left);
+        // Presentational attribute values match in any case
+        assert_eq!(styles.get(by_id(&dom, "r")).float, Float::Right);
+    }
+
+    #[test]
+    fn borders_radii_and_shadows() {
+        let html = r#"<div id="a" style="color: blue">x</div><div id="b"></div><hr id="h">"#;
+        let css = "#a { border: 2px dashed; border-left: thick solid red; border-right-style: none; \
+                   border-radius: 10px 50% / 5px; box-shadow: 1px 2px 3px rgba(0, 0, 0, 0.5), inset 0 0 4px 1px } \
+                   #b { border-width: 1px 2px; border-style: solid dotted; box-shadow: 1px }";
+        let (dom, styles) = computed(html, css, Viewport::default());
+        let a = styles.get(by_id(&dom, "a"));
+        let blue = Rgba::new(0, 0, 255, 255);
+        assert_eq!(a.border.top, BorderSide { width: 2.0, style: BorderStyle::Dashed, color: blue });
+        assert_eq!(a.border.left, BorderSide { width: 5.0, style: BorderStyle::Solid, color: Rgba::new(255, 0, 0, 255) });
+        assert_eq!(a.border.right.width, 0.0);
+        assert_eq!(a.padding_border(100.0).trbl(), (2.0, 0.0, 2.0, 5.0));
+
+        let radii = a.border_radius.resolve(200.0, 100.0);
+        assert_eq!(radii.top_left, (10.0, 5.0));
+        assert_eq!(radii.top_right, (100.0, 5.0));
+        // 10px + 50% overlap on a 10px wide box, so every radius shrinks by 2/3
+        let radii = a.border_radius.resolve(10.0, 100.0);
+        assert!((radii.top_left.0 + radii.top_right.0 - 10.0).abs() < 1e-4);
+        assert!((radii.top_left.1 - 5.0 * 2.0 / 3.0).abs() < 1e-4);
+
+        assert_eq!(a.box_shadow.len(), 2);
+        assert_eq!((a.box_shadow[0].offset_y, a.box_shadow[0].blur, a.box_shadow[0].color.a), (2.0, 3.0, 128));
+        assert!(a.box_shadow[1].inset);
+        assert_eq!((a.box_shadow[1].spread, a.box_shadow[1].color), (1.0, blue));
+
+        let b = styles.get(by_id(&dom, "b"));
+        assert_eq!(b.border_widths().trbl(), (1.0, 2.0, 1.0, 2.0));
+        assert_eq!(b.border.right.style, BorderStyle::Dotted);
+        // One length is not a shadow
+        assert!(b.box_shadow.is_empty());
+        assert_eq!(styles.get(by_id(&dom, "h")).border.bottom.width, 1.0);
+    }
+
+    #[test]
+    fn text_wrapping_properties_inherit() {
+        let html = r#"<pre id="a"><span id="b">x</span></pre><div id="c"><p id="d">y</p></div>"#;
+        let css = "#b { word-wrap: break-word; hyphens: none } #c { word-break: break-word } #d { white-space: pre-line }";
+        let (dom, styles) = computed(html, css, Viewport::default());
+        assert_eq!(styles.get(by_id(&dom, "a")).white_space, WhiteSpace::Pre);
+        let b = styles.get(by_id(&dom, "b"));
+        assert_eq!((b.white_space, b.overflow_wrap, b.hyphens), (WhiteSpace::Pre, OverflowWrap::BreakWord, Hyphens::None));
+        // The legacy break-word keyword sets overflow-wrap, which inherits
+        let d = styles.get(by_id(&dom, "d"));
+        assert_eq!((d.word_break, d.overflow_wrap), (WordBreak::Normal, OverflowWrap::Anywhere));
+        assert_eq!((d.white_space, d.hyphens), (WhiteSpace::PreLine, Hyphens::Manual));
+    }
+}
@@ -1,6 +1,29 @@
 use crate::dom::NodeId;
 use crate::dom::{Dom, NodeType};
+use crate::font::{FontFace, FontSource};
+use crate::net::resolve_url;
+use crate::parser::css::{CssItem, Declaration};
+use cascade::CssWideKeyword;
 use std::collections::HashMap;
+use std::sync::Arc;
+
+mod cascade;
+mod color;
+mod computed;
+mod selector;
+mod shorthand;
+mod values;
+
+pub use crate::parser::css::Selector;
+pub use cascade::{parse_inline_style, user_agent_rules, Origin};
+pub use color::{parse_color, Rgba};
+pub use computed::{
+    BorderSide, BorderStyle, BoxShadow, Clear, ComputedStyle, ComputedStyles, Corners, Display, Edges,
+    Float, FontStyle, Hyphens, LineHeight, Overflow, OverflowWrap, Position, TextAlign, TextDecoration,
+    WhiteSpace, WordBreak,
+};
+pub use selector::{matches, specificity, Specificity};
+pub use values::{CalcNode, CssValue, Length, ResolveContext, Unit};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Breakpoint {
@@ -58,340 +81,6 @@ impl Style {
     pub fn get(&self, key: &str) -> Option<&str> {
         self.properties.get(key).map(|s| s.as_str())
     }
-
-    pub fn get_font_family(&self) -> &str {
-        self.get("font-family").unwrap_or("Times New Roman")
-    }
-
-    pub fn get_font_size(&self) -> f32 {
-        self.get("font-size")
-            .and_then(|s| {
-                let s = s.trim();
-                if s.ends_with("px") {
-                    s.trim_end_matches("px").parse().ok()
-                } else if s.ends_with("em") {
-                    s.trim_end_matches("em").parse::<f32>().ok().map(|e| e * 16.0)
-                } else if s.ends_with("rem") {
-                    s.trim_end_matches("rem").parse::<f32>().ok().map(|e| e * 16.0)
-                } else {
-                    s.parse().ok()
-                }
-            })
-            .unwrap_or(16.0)
-    }
-
-    pub fn get_font_weight(&self) -> &str {
-        self.get("font-weight").unwrap_or("normal")
-    }
-
-    pub fn is_bold(&self) -> bool {
-        matches!(self.get_font_weight(), "bold" | "700" | "800" | "900")
-    }
-
-    pub fn get_font_style(&self) -> &str {
-        self.get("font-style").unwrap_or("normal")
-    }
-
-    pub fn is_italic(&self) -> bool {
-        self.get_font_style() == "italic"
-    }
-
-    pub fn get_color(&self) -> (u8, u8, u8) {
-        if let Some(color_str) = self.get("color") {
-            parse_color(color_str)
-        } else {
-            (0, 0, 0)
-        }
-    }
-
-    pub fn get_background_color(&self) -> Option<(u8, u8, u8)> {
-        self.get("background").or_else(|| self.get("background-color")).map(|c| parse_color(c))
-    }
-
-    pub fn get_opacity(&self) -> f32 {
-        self.get("opacity")
-            .and_then(|s| s.trim().parse().ok())
-            .unwrap_or(1.0)
-    }
-
-    pub fn get_text_decoration(&self) -> Option<&str> {
-        self.get("text-decoration")
-    }
-
-    pub fn has_text_decoration(&self, decoration: &str) -> bool {
-        self.get_text_decoration()
-            .map(|d| d.contains(decoration))
-            .unwrap_or(false)
-    }
-    
-    pub fn get_width_percentage(&self) -> Option<f32> {
-        self.get("width")
-            .and_then(|s| {
-                let s = s.trim();
-                if s.ends_with("vw") {
-                    // Viewport width percentage
-                    s.trim_end_matches("vw").parse::<f32>().ok().map(|v| v / 100.0)
-                } else if s.ends_with("%") {
-                    // Percentage
-                    s.trim_end_matches("%").parse::<f32>().ok().map(|v| v / 100.0)
-                } else {
-                    None
-                }
-            })
-    }
-
-    /// Parse a spacing value (padding or margin) that can be in px, em, or vh
-    /// Note: vh values just return the raw number, must be multiplied by viewport height / 100 by caller
-    fn parse_spacing_value(value: &str) -> f32 {
-        let s = value.trim();
-        if s.ends_with("px") {
-            s.trim_end_matches("px").parse::<f32>().unwrap_or(0.0)
-        } else if s.ends_with("em") {
-            s.trim_end_matches("em").parse::<f32>().unwrap_or(0.0) * 16.0 // 1em = 16px
-        } else if s.ends_with("vh") {
-            // Return negative to signal it's a vh value, caller will handle
-            s.trim_end_matches("vh").parse::<f32>().unwrap_or(0.0)
-        } else {
-            s.parse::<f32>().unwrap_or(0.0)
-        }
-    }
-
-    /// Parse a spacing value with viewport awareness
-    fn parse_spacing_value_with_viewport(value: &str, viewport_height: f32) -> f32 {
-        let s = value.trim();
-        if s.ends_with("px") {
-            s.trim_end_matches("px").parse::<f32>().unwrap_or(0.0)
-        } else if s.ends_with("em") {
-            s.trim_end_matches("em").parse::<f32>().unwrap_or(0.0) * 16.0 // 1em = 16px
-        } else if s.ends_with("vh") {
-            // vh = viewport height percentage
-            let vh_value = s.trim_end_matches("vh").parse::<f32>().unwrap_or(0.0);
-            viewport_height * vh_value / 100.0
-        } else if s.ends_with("vw") {
-            // vw = viewport width percentage - caller must handle this separately
-            // For now just parse the number
-            s.trim_end_matches("vw").parse::<f32>().unwrap_or(0.0)
-        } else {
-            s.parse::<f32>().unwrap_or(0.0)
-        }
-    }
-
-    /// Get padding value (top, right, bottom, left) - returns all four values
-    pub fn get_padding(&self) -> (f32, f32, f32, f32) {
-        if let Some(padding_str) = self.get("padding") {
-            let parts: Vec<&str> = padding_str.split_whitespace().collect();
-            match parts.len() {
-                1 => {
-                    let val = Self::parse_spacing_value(parts[0]);
-                    (val, val, val, val) // all sides same
-                }
-                2 => {
-                    let v1 = Self::parse_spacing_value(parts[0]);
-                    let v2 = Self::parse_spacing_value(parts[1]);
-                    (v1, v2, v1, v2) // top/bottom, left/right
-                }
-                3 => {
-                    let top = Self::parse_spacing_value(parts[0]);
-                    let h = Self::parse_spacing_value(parts[1]);
-                    let bottom = Self::parse_spacing_value(parts[2]);
-                    (top, h, bottom, h)
-                }
-                _ => {
-                    let top = Self::parse_spacing_value(parts[0]);
-                    let right = Self::parse_spacing_value(parts[1]);
-                    let bottom = Self::parse_spacing_value(parts[2]);
-                    let left = Self::parse_spacing_value(parts[3]);
-                    (top, right, bottom, left)
-                }
-            }
-        } else {
-            (0.0, 0.0, 0.0, 0.0)
-        }
-    }
-
-    /// Get margin value (top, right, bottom, left) - returns all four values
-    /// Supports both shorthand `margin` and individual `margin-top`, `margin-right`, etc.
-    pub fn get_margin(&self) -> (f32, f32, f32, f32) {
-        let mut top = 0.0_f32;
-        let mut right = 0.0_f32;
-        let mut bottom = 0.0_f32;
-        let mut left = 0.0_f32;
-
-        // First check shorthand margin
-        if let Some(margin_str) = self.get("margin") {
-            let parts: Vec<&str> = margin_str.split_whitespace().collect();
-            match parts.len() {
-                1 => {
-                    let val = Self::parse_margin_value(parts[0]);
-                    top = val; right = val; bottom = val; left = val;
-                }
-                2 => {
-                    let v1 = Self::parse_margin_value(parts[0]);
-                    let v2 = Self::parse_margin_value(parts[1]);
-                    top = v1; bottom = v1; right = v2; left = v2;
-                }
-                3 => {
-                    top = Self::parse_margin_value(parts[0]);
-                    right = Self::parse_margin_value(parts[1]);
-                    left = right;
-                    bottom = Self::parse_margin_value(parts[2]);
-                }
-                _ => {
-                    top = Self::parse_margin_value(parts[0]);
-                    right = Self::parse_margin_value(parts[1]);
-                    bottom = Self::parse_margin_value(parts[2]);
-                    left = Self::parse_margin_value(parts[3]);
-                }
-            }
-        }
-
-        // Override with individual properties if present
-        if let Some(mt) = self.get("margin-top") {
-            top = Self::parse_margin_value(mt);
-        }
-        if let Some(mr) = self.get("margin-right") {
-            right = Self::parse_margin_value(mr);
-        }
-        if let Some(mb) = self.get("margin-bottom") {
-            bottom = Self::parse_margin_value(mb);
-        }
-        if let Some(ml) = self.get("margin-left") {
-            left = Self::parse_margin_value(ml);
-        }
-
-        (top, right, bottom, left)
-    }
-
-    /// Get margin value (top, right, bottom, left) with viewport unit support
-    /// vh values are converted to pixels using viewport_height
-    pub fn get_margin_with_viewport(&self, viewport_height: f32) -> (f32, f32, f32, f32) {
-        let mut top = 0.0_f32;
-        let mut right = 0.0_f32;
-        let mut bottom = 0.0_f32;
-        let mut left = 0.0_f32;
-
-        // First check shorthand margin
-        if let Some(margin_str) = self.get("margin") {
-            let parts: Vec<&str> = margin_str.split_whitespace().collect();
-            match parts.len() {
-                1 => {
-                    let val = Self::parse_margin_value_with_viewport(parts[0], viewport_height);
-                    top = val; right = val; bottom = val; left = val;
-                }
-                2 => {
-                    let v1 = Self::parse_margin_value_with_viewport(parts[0], viewport_height);
-                    let v2 = Self::parse_margin_value_with_viewport(parts[1], viewport_height);
-                    top = v1; bottom = v1; right = v2; left = v2;
-                }
-                3 => {
-                    top = Self::parse_margin_value_with_viewport(parts[0], viewport_height);
-                    right = Self::parse_margin_value_with_viewport(parts[1], viewport_height);
-                    left = right;
-                    bottom = Self::parse_margin_value_with_viewport(parts[2], viewport_height);
-                }
-                _ => {
-                    top = Self::parse_margin_value_with_viewport(parts[0], viewport_height);
-                    right = Self::parse_margin_value_with_viewport(parts[1], viewport_height);
-                    bottom = Self::parse_margin_value_with_viewport(parts[2], viewport_height);
-                    left = Self::parse_margin_value_with_viewport(parts[3], viewport_height);
-                }
-            }
-        }
-
-        // Override with individual properties if present
-        if let Some(mt) = self.get("margin-top") {
-            top = Self::parse_margin_value_with_viewport(mt, viewport_height);
-        }
-        if let Some(mr) = self.get("margin-right") {
-            right = Self::parse_margin_value_with_viewport(mr, viewport_height);
-        }
-        if let Some(mb) = self.get("margin-bottom") {
-            bottom = Self::parse_margin_value_with_viewport(mb, viewport_height);
-        }
-        if let Some(ml) = self.get("margin-left") {
-            left = Self::parse_margin_value_with_viewport(ml, viewport_height);
-        }
-
-        (top, right, bottom, left)
-    }
-
-    /// Check if element has auto horizontal margin (for centering)
-    pub fn has_auto_horizontal_margin(&self) -> bool {
-        // Check individual properties first
-        let left_auto = self.get("margin-left").map(|s| s.trim() == "auto").unwrap_or(false);
-        let right_auto = self.get("margin-right").map(|s| s.trim() == "auto").unwrap_or(false);
-        
-        if left_auto && right_auto {
-            return true;
-        }
-
-        // Check shorthand margin
-        if let Some(margin_str) = self.get("margin") {
-            let parts: Vec<&str> = margin_str.split_whitespace().collect();
-            match parts.len() {
-                2 => {
-                    // margin: vertical horizontal
-                    return parts[1].trim() == "auto";
-                }
-                4 => {
-                    // margin: top right bottom left
-                    return parts[1].trim() == "auto" && parts[3].trim() == "auto";
-                }
-                _ => {}
-            }
-        }
-        false
-    }
-
-    /// Parse margin value, returning 0 for "auto" (to be handled by layout engine)
-    fn parse_margin_value(value: &str) -> f32 {
-        let s = value.trim();
-        if s == "auto" {
-            0.0 // Layout engine will handle auto margin
-        } else {
-            Self::parse_spacing_value(s)
-        }
-    }
-
-    /// Parse margin value with viewport unit support, returning 0 for "auto"
-    fn parse_margin_value_with_viewport(value: &str, viewport_height: f32) -> f32 {
-        let s = value.trim();
-        if s == "auto" {
-            0.0 // Layout engine will handle auto margin
-        } else {
-            Self::parse_spacing_value_with_viewport(s, viewport_height)
-        }
-    }
-
-    pub fn get_width_px(&self, viewport_width: f32) -> Option<f32> {
-        self.get("width").and_then(|s| {
-            let s = s.trim();
-            if s.ends_with("vw") {
-                s.trim_end_matches("vw").parse::<f32>().ok().map(|v| viewport_width * v / 100.0)
-            } else if s.ends_with("%") {
-                s.trim_end_matches("%").parse::<f32>().ok().map(|v| viewport_width * v / 100.0)
-            } else if s.ends_with("px") {
-                s.trim_end_matches("px").parse::<f32>().ok()
-            } else {
-                s.parse::<f32>().ok()
-            }
-        })
-    }
-
-    pub fn get_max_width_px(&self, viewport_width: f32) -> Option<f32> {
-        self.get("max-width").and_then(|s| {
-            let s = s.trim();
-            if s.ends_with("vw") {
-                s.trim_end_matches("vw").parse::<f32>().ok().map(|v| viewport_width * v / 100.0)
-            } else if s.ends_with("%") {
-                s.trim_end_matches("%").parse::<f32>().ok().map(|v| viewport_width * v / 100.0)
-            } else if s.ends_with("px") {
-                s.trim_end_matches("px").parse::<f32>().ok()
-            } else {
-                s.parse::<f32>().ok()
-            }
-        })
-    }
 }
 
 impl Default for Stylesheet {
@@ -406,32 +95,21 @@ impl Default for Style {
     }
 }
 
-fn parse_color(color: &str) -> (u8, u8, u8) {
-    let color = color.trim();
-    if color.starts_with('#') {
-        let hex = &color[1..];
-        if hex.len() == 3 {
-            let r = u8::from_str_radix(&format!("{}{}", &hex[0..1], &hex[0..1]), 16).unwrap_or(0);
-            let g = u8::from_str_radix(&format!("{}{}", &hex[1..2], &hex[1..2]), 16).unwrap_or(0);
-            let b = u8::from_str_radix(&format!("{}{}", &hex[2..3], &hex[2..3]), 16).unwrap_or(0);
-            (r, g, b)
-        } else if hex.len() >= 6 {
-            let r = u8::from_str_radix(&hex[0..2], 16).unwrap_or(0);
-            let g = u8::from_str_radix(&hex[2..4], 16).unwrap_or(0);
-            let b = u8::from_str_radix(&hex[4..6], 16).unwrap_or(0);
-            (r, g, b)
-        } else {
-            (0, 0, 0)
-        }
-    } else {
-        (0, 0, 0)
-    }
-}
-
 #[derive(Debug, Clone)]
 pub struct CssRule {
     pub selector: Selector,
-    pub declarations: Style,
+    pub declarations: Vec<Declaration>,
+    pub origin: Origin,
+    pub specificity: Specificity,
+    /// Position in the stylesheet; later rules win ties in specificity
+    pub source_order: usize,
+}
+
+impl CssRule {
+    pub fn new(selector: Selector, declarations: Vec<Declaration>, origin: Origin) -> Self {
+        let specificity = specificity(&selector);
+        Self { selector, declarations, origin, specificity, source_order: 0 }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -445,6 +123,10 @@ pub enum MediaCondition {
     MinWidth(f32),
     MaxWidth(f32),
     Breakpoint(Breakpoint),
+    /// All conditions must hold (an empty list always matches)
+    All(Vec<MediaCondition>),
+    /// One of the conditions must hold (a comma-separated query list)
+    Any(Vec<MediaCondition>),
 }
 
 impl MediaCondition {
@@ -453,23 +135,100 @@ impl MediaCondition {
             MediaCondition::MinWidth(min) => viewport.width >= *min,
             MediaCondition::MaxWidth(max) => viewport.width <= *max,
             MediaCondition::Breakpoint(bp) => viewport.breakpoint() == *bp,
+            MediaCondition::All(conditions) => conditions.iter().all(|c| c.matches(viewport)),
+            MediaCondition::Any(conditions) => conditions.iter().any(|c| c.matches(viewport)),
         }
     }
+
+    /// Parse an `@media` prelude such as `screen and (min-width: 600px)`.
+    /// A comma-separated list matches when any of its queries does; queries
+    /// that can never apply to a screen render (e.g. `print`) or that use
+    /// features we don't understand are dropped, and None means none is left.
+    pub fn parse(prelude: &str) -> Option<MediaCondition> {
+        let prelude = prelude.to_ascii_lowercase();
+        let mut queries: Vec<MediaCondition> = prelude.split(',').filter_map(Self::parse_query).collect();
+        match queries.len() {
+            0 => None,
+            1 => queries.pop(),
+            _ => Some(MediaCondition::Any(queries)),
+        }
+    }
+
+    /// Parse one media query, whose parts are joined by the `and` keyword
+    fn parse_query(query: &str) -> Option<MediaCondition> {
+        let mut conditions = Vec::new();
+        for part in split_media_query(query) {
+            match part.as_str() {
+                "" | "screen" | "all" | "onlyscreen" => {}
+                _ if part.starts_with('(') && part.ends_with(')') => {
+                    let (feature, value) = part[1..part.len() - 1].split_once(':')?;
+                    let px: f32 = value.strip_suffix("px")?.parse().ok()?;
+                    conditions.push(match feature {
+                        "min-width" => MediaCondition::MinWidth(px),
+                        "max-width" => MediaCondition::MaxWidth(px),
+                        _ => return None,
+                    });
+                }
+                _ => return None,
+            }
+        }
+
+        Some(match conditions.len() {
+            1 => conditions.remove(0),
+            _ => MediaCondition::All(conditions),
+        })
+    }
 }
 
-#[derive(Debug, Clone)]
-pub enum Selector {
-    Tag(String),
-    Class(String),
-    Id(String),
-    TagWithPseudo(String, String),
-    Any,
+/// Split a media query at each `and` keyword (a whole word outside
+/// parentheses), with the whitespace removed from every part, so that
+/// `only screen and (min-width: 1px)` gives `onlyscreen` and `(min-width:1px)`
+fn split_media_query(query: &str) -> Vec<String> {
+    // Words are split at whitespace and parentheses outside parentheses
+    let mut words: Vec<String> = Vec::new();
+    let mut word = String::new();
+    let mut depth = 0usize;
+    for c in query.chars() {
+        match c {
+            '(' => {
+                if depth == 0 && !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+                depth += 1;
+                word.push(c);
+            }
+            ')' => {
+                word.push(c);
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            _ if c.is_whitespace() => {
+                if depth == 0 && !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            _ => word.push(c),
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+
+    words
+        .split(|word| word == "and")
+        .map(|part| part.concat())
+        .collect()
 }
 
 pub struct Stylesheet {
     pub rules: Vec<CssRule>,
     pub media_rules: Vec<MediaRule>,
+    /// Web fonts from `@font-face` rules, for `FontManager::load_font_faces`
+    pub font_faces: Vec<FontFace>,
     viewport: Viewport,
+    next_source_order: usize,
 }
 
 impl Stylesheet {
@@ -477,7 +236,9 @@ impl Stylesheet {
         Self { 
             rules: vec![], 
             media_rules: vec![],
+            font_faces: vec![],
             viewport: Viewport::default(),
+            next_source_order: 0,
         } 
     }
 
@@ -489,85 +250,184 @@ impl Stylesheet {
         self.viewport
     }
 
+    /// Add an author rule with normal (not `!important`) declarations
     pub fn add_rule(&mut self, selector: Selector, declarations: Style) {
-        self.rules.push(CssRule { selector, declarations });
+        let declarations = declarations
+            .properties
+            .into_iter()
+            .map(|(property, value)| Declaration { property, value, important: false })
+            .collect();
+        self.push_rule(CssRule::new(selector, declarations, Origin::Author));
+    }
+
+    fn push_rule(&mut self, mut rule: CssRule) {
+        rule.source_order = self.take_source_order();
+        self.rules.push(rule);
     }
 
-    pub fn add_media_rule(&mut self, condition: MediaCondition, rules: Vec<CssRule>) {
+    pub fn add_media_rule(&mut self, condition: MediaCondition, mut rules: Vec<CssRule>) {
+        for rule in &mut rules {
+            rule.source_order = self.take_source_order();
+        }
         self.media_rules.push(MediaRule { condition, rules });
     }
 
+    fn take_source_order(&mut self) -> usize {
+        let order = self.next_source_order;
+        self.next_source_order += 1;
+        order
+    }
+
+    /// Parse author CSS source text and append its rules (including `@media` blocks)
+    pub fn add_css(&mut self, css: &str) {
+        self.add_css_with_origin(css, Origin::Author);
+    }
+
+    /// Parse author CSS fetched from `base_url`, such as a linked sheet;
+    /// its `@font-face` sources resolve against `base_url`, not the document
+    pub fn add_css_with_base(&mut self, css: &str, base_url: &str) {
+        let first = self.font_faces.len();
+        self.add_css(css);
+        for source in self.font_faces[first..].iter_mut().flat_map(|face| &mut face.sources) {
+            if let FontSource::Url { url, .. } = source {
+                *url = resolve_url(base_url, url);
+            }
+        }
+    }
+
+    /// Parse a user stylesheet: it overrides the UA defaults, loses to the page's
+    /// normal declarations, and wins with `!important`
+    pub fn add_user_css(&mut self, css: &str) {
+        self.add_css_with_origin(css, Origin::User);
+    }
+
+    pub fn add_css_with_origin(&mut self, css: &str, origin: Origin) {
+        for item in cascade::parse_css(css) {
+            match item {
+                CssItem::Rule(rule) => {
+                    self.push_rule(CssRule::new(rule.selector, rule.declarations, origin));
+                }
+                CssItem::AtRule { name, prelude, content, .. } if name.eq_ignore_ascii_case("media") => {
+                    let Some(condition) = MediaCondition::parse(&prelude) else {
+                        continue;
+                    };
+                    let rules = content
+                        .into_iter()
+                        .filter_map(|item| match item {
+                            CssItem::Rule(rule) => {
+                                Some(CssRule::new(rule.selector, rule.declarations, origin))
+                            }
+                            CssItem::AtRule { .. } => None,
+                        })
+                        .collect();
+                    self.add_media_rule(condition, rules);
+                }
+                CssItem::AtRule { name, declarations, .. } if name.eq_ignore_ascii_case("font-face") => {
+                    self.font_faces.extend(FontFace::parse(&declarations));
+                }
+                CssItem::AtRule { .. } => {}
+            }
+        }
+    }
+
     pub fn compute_style(&self, dom: &Dom, node_id: NodeId) -> Style {
         self.compute_style_with_viewport(dom, node_id, &self.viewport)
     }
 
+    /// Property map of a single node. This re-runs the cascade for every
+    /// ancestor; use [`Stylesheet::compute_styles`] to style a whole document.
     pub fn compute_style_with_viewport(&self, dom: &Dom, node_id: NodeId, viewport: &Viewport) -> Style {
         let node = &dom.nodes[node_id];
-        let mut result = Style { properties: HashMap::new() };
-
-        if let NodeType::Element(el) = &node.node_type {
-            // Step 0: Inherit inheritable properties from parent element
-            if let Some(parent_id) = node.parent {
-                let parent_style = self.compute_style_with_viewport(dom, parent_id, viewport);
-                // Inherit inheritable CSS properties
-                for (key, value) in &parent_style.properties {
-                    if self.is_inheritable_property(key) {
-                        result.properties.insert(key.clone(), value.clone());
+        let parent_style = node
+            .parent
+            .map(|parent_id| self.compute_style_with_viewport(dom, parent_id, viewport));
+
+        // Text nodes take their parent element's style wholesale
+        if let NodeType::Text(_) = &node.node_type {
+            return parent_style.unwrap_or_default();
+        }
+
+        let cascaded = cascade::cascaded_values(self, dom, node_id, viewport);
+        self.resolve_properties(&cascaded, parent_style.as_ref())
+    }
+
+    /// Compute the style of every node in the document in one top-down pass.
+    /// Each element is cascaded exactly once; children resolve relative
+    /// values against their parent's already-computed style.
+    pub fn compute_styles(&self, dom: &Dom, viewport: &Viewport) -> ComputedStyles {
+        let mut styles = ComputedStyles::new(dom.nodes.len());
+        let mut root_font_size = None;
+        let mut stack: Vec<(NodeId, Option<Arc<ComputedStyle>>)> = vec![(dom.root(), None)];
+
+        while let Some((node_id, parent)) = stack.pop() {
+            let node = &dom.nodes[node_id];
+            let style = match &node.node_type {
+                NodeType::Text(_) => parent.unwrap_or_default(),
+                NodeType::Element(el) => {
+                    let cascaded = cascade::cascaded_values(self, dom, node_id, viewport);
+                    let properties = self.resolve_properties(
+                        &cascaded,
+                        parent.as_deref().map(ComputedStyle::properties),
+                    );
+                    let context = computed::ComputeContext {
+                        viewport,
+                        root_font_size: root_font_size.unwrap_or(computed::MEDIUM_FONT_SIZE),
+                    };
+                    let style = ComputedStyle::compute(&el.tag_name, &cascaded, properties, parent.as_deref(), &context);
+                    // `rem` is relative to the root element (<html>)
+                    if el.tag_name == "html" && root_font_size.is_none() {
+                        root_font_size = Some(style.font_size);
                     }
+                    Arc::new(style)
                 }
+            };
+
+            for &child in node.children.iter().rev() {
+                stack.push((child, Some(style.clone())));
             }
+            styles.set(node_id, style);
+        }
 
-            // Step 1: Apply default user agent styles for this element type
-            self.apply_default_styles(&mut result, &el.tag_name);
-
-            // Step 2: Iterate through custom CSS rules and apply matching ones
-            // If a property exists in the custom rule, it overrides the default
-            // If a property doesn't exist in the custom rule, the default is kept
-            for rule in &self.rules {
-                let matches = match &rule.selector {
-                    Selector::Tag(tag) if tag == "*" => true,
-                    Selector::Tag(tag) if tag == &el.tag_name => true,
-                    Selector::Id(id) => el.attributes.iter().any(|(k, v)| k == "id" && v == id),
-                    Selector::Class(class) => el.attributes.iter().any(|(k, v)| k == "class" && v == class),
-                    Selector::TagWithPseudo(tag, _pseudo) => tag == &el.tag_name,
-                    Selector::Any => true,
-                    _ => false,
-                };
-
-                if matches {
-                    // Apply custom rule properties - these override defaults
-                    for (key, value) in &rule.declarations.properties {
-                        result.properties.insert(key.clone(), value.clone());
-                    }
+        styles
+    }
+
+    /// Apply inheritance and the CSS-wide keywords to an element's cascaded values
+    fn resolve_properties(&self, cascaded: &HashMap<String, String>, parent_style: Option<&Style>) -> Style {
+        let mut result = Style::new();
+
+        // Step 1: Inherit inheritable properties from the parent element
+        if let Some(parent_style) = parent_style {
+            for (key, value) in &parent_style.properties {
+                if self.is_inheritable_property(key) {
+                    result.properties.insert(key.clone(), value.clone());
                 }
             }
+        }
 
-            // Step 3: Apply matching media query rules based on viewport
-            for media_rule in &self.media_rules {
-                if media_rule.condition.matches(viewport) {
-                    for rule in &media_rule.rules {
-                        let media_matches = match &rule.selector {
-                            Selector::Tag(tag) if tag == "*" => true,
-                            Selector::Tag(tag) if tag == &el.tag_name => true,
-                            Selector::Id(id) => el.attributes.iter().any(|(k, v)| k == "id" && v == id),
-                            Selector::Class(class) => el.attributes.iter().any(|(k, v)| k == "class" && v == class),
-                            Selector::TagWithPseudo(tag, _pseudo) => tag == &el.tag_name,
-                            Selector::Any => true,
-                            _ => false,
-                        };
-
-                        if media_matches {
-                            for (key, value) in &rule.declarations.properties {
-                                result.properties.insert(key.clone(), value.clone());
-                            }
-                        }
-                    }
+        // Step 2: Apply the cascaded value of every declared property
+        // (UA, user and author origins, inline style, !important)
+        for (property, value) in cascaded {
+            let inherit = match CssWideKeyword::parse(value) {
+                None => {
+                    result.properties.insert(property.clone(), value.clone());
+                    continue;
+                }
+                Some(CssWideKeyword::Inherit) => true,
+                Some(CssWideKeyword::Initial) => false,
+                Some(CssWideKeyword::Unset) => self.is_inheritable_property(property),
+            };
+
+            // An absent property reads as its initial value
+            let parent_value = parent_style
+                .and_then(|parent| parent.properties.get(property))
+                .filter(|_| inherit);
+            match parent_value {
+                Some(parent_value) => {
+                    result.properties.insert(property.clone(), parent_value.clone());
+                }
+                None => {
+                    result.properties.remove(property);
                 }
-            }
-        } else if let NodeType::Text(_text) = &node.node_type {
-            // Text nodes inherit styles from their parent element
-            if let Some(parent_id) = node.parent {
-                result = self.compute_style_with_viewport(dom, parent_id, viewport);
             }
         }
 
@@ -579,142 +439,204 @@ impl Stylesheet {
         match property {
             "font-family" | "font-size" | "font-weight" | "font-style" | 
             "color" | "line-height" | "text-align" | "text-decoration" |
-            "font-variant" | "letter-spacing" | "word-spacing" => true,
+            "font-variant" | "letter-spacing" | "word-spacing" |
+            "white-space" | "overflow-wrap" | "word-wrap" | "word-break" | "hyphens" => true,
             _ => false,
         }
     }
-
-    /// Apply default user agent styles for each element type
-    fn apply_default_styles(&self, style: &mut Style, tag_name: &str) {
-        match tag_name {
-            // Hyperlink
-            "a" => {
-                style.properties.insert("color".to_string(), "#0000ff".to_string());
-                style.properties.insert("text-decoration".to_string(), "underline".to_string());
-            }
-            // Text formatting
-            "b" | "strong" => {
-                style.properties.insert("font-weight".to_string(), "bold".to_string());
-            }
-            "i" | "em" => {
-                style.properties.insert("font-style".to_string(), "italic".to_string());
-            }
-            "u" => {
-                style.properties.insert("text-decoration".to_string(), "underline".to_string());
-            }
-            "s" | "del" => {
-                style.properties.insert("text-decoration".to_string(), "line-through".to_string());
-            }
-            "code" => {
-                style.properties.insert("font-family".to_string(), "monospace".to_string());
-            }
-            "pre" => {
-                style.properties.insert("font-family".to_string(), "monospace".to_string());
-                style.properties.insert("margin".to_string(), "1em 0.5em".to_string());
-            }
-            // Headings - per HTML spec default margins
-            "h1" => {
-                style.properties.insert("font-size".to_string(), "2em".to_string());
-                style.properties.insert("font-weight".to_string(), "bold".to_string());
-                style.properties.insert("margin".to_string(), "0.3em 0.5em".to_string());
-            }
-            "h2" => {
-                style.properties.insert("font-size".to_string(), "1.5em".to_string());
-                style.properties.insert("font-weight".to_string(), "bold".to_string());
-                style.properties.insert("margin".to_string(), "0.25em 0.5em".to_string());
-            }
-            "h3" => {
-                style.properties.insert("font-size".to_string(), "1.17em".to_string());
-                style.properties.insert("font-weight".to_string(), "bold".to_string());
-                style.properties.insert("margin".to_string(), "0.2em 0.5em".to_string());
-            }
-            "h4" => {
-                style.properties.insert("font-size".to_string(), "1em".to_string());
-                style.properties.insert("font-weight".to_string(), "bold".to_string());
-                style.properties.insert("margin".to_string(), "0.2em 0.5em".to_string());
-            }
-            "h5" => {
-                style.properties.insert("font-size".to_string(), "0.83em".to_string());
-                style.properties.insert("font-weight".to_string(), "bold".to_string());
-                style.properties.insert("margin".to_string(), "0.2em 0.5em".to_string());
-            }
-            "h6" => {
-                style.properties.insert("font-size".to_string(), "0.67em".to_string());
-                style.properties.insert("font-weight".to_string(), "bold".to_string());
-                style.properties.insert("margin".to_string(), "0.2em 0.5em".to_string());
-            }
-            // Paragraph
-            "p" => {
-                style.properties.insert("margin".to_string(), "0.3em 0.5em".to_string());
-            }
-            // Lists - with horizontal margins and left padding for indentation
-            "ul" | "ol" => {
-                style.properties.insert("margin".to_string(), "0.3em 0.5em".to_string());
-                style.properties.insert("padding-left".to_string(), "40px".to_string());
-            }
-            "li" => {
-                // li typically has no margin by default, inherits from ul/ol
-                style.properties.insert("margin".to_string(), "0".to_string());
-            }
-            "dl" => {
-                style.properties.insert("margin".to_string(), "0.3em 0.5em".to_string());
-            }
-            "dt" => {
-                // Definition term - bold
-                style.properties.insert("font-weight".to_string(), "bold".to_string());
-            }
-            "dd" => {
-                // Definition data - indented with left margin
-                style.properties.insert("margin-left".to_string(), "2em".to_string());
-            }
-            // Block elements - blockquote with left margin
-            "blockquote" => {
-                style.properties.insert("margin".to_string(), "0.3em 0 0.3em 2em".to_string());
-            }
-            "hr" => {
-                style.properties.insert("margin".to_string(), "0.3em 0.5em".to_string());
-                style.properties.insert("border".to_string(), "1px solid #ccc".to_string());
-            }
-            "address" => {
-                style.properties.insert("margin".to_string(), "0.3em 0.5em".to_string());
-                style.properties.insert("font-style".to_string(), "italic".to_string());
-            }
-            // HTML5 semantic elements - minimal/no margins
-            "article" | "aside" | "section" | "header" | "footer" | "nav" | "main" => {
-                style.properties.insert("margin".to_string(), "0".to_string());
-            }
-            "figure" => {
-                // Figures have left/right margins for visual separation
-                style.properties.insert("margin".to_string(), "0.3em 2em".to_string());
-            }
-            "figcaption" => {
-                style.properties.insert("font-style".to_string(), "italic".to_string());
-                style.properties.insert("margin".to_string(), "0".to_string());
-            }
-            // Form elements
-            "form" => {
-                style.properties.insert("margin".to_string(), "0.3em 0.5em".to_string());
-            }
-            "fieldset" => {
-                style.properties.insert("margin".to_string(), "0.3em 0.5em".to_string());
-                style.properties.insert("padding".to_string(), "0.5em".to_string());
-                style.properties.insert("border".to_string(), "1px solid #ccc".to_string());
-            }
-            "legend" => {
-                style.properties.insert("padding".to_string(), "0 0.25em".to_string());
-            }
-            // Table elements
-            "table" => {
-                style.properties.insert("margin".to_string(), "0.3em 0.5em".to_string());
-                style.properties.insert("border-collapse".to_string(), "collapse".to_string());
-            }
-            // Body element
-            "body" => {
-                style.properties.insert("margin".to_string(), "8px".to_string());
-            }
-            _ => {
-                // No specific defaults for other elements
-            }
-        }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::html::tree_builder::HtmlParser;
+
+    fn style_of(html: &str, css: &str, id: &str, viewport: Viewport) -> Style {
+        let dom = HtmlParser::new(html).parse();
+        let node = (0..dom.nodes.len())
+            .find(|&n| dom.get_attribute(n, "id") == Some(id))
+            .expect("element with id");
+        let mut stylesheet = Stylesheet::new();
+        stylesheet.add_css(css);
+        stylesheet.compute_style_with_viewport(&dom, node, &viewport)
+    }
+
+    #[test]
+    fn specificity_beats_source_order() {
+        let html = r#"<div id="box" class="card">x</div>"#;
+        let css = "#box { color: red } .card { color: blue } div { color: green }";
+        let style = style_of(html, css, "box", Viewport::default());
+        assert_eq!(style.get("color"), Some("red"));
+
+        let css = ".card { color: blue } div.card { color: green } .card { color: black }";
+        let style = style_of(html, css, "box", Viewport::default());
+        assert_eq!(style.get("color"), Some("green"));
+    }
+
+    #[test]
+    fn later_rule_wins_between_equal_specificity_including_media() {
+        let html = r#"<p id="t" class="a b">x</p>"#;
+        let css = ".a { margin: 1px } @media (min-width: 600px) { .b { margin: 2px } } .b { padding: 3px }";
+        let wide = style_of(html, css, "t", Viewport::new(800.0, 600.0));
+        assert_eq!(wide.get("margin-top"), Some("2px"));
+        let narrow = style_of(html, css, "t", Viewport::new(400.0, 600.0));
+        assert_eq!(narrow.get("margin-top"), Some("1px"));
+        assert_eq!(narrow.get("padding-left"), Some("3px"));
+    }
+
+    #[test]
+    fn important_inverts_origin_order() {
+        let html = r#"<p id="t" class="x">x</p>"#;
+        let dom = HtmlParser::new(html).parse();
+        let node = (0..dom.nodes.len()).find(|&n| dom.get_attribute(n, "id") == Some("t")).unwrap();
+
+        let mut stylesheet = Stylesheet::new();
+        stylesheet.add_user_css("p { color: green; margin: 5px !important }");
+        stylesheet.add_css("p { color: blue; margin: 1px !important } #t { margin: 2px }");
+        let style = stylesheet.compute_style(&dom, node);
+        // Normal author beats normal user; important user beats important author
+        assert_eq!(style.get("color"), Some("blue"));
+        assert_eq!(style.get("margin-right"), Some("5px"));
+    }
+
+    #[test]
+    fn inline_style_attribute() {
+        let html = r#"<div id="t" class="c" style="color: red; padding: 1px">x</div>"#;
+        let css = "#t.c { color: blue; padding: 2px !important }";
+        let style = style_of(html, css, "t", Viewport::default());
+        assert_eq!(style.get("color"), Some("red"));
+        assert_eq!(style.get("padding-top"), Some("2px"));
+
+        let html = r#"<div id="t" style="padding: 3px !important">x</div>"#;
+        let style = style_of(html, css, "t", Viewport::default());
+        assert_eq!(style.get("padding-top"), Some("3px"));
+
+        // The inline shorthand sets every longhand, beating any author rule
+        let html = r#"<p id="t" style="margin: 0">x</p>"#;
+        let style = style_of(html, "p { margin-left: 30px }", "t", Viewport::default());
+        assert_eq!(style.get("margin-left"), Some("0"));
+    }
+
+    #[test]
+    fn user_agent_sheet_is_part_of_the_cascade() {
+        let html = r#"<h1 id="t">x</h1><a id="link" href="/">y</a>"#;
+        let style = style_of(html, "h1 { margin: 0 }", "t", Viewport::default());
+        assert_eq!(style.get("font-weight"), Some("bold"));
+        assert_eq!(style.get("margin-top"), Some("0"));
+        let style = style_of(html, "", "link", Viewport::default());
+        assert_eq!(style.get("color"), Some("#0000ff"));
+
+        // An author shorthand overrides a user-agent longhand
+        let html = r#"<dl><dd id="t">x</dd></dl>"#;
+        let style = style_of(html, "", "t", Viewport::default());
+        assert_eq!(style.get("margin-left"), Some("2em"));
+        let style = style_of(html, "dd { margin: 0 }", "t", Viewport::default());
+        assert_eq!(style.get("margin-left"), Some("0"));
+    }
+
+    #[test]
+    fn shorthands_cascade_as_their_longhands() {
+        let html = r#"<div id="t" class="c">x</div>"#;
+        let style = style_of(html, ".c { background-color: red } div { background: blue }", "t", Viewport::default());
+        assert_eq!(style.get("background-color"), Some("red"));
+        assert_eq!(style.get("background-image"), Some("none"));
+
+        let style = style_of(html, ".c { margin: 1px 2px } div { margin-right: 5px }", "t", Viewport::default());
+        assert_eq!(style.get("margin-right"), Some("2px"));
+        let style = style_of(html, ".c { margin-right: 5px } div { margin: 1px 2px }", "t", Viewport::default());
+        assert_eq!(style.get("margin-right"), Some("5px"));
+        assert_eq!(style.get("margin-left"), Some("2px"));
+    }
+
+    #[test]
+    fn css_wide_keywords() {
+        let html = r#"<div id="outer"><p id="t">x</p></div>"#;
+        let css = "#outer { color: red; border: 1px solid black }                    p { color: blue; border: inherit }                    #t { margin: initial; color: unset }";
+        let style = style_of(html, css, "t", Viewport::default());
+        assert_eq!(style.get("border-top-width"), Some("1px"));
+        assert_eq!(style.get("border-left-style"), Some("solid"));
+        assert_eq!(style.get("color"), Some("red"));
+        assert_eq!(style.get("margin-top"), None);
+
+        let css = "#outer { color: red } #t { color: initial } p { border: unset }";
+        let style = style_of(html, css, "t", Viewport::default());
+        assert_eq!(style.get("color"), None);
+        assert_eq!(style.get("border-top-style"), None);
+    }
+
+    #[test]
+    fn declaration_values_keep_their_source_text() {
+        let html = r#"<div id="t">x</div>"#;
+        let css = "#t { width: calc(100%  -  2 * -1.5em); font: 12px/1.5 serif; margin: -4px auto }";
+        let style = style_of(html, css, "t", Viewport::default());
+        assert_eq!(style.get("width"), Some("calc(100% - 2 * -1.5em)"));
+        assert_eq!(style.get("font"), Some("12px/1.5 serif"));
+        assert_eq!(style.get("margin-top"), Some("-4px"));
+        assert_eq!(style.get("margin-left"), Some("auto"));
+    }
+
+    #[test]
+    fn font_face_rules_are_collected_apart_from_style_rules() {
+        let html = r#"<p id="t">x</p>"#;
+        let css = "@font-face { font-family: Brand; src: url(brand.woff2) format('woff2'); unicode-range: U+0-7F }
+                   p { font-family: Brand, serif }";
+        let dom = HtmlParser::new(html).parse();
+        let mut stylesheet = Stylesheet::new();
+        stylesheet.add_css(css);
+        assert_eq!(stylesheet.font_faces.len(), 1);
+        assert_eq!(stylesheet.font_faces[0].family, "Brand");
+        assert_eq!(stylesheet.font_faces[0].unicode_range, vec![0..=0x7F]);
+        assert_eq!(stylesheet.rules.len(), 1);
+        let node = (0..dom.nodes.len()).find(|&n| dom.get_attribute(n, "id") == Some("t")).unwrap();
+        assert_eq!(stylesheet.compute_style(&dom, node).get("font-family"), Some("Brand, serif"));
+    }
+
+    #[test]
+    fn font_face_sources_resolve_against_the_sheet() {
+        let mut stylesheet = Stylesheet::new();
+        stylesheet.add_css("@font-face { font-family: Inline; src: url(inline.woff2) }");
+        stylesheet.add_css_with_base(
+            "@font-face { font-family: Brand; src: url(../fonts/brand.woff2) format('woff2'), local(Brand), url(brand.ttf) }",
+            "https://example.com/css/site.css",
+        );
+        let urls: Vec<&str> = stylesheet.font_faces.iter()
+            .flat_map(|face| &face.sources)
+            .filter_map(|source| match source {
+                FontSource::Url { url, .. } => Some(url.as_str()),
+                FontSource::Local(_) => None,
+            })
+            .collect();
+        assert_eq!(urls, ["inline.woff2", "https://example.com/fonts/brand.woff2", "https://example.com/css/brand.ttf"]);
+    }
+
+    #[test]
+    fn media_prelude_parsing() {
+        assert!(matches!(MediaCondition::parse("(max-width: 600px)"), Some(MediaCondition::MaxWidth(w)) if w == 600.0));
+        assert!(matches!(
+            MediaCondition::parse("screen and (min-width:100px) and (max-width: 200px)"),
+            Some(MediaCondition::All(ref c)) if c.len() == 2
+        ));
+        assert!(MediaCondition::parse("print").is_none());
+    }
+
+    #[test]
+    fn media_query_lists_match_any_query() {
+        let condition = MediaCondition::parse("print, screen and (min-width:600px)").unwrap();
+        assert!(condition.matches(&Viewport::new(800.0, 600.0)));
+        assert!(!condition.matches(&Viewport::new(400.0, 600.0)));
+        let either = MediaCondition::parse("(max-width: 300px), (min-width: 900px)").unwrap();
+        assert!(matches!(either, MediaCondition::Any(ref c) if c.len() == 2));
+        assert!(either.matches(&Viewport::new(200.0, 600.0)) && !either.matches(&Viewport::new(600.0, 600.0)));
+    }
+
+    #[test]
+    fn media_query_and_is_a_keyword() {
+        // "and" inside a feature name or value must not split the query
+        assert_eq!(split_media_query("screen and (orientation: landscape)"), vec!["screen", "(orientation:landscape)"]);
+        assert_eq!(split_media_query("handheld and(min-width:1px)"), vec!["handheld", "(min-width:1px)"]);
+        assert!(matches!(
+            MediaCondition::parse("only screen and (min-width:100px)"),
+            Some(MediaCondition::MinWidth(w)) if w == 100.0
+        ));
+        assert!(MediaCondition::parse("handheld").is_none());
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,332 @@
+//! Selector matching against the DOM.
+//!
+//! Works directly on the CSS parser's [`Selector`] tree. Complex selectors are
+//! matched right to left: the rightmost compound must match the element, then
+//! each combinator walks to ancestors or preceding siblings.
+
+use crate::dom::{Dom, NodeId, NodeType};
+use crate::parser::css::parser::AttrOperator;
+use crate::parser::css::Selector;
+
+/// Selector specificity as (ids, classes/attributes/pseudo-classes, types).
+/// Tuples compare lexicographically, which is exactly the cascade ordering.
+pub type Specificity = (u32, u32, u32);
+
+/// Compute the specificity of a selector (Selectors Level 4, section 17)
+pub fn specificity(selector: &Selector) -> Specificity {
+    match selector {
+        Selector::Universal => (0, 0, 0),
+        Selector::Element(_) | Selector::PseudoElement(_) => (0, 0, 1),
+        Selector::Id(_) => (1, 0, 0),
+        Selector::Class(_)
+        | Selector::Attribute { .. }
+        | Selector::NthChild { .. } => (0, 1, 0),
+        Selector::PseudoClass(name) if is_legacy_pseudo_element(name) => (0, 0, 1),
+        Selector::PseudoClass(_) => (0, 1, 0),
+        // :not() takes the specificity of its most specific argument
+        Selector::Not(list) => list.iter().map(specificity).max().unwrap_or((0, 0, 0)),
+        Selector::Compound(parts) => parts.iter().map(specificity).fold((0, 0, 0), add),
+        Selector::Descendant(left, right)
+        | Selector::Child(left, right)
+        | Selector::Adjacent(left, right)
+        | Selector::GeneralSibling(left, right) => add(specificity(left), specificity(right)),
+    }
+}
+
+fn add(a: Specificity, b: Specificity) -> Specificity {
+    (a.0 + b.0, a.1 + b.1, a.2 + b.2)
+}
+
+/// Does `selector` match the element `node_id`? Text nodes never match.
+pub fn matches(dom: &Dom, node_id: NodeId, selector: &Selector) -> bool {
+    if !is_element(dom, node_id) {
+        return false;
+    }
+
+    match selector {
+        Selector::Universal => true,
+        Selector::Element(tag) => dom
+            .tag_name(node_id)
+            .is_some_and(|name| name.eq_ignore_ascii_case(tag)),
+        Selector::Id(id) => dom.get_attribute(node_id, "id") == Some(id.as_str()),
+        Selector::Class(class) => dom
+            .get_attribute(node_id, "class")
+            .is_some_and(|classes| classes.split_ascii_whitespace().any(|c| c == class)),
+        Selector::Attribute { name, operator, value, case_insensitive } => {
+            match dom.get_attribute(node_id, name) {
+                Some(actual) => match (operator, value) {
+                    (Some(op), Some(expected)) if *case_insensitive => {
+                        attribute_matches(op, &actual.to_ascii_lowercase(), &expected.to_ascii_lowercase())
+                    }
+                    (Some(op), Some(expected)) => attribute_matches(op, actual, expected),
+                    _ => true,
+                },
+                None => false,
+            }
+        }
+        Selector::PseudoClass(name) => pseudo_class_matches(dom, node_id, name),
+        // Generated content is not supported, so pseudo-elements never match an element
+        Selector::PseudoElement(_) => false,
+        Selector::NthChild { a, b, of_type, from_end } => {
+            let index = sibling_index(dom, node_id, *of_type, *from_end);
+            nth_matches(*a, *b, index)
+        }
+        Selector::Not(list) => !list.iter().any(|s| matches(dom, node_id, s)),
+        Selector::Compound(parts) => parts.iter().all(|s| matches(dom, node_id, s)),
+        Selector::Descendant(ancestor, subject) => {
+            if !matches(dom, node_id, subject) {
+                return false;
+            }
+            let mut current = parent_element(dom, node_id);
+            while let Some(id) = current {
+                if matches(dom, id, ancestor) {
+                    return true;
+                }
+                current = parent_element(dom, id);
+            }
+            false
+        }
+        Selector::Child(parent, subject) => {
+            matches(dom, node_id, subject)
+                && parent_element(dom, node_id).is_some_and(|id| matches(dom, id, parent))
+        }
+        Selector::Adjacent(previous, subject) => {
+            matches(dom, node_id, subject)
+                && preceding_element_siblings(dom, node_id)
+                    .next()
+                    .is_some_and(|id| matches(dom, id, previous))
+        }
+        Selector::GeneralSibling(previous, subject) => {
+            matches(dom, node_id, subject)
+                && preceding_element_siblings(dom, node_id).any(|id| matches(dom, id, previous))
+        }
+    }
+}
+
+fn attribute_matches(operator: &AttrOperator, actual: &str, expected: &str) -> bool {
+    match operator {
+        AttrOperator::Exact => actual == expected,
+        AttrOperator::Contains => {
+            !expected.is_empty()
+                && !expected.contains(char::is_whitespace)
+                && actual.split_ascii_whitespace().any(|word| word == expected)
+        }
+        AttrOperator::Dash => {
+            actual == expected
+                || (actual.starts_with(expected) && actual[expected.len()..].starts_with('-'))
+        }
+        AttrOperator::Prefix => !expected.is_empty() && actual.starts_with(expected),
+        AttrOperator::Suffix => !expected.is_empty() && actual.ends_with(expected),
+        AttrOperator::Substring => !expected.is_empty() && actual.contains(expected),
+    }
+}
+
+/// `:before` and friends may be written with a single colon for CSS2 compatibility
+fn is_legacy_pseudo_element(name: &str) -> bool {
+    matches!(name, "before" | "after" | "first-line" | "first-letter")
+}
+
+fn pseudo_class_matches(dom: &Dom, node_id: NodeId, name: &str) -> bool {
+    let tag = dom.tag_name(node_id).unwrap_or("");
+    match name {
+        "root" => parent_element(dom, node_id).is_none(),
+        "first-child" => sibling_index(dom, node_id, false, false) == 1,
+        "last-child" => sibling_index(dom, node_id, false, true) == 1,
+        "only-child" => {
+            sibling_index(dom, node_id, false, false) == 1
+                && sibling_index(dom, node_id, false, true) == 1
+        }
+        "first-of-type" => sibling_index(dom, node_id, true, false) == 1,
+        "last-of-type" => sibling_index(dom, node_id, true, true) == 1,
+        "only-of-type" => {
+            sibling_index(dom, node_id, true, false) == 1
+                && sibling_index(dom, node_id, true, true) == 1
+        }
+        "empty" => dom.nodes[node_id].children.iter().all(|&child| {
+            matches!(&dom.nodes[child].node_type, NodeType::Text(text) if text.is_empty())
+        }),
+        "link" | "any-link" => {
+            matches!(tag, "a" | "area") && dom.get_attribute(node_id, "href").is_some()
+        }
+        "checked" => match tag {
+            "input" => dom.get_attribute(node_id, "checked").is_some(),
+            "option" => dom.get_attribute(node_id, "selected").is_some(),
+            _ => false,
+        },
+        "disabled" => is_form_control(tag) && dom.get_attribute(node_id, "disabled").is_some(),
+        "enabled" => is_form_control(tag) && dom.get_attribute(node_id, "disabled").is_none(),
+        "required" => is_form_control(tag) && dom.get_attribute(node_id, "required").is_some(),
+        "optional" => is_form_control(tag) && dom.get_attribute(node_id, "required").is_none(),
+        // Dynamic states (:hover, :focus, :visited, ...) never apply to a static render,
+        // and anything unrecognised must not match rather than apply unconditionally
+        _ => false,
+    }
+}
+
+fn is_form_control(tag: &str) -> bool {
+    matches!(tag, "input" | "button" | "select" | "textarea" | "option" | "optgroup" | "fieldset")
+}
+
+fn is_element(dom: &Dom, node_id: NodeId) -> bool {
+    matches!(dom.nodes[node_id].node_type, NodeType::Element(_))
+}
+
+/// Parent element, treating the synthetic "document" root as outside the tree
+fn parent_element(dom: &Dom, node_id: NodeId) -> Option<NodeId> {
+    let parent = dom.nodes[node_id].parent?;
+    match dom.tag_name(parent) {
+        Some("document") | None => None,
+        Some(_) => Some(parent),
+    }
+}
+
+/// Element siblings before `node_id`, nearest first
+fn preceding_element_siblings(dom: &Dom, node_id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+    let siblings: &[NodeId] = match dom.nodes[node_id].parent {
+        Some(parent) => &dom.nodes[parent].children,
+        None => &[],
+    };
+    let position = siblings.iter().position(|&id| id == node_id).unwrap_or(0);
+    siblings[..position]
+        .iter()
+        .rev()
+        .copied()
+        .filter(move |&id| is_element(dom, id))
+}
+
+/// 1-based position of an element among its element siblings,
+/// optionally counting only siblings of the same type and/or from the end
+fn sibling_index(dom: &Dom, node_id: NodeId, of_type: bool, from_end: bool) -> i32 {
+    let Some(parent) = dom.nodes[node_id].parent else {
+        return 1;
+    };
+    let tag = dom.tag_name(node_id);
+    let candidates = dom.nodes[parent]
+        .children
+        .iter()
+        .copied()
+        .filter(|&id| is_element(dom, id) && (!of_type || dom.tag_name(id) == tag));
+
+    let position = if from_end {
+        candidates.rev().position(|id| id == node_id)
+    } else {
+        candidates.collect::<Vec<_>>().iter().position(|&id| id == node_id)
+    };
+    position.map_or(1, |p| p as i32 + 1)
+}
+
+/// Is there a non-negative integer n with a*n + b == index?
+fn nth_matches(a: i32, b: i32, index: i32) -> bool {
+    if a == 0 {
+        return index == b;
+    }
+    let diff = index - b;
+    diff % a == 0 && diff / a >= 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::css::{CssParser, CssTokenizer};
+    use crate::parser::css::parser::CssItem;
+    use crate::parser::html::tree_builder::HtmlParser;
+
+    fn parse_selector(css: &str) -> Selector {
+        let tokens = CssTokenizer::new(&format!("{} {{}}", css)).tokenize();
+        match CssParser::new(tokens).parse().into_iter().next() {
+            Some(CssItem::Rule(rule)) => rule.selector,
+            other => panic!("no rule parsed from {:?}: {:?}", css, other),
+        }
+    }
+
+    fn find_by_id(dom: &Dom, id: &str) -> NodeId {
+        (0..dom.nodes.len())
+            .find(|&n| dom.get_attribute(n, "id") == Some(id))
+            .unwrap_or_else(|| panic!("no element with id {}", id))
+    }
+
+    fn matches_id(html: &str, selector: &str, id: &str) -> bool {
+        let dom = HtmlParser::new(html).parse();
+        matches(&dom, find_by_id(&dom, id), &parse_selector(selector))
+    }
+
+    #[test]
+    fn compound_and_descendant_are_distinct() {
+        let html = r#"<div class="note"><p id="inner" class="note">x</p></div><p id="outer">y</p>"#;
+        assert_eq!(
+            parse_selector("div.note"),
+            Selector::Compound(vec![
+                Selector::Element("div".into()),
+                Selector::Class("note".into()),
+            ])
+        );
+        assert!(matches_id(html, "div .note", "inner"));
+        assert!(matches_id(html, "p.note", "inner"));
+        assert!(!matches_id(html, "div.note", "inner"));
+        assert!(!matches_id(html, "div p", "outer"));
+    }
+
+    #[test]
+    fn class_lists_are_whitespace_separated() {
+        let html = r#"<p id="t" class="  lead   warning ">x</p>"#;
+        assert!(matches_id(html, ".warning", "t"));
+        assert!(matches_id(html, ".lead.warning", "t"));
+        assert!(!matches_id(html, ".lead.error", "t"));
+        assert!(!matches_id(html, ".lead warning", "t"));
+    }
+
+    #[test]
+    fn combinators() {
+        let html = r#"<ul id="list"><li id="a">1</li><li id="b">2</li><li id="c">3</li></ul>"#;
+        assert!(matches_id(html, "ul > li", "a"));
+        assert!(!matches_id(html, "body > li", "a"));
+        assert!(matches_id(html, "body li", "a"));
+        assert!(matches_id(html, "#a + li", "b"));
+        assert!(!matches_id(html, "#a + li", "c"));
+        assert!(matches_id(html, "#a ~ li", "c"));
+        assert!(!matches_id(html, "#c ~ li", "a"));
+        assert!(matches_id(html, "html body > ul#list li:last-child", "c"));
+    }
+
+    #[test]
+    fn attribute_operators() {
+        let html = r#"<a id="t" href="https://example.com/doc.pdf" lang="en-US" rel="nofollow noopener">x</a>"#;
+        assert!(matches_id(html, "[href]", "t"));
+        assert!(matches_id(html, r#"[href^="https:"]"#, "t"));
+        assert!(matches_id(html, r#"[href$=".pdf"]"#, "t"));
+        assert!(matches_id(html, r#"[href*="example"]"#, "t"));
+        assert!(matches_id(html, "[lang|=en]", "t"));
+        assert!(matches_id(html, "[rel~=noopener]", "t"));
+        assert!(!matches_id(html, "[rel=noopener]", "t"));
+        assert!(!matches_id(html, "[title]", "t"));
+        assert!(!matches_id(html, "[lang|=EN]", "t"));
+        assert!(matches_id(html, "[lang|=EN i]", "t"));
+        assert!(matches_id(html, r#"[href^="HTTPS:" I]"#, "t"));
+        assert!(!matches_id(html, r#"[href^="HTTPS:" s]"#, "t"));
+    }
+
+    #[test]
+    fn structural_and_negation_pseudo_classes() {
+        let html = r#"<ol><li id="a">1</li><li id="b">2</li><li id="c">3</li><li id="d">4</li></ol>"#;
+        assert!(matches_id(html, "li:nth-child(odd)", "c"));
+        assert!(matches_id(html, "li:nth-child(2n)", "d"));
+        assert!(!matches_id(html, "li:nth-child(2n)", "c"));
+        assert!(matches_id(html, "li:nth-last-child(-n+2)", "c"));
+        assert!(!matches_id(html, "li:nth-last-child(-n+2)", "b"));
+        assert!(matches_id(html, "li:first-child", "a"));
+        assert!(matches_id(html, "li:not(:first-child, #d)", "b"));
+        assert!(!matches_id(html, "li:not(:first-child, #d)", "d"));
+        assert!(!matches_id(html, "li:hover", "a"));
+        assert!(!matches_id(html, "li::before", "a"));
+    }
+
+    #[test]
+    fn specificity_counts() {
+        assert_eq!(specificity(&parse_selector("*")), (0, 0, 0));
+        assert_eq!(specificity(&parse_selector("li")), (0, 0, 1));
+        assert_eq!(specificity(&parse_selector("ul li.item")), (0, 1, 2));
+        assert_eq!(specificity(&parse_selector("#nav a:hover")), (1, 1, 1));
+        assert_eq!(specificity(&parse_selector("a[href]::after")), (0, 1, 2));
+        assert_eq!(specificity(&parse_selector("p:not(#x, .y)")), (1, 0, 1));
+    }
+}
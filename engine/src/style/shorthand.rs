@@ -0,0 +1,394 @@
+//! Shorthand properties.
+//!
+//! The cascade runs on longhands: a shorthand declaration is expanded into
+//! one declaration per longhand, each with the shorthand's origin,
+//! importance, specificity and position, so `margin: 0` and a later or more
+//! specific `margin-left` compete like any two declarations. Omitted parts
+//! of a shorthand reset their longhand to its initial value, and a CSS-wide
+//! keyword applies to every longhand.
+
+use super::cascade::CssWideKeyword;
+use super::computed::BorderStyle;
+use super::values::CssValue;
+use crate::parser::css::Declaration;
+
+const SIDES: [&str; 4] = ["top", "right", "bottom", "left"];
+
+/// `declarations` with every shorthand replaced by its longhands, in order.
+/// A shorthand whose value does not parse is dropped.
+pub(super) fn expand_declarations<'a>(declarations: impl IntoIterator<Item = &'a Declaration>) -> Vec<Declaration> {
+    let mut expanded = Vec::new();
+    for declaration in declarations {
+        match longhands(&declaration.property, &declaration.value) {
+            None => expanded.push(declaration.clone()),
+            Some(longhands) => expanded.extend(longhands.into_iter().map(|(property, value)| Declaration {
+                property,
+                value,
+                important: declaration.important,
+            })),
+        }
+    }
+    expanded
+}
+
+/// The longhands `property: value` sets, or None when `property` is not a
+/// shorthand. An invalid value gives no longhands.
+pub(super) fn longhands(property: &str, value: &str) -> Option<Vec<(String, String)>> {
+    let names = longhand_names(property)?;
+    if CssWideKeyword::parse(value).is_some() {
+        return Some(names.into_iter().map(|name| (name, value.trim().to_string())).collect());
+    }
+    let values = expand(property, value).unwrap_or_default();
+    Some(names.into_iter().zip(values).collect())
+}
+
+/// The longhands of a shorthand, in the order `expand` gives their values
+fn longhand_names(property: &str) -> Option<Vec<String>> {
+    let per_side = |prefix: &str, suffix: &str| -> Vec<String> {
+        SIDES.iter().map(|side| format!("{}{}{}", prefix, side, suffix)).collect()
+    };
+    let side_parts = |side: &str| -> Vec<String> {
+        ["width", "style", "color"].iter().map(|part| format!("border-{}-{}", side, part)).collect()
+    };
+    Some(match property {
+        "margin" => per_side("margin-", ""),
+        "padding" => per_side("padding-", ""),
+        "inset" => per_side("", ""),
+        "border-width" => per_side("border-", "-width"),
+        "border-style" => per_side("border-", "-style"),
+        "border-color" => per_side("border-", "-color"),
+        "border-top" | "border-right" | "border-bottom" | "border-left" => side_parts(&property["border-".len()..]),
+        "border" => SIDES.iter().flat_map(|side| side_parts(side)).collect(),
+        "border-radius" => ["top-left", "top-right", "bottom-right", "bottom-left"]
+            .iter()
+            .map(|corner| format!("border-{}-radius", corner))
+            .collect(),
+        "overflow" => names(&["overflow-x", "overflow-y"]),
+        "background" => names(&["background-color", "background-image"]),
+        "flex" => names(&["flex-grow", "flex-shrink", "flex-basis"]),
+        "flex-flow" => names(&["flex-direction", "flex-wrap"]),
+        "gap" => names(&["row-gap", "column-gap"]),
+        "grid-row" => names(&["grid-row-start", "grid-row-end"]),
+        "grid-column" => names(&["grid-column-start", "grid-column-end"]),
+        "grid-area" => names(&["grid-row-start", "grid-column-start", "grid-row-end", "grid-column-end"]),
+        // Legacy names for a single property
+        "word-wrap" => names(&["overflow-wrap"]),
+        "-webkit-hyphens" => names(&["hyphens"]),
+        _ => return None,
+    })
+}
+
+fn names(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+/// The values of `property`'s longhands, or None when `value` is invalid
+fn expand(property: &str, value: &str) -> Option<Vec<String>> {
+    let parts = components(value);
+    match property {
+        "margin" | "padding" | "inset" | "border-width" | "border-style" | "border-color" => {
+            Some(expand_sides(&parts)?.to_vec())
+        }
+        "border-top" | "border-right" | "border-bottom" | "border-left" => border_side(&parts),
+        "border" => Some(border_side(&parts)?.iter().cycle().take(12).cloned().collect()),
+        "border-radius" => border_radius(value),
+        "overflow" => match parts.as_slice() {
+            [both] => Some(vec![both.clone(), both.clone()]),
+            [x, y] => Some(vec![x.clone(), y.clone()]),
+            _ => None,
+        },
+        "gap" => match parts.as_slice() {
+            [both] => Some(vec![both.clone(), both.clone()]),
+            [row, column] => Some(vec![row.clone(), column.clone()]),
+            _ => None,
+        },
+        "background" => background(value),
+        "flex" => flex(&parts),
+        "flex-flow" => flex_flow(&parts),
+        "grid-row" | "grid-column" => grid_lines(value, 2),
+        "grid-area" => grid_lines(value, 4),
+        "word-wrap" | "-webkit-hyphens" => Some(vec![value.trim().to_string()]),
+        _ => None,
+    }
+}
+
+/// Split a value at top-level whitespace, keeping functions and strings whole
+fn components(value: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut part = String::new();
+    let (mut depth, mut quote) = (0usize, None);
+    for c in value.chars() {
+        match (quote, c) {
+            (Some(q), _) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => depth = depth.saturating_sub(1),
+            (None, _) if c.is_whitespace() && depth == 0 => {
+                if !part.is_empty() {
+                    parts.push(std::mem::take(&mut part));
+                }
+                continue;
+            }
+            _ => {}
+        }
+        part.push(c);
+    }
+    if !part.is_empty() {
+        parts.push(part);
+    }
+    parts
+}
+
+/// Expand 1-4 values to (top, right, bottom, left), or the corners starting
+/// at top-left
+fn expand_sides(values: &[String]) -> Option<[String; 4]> {
+    Some(match values {
+        [all] => [all.clone(), all.clone(), all.clone(), all.clone()],
+        [vertical, horizontal] => [vertical.clone(), horizontal.clone(), vertical.clone(), horizontal.clone()],
+        [top, horizontal, bottom] => [top.clone(), horizontal.clone(), bottom.clone(), horizontal.clone()],
+        [top, right, bottom, left] => [top.clone(), right.clone(), bottom.clone(), left.clone()],
+        _ => return None,
+    })
+}
+
+/// `border` and `border-<side>`: width, style and color in any order,
+/// each at most once
+fn border_side(parts: &[String]) -> Option<Vec<String>> {
+    let (mut width, mut style, mut color) = (None, None, None);
+    for part in parts {
+        let slot = if BorderStyle::parse(part).is_some() {
+            &mut style
+        } else if is_border_width(part) {
+            &mut width
+        } else {
+            &mut color
+        };
+        if slot.replace(part.clone()).is_some() {
+            return None;
+        }
+    }
+    if parts.is_empty() {
+        return None;
+    }
+    Some(vec![
+        width.unwrap_or_else(|| "medium".to_string()),
+        style.unwrap_or_else(|| "none".to_string()),
+        color.unwrap_or_else(|| "currentcolor".to_string()),
+    ])
+}
+
+fn is_border_width(part: &str) -> bool {
+    match CssValue::parse(part) {
+        Some(CssValue::Keyword(keyword)) => matches!(keyword.as_str(), "thin" | "medium" | "thick"),
+        Some(CssValue::Length(..) | CssValue::Math(_)) => true,
+        Some(CssValue::Number(n)) => n == 0.0,
+        _ => false,
+    }
+}
+
+/// `<horizontal>{1,4} [/ <vertical>{1,4}]` as a `<horizontal> [<vertical>]`
+/// value per corner
+fn border_radius(value: &str) -> Option<Vec<String>> {
+    let (horizontal, vertical) = match value.split_once('/') {
+        Some((horizontal, vertical)) => (horizontal, Some(vertical)),
+        None => (value, None),
+    };
+    let horizontal = expand_sides(&components(horizontal))?;
+    let vertical = match vertical {
+        Some(vertical) => expand_sides(&components(vertical))?,
+        None => horizontal.clone(),
+    };
+    Some(
+        horizontal
+            .into_iter()
+            .zip(vertical)
+            .map(|(h, v)| if h == v { h } else { format!("{} {}", h, v) })
+            .collect(),
+    )
+}
+
+/// `background`: the color, from the final layer, and the image, from the
+/// first; the other components have no longhand here
+fn background(value: &str) -> Option<Vec<String>> {
+    let layers: Vec<Vec<String>> = split_commas(value).iter().map(|layer| components(layer)).collect();
+    let image = layers.first()?.iter().find(|part| is_image(part)).cloned();
+    let color = layers.last()?.iter().find(|part| is_color(part)).cloned();
+    Some(vec![
+        color.unwrap_or_else(|| "transparent".to_string()),
+        image.unwrap_or_else(|| "none".to_string()),
+    ])
+}
+
+fn is_image(part: &str) -> bool {
+    let part = part.to_ascii_lowercase();
+    part.starts_with("url(") || part.contains("gradient(")
+}
+
+fn is_color(part: &str) -> bool {
+    match CssValue::parse(part) {
+        Some(CssValue::Color(_)) => true,
+        Some(CssValue::Keyword(keyword)) => super::parse_color(&keyword, super::Rgba::BLACK).is_some(),
+        _ => false,
+    }
+}
+
+/// Split at top-level commas
+fn split_commas(value: &str) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut depth = 0usize;
+    for c in value.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                parts.push(String::new());
+                continue;
+            }
+            _ => {}
+        }
+        parts.last_mut().unwrap().push(c);
+    }
+    parts
+}
+
+/// `flex`: `none`, `auto`, or up to two numbers (grow, shrink) and a
+/// basis; an omitted basis is 0 rather than the initial `auto`
+fn flex(parts: &[String]) -> Option<Vec<String>> {
+    match parts {
+        [keyword] if keyword.eq_ignore_ascii_case("none") => return Some(names(&["0", "0", "auto"])),
+        [keyword] if keyword.eq_ignore_ascii_case("auto") => return Some(names(&["1", "1", "auto"])),
+        [] => return None,
+        _ => {}
+    }
+    let mut numbers = Vec::new();
+    let mut basis = None;
+    for part in parts {
+        let value = CssValue::parse(part)?;
+        if value.to_number().is_some() && numbers.len() < 2 && basis.is_none() {
+            numbers.push(part.clone());
+        } else if basis.is_none() {
+            basis = Some(part.clone());
+        } else {
+            return None;
+        }
+    }
+    let mut numbers = numbers.into_iter();
+    Some(vec![
+        numbers.next().unwrap_or_else(|| "1".to_string()),
+        numbers.next().unwrap_or_else(|| "1".to_string()),
+        basis.unwrap_or_else(|| "0px".to_string()),
+    ])
+}
+
+/// `flex-flow`: a direction and a wrap mode, in either order
+fn flex_flow(parts: &[String]) -> Option<Vec<String>> {
+    let (mut direction, mut wrap) = (None, None);
+    for part in parts {
+        let slot = match part.to_ascii_lowercase().as_str() {
+            "row" | "row-reverse" | "column" | "column-reverse" => &mut direction,
+            "nowrap" | "wrap" | "wrap-reverse" => &mut wrap,
+            _ => return None,
+        };
+        if slot.replace(part.clone()).is_some() {
+            return None;
+        }
+    }
+    Some(vec![
+        direction.unwrap_or_else(|| "row".to_string()),
+        wrap.unwrap_or_else(|| "nowrap".to_string()),
+    ])
+}
+
+/// `grid-row`/`grid-column` (2 lines) and `grid-area` (4): lines separated
+/// by `/`. An omitted line copies its counterpart when that is a single
+/// name, and is `auto` otherwise.
+fn grid_lines(value: &str, count: usize) -> Option<Vec<String>> {
+    let given: Vec<String> = value.split('/').map(|line| line.trim().to_string()).collect();
+    if given.len() > count || given.iter().any(String::is_empty) {
+        return None;
+    }
+    let counterpart = |line: &str| {
+        let is_name = !line.contains(char::is_whitespace)
+            && line.parse::<i32>().is_err()
+            && !line.eq_ignore_ascii_case("auto")
+            && !line.eq_ignore_ascii_case("span");
+        if is_name { line.to_string() } else { "auto".to_string() }
+    };
+    let mut lines = given;
+    while lines.len() < count {
+        // Ends copy their start; in `grid-area` the column start copies the row start
+        let source = match (count, lines.len()) {
+            (4, 1) => 0,
+            (4, index) => index - 2,
+            (_, index) => index - 1,
+        };
+        let line = counterpart(&lines[source]);
+        lines.push(line);
+    }
+    Some(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expanded(property: &str, value: &str) -> Vec<(String, String)> {
+        longhands(property, value).expect("a shorthand")
+    }
+
+    fn pairs(expected: &[(&str, &str)]) -> Vec<(String, String)> {
+        expected.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn box_sides_expand_one_to_four_values() {
+        assert_eq!(expanded("margin", "1px calc(2px + 1em) 3px"), pairs(&[
+            ("margin-top", "1px"),
+            ("margin-right", "calc(2px + 1em)"),
+            ("margin-bottom", "3px"),
+            ("margin-left", "calc(2px + 1em)"),
+        ]));
+        assert_eq!(expanded("inset", "0")[3], ("left".to_string(), "0".to_string()));
+        assert!(expanded("padding", "1px 2px 3px 4px 5px").is_empty());
+        assert!(expanded("padding", "inherit").iter().all(|(_, value)| value == "inherit"));
+        assert_eq!(longhands("margin-left", "1px"), None);
+    }
+
+    #[test]
+    fn borders_reset_omitted_parts() {
+        let border = expanded("border", "2px solid");
+        assert_eq!(border.len(), 12);
+        assert_eq!(border[..3], pairs(&[
+            ("border-top-width", "2px"),
+            ("border-top-style", "solid"),
+            ("border-top-color", "currentcolor"),
+        ]));
+        assert_eq!(expanded("border-left", "red")[0], ("border-left-width".to_string(), "medium".to_string()));
+        assert!(expanded("border", "solid dashed").is_empty());
+        assert_eq!(expanded("border-radius", "1px 2px / 3px")[1], ("border-top-right-radius".to_string(), "2px 3px".to_string()));
+    }
+
+    #[test]
+    fn other_shorthands() {
+        assert_eq!(expanded("background", "url(a.png) no-repeat, #fff"), pairs(&[
+            ("background-color", "#fff"),
+            ("background-image", "url(a.png)"),
+        ]));
+        assert_eq!(expanded("background", "blue")[1].1, "none");
+        assert_eq!(expanded("overflow", "hidden auto")[1].1, "auto");
+        assert_eq!(expanded("flex", "2"), pairs(&[("flex-grow", "2"), ("flex-shrink", "1"), ("flex-basis", "0px")]));
+        assert_eq!(expanded("flex", "none")[1].1, "0");
+        assert_eq!(expanded("flex-flow", "wrap column")[0].1, "column");
+        assert_eq!(expanded("gap", "1px 2px")[1], ("column-gap".to_string(), "2px".to_string()));
+        assert_eq!(expanded("grid-area", "main"), pairs(&[
+            ("grid-row-start", "main"),
+            ("grid-column-start", "main"),
+            ("grid-row-end", "main"),
+            ("grid-column-end", "main"),
+        ]));
+        assert_eq!(expanded("grid-row", "2 / span 3")[1].1, "span 3");
+        assert_eq!(expanded("grid-column", "3")[1].1, "auto");
+        assert_eq!(expanded("word-wrap", "break-word"), pairs(&[("overflow-wrap", "break-word")]));
+    }
+}
@@ -0,0 +1,592 @@
+//! Typed CSS values (CSS Values and Units Level 4).
+//!
+//! Cascaded property strings are parsed into [`CssValue`]s here, then turned
+//! into computed [`Length`]s: font- and viewport-relative units become pixels,
+//! while percentages stay symbolic until layout knows the containing block.
+//! Every length-valued property (width, margin, padding, font-size,
+//! line-height, ...) goes through this one parser.
+
+use std::sync::Arc;
+
+use super::color::{color_from_tokens, is_color_function};
+use super::{Rgba, Viewport};
+use crate::parser::css::{CssToken, CssTokenizer};
+
+/// A length unit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    Px,
+    Em,
+    Rem,
+    Ex,
+    Ch,
+    Vw,
+    Vh,
+    Vmin,
+    Vmax,
+    Pt,
+    Pc,
+    In,
+    Cm,
+    Mm,
+    Q,
+}
+
+impl Unit {
+    pub fn parse(unit: &str) -> Option<Unit> {
+        Some(match unit.to_ascii_lowercase().as_str() {
+            "px" => Unit::Px,
+            "em" => Unit::Em,
+            "rem" => Unit::Rem,
+            "ex" => Unit::Ex,
+            "ch" => Unit::Ch,
+            "vw" => Unit::Vw,
+            "vh" => Unit::Vh,
+            "vmin" => Unit::Vmin,
+            "vmax" => Unit::Vmax,
+            "pt" => Unit::Pt,
+            "pc" => Unit::Pc,
+            "in" => Unit::In,
+            "cm" => Unit::Cm,
+            "mm" => Unit::Mm,
+            "q" => Unit::Q,
+            _ => return None,
+        })
+    }
+}
+
+/// What relative units resolve against
+#[derive(Debug, Clone, Copy)]
+pub struct ResolveContext {
+    /// Font size for `em`, `ex` and `ch`
+    pub font_size: f32,
+    /// Root element font size for `rem`
+    pub root_font_size: f32,
+    pub viewport: Viewport,
+}
+
+impl ResolveContext {
+    /// Convert `value` in `unit` to px
+    pub fn to_px(&self, value: f32, unit: Unit) -> f32 {
+        let viewport = self.viewport;
+        match unit {
+            Unit::Px => value,
+            Unit::Em => value * self.font_size,
+            Unit::Rem => value * self.root_font_size,
+            // Without font metrics at style time, use the spec's 0.5em fallback
+            Unit::Ex | Unit::Ch => value * self.font_size * 0.5,
+            Unit::Vw => value * viewport.width / 100.0,
+            Unit::Vh => value * viewport.height / 100.0,
+            Unit::Vmin => value * viewport.width.min(viewport.height) / 100.0,
+            Unit::Vmax => value * viewport.width.max(viewport.height) / 100.0,
+            Unit::Pt => value * 96.0 / 72.0,
+            Unit::Pc => value * 16.0,
+            Unit::In => value * 96.0,
+            Unit::Cm => value * 96.0 / 2.54,
+            Unit::Mm => value * 96.0 / 25.4,
+            Unit::Q => value * 96.0 / 101.6,
+        }
+    }
+}
+
+/// One parsed component value
+#[derive(Debug, Clone, PartialEq)]
+pub enum CssValue {
+    Number(f32),
+    Length(f32, Unit),
+    Percentage(f32),
+    /// An identifier, lowercased
+    Keyword(String),
+    /// A hex color or color function; named colors parse as keywords
+    Color(Rgba),
+    /// `calc()`, `min()`, `max()` or `clamp()`
+    Math(Box<CalcNode>),
+}
+
+/// A math function expression tree
+#[derive(Debug, Clone, PartialEq)]
+pub enum CalcNode {
+    Number(f32),
+    Length(f32, Unit),
+    Percentage(f32),
+    Sum(Box<CalcNode>, Box<CalcNode>),
+    Difference(Box<CalcNode>, Box<CalcNode>),
+    Product(Box<CalcNode>, Box<CalcNode>),
+    Quotient(Box<CalcNode>, Box<CalcNode>),
+    Min(Vec<CalcNode>),
+    Max(Vec<CalcNode>),
+    Clamp(Box<CalcNode>, Box<CalcNode>, Box<CalcNode>),
+}
+
+/// Whether an expression yields a plain number or a length-percentage
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CalcType {
+    Number,
+    Length,
+}
+
+impl CalcNode {
+    /// Type-check the expression (CSS Values 4, section 10.8)
+    fn calc_type(&self) -> Option<CalcType> {
+        match self {
+            CalcNode::Number(_) => Some(CalcType::Number),
+            CalcNode::Length(..) | CalcNode::Percentage(_) => Some(CalcType::Length),
+            CalcNode::Sum(a, b) | CalcNode::Difference(a, b) => {
+                let t = a.calc_type()?;
+                (t == b.calc_type()?).then_some(t)
+            }
+            CalcNode::Product(a, b) => match (a.calc_type()?, b.calc_type()?) {
+                (CalcType::Number, t) | (t, CalcType::Number) => Some(t),
+                _ => None,
+            },
+            CalcNode::Quotient(a, b) => match b.calc_type()? {
+                CalcType::Number => a.calc_type(),
+                CalcType::Length => None,
+            },
+            CalcNode::Min(args) | CalcNode::Max(args) => {
+                let t = args.first()?.calc_type()?;
+                args.iter().all(|arg| arg.calc_type() == Some(t)).then_some(t)
+            }
+            CalcNode::Clamp(min, value, max) => {
+                let t = value.calc_type()?;
+                (min.calc_type()? == t && max.calc_type()? == t).then_some(t)
+            }
+        }
+    }
+
+    /// Replace every absolute or relative length with px, leaving percentages
+    fn absolutize(&self, context: &ResolveContext) -> CalcNode {
+        let pair = |a: &CalcNode, b: &CalcNode| {
+            (Box::new(a.absolutize(context)), Box::new(b.absolutize(context)))
+        };
+        match self {
+            CalcNode::Number(n) => CalcNode::Number(*n),
+            CalcNode::Length(value, unit) => CalcNode::Length(context.to_px(*value, *unit), Unit::Px),
+            CalcNode::Percentage(pct) => CalcNode::Percentage(*pct),
+            CalcNode::Sum(a, b) => {
+                let (a, b) = pair(a, b);
+                CalcNode::Sum(a, b)
+            }
+            CalcNode::Difference(a, b) => {
+                let (a, b) = pair(a, b);
+                CalcNode::Difference(a, b)
+            }
+            CalcNode::Product(a, b) => {
+                let (a, b) = pair(a, b);
+                CalcNode::Product(a, b)
+            }
+            CalcNode::Quotient(a, b) => {
+                let (a, b) = pair(a, b);
+                CalcNode::Quotient(a, b)
+            }
+            CalcNode::Min(args) => CalcNode::Min(args.iter().map(|a| a.absolutize(context)).collect()),
+            CalcNode::Max(args) => CalcNode::Max(args.iter().map(|a| a.absolutize(context)).collect()),
+            CalcNode::Clamp(min, value, max) => CalcNode::Clamp(
+                Box::new(min.absolutize(context)),
+                Box::new(value.absolutize(context)),
+                Box::new(max.absolutize(context)),
+            ),
+        }
+    }
+
+    fn has_percentage(&self) -> bool {
+        match self {
+            CalcNode::Number(_) | CalcNode::Length(..) => false,
+            CalcNode::Percentage(_) => true,
+            CalcNode::Sum(a, b)
+            | CalcNode::Difference(a, b)
+            | CalcNode::Product(a, b)
+            | CalcNode::Quotient(a, b) => a.has_percentage() || b.has_percentage(),
+            CalcNode::Min(args) | CalcNode::Max(args) => args.iter().any(CalcNode::has_percentage),
+            CalcNode::Clamp(min, value, max) => {
+                min.has_percentage() || value.has_percentage() || max.has_percentage()
+            }
+        }
+    }
+
+    /// Evaluate an absolutized expression; percentages are taken of `basis`
+    fn evaluate(&self, basis: f32) -> f32 {
+        match self {
+            CalcNode::Number(n) => *n,
+            CalcNode::Length(px, _) => *px,
+            CalcNode::Percentage(pct) => basis * pct / 100.0,
+            CalcNode::Sum(a, b) => a.evaluate(basis) + b.evaluate(basis),
+            CalcNode::Difference(a, b) => a.evaluate(basis) - b.evaluate(basis),
+            CalcNode::Product(a, b) => a.evaluate(basis) * b.evaluate(basis),
+            CalcNode::Quotient(a, b) => {
+                let divisor = b.evaluate(basis);
+                if divisor == 0.0 { 0.0 } else { a.evaluate(basis) / divisor }
+            }
+            CalcNode::Min(args) => args.iter().map(|a| a.evaluate(basis)).fold(f32::INFINITY, f32::min),
+            CalcNode::Max(args) => args.iter().map(|a| a.evaluate(basis)).fold(f32::NEG_INFINITY, f32::max),
+            // Per spec, the minimum wins when it exceeds the maximum
+            CalcNode::Clamp(min, value, max) => {
+                value.evaluate(basis).min(max.evaluate(basis)).max(min.evaluate(basis))
+            }
+        }
+    }
+}
+
+/// A computed length: px, or something that still needs a percentage basis
+#[derive(Debug, Clone, PartialEq)]
+pub enum Length {
+    Px(f32),
+    Percent(f32),
+    /// A math expression mixing px and percentages
+    Calc(Arc<CalcNode>),
+}
+
+impl Length {
+    /// Resolve to px, taking percentages of `basis`
+    pub fn resolve(&self, basis: f32) -> f32 {
+        match self {
+            Length::Px(px) => *px,
+            Length::Percent(pct) => basis * pct / 100.0,
+            Length::Calc(node) => node.evaluate(basis),
+        }
+    }
+    /// The px value when it does not depend on a percentage basis
+    pub fn definite(&self) -> Option<f32> {
+        match self {
+            Length::Px(px) => Some(*px),
+            Length::Percent(_) => None,
+            Length::Calc(node) => (!node.has_percentage()).then(|| node.evaluate(0.0)),
+        }
+    }
+}
+
+impl Default for Length {
+    fn default() -> Self {
+        Length::Px(0.0)
+    }
+}
+
+impl CssValue {
+    /// Parse a single component value such as `12px`, `50%` or `calc(1em + 2px)`
+    pub fn parse(text: &str) -> Option<CssValue> {
+        let tokens = CssTokenizer::new(text).tokenize();
+        let mut parser = ValueParser { tokens: &tokens, pos: 0 };
+        let value = parser.component()?;
+        parser.skip_whitespace();
+        parser.at_end().then_some(value)
+    }
+
+    /// Parse a whitespace-separated list, as used by shorthands like `margin`.
+    /// Unparseable components are skipped.
+    pub fn parse_list(text: &str) -> Vec<CssValue> {
+        let tokens = CssTokenizer::new(text).tokenize();
+        let mut parser = ValueParser { tokens: &tokens, pos: 0 };
+        let mut values = Vec::new();
+        loop {
+            parser.skip_whitespace();
+            if parser.at_end() {
+                break;
+            }
+            match parser.component() {
+                Some(value) => values.push(value),
+                None => parser.skip_component(),
+            }
+        }
+        values
+    }
+
+    /// Parse a comma-separated list of whitespace-separated lists, as used by
+    /// `box-shadow`. Commas inside functions do not split.
+    pub fn parse_comma_list(text: &str) -> Vec<Vec<CssValue>> {
+        let tokens = CssTokenizer::new(text).tokenize();
+        let mut parser = ValueParser { tokens: &tokens, pos: 0 };
+        let mut groups = vec![Vec::new()];
+        loop {
+            parser.skip_whitespace();
+            match parser.peek() {
+                None => break,
+                Some(CssToken::Comma) => {
+                    parser.pos += 1;
+                    groups.push(Vec::new());
+                }
+                Some(_) => match parser.component() {
+                    Some(value) => groups.last_mut().expect("at least one group").push(value),
+                    None => parser.skip_component(),
+                },
+            }
+        }
+        groups
+    }
+
+    pub fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self, CssValue::Keyword(k) if k == keyword)
+    }
+
+    /// Compute a length-percentage. Unitless numbers are accepted as px,
+    /// as legacy content expects; keywords and colors are not lengths.
+    pub fn to_length(&self, context: &ResolveContext) -> Option<Length> {
+        match self {
+            CssValue::Number(n) => Some(Length::Px(*n)),
+            CssValue::Length(value, unit) => Some(Length::Px(context.to_px(*value, *unit))),
+            CssValue::Percentage(pct) => Some(Length::Percent(*pct)),
+            CssValue::Math(node) => {
+                if node.calc_type()? != CalcType::Length {
+                    return None;
+                }
+                let node = node.absolutize(context);
+                Some(match node {
+                    CalcNode::Percentage(pct) => Length::Percent(pct),
+                    _ if node.has_percentage() => Length::Calc(Arc::new(node)),
+                    _ => Length::Px(node.evaluate(0.0)),
+                })
+            }
+            CssValue::Keyword(_) | CssValue::Color(_) => None,
+        }
+    }
+
+    /// Resolve straight to px, taking percentages of `basis`
+    pub fn to_px(&self, context: &ResolveContext, basis: f32) -> Option<f32> {
+        Some(self.to_length(context)?.resolve(basis))
+    }
+
+    /// A plain number, including math functions that evaluate to one
+    pub fn to_number(&self) -> Option<f32> {
+        match self {
+            CssValue::Number(n) => Some(*n),
+            CssValue::Math(node) if node.calc_type()? == CalcType::Number => Some(node.evaluate(0.0)),
+            _ => None,
+        }
+    }
+}
+
+/// Recursive-descent parser over tokens from [`CssTokenizer`]
+struct ValueParser<'a> {
+    tokens: &'a [CssToken],
+    pos: usize,
+}
+
+impl ValueParser<'_> {
+    fn peek(&self) -> Option<&CssToken> {
+        self.tokens.get(self.pos)
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(CssToken::Whitespace)) {
+            self.pos += 1;
+        }
+    }
+
+    /// Skip one component, including any parenthesized arguments
+    fn skip_component(&mut self) {
+        let mut depth = 0usize;
+        while let Some(token) = self.peek() {
+            match token {
+                CssToken::Whitespace | CssToken::Comma if depth == 0 => break,
+                CssToken::OpenParen => depth += 1,
+                CssToken::CloseParen => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn component(&mut self) -> Option<CssValue> {
+        let token = self.peek()?.clone();
+        self.pos += 1;
+        match token {
+            CssToken::Number(n) => Some(CssValue::Number(n)),
+            CssToken::Percentage(pct) => Some(CssValue::Percentage(pct)),
+            CssToken::Dimension { value, unit } => Some(CssValue::Length(value, Unit::parse(&unit)?)),
+            CssToken::Ident(ident) => Some(CssValue::Keyword(ident.to_ascii_lowercase())),
+            CssToken::Color(_) => Some(CssValue::Color(color_from_tokens(&[token], Rgba::BLACK)?)),
+            CssToken::Function(name) if is_color_function(&name) => {
+                let start = self.pos - 1;
+                self.skip_component();
+                Some(CssValue::Color(color_from_tokens(&self.tokens[start..self.pos], Rgba::BLACK)?))
+            }
+            CssToken::Function(name) => {
+                let node = self.math_function(&name)?;
+                node.calc_type()?;
+                Some(CssValue::Math(Box::new(node)))
+            }
+            _ => None,
+        }
+    }
+
+    /// `calc(`, `min(`, `max(` or `clamp(`; the function name is already consumed
+    fn math_function(&mut self, name: &str) -> Option<CalcNode> {
+        if self.peek() != Some(&CssToken::OpenParen) {
+            return None;
+        }
+        self.pos += 1;
+        let mut args = vec![self.sum()?];
+        while self.peek() == Some(&CssToken::Comma) {
+            self.pos += 1;
+            args.push(self.sum()?);
+        }
+        if self.peek() != Some(&CssToken::CloseParen) {
+            return None;
+        }
+        self.pos += 1;
+
+        match (name.to_ascii_lowercase().as_str(), args.len()) {
+            ("calc", 1) => args.pop(),
+            ("min", _) => Some(CalcNode::Min(args)),
+            ("max", _) => Some(CalcNode::Max(args)),
+            ("clamp", 3) => {
+                let max = Box::new(args.pop()?);
+                let value = Box::new(args.pop()?);
+                let min = Box::new(args.pop()?);
+                Some(CalcNode::Clamp(min, value, max))
+            }
+            _ => None,
+        }
+    }
+
+    /// `<calc-sum> = <calc-product> [ [ '+' | '-' ] <calc-product> ]*`
+    fn sum(&mut self) -> Option<CalcNode> {
+        self.skip_whitespace();
+        let mut node = self.product()?;
+        loop {
+            // `+` and `-` must be surrounded by whitespace
+            let start = self.pos;
+            self.skip_whitespace();
+            let subtract = match self.peek() {
+                Some(CssToken::Plus) => false,
+                Some(CssToken::Ident(op)) if op == "-" => true,
+                _ => {
+                    self.pos = start;
+                    break;
+                }
+            };
+            if self.pos == start {
+                return None;
+            }
+            self.pos += 1;
+            if !matches!(self.peek(), Some(CssToken::Whitespace)) {
+                return None;
+            }
+            self.skip_whitespace();
+            let rhs = Box::new(self.product()?);
+            node = if subtract {
+                CalcNode::Difference(Box::new(node), rhs)
+            } else {
+                CalcNode::Sum(Box::new(node), rhs)
+            };
+        }
+        self.skip_whitespace();
+        Some(node)
+    }
+
+    /// `<calc-product> = <calc-value> [ [ '*' | '/' ] <calc-value> ]*`
+    fn product(&mut self) -> Option<CalcNode> {
+        let mut node = self.calc_value()?;
+        loop {
+            let start = self.pos;
+            self.skip_whitespace();
+            let divide = match self.peek() {
+                Some(CssToken::Asterisk) => false,
+                Some(CssToken::Slash) => true,
+                _ => {
+                    self.pos = start;
+                    break;
+                }
+            };
+            self.pos += 1;
+            self.skip_whitespace();
+            let rhs = Box::new(self.calc_value()?);
+            node = if divide {
+                CalcNode::Quotient(Box::new(node), rhs)
+            } else {
+                CalcNode::Product(Box::new(node), rhs)
+            };
+        }
+        Some(node)
+    }
+
+    /// A number, dimension, percentage, nested math function or `( <calc-sum> )`
+    fn calc_value(&mut self) -> Option<CalcNode> {
+        let token = self.peek()?.clone();
+        self.pos += 1;
+        match token {
+            CssToken::Number(n) => Some(CalcNode::Number(n)),
+            CssToken::Percentage(pct) => Some(CalcNode::Percentage(pct)),
+            CssToken::Dimension { value, unit } => Some(CalcNode::Length(value, Unit::parse(&unit)?)),
+            CssToken::Function(name) => self.math_function(&name),
+            CssToken::OpenParen => {
+                let node = self.sum()?;
+                if self.peek() != Some(&CssToken::CloseParen) {
+                    return None;
+                }
+                self.pos += 1;
+                Some(node)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> ResolveContext {
+        ResolveContext {
+            font_size: 20.0,
+            root_font_size: 16.0,
+            viewport: Viewport::new(1000.0, 500.0),
+        }
+    }
+
+    fn px(text: &str, basis: f32) -> Option<f32> {
+        CssValue::parse(text)?.to_px(&context(), basis)
+    }
+
+    #[test]
+    fn units() {
+        assert_eq!(px("12px", 0.0), Some(12.0));
+        assert_eq!(px("1.5em", 0.0), Some(30.0));
+        assert_eq!(px("2rem", 0.0), Some(32.0));
+        assert_eq!(px("2ch", 0.0), Some(20.0));
+        assert_eq!(px("10vw", 0.0), Some(100.0));
+        assert_eq!(px("10vmin", 0.0), Some(50.0));
+        assert_eq!(px("10vmax", 0.0), Some(100.0));
+        assert_eq!(px("-4px", 0.0), Some(-4.0));
+        assert_eq!(px("25%", 400.0), Some(100.0));
+        assert_eq!(px("12furlongs", 0.0), None);
+        assert_eq!(CssValue::parse("auto"), Some(CssValue::Keyword("auto".to_string())));
+    }
+
+    #[test]
+    fn calc_keeps_percentages_until_layout() {
+        let value = CssValue::parse("calc(50% - 2em)").unwrap();
+        let length = value.to_length(&context()).unwrap();
+        assert!(matches!(length, Length::Calc(_)));
+        assert_eq!(length.resolve(200.0), 60.0);
+
+        let length = CssValue::parse("calc(2 * (1em + 5px) / 5)").unwrap().to_length(&context()).unwrap();
+        assert_eq!(length, Length::Px(10.0));
+    }
+
+    #[test]
+    fn min_max_clamp() {
+        assert_eq!(px("min(100px, 50%)", 100.0), Some(50.0));
+        assert_eq!(px("max(100px, 50%)", 100.0), Some(100.0));
+        assert_eq!(px("clamp(10px, 5vw, 30px)", 0.0), Some(30.0));
+        assert_eq!(px("clamp(10px, calc(1% + 1px), 30px)", 100.0), Some(10.0));
+    }
+
+    #[test]
+    fn invalid_math_is_rejected() {
+        assert_eq!(CssValue::parse("calc(10px + 2)"), None);
+        assert_eq!(CssValue::parse("calc(10px * 2px)"), None);
+        assert_eq!(CssValue::parse("calc(10px -2px)"), None);
+        assert_eq!(CssValue::parse("calc(1 + 2)").unwrap().to_number(), Some(3.0));
+    }
+
+    #[test]
+    fn lists_split_on_top_level_whitespace() {
+        let values = CssValue::parse_list("1px calc(2px + 3px) auto 4%");
+        assert_eq!(values.len(), 4);
+        assert!(values[2].is_keyword("auto"));
+    }
+}
@@ -10,32 +10,32 @@
 
 #[cfg(test)]
 mod url_resolution_tests {
-    use grob_engine::net::url::{resolve_url, resolve_url_with_base, ParsedUrl};
+    use grob_engine::net::url::{resolve_url, resolve_url_with_base, Url};
     
     #[test]
     fn test_parse_simple_url() {
-        let url = ParsedUrl::parse("https://example.com/path/to/file.html").unwrap();
-        assert_eq!(url.scheme, "https");
-        assert_eq!(url.host, "example.com");
-        assert_eq!(url.path, "/path/to/file.html");
-        assert_eq!(url.port, None);
+        let url = Url::parse("https://example.com/path/to/file.html").unwrap();
+        assert_eq!(url.scheme(), "https");
+        assert_eq!(url.hostname(), "example.com");
+        assert_eq!(url.pathname(), "/path/to/file.html");
+        assert_eq!(url.port(), None);
     }
     
     #[test]
     fn test_parse_url_with_port() {
-        let url = ParsedUrl::parse("http://localhost:8080/api/data").unwrap();
-        assert_eq!(url.scheme, "http");
-        assert_eq!(url.host, "localhost");
-        assert_eq!(url.port, Some(8080));
-        assert_eq!(url.path, "/api/data");
+        let url = Url::parse("http://localhost:8080/api/data").unwrap();
+        assert_eq!(url.scheme(), "http");
+        assert_eq!(url.hostname(), "localhost");
+        assert_eq!(url.port(), Some(8080));
+        assert_eq!(url.pathname(), "/api/data");
     }
     
     #[test]
     fn test_parse_url_with_query_and_fragment() {
-        let url = ParsedUrl::parse("https://example.com/search?q=test&page=1#results").unwrap();
-        assert_eq!(url.path, "/search");
-        assert_eq!(url.query, Some("q=test&page=1".to_string()));
-        assert_eq!(url.fragment, Some("results".to_string()));
+        let url = Url::parse("https://example.com/search?q=test&page=1#results").unwrap();
+        assert_eq!(url.pathname(), "/search");
+        assert_eq!(url.query(), Some("q=test&page=1"));
+        assert_eq!(url.fragment(), Some("results"));
     }
     
     #[test]
@@ -453,7 +453,7 @@ mod cache_tests {
         
         match cache.lookup("https://example.com/img.png") {
             CacheLookup::Hit(entry) => {
-                assert_eq!(entry.etag, Some("\"abc123\"".to_string()));
+                assert_eq!(entry.headers.etag, Some("\"abc123\"".to_string()));
             }
             _ => panic!("Expected cache hit"),
         }
@@ -0,0 +1,96 @@
+// URL parser conformance against the web-platform-tests corpus
+//
+// tests/data/urltestdata.json is url/resources/urltestdata.json from WPT
+// (the revision is recorded in its first line). Each entry is parsed,
+// against its base when it has one, and every component is compared with
+// the expected value; entries marked `failure` must not parse.
+
+#[cfg(test)]
+mod wpt_tests {
+    use grob_engine::net::url::Url;
+    use serde_json::Value;
+
+    fn corpus() -> Vec<Value> {
+        let json = include_str!("data/urltestdata.json");
+        let entries: Vec<Value> = serde_json::from_str(json).expect("urltestdata.json is valid JSON");
+        // Strings in the list are comments
+        entries.into_iter().filter(Value::is_object).collect()
+    }
+
+    fn field<'a>(entry: &'a Value, name: &str) -> &'a str {
+        entry[name].as_str().unwrap_or_default()
+    }
+
+    /// The mismatches of one entry, empty when it passes
+    fn check(entry: &Value) -> Vec<String> {
+        let input = field(entry, "input");
+        let base = match entry["base"].as_str() {
+            Some(base) => match Url::parse(base) {
+                Ok(base) => Some(base),
+                Err(e) => return vec![format!("base {:?} failed to parse: {}", base, e)],
+            },
+            None => None,
+        };
+        let parsed = Url::parse_with_base(input, base.as_ref());
+        if entry["failure"].as_bool() == Some(true) {
+            return match parsed {
+                Ok(url) => vec![format!("parsed to {:?}, expected failure", url.to_string())],
+                Err(_) => Vec::new(),
+            };
+        }
+        let url = match parsed {
+            Ok(url) => url,
+            Err(e) => return vec![format!("failed to parse: {}", e)],
+        };
+
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.hostname(), port),
+            None => url.hostname(),
+        };
+        let actual = [
+            ("href", url.to_string()),
+            ("protocol", format!("{}:", url.scheme())),
+            ("username", url.username().to_string()),
+            ("password", url.password().to_string()),
+            ("host", host),
+            ("hostname", url.hostname()),
+            ("port", url.port().map(|port| port.to_string()).unwrap_or_default()),
+            ("pathname", url.pathname()),
+            ("search", url.search()),
+            ("hash", url.hash()),
+            ("origin", url.origin().to_string()),
+        ];
+        actual
+            .iter()
+            .filter(|(name, _)| !entry[*name].is_null())
+            .filter(|(name, value)| field(entry, name) != value)
+            .map(|(name, value)| format!("{}: got {:?}, expected {:?}", name, value, field(entry, name)))
+            .collect()
+    }
+
+    #[test]
+    fn test_urltestdata() {
+        let entries = corpus();
+        assert!(entries.len() > 800);
+        let failures: Vec<String> = entries
+            .iter()
+            .filter_map(|entry| {
+                let mismatches = check(entry);
+                (!mismatches.is_empty()).then(|| {
+                    format!("{:?} against {:?}:\n    {}", field(entry, "input"), entry["base"], mismatches.join("\n    "))
+                })
+            })
+            .collect();
+        assert!(failures.is_empty(), "{} of {} entries failed:\n{}", failures.len(), entries.len(), failures.join("\n"));
+    }
+
+    #[test]
+    fn test_serialization_round_trips() {
+        // Reparsing a serialized URL gives back the same URL
+        for entry in corpus().iter().filter(|entry| entry["failure"].is_null()) {
+            let Ok(url) = Url::parse(field(entry, "href")) else { continue };
+            let reparsed = Url::parse(&url.to_string()).expect("a serialized URL parses");
+            assert_eq!(reparsed, url, "round trip of {:?}", field(entry, "href"));
+        }
+    }
+}